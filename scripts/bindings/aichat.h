@@ -0,0 +1,27 @@
+/*
+ * C ABI of the aichat safety engine (src/ffi.rs).
+ *
+ * Build the shared library with `cargo rustc --release --lib --no-default-features --crate-type cdylib`.
+ * Strings are NUL-terminated UTF-8. Every result is JSON owned by the caller and must be
+ * released with aichat_free_string. Failures are returned as {"error": "..."}.
+ * `shell` is one of bash, zsh, fish, nu, pwsh, cmd, or NULL for the detected shell.
+ */
+
+#ifndef AICHAT_H
+#define AICHAT_H
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+char *aichat_analyze(const char *command, const char *shell);
+char *aichat_analyze_script(const char *content, const char *shell);
+char *aichat_tutor(const char *command);
+char *aichat_annotate_script(const char *script, const char *shell);
+void aichat_free_string(char *value);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* AICHAT_H */
@@ -0,0 +1,64 @@
+"""Python bindings for the aichat safety engine, over the C ABI of the cdylib.
+
+    cargo rustc --release --lib --no-default-features --crate-type cdylib
+
+    from aichat_engine import Engine
+    engine = Engine("target/release/libaichat.so")
+    engine.analyze("rm -rf ./build", shell="bash")["safety_level"]
+"""
+
+import ctypes
+import json
+import os
+import sys
+
+
+def _default_library():
+    name = {"win32": "aichat.dll", "darwin": "libaichat.dylib"}.get(
+        sys.platform, "libaichat.so"
+    )
+    root = os.path.join(os.path.dirname(os.path.abspath(__file__)), "..", "..")
+    return os.path.join(root, "target", "release", name)
+
+
+class EngineError(Exception):
+    pass
+
+
+class Engine:
+    def __init__(self, path=None):
+        self._lib = ctypes.CDLL(path or os.environ.get("AICHAT_LIB") or _default_library())
+        for name, argc in [
+            ("aichat_analyze", 2),
+            ("aichat_analyze_script", 2),
+            ("aichat_tutor", 1),
+            ("aichat_annotate_script", 2),
+        ]:
+            func = getattr(self._lib, name)
+            func.argtypes = [ctypes.c_char_p] * argc
+            func.restype = ctypes.c_void_p
+        self._lib.aichat_free_string.argtypes = [ctypes.c_void_p]
+        self._lib.aichat_free_string.restype = None
+
+    def _call(self, name, *args):
+        encoded = [None if v is None else v.encode("utf-8") for v in args]
+        ptr = getattr(self._lib, name)(*encoded)
+        try:
+            value = json.loads(ctypes.string_at(ptr).decode("utf-8"))
+        finally:
+            self._lib.aichat_free_string(ptr)
+        if isinstance(value, dict) and "error" in value:
+            raise EngineError(value["error"])
+        return value
+
+    def analyze(self, command, shell=None):
+        return self._call("aichat_analyze", command, shell)
+
+    def analyze_script(self, content, shell=None):
+        return self._call("aichat_analyze_script", content, shell)
+
+    def tutor(self, command):
+        return self._call("aichat_tutor", command)
+
+    def annotate_script(self, script, shell=None):
+        return self._call("aichat_annotate_script", script, shell)["script"]
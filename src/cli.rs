@@ -1,3 +1,5 @@
+use crate::utils::SafetyLevel;
+
 use anyhow::{Context, Result};
 use clap::Parser;
 use is_terminal::IsTerminal;
@@ -9,6 +11,9 @@ pub struct Cli {
     /// Select a LLM model
     #[clap(short, long)]
     pub model: Option<String>,
+    /// Ask these models at once and compare their answers side by side (e.g. openai:gpt-4o,ollama:llama3.1)
+    #[clap(long, value_name = "MODELS", value_delimiter = ',')]
+    pub models: Vec<String>,
     /// Use the system prompt
     #[clap(long)]
     pub prompt: Option<String>,
@@ -42,12 +47,36 @@ pub struct Cli {
     /// Serve the LLM API and WebAPP
     #[clap(long, value_name = "ADDRESS")]
     pub serve: Option<Option<String>>,
+    /// Serve command analysis and tutorials over JSON-RPC on stdio for editors
+    #[clap(long)]
+    pub lens: bool,
+    /// Analyze every command of shell scripts or markdown files
+    #[clap(long, value_name = "FILE", num_args = 1..)]
+    pub lint: Vec<String>,
+    /// Analyze a whole shell script: every command with its line, function and conditional
+    #[clap(long, value_name = "SCRIPT", conflicts_with = "lint")]
+    pub analyze_script: Option<String>,
+    /// Exit non-zero when --lint or --analyze-script finds a command at or above this level
+    #[clap(long, value_name = "LEVEL", default_value = "dangerous")]
+    pub fail_on: SafetyLevel,
+    /// Have the LLM review the scripts after --lint
+    #[clap(long)]
+    pub review: bool,
     /// Execute commands in natural language
     #[clap(short = 'e', long)]
     pub execute: bool,
+    /// Plan, write and review the command with the roles under `team`
+    #[clap(long, requires = "execute")]
+    pub team: bool,
+    /// Execute commands on a configured SSH target
+    #[clap(long, value_name = "TARGET")]
+    pub remote: Option<String>,
     /// Output code only
     #[clap(short = 'c', long)]
     pub code: bool,
+    /// Have the model propose file edits as unified diffs, then review and apply them
+    #[clap(long, conflicts_with_all = ["execute", "code"])]
+    pub patch: bool,
     /// Include files, directories, or URLs
     #[clap(short = 'f', long, value_name = "FILE")]
     pub file: Vec<String>,
@@ -57,6 +86,9 @@ pub struct Cli {
     /// Display the message without sending it
     #[clap(long)]
     pub dry_run: bool,
+    /// Make no network calls, only local models answer
+    #[clap(long)]
+    pub offline: bool,
     /// Display information
     #[clap(long)]
     pub info: bool,
@@ -66,6 +98,9 @@ pub struct Cli {
     /// List all available chat models
     #[clap(long)]
     pub list_models: bool,
+    /// Suggest local models and quantizations that run well on this machine
+    #[clap(long)]
+    pub recommend_models: bool,
     /// List all roles
     #[clap(long)]
     pub list_roles: bool,
@@ -81,6 +116,46 @@ pub struct Cli {
     /// List all macros
     #[clap(long)]
     pub list_macros: bool,
+    /// Manage trusted commands: list, add <PATTERN>, remove <PATTERN>
+    #[clap(long, value_names = ["ACTION", "PATTERN"], num_args = 1..=2)]
+    pub trust: Vec<String>,
+    /// Manage vetted command recipes: list, add <FILE>, run <NAME>, remove <NAME>
+    #[clap(long, value_names = ["ACTION", "NAME"], num_args = 1..=2)]
+    pub recipe: Vec<String>,
+    /// Compare machines: export [FILE] writes this one's environment, diff <A> <B> compares two
+    /// exported ones and puts both in the prompt when a question follows
+    #[clap(long, value_names = ["ACTION", "FILE"], num_args = 1..=3)]
+    pub env: Vec<String>,
+    /// Show the audit log: list [RANGE] (default 7d, or e.g. 2h, 2026-10-09..), search <QUERY>
+    #[clap(long, value_names = ["ACTION", "ARG"], num_args = 1..=2)]
+    pub audit: Vec<String>,
+    /// Draft a post-mortem from the audit log of a session or a time range (e.g. 2h, 2026-10-16..)
+    #[clap(long, value_name = "SESSION|RANGE")]
+    pub postmortem: Option<String>,
+    /// Step through a past session turn by turn, with the recorded outputs of its commands
+    #[clap(long, value_name = "SESSION")]
+    pub replay: Option<String>,
+    /// Execute these steps of the replay again instead of showing what they output (e.g. 2,4)
+    #[clap(long, value_name = "STEPS", value_delimiter = ',', requires = "replay")]
+    pub replay_exec: Vec<usize>,
+    /// Set up the provider, API key and safety settings interactively
+    #[clap(long)]
+    pub init: bool,
+    /// Show a dashboard of your local usage, or write it as HTML to FILE
+    #[clap(long, value_name = "FILE")]
+    pub stats: Option<Option<String>>,
+    /// Report your riskiest recurring shell habits, or write them as Markdown to FILE
+    #[clap(long, value_name = "FILE")]
+    pub habits: Option<Option<String>>,
+    /// Encrypt the sessions and audit log written in plain text with `encrypt_at_rest`
+    #[clap(long)]
+    pub encrypt_existing: bool,
+    /// Update to the latest release after verifying its checksum
+    #[clap(long)]
+    pub self_update: bool,
+    /// Log the analysis, backup and execution steps to stderr
+    #[clap(short = 'v', long)]
+    pub verbose: bool,
     /// Input text
     #[clap(trailing_var_arg = true)]
     text: Vec<String>,
@@ -1,6 +1,8 @@
 use super::*;
 
-use crate::utils::{base64_decode, encode_uri, hex_encode, hmac_sha256, sha256, strip_think_tag};
+use crate::utils::{
+    base64_decode, encode_uri, ensure_network, hex_encode, hmac_sha256, sha256, strip_think_tag,
+};
 
 use anyhow::{bail, Context, Result};
 use aws_smithy_eventstream::frame::{DecodedFrame, MessageFrameDecoder};
@@ -561,6 +563,7 @@ fn aws_fetch(
         mut headers,
         body,
     } = request;
+    ensure_network("AWS Bedrock", &format!("https://{host}"))?;
     let region = &credentials.region;
 
     let endpoint = format!("https://{host}{uri}");
@@ -1,7 +1,7 @@
 use super::*;
 
 use crate::{
-    config::{Config, GlobalConfig, Input},
+    config::{Config, GlobalConfig, Input, UsageEntry, UsageLog},
     function::{eval_tool_calls, FunctionDeclaration, ToolCall, ToolResult},
     render::render_stream,
     utils::*,
@@ -152,9 +152,15 @@ pub trait Client: Sync + Send {
         &self,
         client: &reqwest::Client,
         mut request_data: RequestData,
-    ) -> RequestBuilder {
+    ) -> Result<RequestBuilder> {
         self.patch_request_data(&mut request_data);
-        request_data.into_builder(client)
+        if is_offline() && !is_local_url(&request_data.url) {
+            bail!(
+                "The model '{}' requires network access, which --offline turns off. Use a local model instead, e.g. `--model ollama:llama3.2`, `--recommend-models` suggests some",
+                self.model().id()
+            );
+        }
+        Ok(request_data.into_builder(client))
     }
 
     fn patch_request_data(&self, request_data: &mut RequestData) {
@@ -422,8 +428,11 @@ pub async fn call_chat_completions(
             let ChatCompletionsOutput {
                 mut text,
                 tool_calls,
+                input_tokens,
+                output_tokens,
                 ..
             } = ret;
+            record_usage(client, input, input_tokens, output_tokens, &text);
             if !text.is_empty() {
                 if extract_code {
                     text = extract_code_block(&strip_think_tag(&text)).to_string();
@@ -458,6 +467,7 @@ pub async fn call_chat_completions_streaming(
     render_ret?;
 
     let (text, tool_calls) = handler.take();
+    record_usage(client, input, None, None, &text);
     match send_ret {
         Ok(_) => {
             if !text.is_empty() && !text.ends_with('\n') {
@@ -474,6 +484,35 @@ pub async fn call_chat_completions_streaming(
     }
 }
 
+/// Count the tokens against the budget, estimating them when the API didn't report usage.
+/// Counts the tokens of a response towards the budget and the usage log, estimated where
+/// the provider reports none, and returns them.
+pub fn record_usage(
+    client: &dyn Client,
+    input: &Input,
+    input_tokens: Option<u64>,
+    output_tokens: Option<u64>,
+    text: &str,
+) -> (u64, u64) {
+    let model = client.model();
+    let input_tokens = input_tokens.unwrap_or_else(|| {
+        input
+            .build_messages()
+            .map(|v| model.total_tokens(&v) as u64)
+            .unwrap_or_default()
+    });
+    let output_tokens = output_tokens.unwrap_or_else(|| estimate_token_length(text) as u64);
+    client
+        .global_config()
+        .write()
+        .budget_usage
+        .record_tokens(model, input_tokens, output_tokens);
+    if let Err(err) = UsageLog::append(&UsageEntry::new(model, input_tokens, output_tokens)) {
+        warn!("Failed to record usage: {err}");
+    }
+    (input_tokens, output_tokens)
+}
+
 pub fn noop_prepare_embeddings<T>(_client: &T, _data: &EmbeddingsData) -> Result<RequestData> {
     bail!("The client doesn't support embeddings api")
 }
@@ -2,6 +2,7 @@ use super::access_token::*;
 use super::claude::*;
 use super::openai::*;
 use super::*;
+use crate::utils::ensure_network;
 
 use anyhow::{anyhow, bail, Context, Result};
 use chrono::{Duration, Utc};
@@ -45,7 +46,7 @@ impl Client for VertexAIClient {
         let model = self.model();
         let model_category = ModelCategory::from_str(model.real_name())?;
         let request_data = prepare_chat_completions(self, data, &model_category)?;
-        let builder = self.request_builder(client, request_data);
+        let builder = self.request_builder(client, request_data)?;
         match model_category {
             ModelCategory::Gemini => gemini_chat_completions(builder, model).await,
             ModelCategory::Claude => claude_chat_completions(builder, model).await,
@@ -63,7 +64,7 @@ impl Client for VertexAIClient {
         let model = self.model();
         let model_category = ModelCategory::from_str(model.real_name())?;
         let request_data = prepare_chat_completions(self, data, &model_category)?;
-        let builder = self.request_builder(client, request_data);
+        let builder = self.request_builder(client, request_data)?;
         match model_category {
             ModelCategory::Gemini => {
                 gemini_chat_completions_streaming(builder, handler, model).await
@@ -84,7 +85,7 @@ impl Client for VertexAIClient {
     ) -> Result<Vec<Vec<f32>>> {
         prepare_gcloud_access_token(client, self.name(), &self.config.adc_file).await?;
         let request_data = prepare_embeddings(self, data)?;
-        let builder = self.request_builder(client, request_data);
+        let builder = self.request_builder(client, request_data)?;
         embeddings(builder, self.model()).await
     }
 }
@@ -453,6 +454,7 @@ pub async fn prepare_gcloud_access_token(
     adc_file: &Option<String>,
 ) -> Result<()> {
     if !is_valid_access_token(client_name) {
+        ensure_network("Vertex AI", "https://oauth2.googleapis.com/token")?;
         let (token, expires_in) = fetch_access_token(client, adc_file)
             .await
             .with_context(|| "Failed to fetch access token")?;
@@ -0,0 +1,462 @@
+use super::{ensure_parent_exists, seal, unseal, Config};
+
+use crate::utils::SafetyLevel;
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local};
+use serde::{Deserialize, Serialize};
+use std::{
+    env,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+    process,
+};
+
+const AUDIT_FILE_NAME: &str = "audit.jsonl";
+
+/// Approvals of the same command before suggesting to auto-approve it.
+pub const RULE_SUGGESTION_THRESHOLD: usize = 5;
+
+/// The reason recorded for commands run by `.undo`.
+pub const UNDO_REASON: &str = "undo";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AuditDecision {
+    /// Executed after the user chose to.
+    Approved,
+    /// Executed without prompting because a trust rule matched.
+    Trusted,
+    /// The user quit or declined a confirmation.
+    Denied,
+    /// Refused by a hook, remote policy, the network policy or a canary path.
+    Blocked,
+    /// The user declined the suggestion to auto-approve the command.
+    RuleDeclined,
+    /// A note attached with `.note` to the command executed before it.
+    Noted,
+    /// Analyzed and copied or printed, not executed here.
+    Analyzed,
+}
+
+impl std::fmt::Display for AuditDecision {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            AuditDecision::Approved => "approved",
+            AuditDecision::Trusted => "trusted",
+            AuditDecision::Denied => "denied",
+            AuditDecision::Blocked => "blocked",
+            AuditDecision::RuleDeclined => "rule_declined",
+            AuditDecision::Noted => "noted",
+            AuditDecision::Analyzed => "analyzed",
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// One line of the audit log.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuditEntry {
+    pub timestamp: String,
+    pub command: String,
+    pub decision: AuditDecision,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub safety_level: Option<SafetyLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cwd: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub reason: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub exit_code: Option<i32>,
+    /// The synthesized command that reverses this one.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub undo: Option<String>,
+    /// Ids of the backups taken right before executing.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub backups: Vec<String>,
+    /// The aichat process, which scopes `.report` to the current session.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub pid: Option<u32>,
+    /// Why the command was run, written with `.note`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub note: Option<String>,
+    /// The files a `--patch` changed, `path +added -removed` each.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub changes: Vec<String>,
+}
+
+impl AuditEntry {
+    pub fn new(command: &str, decision: AuditDecision) -> Self {
+        Self {
+            timestamp: Local::now().to_rfc3339(),
+            command: command.trim().to_string(),
+            decision,
+            safety_level: None,
+            cwd: env::current_dir().ok().map(|v| v.display().to_string()),
+            remote: None,
+            reason: None,
+            exit_code: None,
+            undo: None,
+            backups: vec![],
+            pid: Some(process::id()),
+            note: None,
+            changes: vec![],
+        }
+    }
+
+    /// A note on `entry`, which keeps its command, directory and backups so searching the
+    /// notes finds them by any of these.
+    pub fn note(entry: &AuditEntry, text: &str) -> Self {
+        Self {
+            cwd: entry.cwd.clone(),
+            remote: entry.remote.clone(),
+            backups: entry.backups.clone(),
+            note: Some(text.trim().to_string()),
+            ..Self::new(&entry.command, AuditDecision::Noted)
+        }
+    }
+
+    pub fn with_safety_level(mut self, level: SafetyLevel) -> Self {
+        self.safety_level = Some(level);
+        self
+    }
+
+    pub fn with_remote(mut self, remote: Option<String>) -> Self {
+        self.remote = remote;
+        self
+    }
+
+    pub fn with_reason(mut self, reason: &str) -> Self {
+        self.reason = Some(reason.to_string());
+        self
+    }
+
+    pub fn with_exit_code(mut self, code: i32) -> Self {
+        self.exit_code = Some(code);
+        self
+    }
+
+    pub fn with_undo(mut self, undo: Option<String>) -> Self {
+        self.undo = undo;
+        self
+    }
+
+    pub fn with_backups(mut self, backups: Vec<String>) -> Self {
+        self.backups = backups;
+        self
+    }
+
+    pub fn with_changes(mut self, changes: Vec<String>) -> Self {
+        self.changes = changes;
+        self
+    }
+
+    /// One line of `--audit`: time, decision, level, exit status, command and backups.
+    pub fn summary(&self) -> String {
+        let time = DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|v| v.with_timezone(&Local).format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| self.timestamp.clone());
+        let level = self
+            .safety_level
+            .as_ref()
+            .map(|v| format!("{v:?}").to_lowercase())
+            .unwrap_or_default();
+        let exit = self
+            .exit_code
+            .map(|v| format!("exit {v}"))
+            .unwrap_or_default();
+        let mut output = format!(
+            "{time}  {:<13} {level:<9} {exit:<7} {}",
+            self.decision.to_string(),
+            self.command
+        );
+        if let Some(remote) = &self.remote {
+            output.push_str(&format!("  @{remote}"));
+        }
+        if !self.backups.is_empty() {
+            output.push_str(&format!("  (backup {})", self.backups.join(", ")));
+        }
+        if let Some(note) = self.note.as_ref().or(self.reason.as_ref()) {
+            output.push_str(&format!("  # {note}"));
+        }
+        output
+    }
+}
+
+/// Append-only JSON lines log of what happened to every generated command.
+pub struct AuditLog;
+
+impl AuditLog {
+    pub fn path() -> PathBuf {
+        Config::local_path(AUDIT_FILE_NAME)
+    }
+
+    pub fn append(entry: &AuditEntry) -> Result<()> {
+        let path = Self::path();
+        ensure_parent_exists(&path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open '{}'", path.display()))?;
+        writeln!(file, "{}", seal(&serde_json::to_string(entry)?)?)?;
+        Ok(())
+    }
+
+    /// All entries, skipping lines that don't parse. Encrypted lines need the key.
+    pub fn read() -> Result<Vec<AuditEntry>> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        let mut entries = vec![];
+        for line in content.lines() {
+            let line =
+                unseal(line).with_context(|| format!("Failed to read '{}'", path.display()))?;
+            if let Ok(entry) = serde_json::from_str(&line) {
+                entries.push(entry);
+            }
+        }
+        Ok(entries)
+    }
+}
+
+/// The latest command executed locally in `cwd` that can be reversed and wasn't yet,
+/// judging by the entries recorded with the reason [`UNDO_REASON`].
+pub fn find_undoable<'a>(entries: &'a [AuditEntry], cwd: &str) -> Option<&'a AuditEntry> {
+    let mut undone = vec![];
+    for entry in entries.iter().rev() {
+        if entry.remote.is_some() || entry.cwd.as_deref() != Some(cwd) {
+            continue;
+        }
+        if entry.reason.as_deref() == Some(UNDO_REASON) {
+            undone.push(entry.command.as_str());
+            continue;
+        }
+        let Some(undo) = &entry.undo else {
+            continue;
+        };
+        if !matches!(
+            entry.decision,
+            AuditDecision::Approved | AuditDecision::Trusted
+        ) || entry.exit_code.is_some_and(|v| v != 0)
+        {
+            continue;
+        }
+        match undone.iter().position(|v| v == undo) {
+            Some(i) => {
+                undone.remove(i);
+            }
+            None => return Some(entry),
+        }
+    }
+    None
+}
+
+/// The latest command the aichat process `pid` executed.
+pub fn last_executed(entries: &[AuditEntry], pid: u32) -> Option<&AuditEntry> {
+    entries.iter().rev().find(|v| {
+        v.pid == Some(pid) && matches!(v.decision, AuditDecision::Approved | AuditDecision::Trusted)
+    })
+}
+
+/// The notes whose text, command, directory or backup ids contain `query` (ignoring case),
+/// newest first; all of them without a query.
+pub fn search_notes<'a>(entries: &'a [AuditEntry], query: Option<&str>) -> Vec<&'a AuditEntry> {
+    let query = query.map(|v| v.trim().to_lowercase());
+    entries
+        .iter()
+        .rev()
+        .filter(|v| v.decision == AuditDecision::Noted)
+        .filter(|v| {
+            let Some(query) = &query else {
+                return true;
+            };
+            [v.note.as_deref(), Some(&v.command), v.cwd.as_deref()]
+                .into_iter()
+                .flatten()
+                .chain(v.backups.iter().map(|v| v.as_str()))
+                .any(|v| v.to_lowercase().contains(query))
+        })
+        .collect()
+}
+
+/// The entries logged between `from` and `until`, oldest first.
+pub fn entries_between(
+    entries: &[AuditEntry],
+    from: DateTime<Local>,
+    until: DateTime<Local>,
+) -> Vec<&AuditEntry> {
+    entries
+        .iter()
+        .filter(|v| {
+            DateTime::parse_from_rfc3339(&v.timestamp)
+                .map(|v| from <= v && v <= until)
+                .unwrap_or_default()
+        })
+        .collect()
+}
+
+/// The entries whose command, directory, remote, reason, note or backup ids contain `query`
+/// (ignoring case), oldest first.
+pub fn search_entries<'a>(entries: &'a [AuditEntry], query: &str) -> Vec<&'a AuditEntry> {
+    let query = query.trim().to_lowercase();
+    entries
+        .iter()
+        .filter(|v| {
+            [
+                Some(v.command.as_str()),
+                v.cwd.as_deref(),
+                v.remote.as_deref(),
+                v.reason.as_deref(),
+                v.note.as_deref(),
+            ]
+            .into_iter()
+            .flatten()
+            .chain(v.backups.iter().map(|v| v.as_str()))
+            .any(|v| v.to_lowercase().contains(&query))
+        })
+        .collect()
+}
+
+/// The number of approvals when `command` is worth suggesting as an auto-approve rule:
+/// approved at least [`RULE_SUGGESTION_THRESHOLD`] times, never denied or blocked,
+/// and the suggestion wasn't declined before.
+pub fn suggest_rule(entries: &[AuditEntry], command: &str) -> Option<usize> {
+    let command = command.trim();
+    let mut approvals = 0;
+    for entry in entries.iter().filter(|v| v.command == command) {
+        match entry.decision {
+            AuditDecision::Approved => approvals += 1,
+            AuditDecision::Trusted | AuditDecision::Noted | AuditDecision::Analyzed => {}
+            AuditDecision::Denied | AuditDecision::Blocked | AuditDecision::RuleDeclined => {
+                return None
+            }
+        }
+    }
+    (approvals >= RULE_SUGGESTION_THRESHOLD).then_some(approvals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_suggest_rule() {
+        let approve = |command: &str| AuditEntry::new(command, AuditDecision::Approved);
+        let mut entries: Vec<_> = (0..RULE_SUGGESTION_THRESHOLD)
+            .map(|_| approve("docker compose down"))
+            .collect();
+        entries.push(approve("ls"));
+        assert_eq!(
+            suggest_rule(&entries, "docker compose down "),
+            Some(RULE_SUGGESTION_THRESHOLD)
+        );
+        assert_eq!(suggest_rule(&entries, "ls"), None);
+
+        entries.push(AuditEntry::new(
+            "docker compose down",
+            AuditDecision::RuleDeclined,
+        ));
+        assert_eq!(suggest_rule(&entries, "docker compose down"), None);
+    }
+
+    #[test]
+    fn test_find_undoable() {
+        let cwd = AuditEntry::new("ls", AuditDecision::Approved).cwd.unwrap();
+        let run = |command: &str, undo: &str| {
+            AuditEntry::new(command, AuditDecision::Approved)
+                .with_exit_code(0)
+                .with_undo(Some(undo.into()))
+        };
+        let mut entries = vec![
+            run("mv a b", "mv b a"),
+            run("git commit -m x", "git reset --soft HEAD~1"),
+            run("mkdir c", "rmdir c").with_exit_code(1),
+            AuditEntry::new("ls", AuditDecision::Approved),
+        ];
+        assert_eq!(
+            find_undoable(&entries, &cwd).unwrap().command,
+            "git commit -m x"
+        );
+        entries.push(
+            AuditEntry::new("git reset --soft HEAD~1", AuditDecision::Approved)
+                .with_reason(UNDO_REASON),
+        );
+        assert_eq!(find_undoable(&entries, &cwd).unwrap().command, "mv a b");
+        assert!(find_undoable(&entries, "/elsewhere").is_none());
+    }
+
+    #[test]
+    fn test_notes() {
+        let pid = process::id();
+        let mut entries = vec![
+            AuditEntry::new("rm -rf build", AuditDecision::Approved)
+                .with_backups(vec!["20240101_120000_1".into()]),
+            AuditEntry::new("git push --force", AuditDecision::Denied),
+        ];
+        let last = last_executed(&entries, pid).unwrap();
+        assert_eq!(last.command, "rm -rf build");
+        assert!(last_executed(&entries, pid + 1).is_none());
+
+        let note = AuditEntry::note(last, " stale artifacts after the toolchain bump ");
+        assert_eq!(note.decision, AuditDecision::Noted);
+        assert_eq!(note.backups, ["20240101_120000_1"]);
+        entries.push(note);
+        assert_eq!(search_notes(&entries, None).len(), 1);
+        assert_eq!(search_notes(&entries, Some("TOOLCHAIN")).len(), 1);
+        assert_eq!(search_notes(&entries, Some("20240101")).len(), 1);
+        assert!(search_notes(&entries, Some("push")).is_empty());
+        assert_eq!(suggest_rule(&entries, "rm -rf build"), None);
+    }
+
+    #[test]
+    fn test_search_entries() {
+        let entries = vec![
+            AuditEntry::new("rm -rf build", AuditDecision::Approved)
+                .with_safety_level(SafetyLevel::Dangerous)
+                .with_exit_code(0)
+                .with_backups(vec!["20240101_120000_1".into()]),
+            AuditEntry::new("kubectl delete ns staging", AuditDecision::Denied)
+                .with_remote(Some("bastion".into())),
+        ];
+        assert_eq!(search_entries(&entries, "BUILD").len(), 1);
+        assert_eq!(search_entries(&entries, "20240101").len(), 1);
+        assert_eq!(
+            search_entries(&entries, "bastion")[0].command,
+            "kubectl delete ns staging"
+        );
+        assert!(search_entries(&entries, "terraform").is_empty());
+
+        let now = Local::now();
+        let week = entries_between(&entries, now - chrono::Duration::days(7), now);
+        assert_eq!(week.len(), 2);
+        assert!(entries_between(
+            &entries,
+            now - chrono::Duration::days(7),
+            now - chrono::Duration::days(1)
+        )
+        .is_empty());
+
+        let summary = entries[0].summary();
+        assert!(summary.ends_with(
+            "  approved      dangerous exit 0  rm -rf build  (backup 20240101_120000_1)"
+        ));
+    }
+
+    #[test]
+    fn test_audit_entry_serialization() {
+        let entry = AuditEntry::new("rm -rf build", AuditDecision::Approved)
+            .with_safety_level(SafetyLevel::Dangerous)
+            .with_exit_code(0);
+        let json = serde_json::to_string(&entry).unwrap();
+        assert!(json.contains(r#""decision":"approved""#));
+        assert!(json.contains(r#""safety_level":"dangerous""#));
+        assert!(!json.contains("reason"));
+    }
+}
@@ -0,0 +1,179 @@
+use crate::client::Model;
+
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+/// Hard caps on what tool calls may do before the user renews the authorization,
+/// configured under `budget`:
+///
+/// ```yaml
+/// budget:
+///   max_commands: 50
+///   max_risk_score: 300
+///   max_tokens: 500000
+///   max_cost: 2.0
+///   max_minutes: 30
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct BudgetCaps {
+    /// Tool calls executed.
+    pub max_commands: Option<usize>,
+    /// Sum of the 0-100 risk scores of the executed commands, the scale of
+    /// `risk_confirm_score` and `risk_block_score`.
+    pub max_risk_score: Option<u32>,
+    /// Input plus output tokens.
+    pub max_tokens: Option<u64>,
+    /// In the currency of the model prices.
+    pub max_cost: Option<f64>,
+    pub max_minutes: Option<u64>,
+}
+
+impl BudgetCaps {
+    pub fn is_empty(&self) -> bool {
+        *self == Self::default()
+    }
+}
+
+impl std::fmt::Display for BudgetCaps {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let mut caps = vec![];
+        if let Some(v) = self.max_commands {
+            caps.push(format!("{v} commands"));
+        }
+        if let Some(v) = self.max_risk_score {
+            caps.push(format!("risk {v}"));
+        }
+        if let Some(v) = self.max_tokens {
+            caps.push(format!("{v} tokens"));
+        }
+        if let Some(v) = self.max_cost {
+            caps.push(format!("cost {v}"));
+        }
+        if let Some(v) = self.max_minutes {
+            caps.push(format!("{v} min"));
+        }
+        match caps.is_empty() {
+            true => write!(f, "-"),
+            false => write!(f, "{}", caps.join(", ")),
+        }
+    }
+}
+
+/// What the session spent since the budget was last authorized.
+#[derive(Debug, Clone)]
+pub struct BudgetUsage {
+    pub started: Instant,
+    pub commands: usize,
+    pub risk_score: u32,
+    pub tokens: u64,
+    pub cost: f64,
+}
+
+impl Default for BudgetUsage {
+    fn default() -> Self {
+        Self {
+            started: Instant::now(),
+            commands: 0,
+            risk_score: 0,
+            tokens: 0,
+            cost: 0.0,
+        }
+    }
+}
+
+impl BudgetUsage {
+    pub fn record_tokens(&mut self, model: &Model, input_tokens: u64, output_tokens: u64) {
+        self.tokens += input_tokens + output_tokens;
+        self.cost += token_cost(model, input_tokens, output_tokens);
+    }
+
+    /// Why running one more command with `risk` would break `caps`, `None` when it's within.
+    pub fn exceeded(&self, caps: &BudgetCaps, risk: u32) -> Option<String> {
+        self.exceeded_at(caps, risk, self.started.elapsed())
+    }
+
+    fn exceeded_at(&self, caps: &BudgetCaps, risk: u32, elapsed: Duration) -> Option<String> {
+        if let Some(max) = caps.max_commands.filter(|v| self.commands >= *v) {
+            return Some(format!("{max} commands executed"));
+        }
+        if let Some(max) = caps.max_risk_score.filter(|v| self.risk_score + risk > *v) {
+            return Some(format!(
+                "risk score {} of {max} used, the next command scores {risk}",
+                self.risk_score
+            ));
+        }
+        if let Some(max) = caps.max_tokens.filter(|v| self.tokens >= *v) {
+            return Some(format!("{} of {max} tokens used", self.tokens));
+        }
+        if let Some(max) = caps.max_cost.filter(|v| self.cost >= *v) {
+            return Some(format!("cost {:.4} of {max} spent", self.cost));
+        }
+        if let Some(max) = caps.max_minutes.filter(|v| elapsed.as_secs() >= v * 60) {
+            return Some(format!("{max} minutes elapsed"));
+        }
+        None
+    }
+
+    pub fn record_command(&mut self, risk: u32) {
+        self.commands += 1;
+        self.risk_score += risk;
+    }
+}
+
+impl std::fmt::Display for BudgetUsage {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{} commands, risk {}, {} tokens, cost {:.4}, {} min",
+            self.commands,
+            self.risk_score,
+            self.tokens,
+            self.cost,
+            self.started.elapsed().as_secs() / 60
+        )
+    }
+}
+
+/// What the tokens cost at the model's prices, which are per million tokens.
+pub fn token_cost(model: &Model, input_tokens: u64, output_tokens: u64) -> f64 {
+    let data = model.data();
+    (input_tokens as f64 * data.input_price.unwrap_or_default()
+        + output_tokens as f64 * data.output_price.unwrap_or_default())
+        / 1_000_000.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_budget_exceeded() {
+        let caps = BudgetCaps {
+            max_commands: Some(3),
+            max_risk_score: Some(100),
+            max_minutes: Some(30),
+            ..Default::default()
+        };
+        let mut usage = BudgetUsage::default();
+        let minute = Duration::from_secs(60);
+        assert_eq!(usage.exceeded_at(&caps, 30, minute), None);
+        usage.record_command(30);
+        usage.record_command(30);
+        assert_eq!(
+            usage.exceeded_at(&caps, 45, minute),
+            Some("risk score 60 of 100 used, the next command scores 45".into())
+        );
+        usage.record_command(10);
+        assert_eq!(
+            usage.exceeded_at(&caps, 0, minute),
+            Some("3 commands executed".into())
+        );
+        let usage = BudgetUsage::default();
+        assert_eq!(
+            usage.exceeded_at(&caps, 0, minute * 30),
+            Some("30 minutes elapsed".into())
+        );
+        assert_eq!(caps.to_string(), "3 commands, risk 100, 30 min");
+        assert_eq!(BudgetCaps::default().to_string(), "-");
+    }
+}
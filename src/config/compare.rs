@@ -0,0 +1,268 @@
+use super::*;
+
+use crate::client::{record_usage, Model, ModelType};
+
+use anyhow::{bail, Result};
+use futures_util::future::join_all;
+use std::time::{Duration, Instant};
+use textwrap::core::display_width;
+
+/// Columns narrower than this make the answers follow each other instead.
+const MIN_COLUMN_WIDTH: usize = 32;
+
+/// The answer of one model of `--models`/`.compare`.
+#[derive(Debug, Clone)]
+pub struct ModelAnswer {
+    pub model_id: String,
+    pub text: String,
+    /// Why the model gave no answer, a failing provider doesn't fail the others.
+    pub error: Option<String>,
+    pub latency: Duration,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+    /// The shell code blocks of the answer, analyzed on this machine.
+    pub commands: Vec<(String, CommandAnalysis)>,
+}
+
+impl ModelAnswer {
+    fn new(model_id: &str, latency: Duration) -> Self {
+        Self {
+            model_id: model_id.to_string(),
+            text: String::new(),
+            error: None,
+            latency,
+            input_tokens: 0,
+            output_tokens: 0,
+            cost: 0.0,
+            commands: vec![],
+        }
+    }
+
+    /// The level of its most dangerous command, and the highest risk score among them.
+    pub fn danger(&self) -> Option<(SafetyLevel, u8)> {
+        let level = self
+            .commands
+            .iter()
+            .map(|(_, v)| v.safety_level.clone())
+            .max()?;
+        let score = self
+            .commands
+            .iter()
+            .map(|(_, v)| v.risk.score)
+            .max()
+            .unwrap_or_default();
+        Some((level, score))
+    }
+
+    /// `openai:gpt-4o · 2.4s · $0.0031 · 412→96 tokens`
+    pub fn header(&self) -> String {
+        let mut output = format!("{} · {:.1}s", self.model_id, self.latency.as_secs_f64());
+        if self.error.is_none() {
+            output.push_str(&format!(
+                " · ${:.4} · {}→{} tokens",
+                self.cost, self.input_tokens, self.output_tokens
+            ));
+        }
+        output
+    }
+
+    fn body(&self) -> String {
+        if let Some(error) = &self.error {
+            return format!("⚠️  {error}");
+        }
+        let mut output = strip_think_tag(&self.text).trim().to_string();
+        for (command, analysis) in &self.commands {
+            let command = command.lines().collect::<Vec<_>>().join(" ⏎ ");
+            output.push_str(&format!("\n\n{}: {command}", analysis.safety_level.label()));
+            if analysis.risk.score > 0 {
+                output.push_str(&format!(" (risk {})", analysis.risk.score));
+            }
+        }
+        output
+    }
+}
+
+/// Send `text` to each of `model_ids` at once, without tools so nothing runs before the
+/// answers are compared.
+pub async fn compare_models(
+    config: &GlobalConfig,
+    text: &str,
+    model_ids: &[String],
+    abort_signal: AbortSignal,
+) -> Result<Vec<ModelAnswer>> {
+    if model_ids.len() < 2 {
+        bail!("Compare at least two models, e.g. openai:gpt-4o,claude:claude-3-5-sonnet-latest");
+    }
+    let mut inputs = vec![];
+    for model_id in model_ids {
+        let model = Model::retrieve_model(&config.read(), model_id, ModelType::Chat)?;
+        let mut role = config.read().extract_role();
+        role.set_model(model);
+        role.set_use_tools(None);
+        inputs.push((model_id.as_str(), Input::from_str(config, text, Some(role))));
+    }
+    let tasks = inputs.iter().map(|(model_id, input)| ask(model_id, input));
+    let answers = abortable_run_with_spinner(
+        async { Ok(join_all(tasks).await) },
+        "Generating",
+        abort_signal,
+    )
+    .await?;
+    Ok(answers)
+}
+
+async fn ask(model_id: &str, input: &Input) -> ModelAnswer {
+    let start = Instant::now();
+    let output = match input.create_client() {
+        Ok(client) => client.chat_completions(input.clone()).await.map(|output| {
+            let tokens = record_usage(
+                client.as_ref(),
+                input,
+                output.input_tokens,
+                output.output_tokens,
+                &output.text,
+            );
+            (tokens, output)
+        }),
+        Err(err) => Err(err),
+    };
+    let mut answer = ModelAnswer::new(model_id, start.elapsed());
+    match output {
+        Ok(((input_tokens, output_tokens), output)) => {
+            answer.cost = token_cost(input.role().model(), input_tokens, output_tokens);
+            answer.input_tokens = input_tokens;
+            answer.output_tokens = output_tokens;
+            answer.commands = extract_shell_commands(&output.text)
+                .into_iter()
+                .map(|v| {
+                    let analysis = CommandAnalysis::analyze(&v);
+                    (v, analysis)
+                })
+                .collect();
+            answer.text = output.text;
+        }
+        Err(err) => answer.error = Some(format!("{err:#}")),
+    }
+    answer
+}
+
+/// The answer with the least dangerous commands, the first of equally safe ones. Answers
+/// without a command have nothing to pick.
+pub fn safest_answer(answers: &[ModelAnswer]) -> Option<usize> {
+    answers
+        .iter()
+        .enumerate()
+        .filter_map(|(i, v)| v.danger().map(|danger| (i, danger)))
+        .min_by(|(i, a), (j, b)| a.cmp(b).then(i.cmp(j)))
+        .map(|(i, _)| i)
+}
+
+/// The answers in columns across `width`, or one after the other when the columns would
+/// be too narrow to read.
+pub fn render_answers(answers: &[ModelAnswer], width: usize) -> String {
+    let safest = safest_answer(answers);
+    let blocks: Vec<(String, String)> = answers
+        .iter()
+        .enumerate()
+        .map(|(i, v)| {
+            let mut header = v.header();
+            if Some(i) == safest && answers.len() > 1 {
+                header.push_str(" · ★ safest");
+            }
+            (header, v.body())
+        })
+        .collect();
+    let gap = 3;
+    let column = width.saturating_sub(gap * blocks.len().saturating_sub(1)) / blocks.len().max(1);
+    if column < MIN_COLUMN_WIDTH {
+        return blocks
+            .iter()
+            .map(|(header, body)| {
+                let rule = "─".repeat(width.min(display_width(header) + 3));
+                format!("{header}\n{rule}\n{}", wrap(body, width).join("\n"))
+            })
+            .collect::<Vec<_>>()
+            .join("\n\n");
+    }
+    let columns: Vec<Vec<String>> = blocks
+        .iter()
+        .map(|(header, body)| {
+            let mut lines = wrap(header, column);
+            lines.push("─".repeat(column));
+            lines.extend(wrap(body, column));
+            lines
+        })
+        .collect();
+    let height = columns.iter().map(|v| v.len()).max().unwrap_or_default();
+    (0..height)
+        .map(|row| {
+            let cells: Vec<String> = columns
+                .iter()
+                .map(|lines| {
+                    let cell = lines.get(row).map(|v| v.as_str()).unwrap_or_default();
+                    format!(
+                        "{cell}{}",
+                        " ".repeat(column.saturating_sub(display_width(cell)))
+                    )
+                })
+                .collect();
+            cells.join(&" ".repeat(gap)).trim_end().to_string()
+        })
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn wrap(text: &str, width: usize) -> Vec<String> {
+    text.lines()
+        .flat_map(|line| match line.is_empty() {
+            true => vec![String::new()],
+            false => textwrap::wrap(line, width)
+                .into_iter()
+                .map(|v| v.to_string())
+                .collect(),
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn answer(model_id: &str, commands: &[&str]) -> ModelAnswer {
+        let mut answer = ModelAnswer::new(model_id, Duration::from_millis(1500));
+        answer.text = "Free some space:".to_string();
+        answer.cost = 0.0012;
+        answer.input_tokens = 40;
+        answer.output_tokens = 25;
+        answer.commands = commands
+            .iter()
+            .map(|v| (v.to_string(), CommandAnalysis::analyze(v)))
+            .collect();
+        answer
+    }
+
+    #[test]
+    fn test_compare_answers() {
+        let answers = vec![
+            answer("a:reckless", &["rm -rf /var/log"]),
+            answer("b:careful", &["du -sh /var/log/*"]),
+            answer("c:silent", &[]),
+        ];
+        assert_eq!(safest_answer(&answers), Some(1));
+        assert_eq!(
+            answers[1].header(),
+            "b:careful · 1.5s · $0.0012 · 40→25 tokens"
+        );
+
+        let output = render_answers(&answers[..2], 120);
+        let first = output.lines().next().unwrap();
+        assert!(first.starts_with("a:reckless · 1.5s"));
+        assert!(first.ends_with("b:careful · 1.5s · $0.0012 · 40→25 tokens · ★ safest"));
+        assert!(output.contains("CRITICAL: rm -rf /var/log"));
+        assert!(output.contains("SAFE: du -sh /var/log/*"));
+
+        let output = render_answers(&answers, 60);
+        assert!(output.starts_with("a:reckless · 1.5s · $0.0012 · 40→25 tokens\n───"));
+    }
+}
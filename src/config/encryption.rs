@@ -0,0 +1,389 @@
+use super::{ensure_parent_exists, AuditLog, Config, SESSIONS_DIR_NAME};
+
+use crate::utils::{base64_decode, base64_encode, IS_STDOUT_TERMINAL};
+
+use anyhow::{anyhow, bail, Context, Result};
+use inquire::Password;
+use parking_lot::RwLock;
+use ring::{
+    aead::{Aad, LessSafeKey, Nonce, UnboundKey, AES_256_GCM, NONCE_LEN},
+    pbkdf2,
+    rand::{SecureRandom, SystemRandom},
+};
+use serde::{Deserialize, Serialize};
+use std::{
+    env, fs,
+    io::Write,
+    num::NonZeroU32,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+    sync::{Arc, LazyLock},
+};
+
+const ENCRYPTION_FILE_NAME: &str = "encryption.yaml";
+
+/// Starts every encrypted file and audit log line, followed by the base64 of the nonce and
+/// the ciphertext.
+const SEALED_PREFIX: &str = "aichat-sealed:v1:";
+
+const PASSPHRASE_ENV: &str = "AICHAT_PASSPHRASE";
+const KEYCHAIN_SERVICE: &str = "aichat";
+const KEYCHAIN_ACCOUNT: &str = "at-rest";
+const PBKDF2_ITERATIONS: u32 = 600_000;
+
+/// Sealed into `encryption.yaml` to tell a wrong passphrase from a right one.
+const CHECK_TEXT: &str = "aichat";
+
+static AT_REST_KEY: LazyLock<RwLock<Arc<AtRestKey>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(AtRestKey::default())));
+
+/// The key sessions and the audit log are encrypted with (`encrypt_at_rest`).
+pub fn at_rest_key() -> Arc<AtRestKey> {
+    AT_REST_KEY.read().clone()
+}
+
+pub fn set_at_rest_key(key: AtRestKey) {
+    *AT_REST_KEY.write() = Arc::new(key);
+}
+
+/// Where the key of `encrypt_at_rest` comes from.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AtRestEncryption {
+    /// Sessions and the audit log are plain text.
+    #[default]
+    Off,
+    /// A random key kept in the macOS Keychain or the Secret Service (`secret-tool`).
+    Keychain,
+    /// A key derived from a passphrase, asked at startup or read from `AICHAT_PASSPHRASE`.
+    Passphrase,
+}
+
+impl std::str::FromStr for AtRestEncryption {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(AtRestEncryption::Off),
+            "keychain" => Ok(AtRestEncryption::Keychain),
+            "passphrase" => Ok(AtRestEncryption::Passphrase),
+            _ => Err(format!(
+                "invalid encryption '{s}', expected off, keychain or passphrase"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AtRestEncryption {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+#[derive(Default)]
+pub enum AtRestKey {
+    #[default]
+    Plaintext,
+    Key([u8; 32]),
+    /// Encryption is on but the key couldn't be had, nothing is written in plain text.
+    Locked(String),
+}
+
+impl AtRestKey {
+    pub fn load(mode: AtRestEncryption) -> Self {
+        let key = match mode {
+            AtRestEncryption::Off => return Self::Plaintext,
+            AtRestEncryption::Keychain => keychain_key(),
+            AtRestEncryption::Passphrase => {
+                passphrase_key(&Config::local_path(ENCRYPTION_FILE_NAME))
+            }
+        };
+        match key {
+            Ok(key) => Self::Key(key),
+            Err(err) => Self::Locked(format!("{err:#}")),
+        }
+    }
+}
+
+/// `text` encrypted with the key of `encrypt_at_rest` as a single line, or as it is when
+/// encryption is off.
+pub fn seal(text: &str) -> Result<String> {
+    match at_rest_key().as_ref() {
+        AtRestKey::Plaintext => Ok(text.to_string()),
+        AtRestKey::Key(key) => seal_with(key, text),
+        AtRestKey::Locked(err) => {
+            bail!("Not writing in plain text, the encryption key is unavailable: {err}")
+        }
+    }
+}
+
+/// The plain text of what [`seal`] wrote. Plain text written before encryption was turned
+/// on is returned as it is.
+pub fn unseal(text: &str) -> Result<String> {
+    if !is_sealed(text) {
+        return Ok(text.to_string());
+    }
+    match at_rest_key().as_ref() {
+        AtRestKey::Key(key) => open_with(key, text),
+        AtRestKey::Plaintext => bail!("It is encrypted, set `encrypt_at_rest` to read it"),
+        AtRestKey::Locked(err) => bail!("It is encrypted and the key is unavailable: {err}"),
+    }
+}
+
+pub fn is_sealed(text: &str) -> bool {
+    text.starts_with(SEALED_PREFIX)
+}
+
+/// `aichat --encrypt-existing`: encrypts the session files and audit log lines written in
+/// plain text, returns how many files it changed.
+pub fn encrypt_existing(config: &Config) -> Result<usize> {
+    match at_rest_key().as_ref() {
+        AtRestKey::Key(_) => {}
+        AtRestKey::Plaintext => bail!("Set `encrypt_at_rest` to keychain or passphrase first"),
+        AtRestKey::Locked(err) => bail!("The encryption key is unavailable: {err}"),
+    }
+    let mut dirs = vec![config.sessions_dir()];
+    if let Ok(entries) = fs::read_dir(Config::agents_data_dir()) {
+        dirs.extend(entries.flatten().map(|v| v.path().join(SESSIONS_DIR_NAME)));
+    }
+    let mut files = vec![];
+    for dir in dirs {
+        collect_yaml_files(&dir, &mut files);
+    }
+    let mut count = 0;
+    for path in files {
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        if is_sealed(&content) {
+            continue;
+        }
+        replace_file(&path, &seal(&content)?)?;
+        count += 1;
+    }
+    let path = AuditLog::path();
+    if let Ok(content) = fs::read_to_string(&path) {
+        if content.lines().any(|v| !is_sealed(v)) {
+            let mut output = String::new();
+            for line in content.lines().filter(|v| !v.is_empty()) {
+                output.push_str(&match is_sealed(line) {
+                    true => line.to_string(),
+                    false => seal(line)?,
+                });
+                output.push('\n');
+            }
+            replace_file(&path, &output)?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn seal_with(key: &[u8; 32], text: &str) -> Result<String> {
+    let mut nonce = [0u8; NONCE_LEN];
+    SystemRandom::new()
+        .fill(&mut nonce)
+        .map_err(|_| anyhow!("Failed to generate a nonce"))?;
+    let mut data = text.as_bytes().to_vec();
+    cipher(key)?
+        .seal_in_place_append_tag(Nonce::assume_unique_for_key(nonce), Aad::empty(), &mut data)
+        .map_err(|_| anyhow!("Failed to encrypt"))?;
+    Ok(format!(
+        "{SEALED_PREFIX}{}",
+        base64_encode([nonce.as_slice(), &data].concat())
+    ))
+}
+
+fn open_with(key: &[u8; 32], text: &str) -> Result<String> {
+    let data = text
+        .trim_end()
+        .strip_prefix(SEALED_PREFIX)
+        .and_then(|v| base64_decode(v).ok())
+        .filter(|v| v.len() > NONCE_LEN)
+        .ok_or_else(|| anyhow!("Damaged encrypted data"))?;
+    let (nonce, data) = data.split_at(NONCE_LEN);
+    let nonce = Nonce::try_assume_unique_for_key(nonce).map_err(|_| anyhow!("Bad nonce"))?;
+    let mut data = data.to_vec();
+    let plain = cipher(key)?
+        .open_in_place(nonce, Aad::empty(), &mut data)
+        .map_err(|_| anyhow!("Failed to decrypt, wrong key or damaged data"))?;
+    Ok(String::from_utf8(plain.to_vec())?)
+}
+
+fn cipher(key: &[u8; 32]) -> Result<LessSafeKey> {
+    let key = UnboundKey::new(&AES_256_GCM, key).map_err(|_| anyhow!("Invalid key"))?;
+    Ok(LessSafeKey::new(key))
+}
+
+/// The salt of the passphrase and [`CHECK_TEXT`] sealed with its key.
+#[derive(Debug, Serialize, Deserialize)]
+struct PassphraseFile {
+    salt: String,
+    check: String,
+}
+
+fn passphrase_key(path: &Path) -> Result<[u8; 32]> {
+    let existing: Option<PassphraseFile> = match fs::read_to_string(path) {
+        Ok(content) => Some(
+            serde_yaml::from_str(&content)
+                .with_context(|| format!("Invalid '{}'", path.display()))?,
+        ),
+        Err(_) => None,
+    };
+    let passphrase = match env::var(PASSPHRASE_ENV) {
+        Ok(value) => value,
+        Err(_) if *IS_STDOUT_TERMINAL => {
+            let prompt = Password::new("Passphrase for sessions and the audit log:");
+            match existing {
+                Some(_) => prompt.without_confirmation().prompt()?,
+                None => prompt
+                    .with_custom_confirmation_message("Passphrase again:")
+                    .prompt()?,
+            }
+        }
+        Err(_) => bail!("No passphrase, set {PASSPHRASE_ENV}"),
+    };
+    match existing {
+        Some(file) => {
+            let salt = base64_decode(&file.salt).context("Invalid salt")?;
+            let key = derive_key(&passphrase, &salt);
+            open_with(&key, &file.check)
+                .ok()
+                .filter(|v| v == CHECK_TEXT)
+                .ok_or_else(|| anyhow!("Wrong passphrase"))?;
+            Ok(key)
+        }
+        None => {
+            let salt = random_bytes::<16>()?;
+            let key = derive_key(&passphrase, &salt);
+            let file = PassphraseFile {
+                salt: base64_encode(salt),
+                check: seal_with(&key, CHECK_TEXT)?,
+            };
+            ensure_parent_exists(path)?;
+            fs::write(path, serde_yaml::to_string(&file)?)
+                .with_context(|| format!("Failed to write '{}'", path.display()))?;
+            Ok(key)
+        }
+    }
+}
+
+fn derive_key(passphrase: &str, salt: &[u8]) -> [u8; 32] {
+    let mut key = [0u8; 32];
+    let iterations = NonZeroU32::new(PBKDF2_ITERATIONS).expect("non-zero iterations");
+    pbkdf2::derive(
+        pbkdf2::PBKDF2_HMAC_SHA256,
+        iterations,
+        salt,
+        passphrase.as_bytes(),
+        &mut key,
+    );
+    key
+}
+
+/// The key in the OS keychain, a new random one the first time.
+fn keychain_key() -> Result<[u8; 32]> {
+    if let Some(encoded) = keychain_lookup()? {
+        return base64_decode(encoded.trim())
+            .ok()
+            .and_then(|v| <[u8; 32]>::try_from(v).ok())
+            .ok_or_else(|| anyhow!("Invalid key in the keychain"));
+    }
+    let key = random_bytes::<32>()?;
+    keychain_store(&base64_encode(key))?;
+    Ok(key)
+}
+
+fn keychain_lookup() -> Result<Option<String>> {
+    let output = match env::consts::OS {
+        "macos" => Command::new("security")
+            .args(["find-generic-password", "-s", KEYCHAIN_SERVICE])
+            .args(["-a", KEYCHAIN_ACCOUNT, "-w"])
+            .output(),
+        "linux" | "freebsd" | "openbsd" => Command::new("secret-tool")
+            .args(["lookup", "service", KEYCHAIN_SERVICE])
+            .args(["account", KEYCHAIN_ACCOUNT])
+            .output(),
+        os => bail!("No keychain support on {os}, use `encrypt_at_rest: passphrase`"),
+    }
+    .context("Failed to query the keychain")?;
+    let value = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    Ok(Some(value).filter(|v| output.status.success() && !v.is_empty()))
+}
+
+fn keychain_store(value: &str) -> Result<()> {
+    let status = match env::consts::OS {
+        // `security` only takes the password as an argument, it is briefly visible to `ps`
+        "macos" => Command::new("security")
+            .args(["add-generic-password", "-U", "-s", KEYCHAIN_SERVICE])
+            .args(["-a", KEYCHAIN_ACCOUNT, "-w", value])
+            .status()?,
+        _ => {
+            let mut child = Command::new("secret-tool")
+                .args(["store", "--label=aichat sessions"])
+                .args(["service", KEYCHAIN_SERVICE, "account", KEYCHAIN_ACCOUNT])
+                .stdin(Stdio::piped())
+                .spawn()
+                .context("Failed to run secret-tool")?;
+            if let Some(mut stdin) = child.stdin.take() {
+                stdin.write_all(value.as_bytes())?;
+            }
+            child.wait()?
+        }
+    };
+    if !status.success() {
+        bail!("Failed to store the key in the keychain");
+    }
+    Ok(())
+}
+
+fn random_bytes<const N: usize>() -> Result<[u8; N]> {
+    let mut bytes = [0u8; N];
+    SystemRandom::new()
+        .fill(&mut bytes)
+        .map_err(|_| anyhow!("Failed to generate random bytes"))?;
+    Ok(bytes)
+}
+
+fn collect_yaml_files(dir: &Path, files: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for path in entries.flatten().map(|v| v.path()) {
+        if path.is_dir() {
+            collect_yaml_files(&path, files);
+        } else if path.extension().is_some_and(|v| v == "yaml") {
+            files.push(path);
+        }
+    }
+}
+
+/// Writes beside `path` and renames over it, a crash never leaves half a file.
+fn replace_file(path: &Path, content: &str) -> Result<()> {
+    let temp = path.with_extension("encrypting");
+    fs::write(&temp, content).with_context(|| format!("Failed to write '{}'", temp.display()))?;
+    fs::rename(&temp, path).with_context(|| format!("Failed to replace '{}'", path.display()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_seal_and_open() {
+        let key = [7u8; 32];
+        let sealed = seal_with(&key, "role: user\ncontent: ssh prod-db").unwrap();
+        assert!(is_sealed(&sealed) && !sealed.contains('\n'));
+        assert!(!sealed.contains("prod-db"));
+        assert_ne!(
+            sealed,
+            seal_with(&key, "role: user\ncontent: ssh prod-db").unwrap()
+        );
+        assert_eq!(
+            open_with(&key, &sealed).unwrap(),
+            "role: user\ncontent: ssh prod-db"
+        );
+        assert!(open_with(&[8u8; 32], &sealed).is_err());
+        assert!(open_with(&key, &format!("{SEALED_PREFIX}AAAA")).is_err());
+        assert_eq!(unseal("model: x").unwrap(), "model: x");
+    }
+}
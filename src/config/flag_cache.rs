@@ -0,0 +1,109 @@
+use super::{ensure_parent_exists, Config};
+use crate::utils::{
+    check_tool_flags, detect_toolchain, parse_help_flags, FlagIssue, ShellKind, ToolInfo,
+};
+
+use anyhow::{Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+const FLAG_CACHE_FILE_NAME: &str = "flag_cache.yaml";
+
+/// The long options of the installed tools, read from their `--help` and man page once per
+/// version and kept in the config dir.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FlagCache {
+    #[serde(default)]
+    pub tools: BTreeMap<String, CachedFlags>,
+}
+
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct CachedFlags {
+    pub version: String,
+    pub flags: BTreeSet<String>,
+}
+
+impl FlagCache {
+    pub fn path() -> PathBuf {
+        Config::local_path(FLAG_CACHE_FILE_NAME)
+    }
+
+    /// A cache that fails to load is rebuilt.
+    pub fn load() -> Self {
+        fs::read_to_string(Self::path())
+            .ok()
+            .and_then(|v| serde_yaml::from_str(&v).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        ensure_parent_exists(&path)?;
+        fs::write(&path, serde_yaml::to_string(self)?)
+            .with_context(|| format!("Failed to write '{}'", path.display()))
+    }
+
+    /// The long options of `tool`, read again when its version changed. Returns whether the
+    /// cache changed.
+    pub fn flags(&mut self, tool: &ToolInfo) -> (BTreeSet<String>, bool) {
+        if let Some(cached) = self.tools.get(&tool.name) {
+            if cached.version == tool.version {
+                return (cached.flags.clone(), false);
+            }
+        }
+        let mut flags = parse_help_flags(&run_text(&tool.name, &["--help"]));
+        flags.extend(parse_help_flags(&run_text("man", &[&tool.name])));
+        self.tools.insert(
+            tool.name.clone(),
+            CachedFlags {
+                version: tool.version.clone(),
+                flags: flags.clone(),
+            },
+        );
+        (flags, true)
+    }
+}
+
+/// Flags of `command` the installed tools don't have, see [`check_tool_flags`].
+pub fn flag_issues(command: &str, shell: &ShellKind) -> Vec<FlagIssue> {
+    let toolchain = detect_toolchain();
+    if toolchain.is_empty() {
+        return vec![];
+    }
+    let mut cache = FlagCache::load();
+    let mut changed = false;
+    let issues = check_tool_flags(command, shell, toolchain, &mut |tool| {
+        let (flags, refreshed) = cache.flags(tool);
+        changed |= refreshed;
+        Some(flags)
+    });
+    if changed {
+        if let Err(err) = cache.save() {
+            warn!("Failed to save the flag cache: {err}");
+        }
+    }
+    issues
+}
+
+/// Stdout and stderr of a help command, BSD tools print their usage to stderr.
+fn run_text(program: &str, args: &[&str]) -> String {
+    let output = Command::new(program)
+        .args(args)
+        .env("MANPAGER", "cat")
+        .env("PAGER", "cat")
+        .stdin(Stdio::null())
+        .output();
+    match output {
+        Ok(output) => format!(
+            "{}{}",
+            String::from_utf8_lossy(&output.stdout),
+            String::from_utf8_lossy(&output.stderr)
+        ),
+        Err(_) => String::new(),
+    }
+}
@@ -0,0 +1,171 @@
+use crate::utils::resolve_home_dir;
+
+use anyhow::{Context, Result};
+use chrono::{Datelike, NaiveDate, NaiveDateTime, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use std::fs;
+
+/// A period during which Dangerous/Critical commands need a typed justification,
+/// configured under `freeze_windows`:
+///
+/// ```yaml
+/// freeze_windows:
+///   - name: friday-afternoon
+///     weekdays: [fri]
+///     from: "16:00"
+///   - name: release-freeze
+///     dates: ["2026-12-20..2027-01-03"]
+///     calendar_file: ~/.config/aichat/freeze-dates.txt
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct FreezeWindow {
+    pub name: String,
+    /// `mon`..`sun`; empty with no dates means every day.
+    #[serde(default)]
+    pub weekdays: Vec<String>,
+    /// `YYYY-MM-DD` or `YYYY-MM-DD..YYYY-MM-DD`.
+    #[serde(default)]
+    pub dates: Vec<String>,
+    /// A file with one date or date range per line, `#` starts a comment.
+    #[serde(default)]
+    pub calendar_file: Option<String>,
+    /// `HH:MM`, defaults to the start of the day.
+    #[serde(default)]
+    pub from: Option<String>,
+    /// `HH:MM` (exclusive), defaults to the end of the day.
+    #[serde(default)]
+    pub until: Option<String>,
+}
+
+impl FreezeWindow {
+    pub fn is_active(&self, now: NaiveDateTime) -> Result<bool> {
+        let date = now.date();
+        let mut dates = self.dates.clone();
+        if let Some(file) = &self.calendar_file {
+            let path = resolve_home_dir(file);
+            let content = fs::read_to_string(&path)
+                .with_context(|| format!("Failed to read freeze calendar '{path}'"))?;
+            dates.extend(
+                content
+                    .lines()
+                    .map(|v| v.split('#').next().unwrap_or_default().trim().to_string())
+                    .filter(|v| !v.is_empty()),
+            );
+        }
+
+        let weekdays = self
+            .weekdays
+            .iter()
+            .map(|v| {
+                v.parse::<Weekday>()
+                    .map_err(|_| anyhow::anyhow!("Invalid weekday '{v}' in '{}'", self.name))
+            })
+            .collect::<Result<Vec<_>>>()?;
+        let day_matches = if weekdays.is_empty() && dates.is_empty() {
+            true
+        } else {
+            let in_dates = dates
+                .iter()
+                .map(|v| date_in_range(v, date))
+                .collect::<Result<Vec<_>>>()?
+                .into_iter()
+                .any(|v| v);
+            weekdays.contains(&date.weekday()) || in_dates
+        };
+        if !day_matches {
+            return Ok(false);
+        }
+
+        let time = now.time();
+        let from = parse_time(self.from.as_deref())?.unwrap_or(NaiveTime::MIN);
+        let in_window = match parse_time(self.until.as_deref())? {
+            Some(until) => time >= from && time < until,
+            None => time >= from,
+        };
+        Ok(in_window)
+    }
+}
+
+fn parse_time(value: Option<&str>) -> Result<Option<NaiveTime>> {
+    value
+        .map(|v| {
+            NaiveTime::parse_from_str(v, "%H:%M").with_context(|| format!("Invalid time '{v}'"))
+        })
+        .transpose()
+}
+
+fn date_in_range(value: &str, date: NaiveDate) -> Result<bool> {
+    let parse = |v: &str| {
+        NaiveDate::parse_from_str(v.trim(), "%Y-%m-%d")
+            .with_context(|| format!("Invalid date '{v}'"))
+    };
+    match value.split_once("..") {
+        Some((start, end)) => Ok(parse(start)? <= date && date <= parse(end)?),
+        None => Ok(parse(value)? == date),
+    }
+}
+
+/// The name of the first window active at `now`.
+pub fn active_freeze_window(windows: &[FreezeWindow], now: NaiveDateTime) -> Result<Option<&str>> {
+    for window in windows {
+        if window.is_active(now)? {
+            return Ok(Some(&window.name));
+        }
+    }
+    Ok(None)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(value: &str) -> NaiveDateTime {
+        NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M").unwrap()
+    }
+
+    #[test]
+    fn test_weekday_window() {
+        let window = FreezeWindow {
+            name: "friday".into(),
+            weekdays: vec!["fri".into()],
+            from: Some("16:00".into()),
+            ..Default::default()
+        };
+        // 2026-10-16 is a Friday
+        assert!(!window.is_active(at("2026-10-16 15:59")).unwrap());
+        assert!(window.is_active(at("2026-10-16 16:00")).unwrap());
+        assert!(window.is_active(at("2026-10-16 23:30")).unwrap());
+        assert!(!window.is_active(at("2026-10-17 17:00")).unwrap());
+    }
+
+    #[test]
+    fn test_date_window() {
+        let path = std::env::temp_dir().join(format!("aichat-freeze-{}.txt", std::process::id()));
+        fs::write(
+            &path,
+            "# holidays\n2026-12-24\n2026-12-31..2027-01-01 # new year\n",
+        )
+        .unwrap();
+        let windows = vec![FreezeWindow {
+            name: "holidays".into(),
+            dates: vec!["2026-11-26".into()],
+            calendar_file: Some(path.display().to_string()),
+            until: Some("18:00".into()),
+            ..Default::default()
+        }];
+        let active = |v| active_freeze_window(&windows, at(v)).unwrap();
+        assert_eq!(active("2026-11-26 09:00"), Some("holidays"));
+        assert_eq!(active("2026-12-24 09:00"), Some("holidays"));
+        assert_eq!(active("2027-01-01 12:00"), Some("holidays"));
+        assert_eq!(active("2026-12-24 18:00"), None);
+        assert_eq!(active("2026-12-25 09:00"), None);
+        let _ = fs::remove_file(&path);
+
+        let invalid = FreezeWindow {
+            name: "bad".into(),
+            weekdays: vec!["someday".into()],
+            ..Default::default()
+        };
+        assert!(invalid.is_active(at("2026-10-16 12:00")).is_err());
+    }
+}
@@ -0,0 +1,570 @@
+use super::*;
+
+use crate::hooks::HookVerdict;
+
+use anyhow::{anyhow, Result};
+use inquire::{Confirm, Text};
+
+/// What [`check_execution`] decided about a command.
+#[derive(Debug, PartialEq)]
+pub enum Guard {
+    /// Run it, with the justification typed during a change freeze.
+    Run(Option<String>),
+    /// The command was revised or a dry run went first, check it again.
+    Again,
+    /// Refused or declined, already reported and in the audit log.
+    Stop,
+}
+
+/// The prompts already answered for a command and its revisions, so each is offered once.
+#[derive(Debug, Default)]
+pub struct GuardState {
+    /// Trusted commands skip the typo fix and acknowledging warnings.
+    pub trusted: bool,
+    picked: bool,
+    confirmed: bool,
+    dry_run_done: bool,
+    script_reviewed: bool,
+    safer_offered: bool,
+    flags_checked: bool,
+}
+
+impl GuardState {
+    /// For a command picked from a menu that runs on Enter: only risky commands are
+    /// confirmed again, where other commands are confirmed once with their consequence.
+    pub fn picked() -> Self {
+        Self {
+            picked: true,
+            ..Default::default()
+        }
+    }
+}
+
+/// Why a command must not run, see [`execution_refusal`].
+#[derive(Debug, Clone, PartialEq)]
+pub struct Refusal {
+    pub reason: String,
+    /// The canary path the command touches.
+    pub canary: Option<String>,
+}
+
+impl Refusal {
+    /// Tell the `canary_webhook` that a canary tripped, if one did.
+    pub async fn notify(&self, config: &GlobalConfig, command: &str) {
+        let webhook = config.read().canary_webhook.clone();
+        if let (Some(url), Some(canary)) = (webhook, &self.canary) {
+            if let Err(err) = fire_canary_webhook(&url, command, canary).await {
+                warn!("Failed to notify canary webhook: {err}");
+            }
+        }
+    }
+}
+
+/// Why `command` must not run, from the checks that need no answer: the command lists,
+/// the `on_before_execute` hook, canary paths, the remote target, the policy, read-only
+/// mode, the risk score and the network policy. The execute flow, suggested commands,
+/// recipes, replays, `.undo` and tool calls all stop here; the refusal is printed and
+/// recorded in the audit log.
+pub fn execution_refusal(
+    config: &GlobalConfig,
+    shell: &Shell,
+    command: &str,
+    analysis: &CommandAnalysis,
+) -> Result<Option<Refusal>> {
+    let Some(refusal) = find_refusal(config, shell, command, analysis)? else {
+        return Ok(None);
+    };
+    eprintln!(
+        "{}",
+        warning_text(&format!("⚠ Execution blocked: {}", refusal.reason))
+    );
+    audit(
+        config,
+        AuditEntry::new(command, AuditDecision::Blocked)
+            .with_safety_level(analysis.safety_level.clone())
+            .with_reason(&refusal.reason),
+    );
+    Ok(Some(refusal))
+}
+
+fn find_refusal(
+    config: &GlobalConfig,
+    shell: &Shell,
+    command: &str,
+    analysis: &CommandAnalysis,
+) -> Result<Option<Refusal>> {
+    let refusal = |reason: String| -> Result<Option<Refusal>> {
+        Ok(Some(Refusal {
+            reason,
+            canary: None,
+        }))
+    };
+    let command_lists = config.read().command_lists();
+    if let Some(reason) = command_lists.check(command, &ShellKind::from_name(&shell.name)) {
+        return refusal(reason);
+    }
+    let hooks = config.read().hooks.clone();
+    if let Some(hooks) = &hooks {
+        if let HookVerdict::Block(reason) = hooks.on_before_execute(command)? {
+            return refusal(reason);
+        }
+    }
+    let remote = config.read().remote.clone();
+    match &remote {
+        Some(remote) => {
+            if let Err(err) = remote.target.check(analysis) {
+                return refusal(err.to_string());
+            }
+        }
+        None => {
+            let canaries = config.read().canary_paths.clone();
+            let covers_contents = analysis.operation.is_destructive()
+                || analysis.operation.needs_backup()
+                || !analysis.permission_changes.is_empty();
+            if let Some(canary) =
+                find_canary(command, &canaries, covers_contents, PathStyle::current())
+            {
+                return Ok(Some(Refusal {
+                    reason: format!("the command touches canary path '{canary}'"),
+                    canary: Some(canary),
+                }));
+            }
+        }
+    }
+    let read_only = config.read().read_only_refusal("this one", Some(analysis));
+    if let Some(reason) = analysis.blocked_by_policy.clone().or(read_only) {
+        return refusal(reason);
+    }
+    let risk_block_score = config.read().risk_block_score;
+    if let Some(reason) = analysis.risk.blocked(risk_block_score) {
+        return refusal(reason);
+    }
+    let network_policy = config.read().network_policy();
+    if let Some(reason) = analysis.blocked_destination(&network_policy) {
+        return refusal(reason);
+    }
+    Ok(None)
+}
+
+/// Check `command` before it runs, refusing what [`execution_refusal`] refuses, then ask
+/// what the checks leave to the user: placeholders, typos, risky and dangerous commands,
+/// resource exhaustion, low disk space, downloaded scripts, safer git and flag
+/// alternatives, production kube contexts, change freezes and dry runs. Revisions replace
+/// `command` and come back as [`Guard::Again`], to be analyzed and checked once more.
+pub async fn check_execution(
+    config: &GlobalConfig,
+    shell: &Shell,
+    command: &mut String,
+    analysis: &CommandAnalysis,
+    state: &mut GuardState,
+) -> Result<Guard> {
+    let shell_kind = ShellKind::from_name(&shell.name);
+    let remote = config.read().remote.clone();
+
+    // The literal `<your-bucket>` would run otherwise
+    if !analysis.placeholders.is_empty() {
+        return match fill_placeholders_interactively(command, &analysis.placeholders)? {
+            Some(filled) => {
+                *command = filled;
+                Ok(Guard::Again)
+            }
+            None => deny(config, command, analysis, "placeholders left unfilled"),
+        };
+    }
+
+    if !state.trusted {
+        if let Some(fix) = confirm_typo_fix(analysis)? {
+            *command = fix;
+            return Ok(Guard::Again);
+        }
+    }
+
+    if let Some(refusal) = execution_refusal(config, shell, command, analysis)? {
+        refusal.notify(config, command).await;
+        return Ok(Guard::Stop);
+    }
+
+    let risk_confirm_score = config.read().risk_confirm_score;
+    let risk = analysis.risk.needs_confirmation(risk_confirm_score);
+    if state.picked {
+        // The menu runs on Enter, so risky untrusted commands are confirmed again
+        if let Some(reason) = risk {
+            eprintln!("{}", warning_text(&format!("⚠ The {reason}")));
+            let ans = Confirm::new("Execute it?").with_default(false).prompt()?;
+            if !ans {
+                return deny(config, command, analysis, &reason);
+            }
+        }
+    } else if !state.confirmed {
+        let message = match analysis.consequence() {
+            Some(consequence) => format!("{consequence} Execute it?"),
+            None => "Execute it?".to_string(),
+        };
+        let ans = Confirm::new(&message)
+            .with_default(analysis.safety_level < SafetyLevel::Dangerous && risk.is_none())
+            .prompt()?;
+        if !ans {
+            audit(config, AuditEntry::new(command, AuditDecision::Denied));
+            return Ok(Guard::Stop);
+        }
+        state.confirmed = true;
+    }
+
+    let acknowledge = config.read().acknowledge_warnings
+        && !state.trusted
+        && analysis.safety_level >= SafetyLevel::Dangerous;
+    if acknowledge {
+        if let Some(reason) = acknowledge_warnings(analysis, &shell_kind)? {
+            eprintln!("{}", warning_text("⚠ Execution cancelled."));
+            return deny(config, command, analysis, &reason);
+        }
+    }
+
+    if let Some(exhaustion) = analysis.resource_exhaustion.first() {
+        eprintln!("{}", warning_text(&exhaustion.warning()));
+        let answer = Text::new(&format!(
+            "Refusing to run {}, type 'override' to run it anyway:",
+            exhaustion.summary()
+        ))
+        .prompt()?;
+        if answer.trim() != "override" {
+            let reason = format!("{} exhausts the machine", exhaustion.summary());
+            return block(config, command, analysis, &reason);
+        }
+    }
+
+    let threshold = config.read().disk_space_threshold;
+    let space = match remote.is_none() {
+        true => disk_space_preflight(command, threshold).await,
+        false => None,
+    };
+    if let Some(space) = space {
+        let reason = format!("the command {}", space.message());
+        if space.exceeds {
+            return block(config, command, analysis, &reason);
+        }
+        eprintln!("{}", warning_text(&format!("⚠ Low disk space: {reason}")));
+        let ans = Confirm::new("Run it anyway?")
+            .with_default(false)
+            .prompt()?;
+        if !ans {
+            return deny(config, command, analysis, &reason);
+        }
+    }
+    if remote.is_none() {
+        if let Some(warning) = download_duration_warning(analysis).await {
+            println!("{}", dimmed_text(&warning));
+        }
+    }
+
+    if let Some(pipe) = &analysis.pipe_to_shell {
+        eprintln!("{}", warning_text(&pipe.warning()));
+        if !state.script_reviewed && pipe.url.is_some() {
+            state.script_reviewed = true;
+            let ans = Confirm::new("Download the script to a temp file and review it first?")
+                .with_default(true)
+                .prompt()?;
+            if ans {
+                match download_script(pipe).await {
+                    Ok(path) => {
+                        let content = std::fs::read_to_string(&path)?;
+                        println!("{}", dimmed_text(&format!("Saved to {}", path.display())));
+                        println!("{content}");
+                        let shell_kind = match pipe.is_powershell() {
+                            true => ShellKind::PowerShell,
+                            false => ShellKind::Bash,
+                        };
+                        let script_analysis =
+                            CommandAnalysis::analyze_script(&content, &shell_kind);
+                        println!("{}", script_analysis.display());
+                        let reviewed = pipe.reviewed_command(&path);
+                        let ans = Confirm::new(&format!(
+                            "Use '{reviewed}' to run the reviewed copy instead?"
+                        ))
+                        .with_default(true)
+                        .prompt()?;
+                        if ans {
+                            *command = reviewed;
+                        }
+                    }
+                    Err(err) => {
+                        eprintln!("{}", warning_text(&format!("⚠ Download failed: {err}")));
+                    }
+                }
+                return Ok(Guard::Again);
+            }
+        }
+        let ans = Confirm::new("Run the downloaded script without reviewing it?")
+            .with_default(false)
+            .prompt()?;
+        if !ans {
+            return deny(
+                config,
+                command,
+                analysis,
+                "declined running an unreviewed download",
+            );
+        }
+    }
+
+    if let (Some(git), false) = (
+        analysis.git_commands.iter().find(|v| v.safer.is_some()),
+        state.safer_offered,
+    ) {
+        state.safer_offered = true;
+        let safer = git.safer.clone().unwrap_or_default();
+        let ans = Confirm::new(&format!("Use '{safer}' instead?"))
+            .with_default(true)
+            .prompt()?;
+        if ans {
+            *command = command.replacen(&git.command, &safer, 1);
+            return Ok(Guard::Again);
+        }
+    }
+
+    if !state.flags_checked && remote.is_none() {
+        state.flags_checked = true;
+        let issues = flag_issues(command, &shell_kind);
+        for issue in &issues {
+            eprintln!("{}", warning_text(&issue.warning()));
+        }
+        if let Some(fixed) = apply_flag_fixes(command, &issues) {
+            let ans = Confirm::new(&format!("Use '{fixed}' instead?"))
+                .with_default(true)
+                .prompt()?;
+            if ans {
+                *command = fixed;
+                return Ok(Guard::Again);
+            }
+        }
+    }
+
+    let kube_pattern = config.read().kube_production_pattern();
+    if let Some(kube) = analysis
+        .kube_commands
+        .iter()
+        .find(|v| v.destructive.is_some() && v.is_production(&kube_pattern))
+    {
+        let context = kube.context.clone().unwrap_or_default();
+        let answer = Text::new(&format!(
+            "This will {} on production context {}, type '{context}' to confirm:",
+            kube.destructive.as_deref().unwrap_or_default(),
+            kube.target()
+        ))
+        .prompt()?;
+        if answer.trim() != context {
+            eprintln!(
+                "{}",
+                warning_text("⚠ Confirmation did not match, execution cancelled.")
+            );
+            return deny(
+                config,
+                command,
+                analysis,
+                "production context confirmation did not match",
+            );
+        }
+    }
+
+    let mut justification = None;
+    let freeze = config.read().active_freeze_window()?;
+    if let Some(freeze) = freeze.filter(|_| analysis.safety_level >= SafetyLevel::Dangerous) {
+        let consequence = analysis.consequence().unwrap_or_default();
+        let answer = Text::new(&format!(
+            "Change freeze '{freeze}' is active. {consequence} Type a justification to run it:"
+        ))
+        .prompt()?;
+        if answer.trim().is_empty() {
+            eprintln!(
+                "{}",
+                warning_text("⚠ No justification given, execution cancelled.")
+            );
+            return deny(
+                config,
+                command,
+                analysis,
+                &format!("change freeze '{freeze}'"),
+            );
+        }
+        justification = Some(format!("change freeze '{freeze}': {}", answer.trim()));
+    }
+
+    if let (Some(dry_run), false) = (analysis.dry_run_command(), state.dry_run_done) {
+        state.dry_run_done = true;
+        let ans = Confirm::new(&format!("Run '{dry_run}' first?"))
+            .with_default(true)
+            .prompt()?;
+        if ans {
+            let code = match &remote {
+                Some(remote) => remote.target.run(&dry_run)?,
+                None => run_command(&shell.cmd, &[&shell.arg, &dry_run], None)?,
+            };
+            println!("{}", dimmed_text(&format!("Dry run exited with {code}.")));
+            return Ok(Guard::Again);
+        }
+    }
+
+    Ok(Guard::Run(justification))
+}
+
+/// What [`prepare_execution`] set up for a command, kept until it finished.
+#[derive(Debug)]
+pub struct Prepared {
+    pub exec_lock: Option<ExecLock>,
+    /// The backup to restore when the command fails.
+    pub backup_id: Option<String>,
+    /// Every backup and snapshot taken, for the audit log.
+    pub backup_ids: Vec<String>,
+}
+
+/// Take the execution lock of the working directory and back up what the command changes:
+/// files and persistent changes like crontabs locally, files on a remote target, and a
+/// file system snapshot before Critical commands with `fs_snapshots`. `None` when the lock
+/// or a failed backup stop the command, already reported and in the audit log.
+pub async fn prepare_execution(
+    config: &GlobalConfig,
+    command: &str,
+    analysis: &CommandAnalysis,
+) -> Result<Option<Prepared>> {
+    let remote = config.read().remote.clone();
+    let exec_lock = match remote.is_none() && analysis.safety_level >= SafetyLevel::Caution {
+        true => {
+            let wait = std::time::Duration::from_secs(config.read().exec_lock_wait);
+            match ExecLock::acquire(&env::current_dir()?, command, wait).await {
+                Ok(lock) => Some(lock),
+                Err(err) => {
+                    eprintln!("{}", warning_text(&format!("⚠ {err}")));
+                    audit(
+                        config,
+                        AuditEntry::new(command, AuditDecision::Blocked)
+                            .with_safety_level(analysis.safety_level.clone())
+                            .with_reason(&err.to_string()),
+                    );
+                    return Ok(None);
+                }
+            }
+        }
+        false => None,
+    };
+
+    let backup_manager = BackupManager::new();
+    let manager = || backup_manager.as_ref().map_err(|err| anyhow!("{err}"));
+    let backup = match &remote {
+        Some(remote) => {
+            let paths = command_path_candidates(command, PathStyle::Unix);
+            let needs_backup = remote.target.policy.backup
+                && analysis.operation.needs_backup()
+                && !paths.is_empty();
+            needs_backup.then(|| manager()?.create_remote_backup(command, &remote.target, paths))
+        }
+        None => {
+            let file_paths = analysis.required_backups();
+            let persistent = !detect_persistent_changes(command).is_empty();
+            (!file_paths.is_empty() || persistent)
+                .then(|| manager()?.create_backup(command, file_paths))
+        }
+    };
+
+    let mut backup_ids = vec![];
+    let fs_snapshots = config.read().fs_snapshots;
+    if let (None, SafetyLevel::Critical, true, Ok(backup_manager)) = (
+        &remote,
+        &analysis.safety_level,
+        fs_snapshots,
+        &backup_manager,
+    ) {
+        let cwd = env::current_dir()?;
+        match backup_manager.create_snapshot_backup(command, &cwd) {
+            Ok(Some(entry)) => {
+                let name = entry.snapshot.map(|v| v.name).unwrap_or_default();
+                let message = format!("✓ Snapshot created: {name} ({})", entry.id);
+                println!("{}", dimmed_text(&message));
+                backup_ids.push(entry.id);
+            }
+            Ok(None) => {}
+            Err(e) => {
+                eprintln!("{}", dimmed_text(&format!("⚠ Snapshot failed: {e}")));
+            }
+        }
+    }
+
+    let mut backup_id = None;
+    if let Some(backup) = backup {
+        match backup {
+            Ok(backup) => {
+                if let Some(fallback) = backup_manager.as_ref().ok().and_then(|v| v.fallback()) {
+                    eprintln!("{}", warning_text(fallback));
+                }
+                println!(
+                    "{}",
+                    dimmed_text(&format!("✓ Backup created: {}", backup.id))
+                );
+                backup_ids.push(backup.id.clone());
+                backup_id = Some(backup.id);
+            }
+            Err(e) => {
+                eprintln!("{}", warning_text(&format!("⚠ Backup failed: {e}")));
+                let proceed = match config.read().backup_failure {
+                    BackupFailure::Proceed => true,
+                    BackupFailure::Block => false,
+                    BackupFailure::Ask => Confirm::new("Run it without a backup?")
+                        .with_default(false)
+                        .prompt()?,
+                };
+                if !proceed {
+                    println!("{}", dimmed_text("Execution cancelled."));
+                    deny(config, command, analysis, &format!("backup failed: {e}"))?;
+                    return Ok(None);
+                }
+            }
+        }
+    }
+
+    Ok(Some(Prepared {
+        exec_lock,
+        backup_id,
+        backup_ids,
+    }))
+}
+
+/// Record `entry` in the audit log, with the remote host it runs on.
+pub fn audit(config: &GlobalConfig, entry: AuditEntry) {
+    let remote = config.read().remote.as_ref().map(|v| v.target.name.clone());
+    if let Err(err) = AuditLog::append(&entry.with_remote(remote)) {
+        warn!("Failed to write the audit log: {err}");
+    }
+}
+
+fn block(
+    config: &GlobalConfig,
+    command: &str,
+    analysis: &CommandAnalysis,
+    reason: &str,
+) -> Result<Guard> {
+    eprintln!(
+        "{}",
+        warning_text(&format!("⚠ Execution blocked: {reason}"))
+    );
+    audit(
+        config,
+        AuditEntry::new(command, AuditDecision::Blocked)
+            .with_safety_level(analysis.safety_level.clone())
+            .with_reason(reason),
+    );
+    Ok(Guard::Stop)
+}
+
+fn deny(
+    config: &GlobalConfig,
+    command: &str,
+    analysis: &CommandAnalysis,
+    reason: &str,
+) -> Result<Guard> {
+    audit(
+        config,
+        AuditEntry::new(command, AuditDecision::Denied)
+            .with_safety_level(analysis.safety_level.clone())
+            .with_reason(reason),
+    );
+    Ok(Guard::Stop)
+}
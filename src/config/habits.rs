@@ -0,0 +1,419 @@
+use crate::utils::{
+    CommandAnalysis, CommandOperation, MemoryFs, PathStyle, RiskScore, SafetyLevel, ShellKind,
+};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Local, TimeZone};
+use std::{env, fmt::Write, fs, path::PathBuf};
+
+/// Habits listed by `--habits`, riskiest first.
+const MAX_HABITS: usize = 10;
+/// Occurrences that make a pattern a habit.
+const MIN_OCCURRENCES: usize = 2;
+
+/// A command of a shell history file, with its time when the format records one.
+#[derive(Debug, Clone, PartialEq)]
+pub struct HistoryEntry {
+    pub command: String,
+    pub time: Option<DateTime<Local>>,
+}
+
+/// A risky pattern that recurs in the shell history.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Habit {
+    /// The program and what makes it risky, e.g. "`rm` deletes files, recursive, wildcards".
+    pub pattern: String,
+    pub count: usize,
+    /// Occurrences per 30 days, when the history records times.
+    pub per_month: Option<f64>,
+    /// The highest risk score of the occurrences.
+    pub risk: u8,
+    pub example: String,
+    pub alternative: Option<String>,
+}
+
+/// `--habits`: the riskiest recurring patterns of the shell history. Every command is
+/// analyzed offline against an empty filesystem, so nothing on disk is walked and nothing
+/// leaves the machine.
+#[derive(Debug, Default)]
+pub struct HabitReport {
+    pub sources: Vec<PathBuf>,
+    pub commands: usize,
+    pub habits: Vec<Habit>,
+}
+
+impl HabitReport {
+    /// Read `$HISTFILE` and the bash, zsh, fish and PowerShell histories that exist.
+    pub fn collect() -> Result<Self> {
+        let mut sources = vec![];
+        let mut entries = vec![];
+        for (path, shell) in history_files() {
+            let Ok(content) = fs::read(&path) else {
+                continue;
+            };
+            let content = String::from_utf8_lossy(&content);
+            entries.extend(
+                parse_history(&content)
+                    .into_iter()
+                    .map(|v| (v, shell.clone())),
+            );
+            sources.push(path);
+        }
+        if sources.is_empty() {
+            bail!("No shell history found, point HISTFILE at it");
+        }
+        Ok(Self::analyze(sources, &entries))
+    }
+
+    pub fn analyze(sources: Vec<PathBuf>, entries: &[(HistoryEntry, ShellKind)]) -> Self {
+        let fs = MemoryFs::new();
+        let times: Vec<_> = entries.iter().filter_map(|(v, _)| v.time).collect();
+        let days = match (times.iter().min(), times.iter().max()) {
+            (Some(first), Some(last)) => {
+                Some(((*last - *first).num_hours() as f64 / 24.0).max(1.0))
+            }
+            _ => None,
+        };
+        let mut habits: Vec<(Habit, usize)> = vec![];
+        for (entry, shell) in entries {
+            let style = match shell {
+                ShellKind::PowerShell | ShellKind::Cmd => PathStyle::Windows,
+                _ => PathStyle::Unix,
+            };
+            let analysis = CommandAnalysis::analyze_with(&entry.command, style, shell, &fs);
+            if analysis.risk.score < RiskScore::floor(&SafetyLevel::Dangerous) {
+                continue;
+            }
+            let pattern = pattern(&analysis);
+            let timed = entry.time.is_some() as usize;
+            match habits.iter_mut().find(|(v, _)| v.pattern == pattern) {
+                Some((habit, timed_count)) => {
+                    habit.count += 1;
+                    habit.risk = habit.risk.max(analysis.risk.score);
+                    *timed_count += timed;
+                }
+                None => habits.push((
+                    Habit {
+                        pattern,
+                        count: 1,
+                        per_month: None,
+                        risk: analysis.risk.score,
+                        example: entry.command.trim().to_string(),
+                        alternative: alternative(&analysis),
+                    },
+                    timed,
+                )),
+            }
+        }
+        let mut habits: Vec<Habit> = habits
+            .into_iter()
+            .filter(|(v, _)| v.count >= MIN_OCCURRENCES)
+            .map(|(mut habit, timed)| {
+                habit.per_month = days.filter(|_| timed > 0).map(|v| timed as f64 * 30.0 / v);
+                habit
+            })
+            .collect();
+        habits.sort_by_key(|v| std::cmp::Reverse(v.count * v.risk as usize));
+        habits.truncate(MAX_HABITS);
+        Self {
+            sources,
+            commands: entries.len(),
+            habits,
+        }
+    }
+
+    fn summary(&self) -> String {
+        let sources = self
+            .sources
+            .iter()
+            .map(|v| v.display().to_string())
+            .collect::<Vec<_>>()
+            .join(", ");
+        format!(
+            "Analyzed {} commands from {sources}, {} risky habits",
+            self.commands,
+            self.habits.len()
+        )
+    }
+
+    /// The habits for the terminal.
+    pub fn table(&self) -> String {
+        let mut output = format!("{}\n", self.summary());
+        for (i, habit) in self.habits.iter().enumerate() {
+            let _ = write!(
+                output,
+                "\n{:>2}. {}\n    {}, risk {}/100, e.g. {}\n",
+                i + 1,
+                habit.pattern,
+                frequency(habit),
+                habit.risk,
+                habit.example.lines().next().unwrap_or_default()
+            );
+            if let Some(alternative) = &habit.alternative {
+                let _ = writeln!(output, "    Consider: {alternative}");
+            }
+        }
+        output
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("# Shell Habits\n\n{}.\n", self.summary());
+        if self.habits.is_empty() {
+            return output;
+        }
+        output.push_str("\n| Pattern | Frequency | Risk | Example | Safer alternative |\n");
+        output.push_str("| --- | --- | --- | --- | --- |\n");
+        let cell = |v: &str| v.replace('|', "\\|").replace('\n', " ");
+        for habit in &self.habits {
+            let _ = writeln!(
+                output,
+                "| {} | {} | {}/100 | `{}` | {} |",
+                cell(&habit.pattern),
+                frequency(habit),
+                habit.risk,
+                cell(&habit.example),
+                cell(habit.alternative.as_deref().unwrap_or("-"))
+            );
+        }
+        output
+    }
+}
+
+/// Commands of a bash (`#<epoch>` lines), zsh (extended `: <epoch>:0;` lines), fish
+/// (`- cmd:`/`when:`) or plain history file.
+pub fn parse_history(content: &str) -> Vec<HistoryEntry> {
+    let mut entries = vec![];
+    let mut time = None;
+    let mut lines = content.lines().peekable();
+    while let Some(line) = lines.next() {
+        if let Some(command) = line.strip_prefix("- cmd: ") {
+            let mut entry = HistoryEntry {
+                command: command.replace("\\n", "\n").replace("\\\\", "\\"),
+                time: None,
+            };
+            while let Some(next) = lines.next_if(|v| v.starts_with("  ")) {
+                if let Some(when) = next.trim_start().strip_prefix("when: ") {
+                    entry.time = epoch(when);
+                }
+            }
+            entries.push(entry);
+            continue;
+        }
+        if let Some(stamp) = line.strip_prefix('#').and_then(epoch) {
+            time = Some(stamp);
+            continue;
+        }
+        let (command, stamp) = match line.strip_prefix(": ").and_then(|v| v.split_once(';')) {
+            Some((meta, command)) => (command, meta.split(':').next().and_then(epoch)),
+            None => (line, time.take()),
+        };
+        // Multi-line commands continue after a trailing backslash
+        let mut command = command.to_string();
+        while command.ends_with('\\') {
+            let Some(next) = lines.next() else {
+                break;
+            };
+            command.pop();
+            command.push('\n');
+            command.push_str(next);
+        }
+        if !command.trim().is_empty() {
+            entries.push(HistoryEntry {
+                command,
+                time: stamp,
+            });
+        }
+    }
+    entries
+}
+
+fn epoch(value: &str) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    if value.is_empty() || !value.chars().all(|c| c.is_ascii_digit()) {
+        return None;
+    }
+    Local.timestamp_opt(value.parse().ok()?, 0).single()
+}
+
+fn history_files() -> Vec<(PathBuf, ShellKind)> {
+    let mut paths = vec![];
+    if let Ok(path) = env::var("HISTFILE") {
+        paths.push(PathBuf::from(path));
+    }
+    if let Some(home) = dirs::home_dir() {
+        paths.push(home.join(".zsh_history"));
+        paths.push(home.join(".bash_history"));
+    }
+    if let Some(data) = dirs::data_dir() {
+        paths.push(data.join("fish").join("fish_history"));
+        paths.push(
+            data.join("Microsoft")
+                .join("Windows")
+                .join("PowerShell")
+                .join("PSReadLine")
+                .join("ConsoleHost_history.txt"),
+        );
+    }
+    let mut files: Vec<(PathBuf, ShellKind)> = vec![];
+    for path in paths {
+        if !path.is_file() || files.iter().any(|(v, _)| *v == path) {
+            continue;
+        }
+        let name = path.to_string_lossy().to_lowercase();
+        let shell = if name.contains("fish") {
+            ShellKind::Fish
+        } else if name.contains("zsh") {
+            ShellKind::Zsh
+        } else if name.contains("powershell") {
+            ShellKind::PowerShell
+        } else {
+            ShellKind::Bash
+        };
+        files.push((path, shell));
+    }
+    files
+}
+
+/// The program and the risk factors of a command, which group its occurrences.
+fn pattern(analysis: &CommandAnalysis) -> String {
+    let program = analysis
+        .command
+        .split_whitespace()
+        .find(|v| !matches!(*v, "sudo" | "doas") && !v.contains('='))
+        .unwrap_or_default();
+    let mut reasons: Vec<&str> = analysis
+        .risk
+        .factors
+        .iter()
+        .map(|v| v.reason.as_str())
+        .filter(|v| !v.starts_with("rated ") && !v.starts_with("wildcards"))
+        .collect();
+    if has_wildcards(analysis) {
+        reasons.push("wildcards");
+    }
+    format!("`{program}` {}", reasons.join(", "))
+}
+
+/// The history is analyzed without the files, so wildcards are spotted in the words.
+fn has_wildcards(analysis: &CommandAnalysis) -> bool {
+    analysis.operation.is_destructive()
+        && analysis
+            .command
+            .split_whitespace()
+            .skip(1)
+            .any(|v| !v.starts_with(['\'', '"']) && v.contains(['*', '?']))
+}
+
+fn alternative(analysis: &CommandAnalysis) -> Option<String> {
+    if let Some(safer) = analysis.git_commands.iter().find_map(|v| v.safer.clone()) {
+        return Some(format!("`{safer}`"));
+    }
+    if analysis.pipe_to_shell.is_some() {
+        return Some("download the script, read it, then run it".to_string());
+    }
+    let deletes = analysis.operation == CommandOperation::Delete;
+    if deletes && has_wildcards(analysis) {
+        return Some(
+            "list the matches with `ls` first, or delete with trash-cli (`trash-put`)".to_string(),
+        );
+    }
+    if deletes {
+        return Some("trash-cli (`trash-put`) keeps deleted files recoverable".to_string());
+    }
+    if !analysis.permission_changes.is_empty() {
+        return Some("grant only the bits needed, e.g. `chmod u+x` instead of `777`".to_string());
+    }
+    if let Some(dry_run) = analysis.dry_run_command() {
+        return Some(format!("preview with `{dry_run}` first"));
+    }
+    None
+}
+
+fn frequency(habit: &Habit) -> String {
+    match habit.per_month {
+        Some(v) => format!("~{:.0}×/month ({} times)", v.max(1.0), habit.count),
+        None => format!("{} times", habit.count),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_history() {
+        let bash = "#1700000000\nls -la\nrm -rf build/*\n#1700086400\necho a \\\nb\n";
+        assert_eq!(
+            parse_history(bash),
+            vec![
+                HistoryEntry {
+                    command: "ls -la".into(),
+                    time: epoch("1700000000"),
+                },
+                HistoryEntry {
+                    command: "rm -rf build/*".into(),
+                    time: None,
+                },
+                HistoryEntry {
+                    command: "echo a \nb".into(),
+                    time: epoch("1700086400"),
+                },
+            ]
+        );
+        let zsh = ": 1700000000:0;git push --force\n";
+        assert_eq!(parse_history(zsh)[0].command, "git push --force");
+        assert_eq!(parse_history(zsh)[0].time, epoch("1700000000"));
+        let fish = "- cmd: rm -rf ~/tmp\n  when: 1700000000\n  paths:\n    - ~/tmp\n- cmd: ls\n";
+        let entries = parse_history(fish);
+        assert_eq!(entries.len(), 2);
+        assert_eq!(entries[0].command, "rm -rf ~/tmp");
+        assert_eq!(entries[0].time, epoch("1700000000"));
+    }
+
+    #[test]
+    fn test_habit_report() {
+        let day = 86_400;
+        let history: String = (0..30)
+            .map(|i| {
+                let command = match i % 3 {
+                    0 => "rm -rf build/*",
+                    1 => "git push --force origin main",
+                    _ => "ls -la",
+                };
+                format!("#{}\n{command}\n", 1_700_000_000 + i * day)
+            })
+            .collect();
+        let entries: Vec<_> = parse_history(&history)
+            .into_iter()
+            .map(|v| (v, ShellKind::Bash))
+            .collect();
+        let report = HabitReport::analyze(vec![PathBuf::from("~/.bash_history")], &entries);
+        assert_eq!(report.commands, 30);
+        assert_eq!(report.habits.len(), 2);
+        let rm = report
+            .habits
+            .iter()
+            .find(|v| v.example == "rm -rf build/*")
+            .unwrap();
+        assert_eq!(rm.count, 10);
+        assert!(rm.pattern.starts_with("`rm` deletes files"));
+        assert!(rm.pattern.ends_with("wildcards"));
+        assert!(rm.alternative.as_deref().unwrap().contains("trash-put"));
+        let per_month = rm.per_month.unwrap();
+        assert!((10.0..=11.0).contains(&per_month), "{per_month}");
+        let push = report
+            .habits
+            .iter()
+            .find(|v| v.example.starts_with("git push"))
+            .unwrap();
+        assert!(push
+            .alternative
+            .as_deref()
+            .unwrap()
+            .contains("--force-with-lease"));
+
+        let markdown = report.to_markdown();
+        assert!(markdown.starts_with("# Shell Habits\n\nAnalyzed 30 commands"));
+        assert!(markdown.contains("| `rm -rf build/*` |"));
+    }
+}
@@ -462,7 +462,7 @@ async fn load_documents(
         files.push(("CMD", cmd, output));
     }
 
-    let local_files = expand_glob_paths(&local_paths, true).await?;
+    let local_files = expand_glob_paths(&local_paths, true)?;
     for file_path in local_files {
         if is_image(&file_path) {
             let contents = read_media_to_data_url(&file_path)
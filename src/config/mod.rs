@@ -1,22 +1,67 @@
 mod agent;
+mod audit;
+mod budget;
+mod compare;
+mod encryption;
+mod flag_cache;
+mod freeze;
+mod guard;
+mod habits;
 mod input;
+mod postmortem;
+mod recipe;
+mod replay;
+mod report;
 mod role;
 mod session;
-pub mod environments;
+mod setup;
+mod stats;
+mod supervisor;
+mod team;
+mod trust;
+mod watch;
 
 pub use self::agent::{complete_agent_variables, list_agents, Agent, AgentVariables};
+pub use self::audit::{
+    entries_between, find_undoable, last_executed, search_entries, search_notes, suggest_rule,
+    AuditDecision, AuditEntry, AuditLog, UNDO_REASON,
+};
+pub use self::budget::{token_cost, BudgetCaps, BudgetUsage};
+pub use self::compare::{compare_models, render_answers, safest_answer};
+pub use self::encryption::{
+    encrypt_existing, seal, set_at_rest_key, unseal, AtRestEncryption, AtRestKey,
+};
+pub use self::flag_cache::flag_issues;
+pub use self::freeze::FreezeWindow;
+pub use self::guard::{
+    audit, check_execution, execution_refusal, prepare_execution, Guard, GuardState,
+};
+pub use self::habits::HabitReport;
 pub use self::input::Input;
+pub use self::postmortem::{Postmortem, PostmortemScope};
+pub use self::recipe::{Recipe, RecipeBook};
+pub use self::replay::{RecordedRun, SessionReplay};
+pub use self::report::SessionReport;
 pub use self::role::{
-    Role, RoleLike, CODE_ROLE, CREATE_TITLE_ROLE, SHELL_ROLE,
+    Role, RoleLike, CODE_ROLE, CREATE_TITLE_ROLE, PATCH_ROLE, PLANNER_ROLE, REVIEWER_ROLE,
+    SHELL_ROLE, SUPERVISOR_ROLE,
 };
-pub use environments::EnvProfile;
 use self::session::Session;
+pub use self::setup::run_setup_wizard;
+pub use self::stats::{Stats, UsageEntry, UsageLog};
+pub use self::supervisor::{interpret_progress, supervise, SupervisedJob};
+pub use self::team::{format_objections, run_team, TeamRoles};
+pub use self::trust::{project_root, TrustScope, TrustStore};
+pub use self::watch::{is_rag_document, reload_config, ConfigWatcher};
+pub use crate::utils::{EnvComparison, EnvProfile, RemoteSession, ShellKind, SshTarget};
 
 use crate::client::{
     create_client_config, list_client_types, list_models, ClientConfig, MessageContentToolCalls,
     Model, ModelType, ProviderModels, OPENAI_COMPATIBLE_PROVIDERS,
 };
 use crate::function::{FunctionDeclaration, Functions, ToolResult};
+use crate::hooks::Hooks;
+use crate::plugin::{PluginCapabilities, Plugins};
 use crate::rag::Rag;
 use crate::render::{MarkdownRender, RenderOptions};
 use crate::repl::{run_repl_command, split_args_text};
@@ -24,12 +69,12 @@ use crate::utils::*;
 
 use anyhow::{anyhow, bail, Context, Result};
 use indexmap::IndexMap;
-use inquire::{list_option::ListOption, validator::Validation, Confirm, MultiSelect, Select, Text};
+use inquire::{list_option::ListOption, validator::Validation, Confirm, MultiSelect, Text};
+use log::LevelFilter;
 use parking_lot::RwLock;
 use serde::{Deserialize, Serialize};
 use serde_json::json;
-use simplelog::LevelFilter;
-use std::collections::{HashMap, HashSet};
+use std::collections::{HashMap, HashSet, VecDeque};
 use std::{
     env,
     fs::{
@@ -62,9 +107,17 @@ const FUNCTIONS_DIR_NAME: &str = "functions";
 const FUNCTIONS_FILE_NAME: &str = "functions.json";
 const FUNCTIONS_BIN_DIR_NAME: &str = "bin";
 const AGENTS_DIR_NAME: &str = "agents";
+const PLUGINS_DIR_NAME: &str = "plugins";
+const HOOKS_FILE_NAME: &str = "hooks.rhai";
+const POLICY_FILE_NAME: &str = "policy.yaml";
+const TUTOR_FILE_NAME: &str = "tutor.yaml";
+const TLDR_DIR_NAME: &str = "tldr";
 
 const CLIENTS_FIELD: &str = "clients";
 
+/// Commands kept for `.cmds`, the oldest are dropped first.
+const SUGGESTED_COMMANDS_LIMIT: usize = 50;
+
 const SERVE_ADDR: &str = "127.0.0.1:8000";
 
 const SYNC_MODELS_URL: &str =
@@ -93,11 +146,14 @@ __CONTEXT__
 __INPUT__
 </user_query>"#;
 
-const LEFT_PROMPT: &str = "{color.green}{?session {?agent {agent}>}{session}{?role /}}{!session {?agent {agent}>}}{role}{?rag @{rag}}{color.cyan}{?session )}{!session >}{color.reset} ";
-const RIGHT_PROMPT: &str = "{color.purple}{?session {?consume_tokens {consume_tokens}({consume_percent}%)}{!consume_tokens {consume_tokens}}}{color.reset}";
+const LEFT_PROMPT: &str = "{color.role}{?session {?agent {agent}>}{session}{?role /}}{!session {?agent {agent}>}}{role}{?rag @{rag}}{color.prompt}{?session )}{!session >}{color.reset} ";
+const RIGHT_PROMPT: &str = "{?read_only {color.red}read-only }{color.purple}{?session {?consume_tokens {consume_tokens}({consume_percent}%)}{!consume_tokens {consume_tokens}}}{color.reset}";
 
 static EDITOR: OnceLock<Option<String>> = OnceLock::new();
 
+/// Appended to the system prompt in read-only mode, see [`Config::read_only`].
+const READ_ONLY_PROMPT: &str = "Read-only mode: the user is investigating a machine that must not change. Only propose read-only diagnostics, like listing, reading, searching, querying status, logs and metrics. Never propose commands that create, modify, move or delete files, restart or stop services, install or remove packages, change permissions, or write to databases, clusters or remote systems. If a fix needs a change, describe it instead of giving the command.";
+
 #[derive(Debug, Clone, Deserialize)]
 #[serde(default)]
 pub struct Config {
@@ -118,6 +174,12 @@ pub struct Config {
     pub function_calling: bool,
     pub mapping_tools: IndexMap<String, String>,
     pub use_tools: Option<String>,
+    #[serde(default)]
+    pub budget: BudgetCaps,
+    #[serde(default)]
+    pub team: TeamRoles,
+    /// What each WASM plugin may use of the capabilities its manifest asks for.
+    pub plugin_grants: IndexMap<String, PluginCapabilities>,
 
     pub repl_prelude: Option<String>,
     pub cmd_prelude: Option<String>,
@@ -140,6 +202,7 @@ pub struct Config {
 
     pub highlight: bool,
     pub theme: Option<String>,
+    pub accessible: bool,
     pub left_prompt: Option<String>,
     pub right_prompt: Option<String>,
 
@@ -148,6 +211,39 @@ pub struct Config {
     pub save_shell_history: bool,
     pub sync_models_url: Option<String>,
 
+    #[serde(default)]
+    pub ssh_targets: Vec<SshTarget>,
+    pub kube_production_pattern: Option<String>,
+    #[serde(default)]
+    pub network_allowlist: Vec<String>,
+    #[serde(default)]
+    pub network_denylist: Vec<String>,
+    #[serde(default)]
+    pub blocked_commands: Vec<String>,
+    #[serde(default)]
+    pub always_allowed_commands: Vec<String>,
+    pub suggest_rules: bool,
+    pub exec_lock_wait: u64,
+    pub disk_space_threshold: u8,
+    pub risk_confirm_score: u8,
+    pub risk_block_score: Option<u8>,
+    pub acknowledge_warnings: bool,
+    pub fs_snapshots: bool,
+    pub backup_failure: BackupFailure,
+    pub resolve_aliases: AliasResolution,
+    pub encrypt_at_rest: AtRestEncryption,
+    pub annotate_scripts: bool,
+    pub glob_preview_limit: usize,
+    pub capture_limit: usize,
+    #[serde(default)]
+    pub freeze_windows: Vec<FreezeWindow>,
+    #[serde(default)]
+    pub canary_paths: Vec<String>,
+    pub canary_webhook: Option<String>,
+    pub self_update: bool,
+    pub self_update_public_key: Option<String>,
+    pub offline: bool,
+
     pub clients: Vec<ClientConfig>,
 
     #[serde(skip)]
@@ -162,9 +258,35 @@ pub struct Config {
     #[serde(skip)]
     pub functions: Functions,
     #[serde(skip)]
+    pub plugins: Plugins,
+    #[serde(skip)]
+    pub hooks: Option<Hooks>,
+    #[serde(skip)]
+    pub remote: Option<RemoteSession>,
+    /// Set by `.mode readonly`, only Safe commands run until the REPL exits.
+    #[serde(skip)]
+    pub read_only: bool,
+    /// Set by `--env diff`, both machines are described in the prompt.
+    #[serde(skip)]
+    pub env_comparison: Option<EnvComparison>,
+    #[serde(skip)]
     pub working_mode: WorkingMode,
     #[serde(skip)]
     pub last_message: Option<LastMessage>,
+    /// Captured by `.paste` or `.pane`, prepended to the next prompt.
+    #[serde(skip)]
+    pub pending_capture: Option<Capture>,
+    /// Commands the model suggested in this process, newest first.
+    #[serde(skip)]
+    pub suggested_commands: VecDeque<String>,
+    /// The last long command run under supervision, for `.progress`.
+    #[serde(skip)]
+    pub supervised_job: Option<Arc<SupervisedJob>>,
+    #[serde(skip)]
+    pub budget_usage: BudgetUsage,
+    /// The top-level settings as written in the config file, to tell what a reload changes.
+    #[serde(skip)]
+    pub file_settings: serde_yaml::Mapping,
 
     #[serde(skip)]
     pub role: Option<Role>,
@@ -194,6 +316,9 @@ impl Default for Config {
             function_calling: true,
             mapping_tools: Default::default(),
             use_tools: None,
+            budget: Default::default(),
+            team: Default::default(),
+            plugin_grants: Default::default(),
 
             repl_prelude: None,
             cmd_prelude: None,
@@ -215,6 +340,7 @@ impl Default for Config {
 
             highlight: true,
             theme: None,
+            accessible: false,
             left_prompt: None,
             right_prompt: None,
 
@@ -223,6 +349,32 @@ impl Default for Config {
             save_shell_history: true,
             sync_models_url: None,
 
+            ssh_targets: vec![],
+            kube_production_pattern: None,
+            network_allowlist: vec![],
+            network_denylist: vec![],
+            blocked_commands: vec![],
+            always_allowed_commands: vec![],
+            suggest_rules: false,
+            exec_lock_wait: 0,
+            disk_space_threshold: 90,
+            risk_confirm_score: 75,
+            risk_block_score: None,
+            acknowledge_warnings: false,
+            fs_snapshots: false,
+            backup_failure: BackupFailure::default(),
+            resolve_aliases: AliasResolution::default(),
+            encrypt_at_rest: AtRestEncryption::default(),
+            annotate_scripts: false,
+            glob_preview_limit: 20,
+            capture_limit: 20000,
+            freeze_windows: vec![],
+            canary_paths: vec![],
+            canary_webhook: None,
+            self_update: true,
+            self_update_public_key: None,
+            offline: false,
+
             clients: vec![],
 
             macro_flag: false,
@@ -231,8 +383,18 @@ impl Default for Config {
 
             model: Default::default(),
             functions: Default::default(),
+            plugins: Default::default(),
+            hooks: None,
+            remote: None,
+            read_only: false,
+            env_comparison: None,
             working_mode: WorkingMode::Cmd,
             last_message: None,
+            pending_capture: None,
+            suggested_commands: VecDeque::new(),
+            supervised_job: None,
+            budget_usage: Default::default(),
+            file_settings: Default::default(),
 
             role: None,
             session: None,
@@ -269,12 +431,19 @@ impl Config {
 
         let setup = |config: &mut Self| -> Result<()> {
             config.load_envs();
+            if config.offline {
+                set_offline(true);
+            }
+            set_at_rest_key(AtRestKey::load(config.encrypt_at_rest));
 
             if let Some(wrap) = config.wrap.clone() {
                 config.set_wrap(&wrap)?;
             }
 
             config.load_functions()?;
+            config.load_hooks()?;
+            let globals = config.load_globals()?;
+            config.publish_globals(globals);
 
             config.setup_model()?;
             config.setup_document_loaders();
@@ -381,6 +550,42 @@ impl Config {
         Self::functions_dir().join(FUNCTIONS_BIN_DIR_NAME)
     }
 
+    pub fn plugins_dir() -> PathBuf {
+        match env::var(get_env_name("plugins_dir")) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => Self::local_path(PLUGINS_DIR_NAME),
+        }
+    }
+
+    pub fn hooks_file() -> PathBuf {
+        match env::var(get_env_name("hooks_file")) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => Self::local_path(HOOKS_FILE_NAME),
+        }
+    }
+
+    pub fn policy_file() -> PathBuf {
+        match env::var(get_env_name("policy_file")) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => Self::local_path(POLICY_FILE_NAME),
+        }
+    }
+
+    pub fn tutor_file() -> PathBuf {
+        match env::var(get_env_name("tutor_file")) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => Self::local_path(TUTOR_FILE_NAME),
+        }
+    }
+
+    /// The tldr pages cached for the tutor.
+    pub fn tldr_dir() -> PathBuf {
+        match env::var(get_env_name("tldr_dir")) {
+            Ok(value) => PathBuf::from(value),
+            Err(_) => Self::local_path(TLDR_DIR_NAME),
+        }
+    }
+
     pub fn session_file(&self, name: &str) -> PathBuf {
         match name.split_once("/") {
             Some((dir, name)) => self.sessions_dir().join(dir).join(format!("{name}.yaml")),
@@ -493,14 +698,55 @@ impl Config {
         let config_path = Self::config_file();
         let editor = self.editor()?;
         edit_file(&editor, &config_path)?;
-        println!(
-            "NOTE: Remember to restart {} if there are changes made to '{}",
-            env!("CARGO_CRATE_NAME"),
-            config_path.display(),
-        );
         Ok(())
     }
 
+    /// Re-read the config file, the hooks, the theme, the policy, the tutor knowledge and the
+    /// functions, keeping the current role, session, RAG and agent. Nothing changes, the
+    /// globals included, if any of them fails to load. Returns the settings that changed.
+    pub fn reload(config: &GlobalConfig) -> Result<Vec<String>> {
+        let mut new = Self::load_from_file(&Self::config_file())?;
+        new.load_envs();
+        let changes = {
+            let old = config.read();
+            new.working_mode = old.working_mode;
+            new.info_flag = old.info_flag;
+            new.macro_flag = old.macro_flag;
+            new.agent_variables = old.agent_variables.clone();
+            new.remote = old.remote.clone();
+            new.last_message = old.last_message.clone();
+            new.pending_capture = old.pending_capture.clone();
+            new.suggested_commands = old.suggested_commands.clone();
+            new.supervised_job = old.supervised_job.clone();
+            new.budget_usage = old.budget_usage.clone();
+            new.read_only = old.read_only;
+            new.env_comparison = old.env_comparison.clone();
+            // A model switched with `.model` survives unless the file names another one.
+            if new.model_id == old.model_id || new.model_id.is_empty() {
+                new.model_id = old.model_id.clone();
+                new.model = old.model.clone();
+            } else {
+                new.setup_model()?;
+            }
+            new.role = old.role.clone();
+            new.session = old.session.clone();
+            new.rag = old.rag.clone();
+            new.agent = old.agent.clone();
+            watch::describe_changes(&old.file_settings, &new.file_settings)
+        };
+        if let Some(wrap) = new.wrap.clone() {
+            new.set_wrap(&wrap)?;
+        }
+        new.load_functions()?;
+        new.load_hooks()?;
+        let globals = new.load_globals()?;
+        new.setup_document_loaders();
+        new.setup_user_agent();
+        new.publish_globals(globals);
+        *config.write() = new;
+        Ok(changes)
+    }
+
     pub fn current_model(&self) -> &Model {
         if let Some(session) = self.session.as_ref() {
             session.model()
@@ -525,6 +771,28 @@ impl Config {
         }
     }
 
+    /// Why read-only mode refuses to run `what`: only Safe commands run, and tools and
+    /// plugins only when called with a Safe command. Every execution path asks this.
+    pub fn read_only_refusal(
+        &self,
+        what: &str,
+        analysis: Option<&CommandAnalysis>,
+    ) -> Option<String> {
+        if !self.read_only {
+            return None;
+        }
+        match analysis {
+            Some(analysis) if analysis.safety_level == SafetyLevel::Safe => None,
+            Some(analysis) => Some(format!(
+                "read-only mode only runs Safe commands, {what} is {:?}",
+                analysis.safety_level
+            )),
+            None => Some(format!(
+                "read-only mode only runs Safe commands, {what} runs no command that can be checked"
+            )),
+        }
+    }
+
     pub fn extract_role(&self) -> Role {
         // =====================================
         // 先取得基礎 role（session/agent/role/default）
@@ -545,16 +813,29 @@ impl Config {
             );
             role
         };
-    
+
         // ===============================
         // 加入使用者環境 context
         // ===============================
-        let env = EnvProfile::detect();
-        let env_ctx = env.to_prompt_context();
-    
-        let new_prompt = format!("{}\n\n{}", env_ctx, role.prompt());
-        role.set_prompt(new_prompt); 
-    
+        let env_ctx = match &self.remote {
+            Some(remote) => format!(
+                "{}\nCommands run on the remote host '{}' over SSH, target its environment above instead of the local machine.",
+                remote.env.to_prompt_context(),
+                remote.target.host
+            ),
+            None => EnvProfile::detect().to_prompt_context(),
+        };
+        let env_ctx = match &self.env_comparison {
+            Some(comparison) => format!("{env_ctx}\n{}", comparison.to_prompt_context()),
+            None => env_ctx,
+        };
+
+        let mut new_prompt = format!("{}\n\n{}", env_ctx, role.prompt());
+        if self.read_only {
+            new_prompt.push_str(&format!("\n\n{READ_ONLY_PROMPT}"));
+        }
+        role.set_prompt(new_prompt);
+
         role
     }
 
@@ -615,6 +896,8 @@ impl Config {
             ("rag_top_k", rag_top_k.to_string()),
             ("dry_run", self.dry_run.to_string()),
             ("function_calling", self.function_calling.to_string()),
+            ("budget", self.budget.to_string()),
+            ("team", self.team.to_string()),
             ("stream", self.stream.to_string()),
             ("save", self.save.to_string()),
             ("keybindings", self.keybindings.clone()),
@@ -622,6 +905,38 @@ impl Config {
             ("wrap_code", self.wrap_code.to_string()),
             ("highlight", self.highlight.to_string()),
             ("theme", format_option_value(&self.theme)),
+            ("accessible", self.accessible.to_string()),
+            ("suggest_rules", self.suggest_rules.to_string()),
+            ("exec_lock_wait", self.exec_lock_wait.to_string()),
+            (
+                "disk_space_threshold",
+                self.disk_space_threshold.to_string(),
+            ),
+            ("risk_confirm_score", self.risk_confirm_score.to_string()),
+            (
+                "risk_block_score",
+                format_option_value(&self.risk_block_score),
+            ),
+            (
+                "acknowledge_warnings",
+                self.acknowledge_warnings.to_string(),
+            ),
+            ("fs_snapshots", self.fs_snapshots.to_string()),
+            ("backup_failure", self.backup_failure.to_string()),
+            ("resolve_aliases", self.resolve_aliases.to_string()),
+            ("encrypt_at_rest", self.encrypt_at_rest.to_string()),
+            ("annotate_scripts", self.annotate_scripts.to_string()),
+            ("glob_preview_limit", self.glob_preview_limit.to_string()),
+            ("capture_limit", self.capture_limit.to_string()),
+            ("self_update", self.self_update.to_string()),
+            ("offline", is_offline().to_string()),
+            (
+                "remote",
+                self.remote
+                    .as_ref()
+                    .map(|v| v.target.destination())
+                    .unwrap_or_else(|| "-".into()),
+            ),
             ("config_file", display_path(&Self::config_file())),
             ("env_file", display_path(&Self::env_file())),
             ("roles_dir", display_path(&Self::roles_dir())),
@@ -629,6 +944,11 @@ impl Config {
             ("rags_dir", display_path(&Self::rags_dir())),
             ("macros_dir", display_path(&Self::macros_dir())),
             ("functions_dir", display_path(&Self::functions_dir())),
+            ("plugins_dir", display_path(&Self::plugins_dir())),
+            ("hooks_file", display_path(&Self::hooks_file())),
+            ("policy_file", display_path(&Self::policy_file())),
+            ("tutor_file", display_path(&Self::tutor_file())),
+            ("tldr_dir", display_path(&Self::tldr_dir())),
             ("messages_file", display_path(&self.messages_file())),
         ];
         if let Ok((_, Some(log_path))) = Self::log_config(self.working_mode.is_serve()) {
@@ -643,12 +963,10 @@ impl Config {
     }
 
     pub fn update(config: &GlobalConfig, data: &str) -> Result<()> {
-        let parts: Vec<&str> = data.split_whitespace().collect();
-        if parts.len() != 2 {
+        let Some((key, value)) = data.trim().split_once(char::is_whitespace) else {
             bail!("Usage: .set <key> <value>. If value is null, unset key.");
-        }
-        let key = parts[0];
-        let value = parts[1];
+        };
+        let value = value.trim();
         match key {
             "temperature" => {
                 let value = parse_value(value)?;
@@ -674,6 +992,14 @@ impl Config {
                 let value = parse_value(value)?;
                 config.write().set_compress_threshold(value);
             }
+            "blocked_commands" => {
+                let value = parse_list(value);
+                config.write().set_blocked_commands(value);
+            }
+            "always_allowed_commands" => {
+                let value = parse_list(value);
+                config.write().set_always_allowed_commands(value);
+            }
             "rag_reranker_model" => {
                 let value = parse_value(value)?;
                 Self::set_rag_reranker_model(config, value)?;
@@ -705,6 +1031,74 @@ impl Config {
                 let value = value.parse().with_context(|| "Invalid value")?;
                 config.write().highlight = value;
             }
+            "accessible" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().accessible = value;
+                set_accessible(value);
+            }
+            "suggest_rules" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().suggest_rules = value;
+            }
+            "exec_lock_wait" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().exec_lock_wait = value;
+            }
+            "disk_space_threshold" => {
+                let value: u8 = value.parse().with_context(|| "Invalid value")?;
+                if value > 100 {
+                    bail!("Invalid value, expected a percentage from 0 to 100");
+                }
+                config.write().disk_space_threshold = value;
+            }
+            "risk_confirm_score" => {
+                let value: u8 = value.parse().with_context(|| "Invalid value")?;
+                if value > 100 {
+                    bail!("Invalid value, expected a score from 0 to 100");
+                }
+                config.write().risk_confirm_score = value;
+            }
+            "risk_block_score" => {
+                let value = parse_value::<u8>(value)?;
+                if value.is_some_and(|v| v > 100) {
+                    bail!("Invalid value, expected a score from 0 to 100");
+                }
+                config.write().risk_block_score = value;
+            }
+            "acknowledge_warnings" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().acknowledge_warnings = value;
+            }
+            "fs_snapshots" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().fs_snapshots = value;
+            }
+            "backup_failure" => {
+                let value = value
+                    .parse()
+                    .map_err(|err| anyhow!("Invalid value, {err}"))?;
+                config.write().backup_failure = value;
+            }
+            "resolve_aliases" => {
+                let value = value
+                    .parse()
+                    .map_err(|err| anyhow!("Invalid value, {err}"))?;
+                config.write().resolve_aliases = value;
+                set_shell_aliases(config.read().shell_aliases());
+            }
+            "annotate_scripts" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().annotate_scripts = value;
+            }
+            "glob_preview_limit" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().glob_preview_limit = value;
+                set_glob_preview_limit(value);
+            }
+            "capture_limit" => {
+                let value = value.parse().with_context(|| "Invalid value")?;
+                config.write().capture_limit = value;
+            }
             _ => bail!("Unknown key '{key}'"),
         }
         Ok(())
@@ -816,6 +1210,22 @@ impl Config {
         }
     }
 
+    pub fn set_blocked_commands(&mut self, value: Option<Vec<String>>) {
+        if let Some(session) = self.session.as_mut() {
+            session.set_blocked_commands(value);
+        } else {
+            self.blocked_commands = value.unwrap_or_default();
+        }
+    }
+
+    pub fn set_always_allowed_commands(&mut self, value: Option<Vec<String>>) {
+        if let Some(session) = self.session.as_mut() {
+            session.set_always_allowed_commands(value);
+        } else {
+            self.always_allowed_commands = value.unwrap_or_default();
+        }
+    }
+
     pub fn set_rag_reranker_model(config: &GlobalConfig, value: Option<String>) -> Result<()> {
         if let Some(id) = &value {
             Model::retrieve_model(&config.read(), id, ModelType::Reranker)?;
@@ -1252,11 +1662,7 @@ impl Config {
         if !need_compress {
             return;
         }
-        let color = if config.read().light_theme() {
-            nu_ansi_term::Color::LightGray
-        } else {
-            nu_ansi_term::Color::DarkGray
-        };
+        let color = theme().info.0;
         print!(
             "\n📢 {}\n",
             color.italic().paint("Compressing the session."),
@@ -1318,11 +1724,7 @@ impl Config {
         if !need_autoname {
             return;
         }
-        let color = if config.read().light_theme() {
-            nu_ansi_term::Color::LightGray
-        } else {
-            nu_ansi_term::Color::DarkGray
-        };
+        let color = theme().info.0;
         print!("\n📢 {}\n", color.italic().paint("Autonaming the session."),);
         tokio::spawn(async move {
             if let Err(err) = Config::autoname_session(&config).await {
@@ -1523,6 +1925,7 @@ impl Config {
         });
         config.write().rag = agent.rag();
         config.write().agent = Some(agent);
+        config.write().budget_usage = BudgetUsage::default();
         if let Some(session) = session {
             config.write().use_session(Some(&session))?;
         } else {
@@ -1782,6 +2185,7 @@ impl Config {
                 ".rag" => map_completion_values(Self::list_rags()),
                 ".agent" => map_completion_values(list_agents()),
                 ".macro" => map_completion_values(Self::list_macros()),
+                ".mode" => map_completion_values(vec!["readonly"]),
                 ".starter" => match &self.agent {
                     Some(agent) => agent
                         .conversation_staters()
@@ -1798,6 +2202,8 @@ impl Config {
                         "use_tools",
                         "save_session",
                         "compress_threshold",
+                        "blocked_commands",
+                        "always_allowed_commands",
                         "rag_reranker_model",
                         "rag_top_k",
                         "max_output_tokens",
@@ -1806,6 +2212,19 @@ impl Config {
                         "stream",
                         "save",
                         "highlight",
+                        "accessible",
+                        "suggest_rules",
+                        "exec_lock_wait",
+                        "disk_space_threshold",
+                        "risk_confirm_score",
+                        "risk_block_score",
+                        "acknowledge_warnings",
+                        "fs_snapshots",
+                        "backup_failure",
+                        "resolve_aliases",
+                        "annotate_scripts",
+                        "glob_preview_limit",
+                        "capture_limit",
                     ];
                     values.sort_unstable();
                     values
@@ -1860,6 +2279,21 @@ impl Config {
                     .map(|v| v.id())
                     .collect(),
                 "highlight" => complete_bool(self.highlight),
+                "accessible" => complete_bool(self.accessible),
+                "suggest_rules" => complete_bool(self.suggest_rules),
+                "acknowledge_warnings" => complete_bool(self.acknowledge_warnings),
+                "fs_snapshots" => complete_bool(self.fs_snapshots),
+                "backup_failure" => ["ask", "proceed", "block"]
+                    .into_iter()
+                    .filter(|v| *v != self.backup_failure.to_string())
+                    .map(|v| v.to_string())
+                    .collect(),
+                "resolve_aliases" => ["off", "rc", "shell"]
+                    .into_iter()
+                    .filter(|v| *v != self.resolve_aliases.to_string())
+                    .map(|v| v.to_string())
+                    .collect(),
+                "annotate_scripts" => complete_bool(self.annotate_scripts),
                 _ => vec![],
             };
             values = candidates.into_iter().map(|v| (v, None)).collect();
@@ -2005,6 +2439,9 @@ impl Config {
         if self.dry_run {
             output.insert("dry_run", "true".to_string());
         }
+        if self.read_only {
+            output.insert("read_only", "true".to_string());
+        }
         if self.stream {
             output.insert("stream", "true".to_string());
         }
@@ -2057,6 +2494,8 @@ impl Config {
             output.insert("color.light_cyan", "\u{1b}[96m".to_string());
             output.insert("color.white", "\u{1b}[37m".to_string());
             output.insert("color.light_gray", "\u{1b}[97m".to_string());
+            output.insert("color.role", theme().role.0.prefix().to_string());
+            output.insert("color.prompt", theme().prompt.0.prefix().to_string());
         }
 
         // Inject environment info for template use
@@ -2070,7 +2509,12 @@ impl Config {
         output.insert("memory_used_gb", env.memory_used_gb.to_string());
         output.insert("disk_total_gb", env.disk_total_gb.to_string());
         output.insert("disk_available_gb", env.disk_available_gb.to_string());
-        output.insert("gpu_name", env.gpu_name.clone().unwrap_or_else(|| "Unknown".to_string()));   
+        output.insert(
+            "gpu_name",
+            env.gpu_name
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string()),
+        );
 
         output
     }
@@ -2090,12 +2534,36 @@ impl Config {
             return Ok(());
         }
         self.last_message = Some(LastMessage::new(input.clone(), output.to_string()));
+        self.record_suggested_commands(input, output);
+        if let Some(hooks) = &self.hooks {
+            if let Err(err) = hooks.on_response(output) {
+                warn!("{err}");
+            }
+        }
         if !self.dry_run {
             self.save_message(input, output)?;
         }
         Ok(())
     }
 
+    /// The whole response of the shell role, the shell code blocks of any other.
+    fn record_suggested_commands(&mut self, input: &Input, output: &str) {
+        let commands = match input.role().name() == SHELL_ROLE {
+            true => vec![extract_code_block(output).trim().to_string()],
+            false => extract_shell_commands(output),
+        };
+        self.add_suggested_commands(commands);
+    }
+
+    /// Puts `commands` first in `.cmds`, the last one on top.
+    pub fn add_suggested_commands(&mut self, commands: Vec<String>) {
+        for command in commands.into_iter().filter(|v| !v.is_empty()) {
+            self.suggested_commands.retain(|v| *v != command);
+            self.suggested_commands.push_front(command);
+        }
+        self.suggested_commands.truncate(SUGGESTED_COMMANDS_LIMIT);
+    }
+
     fn discontinuous_last_message(&mut self) {
         if let Some(last_message) = self.last_message.as_mut() {
             last_message.continuous = false;
@@ -2230,7 +2698,7 @@ impl Config {
     fn load_from_file(config_path: &Path) -> Result<Self> {
         let err = || format!("Failed to load config at '{}'", config_path.display());
         let content = read_to_string(config_path).with_context(err)?;
-        let config: Self = serde_yaml::from_str(&content)
+        let mut config: Self = serde_yaml::from_str(&content)
             .map_err(|err| {
                 let err_msg = err.to_string();
                 let err_msg = if err_msg.starts_with(&format!("{CLIENTS_FIELD}: ")) {
@@ -2247,6 +2715,7 @@ impl Config {
                 anyhow!("{err_msg}")
             })
             .with_context(err)?;
+        config.file_settings = serde_yaml::from_str(&content).unwrap_or_default();
 
         Ok(config)
     }
@@ -2372,7 +2841,62 @@ impl Config {
         if let Some(Some(v)) = read_env_bool(&get_env_name("highlight")) {
             self.highlight = v;
         }
-        if *NO_COLOR {
+        if let Some(Some(v)) = read_env_bool(&get_env_name("accessible")) {
+            self.accessible = v;
+        }
+        if let Some(Some(v)) = read_env_bool(&get_env_name("suggest_rules")) {
+            self.suggest_rules = v;
+        }
+        if let Some(Some(v)) = read_env_value::<u64>(&get_env_name("exec_lock_wait")) {
+            self.exec_lock_wait = v;
+        }
+        if let Some(Some(v)) = read_env_value::<u8>(&get_env_name("disk_space_threshold")) {
+            self.disk_space_threshold = v.min(100);
+        }
+        if let Some(Some(v)) = read_env_value::<u8>(&get_env_name("risk_confirm_score")) {
+            self.risk_confirm_score = v.min(100);
+        }
+        if let Some(v) = read_env_value::<u8>(&get_env_name("risk_block_score")) {
+            self.risk_block_score = v.map(|v| v.min(100));
+        }
+        if let Some(Some(v)) = read_env_bool(&get_env_name("acknowledge_warnings")) {
+            self.acknowledge_warnings = v;
+        }
+        if let Some(Some(v)) = read_env_bool(&get_env_name("fs_snapshots")) {
+            self.fs_snapshots = v;
+        }
+        if let Some(Some(v)) = read_env_value::<BackupFailure>(&get_env_name("backup_failure")) {
+            self.backup_failure = v;
+        }
+        if let Some(Some(v)) = read_env_value::<AliasResolution>(&get_env_name("resolve_aliases")) {
+            self.resolve_aliases = v;
+        }
+        if let Some(Some(v)) = read_env_value::<AtRestEncryption>(&get_env_name("encrypt_at_rest"))
+        {
+            self.encrypt_at_rest = v;
+        }
+        if let Some(Some(v)) = read_env_bool(&get_env_name("annotate_scripts")) {
+            self.annotate_scripts = v;
+        }
+        if let Some(Some(v)) = read_env_value::<usize>(&get_env_name("glob_preview_limit")) {
+            self.glob_preview_limit = v;
+        }
+        if let Some(Some(v)) = read_env_value::<usize>(&get_env_name("capture_limit")) {
+            self.capture_limit = v;
+        }
+        if let Some(v) = read_env_value::<String>(&get_env_name("canary_webhook")) {
+            self.canary_webhook = v;
+        }
+        if let Some(Some(v)) = read_env_bool(&get_env_name("self_update")) {
+            self.self_update = v;
+        }
+        if let Some(v) = read_env_value::<String>(&get_env_name("self_update_public_key")) {
+            self.self_update_public_key = v;
+        }
+        if let Some(Some(v)) = read_env_bool(&get_env_name("offline")) {
+            self.offline = v;
+        }
+        if *NO_COLOR || self.accessible {
             self.highlight = false;
         }
         if self.highlight && self.theme.is_none() {
@@ -2407,13 +2931,100 @@ impl Config {
         if let Some(v) = read_env_value::<String>(&get_env_name("sync_models_url")) {
             self.sync_models_url = v;
         }
+        if let Some(v) = read_env_value::<String>(&get_env_name("kube_production_pattern")) {
+            self.kube_production_pattern = v;
+        }
     }
 
     fn load_functions(&mut self) -> Result<()> {
         self.functions = Functions::init(&Self::functions_file())?;
+        self.plugins = Plugins::init(&Self::plugins_dir(), &self.plugin_grants)?;
+        self.plugins.skip_functions(&self.functions);
+        self.functions.extend(self.plugins.declarations());
+        Ok(())
+    }
+
+    pub fn kube_production_pattern(&self) -> String {
+        self.kube_production_pattern
+            .clone()
+            .unwrap_or_else(|| DEFAULT_KUBE_PRODUCTION_PATTERN.into())
+    }
+
+    /// The name of the change-freeze window active right now, if any.
+    pub fn active_freeze_window(&self) -> Result<Option<String>> {
+        let now = chrono::Local::now().naive_local();
+        Ok(freeze::active_freeze_window(&self.freeze_windows, now)?.map(|v| v.to_string()))
+    }
+
+    /// `blocked_commands` and `always_allowed_commands`, as overridden by the session.
+    pub fn command_lists(&self) -> CommandLists {
+        let session = self.session.as_ref();
+        CommandLists {
+            blocked: session
+                .and_then(|v| v.blocked_commands())
+                .unwrap_or(&self.blocked_commands)
+                .clone(),
+            allowed: session
+                .and_then(|v| v.always_allowed_commands())
+                .unwrap_or(&self.always_allowed_commands)
+                .clone(),
+        }
+    }
+
+    pub fn network_policy(&self) -> NetworkPolicy {
+        NetworkPolicy {
+            allow: self.network_allowlist.clone(),
+            deny: self.network_denylist.clone(),
+        }
+    }
+
+    /// Switch command execution to a configured SSH target, probing its environment.
+    pub fn use_remote(&mut self, name: &str) -> Result<()> {
+        let target = self
+            .ssh_targets
+            .iter()
+            .find(|v| v.name == name)
+            .cloned()
+            .ok_or_else(|| anyhow!("Unknown ssh target '{name}'"))?;
+        let env = target.probe()?;
+        self.remote = Some(RemoteSession { target, env });
+        Ok(())
+    }
+
+    fn load_hooks(&mut self) -> Result<()> {
+        self.hooks = Hooks::init(&Self::hooks_file())?;
         Ok(())
     }
 
+    /// Load what the engine reads from globals without touching them, so a failure
+    /// leaves the current ones in place.
+    fn load_globals(&self) -> Result<LoadedGlobals> {
+        let theme_mode = if self.light_theme() { "light" } else { "dark" };
+        let theme_path = Self::local_path(&format!("{theme_mode}.theme.yaml"));
+        Ok(LoadedGlobals {
+            theme: Theme::load(self.light_theme(), &theme_path)?,
+            policy: SafetyPolicy::load(&Self::policy_file())?,
+            tutor_knowledge: TutorKnowledge::load(&Self::tutor_file())?,
+            aliases: self.shell_aliases(),
+        })
+    }
+
+    /// Aliases and shell functions the analyzer expands (`resolve_aliases`).
+    fn shell_aliases(&self) -> ShellAliases {
+        let shell = ShellKind::from_name(&SHELL.name);
+        ShellAliases::load(self.resolve_aliases, &shell)
+    }
+
+    fn publish_globals(&self, globals: LoadedGlobals) {
+        set_accessible(self.accessible);
+        set_theme(globals.theme);
+        set_glob_preview_limit(self.glob_preview_limit);
+        set_tldr_dir(Some(Self::tldr_dir()));
+        set_shell_aliases(globals.aliases);
+        set_safety_policy(globals.policy);
+        set_tutor_knowledge(globals.tutor_knowledge);
+    }
+
     fn setup_model(&mut self) -> Result<()> {
         let mut model_id = self.model_id.clone();
         if model_id.is_empty() {
@@ -2461,7 +3072,17 @@ pub fn load_env_file() -> Result<()> {
             continue;
         }
         if let Some((key, value)) = line.split_once('=') {
-            env::set_var(key.trim(), value.trim());
+            let value = match value.trim().strip_prefix(KEYCHAIN_PREFIX) {
+                Some(account) => match keychain_lookup(account) {
+                    Ok(v) => v,
+                    Err(err) => {
+                        eprintln!("Skipped {} from the env file: {err}", key.trim());
+                        continue;
+                    }
+                },
+                None => value.trim().to_string(),
+            };
+            env::set_var(key.trim(), value);
         }
     }
     Ok(())
@@ -2609,6 +3230,15 @@ impl LastMessage {
     }
 }
 
+/// The engine's globals as loaded from the config dir, published together once every
+/// one of them loaded, see [`Config::reload`].
+struct LoadedGlobals {
+    theme: Theme,
+    policy: SafetyPolicy,
+    tutor_knowledge: TutorKnowledge,
+    aliases: ShellAliases,
+}
+
 bitflags::bitflags! {
     #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
     pub struct StateFlags: u32 {
@@ -2658,31 +3288,7 @@ async fn create_config_file(config_path: &Path) -> Result<()> {
         process::exit(0);
     }
 
-    let client = Select::new("API Provider (required):", list_client_types()).prompt()?;
-
-    let mut config = serde_json::json!({});
-    let (model, clients_config) = create_client_config(client).await?;
-    config["model"] = model.into();
-    config[CLIENTS_FIELD] = clients_config;
-
-    let config_data = serde_yaml::to_string(&config).with_context(|| "Failed to create config")?;
-    let config_data = format!(
-        "# see https://github.com/sigoden/aichat/blob/main/config.example.yaml\n\n{config_data}"
-    );
-
-    ensure_parent_exists(config_path)?;
-    std::fs::write(config_path, config_data)
-        .with_context(|| format!("Failed to write to '{}'", config_path.display()))?;
-    #[cfg(unix)]
-    {
-        use std::os::unix::prelude::PermissionsExt;
-        let perms = std::fs::Permissions::from_mode(0o600);
-        std::fs::set_permissions(config_path, perms)?;
-    }
-
-    println!("✓ Saved the config file to '{}'.\n", config_path.display());
-
-    Ok(())
+    run_setup_wizard(config_path).await
 }
 
 pub(crate) fn ensure_parent_exists(path: &Path) -> Result<()> {
@@ -2712,6 +3318,17 @@ where
     Some(value)
 }
 
+/// A comma-separated list, `null` for none.
+fn parse_list(value: &str) -> Option<Vec<String>> {
+    (value != "null").then(|| {
+        value
+            .split(',')
+            .map(|v| v.trim().to_string())
+            .filter(|v| !v.is_empty())
+            .collect()
+    })
+}
+
 fn parse_value<T>(value: &str) -> Result<Option<T>>
 where
     T: std::str::FromStr,
@@ -2771,3 +3388,81 @@ where
         None => "null".to_string(),
     }
 }
+
+/// Point the config dir at a temp dir, once per test run, for tests that write the audit
+/// log.
+#[cfg(test)]
+pub fn use_temp_config_dir() {
+    static ONCE: std::sync::Once = std::sync::Once::new();
+    ONCE.call_once(|| {
+        let dir = env::temp_dir().join(format!("aichat-config-{}", process::id()));
+        env::set_var(get_env_name("config_dir"), dir);
+    });
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::function::ToolCall;
+
+    #[test]
+    fn test_read_only_refusal() {
+        let config = Config {
+            read_only: true,
+            ..Default::default()
+        };
+        let analyze = |command: &str| CommandAnalysis::analyze(command);
+        assert!(config
+            .read_only_refusal("this one", Some(&analyze("ls -la")))
+            .is_none());
+        assert!(config
+            .read_only_refusal("this one", Some(&analyze("rm -rf build")))
+            .is_some_and(|v| v.ends_with("this one is Dangerous")));
+        assert!(config.read_only_refusal("'.deploy'", None).is_some());
+        assert!(Config::default()
+            .read_only_refusal("'.deploy'", None)
+            .is_none());
+
+        let config: GlobalConfig = Arc::new(RwLock::new(config));
+        let call = ToolCall::new(
+            "execute_command".into(),
+            json!({ "command": "rm -rf build" }),
+            None,
+        );
+        let result = call.eval(&config).unwrap();
+        assert!(result["error"]
+            .as_str()
+            .is_some_and(|v| v.starts_with("Execution blocked: read-only mode")));
+    }
+
+    #[test]
+    fn test_tool_call_refusal() {
+        use_temp_config_dir();
+        let dir = env::temp_dir().join(format!("aichat-tool-canary-{}", process::id()));
+        let config = Config {
+            blocked_commands: vec!["shutdown".into()],
+            canary_paths: vec![dir.join("canary").display().to_string()],
+            ..Default::default()
+        };
+        let config: GlobalConfig = Arc::new(RwLock::new(config));
+        let eval = |command: &str| {
+            let call = ToolCall::new(
+                "execute_command".into(),
+                json!({ "command": command }),
+                None,
+            );
+            call.eval(&config)
+        };
+        let error = |command: &str| eval(command).unwrap()["error"].as_str().map(String::from);
+
+        assert!(error("sudo shutdown now").is_some_and(|v| v.contains("blocked_commands")));
+        assert!(
+            error(&format!("rm -rf {}", dir.display())).is_some_and(|v| v.contains("canary path"))
+        );
+        // Allowed commands get as far as the (here missing) function
+        assert!(eval("ls -la")
+            .unwrap_err()
+            .to_string()
+            .starts_with("Unexpected call"));
+    }
+}
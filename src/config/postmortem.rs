@@ -0,0 +1,342 @@
+use super::{session::Session, AuditEntry, AuditLog, Config};
+
+use crate::utils::{run_command_with_output, BackupEntry, BackupManager, CommandAnalysis};
+
+use anyhow::{bail, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate, NaiveDateTime, TimeZone};
+use std::fmt::Write;
+
+/// Longest diff included per backed up file, so a single big file can't crowd out the rest.
+const MAX_DIFF_LEN: usize = 4000;
+
+const POSTMORTEM_PROMPT: &str = r#"Write a blameless post-mortem draft in Markdown for the incident recorded below, with the sections: Summary, Timeline, Impact, Root Cause, Remediation, Follow-ups.
+
+Rules:
+- Every claim must be grounded in the evidence and cite it, e.g. [E3] for an audit entry or [B1] for a backup.
+- When the evidence doesn't show something (impact on other systems, intent, ...), say it is unknown rather than guessing.
+- Remediation must reference the recorded backups and their restore commands where they exist.
+
+"#;
+
+/// What `--postmortem` covers: a saved session, or a time range of the audit log.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PostmortemScope {
+    Session(String),
+    Range {
+        from: DateTime<Local>,
+        until: DateTime<Local>,
+    },
+}
+
+impl PostmortemScope {
+    /// `2h`, `30m`, `1d` (the last 2 hours, ...), `FROM..UNTIL` with dates or
+    /// `YYYY-MM-DD HH:MM` times (`UNTIL` defaults to now), or otherwise a session name.
+    pub fn parse(value: &str, now: DateTime<Local>) -> Self {
+        let value = value.trim();
+        if let Some(duration) = parse_duration(value) {
+            return Self::Range {
+                from: now - duration,
+                until: now,
+            };
+        }
+        if let Some((from, until)) = value.split_once("..") {
+            let until = match until.trim() {
+                "" => Some(now),
+                v => parse_time(v, true),
+            };
+            if let (Some(from), Some(until)) = (parse_time(from, false), until) {
+                return Self::Range { from, until };
+            }
+        }
+        Self::Session(value.to_string())
+    }
+}
+
+/// Everything logged about an incident, numbered so the narrative can cite it.
+#[derive(Debug, Default)]
+pub struct Postmortem {
+    pub label: String,
+    pub entries: Vec<AuditEntry>,
+    pub backups: Vec<BackupEntry>,
+    /// `(role, text)` of the session messages.
+    pub conversation: Vec<(String, String)>,
+}
+
+impl Postmortem {
+    pub fn collect(config: &Config, scope: &PostmortemScope) -> Result<Self> {
+        let entries = AuditLog::read()?;
+        let backups = BackupManager::new()?.list_backups()?;
+        match scope {
+            PostmortemScope::Range { from, until } => {
+                let in_range = |timestamp: &str| {
+                    DateTime::parse_from_rfc3339(timestamp)
+                        .map(|v| *from <= v && v <= *until)
+                        .unwrap_or_default()
+                };
+                Ok(Self {
+                    label: format!(
+                        "{} .. {}",
+                        from.format("%Y-%m-%d %H:%M"),
+                        until.format("%Y-%m-%d %H:%M")
+                    ),
+                    entries: entries
+                        .into_iter()
+                        .filter(|v| in_range(&v.timestamp))
+                        .collect(),
+                    backups: backups
+                        .into_iter()
+                        .filter(|v| in_range(&v.timestamp))
+                        .rev()
+                        .collect(),
+                    conversation: vec![],
+                })
+            }
+            PostmortemScope::Session(name) => {
+                let path = config.session_file(name);
+                if !path.exists() {
+                    bail!("No session or time range '{name}'");
+                }
+                let session = Session::load(config, name, &path)?;
+                let conversation: Vec<_> = session
+                    .messages()
+                    .iter()
+                    .map(|v| {
+                        let role = serde_json::to_value(v.role)
+                            .ok()
+                            .and_then(|v| v.as_str().map(|v| v.to_string()))
+                            .unwrap_or_default();
+                        (role, v.content.to_text())
+                    })
+                    .collect();
+                // Sessions don't timestamp messages, so match on the commands they produced.
+                let produced = |command: &str| {
+                    conversation
+                        .iter()
+                        .any(|(role, text)| role == "assistant" && text.contains(command))
+                };
+                let entries: Vec<_> = entries
+                    .into_iter()
+                    .filter(|v| produced(&v.command))
+                    .collect();
+                let backups = backups
+                    .into_iter()
+                    .filter(|v| entries.iter().any(|e| e.command == v.command.trim()))
+                    .rev()
+                    .collect();
+                Ok(Self {
+                    label: format!("session {name}"),
+                    entries,
+                    backups,
+                    conversation,
+                })
+            }
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.entries.is_empty() && self.backups.is_empty() && self.conversation.is_empty()
+    }
+
+    /// The audit entries as a table, straight from the log.
+    pub fn timeline(&self) -> String {
+        let mut output = format!("# Post-mortem: {}\n\n## Recorded timeline\n\n", self.label);
+        if self.entries.is_empty() {
+            output.push_str("No audit log entries.\n");
+            return output;
+        }
+        output.push_str("| ID | Time | Decision | Level | Exit | Command | Reason |\n");
+        output.push_str("|----|------|----------|-------|------|---------|--------|\n");
+        for (i, entry) in self.entries.iter().enumerate() {
+            let time = DateTime::parse_from_rfc3339(&entry.timestamp)
+                .map(|v| v.format("%Y-%m-%d %H:%M:%S").to_string())
+                .unwrap_or_else(|_| entry.timestamp.clone());
+            let _ = writeln!(
+                output,
+                "| E{} | {time} | {:?} | {} | {} | `{}` | {} |",
+                i + 1,
+                entry.decision,
+                entry
+                    .safety_level
+                    .as_ref()
+                    .map(|v| format!("{v:?}"))
+                    .unwrap_or_default(),
+                entry.exit_code.map(|v| v.to_string()).unwrap_or_default(),
+                entry.command.replace('|', "\\|"),
+                entry
+                    .reason
+                    .as_deref()
+                    .unwrap_or_default()
+                    .replace('|', "\\|"),
+            );
+        }
+        output
+    }
+
+    /// The timeline plus analyses, backups with diffs and the conversation, for the model.
+    pub fn evidence(&self) -> String {
+        let mut output = self.timeline();
+
+        let mut commands: Vec<&str> = vec![];
+        for entry in &self.entries {
+            if !commands.contains(&entry.command.as_str()) {
+                commands.push(&entry.command);
+            }
+        }
+        if !commands.is_empty() {
+            output.push_str("\n## Analyses\n");
+        }
+        for command in commands {
+            let analysis = CommandAnalysis::analyze(command);
+            let _ = writeln!(
+                output,
+                "\n`{command}`: {:?} operation, {:?}",
+                analysis.operation, analysis.safety_level
+            );
+            for file in &analysis.affected_files {
+                let _ = writeln!(output, "- affects {}", file.display());
+            }
+            for warning in &analysis.warnings {
+                let _ = writeln!(output, "- {warning}");
+            }
+        }
+
+        if !self.backups.is_empty() {
+            output.push_str("\n## Backups\n");
+        }
+        for (i, backup) in self.backups.iter().enumerate() {
+            let _ = writeln!(
+                output,
+                "\n[B{}] {} before `{}`, restore with `.backup restore {}`",
+                i + 1,
+                backup.timestamp,
+                backup.command,
+                backup.id
+            );
+            if let Some(snapshot) = &backup.snapshot {
+                let _ = writeln!(output, "- filesystem snapshot {}", snapshot.name);
+            }
+            for file in &backup.files {
+                let _ = writeln!(output, "- {}", file.original_path.display());
+                if backup.remote.is_some() {
+                    continue;
+                }
+                if let Some(diff) = backup_diff(&file.backup_path, &file.original_path) {
+                    let _ = writeln!(output, "```diff\n{diff}\n```");
+                }
+            }
+        }
+
+        if !self.conversation.is_empty() {
+            output.push_str("\n## Conversation\n");
+        }
+        for (role, text) in &self.conversation {
+            let _ = writeln!(output, "\n**{role}**:\n{text}");
+        }
+        output
+    }
+
+    pub fn prompt(&self) -> String {
+        format!("{POSTMORTEM_PROMPT}{}", self.evidence())
+    }
+}
+
+/// What changed since the backup, `None` when unchanged or `diff` isn't available.
+fn backup_diff(backup: &std::path::Path, current: &std::path::Path) -> Option<String> {
+    let backup = backup.display().to_string();
+    let current = current.display().to_string();
+    let (_, stdout, _) = run_command_with_output("diff", &["-u", &backup, &current], None).ok()?;
+    let diff = stdout.trim();
+    if diff.is_empty() {
+        return None;
+    }
+    match diff.char_indices().nth(MAX_DIFF_LEN) {
+        Some((i, _)) => Some(format!("{}\n... (truncated)", &diff[..i])),
+        None => Some(diff.to_string()),
+    }
+}
+
+fn parse_duration(value: &str) -> Option<Duration> {
+    let unit = value.chars().last()?;
+    let number: i64 = value[..value.len() - unit.len_utf8()].parse().ok()?;
+    match unit {
+        'm' => Some(Duration::minutes(number)),
+        'h' => Some(Duration::hours(number)),
+        'd' => Some(Duration::days(number)),
+        'w' => Some(Duration::weeks(number)),
+        _ => None,
+    }
+}
+
+/// A bare date means the start of the day, or the end of it for `end_of_day`.
+fn parse_time(value: &str, end_of_day: bool) -> Option<DateTime<Local>> {
+    let value = value.trim();
+    if let Ok(v) = DateTime::parse_from_rfc3339(value) {
+        return Some(v.with_timezone(&Local));
+    }
+    let naive = ["%Y-%m-%d %H:%M", "%Y-%m-%dT%H:%M", "%Y-%m-%d %H:%M:%S"]
+        .iter()
+        .find_map(|v| NaiveDateTime::parse_from_str(value, v).ok())
+        .or_else(|| {
+            let date = NaiveDate::parse_from_str(value, "%Y-%m-%d").ok()?;
+            match end_of_day {
+                true => date.and_hms_opt(23, 59, 59),
+                false => date.and_hms_opt(0, 0, 0),
+            }
+        })?;
+    Local.from_local_datetime(&naive).earliest()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AuditDecision;
+
+    #[test]
+    fn test_parse_scope() {
+        let now = Local.with_ymd_and_hms(2026, 10, 16, 12, 0, 0).unwrap();
+        assert_eq!(
+            PostmortemScope::parse("2h", now),
+            PostmortemScope::Range {
+                from: Local.with_ymd_and_hms(2026, 10, 16, 10, 0, 0).unwrap(),
+                until: now,
+            }
+        );
+        assert_eq!(
+            PostmortemScope::parse("2026-10-15..2026-10-15", now),
+            PostmortemScope::Range {
+                from: Local.with_ymd_and_hms(2026, 10, 15, 0, 0, 0).unwrap(),
+                until: Local.with_ymd_and_hms(2026, 10, 15, 23, 59, 59).unwrap(),
+            }
+        );
+        assert_eq!(
+            PostmortemScope::parse("2026-10-16 09:30..", now),
+            PostmortemScope::Range {
+                from: Local.with_ymd_and_hms(2026, 10, 16, 9, 30, 0).unwrap(),
+                until: now,
+            }
+        );
+        assert_eq!(
+            PostmortemScope::parse("deploy-fix", now),
+            PostmortemScope::Session("deploy-fix".into())
+        );
+    }
+
+    #[test]
+    fn test_timeline() {
+        let postmortem = Postmortem {
+            label: "session deploy".into(),
+            entries: vec![
+                AuditEntry::new("rm -rf build | tee log", AuditDecision::Approved)
+                    .with_exit_code(1),
+                AuditEntry::new("sudo reboot", AuditDecision::Denied),
+            ],
+            ..Default::default()
+        };
+        let timeline = postmortem.timeline();
+        assert!(timeline.starts_with("# Post-mortem: session deploy"));
+        assert!(timeline.contains("| E1 |"));
+        assert!(timeline.contains(r"`rm -rf build \| tee log`"));
+        assert!(timeline.contains("| E2 |"));
+        assert!(postmortem.prompt().contains("[E3]"));
+    }
+}
@@ -0,0 +1,273 @@
+use super::{ensure_parent_exists, Config};
+use crate::utils::{CommandAnalysis, SafetyLevel, RE_VARIABLE};
+
+use anyhow::{bail, Context, Result};
+use fancy_regex::Captures;
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path, path::PathBuf};
+
+const RECIPES_FILE_NAME: &str = "recipes.yaml";
+const BUILTIN_RECIPES: &str = include_str!("../../assets/recipes.yaml");
+
+/// Vetted, parameterized command sequences, run with `--recipe run <NAME>`.
+///
+/// The recipes added by the user are kept in the config dir, each step with the verdict it
+/// got when added:
+///
+/// ```yaml
+/// recipes:
+///   - name: clear-cache
+///     params:
+///       - name: app
+///     steps:
+///       - command: rm -rf ~/.cache/{{app}}
+///         level: dangerous
+///         risk: 50
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct RecipeBook {
+    #[serde(default)]
+    pub recipes: Vec<Recipe>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Recipe {
+    pub name: String,
+    #[serde(default)]
+    pub description: String,
+    #[serde(default)]
+    pub params: Vec<RecipeParam>,
+    pub steps: Vec<RecipeStep>,
+    #[serde(skip)]
+    pub builtin: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeParam {
+    pub name: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub description: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub default: Option<String>,
+}
+
+/// A command of a recipe with its verdict from [`Recipe::annotate`].
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RecipeStep {
+    pub command: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub level: Option<SafetyLevel>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub risk: Option<u8>,
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub warnings: Vec<String>,
+}
+
+impl RecipeBook {
+    pub fn path() -> PathBuf {
+        Config::local_path(RECIPES_FILE_NAME)
+    }
+
+    /// The recipes added by the user.
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse '{}'", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        ensure_parent_exists(&path)?;
+        fs::write(&path, serde_yaml::to_string(self)?)
+            .with_context(|| format!("Failed to write '{}'", path.display()))
+    }
+
+    /// The built-in recipes, annotated for this machine, followed by the user's. A recipe of
+    /// the user replaces the built-in one of the same name.
+    pub fn all(&self) -> Vec<Recipe> {
+        let builtin: RecipeBook =
+            serde_yaml::from_str(BUILTIN_RECIPES).expect("invalid builtin recipes");
+        let mut output: Vec<Recipe> = builtin
+            .recipes
+            .into_iter()
+            .filter(|v| !self.recipes.iter().any(|u| u.name == v.name))
+            .map(|mut v| {
+                v.builtin = true;
+                v.annotate();
+                v
+            })
+            .collect();
+        output.extend(self.recipes.iter().cloned());
+        output
+    }
+
+    pub fn find(&self, name: &str) -> Option<Recipe> {
+        self.all().into_iter().find(|v| v.name == name)
+    }
+
+    pub fn add(&mut self, recipe: Recipe) {
+        self.recipes.retain(|v| v.name != recipe.name);
+        self.recipes.push(recipe);
+    }
+
+    pub fn remove(&mut self, name: &str) -> bool {
+        let len = self.recipes.len();
+        self.recipes.retain(|v| v.name != name);
+        self.recipes.len() != len
+    }
+}
+
+impl Recipe {
+    /// Read a recipe from a YAML file, checked and annotated, ready for [`RecipeBook::add`].
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        let mut recipe: Self = serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse '{}'", path.display()))?;
+        recipe.validate()?;
+        recipe.annotate();
+        Ok(recipe)
+    }
+
+    fn validate(&self) -> Result<()> {
+        if self.name.is_empty() || self.name.contains(char::is_whitespace) {
+            bail!("Invalid recipe name '{}'", self.name);
+        }
+        if self.steps.is_empty() {
+            bail!("The recipe '{}' has no steps", self.name);
+        }
+        for (i, param) in self.params.iter().enumerate() {
+            if self.params[..i].iter().any(|v| v.name == param.name) {
+                bail!("The parameter '{}' is declared twice", param.name);
+            }
+        }
+        for step in &self.steps {
+            for name in placeholders(&step.command) {
+                if !self.params.iter().any(|v| v.name == name) {
+                    bail!("'{}' uses the undeclared parameter '{name}'", step.command);
+                }
+            }
+        }
+        Ok(())
+    }
+
+    /// Analyze every step, with the parameters at their defaults, and keep the verdicts.
+    pub fn annotate(&mut self) {
+        let defaults: IndexMap<String, String> = self
+            .params
+            .iter()
+            .filter_map(|v| Some((v.name.clone(), v.default.clone()?)))
+            .collect();
+        for step in self.steps.iter_mut() {
+            let analysis = CommandAnalysis::analyze(&fill(&step.command, &defaults));
+            step.level = Some(analysis.safety_level);
+            step.risk = Some(analysis.risk.score);
+            step.warnings = analysis.warnings;
+        }
+    }
+
+    /// The riskiest step's level and score.
+    pub fn verdict(&self) -> (SafetyLevel, u8) {
+        let level = self.steps.iter().filter_map(|v| v.level.clone()).max();
+        let risk = self.steps.iter().filter_map(|v| v.risk).max();
+        (level.unwrap_or(SafetyLevel::Safe), risk.unwrap_or_default())
+    }
+
+    /// The commands of the steps with the parameters filled in, shell-quoted.
+    pub fn fill(&self, values: &IndexMap<String, String>) -> Result<Vec<String>> {
+        for param in &self.params {
+            if !values.contains_key(&param.name) {
+                bail!("Missing value for parameter '{}'", param.name);
+            }
+        }
+        let values: IndexMap<String, String> = values
+            .iter()
+            .map(|(k, v)| (k.clone(), shell_words::quote(v).to_string()))
+            .collect();
+        Ok(self
+            .steps
+            .iter()
+            .map(|v| fill(&v.command, &values))
+            .collect())
+    }
+}
+
+impl RecipeStep {
+    /// A warning when the filled-in command is riskier than the step was when vetted.
+    pub fn drift(&self, analysis: &CommandAnalysis) -> Option<String> {
+        let (level, risk) = (self.level.as_ref()?, self.risk?);
+        if analysis.safety_level > *level {
+            return Some(format!(
+                "⚠️  Rated {:?} with these values, the recipe was vetted as {level:?}.",
+                analysis.safety_level
+            ));
+        }
+        if analysis.risk.score > risk {
+            return Some(format!(
+                "⚠️  Scores {} with these values, the recipe was vetted at {risk}/100.",
+                analysis.risk
+            ));
+        }
+        None
+    }
+}
+
+fn placeholders(command: &str) -> Vec<String> {
+    RE_VARIABLE
+        .captures_iter(command)
+        .flatten()
+        .map(|v| v[1].to_string())
+        .collect()
+}
+
+/// Replace the `{{name}}` of `values`, leaving others as they are.
+fn fill(command: &str, values: &IndexMap<String, String>) -> String {
+    RE_VARIABLE
+        .replace_all(command, |caps: &Captures<'_>| match values.get(&caps[1]) {
+            Some(value) => value.clone(),
+            None => caps[0].to_string(),
+        })
+        .to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_builtin_recipes() {
+        let recipes = RecipeBook::default().all();
+        assert!(!recipes.is_empty());
+        for recipe in recipes {
+            recipe.validate().unwrap();
+            assert!(recipe.steps.iter().all(|v| v.level.is_some()));
+        }
+    }
+
+    #[test]
+    fn test_fill_recipe() {
+        let mut recipe: Recipe = serde_yaml::from_str(
+            "name: clear-cache\nparams:\n  - name: app\nsteps:\n  - command: rm -rf ~/.cache/{{app}}\n",
+        )
+        .unwrap();
+        recipe.validate().unwrap();
+        recipe.annotate();
+        assert_eq!(recipe.verdict().0, SafetyLevel::Dangerous);
+
+        let values = IndexMap::from([("app".to_string(), "my app; rm -rf /".to_string())]);
+        assert_eq!(
+            recipe.fill(&values).unwrap(),
+            ["rm -rf ~/.cache/'my app; rm -rf /'"]
+        );
+        assert!(recipe.fill(&IndexMap::new()).is_err());
+
+        recipe.steps[0].command = "rm -rf {{dir}}".into();
+        assert!(recipe.validate().is_err());
+    }
+}
@@ -0,0 +1,218 @@
+use super::{session::Session, unseal, AuditDecision, AuditEntry, AuditLog, Config};
+
+use crate::client::{Message, MessageContent, MessageRole};
+use crate::utils::extract_shell_commands;
+
+use anyhow::{bail, Context, Result};
+use serde_json::Value;
+use std::fs;
+
+/// A past session as the turns and numbered command steps `--replay` walks through.
+#[derive(Debug, Default)]
+pub struct SessionReplay {
+    pub name: String,
+    pub turns: Vec<ReplayTurn>,
+}
+
+#[derive(Debug, Clone)]
+pub struct ReplayTurn {
+    pub role: MessageRole,
+    pub text: String,
+    pub steps: Vec<ReplayStep>,
+}
+
+/// A command of the session and what came of it back then.
+#[derive(Debug, Clone)]
+pub struct ReplayStep {
+    /// Counts from 1 across the session, for `--replay-exec`.
+    pub number: usize,
+    pub command: String,
+    pub recorded: RecordedRun,
+}
+
+#[derive(Debug, Clone)]
+pub enum RecordedRun {
+    /// The output a tool call returned to the model.
+    Output(String),
+    /// The audit entry of a suggested command that was executed.
+    Executed(Box<AuditEntry>),
+    /// Suggested, but the audit log has no execution of it.
+    NotExecuted,
+}
+
+impl RecordedRun {
+    pub fn stub(&self) -> String {
+        match self {
+            RecordedRun::Output(output) if output.trim().is_empty() => {
+                "(recorded: no output)".to_string()
+            }
+            RecordedRun::Output(output) => format!("(recorded output)\n{}", output.trim_end()),
+            RecordedRun::Executed(entry) => {
+                let exit = entry
+                    .exit_code
+                    .map(|v| format!(", exit {v}"))
+                    .unwrap_or_default();
+                let mut output =
+                    format!("(recorded: {}{exit} at {}", entry.decision, entry.timestamp);
+                if !entry.backups.is_empty() {
+                    output.push_str(&format!(", backup {}", entry.backups.join(", ")));
+                }
+                output.push(')');
+                output
+            }
+            RecordedRun::NotExecuted => "(recorded: not executed)".to_string(),
+        }
+    }
+}
+
+impl SessionReplay {
+    /// Reads the session file as it is, without the model it was held with.
+    pub fn load(config: &Config, name: &str) -> Result<Self> {
+        let path = config.session_file(name);
+        if !path.exists() {
+            bail!("No session '{name}'");
+        }
+        let content = fs::read_to_string(&path)
+            .map_err(anyhow::Error::from)
+            .and_then(|v| unseal(&v))
+            .with_context(|| format!("Failed to load session {name} at {}", path.display()))?;
+        let session: Session =
+            serde_yaml::from_str(&content).with_context(|| format!("Invalid session {name}"))?;
+        Ok(Self::from_messages(
+            name,
+            session.messages(),
+            &AuditLog::read()?,
+        ))
+    }
+
+    /// The turns of `messages`. Tool calls bring their output along, shell code blocks of
+    /// the assistant the latest execution of the same command in `entries`.
+    pub fn from_messages(name: &str, messages: &[Message], entries: &[AuditEntry]) -> Self {
+        let mut number = 0;
+        let mut step = |command: String, recorded: RecordedRun| {
+            number += 1;
+            ReplayStep {
+                number,
+                command,
+                recorded,
+            }
+        };
+        let mut turns = vec![];
+        for message in messages.iter().filter(|v| !v.role.is_system()) {
+            let turn = match &message.content {
+                MessageContent::ToolCalls(calls) => ReplayTurn {
+                    role: message.role,
+                    text: calls.text.clone(),
+                    steps: calls
+                        .tool_results
+                        .iter()
+                        .filter_map(|v| {
+                            let command = v.call.command()?;
+                            let output = match &v.output {
+                                Value::String(text) => text.clone(),
+                                Value::Null => String::new(),
+                                output => serde_json::to_string_pretty(output).unwrap_or_default(),
+                            };
+                            Some(step(command, RecordedRun::Output(output)))
+                        })
+                        .collect(),
+                },
+                content => {
+                    let text = content.to_text();
+                    let steps = match message.role.is_assistant() {
+                        true => extract_shell_commands(&text)
+                            .into_iter()
+                            .map(|command| {
+                                let executed = entries.iter().rev().find(|v| {
+                                    v.command == command.trim()
+                                        && matches!(
+                                            v.decision,
+                                            AuditDecision::Approved | AuditDecision::Trusted
+                                        )
+                                });
+                                let recorded = match executed {
+                                    Some(entry) => RecordedRun::Executed(Box::new(entry.clone())),
+                                    None => RecordedRun::NotExecuted,
+                                };
+                                step(command, recorded)
+                            })
+                            .collect(),
+                        false => vec![],
+                    };
+                    ReplayTurn {
+                        role: message.role,
+                        text,
+                        steps,
+                    }
+                }
+            };
+            turns.push(turn);
+        }
+        Self {
+            name: name.to_string(),
+            turns,
+        }
+    }
+
+    pub fn steps(&self) -> usize {
+        self.turns.iter().map(|v| v.steps.len()).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::client::MessageContentToolCalls;
+    use crate::function::{ToolCall, ToolResult};
+    use serde_json::json;
+
+    #[test]
+    fn test_session_replay() {
+        let call = ToolCall::new(
+            "execute_command".into(),
+            json!({"command": "df -h /var"}),
+            Some("call_1".into()),
+        );
+        let messages = vec![
+            Message::new(
+                MessageRole::User,
+                MessageContent::Text("the disk is full".into()),
+            ),
+            Message::new(
+                MessageRole::Assistant,
+                MessageContent::ToolCalls(MessageContentToolCalls::new(
+                    vec![ToolResult::new(call, json!("/dev/sda1  50G  50G  0 100% /var"))],
+                    String::new(),
+                )),
+            ),
+            Message::new(
+                MessageRole::Assistant,
+                MessageContent::Text(
+                    "Clear the journal:\n```sh\njournalctl --vacuum-size=1G\n```\nThen:\n```sh\nrm -rf /var/tmp/cache\n```".into(),
+                ),
+            ),
+        ];
+        let entries = vec![
+            AuditEntry::new("journalctl --vacuum-size=1G", AuditDecision::Approved)
+                .with_exit_code(0),
+        ];
+        let replay = SessionReplay::from_messages("incident", &messages, &entries);
+        assert_eq!(replay.turns.len(), 3);
+        assert_eq!(replay.steps(), 3);
+
+        let df = &replay.turns[1].steps[0];
+        assert_eq!((df.number, df.command.as_str()), (1, "df -h /var"));
+        assert_eq!(
+            df.recorded.stub(),
+            "(recorded output)\n/dev/sda1  50G  50G  0 100% /var"
+        );
+        let steps = &replay.turns[2].steps;
+        assert_eq!(steps[0].number, 2);
+        assert!(steps[0]
+            .recorded
+            .stub()
+            .starts_with("(recorded: approved, exit 0 at "));
+        assert_eq!(steps[1].command, "rm -rf /var/tmp/cache");
+        assert!(matches!(steps[1].recorded, RecordedRun::NotExecuted));
+    }
+}
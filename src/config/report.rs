@@ -0,0 +1,264 @@
+use super::{AuditDecision, AuditEntry, AuditLog};
+
+use crate::utils::{color_text, CommandAnalysis, SafetyLevel};
+
+use anyhow::Result;
+use chrono::DateTime;
+use serde::Serialize;
+use serde_json::{json, Value};
+use std::{fmt::Write, path::PathBuf, process};
+
+const LEVELS: [SafetyLevel; 4] = [
+    SafetyLevel::Critical,
+    SafetyLevel::Dangerous,
+    SafetyLevel::Caution,
+    SafetyLevel::Safe,
+];
+
+/// One command executed in the session.
+#[derive(Debug, Clone, Serialize)]
+pub struct ReportCommand {
+    pub timestamp: String,
+    pub command: String,
+    pub safety_level: SafetyLevel,
+    pub exit_code: Option<i32>,
+    pub files: Vec<PathBuf>,
+    pub backups: Vec<String>,
+}
+
+impl ReportCommand {
+    pub fn failed(&self) -> bool {
+        self.exit_code.is_some_and(|v| v != 0)
+    }
+
+    fn time(&self) -> String {
+        DateTime::parse_from_rfc3339(&self.timestamp)
+            .map(|v| v.format("%H:%M:%S").to_string())
+            .unwrap_or_else(|_| self.timestamp.clone())
+    }
+}
+
+/// `.report`: what the current REPL session executed, for pasting into a change ticket.
+#[derive(Debug, Default)]
+pub struct SessionReport {
+    /// Most dangerous first, then in execution order.
+    pub commands: Vec<ReportCommand>,
+}
+
+impl SessionReport {
+    /// The commands this aichat process executed, according to the audit log.
+    pub fn collect() -> Result<Self> {
+        let pid = process::id();
+        let entries: Vec<_> = AuditLog::read()?
+            .into_iter()
+            .filter(|v| v.pid == Some(pid))
+            .collect();
+        Ok(Self::from_entries(&entries))
+    }
+
+    pub fn from_entries(entries: &[AuditEntry]) -> Self {
+        let mut commands: Vec<_> = entries
+            .iter()
+            .filter(|v| matches!(v.decision, AuditDecision::Approved | AuditDecision::Trusted))
+            .map(|v| ReportCommand {
+                timestamp: v.timestamp.clone(),
+                command: v.command.clone(),
+                safety_level: v.safety_level.clone().unwrap_or(SafetyLevel::Safe),
+                exit_code: v.exit_code,
+                files: CommandAnalysis::analyze(&v.command).affected_files,
+                backups: v.backups.clone(),
+            })
+            .collect();
+        commands.sort_by(|a, b| b.safety_level.cmp(&a.safety_level));
+        Self { commands }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.commands.is_empty()
+    }
+
+    pub fn count(&self, level: &SafetyLevel) -> usize {
+        self.commands
+            .iter()
+            .filter(|v| v.safety_level == *level)
+            .count()
+    }
+
+    pub fn files_touched(&self) -> Vec<PathBuf> {
+        let mut files: Vec<PathBuf> = vec![];
+        for file in self.commands.iter().flat_map(|v| &v.files) {
+            if !files.contains(file) {
+                files.push(file.clone());
+            }
+        }
+        files
+    }
+
+    pub fn backups(&self) -> Vec<&str> {
+        self.commands
+            .iter()
+            .flat_map(|v| v.backups.iter().map(|v| v.as_str()))
+            .collect()
+    }
+
+    pub fn failures(&self) -> usize {
+        self.commands.iter().filter(|v| v.failed()).count()
+    }
+
+    fn summary(&self) -> String {
+        let levels: Vec<_> = LEVELS
+            .iter()
+            .map(|v| format!("{} {}", self.count(v), v.label().to_lowercase()))
+            .collect();
+        format!(
+            "{} commands ({}), {} files touched, {} backups, {} failures",
+            self.commands.len(),
+            levels.join(", "),
+            self.files_touched().len(),
+            self.backups().len(),
+            self.failures()
+        )
+    }
+
+    /// An aligned table for the terminal.
+    pub fn table(&self) -> String {
+        let mut output = format!("{}\n", self.summary());
+        if self.is_empty() {
+            return output;
+        }
+        let width = self
+            .commands
+            .iter()
+            .map(|v| v.command.chars().count())
+            .max()
+            .unwrap_or_default()
+            .min(60);
+        let _ = writeln!(
+            output,
+            "\n{:<10} {:<8} {:<width$} {:>4} {:>5} {:>7}",
+            "LEVEL", "TIME", "COMMAND", "EXIT", "FILES", "BACKUPS"
+        );
+        for command in &self.commands {
+            let level = format!("{:<10}", command.safety_level.label());
+            let _ = writeln!(
+                output,
+                "{} {:<8} {:<width$} {:>4} {:>5} {:>7}",
+                color_text(&level, command.safety_level.color()),
+                command.time(),
+                truncate(&command.command, width),
+                exit_text(command.exit_code),
+                command.files.len(),
+                command.backups.len(),
+            );
+        }
+        output
+    }
+
+    pub fn to_markdown(&self) -> String {
+        let mut output = format!("## Session report\n\n{}\n", self.summary());
+        for level in &LEVELS {
+            let commands: Vec<_> = self
+                .commands
+                .iter()
+                .filter(|v| v.safety_level == *level)
+                .collect();
+            if commands.is_empty() {
+                continue;
+            }
+            let _ = write!(
+                output,
+                "\n### {} ({})\n\n| Time | Command | Exit | Files | Backups |\n|------|---------|------|-------|---------|\n",
+                level.label(),
+                commands.len()
+            );
+            for command in commands {
+                let files: Vec<_> = command
+                    .files
+                    .iter()
+                    .map(|v| format!("`{}`", v.display()))
+                    .collect();
+                let _ = writeln!(
+                    output,
+                    "| {} | `{}` | {} | {} | {} |",
+                    command.time(),
+                    command.command.replace('|', "\\|"),
+                    exit_text(command.exit_code),
+                    files.join(", "),
+                    command.backups.join(", "),
+                );
+            }
+        }
+        output
+    }
+
+    pub fn to_json(&self) -> Value {
+        let levels: serde_json::Map<String, Value> = LEVELS
+            .iter()
+            .map(|v| (v.label().to_lowercase(), json!(self.count(v))))
+            .collect();
+        json!({
+            "summary": {
+                "commands": self.commands.len(),
+                "by_safety_level": levels,
+                "files_touched": self.files_touched(),
+                "backups": self.backups(),
+                "failures": self.failures(),
+            },
+            "commands": self.commands,
+        })
+    }
+}
+
+fn exit_text(code: Option<i32>) -> String {
+    code.map(|v| v.to_string()).unwrap_or_else(|| "-".into())
+}
+
+fn truncate(text: &str, width: usize) -> String {
+    match text.char_indices().nth(width) {
+        Some((i, _)) if width > 3 => {
+            let (end, _) = text.char_indices().nth(width - 3).unwrap_or((i, ' '));
+            format!("{}...", &text[..end])
+        }
+        _ => text.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_session_report() {
+        let entries = vec![
+            AuditEntry::new("ls -la", AuditDecision::Approved).with_exit_code(0),
+            AuditEntry::new("rm -rf build | tee log", AuditDecision::Approved)
+                .with_safety_level(SafetyLevel::Dangerous)
+                .with_exit_code(1)
+                .with_backups(vec!["20261016_120000".into()]),
+            AuditEntry::new("sudo reboot", AuditDecision::Denied)
+                .with_safety_level(SafetyLevel::Critical),
+        ];
+        let report = SessionReport::from_entries(&entries);
+        assert_eq!(report.commands.len(), 2);
+        assert_eq!(report.commands[0].command, "rm -rf build | tee log");
+        assert_eq!(report.count(&SafetyLevel::Critical), 0);
+        assert_eq!(report.failures(), 1);
+        assert_eq!(report.backups(), ["20261016_120000"]);
+
+        let markdown = report.to_markdown();
+        assert!(markdown.contains("### DANGEROUS (1)"));
+        assert!(markdown.contains(r"`rm -rf build \| tee log`"));
+        assert!(!markdown.contains("### CRITICAL"));
+
+        let json = report.to_json();
+        assert_eq!(json["summary"]["by_safety_level"]["dangerous"], 1);
+        assert_eq!(json["summary"]["failures"], 1);
+        assert_eq!(json["commands"][1]["command"], "ls -la");
+    }
+
+    #[test]
+    fn test_truncate() {
+        assert_eq!(truncate("kubectl get pods", 10), "kubectl...");
+        assert_eq!(truncate("ls", 10), "ls");
+    }
+}
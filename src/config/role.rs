@@ -13,7 +13,11 @@ pub const SHELL_ROLE: &str = "%shell%";
 #[allow(dead_code)]
 pub const EXPLAIN_SHELL_ROLE: &str = "%explain-shell%";
 pub const CODE_ROLE: &str = "%code%";
+pub const PATCH_ROLE: &str = "%patch%";
 pub const CREATE_TITLE_ROLE: &str = "%create-title%";
+pub const PLANNER_ROLE: &str = "%planner%";
+pub const REVIEWER_ROLE: &str = "%reviewer%";
+pub const SUPERVISOR_ROLE: &str = "%supervisor%";
 
 pub const INPUT_PLACEHOLDER: &str = "__INPUT__";
 
@@ -207,7 +211,7 @@ impl Role {
     pub fn set_prompt(&mut self, p: String) {
         self.prompt = p;
     }
-    
+
     pub fn is_empty_prompt(&self) -> bool {
         self.prompt.is_empty()
     }
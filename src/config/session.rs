@@ -30,6 +30,10 @@ pub struct Session {
     save_session: Option<bool>,
     #[serde(skip_serializing_if = "Option::is_none")]
     compress_threshold: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    blocked_commands: Option<Vec<String>>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    always_allowed_commands: Option<Vec<String>>,
 
     #[serde(skip_serializing_if = "Option::is_none")]
     role_name: Option<String>,
@@ -79,6 +83,8 @@ impl Session {
 
     pub fn load(config: &Config, name: &str, path: &Path) -> Result<Self> {
         let content = read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|v| unseal(&v))
             .with_context(|| format!("Failed to load session {} at {}", name, path.display()))?;
         let mut session: Self =
             serde_yaml::from_str(&content).with_context(|| format!("Invalid session {name}"))?;
@@ -127,6 +133,14 @@ impl Session {
         self.save_session
     }
 
+    pub fn blocked_commands(&self) -> Option<&Vec<String>> {
+        self.blocked_commands.as_ref()
+    }
+
+    pub fn always_allowed_commands(&self) -> Option<&Vec<String>> {
+        self.always_allowed_commands.as_ref()
+    }
+
     pub fn tokens(&self) -> usize {
         self.tokens
     }
@@ -143,6 +157,10 @@ impl Session {
         self.messages.iter().filter(|v| v.role.is_user()).count()
     }
 
+    pub fn messages(&self) -> &[Message] {
+        &self.messages
+    }
+
     pub fn export(&self) -> Result<String> {
         let mut data = json!({
             "path": self.path,
@@ -211,6 +229,14 @@ impl Session {
             items.push(("compress_threshold", compress_threshold.to_string()));
         }
 
+        if let Some(blocked_commands) = &self.blocked_commands {
+            items.push(("blocked_commands", blocked_commands.join(",")));
+        }
+
+        if let Some(always_allowed_commands) = &self.always_allowed_commands {
+            items.push(("always_allowed_commands", always_allowed_commands.join(",")));
+        }
+
         if let Some(max_input_tokens) = self.model().max_input_tokens() {
             items.push(("max_input_tokens", max_input_tokens.to_string()));
         }
@@ -306,6 +332,20 @@ impl Session {
         }
     }
 
+    pub fn set_blocked_commands(&mut self, value: Option<Vec<String>>) {
+        if self.blocked_commands != value {
+            self.blocked_commands = value;
+            self.dirty = true;
+        }
+    }
+
+    pub fn set_always_allowed_commands(&mut self, value: Option<Vec<String>>) {
+        if self.always_allowed_commands != value {
+            self.always_allowed_commands = value;
+            self.dirty = true;
+        }
+    }
+
     pub fn set_save_session_this_time(&mut self) {
         self.save_session_this_time = true;
     }
@@ -438,7 +478,7 @@ impl Session {
 
         let content = serde_yaml::to_string(&self)
             .with_context(|| format!("Failed to serde session '{}'", self.name))?;
-        write(session_path, content).with_context(|| {
+        write(session_path, seal(&content)?).with_context(|| {
             format!(
                 "Failed to write session '{}' to '{}'",
                 self.name,
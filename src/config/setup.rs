@@ -0,0 +1,270 @@
+use super::*;
+
+use anyhow::{Context, Result};
+use inquire::{Confirm, Select};
+use serde_json::Value;
+use std::{fmt, fs, path::Path};
+
+/// How much the safety net gets in the way, picked in `aichat --init`.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SafetyPreset {
+    Careful,
+    Balanced,
+    Expert,
+}
+
+impl fmt::Display for SafetyPreset {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            SafetyPreset::Careful => {
+                "Careful (recommended if you are learning the shell): explain every generated script, stop the AI after 20 commands"
+            }
+            SafetyPreset::Balanced => {
+                "Balanced: stop the AI after 50 commands, offer to auto-approve commands you keep approving"
+            }
+            SafetyPreset::Expert => "Expert: no limits on the AI, still asks before risky commands",
+        };
+        write!(f, "{text}")
+    }
+}
+
+impl SafetyPreset {
+    pub const ALL: [SafetyPreset; 3] = [Self::Careful, Self::Balanced, Self::Expert];
+
+    /// `budget.max_commands` and `budget.max_risk_score`.
+    fn budget(&self) -> (Option<usize>, Option<u32>) {
+        match self {
+            SafetyPreset::Careful => (Some(20), Some(150)),
+            SafetyPreset::Balanced => (Some(50), Some(300)),
+            SafetyPreset::Expert => (None, None),
+        }
+    }
+}
+
+/// Where the API key goes.
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum KeyStorage {
+    Keychain,
+    EnvFile,
+    Shell,
+    ConfigFile,
+}
+
+impl fmt::Display for KeyStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let text = match self {
+            KeyStorage::Keychain => "The system keychain (most secure)",
+            KeyStorage::EnvFile => "A private .env file next to the config",
+            KeyStorage::Shell => "I'll set the environment variable myself",
+            KeyStorage::ConfigFile => "In the config file",
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// The interactive setup behind `aichat --init` and the first run: detect the machine,
+/// pick a provider, store its API key, choose the safety settings, then write a commented
+/// config file.
+pub async fn run_setup_wizard(config_path: &Path) -> Result<()> {
+    let env = EnvProfile::detect();
+    println!(
+        "Detected {} with {} and the {} package manager, commands will be written for them.\n",
+        env.os, env.shell, env.pkg
+    );
+
+    let client = Select::new("API Provider (required):", list_client_types()).prompt()?;
+    let (model, mut clients) = create_client_config(client).await?;
+    store_api_key(&mut clients)?;
+
+    println!(
+        "\nAIChat asks before running any command that changes files, and explains why it is risky.\nThe safety level decides what else it does to protect you."
+    );
+    let preset = Select::new("Safety level:", SafetyPreset::ALL.to_vec()).prompt()?;
+    let accessible = Confirm::new("Do you use a screen reader?")
+        .with_default(false)
+        .with_help_message("Text labels instead of emoji, colors and spinners")
+        .prompt()?;
+
+    let config_data = render_config(&model, &clients, preset, accessible)?;
+    ensure_parent_exists(config_path)?;
+    if config_path.exists() {
+        let backup = config_path.with_extension("yaml.bak");
+        fs::copy(config_path, &backup)?;
+        println!("✓ Saved the previous config to '{}'.", backup.display());
+    }
+    fs::write(config_path, config_data)
+        .with_context(|| format!("Failed to write to '{}'", config_path.display()))?;
+    set_private(config_path)?;
+
+    println!("✓ Saved the config file to '{}'.\n", config_path.display());
+    Ok(())
+}
+
+/// Move the API key the provider prompts asked for out of the config, if the user wants it.
+fn store_api_key(clients: &mut Value) -> Result<()> {
+    let Some(client) = clients.get_mut(0) else {
+        return Ok(());
+    };
+    let name = client["name"]
+        .as_str()
+        .or_else(|| client["type"].as_str())
+        .unwrap_or_default();
+    let env_name = format!("{name}_api_key").to_ascii_uppercase();
+    let Some(api_key) = client["api_key"].as_str().map(|v| v.to_string()) else {
+        if env::var(&env_name).is_ok() {
+            println!("✓ Using the API key in ${env_name}.");
+        }
+        return Ok(());
+    };
+
+    let mut options = vec![
+        KeyStorage::EnvFile,
+        KeyStorage::Shell,
+        KeyStorage::ConfigFile,
+    ];
+    if keychain_available() {
+        options.insert(0, KeyStorage::Keychain);
+    }
+    let storage = Select::new("Where should the API key be kept?", options)
+        .with_help_message("Keys in files can leak through backups or screen sharing")
+        .prompt()?;
+    let env_file = Config::env_file();
+    match storage {
+        KeyStorage::Keychain => {
+            keychain_store(&env_name, &api_key)?;
+            write_env_value(
+                &env_file,
+                &env_name,
+                &format!("{KEYCHAIN_PREFIX}{env_name}"),
+            )?;
+            println!("✓ Stored the API key in the keychain.");
+        }
+        KeyStorage::EnvFile => {
+            write_env_value(&env_file, &env_name, &api_key)?;
+            println!("✓ Stored the API key in '{}'.", env_file.display());
+        }
+        KeyStorage::Shell => {
+            println!("Add `export {env_name}=...` to your shell profile before using AIChat.");
+        }
+        KeyStorage::ConfigFile => return Ok(()),
+    }
+    if let Some(client) = client.as_object_mut() {
+        client.remove("api_key");
+    }
+    Ok(())
+}
+
+/// Set `key` in an env file, keeping its other lines.
+fn write_env_value(path: &Path, key: &str, value: &str) -> Result<()> {
+    let content = fs::read_to_string(path).unwrap_or_default();
+    ensure_parent_exists(path)?;
+    fs::write(path, upsert_env_line(&content, key, value))
+        .with_context(|| format!("Failed to write to '{}'", path.display()))?;
+    set_private(path)
+}
+
+fn upsert_env_line(content: &str, key: &str, value: &str) -> String {
+    let line = format!("{key}={value}");
+    let mut found = false;
+    let mut lines: Vec<String> = content
+        .lines()
+        .map(|v| match v.split_once('=') {
+            Some((k, _)) if k.trim() == key => {
+                found = true;
+                line.clone()
+            }
+            _ => v.to_string(),
+        })
+        .collect();
+    if !found {
+        lines.push(line);
+    }
+    lines.join("\n") + "\n"
+}
+
+#[cfg(unix)]
+fn set_private(path: &Path) -> Result<()> {
+    use std::os::unix::prelude::PermissionsExt;
+    fs::set_permissions(path, fs::Permissions::from_mode(0o600))?;
+    Ok(())
+}
+
+#[cfg(not(unix))]
+fn set_private(_path: &Path) -> Result<()> {
+    Ok(())
+}
+
+fn render_config(
+    model: &str,
+    clients: &Value,
+    preset: SafetyPreset,
+    accessible: bool,
+) -> Result<String> {
+    let yaml = |v: Option<String>| v.unwrap_or_else(|| "null".into());
+    let (max_commands, max_risk_score) = preset.budget();
+    let annotate_scripts = preset == SafetyPreset::Careful;
+    let suggest_rules = preset != SafetyPreset::Careful;
+    let clients = serde_yaml::to_string(&json!({ CLIENTS_FIELD: clients }))
+        .with_context(|| "Failed to create config")?;
+    Ok(format!(
+        r#"# Created by `aichat --init`, run it again to start over.
+# see https://github.com/sigoden/aichat/blob/main/config.example.yaml for every setting
+
+model: {model}
+
+# ---- safety ----
+# When the AI runs commands on its own (tools, agents), it stops and asks you to continue
+# after this many commands, or once their risk adds up (caution 1, dangerous 3, critical 10).
+budget:
+  max_commands: {max_commands}
+  max_risk_score: {max_risk_score}
+
+# Print the tutor's explanation above each command of a generated script.
+annotate_scripts: {annotate_scripts}
+
+# After you approve the same command 5 times, offer to always allow it.
+suggest_rules: {suggest_rules}
+
+# Hosts commands must never reach, e.g. ['pastebin.com']
+network_denylist: []
+
+# ---- appearance ----
+# Screen-reader-friendly output: text labels instead of emoji, colors and spinners.
+accessible: {accessible}
+
+{clients}"#,
+        model = serde_json::to_string(model)?,
+        max_commands = yaml(max_commands.map(|v| v.to_string())),
+        max_risk_score = yaml(max_risk_score.map(|v| v.to_string())),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_render_config() {
+        let clients = json!([{ "type": "openai" }]);
+        let data = render_config("openai:gpt-4o", &clients, SafetyPreset::Careful, false).unwrap();
+        let config: Config = serde_yaml::from_str(&data).unwrap();
+        assert_eq!(config.model_id, "openai:gpt-4o");
+        assert_eq!(config.budget.max_commands, Some(20));
+        assert!(config.annotate_scripts);
+        assert_eq!(config.clients.len(), 1);
+
+        let data = render_config("openai:gpt-4o", &clients, SafetyPreset::Expert, true).unwrap();
+        let config: Config = serde_yaml::from_str(&data).unwrap();
+        assert!(config.budget.is_empty());
+        assert!(config.accessible && config.suggest_rules);
+    }
+
+    #[test]
+    fn test_upsert_env_line() {
+        assert_eq!(upsert_env_line("", "A_KEY", "1"), "A_KEY=1\n");
+        assert_eq!(
+            upsert_env_line("# keys\nA_KEY=old\nB=2\n", "A_KEY", "keychain:A_KEY"),
+            "# keys\nA_KEY=keychain:A_KEY\nB=2\n"
+        );
+    }
+}
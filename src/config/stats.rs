@@ -0,0 +1,504 @@
+use super::{ensure_parent_exists, token_cost, AuditDecision, AuditEntry, AuditLog, Config};
+
+use crate::client::Model;
+use crate::utils::{
+    color_text, format_bytes, BackupEntry, BackupManager, FileSystem, RealFs, SafetyLevel,
+};
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Duration, Local, NaiveDate};
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::BTreeMap,
+    fmt::Write as _,
+    fs::{self, OpenOptions},
+    io::Write,
+    path::PathBuf,
+};
+
+const USAGE_FILE_NAME: &str = "usage.jsonl";
+
+/// Days covered by `--stats`, ending today.
+const STATS_DAYS: i64 = 30;
+
+const BAR_WIDTH: usize = 20;
+
+const LEVELS: [SafetyLevel; 4] = [
+    SafetyLevel::Safe,
+    SafetyLevel::Caution,
+    SafetyLevel::Dangerous,
+    SafetyLevel::Critical,
+];
+
+/// Tokens spent by one LLM call.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct UsageEntry {
+    pub timestamp: String,
+    pub model: String,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+}
+
+impl UsageEntry {
+    pub fn new(model: &Model, input_tokens: u64, output_tokens: u64) -> Self {
+        Self {
+            timestamp: Local::now().to_rfc3339(),
+            model: model.id(),
+            input_tokens,
+            output_tokens,
+            cost: token_cost(model, input_tokens, output_tokens),
+        }
+    }
+}
+
+/// Append-only JSON lines log of the tokens every LLM call spent, kept for `--stats`.
+/// Like the audit log it never leaves the machine.
+pub struct UsageLog;
+
+impl UsageLog {
+    pub fn path() -> PathBuf {
+        Config::local_path(USAGE_FILE_NAME)
+    }
+
+    pub fn append(entry: &UsageEntry) -> Result<()> {
+        let path = Self::path();
+        ensure_parent_exists(&path)?;
+        let mut file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .with_context(|| format!("Failed to open '{}'", path.display()))?;
+        writeln!(file, "{}", serde_json::to_string(entry)?)?;
+        Ok(())
+    }
+
+    pub fn read() -> Result<Vec<UsageEntry>> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(vec![]);
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        Ok(content
+            .lines()
+            .filter_map(|v| serde_json::from_str(v).ok())
+            .collect())
+    }
+}
+
+/// Commands of one day, from the audit log.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DayStats {
+    pub date: NaiveDate,
+    pub analyzed: usize,
+    pub executed: usize,
+    /// Analyzed commands per safety level.
+    pub levels: BTreeMap<SafetyLevel, usize>,
+}
+
+impl DayStats {
+    pub fn count(&self, level: &SafetyLevel) -> usize {
+        self.levels.get(level).copied().unwrap_or_default()
+    }
+}
+
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModelStats {
+    pub model: String,
+    pub requests: usize,
+    pub input_tokens: u64,
+    pub output_tokens: u64,
+    pub cost: f64,
+}
+
+/// Backups taken on one day, and the size of all backups up to then.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct BackupDay {
+    pub date: NaiveDate,
+    pub backups: usize,
+    pub bytes: u64,
+    pub total_bytes: u64,
+}
+
+/// `--stats`: a dashboard of the local logs, the audit log, the usage log and the backup
+/// index. Nothing is sent anywhere.
+#[derive(Debug, Default)]
+pub struct Stats {
+    pub from: NaiveDate,
+    /// Only days with commands, oldest first.
+    pub days: Vec<DayStats>,
+    /// Most requests first.
+    pub models: Vec<ModelStats>,
+    /// Only days with backups, oldest first.
+    pub backups: Vec<BackupDay>,
+}
+
+impl Stats {
+    /// The last [`STATS_DAYS`] days.
+    pub fn collect() -> Result<Self> {
+        let from = Local::now().date_naive() - Duration::days(STATS_DAYS - 1);
+        let backups: Vec<_> = BackupManager::new()?
+            .list_backups()?
+            .iter()
+            .map(|v| (v.timestamp.clone(), backup_size(v)))
+            .collect();
+        Ok(Self::from_logs(
+            &AuditLog::read()?,
+            &UsageLog::read()?,
+            &backups,
+            from,
+        ))
+    }
+
+    /// `backups` are `(timestamp, bytes)`.
+    pub fn from_logs(
+        entries: &[AuditEntry],
+        usage: &[UsageEntry],
+        backups: &[(String, u64)],
+        from: NaiveDate,
+    ) -> Self {
+        let mut days: BTreeMap<NaiveDate, DayStats> = BTreeMap::new();
+        for entry in entries {
+            let Some(date) = local_date(&entry.timestamp).filter(|v| *v >= from) else {
+                continue;
+            };
+            if entry.decision == AuditDecision::Noted {
+                continue;
+            }
+            let day = days.entry(date).or_insert_with(|| DayStats {
+                date,
+                ..Default::default()
+            });
+            day.analyzed += 1;
+            if matches!(
+                entry.decision,
+                AuditDecision::Approved | AuditDecision::Trusted
+            ) {
+                day.executed += 1;
+            }
+            let level = entry.safety_level.clone().unwrap_or(SafetyLevel::Safe);
+            *day.levels.entry(level).or_default() += 1;
+        }
+
+        let mut models: BTreeMap<&str, ModelStats> = BTreeMap::new();
+        for entry in usage {
+            if local_date(&entry.timestamp).is_none_or(|v| v < from) {
+                continue;
+            }
+            let stats = models.entry(&entry.model).or_insert_with(|| ModelStats {
+                model: entry.model.clone(),
+                ..Default::default()
+            });
+            stats.requests += 1;
+            stats.input_tokens += entry.input_tokens;
+            stats.output_tokens += entry.output_tokens;
+            stats.cost += entry.cost;
+        }
+        let mut models: Vec<_> = models.into_values().collect();
+        models.sort_by_key(|v| std::cmp::Reverse(v.requests));
+
+        let mut total_bytes = 0;
+        let mut backup_days: BTreeMap<NaiveDate, BackupDay> = BTreeMap::new();
+        let mut backups: Vec<_> = backups
+            .iter()
+            .filter_map(|(timestamp, bytes)| Some((local_date(timestamp)?, *bytes)))
+            .collect();
+        backups.sort();
+        for (date, bytes) in backups {
+            total_bytes += bytes;
+            if date < from {
+                continue;
+            }
+            let day = backup_days.entry(date).or_insert_with(|| BackupDay {
+                date,
+                ..Default::default()
+            });
+            day.backups += 1;
+            day.bytes += bytes;
+            day.total_bytes = total_bytes;
+        }
+
+        Self {
+            from,
+            days: days.into_values().collect(),
+            models,
+            backups: backup_days.into_values().collect(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.days.is_empty() && self.models.is_empty() && self.backups.is_empty()
+    }
+
+    fn summary(&self) -> String {
+        let analyzed: usize = self.days.iter().map(|v| v.analyzed).sum();
+        let executed: usize = self.days.iter().map(|v| v.executed).sum();
+        let tokens: u64 = self
+            .models
+            .iter()
+            .map(|v| v.input_tokens + v.output_tokens)
+            .sum();
+        let cost = self.models.iter().fold(0.0, |sum, v| sum + v.cost);
+        let storage = self
+            .backups
+            .last()
+            .map(|v| v.total_bytes)
+            .unwrap_or_default();
+        format!(
+            "Since {}: {analyzed} commands analyzed, {executed} executed, {tokens} tokens, cost {cost:.4}, {} of backups",
+            self.from,
+            format_bytes(storage)
+        )
+    }
+
+    /// Aligned tables for the terminal.
+    pub fn table(&self) -> String {
+        let mut output = format!("{}\n", self.summary());
+        if !self.days.is_empty() {
+            let max = self.days.iter().map(|v| v.analyzed).max().unwrap_or(1);
+            let _ = writeln!(
+                output,
+                "\n{:<10} {:>8} {:>8} {:>6} {:>7} {:>9} {:>8}",
+                "DATE", "ANALYZED", "EXECUTED", "SAFE", "CAUTION", "DANGEROUS", "CRITICAL"
+            );
+            for day in &self.days {
+                let _ = writeln!(
+                    output,
+                    "{:<10} {:>8} {:>8} {:>6} {:>7} {:>9} {:>8}  {}",
+                    day.date.to_string(),
+                    day.analyzed,
+                    day.executed,
+                    day.count(&SafetyLevel::Safe),
+                    day.count(&SafetyLevel::Caution),
+                    day.count(&SafetyLevel::Dangerous),
+                    day.count(&SafetyLevel::Critical),
+                    bar(day, max),
+                );
+            }
+        }
+        if !self.models.is_empty() {
+            let width = self
+                .models
+                .iter()
+                .map(|v| v.model.chars().count())
+                .max()
+                .unwrap_or_default()
+                .max(5);
+            let _ = writeln!(
+                output,
+                "\n{:<width$} {:>8} {:>12} {:>12} {:>10}",
+                "MODEL", "REQUESTS", "INPUT", "OUTPUT", "COST"
+            );
+            for model in &self.models {
+                let _ = writeln!(
+                    output,
+                    "{:<width$} {:>8} {:>12} {:>12} {:>10.4}",
+                    model.model,
+                    model.requests,
+                    model.input_tokens,
+                    model.output_tokens,
+                    model.cost
+                );
+            }
+        }
+        if !self.backups.is_empty() {
+            let _ = writeln!(
+                output,
+                "\n{:<10} {:>7} {:>10} {:>10}",
+                "DATE", "BACKUPS", "SIZE", "TOTAL"
+            );
+            for day in &self.backups {
+                let _ = writeln!(
+                    output,
+                    "{:<10} {:>7} {:>10} {:>10}",
+                    day.date.to_string(),
+                    day.backups,
+                    format_bytes(day.bytes),
+                    format_bytes(day.total_bytes)
+                );
+            }
+        }
+        output
+    }
+
+    /// A self-contained page, no scripts or remote assets.
+    pub fn to_html(&self) -> String {
+        let max = self.days.iter().map(|v| v.analyzed).max().unwrap_or(1);
+        let mut days = String::new();
+        for day in &self.days {
+            let bars: String = LEVELS
+                .iter()
+                .rev()
+                .map(|level| {
+                    format!(
+                        r#"<span class="{}" style="width:{}%"></span>"#,
+                        level.label().to_lowercase(),
+                        day.count(level) * 100 / max
+                    )
+                })
+                .collect();
+            let _ = write!(
+                days,
+                "<tr><td>{}</td><td>{}</td><td>{}</td>{}<td class=\"bar\">{bars}</td></tr>",
+                day.date,
+                day.analyzed,
+                day.executed,
+                LEVELS
+                    .iter()
+                    .map(|v| format!("<td>{}</td>", day.count(v)))
+                    .collect::<String>(),
+            );
+        }
+        let mut models = String::new();
+        for model in &self.models {
+            let _ = write!(
+                models,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{:.4}</td></tr>",
+                escape_html(&model.model),
+                model.requests,
+                model.input_tokens,
+                model.output_tokens,
+                model.cost
+            );
+        }
+        let mut backups = String::new();
+        for day in &self.backups {
+            let _ = write!(
+                backups,
+                "<tr><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                day.date,
+                day.backups,
+                format_bytes(day.bytes),
+                format_bytes(day.total_bytes)
+            );
+        }
+        format!(
+            r#"<!DOCTYPE html>
+<html>
+<head>
+<meta charset="utf-8">
+<title>aichat stats</title>
+<style>
+body {{ font-family: sans-serif; margin: 2em; }}
+table {{ border-collapse: collapse; margin-bottom: 2em; }}
+th, td {{ padding: 4px 10px; text-align: right; border-bottom: 1px solid #ddd; }}
+th:first-child, td:first-child {{ text-align: left; }}
+td.bar {{ width: 300px; text-align: left; }}
+td.bar span {{ display: inline-block; height: 12px; }}
+.safe {{ background: #4caf50; }} .caution {{ background: #ffc107; }}
+.dangerous {{ background: #ff5722; }} .critical {{ background: #b71c1c; }}
+</style>
+</head>
+<body>
+<h1>aichat stats</h1>
+<p>{}</p>
+<h2>Commands</h2>
+<table><tr><th>Date</th><th>Analyzed</th><th>Executed</th><th>Safe</th><th>Caution</th><th>Dangerous</th><th>Critical</th><th></th></tr>{days}</table>
+<h2>Models</h2>
+<table><tr><th>Model</th><th>Requests</th><th>Input tokens</th><th>Output tokens</th><th>Cost</th></tr>{models}</table>
+<h2>Backups</h2>
+<table><tr><th>Date</th><th>Backups</th><th>Size</th><th>Total</th></tr>{backups}</table>
+</body>
+</html>
+"#,
+            escape_html(&self.summary())
+        )
+    }
+}
+
+fn local_date(timestamp: &str) -> Option<NaiveDate> {
+    DateTime::parse_from_rfc3339(timestamp)
+        .ok()
+        .map(|v| v.with_timezone(&Local).date_naive())
+}
+
+fn backup_size(entry: &BackupEntry) -> u64 {
+    let paths: Vec<_> = entry.files.iter().map(|v| v.backup_path.clone()).collect();
+    RealFs.estimate_size(&paths).bytes
+}
+
+/// The analyzed commands of a day scaled to [`BAR_WIDTH`], colored by safety level.
+fn bar(day: &DayStats, max: usize) -> String {
+    LEVELS
+        .iter()
+        .rev()
+        .map(|level| {
+            let width = (day.count(level) * BAR_WIDTH).div_ceil(max.max(1));
+            color_text(&"█".repeat(width), level.color())
+        })
+        .collect()
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_stats() {
+        let at = |date: &str| format!("{date}T12:00:00{}", Local::now().format("%:z"));
+        let mut entries = vec![
+            AuditEntry::new("ls", AuditDecision::Approved),
+            AuditEntry::new("rm -rf build", AuditDecision::Denied)
+                .with_safety_level(SafetyLevel::Dangerous),
+            AuditEntry::new("sudo reboot", AuditDecision::Trusted)
+                .with_safety_level(SafetyLevel::Critical),
+            AuditEntry::new("old", AuditDecision::Approved),
+        ];
+        for (entry, date) in
+            entries
+                .iter_mut()
+                .zip(["2026-10-15", "2026-10-15", "2026-10-16", "2026-09-01"])
+        {
+            entry.timestamp = at(date);
+        }
+        let usage = |model: &str, input_tokens, cost| UsageEntry {
+            timestamp: at("2026-10-16"),
+            model: model.into(),
+            input_tokens,
+            output_tokens: 10,
+            cost,
+        };
+        let usage = vec![
+            usage("openai:gpt-4o", 100, 0.5),
+            usage("ollama:llama3", 50, 0.0),
+            usage("ollama:llama3", 50, 0.0),
+        ];
+        let backups = vec![
+            (at("2026-09-01"), 1000),
+            (at("2026-10-16"), 24),
+            (at("2026-10-16"), 1024),
+        ];
+        let from = NaiveDate::from_ymd_opt(2026, 10, 1).unwrap();
+        let stats = Stats::from_logs(&entries, &usage, &backups, from);
+
+        assert_eq!(stats.days.len(), 2);
+        assert_eq!(stats.days[0].analyzed, 2);
+        assert_eq!(stats.days[0].executed, 1);
+        assert_eq!(stats.days[1].count(&SafetyLevel::Critical), 1);
+        assert_eq!(stats.models[0].model, "ollama:llama3");
+        assert_eq!(stats.models[0].input_tokens, 100);
+        assert_eq!(
+            stats.backups,
+            [BackupDay {
+                date: NaiveDate::from_ymd_opt(2026, 10, 16).unwrap(),
+                backups: 2,
+                bytes: 1048,
+                total_bytes: 2048,
+            }]
+        );
+        assert_eq!(
+            stats.summary(),
+            "Since 2026-10-01: 3 commands analyzed, 2 executed, 230 tokens, cost 0.5000, 2.0 KB of backups"
+        );
+        assert!(stats.to_html().contains("<td>openai:gpt-4o</td>"));
+        assert_eq!(escape_html("<a&b>"), "&lt;a&amp;b&gt;");
+    }
+}
@@ -0,0 +1,155 @@
+use super::*;
+
+use anyhow::{Context, Result};
+use parking_lot::Mutex;
+use std::{
+    io::{BufRead, BufReader, Read},
+    process::{Command, Stdio},
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc;
+
+/// Quiet this long and the model is asked whether the command is stuck.
+const STALL_AFTER: Duration = Duration::from_secs(120);
+/// Anomalies handed to the model per run, later ones only show up in `.progress`.
+const MAX_INTERPRETATIONS: usize = 3;
+
+/// A command run by [`supervise`], kept in the config so `.progress` can look at it.
+#[derive(Debug)]
+pub struct SupervisedJob {
+    pub command: String,
+    pub monitor: Mutex<OutputMonitor>,
+}
+
+impl SupervisedJob {
+    pub fn new(command: &str) -> Arc<Self> {
+        Arc::new(Self {
+            command: command.to_string(),
+            monitor: Mutex::new(OutputMonitor::new(Instant::now())),
+        })
+    }
+
+    pub fn is_running(&self) -> bool {
+        self.monitor.lock().exit_code().is_none()
+    }
+}
+
+/// Run the command of `job` with `shell` while watching its output. Errors and stalls are
+/// handed to the `%supervisor%` role as they happen and its reading is printed. With `echo`
+/// the output goes to the terminal as well, otherwise only to the monitor.
+pub async fn supervise(
+    config: &GlobalConfig,
+    shell: &Shell,
+    job: &Arc<SupervisedJob>,
+    echo: bool,
+) -> Result<i32> {
+    let stdin = match echo {
+        true => Stdio::inherit(),
+        false => Stdio::null(),
+    };
+    let mut child = Command::new(&shell.cmd)
+        .args([&shell.arg, &job.command])
+        .stdin(stdin)
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .with_context(|| format!("Failed to run '{}'", job.command))?;
+    let (tx, mut rx) = mpsc::unbounded_channel();
+    read_lines(child.stdout.take(), false, tx.clone());
+    read_lines(child.stderr.take(), true, tx);
+
+    let mut ticker = tokio::time::interval(Duration::from_secs(1));
+    let mut interpreted = 0;
+    loop {
+        let anomaly = tokio::select! {
+            line = rx.recv() => match line {
+                Some((line, stderr)) => {
+                    if echo && stderr {
+                        eprintln!("{line}");
+                    } else if echo {
+                        println!("{line}");
+                    }
+                    job.monitor.lock().feed(&line, Instant::now());
+                    continue;
+                }
+                None => break,
+            },
+            _ = ticker.tick() => job.monitor.lock().poll(Instant::now(), STALL_AFTER),
+        };
+        let Some(anomaly) = anomaly.filter(|_| interpreted < MAX_INTERPRETATIONS) else {
+            continue;
+        };
+        interpreted += 1;
+        let prompt = job
+            .monitor
+            .lock()
+            .anomaly_prompt(&job.command, &anomaly, Instant::now());
+        let config = config.clone();
+        tokio::spawn(async move { interpret(&config, &anomaly, &prompt).await });
+    }
+
+    let status = tokio::task::spawn_blocking(move || child.wait()).await??;
+    let code = status.code().unwrap_or_default();
+    job.monitor.lock().finish(code, Instant::now());
+    Ok(code)
+}
+
+/// Ask the model how far the job got, for `.progress`.
+pub async fn interpret_progress(config: &GlobalConfig, job: &SupervisedJob) -> Result<String> {
+    let prompt = job
+        .monitor
+        .lock()
+        .progress_prompt(&job.command, Instant::now());
+    let role = config.read().retrieve_role(SUPERVISOR_ROLE)?;
+    Input::from_str(config, &prompt, Some(role))
+        .fetch_chat_text()
+        .await
+}
+
+async fn interpret(config: &GlobalConfig, anomaly: &Anomaly, prompt: &str) {
+    let heading = match anomaly {
+        Anomaly::Error(line) => format!("⚠ Supervisor: {line}"),
+        Anomaly::Stalled(quiet) => format!("⚠ Supervisor: no output for {}", format_age(*quiet)),
+    };
+    let text = match config.read().retrieve_role(SUPERVISOR_ROLE) {
+        Ok(role) => Input::from_str(config, prompt, Some(role)),
+        Err(err) => {
+            warn!("Failed to load the supervisor role: {err}");
+            return;
+        }
+    };
+    match text.fetch_chat_text().await {
+        Ok(text) => {
+            eprintln!("\n{}", warning_text(&heading));
+            if let Err(err) = config.read().print_markdown(&text) {
+                warn!("Failed to print the supervisor's reading: {err}");
+            }
+        }
+        Err(err) => warn!("Failed to interpret the command output: {err}"),
+    }
+}
+
+/// Forward the lines of a child's pipe, on a thread of its own since reads block.
+fn read_lines<R: Read + Send + 'static>(
+    pipe: Option<R>,
+    stderr: bool,
+    tx: mpsc::UnboundedSender<(String, bool)>,
+) {
+    let Some(pipe) = pipe else {
+        return;
+    };
+    std::thread::spawn(move || {
+        let mut reader = BufReader::new(pipe);
+        let mut buf = vec![];
+        while let Ok(n) = reader.read_until(b'\n', &mut buf) {
+            if n == 0 {
+                break;
+            }
+            let line = String::from_utf8_lossy(&buf).trim_end().to_string();
+            if tx.send((line, stderr)).is_err() {
+                break;
+            }
+            buf.clear();
+        }
+    });
+}
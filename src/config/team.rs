@@ -0,0 +1,211 @@
+use super::*;
+
+use crate::client::call_chat_completions;
+
+use anyhow::{bail, Result};
+use serde::{Deserialize, Serialize};
+
+/// Roles of `--execute --team`, configured under `team`. A role sets its own model with
+/// `model:` in its metadata:
+///
+/// ```yaml
+/// team:
+///   planner: null    # `%planner%` by default
+///   executor: null   # `%shell%` by default
+///   reviewer: null   # `%reviewer%` by default
+///   rounds: 2
+/// ```
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TeamRoles {
+    pub planner: Option<String>,
+    /// Runs without tools like the other roles, nothing executes before the review.
+    pub executor: Option<String>,
+    pub reviewer: Option<String>,
+    /// Reviews at most, the executor revises the command after each one that objects but
+    /// the last.
+    pub rounds: usize,
+}
+
+impl Default for TeamRoles {
+    fn default() -> Self {
+        Self {
+            planner: None,
+            executor: None,
+            reviewer: None,
+            rounds: 2,
+        }
+    }
+}
+
+impl std::fmt::Display for TeamRoles {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "{}, {}, {}, {} rounds",
+            self.planner.as_deref().unwrap_or(PLANNER_ROLE),
+            self.executor.as_deref().unwrap_or(SHELL_ROLE),
+            self.reviewer.as_deref().unwrap_or(REVIEWER_ROLE),
+            self.rounds
+        )
+    }
+}
+
+/// The command of a team run and the objections of the last review.
+#[derive(Debug, Clone, Default)]
+pub struct TeamPlan {
+    pub command: String,
+    pub objections: Vec<String>,
+}
+
+/// Have the planner break `text` into steps, the executor turn them into a command and the
+/// reviewer vet it, the executor revising while the reviewer objects.
+pub async fn run_team(
+    config: &GlobalConfig,
+    text: &str,
+    abort_signal: AbortSignal,
+) -> Result<TeamPlan> {
+    let (team, planner, executor, reviewer) = {
+        let config = config.read();
+        let team = config.team.clone();
+        let mut planner = config.retrieve_role(team.planner.as_deref().unwrap_or(PLANNER_ROLE))?;
+        let mut executor = config.retrieve_role(team.executor.as_deref().unwrap_or(SHELL_ROLE))?;
+        let mut reviewer =
+            config.retrieve_role(team.reviewer.as_deref().unwrap_or(REVIEWER_ROLE))?;
+        planner.set_use_tools(None);
+        executor.set_use_tools(None);
+        reviewer.set_use_tools(None);
+        (team, planner, executor, reviewer)
+    };
+
+    print_turn(&planner);
+    let input = Input::from_str(config, text, Some(planner));
+    let plan = ask(&input, abort_signal.clone()).await?;
+    eprintln!("{plan}\n");
+
+    let mut command = String::new();
+    let mut objections: Vec<String> = vec![];
+    for round in 1..=team.rounds.max(1) {
+        print_turn(&executor);
+        let mut prompt = format!("{text}\n\nFollow this plan:\n{plan}");
+        if !objections.is_empty() {
+            prompt.push_str(&format!(
+                "\n\nA reviewer objected to your previous command:\n```\n{command}\n```\n{}\n\nRevise the command to address the objections.",
+                format_objections(&objections)
+            ));
+        }
+        // The executor only proposes, a tool call would run before the reviewer saw it
+        let input = Input::from_str(config, &prompt, Some(executor.clone()));
+        command = ask(&input, abort_signal.clone()).await?;
+        if command.is_empty() {
+            bail!("No command generated");
+        }
+        eprintln!("{}\n", command.trim());
+
+        print_turn(&reviewer);
+        let input = Input::from_str(
+            config,
+            &review_prompt(text, &plan, &command),
+            Some(reviewer.clone()),
+        );
+        let review = ask(&input, abort_signal.clone()).await?;
+        objections = parse_review(&review);
+        match objections.is_empty() {
+            true => eprintln!("APPROVE\n"),
+            false => eprintln!("{}\n", format_objections(&objections)),
+        }
+        if objections.is_empty() {
+            break;
+        }
+        if round < team.rounds {
+            eprintln!("{}", dimmed_text(&format!("Revising, round {}", round + 1)));
+        }
+    }
+
+    Ok(TeamPlan {
+        command,
+        objections,
+    })
+}
+
+async fn ask(input: &Input, abort_signal: AbortSignal) -> Result<String> {
+    let client = input.create_client()?;
+    let (output, _) =
+        call_chat_completions(input, false, false, client.as_ref(), abort_signal).await?;
+    Ok(strip_think_tag(&output).trim().to_string())
+}
+
+pub fn format_objections(objections: &[String]) -> String {
+    objections
+        .iter()
+        .map(|v| format!("- {v}"))
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+fn print_turn(role: &Role) {
+    eprintln!(
+        "{}",
+        dimmed_text(&format!("── {} ({})", role.name(), role.model().id()))
+    );
+}
+
+/// What the reviewer sees: the task, the plan, the command and the local analysis.
+fn review_prompt(text: &str, plan: &str, command: &str) -> String {
+    let analysis = CommandAnalysis::analyze(command);
+    let mut prompt = format!(
+        "Task: {text}\n\nPlan:\n{plan}\n\nCommand:\n```\n{}\n```\n\nLocal analysis: {:?}",
+        command.trim(),
+        analysis.safety_level
+    );
+    for warning in &analysis.warnings {
+        prompt.push_str(&format!("\n{warning}"));
+    }
+    prompt
+}
+
+/// The objections of a review, none when it approves. A reply that is neither `APPROVE`
+/// nor a list counts as a single objection.
+fn parse_review(review: &str) -> Vec<String> {
+    let review = strip_think_tag(review);
+    let review = review.trim();
+    if review.is_empty()
+        || review
+            .trim_matches(['*', '.'])
+            .eq_ignore_ascii_case("approve")
+    {
+        return vec![];
+    }
+    let items: Vec<String> = review
+        .lines()
+        .map(|v| v.trim())
+        .filter(|v| v.starts_with("- ") || v.starts_with("* "))
+        .map(|v| v[2..].trim().to_string())
+        .collect();
+    match items.is_empty() {
+        true => vec![review.to_string()],
+        false => items,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_review() {
+        assert!(parse_review("APPROVE").is_empty());
+        assert!(parse_review("<think>looks fine</think>\n**Approve**.").is_empty());
+        assert_eq!(
+            parse_review("- `rm -rf $DIR` runs on / when DIR is unset\n* no backup of config"),
+            [
+                "`rm -rf $DIR` runs on / when DIR is unset",
+                "no backup of config"
+            ]
+        );
+        assert_eq!(
+            parse_review("This deletes the wrong directory."),
+            ["This deletes the wrong directory."]
+        );
+    }
+}
@@ -0,0 +1,190 @@
+use super::{ensure_parent_exists, Config};
+
+use anyhow::{Context, Result};
+use fancy_regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::{collections::BTreeMap, env, fs, path::PathBuf};
+
+const TRUST_FILE_NAME: &str = "trust.yaml";
+
+/// Commands the user reviewed once and allowed to run without the execute prompt.
+///
+/// Kept in the config dir rather than in the project, so a cloned repository can't
+/// trust commands on the user's behalf:
+///
+/// ```yaml
+/// global:
+///   - cargo clean
+/// projects:
+///   /home/alice/app:
+///     - npm run build*
+/// ```
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct TrustStore {
+    #[serde(default)]
+    pub global: Vec<String>,
+    #[serde(default)]
+    pub projects: BTreeMap<String, Vec<String>>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum TrustScope {
+    Global,
+    Project(PathBuf),
+}
+
+impl std::fmt::Display for TrustScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            TrustScope::Global => write!(f, "global"),
+            TrustScope::Project(path) => write!(f, "{}", path.display()),
+        }
+    }
+}
+
+impl TrustStore {
+    pub fn path() -> PathBuf {
+        Config::local_path(TRUST_FILE_NAME)
+    }
+
+    pub fn load() -> Result<Self> {
+        let path = Self::path();
+        if !path.exists() {
+            return Ok(Self::default());
+        }
+        let content = fs::read_to_string(&path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        serde_yaml::from_str(&content)
+            .with_context(|| format!("Failed to parse '{}'", path.display()))
+    }
+
+    pub fn save(&self) -> Result<()> {
+        let path = Self::path();
+        ensure_parent_exists(&path)?;
+        fs::write(&path, serde_yaml::to_string(self)?)
+            .with_context(|| format!("Failed to write '{}'", path.display()))
+    }
+
+    /// The pattern that trusts `command` in the current project or globally.
+    pub fn find(&self, command: &str) -> Option<(TrustScope, String)> {
+        let command = command.trim();
+        if let Some(root) = project_root() {
+            let key = root.display().to_string();
+            if let Some(pattern) = self
+                .projects
+                .get(&key)
+                .and_then(|v| v.iter().find(|v| pattern_matches(v, command)))
+            {
+                return Some((TrustScope::Project(root), pattern.clone()));
+            }
+        }
+        self.global
+            .iter()
+            .find(|v| pattern_matches(v, command))
+            .map(|v| (TrustScope::Global, v.clone()))
+    }
+
+    pub fn add(&mut self, pattern: &str, scope: &TrustScope) {
+        let patterns = match scope {
+            TrustScope::Global => &mut self.global,
+            TrustScope::Project(root) => {
+                self.projects.entry(root.display().to_string()).or_default()
+            }
+        };
+        let pattern = pattern.trim().to_string();
+        if !patterns.contains(&pattern) {
+            patterns.push(pattern);
+        }
+    }
+
+    /// Remove the pattern from the current project and the global list.
+    pub fn remove(&mut self, pattern: &str) -> bool {
+        let pattern = pattern.trim();
+        let mut removed = false;
+        if let Some(root) = project_root() {
+            let key = root.display().to_string();
+            if let Some(patterns) = self.projects.get_mut(&key) {
+                let len = patterns.len();
+                patterns.retain(|v| v != pattern);
+                removed |= patterns.len() != len;
+                if patterns.is_empty() {
+                    self.projects.remove(&key);
+                }
+            }
+        }
+        let len = self.global.len();
+        self.global.retain(|v| v != pattern);
+        removed || self.global.len() != len
+    }
+
+    pub fn list(&self) -> Vec<(TrustScope, String)> {
+        let mut output: Vec<_> = self
+            .global
+            .iter()
+            .map(|v| (TrustScope::Global, v.clone()))
+            .collect();
+        for (root, patterns) in &self.projects {
+            output.extend(
+                patterns
+                    .iter()
+                    .map(|v| (TrustScope::Project(PathBuf::from(root)), v.clone())),
+            );
+        }
+        output
+    }
+}
+
+/// The nearest ancestor of the working directory containing `.git`.
+pub fn project_root() -> Option<PathBuf> {
+    let cwd = env::current_dir().ok()?;
+    cwd.ancestors()
+        .find(|v| v.join(".git").exists())
+        .map(|v| v.to_path_buf())
+}
+
+/// Patterns match the whole command. `*` matches anything except shell operators,
+/// so `npm run *` doesn't trust `npm run build && rm -rf ~`.
+fn pattern_matches(pattern: &str, command: &str) -> bool {
+    if !pattern.contains('*') {
+        return pattern == command;
+    }
+    let regex = pattern
+        .split('*')
+        .map(fancy_regex::escape)
+        .collect::<Vec<_>>()
+        .join(r"[^;&|`$()<>\n]*");
+    Regex::new(&format!("^{regex}$"))
+        .ok()
+        .and_then(|re| re.is_match(command).ok())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pattern_matches() {
+        assert!(pattern_matches("cargo clean", "cargo clean"));
+        assert!(!pattern_matches("cargo clean", "cargo clean -p x"));
+        assert!(pattern_matches("cargo clean*", "cargo clean -p x"));
+        assert!(pattern_matches("npm run *", "npm run build"));
+        assert!(!pattern_matches("npm run *", "npm run build && rm -rf ~"));
+        assert!(pattern_matches("echo (a)*", "echo (a) b"));
+    }
+
+    #[test]
+    fn test_trust_store() {
+        let mut store = TrustStore::default();
+        store.add("cargo clean", &TrustScope::Global);
+        store.add("cargo clean", &TrustScope::Global);
+        assert_eq!(store.list().len(), 1);
+        assert_eq!(
+            store.find("  cargo clean "),
+            Some((TrustScope::Global, "cargo clean".into()))
+        );
+        assert!(store.find("cargo build").is_none());
+        assert!(store.remove("cargo clean"));
+        assert!(!store.remove("cargo clean"));
+    }
+}
@@ -0,0 +1,153 @@
+use super::*;
+
+use anyhow::Result;
+use std::{collections::HashMap, fs, time::SystemTime};
+
+/// Notices changes to the config file, the hooks, the theme, the functions and the
+/// documents of the active RAG by polling their modification times, which is cheap
+/// enough to run before every REPL input.
+#[derive(Debug, Default)]
+pub struct ConfigWatcher {
+    mtimes: HashMap<PathBuf, Option<SystemTime>>,
+}
+
+impl ConfigWatcher {
+    pub fn new(config: &Config) -> Self {
+        let mut watcher = Self::default();
+        watcher.changed_files(config);
+        watcher
+    }
+
+    /// Files modified, created or removed since the last call. Files seen for the first
+    /// time, e.g. the documents of a RAG just switched to, don't count as changed.
+    pub fn changed_files(&mut self, config: &Config) -> Vec<PathBuf> {
+        let mut changed = vec![];
+        for path in watched_files(config) {
+            let mtime = fs::metadata(&path).and_then(|v| v.modified()).ok();
+            match self.mtimes.insert(path.clone(), mtime) {
+                Some(previous) if previous != mtime => changed.push(path),
+                _ => {}
+            }
+        }
+        changed
+    }
+}
+
+/// [`Config::reload`] after `files` changed, or on request when `files` is empty, logging
+/// and returning a report of what changed.
+pub fn reload_config(config: &GlobalConfig, files: &[PathBuf]) -> Result<String> {
+    let changes = Config::reload(config)?;
+    let mut report = match files.is_empty() {
+        true => "Reloaded the config".to_string(),
+        false => {
+            let names: Vec<String> = files
+                .iter()
+                .map(|v| {
+                    v.file_name()
+                        .unwrap_or(v.as_os_str())
+                        .to_string_lossy()
+                        .to_string()
+                })
+                .collect();
+            format!("Reloaded after changes to {}", names.join(", "))
+        }
+    };
+    if changes.is_empty() {
+        report.push_str(", no setting changed");
+    }
+    for change in changes {
+        report.push_str(&format!("\n  {change}"));
+    }
+    info!("{report}");
+    Ok(report)
+}
+
+/// Whether `path` is a document of the active RAG, which needs a rebuild instead of a reload.
+pub fn is_rag_document(config: &Config, path: &Path) -> bool {
+    rag_documents(config).iter().any(|v| v == path)
+}
+
+fn watched_files(config: &Config) -> Vec<PathBuf> {
+    let mut files = vec![
+        Config::config_file(),
+        Config::hooks_file(),
+        Config::policy_file(),
+        Config::tutor_file(),
+        Config::functions_file(),
+        Config::local_path("dark.theme.yaml"),
+        Config::local_path("light.theme.yaml"),
+    ];
+    files.extend(rag_documents(config));
+    files
+}
+
+/// Local files and directories of the active RAG, URLs and globs are left out.
+fn rag_documents(config: &Config) -> Vec<PathBuf> {
+    let Some(rag) = &config.rag else {
+        return vec![];
+    };
+    rag.document_paths()
+        .iter()
+        .filter(|v| !v.contains("://") && !v.contains('*'))
+        .map(PathBuf::from)
+        .filter(|v| v.exists())
+        .collect()
+}
+
+/// One line per top-level setting that differs between two versions of the config file.
+/// Values are shown for plain settings, clients only by name since they hold API keys.
+pub fn describe_changes(old: &serde_yaml::Mapping, new: &serde_yaml::Mapping) -> Vec<String> {
+    let mut keys: Vec<&serde_yaml::Value> = new.keys().collect();
+    keys.extend(old.keys().filter(|v| !new.contains_key(*v)));
+    keys.into_iter()
+        .filter_map(|key| {
+            let name = key.as_str().unwrap_or_default();
+            let secret = name == CLIENTS_FIELD
+                || ["key", "token", "secret"].iter().any(|v| name.contains(v));
+            let show = |value: &serde_yaml::Value| match value {
+                serde_yaml::Value::Sequence(_) | serde_yaml::Value::Mapping(_) => None,
+                _ if secret => None,
+                _ => serde_json::to_string(value).ok(),
+            };
+            match (old.get(key), new.get(key)) {
+                (Some(a), Some(b)) if a == b => None,
+                (Some(a), Some(b)) => Some(match (show(a), show(b)) {
+                    (Some(a), Some(b)) => format!("{name}: {a} -> {b}"),
+                    _ => format!("{name}: changed"),
+                }),
+                (None, Some(b)) => Some(match show(b) {
+                    Some(b) => format!("{name}: {b} (added)"),
+                    None => format!("{name}: added"),
+                }),
+                _ => Some(format!("{name}: removed")),
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_describe_changes() {
+        let old: serde_yaml::Mapping = serde_yaml::from_str(
+            "model: openai:gpt-4o\nstream: true\nnetwork_denylist: [a.com]\nclients: [{type: openai, api_key: x}]\nwrap: auto\n",
+        )
+        .unwrap();
+        let new: serde_yaml::Mapping = serde_yaml::from_str(
+            "model: claude:claude-3-5-sonnet\nstream: true\nnetwork_denylist: [a.com, b.com]\nclients: [{type: openai, api_key: y}]\ntheme: light\n",
+        )
+        .unwrap();
+        assert_eq!(
+            describe_changes(&old, &new),
+            [
+                r#"model: "openai:gpt-4o" -> "claude:claude-3-5-sonnet""#,
+                "network_denylist: changed",
+                "clients: changed",
+                r#"theme: "light" (added)"#,
+                "wrap: removed",
+            ]
+        );
+    }
+}
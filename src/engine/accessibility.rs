@@ -0,0 +1,108 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+static ACCESSIBLE: AtomicBool = AtomicBool::new(false);
+
+/// Whether screen-reader-friendly output is enabled (`accessible: true` or `AICHAT_ACCESSIBLE`).
+pub fn is_accessible() -> bool {
+    ACCESSIBLE.load(Ordering::Relaxed)
+}
+
+pub fn set_accessible(value: bool) {
+    ACCESSIBLE.store(value, Ordering::Relaxed);
+}
+
+/// Rewrite display output for screen readers when accessibility mode is on.
+///
+/// Separator rules are dropped and leading emoji markers become text labels, e.g.
+/// `⚠ Backup failed` -> `WARNING: Backup failed`, `✓ Restored` -> `OK: Restored`.
+pub fn accessible_text(input: &str) -> String {
+    if !is_accessible() {
+        return input.to_string();
+    }
+    let lines: Vec<String> = input
+        .lines()
+        .filter(|line| !is_separator(line))
+        .map(label_line)
+        .collect();
+    let mut output = lines.join("\n");
+    if input.ends_with('\n') {
+        output.push('\n');
+    }
+    output
+}
+
+fn is_separator(line: &str) -> bool {
+    let line = line.trim();
+    line.len() >= 3
+        && line
+            .chars()
+            .all(|c| matches!(c, '=' | '-' | '─' | '━' | '═'))
+}
+
+fn label_line(line: &str) -> String {
+    let rest = line.trim_start();
+    let indent = &line[..line.len() - rest.len()];
+    let marker_len = rest
+        .find(|c: char| c.is_ascii() || c.is_alphanumeric())
+        .unwrap_or(rest.len());
+    if marker_len == 0 {
+        return line.to_string();
+    }
+    let marker = &rest[..marker_len];
+    let text = rest[marker_len..].trim_start();
+    let label = if has_label(text) {
+        None
+    } else if marker.contains('⚠') {
+        Some("WARNING:")
+    } else if marker.contains('✓') || marker.contains('✅') {
+        Some("OK:")
+    } else if marker.contains('✗') || marker.contains('❌') {
+        Some("ERROR:")
+    } else if marker.contains('💡') {
+        Some("NOTE:")
+    } else {
+        None
+    };
+    match label {
+        Some(label) => format!("{indent}{label} {text}"),
+        None => format!("{indent}{text}"),
+    }
+}
+
+/// Whether the text already starts with an upper-case label such as `CRITICAL:`.
+fn has_label(text: &str) -> bool {
+    match text.split_once(':') {
+        Some((word, _)) => {
+            !word.is_empty() && word.chars().all(|c| c.is_ascii_uppercase() || c == ' ')
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_label_line() {
+        assert_eq!(label_line("⚠ Backup failed"), "WARNING: Backup failed");
+        assert_eq!(
+            label_line("  ⚠️  CRITICAL: affects system files"),
+            "  CRITICAL: affects system files"
+        );
+        assert_eq!(label_line("✓ Restored: a.txt"), "OK: Restored: a.txt");
+        assert_eq!(
+            label_line("💡 Files will be moved"),
+            "NOTE: Files will be moved"
+        );
+        assert_eq!(label_line("📦 Backup History"), "Backup History");
+        assert_eq!(label_line("Command: ls"), "Command: ls");
+    }
+
+    #[test]
+    fn test_separator() {
+        assert!(is_separator(&"=".repeat(60)));
+        assert!(!is_separator("Command: ls"));
+        assert!(!is_separator(""));
+    }
+}
@@ -0,0 +1,531 @@
+use super::*;
+
+use fancy_regex::{Captures, Regex};
+use indexmap::IndexMap;
+use parking_lot::RwLock;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::PathBuf,
+    process::{Command, Stdio},
+    sync::{Arc, LazyLock},
+};
+
+/// Expansions of aliases made of aliases stop here, like a shell stops at a loop.
+const MAX_EXPANSION_DEPTH: usize = 8;
+
+static ALIASES: LazyLock<RwLock<Arc<ShellAliases>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(ShellAliases::default())));
+
+static RE_POSITIONAL: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r#""?\$(?:\{([1-9])\}|([1-9]))"?"#).unwrap());
+
+/// The aliases the analyzer expands, none until [`set_shell_aliases`] runs.
+pub fn shell_aliases() -> Arc<ShellAliases> {
+    ALIASES.read().clone()
+}
+
+pub fn set_shell_aliases(aliases: ShellAliases) {
+    *ALIASES.write() = Arc::new(aliases);
+}
+
+/// Where aliases and shell functions come from before analysis (`resolve_aliases`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AliasResolution {
+    /// Analyze the command as written.
+    #[default]
+    Off,
+    /// Parse the shell's rc files.
+    Rc,
+    /// Ask an interactive shell, falling back to the rc files.
+    Shell,
+}
+
+impl std::str::FromStr for AliasResolution {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "off" => Ok(AliasResolution::Off),
+            "rc" => Ok(AliasResolution::Rc),
+            "shell" => Ok(AliasResolution::Shell),
+            _ => Err(format!(
+                "invalid alias resolution '{s}', expected off, rc or shell"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for AliasResolution {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+/// The user's aliases and shell functions, so `rm` aliased to `rm -i` or a function
+/// wrapping `git push --force` are analyzed as what actually runs.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ShellAliases {
+    aliases: IndexMap<String, String>,
+    functions: IndexMap<String, String>,
+}
+
+impl ShellAliases {
+    pub fn load(resolution: AliasResolution, shell: &ShellKind) -> Self {
+        match resolution {
+            AliasResolution::Off => Self::default(),
+            AliasResolution::Rc => Self::from_rc_files(shell),
+            AliasResolution::Shell => {
+                Self::query(shell).unwrap_or_else(|| Self::from_rc_files(shell))
+            }
+        }
+    }
+
+    pub fn alias(mut self, name: &str, value: &str) -> Self {
+        self.aliases.insert(name.to_string(), value.to_string());
+        self
+    }
+
+    pub fn function(mut self, name: &str, body: &str) -> Self {
+        self.functions.insert(name.to_string(), body.to_string());
+        self
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.aliases.is_empty() && self.functions.is_empty()
+    }
+
+    /// Definitions in the rc files of `shell`, later ones win like when they are sourced.
+    pub fn from_rc_files(shell: &ShellKind) -> Self {
+        let mut output = Self::default();
+        for path in rc_files(shell) {
+            if let Ok(content) = fs::read_to_string(&path) {
+                output.merge(Self::parse(&content, shell));
+            }
+        }
+        output
+    }
+
+    /// What an interactive `shell` defines after reading all of its startup files.
+    fn query(shell: &ShellKind) -> Option<Self> {
+        let (program, script) = match shell {
+            ShellKind::Bash | ShellKind::Msys => ("bash", "alias; declare -f"),
+            ShellKind::Zsh => ("zsh", "alias -L; functions"),
+            ShellKind::Fish => ("fish", "alias; abbr --show"),
+            _ => return None,
+        };
+        let output = Command::new(program)
+            .args(["-i", "-c", script])
+            .stdin(Stdio::null())
+            .stderr(Stdio::null())
+            .output()
+            .ok()
+            .filter(|v| v.status.success())?;
+        let mut aliases = Self::parse(&String::from_utf8_lossy(&output.stdout), shell);
+        // fish prints function bodies with `functions NAME` only, the files are readable
+        if matches!(shell, ShellKind::Fish) {
+            aliases.merge(Self::from_rc_files(shell).only_functions());
+        }
+        Some(aliases)
+    }
+
+    /// `alias name=value` lines and function definitions, `abbr` and `function ... end`
+    /// for fish.
+    pub fn parse(content: &str, shell: &ShellKind) -> Self {
+        let mut output = Self::default();
+        let lines: Vec<&str> = content.lines().collect();
+        let mut i = 0;
+        while i < lines.len() {
+            let line = lines[i].trim();
+            i += 1;
+            let words = split_command_words(line, PathStyle::Unix);
+            match words.first().map(|v| v.as_str()) {
+                Some("alias") => {
+                    let args: Vec<&String> =
+                        words[1..].iter().filter(|v| !v.starts_with('-')).collect();
+                    for arg in &args {
+                        if let Some((name, value)) = arg.split_once('=') {
+                            output.aliases.insert(name.to_string(), value.to_string());
+                        }
+                    }
+                    // fish: `alias name 'value'`
+                    if let [name, value, ..] = args.as_slice() {
+                        if !name.contains('=') {
+                            output.aliases.insert(name.to_string(), value.to_string());
+                        }
+                    }
+                }
+                Some("abbr") if matches!(shell, ShellKind::Fish) => {
+                    let args: Vec<&String> = words[1..]
+                        .iter()
+                        .skip_while(|v| v.starts_with('-'))
+                        .collect();
+                    if let [name, value @ ..] = args.as_slice() {
+                        if !value.is_empty() {
+                            let value: Vec<&str> = value.iter().map(|v| v.as_str()).collect();
+                            output.aliases.insert(name.to_string(), value.join(" "));
+                        }
+                    }
+                }
+                Some("function") if matches!(shell, ShellKind::Fish) => {
+                    let Some(name) = words.get(1) else {
+                        continue;
+                    };
+                    let (body, end) = fish_function_body(&lines, i);
+                    output.functions.insert(name.to_string(), body);
+                    i = end;
+                }
+                _ => {
+                    // `declare -f` puts the brace on a line of its own
+                    let opens = line.contains('{') || lines.get(i).is_some_and(|v| v.trim() == "{");
+                    let Some(name) = function_name(line).filter(|_| opens) else {
+                        continue;
+                    };
+                    let (body, end) = brace_function_body(&lines, i - 1);
+                    output.functions.insert(name, body);
+                    i = end;
+                }
+            }
+        }
+        output
+    }
+
+    /// The command with aliases and function calls in command position replaced by what
+    /// they run, `None` when nothing was expanded.
+    pub fn expand(&self, command: &str, shell: &ShellKind) -> Option<String> {
+        if self.is_empty() {
+            return None;
+        }
+        let mut seen = vec![];
+        let mut output = self.expand_once(command, shell, &mut seen)?;
+        for _ in 1..MAX_EXPANSION_DEPTH {
+            match self.expand_once(&output, shell, &mut seen) {
+                Some(next) => output = next,
+                None => break,
+            }
+        }
+        Some(output)
+    }
+
+    fn expand_once(
+        &self,
+        command: &str,
+        shell: &ShellKind,
+        seen: &mut Vec<String>,
+    ) -> Option<String> {
+        let tokens = tokenize(command, PathStyle::Unix);
+        let mut output = String::new();
+        let mut last = 0;
+        let mut expanded = vec![];
+        let mut command_position = true;
+        let mut i = 0;
+        while i < tokens.len() {
+            let token = &tokens[i];
+            i += 1;
+            if token.is_control() {
+                command_position = true;
+                continue;
+            }
+            if !command_position || !token.is_word() {
+                continue;
+            }
+            command_position = false;
+            // Quoted or escaped words like `\rm` skip aliases
+            if token.quoted || token.raw(command).starts_with('\\') {
+                continue;
+            }
+            let name = token.text.as_str();
+            if is_assignment(name) || matches!(name, "{" | "!" | "then" | "do" | "else" | "time") {
+                command_position = true;
+                continue;
+            }
+            if seen.iter().any(|v| v == name) {
+                continue;
+            }
+            if let Some(value) = self.aliases.get(name) {
+                output.push_str(&command[last..token.span.start]);
+                output.push_str(value);
+                last = token.span.end;
+                expanded.push(name.to_string());
+                // An alias ending in a blank expands the next word too, `sudo='sudo '`
+                command_position = value.ends_with(' ');
+            } else if let Some(body) = self.functions.get(name) {
+                let end = tokens[i..]
+                    .iter()
+                    .position(|v| v.is_control())
+                    .map_or(tokens.len(), |v| i + v);
+                let args: Vec<&str> = tokens[i..end]
+                    .iter()
+                    .filter(|v| v.is_word())
+                    .map(|v| v.raw(command))
+                    .collect();
+                output.push_str(&command[last..token.span.start]);
+                output.push_str(&call_function(body, &args, shell));
+                last = tokens[i - 1..end]
+                    .last()
+                    .map_or(token.span.end, |v| v.span.end);
+                expanded.push(name.to_string());
+                i = end;
+            }
+        }
+        if expanded.is_empty() {
+            return None;
+        }
+        // Within its own expansion a name is left alone, `rm='rm -i'`
+        seen.extend(expanded);
+        output.push_str(&command[last..]);
+        Some(output)
+    }
+
+    fn merge(&mut self, other: Self) {
+        self.aliases.extend(other.aliases);
+        self.functions.extend(other.functions);
+    }
+
+    fn only_functions(self) -> Self {
+        Self {
+            aliases: IndexMap::new(),
+            functions: self.functions,
+        }
+    }
+}
+
+fn rc_files(shell: &ShellKind) -> Vec<PathBuf> {
+    let Some(home) = dirs::home_dir() else {
+        return vec![];
+    };
+    match shell {
+        ShellKind::Bash | ShellKind::Msys => [".bash_profile", ".bashrc", ".bash_aliases"]
+            .iter()
+            .map(|v| home.join(v))
+            .collect(),
+        ShellKind::Zsh => {
+            let dir = std::env::var_os("ZDOTDIR").map_or(home, PathBuf::from);
+            [".zshenv", ".zprofile", ".zshrc", ".zsh_aliases"]
+                .iter()
+                .map(|v| dir.join(v))
+                .collect()
+        }
+        ShellKind::Fish => {
+            let dir = dirs::config_dir()
+                .unwrap_or_else(|| home.join(".config"))
+                .join("fish");
+            let mut files = vec![dir.join("config.fish")];
+            for sub in ["conf.d", "functions"] {
+                if let Ok(entries) = fs::read_dir(dir.join(sub)) {
+                    let mut paths: Vec<PathBuf> = entries
+                        .flatten()
+                        .map(|v| v.path())
+                        .filter(|v| v.extension().is_some_and(|v| v == "fish"))
+                        .collect();
+                    paths.sort();
+                    files.extend(paths);
+                }
+            }
+            files
+        }
+        _ => vec![],
+    }
+}
+
+/// `VAR=value` before the command word.
+fn is_assignment(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty()
+            && !name.starts_with(|c: char| c.is_ascii_digit())
+            && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// The name in `name() {`, `name () {`, `function name {` and `function name() {`.
+fn function_name(line: &str) -> Option<String> {
+    let head = line.split('{').next()?.trim();
+    let (head, keyword) = match head.strip_prefix("function ") {
+        Some(rest) => (rest.trim(), true),
+        None => (head, false),
+    };
+    let name = match head.strip_suffix("()") {
+        Some(name) => name.trim_end(),
+        None if keyword => head,
+        None => return None,
+    };
+    let valid = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '_' | '-' | '.' | ':'));
+    valid.then(|| name.to_string())
+}
+
+/// The commands between the braces of a function starting on line `start`, and the line
+/// after it ends.
+fn brace_function_body(lines: &[&str], start: usize) -> (String, usize) {
+    let mut depth = 0;
+    let mut body: Vec<String> = vec![];
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let mut text = String::new();
+        for c in line.chars() {
+            match c {
+                '{' => {
+                    depth += 1;
+                    if depth == 1 {
+                        continue;
+                    }
+                }
+                '}' => {
+                    depth -= 1;
+                    if depth == 0 {
+                        body.push(text.trim().trim_end_matches(';').trim().to_string());
+                        return (join_body(&body), i + 1);
+                    }
+                }
+                _ => {}
+            }
+            if depth > 0 {
+                text.push(c);
+            }
+        }
+        body.push(text.trim().to_string());
+    }
+    (join_body(&body), lines.len())
+}
+
+/// The lines up to the `end` matching a fish `function` on the line before `start`.
+fn fish_function_body(lines: &[&str], start: usize) -> (String, usize) {
+    let mut depth = 1;
+    let mut body = vec![];
+    for (i, line) in lines.iter().enumerate().skip(start) {
+        let line = line.trim();
+        let word = line.split_whitespace().next().unwrap_or_default();
+        match word {
+            "if" | "for" | "while" | "switch" | "begin" | "function" => depth += 1,
+            "end" => depth -= 1,
+            _ => {}
+        }
+        if depth == 0 {
+            return (join_body(&body), i + 1);
+        }
+        body.push(line.to_string());
+    }
+    (join_body(&body), lines.len())
+}
+
+fn join_body(lines: &[String]) -> String {
+    let lines: Vec<&str> = lines
+        .iter()
+        .map(|v| v.as_str())
+        .filter(|v| !v.is_empty() && !v.starts_with('#'))
+        .collect();
+    lines.join("; ")
+}
+
+/// The body of a function with its parameters replaced by the arguments of the call.
+fn call_function(body: &str, args: &[&str], shell: &ShellKind) -> String {
+    let all = args.join(" ");
+    match shell {
+        ShellKind::Fish => {
+            let mut body = body.to_string();
+            for (i, arg) in args.iter().enumerate() {
+                body = body.replace(&format!("$argv[{}]", i + 1), arg);
+            }
+            body.replace("$argv", &all)
+        }
+        _ => {
+            let body = ["\"$@\"", "$@", "\"$*\"", "$*"]
+                .iter()
+                .fold(body.to_string(), |body, v| body.replace(v, &all));
+            let body = RE_POSITIONAL.replace_all(&body, |caps: &Captures| {
+                let n: usize = caps
+                    .get(1)
+                    .or_else(|| caps.get(2))
+                    .and_then(|v| v.as_str().parse().ok())
+                    .unwrap_or_default();
+                args.get(n - 1).copied().unwrap_or_default().to_string()
+            });
+            format!("{{ {body}; }}")
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_aliases() {
+        let bashrc = r#"
+# aliases
+alias ll='ls -la'
+alias rm='rm -i' cp="cp -i"
+export PATH=$HOME/bin:$PATH
+
+deploy() {
+    git push --force origin "$1"
+}
+function nuke { rm -rf "$@"; }
+"#;
+        let aliases = ShellAliases::parse(bashrc, &ShellKind::Bash);
+        assert_eq!(
+            aliases,
+            ShellAliases::default()
+                .alias("ll", "ls -la")
+                .alias("rm", "rm -i")
+                .alias("cp", "cp -i")
+                .function("deploy", r#"git push --force origin "$1""#)
+                .function("nuke", r#"rm -rf "$@""#)
+        );
+
+        let config = r#"
+alias gp 'git push'
+abbr -a gpf git push --force
+function clean --description 'Remove build output'
+    if test -d build
+        rm -rf build $argv
+    end
+end
+"#;
+        let aliases = ShellAliases::parse(config, &ShellKind::Fish);
+        assert_eq!(
+            aliases,
+            ShellAliases::default()
+                .alias("gp", "git push")
+                .alias("gpf", "git push --force")
+                .function("clean", "if test -d build; rm -rf build $argv; end")
+        );
+    }
+
+    #[test]
+    fn test_expand_aliases() {
+        let aliases = ShellAliases::default()
+            .alias("rm", "rm -i")
+            .alias("please", "sudo ")
+            .alias("gpf", "gp --force")
+            .alias("gp", "git push")
+            .function("nuke", r#"rm -rf "$@""#)
+            .function("deploy", r#"git push --force origin "$1""#);
+        let expand = |command: &str| aliases.expand(command, &ShellKind::Bash);
+        assert_eq!(expand("rm a.txt").as_deref(), Some("rm -i a.txt"));
+        assert_eq!(expand(r"\rm a.txt"), None);
+        assert_eq!(
+            expand("ls && please rm a.txt").as_deref(),
+            Some("ls && sudo  rm -i a.txt")
+        );
+        assert_eq!(
+            expand("gpf origin main").as_deref(),
+            Some("git push --force origin main")
+        );
+        assert_eq!(expand("echo rm"), None);
+        assert_eq!(
+            expand("nuke build dist | tee log").as_deref(),
+            Some("{ rm -i -rf build dist; } | tee log")
+        );
+        assert_eq!(
+            expand("deploy main").as_deref(),
+            Some("{ git push --force origin main; }")
+        );
+        assert_eq!(
+            expand("FOO=1 deploy main").as_deref(),
+            Some("FOO=1 { git push --force origin main; }")
+        );
+    }
+}
@@ -0,0 +1,701 @@
+use super::*;
+
+use anyhow::{anyhow, bail, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::env;
+use std::fs::{self, File};
+use std::io::{BufReader, BufWriter};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const BACKUP_DIR_NAME: &str = ".aichat_backups";
+const TEMP_BACKUP_DIR_NAME: &str = "aichat-backups";
+
+/// Files taken at most from the arguments of a command, e.g. `rm *` in a huge directory.
+pub const MAX_GLOB_FILES: usize = 1000;
+const BACKUP_INDEX_FILE: &str = "backup_index.json";
+
+static GLOB_PREVIEW_LIMIT: AtomicUsize = AtomicUsize::new(20);
+
+/// Matches listed per wildcard in an analysis (`glob_preview_limit`).
+pub fn glob_preview_limit() -> usize {
+    GLOB_PREVIEW_LIMIT.load(Ordering::Relaxed)
+}
+
+pub fn set_glob_preview_limit(value: usize) {
+    GLOB_PREVIEW_LIMIT.store(value, Ordering::Relaxed);
+}
+
+/// What an unquoted wildcard argument expands to in the current directory.
+#[derive(Debug, Clone, Serialize)]
+pub struct GlobExpansion {
+    pub pattern: String,
+    /// The first [`glob_preview_limit`] matches.
+    pub matches: Vec<PathBuf>,
+    pub total: usize,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupEntry {
+    pub id: String,
+    pub timestamp: String,
+    pub command: String,
+    pub files: Vec<BackupFile>,
+    pub description: String,
+    /// Set when the files were copied from a remote host over SSH.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub remote: Option<SshTarget>,
+    /// Set when a filesystem snapshot was taken instead of copying files.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub snapshot: Option<FsSnapshot>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct BackupFile {
+    pub original_path: PathBuf,
+    pub backup_path: PathBuf,
+    pub file_hash: String,
+    #[serde(default)]
+    pub source: BackupSource,
+}
+
+/// What [`BackupManager::restore_backup`] did, for the caller to show.
+#[derive(Debug, Clone, Default)]
+pub struct RestoreReport {
+    /// The files put back, `target:path` for remote ones.
+    pub restored: Vec<String>,
+    /// Files whose copy is gone from the backup directory.
+    pub missing: Vec<PathBuf>,
+    /// A filesystem snapshot isn't restored automatically, these commands roll it back.
+    pub snapshot: Option<(String, Vec<String>)>,
+}
+
+/// Where a backed up file came from, which decides how it is restored.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub enum BackupSource {
+    #[default]
+    File,
+    /// Output of `crontab -l`, restored with `crontab <file>`.
+    Crontab { user: Option<String> },
+}
+
+/// What happens when a command that needs a backup can't get one (`backup_failure`).
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum BackupFailure {
+    /// Ask whether to run the command anyway.
+    #[default]
+    Ask,
+    /// Warn and run it.
+    Proceed,
+    /// Refuse to run it.
+    Block,
+}
+
+impl std::str::FromStr for BackupFailure {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "ask" => Ok(BackupFailure::Ask),
+            "proceed" => Ok(BackupFailure::Proceed),
+            "block" => Ok(BackupFailure::Block),
+            _ => Err(format!(
+                "invalid backup failure policy '{s}', expected ask, proceed or block"
+            )),
+        }
+    }
+}
+
+impl std::fmt::Display for BackupFailure {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", format!("{self:?}").to_lowercase())
+    }
+}
+
+pub struct BackupManager {
+    backup_dir: PathBuf,
+    index_file: PathBuf,
+    fallback: Option<String>,
+}
+
+impl BackupManager {
+    /// Keep the backups in `~/.aichat_backups`, or when that isn't writable (read-only home,
+    /// containers, full disks) in `$XDG_STATE_HOME/aichat/backups`, and as a last resort in
+    /// the temporary directory. See [`BackupManager::fallback`].
+    pub fn new() -> Result<Self> {
+        let mut candidates = vec![];
+        if let Some(home) = dirs::home_dir() {
+            candidates.push((home.join(BACKUP_DIR_NAME), false));
+        }
+        if let Some(state) = dirs::state_dir() {
+            candidates.push((state.join("aichat").join("backups"), false));
+        }
+        candidates.push((env::temp_dir().join(TEMP_BACKUP_DIR_NAME), true));
+        Self::first_writable(candidates)
+    }
+
+    /// Keep the backups and their index in `backup_dir` instead of `~/.aichat_backups`.
+    pub fn with_dir(backup_dir: impl Into<PathBuf>) -> Result<Self> {
+        let backup_dir = backup_dir.into();
+        if !backup_dir.exists() {
+            fs::create_dir_all(&backup_dir)?;
+        }
+
+        let index_file = backup_dir.join(BACKUP_INDEX_FILE);
+
+        Ok(Self {
+            backup_dir,
+            index_file,
+            fallback: None,
+        })
+    }
+
+    /// Why the backups aren't kept in the usual place, to be shown before relying on them.
+    pub fn fallback(&self) -> Option<&str> {
+        self.fallback.as_deref()
+    }
+
+    pub fn backup_dir(&self) -> &Path {
+        &self.backup_dir
+    }
+
+    fn first_writable(candidates: Vec<(PathBuf, bool)>) -> Result<Self> {
+        let mut failures = vec![];
+        for (dir, temporary) in candidates {
+            let manager = Self::with_dir(&dir).and_then(|manager| {
+                let probe = dir.join(".write-test");
+                fs::write(&probe, b"")?;
+                fs::remove_file(&probe)?;
+                Ok(manager)
+            });
+            match manager {
+                Ok(mut manager) => {
+                    let reasons = failures.join(", ");
+                    manager.fallback = match (temporary, failures.is_empty()) {
+                        (true, _) => Some(format!(
+                            "⚠ Backups are kept in the temporary directory {} and may not survive a reboot ({reasons})",
+                            dir.display()
+                        )),
+                        (false, false) => Some(format!(
+                            "Backups are kept in {} ({reasons})",
+                            dir.display()
+                        )),
+                        (false, true) => None,
+                    };
+                    return Ok(manager);
+                }
+                Err(err) => failures.push(format!("{} is not writable: {err}", dir.display())),
+            }
+        }
+        bail!("No writable backup directory, {}", failures.join(", "))
+    }
+
+    /// Back up `paths`, plus the crontab and systemd unit files the command changes.
+    pub fn create_backup(&self, command: &str, mut paths: Vec<PathBuf>) -> Result<BackupEntry> {
+        let _span = tracing::info_span!("backup", command).entered();
+        let id = uuid::Uuid::new_v4().to_string();
+        let timestamp = Local::now().to_rfc3339();
+        let backup_subdir = self.backup_dir.join(&id);
+        fs::create_dir_all(&backup_subdir)?;
+
+        let mut backup_files = Vec::new();
+
+        for change in detect_persistent_changes(command) {
+            match change {
+                PersistentChange::Crontab { user, .. } => {
+                    let name = format!("crontab-{}", user.as_deref().unwrap_or("current"));
+                    let backup_path = backup_subdir.join(&name);
+                    fs::write(&backup_path, read_crontab(user.as_deref()))?;
+                    backup_files.push(BackupFile {
+                        original_path: PathBuf::from(format!(
+                            "crontab:{}",
+                            user.clone().unwrap_or_default()
+                        )),
+                        file_hash: self.calculate_file_hash(&backup_path)?,
+                        backup_path,
+                        source: BackupSource::Crontab { user },
+                    });
+                }
+                PersistentChange::SystemdUnit {
+                    path: Some(path), ..
+                } if !paths.contains(&path) => paths.push(path),
+                PersistentChange::SystemdUnit { .. }
+                | PersistentChange::AtJob { .. }
+                | PersistentChange::ScheduledTask { .. } => {}
+            }
+        }
+
+        for (i, path) in paths.into_iter().enumerate() {
+            if !path.exists() {
+                continue; // Skip non-existent files
+            }
+
+            // Only backup if it's a file (not directory for now)
+            if path.is_file() {
+                let file_name = path
+                    .file_name()
+                    .ok_or_else(|| anyhow!("Invalid file name"))?;
+                // Prefix with the index so `a\x.txt` and `b\X.TXT` don't collide on
+                // case-insensitive file systems.
+                let backup_path =
+                    backup_subdir.join(format!("{i}-{}", file_name.to_string_lossy()));
+
+                fs::copy(&path, &backup_path)?;
+                tracing::debug!(path = %path.display(), backup = %backup_path.display(), "copied file");
+
+                // Calculate hash for verification
+                let file_hash = self.calculate_file_hash(&path)?;
+
+                backup_files.push(BackupFile {
+                    original_path: path,
+                    backup_path,
+                    file_hash,
+                    source: BackupSource::File,
+                });
+            }
+        }
+
+        let entry = BackupEntry {
+            id: id.clone(),
+            timestamp,
+            command: command.to_string(),
+            files: backup_files,
+            description: format!("Backup before executing: {}", command),
+            remote: None,
+            snapshot: None,
+        };
+
+        // Add to index
+        self.add_to_index(&entry)?;
+        tracing::info!(id, files = entry.files.len(), "created backup");
+
+        Ok(entry)
+    }
+
+    /// Copy remote files into a local backup with scp before running `command` over SSH.
+    pub fn create_remote_backup(
+        &self,
+        command: &str,
+        target: &SshTarget,
+        paths: Vec<String>,
+    ) -> Result<BackupEntry> {
+        let _span = tracing::info_span!("backup", command, target = target.name).entered();
+        let id = uuid::Uuid::new_v4().to_string();
+        let timestamp = Local::now().to_rfc3339();
+        let backup_subdir = self.backup_dir.join(&id);
+        fs::create_dir_all(&backup_subdir)?;
+
+        let mut backup_files = Vec::new();
+        for (i, path) in target.existing_files(&paths)?.into_iter().enumerate() {
+            let file_name = Path::new(&path)
+                .file_name()
+                .ok_or_else(|| anyhow!("Invalid file name"))?;
+            let backup_path = backup_subdir.join(format!("{i}-{}", file_name.to_string_lossy()));
+            target.download(&path, &backup_path)?;
+            let file_hash = self.calculate_file_hash(&backup_path)?;
+            backup_files.push(BackupFile {
+                original_path: PathBuf::from(path),
+                backup_path,
+                file_hash,
+                source: BackupSource::File,
+            });
+        }
+
+        let entry = BackupEntry {
+            id: id.clone(),
+            timestamp,
+            command: command.to_string(),
+            files: backup_files,
+            description: format!("Backup before executing on {}: {}", target.name, command),
+            remote: Some(target.clone()),
+            snapshot: None,
+        };
+        self.add_to_index(&entry)?;
+        tracing::info!(id, files = entry.files.len(), "created remote backup");
+
+        Ok(entry)
+    }
+
+    /// Snapshot the filesystem containing `path` before a Critical command. `Ok(None)` when
+    /// the filesystem doesn't support snapshots.
+    pub fn create_snapshot_backup(
+        &self,
+        command: &str,
+        path: &Path,
+    ) -> Result<Option<BackupEntry>> {
+        let _span = tracing::info_span!("backup", command).entered();
+        let Some(snapshot) = FsSnapshot::create(path)? else {
+            tracing::debug!(path = %path.display(), "no snapshot support");
+            return Ok(None);
+        };
+        let entry = BackupEntry {
+            id: uuid::Uuid::new_v4().to_string(),
+            timestamp: Local::now().to_rfc3339(),
+            command: command.to_string(),
+            files: vec![],
+            description: format!("Snapshot {} before executing: {}", snapshot.name, command),
+            remote: None,
+            snapshot: Some(snapshot),
+        };
+        self.add_to_index(&entry)?;
+        tracing::info!(id = entry.id, "created snapshot backup");
+
+        Ok(Some(entry))
+    }
+
+    pub fn restore_backup(&self, backup_id: &str) -> Result<RestoreReport> {
+        let _span = tracing::info_span!("restore", backup_id).entered();
+        let entry = self.get_backup_entry(backup_id)?;
+        let mut report = RestoreReport::default();
+
+        if let Some(snapshot) = &entry.snapshot {
+            tracing::info!(snapshot = snapshot.name, "snapshot needs a manual rollback");
+            report.snapshot = Some((snapshot.name.clone(), snapshot.rollback_instructions()));
+            return Ok(report);
+        }
+
+        for file in &entry.files {
+            let restored = if let (BackupSource::Crontab { user }, true) =
+                (&file.source, file.backup_path.exists())
+            {
+                let mut args = vec![];
+                if let Some(user) = user {
+                    args.extend(["-u".to_string(), user.clone()]);
+                }
+                args.push(file.backup_path.display().to_string());
+                let code = run_command("crontab", &args, None)?;
+                if code != 0 {
+                    bail!("Failed to restore the crontab, crontab exited with {code}");
+                }
+                file.original_path.display().to_string()
+            } else if let (Some(target), true) = (&entry.remote, file.backup_path.exists()) {
+                target.upload(&file.backup_path, &file.original_path.to_string_lossy())?;
+                format!("{}:{}", target.name, file.original_path.display())
+            } else if file.backup_path.exists() {
+                if let Some(parent) = file.original_path.parent() {
+                    fs::create_dir_all(parent)?;
+                }
+                fs::copy(&file.backup_path, &file.original_path)?;
+                file.original_path.display().to_string()
+            } else {
+                tracing::warn!(backup = %file.backup_path.display(), "backup file not found");
+                report.missing.push(file.backup_path.clone());
+                continue;
+            };
+            tracing::info!(path = restored, "restored file");
+            report.restored.push(restored);
+        }
+
+        Ok(report)
+    }
+
+    pub fn list_backups(&self) -> Result<Vec<BackupEntry>> {
+        if !self.index_file.exists() {
+            return Ok(Vec::new());
+        }
+
+        let file = File::open(&self.index_file)?;
+        let reader = BufReader::new(file);
+        let entries: HashMap<String, BackupEntry> = serde_json::from_reader(reader)?;
+
+        let mut list: Vec<BackupEntry> = entries.into_values().collect();
+        list.sort_by(|a, b| b.timestamp.cmp(&a.timestamp));
+
+        Ok(list)
+    }
+
+    pub fn get_backup_entry(&self, backup_id: &str) -> Result<BackupEntry> {
+        if !self.index_file.exists() {
+            bail!("No backups found");
+        }
+
+        let file = File::open(&self.index_file)?;
+        let reader = BufReader::new(file);
+        let entries: HashMap<String, BackupEntry> = serde_json::from_reader(reader)?;
+
+        entries
+            .get(backup_id)
+            .cloned()
+            .ok_or_else(|| anyhow!("Backup {} not found", backup_id))
+    }
+
+    pub fn delete_backup(&self, backup_id: &str) -> Result<()> {
+        let _entry = self.get_backup_entry(backup_id)?;
+
+        // Delete backup directory
+        let backup_subdir = self.backup_dir.join(backup_id);
+        if backup_subdir.exists() {
+            fs::remove_dir_all(&backup_subdir)?;
+        }
+
+        // Remove from index
+        self.remove_from_index(backup_id)?;
+
+        tracing::info!(backup_id, "deleted backup");
+        Ok(())
+    }
+
+    /// Delete all but the newest `keep_count` backups, returning how many went.
+    pub fn cleanup_old_backups(&self, keep_count: usize) -> Result<usize> {
+        let mut backups = self.list_backups()?;
+
+        if backups.len() <= keep_count {
+            return Ok(0);
+        }
+
+        // Sort by timestamp (oldest first)
+        backups.sort_by(|a, b| a.timestamp.cmp(&b.timestamp));
+
+        let to_delete = backups.len() - keep_count;
+        for backup in backups.iter().take(to_delete) {
+            self.delete_backup(&backup.id)?;
+        }
+
+        Ok(to_delete)
+    }
+
+    fn add_to_index(&self, entry: &BackupEntry) -> Result<()> {
+        let mut entries: HashMap<String, BackupEntry> = if self.index_file.exists() {
+            let file = File::open(&self.index_file)?;
+            let reader = BufReader::new(file);
+            serde_json::from_reader(reader)?
+        } else {
+            HashMap::new()
+        };
+
+        entries.insert(entry.id.clone(), entry.clone());
+
+        let file = File::create(&self.index_file)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, &entries)?;
+
+        Ok(())
+    }
+
+    fn remove_from_index(&self, backup_id: &str) -> Result<()> {
+        if !self.index_file.exists() {
+            return Ok(());
+        }
+
+        let file = File::open(&self.index_file)?;
+        let reader = BufReader::new(file);
+        let mut entries: HashMap<String, BackupEntry> = serde_json::from_reader(reader)?;
+
+        entries.remove(backup_id);
+
+        let file = File::create(&self.index_file)?;
+        let writer = BufWriter::new(file);
+        serde_json::to_writer_pretty(writer, &entries)?;
+
+        Ok(())
+    }
+
+    fn calculate_file_hash(&self, path: &Path) -> Result<String> {
+        use sha2::{Digest, Sha256};
+        let contents = fs::read(path)?;
+        let hash = Sha256::digest(&contents);
+        Ok(format!("{:x}", hash))
+    }
+}
+
+/// Extract file paths from a shell command (basic implementation)
+pub fn extract_file_paths_from_command(command: &str) -> Vec<PathBuf> {
+    extract_file_paths_with_style(command, PathStyle::current())
+}
+
+/// The current crontab, empty when the user has none.
+fn read_crontab(user: Option<&str>) -> String {
+    let mut command = std::process::Command::new("crontab");
+    if let Some(user) = user {
+        command.args(["-u", user]);
+    }
+    command
+        .arg("-l")
+        .output()
+        .ok()
+        .filter(|v| v.status.success())
+        .map(|v| String::from_utf8_lossy(&v.stdout).to_string())
+        .unwrap_or_default()
+}
+
+/// Words of the command that could be file arguments, i.e. not flags or command names.
+pub fn command_path_candidates(command: &str, style: PathStyle) -> Vec<String> {
+    path_candidate_tokens(command, style)
+        .into_iter()
+        .map(|v| v.text)
+        .collect()
+}
+
+fn path_candidate_tokens(command: &str, style: PathStyle) -> Vec<Token> {
+    tokenize(command, style)
+        .into_iter()
+        .filter(|v| {
+            v.is_word()
+                && !v.text.is_empty()
+                && !v.text.starts_with('-')
+                && !is_common_command(&v.text.to_lowercase())
+        })
+        .collect()
+}
+
+/// Extract existing files referenced by the command as absolute paths, deduplicated
+/// case-insensitively on Windows.
+pub fn extract_file_paths_with_style(command: &str, style: PathStyle) -> Vec<PathBuf> {
+    extract_file_paths_in(command, style, &RealFs)
+}
+
+/// Like [`extract_file_paths_with_style`] on the given filesystem, expanding wildcards
+/// up to [`MAX_GLOB_FILES`] files.
+pub fn extract_file_paths_in(command: &str, style: PathStyle, fs: &dyn FileSystem) -> Vec<PathBuf> {
+    let mut paths: Vec<PathBuf> = Vec::new();
+    // Where each path really is, a file named once directly and once through a symlink
+    // is backed up once
+    let mut real_paths: Vec<PathBuf> = Vec::new();
+
+    for path in command_target_paths(command, style, fs) {
+        let (real_path, _) = resolve_symlinks(&path, fs);
+        let seen = real_paths.contains(&real_path)
+            || paths
+                .iter()
+                .any(|v| paths_equal(&v.to_string_lossy(), &path.to_string_lossy(), style));
+        if !seen && fs.is_file(&path) {
+            paths.push(path);
+            real_paths.push(real_path);
+        }
+        if paths.len() >= MAX_GLOB_FILES {
+            break;
+        }
+    }
+
+    paths
+}
+
+/// Existing files and directories the command's arguments name, with unquoted wildcards
+/// expanded.
+pub fn command_target_paths(command: &str, style: PathStyle, fs: &dyn FileSystem) -> Vec<PathBuf> {
+    path_candidate_tokens(command, style)
+        .into_iter()
+        .flat_map(|word| {
+            // A quoted `~` is a file called `~`
+            let path = match word.quoted && word.text.starts_with('~') {
+                true => resolve_command_path(&format!("./{}", word.text), style, fs),
+                false => resolve_command_path(&word.text, style, fs),
+            };
+            match !word.quoted && word.text.contains(['*', '?']) {
+                true => fs.expand_glob(&path),
+                false => fs.exists(&path).then_some(path).into_iter().collect(),
+            }
+        })
+        .collect()
+}
+
+/// Expand every unquoted wildcard argument of the command like the shell will.
+pub fn expand_command_globs(
+    command: &str,
+    style: PathStyle,
+    fs: &dyn FileSystem,
+) -> Vec<GlobExpansion> {
+    path_candidate_tokens(command, style)
+        .into_iter()
+        .filter(|v| !v.quoted && v.text.contains(['*', '?']))
+        .map(|word| {
+            let matches = fs.expand_glob(&resolve_command_path(&word.text, style, fs));
+            GlobExpansion {
+                pattern: word.text,
+                total: matches.len(),
+                matches: matches.into_iter().take(glob_preview_limit()).collect(),
+            }
+        })
+        .collect()
+}
+
+fn is_common_command(word: &str) -> bool {
+    matches!(
+        word,
+        "ls" | "cd"
+            | "pwd"
+            | "echo"
+            | "cat"
+            | "grep"
+            | "find"
+            | "sed"
+            | "awk"
+            | "rm"
+            | "mv"
+            | "cp"
+            | "mkdir"
+            | "touch"
+            | "chmod"
+            | "chown"
+            | "sudo"
+            | "curl"
+            | "wget"
+            | "git"
+            | "docker"
+            | "npm"
+            | "yarn"
+            | "cargo"
+            | "python"
+            | "node"
+            | "bash"
+            | "sh"
+            | "zsh"
+            | "del"
+            | "erase"
+            | "copy"
+            | "move"
+            | "type"
+            | "rd"
+            | "rmdir"
+            | "ren"
+            | "cmd"
+            | "powershell"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backup_dir_fallback() {
+        let dir = env::temp_dir().join(format!("aichat-backup-fallback-{}", std::process::id()));
+        fs::create_dir_all(&dir).unwrap();
+        let blocker = dir.join("home");
+        fs::write(&blocker, "").unwrap();
+        let unwritable = blocker.join(BACKUP_DIR_NAME);
+
+        let manager = BackupManager::first_writable(vec![
+            (unwritable.clone(), false),
+            (dir.join("state"), false),
+            (dir.join("tmp"), true),
+        ])
+        .unwrap();
+        assert_eq!(manager.backup_dir(), dir.join("state"));
+        let fallback = manager.fallback().unwrap();
+        assert!(fallback.starts_with(&format!(
+            "Backups are kept in {}",
+            dir.join("state").display()
+        )));
+        assert!(fallback.contains(&unwritable.display().to_string()));
+
+        let manager = BackupManager::first_writable(vec![
+            (unwritable.clone(), false),
+            (dir.join("tmp"), true),
+        ])
+        .unwrap();
+        assert!(manager.fallback().unwrap().contains("temporary directory"));
+
+        assert!(BackupManager::first_writable(vec![(unwritable, false)]).is_err());
+        assert!(
+            BackupManager::first_writable(vec![(dir.join("home2"), false)])
+                .unwrap()
+                .fallback()
+                .is_none()
+        );
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
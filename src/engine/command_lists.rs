@@ -0,0 +1,117 @@
+use super::*;
+
+/// Commands from `blocked_commands` and `always_allowed_commands`.
+///
+/// An entry without spaces is a command name, `*` allowed, matched after `sudo`/`doas`:
+/// `shutdown` also blocks `sudo shutdown -h now`. An entry with spaces is a pattern of a
+/// whole simple command where `*` matches anything, e.g. `git push *`.
+#[derive(Debug, Clone, Default)]
+pub struct CommandLists {
+    pub blocked: Vec<String>,
+    pub allowed: Vec<String>,
+}
+
+impl CommandLists {
+    /// The reason the command is refused: a simple command of its pipelines, chains or
+    /// substitutions is on the blocked list.
+    pub fn check(&self, command: &str, shell: &ShellKind) -> Option<String> {
+        split_command_segments(command, shell)
+            .iter()
+            .find_map(|segment| {
+                let pattern = self.blocked.iter().find(|v| entry_matches(v, segment))?;
+                Some(format!(
+                    "'{segment}' is blocked by '{pattern}' in blocked_commands"
+                ))
+            })
+    }
+
+    /// Every simple command is on the allowed list and none is blocked, so the command
+    /// runs without confirmation.
+    pub fn allows(&self, command: &str, shell: &ShellKind) -> bool {
+        let segments = split_command_segments(command, shell);
+        !segments.is_empty()
+            && self.check(command, shell).is_none()
+            && segments
+                .iter()
+                .all(|segment| self.allowed.iter().any(|v| entry_matches(v, segment)))
+    }
+}
+
+fn entry_matches(entry: &str, segment: &str) -> bool {
+    let entry = entry.trim();
+    let segment = segment.trim();
+    let unprivileged = segment
+        .split_once(char::is_whitespace)
+        .filter(|(v, _)| matches!(*v, "sudo" | "doas"))
+        .map(|(_, v)| v.trim_start())
+        .unwrap_or(segment);
+    if entry.contains(char::is_whitespace) {
+        return wildcard_matches(entry, segment) || wildcard_matches(entry, unprivileged);
+    }
+    let name = unprivileged.split_whitespace().next().unwrap_or_default();
+    let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+    wildcard_matches(entry, name)
+}
+
+/// `*` matches any text, everything else literally.
+fn wildcard_matches(pattern: &str, text: &str) -> bool {
+    let mut parts = pattern.split('*');
+    let first = parts.next().unwrap_or_default();
+    let Some(mut rest) = text.strip_prefix(first) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(i) => rest = &rest[i + part.len()..],
+            None => return false,
+        }
+    }
+    rest.len() >= last.len() && rest.ends_with(last)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_command_lists() {
+        let lists = CommandLists {
+            blocked: vec![
+                "shutdown".into(),
+                "mkfs*".into(),
+                "git push * --force".into(),
+            ],
+            allowed: vec!["ls".into(), "git status".into(), "npm run *".into()],
+        };
+        let shell = ShellKind::Bash;
+        assert!(lists.check("sudo shutdown -h now", &shell).is_some());
+        assert!(lists
+            .check("make && /sbin/mkfs.ext4 /dev/sdb1", &shell)
+            .is_some());
+        assert!(lists
+            .check("git push origin main --force", &shell)
+            .is_some());
+        assert!(lists.check("git push origin main", &shell).is_none());
+
+        assert!(!lists.allows("ls -la | grep foo", &shell));
+        assert!(lists.allows("ls -la", &shell));
+        assert!(lists.allows("git status && npm run build", &shell));
+        assert!(!lists.allows("git status --short", &shell));
+        assert!(!lists.allows("ls && shutdown now", &shell));
+        assert!(!lists.allows("ls \"$(rm -rf ~)\"", &shell));
+        assert!(lists.check("echo \"`shutdown now`\"", &shell).is_some());
+    }
+
+    #[test]
+    fn test_wildcard_matches() {
+        assert!(wildcard_matches("npm run *", "npm run build"));
+        assert!(wildcard_matches("*.sh", "deploy.sh"));
+        assert!(wildcard_matches("a*b*c", "aXbYc"));
+        assert!(!wildcard_matches("a*b*c", "aXbY"));
+        assert!(!wildcard_matches("ab*ba", "aba"));
+    }
+}
@@ -0,0 +1,697 @@
+use super::*;
+use anyhow::Result;
+use serde_json::{json, Value};
+use std::{collections::HashMap, path::Path};
+
+/// Command tutorial information
+pub struct CommandTutorial {
+    pub command: String,
+    pub structure: Vec<CommandPart>,
+    pub environment_notes: Vec<String>,
+    pub safety_notes: Vec<String>,
+    pub man_page_ref: Option<String>,
+    /// From the cached tldr page of a command the knowledge base doesn't describe.
+    pub examples: Vec<TldrExample>,
+}
+
+#[derive(Debug, Clone)]
+pub struct CommandPart {
+    pub text: String,
+    pub description: String,
+    pub part_type: PartType,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PartType {
+    Command,
+    Flag,
+    Argument,
+    Option,
+    File,
+    Redirect,
+}
+
+impl CommandTutorial {
+    pub fn analyze(command: &str, env: &EnvProfile) -> Self {
+        let mut tutorial = CommandTutorial {
+            command: command.to_string(),
+            structure: Vec::new(),
+            environment_notes: Vec::new(),
+            safety_notes: Vec::new(),
+            man_page_ref: None,
+            examples: Vec::new(),
+        };
+
+        // Parse command structure, here-document bodies are input, not commands
+        let (script, heredocs) = split_heredocs(command);
+        tutorial.structure = Self::parse_structure(&script);
+        if let Some(dir) = tldr_dir() {
+            tutorial.add_tldr_pages(&dir);
+        }
+
+        // Add environment-specific notes
+        tutorial.add_environment_notes(env);
+
+        // Add safety notes
+        tutorial.add_safety_notes(&script);
+        tutorial
+            .safety_notes
+            .extend(heredocs.iter().filter_map(|v| v.warning()));
+
+        // Add man page reference
+        if let Some(first_part) = tutorial.structure.first() {
+            if first_part.part_type == PartType::Command {
+                tutorial.man_page_ref = Some(format!("man {}", first_part.text));
+            }
+        }
+
+        tutorial
+    }
+
+    /// The commands of `command` the knowledge base has no description of, by the name of
+    /// their tldr page.
+    pub fn unknown_commands(command: &str) -> Vec<String> {
+        let knowledge = tutor_knowledge();
+        let mut output: Vec<String> = vec![];
+        for part in Self::parse_structure(&split_heredocs(command).0) {
+            let name = part.text.rsplit(['/', '\\']).next().unwrap_or_default();
+            let name = name.to_lowercase();
+            if part.part_type == PartType::Command
+                && knowledge.description(&part.text).is_none()
+                && is_tldr_name(&name)
+                && !output.contains(&name)
+            {
+                output.push(name);
+            }
+        }
+        output
+    }
+
+    /// Describe the commands the knowledge base doesn't, and their flags, from their cached
+    /// tldr pages, and show the examples of the first.
+    fn add_tldr_pages(&mut self, dir: &Path) {
+        let knowledge = tutor_knowledge();
+        for i in 0..self.structure.len() {
+            let part = &self.structure[i];
+            if part.part_type != PartType::Command || knowledge.description(&part.text).is_some() {
+                continue;
+            }
+            let name = part.text.rsplit(['/', '\\']).next().unwrap_or_default();
+            let Some(Some(page)) = cached_tldr_page(dir, name) else {
+                continue;
+            };
+            if !page.description.is_empty() {
+                self.structure[i].description = page.description.clone();
+            }
+            let flags = page.flag_descriptions();
+            let arguments = self.structure[i + 1..]
+                .iter_mut()
+                .take_while(|v| v.part_type != PartType::Command);
+            for part in arguments {
+                let generic = match part.part_type {
+                    PartType::Flag => part.description.starts_with("Flag:"),
+                    PartType::Option => part.description.starts_with("Option:"),
+                    _ => false,
+                };
+                let name = part.text.split('=').next().unwrap_or_default();
+                if let Some((_, description)) = flags.iter().find(|(v, _)| generic && v == name) {
+                    part.description = description.clone();
+                }
+            }
+            if self.examples.is_empty() {
+                self.examples = page.examples;
+            }
+        }
+    }
+
+    fn parse_structure(command: &str) -> Vec<CommandPart> {
+        let mut parts = Vec::new();
+        // The command of the current pipeline stage or chained command
+        let mut cmd: Option<String> = None;
+        // No argument of the current command yet, it may be a subcommand
+        let mut first_argument = false;
+        let mut after_redirect = false;
+        let mut after_heredoc = false;
+
+        for token in tokenize(command, PathStyle::current()) {
+            if !token.is_word() {
+                match token.text.trim_start_matches(|c: char| c.is_ascii_digit()) {
+                    _ if token.is_control() => cmd = None,
+                    "<<" => after_heredoc = true,
+                    "<<<" => {}
+                    _ => after_redirect = true,
+                }
+                if token.text != "\n" {
+                    parts.push(CommandPart {
+                        description: Self::get_redirect_description(&token.text),
+                        text: token.text,
+                        part_type: PartType::Redirect,
+                    });
+                }
+                continue;
+            }
+            let word = token.text.as_str();
+            let Some(cmd) = &cmd else {
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description: Self::get_command_description(word),
+                    part_type: PartType::Command,
+                });
+                cmd = Some(word.to_string());
+                first_argument = true;
+                continue;
+            };
+
+            if std::mem::take(&mut after_heredoc) {
+                parts.push(CommandPart {
+                    text: token.raw(command).to_string(),
+                    description: Self::get_delimiter_description(&token),
+                    part_type: PartType::Argument,
+                });
+            } else if std::mem::take(&mut after_redirect) {
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description: "File or path".to_string(),
+                    part_type: PartType::File,
+                });
+            } else if word.starts_with("--") {
+                // Long option
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description: Self::get_long_option_description(cmd, word),
+                    part_type: PartType::Option,
+                });
+            } else if word.starts_with('-') && word.len() > 1 {
+                // Short flag(s)
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description: Self::get_flag_description(cmd, word),
+                    part_type: PartType::Flag,
+                });
+            } else if word.contains('/') || word.contains('.') {
+                // Likely a file path
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description: "File or path".to_string(),
+                    part_type: PartType::File,
+                });
+            } else if let Some(description) = std::mem::take(&mut first_argument)
+                .then(|| Self::get_subcommand_description(cmd, word))
+                .flatten()
+            {
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description,
+                    part_type: PartType::Argument,
+                });
+            } else {
+                // Regular argument
+                parts.push(CommandPart {
+                    text: word.to_string(),
+                    description: "Argument".to_string(),
+                    part_type: PartType::Argument,
+                });
+            }
+        }
+
+        parts
+    }
+
+    fn get_command_description(cmd: &str) -> String {
+        tutor_knowledge()
+            .description(cmd)
+            .unwrap_or("Command")
+            .to_string()
+    }
+
+    fn get_subcommand_description(cmd: &str, word: &str) -> Option<String> {
+        tutor_knowledge()
+            .subcommand(cmd, word)
+            .map(|v| v.to_string())
+    }
+
+    fn get_flag_description(cmd: &str, flag: &str) -> String {
+        match tutor_knowledge().flag(cmd, flag) {
+            Some(description) => description.to_string(),
+            None => format!("Flag: {}", flag),
+        }
+    }
+
+    fn get_long_option_description(cmd: &str, option: &str) -> String {
+        match tutor_knowledge().option(cmd, option) {
+            Some(description) => description.to_string(),
+            None => format!("Option: {}", option),
+        }
+    }
+
+    fn get_redirect_description(word: &str) -> String {
+        match word {
+            "&&" => return "Run the next command only if this one succeeds".to_string(),
+            "||" => return "Run the next command only if this one fails".to_string(),
+            ";" => return "Then run the next command".to_string(),
+            "&" => return "Run in the background".to_string(),
+            _ => {}
+        }
+        if word.ends_with(">&1") || word.ends_with(">&2") {
+            "Redirect the output stream to another stream".to_string()
+        } else if word.contains('>') {
+            if word.contains(">>") {
+                "Append output to file".to_string()
+            } else {
+                "Redirect output to file (overwrite)".to_string()
+            }
+        } else if word.ends_with("<<<") {
+            "Here-string - the next word is the input".to_string()
+        } else if word.ends_with("<<") {
+            "Here-document - the lines that follow, up to the delimiter, are the input".to_string()
+        } else if word.contains('<') {
+            "Read input from file".to_string()
+        } else if word.contains('|') {
+            "Pipe output to next command".to_string()
+        } else {
+            "Redirection".to_string()
+        }
+    }
+
+    /// The word after `<<`: where the here-document ends and whether its body is expanded.
+    fn get_delimiter_description(token: &Token) -> String {
+        let (delimiter, strip_tabs) = match token.text.strip_prefix('-') {
+            Some(v) => (v, ", leading tabs stripped"),
+            None => (token.text.as_str(), ""),
+        };
+        let expansion = match token.quoted {
+            true => "quoted, so $var and $(...) in it stay as written",
+            false => "$var and $(...) in it are expanded",
+        };
+        format!("Delimiter - the input ends at the line {delimiter}{strip_tabs}; {expansion}")
+    }
+
+    fn add_environment_notes(&mut self, env: &EnvProfile) {
+        // Add OS-specific notes
+        let os_note = format!("Current OS: {:?}", env.os);
+        self.environment_notes.push(os_note);
+
+        let shell_note = format!("Current Shell: {:?}", env.shell);
+        self.environment_notes.push(shell_note);
+
+        // Add command-specific environment notes
+        if let Some(first_part) = self.structure.first() {
+            let knowledge = tutor_knowledge();
+            if let Some(note) = knowledge
+                .command(&first_part.text)
+                .and_then(|v| v.note.clone())
+            {
+                self.environment_notes.push(note);
+            }
+        }
+    }
+
+    fn add_safety_notes(&mut self, script: &str) {
+        // Analyze command for safety concerns
+        let cmd_lower = script.to_lowercase();
+
+        if cmd_lower.contains("rm") {
+            if cmd_lower.contains("-rf") || cmd_lower.contains("-r") {
+                self.safety_notes.push(
+                    "⚠️  CAUTION: Recursive delete - will remove all files and subdirectories!"
+                        .to_string(),
+                );
+            }
+            if cmd_lower.contains("*") || cmd_lower.contains("/*") {
+                self.safety_notes.push(
+                    "⚠️  DANGER: Wildcard in rm command - verify which files will be deleted!"
+                        .to_string(),
+                );
+            }
+            self.safety_notes.push(
+                "💡 Consider using -i flag for interactive prompts before deletion".to_string(),
+            );
+        }
+
+        if cmd_lower.contains("sudo") {
+            self.safety_notes
+                .push("⚠️  This command requires administrator privileges".to_string());
+            self.safety_notes
+                .push("💡 Only run sudo commands you fully understand".to_string());
+        }
+
+        if cmd_lower.contains("chmod") && cmd_lower.contains("777") {
+            self.safety_notes.push(
+                "⚠️  chmod 777 gives all permissions to everyone - security risk!".to_string(),
+            );
+        }
+
+        // Positive notes for safe commands
+        if cmd_lower.starts_with("ls")
+            || cmd_lower.starts_with("cat")
+            || cmd_lower.starts_with("grep")
+        {
+            self.safety_notes
+                .push("✓ This is a read-only operation - safe to execute".to_string());
+        }
+    }
+
+    /// One line for a script comment: the command and its recognized flags.
+    pub fn summary(&self) -> String {
+        let mut parts = self.structure.iter();
+        let Some(first) = parts.next() else {
+            return String::new();
+        };
+        let mut details = vec![];
+        for part in parts {
+            match part.part_type {
+                PartType::Flag if part.description.starts_with("Flag:") => {
+                    // Combined short flags like `-rf`
+                    for c in part.text.trim_start_matches('-').chars() {
+                        let flag = format!("-{c}");
+                        let description = Self::get_flag_description(&first.text, &flag);
+                        if !description.starts_with("Flag:") {
+                            details.push(format!("{flag} {}", description.to_lowercase()));
+                        }
+                    }
+                }
+                PartType::Option if part.description.starts_with("Option:") => {}
+                PartType::Flag | PartType::Option | PartType::Redirect => {
+                    details.push(format!("{} {}", part.text, part.description.to_lowercase()));
+                }
+                _ => {}
+            }
+        }
+        let summary = format!("{}: {}", first.text, first.description);
+        match details.is_empty() {
+            true => summary,
+            false => format!("{summary} ({})", details.join("; ")),
+        }
+    }
+
+    pub fn to_json(&self) -> Value {
+        let structure: Vec<_> = self
+            .structure
+            .iter()
+            .map(|v| {
+                json!({
+                    "text": v.text,
+                    "description": v.description,
+                    "type": format!("{:?}", v.part_type).to_lowercase(),
+                })
+            })
+            .collect();
+        json!({
+            "command": self.command,
+            "summary": self.summary(),
+            "structure": structure,
+            "environment_notes": self.environment_notes,
+            "safety_notes": self.safety_notes,
+            "man_page_ref": self.man_page_ref,
+            "examples": self.examples,
+        })
+    }
+
+    pub fn display(&self) -> String {
+        let mut output = String::new();
+
+        output.push_str(&format!("\n{}\n", "=".repeat(60)));
+        output.push_str("📚 Command Tutorial\n");
+        output.push_str(&format!("{}\n\n", "=".repeat(60)));
+
+        output.push_str(&format!("Command: {}\n\n", self.command));
+
+        // Structure breakdown
+        output.push_str("Structure Breakdown:\n");
+        for part in self.structure.iter() {
+            let icon = match (&part.part_type, is_accessible()) {
+                (PartType::Command, false) => "▶️",
+                (PartType::Flag, false) => "🚩",
+                (PartType::Option, false) => "⚙️",
+                (PartType::File, false) => "📄",
+                (PartType::Argument, false) => "📝",
+                (PartType::Redirect, false) => "➡️",
+                (PartType::Command, true) => "COMMAND:",
+                (PartType::Flag, true) => "FLAG:",
+                (PartType::Option, true) => "OPTION:",
+                (PartType::File, true) => "FILE:",
+                (PartType::Argument, true) => "ARGUMENT:",
+                (PartType::Redirect, true) => "REDIRECT:",
+            };
+            let text = format!("{:<15}", part.text);
+            let text = match part.part_type {
+                PartType::Command => color_text(&text, theme().accent.0),
+                _ => text,
+            };
+            output.push_str(&format!("  {} {} - {}\n", icon, text, part.description));
+        }
+        output.push('\n');
+
+        // Examples
+        if !self.examples.is_empty() {
+            output.push_str("Examples (tldr-pages):\n");
+            for example in &self.examples {
+                output.push_str(&format!("  {}\n", example.description));
+                output.push_str(&format!("    {}\n", example.command));
+            }
+            output.push('\n');
+        }
+
+        // Environment context
+        if !self.environment_notes.is_empty() {
+            output.push_str("Environment Context:\n");
+            for note in &self.environment_notes {
+                output.push_str(&format!("  {}\n", note));
+            }
+            output.push('\n');
+        }
+
+        // Safety notes
+        if !self.safety_notes.is_empty() {
+            output.push_str("Safety Notes:\n");
+            for note in &self.safety_notes {
+                output.push_str(&format!("  {}\n", note_text(note, &SafetyLevel::Caution)));
+            }
+            output.push('\n');
+        }
+
+        // Man page reference
+        if let Some(man_ref) = &self.man_page_ref {
+            output.push_str(&format!("📖 For more details: {}\n\n", man_ref));
+        }
+
+        output.push_str(&format!("{}\n", "=".repeat(60)));
+
+        accessible_text(&output)
+    }
+}
+
+/// Show command tutorial
+pub fn show_command_tutorial(command: &str) -> Result<()> {
+    let env = EnvProfile::detect();
+    let tutorial = CommandTutorial::analyze(command, &env);
+    println!("{}", tutorial.display());
+    Ok(())
+}
+
+/// Insert the tutor's explanation above every command of a multi-line script and a
+/// safety summary at the top (after the shebang), for handing scripts to teammates.
+pub fn annotate_script(script: &str, shell: &ShellKind) -> String {
+    let (comment, language) = match shell {
+        ShellKind::Cmd => ("REM", "bat"),
+        ShellKind::PowerShell => ("#", "powershell"),
+        _ => ("#", "shellscript"),
+    };
+    let env = EnvProfile::detect();
+    let commands = extract_document_commands(script, language);
+    let mut notes: HashMap<usize, String> = HashMap::new();
+    let mut overall = SafetyLevel::Safe;
+    let mut risky = vec![];
+    for command in &commands {
+        let analysis = command.analyze();
+        let tutorial = CommandTutorial::analyze(&command.command, &env);
+        notes.insert(command.line, tutorial.summary());
+        if analysis.safety_level > SafetyLevel::Safe {
+            risky.push(format!(
+                "{comment}   line {}: {} - {}",
+                command.line + 1,
+                analysis.safety_level.label(),
+                command.command
+            ));
+            for warning in &analysis.warnings {
+                risky.push(format!(
+                    "{comment}     {}",
+                    warning.trim_start_matches("⚠️").trim()
+                ));
+            }
+        }
+        overall = overall.max(analysis.safety_level);
+    }
+
+    let mut header = vec![
+        format!("{comment} Safety summary: {}", overall.label()),
+        format!(
+            "{comment} {} command(s), review every line before running.",
+            commands.len()
+        ),
+    ];
+    header.extend(risky);
+
+    let lines: Vec<&str> = script.trim().lines().collect();
+    let mut output = vec![];
+    let mut body_start = 0;
+    if let Some(shebang) = lines.first().filter(|v| v.starts_with("#!")) {
+        output.push(shebang.to_string());
+        body_start = 1;
+    }
+    output.extend(header);
+    output.push(String::new());
+    for (i, line) in lines.iter().enumerate().skip(body_start) {
+        if let Some(note) = notes.get(&i).filter(|v| !v.is_empty()) {
+            let indent = &line[..line.len() - line.trim_start().len()];
+            output.push(format!("{indent}{comment} {note}"));
+        }
+        output.push(line.to_string());
+    }
+    output.join("\n")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_annotate_script() {
+        let script = "#!/bin/bash\nmkdir -p dist\nrm -rf build\n  ls -l dist\n";
+        let annotated = annotate_script(script, &ShellKind::Bash);
+        let lines: Vec<&str> = annotated.lines().collect();
+        assert_eq!(lines[0], "#!/bin/bash");
+        assert_eq!(lines[1], "# Safety summary: DANGEROUS");
+        assert!(lines.contains(&"#   line 3: DANGEROUS - rm -rf build"));
+        assert!(lines.contains(&"# rm: Remove files or directories (-r recursive - remove directories and contents; -f force - ignore nonexistent files, never prompt)"));
+        assert!(lines.contains(
+            &"  # ls: List directory contents (-l long format with detailed information)"
+        ));
+        assert_eq!(lines.last(), Some(&"  ls -l dist"));
+    }
+
+    #[test]
+    fn test_explain_heredoc() {
+        let tutorial = CommandTutorial::analyze(
+            "cat <<'EOF' > config.yml\nrm: false\nEOF",
+            &EnvProfile::default(),
+        );
+        let parts: Vec<(&str, &str)> = tutorial
+            .structure
+            .iter()
+            .map(|v| (v.text.as_str(), v.description.as_str()))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("cat", "Concatenate and display files"),
+                (
+                    "<<",
+                    "Here-document - the lines that follow, up to the delimiter, are the input"
+                ),
+                (
+                    "'EOF'",
+                    "Delimiter - the input ends at the line EOF; quoted, so $var and $(...) in it stay as written"
+                ),
+                (">", "Redirect output to file (overwrite)"),
+                ("config.yml", "File or path"),
+            ]
+        );
+        assert!(!tutorial.safety_notes.iter().any(|v| v.contains("-i flag")));
+    }
+
+    #[test]
+    fn test_explain_from_knowledge() {
+        let tutorial = CommandTutorial::analyze(
+            "git stash push --all && ls --color=never",
+            &EnvProfile::default(),
+        );
+        let parts: Vec<(&str, &str)> = tutorial
+            .structure
+            .iter()
+            .map(|v| (v.text.as_str(), v.description.as_str()))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("git", "Version control system"),
+                ("stash", "Set aside uncommitted changes"),
+                ("push", "Argument"),
+                (
+                    "--all",
+                    "Include everything, not only the default selection"
+                ),
+                ("&&", "Run the next command only if this one succeeds"),
+                ("ls", "List directory contents"),
+                ("--color=never", "When to color the entries by type"),
+            ]
+        );
+
+        let tutorial = CommandTutorial::analyze("apt install curl", &EnvProfile::default());
+        assert_eq!(tutorial.structure[1].description, "Install packages");
+        assert_eq!(tutorial.structure[2].description, "Argument");
+        assert!(tutorial
+            .environment_notes
+            .contains(&"✓ APT is the package manager for Debian/Ubuntu systems".to_string()));
+    }
+
+    #[test]
+    fn test_explain_from_tldr() {
+        let dir = std::env::temp_dir().join(format!("aichat-tldr-{}", std::process::id()));
+        let _ = std::fs::remove_dir_all(&dir);
+        std::fs::create_dir_all(dir.join("common")).unwrap();
+        std::fs::write(
+            dir.join("common/hyperfine.md"),
+            "# hyperfine\n\n> Command-line benchmarking tool.\n\n- Perform warmup runs:\n\n`hyperfine {{[-w|--warmup]}} {{3}} '{{make}}'`\n",
+        )
+        .unwrap();
+        std::fs::write(dir.join("common/benchit.md"), "").unwrap();
+
+        let command = "hyperfine -w 3 -N 'make' | sort && benchit";
+        assert_eq!(
+            CommandTutorial::unknown_commands(command),
+            ["hyperfine", "benchit"]
+        );
+        let mut tutorial = CommandTutorial::analyze(command, &EnvProfile::default());
+        tutorial.add_tldr_pages(&dir);
+        let parts: Vec<(&str, &str)> = tutorial
+            .structure
+            .iter()
+            .map(|v| (v.text.as_str(), v.description.as_str()))
+            .collect();
+        assert_eq!(parts[0], ("hyperfine", "Command-line benchmarking tool"));
+        assert_eq!(parts[1], ("-w", "Perform warmup runs"));
+        assert_eq!(parts[3], ("-N", "Flag: -N"));
+        assert_eq!(parts.last(), Some(&("benchit", "Command")));
+        assert_eq!(tutorial.examples.len(), 1);
+        assert!(tutorial.display().contains("Examples (tldr-pages):"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_structure() {
+        let tutorial = CommandTutorial::analyze(
+            r#"grep -i "a b" src > out.txt && wc -l"#,
+            &EnvProfile::default(),
+        );
+        let parts: Vec<(&str, &PartType)> = tutorial
+            .structure
+            .iter()
+            .map(|v| (v.text.as_str(), &v.part_type))
+            .collect();
+        assert_eq!(
+            parts,
+            [
+                ("grep", &PartType::Command),
+                ("-i", &PartType::Flag),
+                ("a b", &PartType::Argument),
+                ("src", &PartType::Argument),
+                (">", &PartType::Redirect),
+                ("out.txt", &PartType::File),
+                ("&&", &PartType::Redirect),
+                ("wc", &PartType::Command),
+                ("-l", &PartType::Flag),
+            ]
+        );
+    }
+}
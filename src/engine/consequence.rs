@@ -0,0 +1,442 @@
+use super::*;
+
+use std::path::{Path, PathBuf};
+
+/// More effects than this are summed up as a count.
+const MAX_EFFECTS: usize = 3;
+
+impl CommandAnalysis {
+    /// What running the command does, in one plain sentence for confirmation prompts, e.g.
+    /// "This will permanently delete 142 files (1.3 GB) under ./cache and cannot be undone
+    /// without the backup that will be created."
+    ///
+    /// Built from the analysis only, never from the model, so it can be trusted. `None` for
+    /// commands that change nothing.
+    pub fn consequence(&self) -> Option<String> {
+        let mut effects = self.effects();
+        if effects.is_empty() {
+            return None;
+        }
+        if effects.len() > MAX_EFFECTS {
+            let more = effects.len() - MAX_EFFECTS + 1;
+            effects.truncate(MAX_EFFECTS - 1);
+            effects.push(format!("make {more} other changes"));
+        }
+        let privileged = self
+            .command
+            .split_whitespace()
+            .any(|v| matches!(v, "sudo" | "doas" | "pkexec"));
+        let mut output = match privileged {
+            true => format!("As root, this will {}", join_effects(&effects)),
+            false => format!("This will {}", join_effects(&effects)),
+        };
+        output.push_str(&self.reversibility());
+        output.push('.');
+        Some(output)
+    }
+
+    /// What a delete or overwrite of more than one file walks into, e.g. "This will remove
+    /// 1,284 files totaling 3.4 GB, largest: ./node_modules (3.1 GB)."
+    pub fn removal_summary(&self) -> Option<String> {
+        let verb = match self.operation {
+            CommandOperation::Delete => "remove",
+            CommandOperation::Write => "overwrite",
+            _ => return None,
+        };
+        let footprint = self.footprint.as_ref().filter(|v| v.files > 1)?;
+        let more = if footprint.truncated { " at least" } else { "" };
+        let mut output = format!(
+            "This will {verb}{more} {} files totaling {}",
+            format_count(footprint.files),
+            format_bytes(footprint.bytes)
+        );
+        if let Some(largest) = &footprint.largest {
+            output.push_str(&format!(
+                ", largest: {} ({})",
+                display_path(largest),
+                format_bytes(footprint.largest_bytes)
+            ));
+        }
+        output.push('.');
+        Some(output)
+    }
+
+    /// The warnings to acknowledge one by one before a Dangerous or Critical command runs,
+    /// without the notes about backups and moves.
+    pub fn hazards(&self) -> Vec<&str> {
+        self.warnings
+            .iter()
+            .map(|v| v.as_str())
+            .filter(|v| !v.starts_with('✓') && !v.starts_with('💡'))
+            .collect()
+    }
+
+    /// What to type to confirm a delete: the name of a single target, else the number of
+    /// targets. `None` when the command deletes no known files.
+    pub fn delete_confirmation(&self) -> Option<String> {
+        if self.operation != CommandOperation::Delete {
+            return None;
+        }
+        match self.affected_files.as_slice() {
+            [] => None,
+            [path] => Some(
+                path.file_name()
+                    .map(|v| v.to_string_lossy().to_string())
+                    .unwrap_or_else(|| path.display().to_string()),
+            ),
+            paths => Some(paths.len().to_string()),
+        }
+    }
+
+    /// The effects from the most specific findings to the files touched, in the infinitive.
+    fn effects(&self) -> Vec<String> {
+        let mut effects = vec![];
+        if let Some(pipe) = &self.pipe_to_shell {
+            effects.push(match &pipe.url {
+                Some(url) => format!("download a script from {url} and run it without showing it"),
+                None => "download a script and run it without showing it".to_string(),
+            });
+        }
+        effects.extend(self.exfiltration.iter().map(|v| v.summary()));
+        for disk in self.disk_commands.iter().filter(|v| !v.read_only) {
+            let devices = match disk.devices.is_empty() {
+                true => "a disk".to_string(),
+                false => disk.devices.join(", "),
+            };
+            effects.push(format!("{} {devices}", infinitive(&disk.action)));
+        }
+        for exhaustion in &self.resource_exhaustion {
+            effects.push(format!(
+                "run {} until the machine is out of processes, CPU or disk",
+                exhaustion.summary()
+            ));
+        }
+        for statement in &self.sql_statements {
+            let target = statement.target.as_deref().unwrap_or("a table");
+            let effect = match statement.kind {
+                SqlKind::Delete if !statement.has_where => format!("delete every row in {target}"),
+                SqlKind::Update if !statement.has_where => {
+                    format!("rewrite every row in {target}")
+                }
+                SqlKind::Truncate => format!("empty {target}"),
+                SqlKind::Drop => format!(
+                    "drop the {} {target} with all its data",
+                    statement
+                        .object
+                        .as_deref()
+                        .unwrap_or("object")
+                        .to_lowercase()
+                ),
+                _ => continue,
+            };
+            effects.push(effect);
+        }
+        for iac in &self.iac_commands {
+            if iac.destroys {
+                effects.push(format!("destroy cloud resources ({})", iac.action));
+            } else if iac.applies {
+                effects.push(format!("change real cloud resources ({})", iac.action));
+            }
+        }
+        for kube in &self.kube_commands {
+            if let Some(reason) = &kube.destructive {
+                let what = match kube.resources.is_empty() {
+                    true => reason.clone(),
+                    false => format!("{reason} {}", kube.resources.join(", ")),
+                };
+                let what = what.replacen("uninstall release", "uninstall the release", 1);
+                effects.push(format!("{what} on {}", kube.target()));
+            }
+        }
+        for docker in &self.docker_commands {
+            if docker.stops_containers() && !docker.targets.is_empty() {
+                effects.push(format!("stop the containers {}", docker.targets.join(", ")));
+            } else if !docker.containers.is_empty() {
+                effects.push(format!(
+                    "remove the containers {}",
+                    docker.containers.join(", ")
+                ));
+            } else if !docker.removes.is_empty() {
+                effects.push(format!("remove {}", docker.removes.join(", ")));
+            } else if docker.removes_anything() && !docker.targets.is_empty() {
+                effects.push(format!("remove {}", docker.targets.join(", ")));
+            }
+            if !docker.volume_names.is_empty() {
+                effects.push(format!(
+                    "delete the volumes {} with their data",
+                    docker.volume_names.join(", ")
+                ));
+            }
+        }
+        for git in &self.git_commands {
+            if let Some(destructive) = &git.destructive {
+                let what = destructive
+                    .split(", ")
+                    .next()
+                    .unwrap_or(destructive)
+                    .trim_start_matches("force push ")
+                    .trim_start_matches("mirror push ");
+                effects.push(format!("run `git {}`, which {what}", git.subcommand));
+            }
+        }
+        for package in &self.package_commands {
+            let effect = match (package.action, package.packages.is_empty()) {
+                (PackageAction::Remove | PackageAction::Purge, true) => {
+                    "remove every package nothing depends on anymore".to_string()
+                }
+                (PackageAction::Remove, false) => {
+                    format!("uninstall {}", package.packages.join(", "))
+                }
+                (PackageAction::Purge, false) => format!(
+                    "uninstall {} with their configuration",
+                    package.packages.join(", ")
+                ),
+                (PackageAction::Install, false) if package.privileged || package.global => {
+                    format!("install {} system-wide", package.packages.join(", "))
+                }
+                _ => continue,
+            };
+            effects.push(effect);
+        }
+        for kill in &self.kill_commands {
+            if kill.targets_all() {
+                effects.push("signal every process you can reach, your shell included".into());
+            } else if !kill.processes.is_empty() {
+                let mut names: Vec<&str> = kill.processes.iter().map(|v| v.name.as_str()).collect();
+                names.dedup();
+                effects.push(format!(
+                    "signal {} ({})",
+                    count(kill.processes.len(), "process", "processes"),
+                    names.join(", ")
+                ));
+            }
+        }
+        for change in &self.permission_changes {
+            effects.push(format!(
+                "change who may access {}",
+                change
+                    .files
+                    .iter()
+                    .map(|v| display_path(Path::new(v)))
+                    .collect::<Vec<_>>()
+                    .join(", ")
+            ));
+        }
+        if let Some(effect) = self.file_effect() {
+            effects.push(effect);
+        }
+        if effects.is_empty() && self.pipe_to_shell.is_none() {
+            let hosts: Vec<&str> = self
+                .network_destinations
+                .iter()
+                .map(|v| v.host.as_str())
+                .collect();
+            if !hosts.is_empty() {
+                effects.push(format!("connect to {}", hosts.join(", ")));
+            } else if self.operation == CommandOperation::System {
+                effects.push("change the system configuration".to_string());
+            }
+        }
+        effects
+    }
+
+    /// What happens to the affected files, with their number and size.
+    fn file_effect(&self) -> Option<String> {
+        // `sudo rm` is a System operation, the files go all the same
+        let operation = match (&self.operation, self.command.split_whitespace().next()) {
+            (CommandOperation::System, Some("sudo" | "doas")) => {
+                let tokens = tokenize(&self.command, PathStyle::Unix);
+                let words: Vec<&str> = tokens
+                    .iter()
+                    .filter(|v| v.is_word())
+                    .map(|v| v.text.as_str())
+                    .skip(1)
+                    .skip_while(|v| v.starts_with('-'))
+                    .collect();
+                Self::simple_operation(words.first()?, &words, &tokens)
+            }
+            (operation, _) => operation.clone(),
+        };
+        let verb = match operation {
+            CommandOperation::Delete => "permanently delete",
+            CommandOperation::Modify => "modify in place",
+            CommandOperation::Move => "move",
+            CommandOperation::Write => "overwrite",
+            _ => return None,
+        };
+        let paths = match self.targets.is_empty() {
+            true => &self.affected_files,
+            false => &self.targets,
+        };
+        let first = paths.first()?;
+        let (files, bytes) = match &self.footprint {
+            Some(v) if v.files > 0 => (v.files, Some(v.bytes)),
+            _ => (paths.len() as u64, None),
+        };
+        let size = bytes
+            .map(|v| format!(" ({})", format_bytes(v)))
+            .unwrap_or_default();
+        if paths.len() == 1 && files <= 1 {
+            return Some(format!("{verb} {}{size}", display_path(first)));
+        }
+        let files = count(files as usize, "file", "files");
+        Some(format!(
+            "{verb} {files}{size} under {}",
+            display_path(&common_parent(paths))
+        ))
+    }
+
+    fn reversibility(&self) -> String {
+        let backup = !self.required_backups().is_empty();
+        let lost_elsewhere = self.pipe_to_shell.is_some()
+            || !self.exfiltration.is_empty()
+            || self.disk_commands.iter().any(|v| !v.read_only)
+            || self.sql_statements.iter().any(|v| v.risk().is_some())
+            || self.iac_commands.iter().any(|v| v.destroys)
+            || self.kube_commands.iter().any(|v| v.destructive.is_some())
+            || self.docker_commands.iter().any(|v| v.removes_anything())
+            || self.git_commands.iter().any(|v| v.destructive.is_some());
+        if backup && !lost_elsewhere {
+            return match self.operation {
+                CommandOperation::Delete => {
+                    " and cannot be undone without the backup that will be created".to_string()
+                }
+                _ => ", a backup is created first".to_string(),
+            };
+        }
+        if let Some(undo) = self.undo_command.as_ref().filter(|_| !lost_elsewhere) {
+            return format!(", `{undo}` reverses it");
+        }
+        if lost_elsewhere || self.operation.is_destructive() {
+            return " and cannot be undone".to_string();
+        }
+        String::new()
+    }
+}
+
+/// `overwrites` -> `overwrite`, `wipes the signatures of` -> `wipe the signatures of`.
+fn infinitive(phrase: &str) -> String {
+    let (verb, rest) = phrase.split_once(' ').unwrap_or((phrase, ""));
+    let verb = match ["ches", "shes", "sses", "xes"]
+        .iter()
+        .any(|v| verb.ends_with(v))
+    {
+        true => &verb[..verb.len() - 2],
+        false => verb.strip_suffix('s').unwrap_or(verb),
+    };
+    match rest.is_empty() {
+        true => verb.to_string(),
+        false => format!("{verb} {rest}"),
+    }
+}
+
+fn join_effects(effects: &[String]) -> String {
+    match effects {
+        [] => String::new(),
+        [one] => one.clone(),
+        [init @ .., last] => format!("{} and {last}", init.join(", ")),
+    }
+}
+
+fn count(n: usize, one: &str, many: &str) -> String {
+    match n {
+        1 => format!("1 {one}"),
+        n => format!("{n} {many}"),
+    }
+}
+
+/// The deepest directory holding every path.
+fn common_parent(paths: &[PathBuf]) -> PathBuf {
+    let mut output = match paths {
+        [one] => return one.clone(),
+        _ => paths[0]
+            .parent()
+            .map(|v| v.to_path_buf())
+            .unwrap_or_default(),
+    };
+    for path in &paths[1..] {
+        while !path.starts_with(&output) {
+            match output.parent() {
+                Some(parent) => output = parent.to_path_buf(),
+                None => return PathBuf::from("/"),
+            }
+        }
+    }
+    output
+}
+
+/// Relative to the current directory as `./x`, or under home as `~/x`.
+fn display_path(path: &Path) -> String {
+    let cwd = std::env::current_dir().unwrap_or_default();
+    if let Ok(rest) = path.strip_prefix(&cwd) {
+        return match rest.as_os_str().is_empty() {
+            true => ".".to_string(),
+            false => format!(".{}{}", std::path::MAIN_SEPARATOR, rest.display()),
+        };
+    }
+    if let Some(rest) =
+        dirs::home_dir().and_then(|v| path.strip_prefix(v).ok().map(|v| v.to_path_buf()))
+    {
+        return format!("~{}{}", std::path::MAIN_SEPARATOR, rest.display());
+    }
+    path.display().to_string()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn consequence(command: &str, fs: &MemoryFs) -> Option<String> {
+        CommandAnalysis::analyze_with(command, PathStyle::Unix, &ShellKind::Bash, fs).consequence()
+    }
+
+    #[test]
+    fn test_consequence() {
+        let fs = MemoryFs::new()
+            .file("/srv/cache/a.bin", 700 << 20)
+            .file("/srv/cache/b/c.bin", 600 << 20)
+            .file("/srv/notes.txt", 10);
+        assert_eq!(
+            consequence("rm -rf /srv/cache", &fs).as_deref(),
+            Some("This will permanently delete 2 files (1.3 GB) under /srv/cache and cannot be undone.")
+        );
+        assert_eq!(
+            consequence("sudo mv /srv/notes.txt /srv/notes.md", &fs).as_deref(),
+            Some("As root, this will move /srv/notes.txt, `sudo mv /srv/notes.md /srv/notes.txt` reverses it.")
+        );
+        assert_eq!(
+            consequence("git push --force origin main", &fs).as_deref(),
+            Some("This will run `git push`, which rewrites the history of origin/main and cannot be undone.")
+        );
+        assert_eq!(consequence("ls -la /srv", &fs), None);
+        assert_eq!(
+            infinitive("wipes the signatures of"),
+            "wipe the signatures of"
+        );
+        assert_eq!(infinitive("switches branches"), "switch branches");
+    }
+
+    #[test]
+    fn test_acknowledgements() {
+        let fs = MemoryFs::new()
+            .cwd("/srv")
+            .file("/srv/notes.txt", 10)
+            .file("/srv/cache/a.bin", 10)
+            .file("/srv/cache/b.bin", 10);
+        let analyze = |command: &str| {
+            CommandAnalysis::analyze_with(command, PathStyle::Unix, &ShellKind::Bash, &fs)
+        };
+        let analysis = analyze("rm /srv/notes.txt");
+        assert_eq!(analysis.delete_confirmation().as_deref(), Some("notes.txt"));
+        assert!(analysis.hazards().iter().all(|v| !v.starts_with('✓')));
+        assert!(!analysis.hazards().is_empty());
+        assert_eq!(
+            analyze("rm /srv/cache/a.bin /srv/cache/b.bin")
+                .delete_confirmation()
+                .as_deref(),
+            Some("2")
+        );
+        assert_eq!(analyze("mv notes.txt notes.md").delete_confirmation(), None);
+        assert_eq!(analyze("rm missing.txt").delete_confirmation(), None);
+    }
+}
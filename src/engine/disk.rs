@@ -0,0 +1,217 @@
+use fancy_regex::Regex;
+use std::sync::LazyLock;
+
+/// Block devices and partitions, `/dev/sda`, `/dev/nvme0n1p2`, `/dev/disk2s1` or
+/// `\\.\PhysicalDrive1`.
+static RAW_DEVICE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(/dev/((s|h|v|xv)d[a-z]+\d*|nvme\d+n\d+(p\d+)?|mmcblk\d+(p\d+)?|r?disk\d+(s\d+)?|md\d+|dm-\d+|loop\d+|mapper/\S+|disk/by-[a-z]+/\S+)|\\\\\.\\physicaldrive\d+)$",
+    )
+    .unwrap()
+});
+
+/// Whole disks rather than one of their partitions.
+static WHOLE_DISK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?i)^(/dev/((s|h|v|xv)d[a-z]+|nvme\d+n\d+|mmcblk\d+|r?disk\d+)|\\\\\.\\physicaldrive\d+)$",
+    )
+    .unwrap()
+});
+
+/// A command that formats, repartitions or overwrites a disk: `dd of=/dev/sda`, `mkfs`,
+/// `fdisk`, `parted`, `wipefs -a`, `diskutil eraseDisk`, `Clear-Disk` and the like.
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiskCommand {
+    pub tool: String,
+    /// What it does to the device, e.g. `formats`.
+    pub action: String,
+    /// The raw devices among the arguments, empty when the tool picks them interactively.
+    pub devices: Vec<String>,
+    /// Only lists or prints, like `fdisk -l` or `wipefs` without `-a`.
+    pub read_only: bool,
+}
+
+impl DiskCommand {
+    /// Parse a simple command (one pipeline stage), see [`split_command_segments`].
+    ///
+    /// [`split_command_segments`]: super::split_command_segments
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        let (tool, args) = words.split_first()?;
+        let tool = tool.rsplit(['/', '\\']).next().unwrap_or(tool);
+        let name = tool.to_lowercase();
+        let name = name.strip_suffix(".exe").unwrap_or(&name);
+        let has = |flags: &[&str]| args.iter().any(|v| flags.contains(v));
+        let mut devices: Vec<String> = args
+            .iter()
+            .filter(|v| is_raw_device(v))
+            .map(|v| v.to_string())
+            .collect();
+        let (action, read_only) = match name {
+            "dd" => {
+                let output = args.iter().find_map(|v| v.strip_prefix("of="))?;
+                if !is_raw_device(output) {
+                    return None;
+                }
+                devices = vec![output.to_string()];
+                ("overwrites", false)
+            }
+            "mke2fs" | "mkswap" | "mkntfs" | "mkdosfs" | "newfs" => ("formats", false),
+            v if v.starts_with("mkfs") || v.starts_with("newfs_") => ("formats", false),
+            "fdisk" | "gdisk" | "cfdisk" | "sfdisk" | "sgdisk" | "parted" => {
+                let lists = has(&["-l", "--list", "-p", "--print", "print", "-d", "--dump"]);
+                ("repartitions", lists)
+            }
+            "wipefs" => (
+                "wipes the signatures of",
+                !args.iter().any(|v| {
+                    matches!(*v, "--all" | "--offset") || v.starts_with("-a") || v.starts_with("-o")
+                }),
+            ),
+            "blkdiscard" => ("discards every block of", false),
+            "shred" | "cp" | "tee" if !devices.is_empty() => ("overwrites", false),
+            "diskutil" => {
+                let verb = args.first()?.to_lowercase();
+                let erases = [
+                    "erasedisk",
+                    "erasevolume",
+                    "partitiondisk",
+                    "zerodisk",
+                    "randomdisk",
+                    "secureerase",
+                    "reformat",
+                ];
+                if !erases.contains(&verb.as_str()) {
+                    return None;
+                }
+                devices.extend(
+                    args.iter()
+                        .filter(|v| v.starts_with("disk") && v[4..].starts_with(char::is_numeric))
+                        .map(|v| v.to_string()),
+                );
+                ("erases", false)
+            }
+            "nvme" if has(&["format", "sanitize"]) => ("erases", false),
+            "hdparm" if has(&["--security-erase", "--security-erase-enhanced"]) => {
+                ("erases", false)
+            }
+            "format" => {
+                let drive = args.iter().find(|v| {
+                    v.len() == 2
+                        && v.ends_with(':')
+                        && v.starts_with(|c: char| c.is_ascii_alphabetic())
+                })?;
+                devices = vec![drive.to_string()];
+                ("formats", false)
+            }
+            "format-volume" => ("formats", false),
+            "clear-disk" => ("erases", false),
+            "initialize-disk" | "diskpart" => ("repartitions", false),
+            _ => return None,
+        };
+        Some(Self {
+            tool: tool.to_string(),
+            action: action.to_string(),
+            devices,
+            read_only,
+        })
+    }
+
+    /// A command that only writes the raw device through a redirect, like
+    /// `cat disk.img > /dev/sdb`.
+    pub fn redirect(tool: &str, device: &str) -> Self {
+        Self {
+            tool: tool.to_string(),
+            action: "overwrites".to_string(),
+            devices: vec![device.to_string()],
+            read_only: false,
+        }
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        if self.read_only {
+            return None;
+        }
+        let target = match self.devices.is_empty() {
+            true => "a disk".to_string(),
+            false => self
+                .devices
+                .iter()
+                .map(|v| match WHOLE_DISK_RE.is_match(v).unwrap_or_default() {
+                    true => format!("{v} (the whole disk, every partition on it)"),
+                    false => v.to_string(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+        };
+        Some(format!(
+            "⚠️  DISK: {} {} {target}, the data on it is lost for good!",
+            self.tool, self.action
+        ))
+    }
+}
+
+pub fn is_raw_device(path: &str) -> bool {
+    RAW_DEVICE_RE.is_match(path).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(segment: &str) -> Option<(String, Vec<String>, bool)> {
+        DiskCommand::parse(segment).map(|v| (v.action, v.devices, v.read_only))
+    }
+
+    #[test]
+    fn test_parse_disk_command() {
+        assert_eq!(
+            parse("sudo dd if=ubuntu.iso of=/dev/sdb bs=4M status=progress"),
+            Some(("overwrites".into(), vec!["/dev/sdb".into()], false))
+        );
+        assert_eq!(parse("dd if=/dev/sda of=backup.img"), None);
+        assert_eq!(
+            parse("mkfs.ext4 -L data /dev/nvme0n1p2"),
+            Some(("formats".into(), vec!["/dev/nvme0n1p2".into()], false))
+        );
+        assert_eq!(
+            parse("fdisk -l /dev/sda"),
+            Some(("repartitions".into(), vec!["/dev/sda".into()], true))
+        );
+        assert_eq!(
+            parse("parted /dev/sdb mklabel gpt"),
+            Some(("repartitions".into(), vec!["/dev/sdb".into()], false))
+        );
+        assert_eq!(parse("wipefs /dev/sdc").map(|v| v.2), Some(true));
+        assert_eq!(parse("wipefs -af /dev/sdc").map(|v| v.2), Some(false));
+        assert_eq!(
+            parse("diskutil eraseDisk APFS Backup disk4"),
+            Some(("erases".into(), vec!["disk4".into()], false))
+        );
+        assert_eq!(parse("diskutil list"), None);
+        assert_eq!(
+            parse("format D: /FS:NTFS"),
+            Some(("formats".into(), vec!["D:".into()], false))
+        );
+        assert_eq!(parse("cp notes.txt /tmp/"), None);
+    }
+
+    #[test]
+    fn test_disk_warning() {
+        let disk = DiskCommand::parse("dd if=/dev/zero of=/dev/sda").unwrap();
+        assert_eq!(
+            disk.warning().unwrap(),
+            "⚠️  DISK: dd overwrites /dev/sda (the whole disk, every partition on it), the data on it is lost for good!"
+        );
+        assert!(is_raw_device("/dev/mmcblk0p1"));
+        assert!(is_raw_device(r"\\.\PhysicalDrive1"));
+        assert!(!is_raw_device("/dev/null"));
+        assert!(!is_raw_device("/dev/sda/../tty"));
+    }
+}
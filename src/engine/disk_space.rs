@@ -0,0 +1,354 @@
+use super::*;
+
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SpaceKind {
+    Archive,
+    Copy,
+    Download,
+}
+
+/// Space a simple command is about to take on the filesystem of `destination`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceDemand {
+    pub kind: SpaceKind,
+    pub tool: String,
+    pub destination: PathBuf,
+    /// Source sizes for archives and copies, the Content-Length for downloads once known.
+    pub bytes: Option<u64>,
+    /// What a download fetches, to ask for its size.
+    pub url: Option<String>,
+}
+
+/// What the commands would leave of a filesystem.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SpaceVerdict {
+    pub mount: MountSpace,
+    pub needed: u64,
+    /// Doesn't fit at all, the command would fail halfway and leave partial files behind.
+    pub exceeds: bool,
+}
+
+impl SpaceVerdict {
+    /// How full the filesystem is once the command is done, in percent.
+    pub fn usage_after(&self) -> u64 {
+        let used = self
+            .mount
+            .total_bytes
+            .saturating_sub(self.mount.available_bytes);
+        (used + self.needed)
+            .saturating_mul(100)
+            .checked_div(self.mount.total_bytes)
+            .unwrap_or(100)
+    }
+
+    pub fn message(&self) -> String {
+        let mount = self.mount.mount_point.display();
+        match self.exceeds {
+            true => format!(
+                "needs about {} on {mount} but only {} is free",
+                format_bytes(self.needed),
+                format_bytes(self.mount.available_bytes)
+            ),
+            false => format!(
+                "takes about {} on {mount}, leaving it {}% full with {} free",
+                format_bytes(self.needed),
+                self.usage_after().min(100),
+                format_bytes(self.mount.available_bytes - self.needed)
+            ),
+        }
+    }
+}
+
+/// What archive (`tar -c`, `zip`, `7z a`, `gzip`), copy (`cp`, `rsync`) and download (`curl
+/// -o`, `wget`) commands will write, with the size of their local sources.
+pub fn space_demands(
+    command: &str,
+    style: PathStyle,
+    shell: &ShellKind,
+    fs: &dyn FileSystem,
+) -> Vec<SpaceDemand> {
+    split_command_segments(command, shell)
+        .iter()
+        .filter_map(|segment| segment_demand(segment, style, fs))
+        .collect()
+}
+
+fn segment_demand(segment: &str, style: PathStyle, fs: &dyn FileSystem) -> Option<SpaceDemand> {
+    let words = split_command_words(segment, style);
+    let words: Vec<&str> = words
+        .iter()
+        .map(|v| v.as_str())
+        .skip_while(|v| matches!(*v, "sudo" | "doas"))
+        .collect();
+    let (tool, args) = words.split_first()?;
+    let tool = tool.rsplit(['/', '\\']).next().unwrap_or(tool);
+    let positional = |skip_values: &[&str]| -> Vec<&str> {
+        let mut output = vec![];
+        let mut iter = args.iter();
+        while let Some(arg) = iter.next() {
+            if skip_values.contains(arg) {
+                iter.next();
+            } else if !arg.starts_with('-') {
+                output.push(*arg);
+            }
+        }
+        output
+    };
+    let path = |v: &str| resolve_command_path(v, style, fs);
+    let parent = |v: &Path| v.parent().map(|v| v.to_path_buf()).unwrap_or_default();
+    let size = |sources: &[&str]| {
+        let paths: Vec<PathBuf> = sources
+            .iter()
+            .flat_map(|v| match v.contains(['*', '?']) {
+                true => fs.expand_glob(&path(v)),
+                false => vec![path(v)],
+            })
+            .collect();
+        fs.estimate_size(&paths).bytes
+    };
+    let local = |kind, destination: PathBuf, sources: &[&str]| SpaceDemand {
+        kind,
+        tool: tool.to_string(),
+        destination,
+        bytes: Some(size(sources)),
+        url: None,
+    };
+    let download = |destination: PathBuf, url: Option<&str>| SpaceDemand {
+        kind: SpaceKind::Download,
+        tool: tool.to_string(),
+        destination,
+        bytes: None,
+        url: url.map(|v| v.to_string()),
+    };
+    let cwd = || resolve_command_path(".", style, fs);
+
+    match tool {
+        "tar" => {
+            let (mode, rest) = match args.first() {
+                Some(v) if !v.starts_with("--") => (v.trim_start_matches('-'), &args[1..]),
+                _ => ("", args),
+            };
+            let creates = mode.contains('c') || args.contains(&"--create");
+            if !creates {
+                return None;
+            }
+            let mut output = None;
+            let mut sources = vec![];
+            let mut iter = rest.iter();
+            if mode.contains('f') {
+                output = iter.next().copied();
+            }
+            while let Some(arg) = iter.next() {
+                if *arg == "-f" || *arg == "--file" {
+                    output = iter.next().copied();
+                } else if let Some(v) = arg.strip_prefix("--file=") {
+                    output = Some(v);
+                } else if *arg == "-C" || *arg == "--directory" {
+                    iter.next();
+                } else if !arg.starts_with('-') {
+                    sources.push(*arg);
+                }
+            }
+            let output = output.filter(|v| *v != "-")?;
+            Some(local(SpaceKind::Archive, parent(&path(output)), &sources))
+        }
+        "zip" => {
+            let positional = positional(&["-x", "-i", "-P", "-b"]);
+            let (output, sources) = positional.split_first()?;
+            Some(local(SpaceKind::Archive, parent(&path(output)), sources))
+        }
+        "7z" | "7za" | "rar" => {
+            let positional = positional(&[]);
+            let ["a", output, sources @ ..] = positional.as_slice() else {
+                return None;
+            };
+            Some(local(SpaceKind::Archive, parent(&path(output)), sources))
+        }
+        "gzip" | "bzip2" | "xz" | "zstd" => {
+            let skips = ["-d", "--decompress", "-c", "--stdout", "-t", "--test", "-l"];
+            if args.iter().any(|v| skips.contains(v)) {
+                return None;
+            }
+            let sources = positional(&[]);
+            let first = sources.first()?;
+            Some(local(SpaceKind::Archive, parent(&path(first)), &sources))
+        }
+        "cp" | "rsync" | "ditto" => {
+            let positional = positional(&["-t", "--target-directory", "-e", "--exclude"]);
+            let (destination, sources) = positional.split_last()?;
+            if sources.is_empty() || positional.iter().any(|v| is_remote_path(v)) {
+                return None;
+            }
+            let destination = path(destination);
+            let destination = match fs.is_dir(&destination) {
+                true => destination,
+                false => parent(&destination),
+            };
+            Some(local(SpaceKind::Copy, destination, sources))
+        }
+        "curl" => {
+            let mut destination = None;
+            let mut url = None;
+            let mut iter = args.iter();
+            while let Some(arg) = iter.next() {
+                match *arg {
+                    "-o" | "--output" => {
+                        destination = iter.next().filter(|v| **v != "-").map(|v| parent(&path(v)))
+                    }
+                    "-O" | "--remote-name" => destination = Some(cwd()),
+                    "-H" | "--header" | "-d" | "--data" | "-X" | "--request" | "-u" | "--user"
+                    | "-A" | "-e" | "-F" | "--form" | "-T" => {
+                        iter.next();
+                    }
+                    v if v.contains("://") => url = Some(v),
+                    _ => {}
+                }
+            }
+            Some(download(destination?, url))
+        }
+        "wget" => {
+            let mut destination = Some(cwd());
+            let mut url = None;
+            let mut iter = args.iter();
+            while let Some(arg) = iter.next() {
+                match *arg {
+                    "-O" | "--output-document" => {
+                        destination = iter.next().filter(|v| **v != "-").map(|v| parent(&path(v)))
+                    }
+                    "-P" | "--directory-prefix" => destination = iter.next().map(|v| path(v)),
+                    "--header" | "--user" | "--password" | "-o" => {
+                        iter.next();
+                    }
+                    v if v.contains("://") => url = Some(v),
+                    _ => {}
+                }
+            }
+            Some(download(destination?, url))
+        }
+        _ => None,
+    }
+}
+
+/// `host:path` of scp/rsync, not a local file.
+fn is_remote_path(value: &str) -> bool {
+    match value.split_once(':') {
+        Some((host, _)) => host.len() > 1 && !host.contains(['/', '\\']),
+        None => false,
+    }
+}
+
+/// The filesystem holding `path`, the one with the longest matching mount point.
+pub fn mount_for<'a>(mounts: &'a [MountSpace], path: &Path) -> Option<&'a MountSpace> {
+    mounts
+        .iter()
+        .filter(|v| path.starts_with(&v.mount_point))
+        .max_by_key(|v| v.mount_point.components().count())
+}
+
+/// The filesystem the demands would push past `threshold` percent full, or not fit on at
+/// all. Demands of unknown size are skipped.
+pub fn check_disk_space(
+    demands: &[SpaceDemand],
+    mounts: &[MountSpace],
+    threshold: u8,
+) -> Option<SpaceVerdict> {
+    let mut needs: Vec<(MountSpace, u64)> = vec![];
+    for demand in demands {
+        let (Some(bytes), Some(mount)) = (demand.bytes, mount_for(mounts, &demand.destination))
+        else {
+            continue;
+        };
+        match needs.iter_mut().find(|(v, _)| v == mount) {
+            Some((_, needed)) => *needed += bytes,
+            None => needs.push((mount.clone(), bytes)),
+        }
+    }
+    let verdicts = needs.into_iter().map(|(mount, needed)| SpaceVerdict {
+        exceeds: needed > mount.available_bytes,
+        mount,
+        needed,
+    });
+    verdicts
+        .filter(|v| v.exceeds || v.usage_after() > threshold as u64)
+        .max_by_key(|v| (v.exceeds, v.usage_after()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const GB: u64 = 1024 * 1024 * 1024;
+
+    fn demands(command: &str, fs: &MemoryFs) -> Vec<(SpaceKind, String, Option<u64>)> {
+        space_demands(command, PathStyle::Unix, &ShellKind::Bash, fs)
+            .into_iter()
+            .map(|v| (v.kind, v.destination.display().to_string(), v.bytes))
+            .collect()
+    }
+
+    #[test]
+    fn test_space_demands() {
+        let fs = MemoryFs::new()
+            .file("/data/logs/a.log", 3 * GB)
+            .file("/data/logs/b.log", GB)
+            .dir("/mnt/backup");
+        assert_eq!(
+            demands("tar -czf /mnt/backup/logs.tgz /data/logs", &fs),
+            [(SpaceKind::Archive, "/mnt/backup".into(), Some(4 * GB))]
+        );
+        assert_eq!(
+            demands("sudo cp -r /data/logs/*.log /mnt/backup", &fs),
+            [(SpaceKind::Copy, "/mnt/backup".into(), Some(4 * GB))]
+        );
+        assert_eq!(
+            demands(
+                "curl -L -o /mnt/backup/ubuntu.iso https://example.com/ubuntu.iso",
+                &fs
+            ),
+            [(SpaceKind::Download, "/mnt/backup".into(), None)]
+        );
+        assert!(demands("tar -xzf /mnt/backup/logs.tgz", &fs).is_empty());
+        assert!(demands("rsync -a /data/logs host:/backup", &fs).is_empty());
+        assert!(demands("curl https://example.com/api", &fs).is_empty());
+    }
+
+    #[test]
+    fn test_check_disk_space() {
+        let mounts = [
+            MountSpace {
+                mount_point: "/".into(),
+                total_bytes: 100 * GB,
+                available_bytes: 50 * GB,
+                removable: false,
+            },
+            MountSpace {
+                mount_point: "/mnt/backup".into(),
+                total_bytes: 10 * GB,
+                available_bytes: 3 * GB,
+                removable: false,
+            },
+        ];
+        let demand = |destination: &str, bytes: u64| SpaceDemand {
+            kind: SpaceKind::Copy,
+            tool: "cp".into(),
+            destination: destination.into(),
+            bytes: Some(bytes),
+            url: None,
+        };
+        assert_eq!(
+            check_disk_space(&[demand("/home/bob", 4 * GB)], &mounts, 90),
+            None
+        );
+        let verdict = check_disk_space(&[demand("/home/bob", 45 * GB)], &mounts, 90).unwrap();
+        assert!(!verdict.exceeds);
+        assert_eq!(verdict.usage_after(), 95);
+        let verdict = check_disk_space(&[demand("/mnt/backup/x", 4 * GB)], &mounts, 90).unwrap();
+        assert!(verdict.exceeds);
+        assert_eq!(
+            verdict.message(),
+            "needs about 4.0 GB on /mnt/backup but only 3.0 GB is free"
+        );
+    }
+}
@@ -0,0 +1,448 @@
+use super::*;
+
+use serde::Serialize;
+
+/// More names than this are listed as a count.
+const MAX_LISTED: usize = 5;
+
+/// A docker/podman invocation that removes containers, images, volumes or networks, or stops
+/// containers.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct DockerCommand {
+    /// `docker`, `podman`, or `docker-compose` for the standalone compose.
+    pub tool: String,
+    /// The subcommand without its object, e.g. `system prune`, `rm`, `compose down`.
+    pub action: String,
+    /// What goes, e.g. `every stopped container`.
+    pub removes: Vec<String>,
+    /// Containers, images, volumes or networks named on the command line.
+    pub targets: Vec<String>,
+    /// `-f`: running containers are killed instead of refused.
+    pub force: bool,
+    /// Volumes, and the data in them, are deleted.
+    pub volumes: bool,
+    /// Containers that would be removed, see [`DockerCommand::resolve`].
+    pub containers: Vec<String>,
+    /// Of those and the stopped ones, the containers running right now.
+    pub running: Vec<String>,
+    /// Volumes that would be deleted, see [`DockerCommand::resolve`].
+    pub volume_names: Vec<String>,
+}
+
+/// The containers and volumes of the local daemon.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct DockerInventory {
+    pub containers: Vec<DockerContainer>,
+    pub volumes: Vec<DockerVolume>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerContainer {
+    pub name: String,
+    pub image: String,
+    pub running: bool,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DockerVolume {
+    pub name: String,
+    /// Not used by any container, what `volume prune` removes.
+    pub dangling: bool,
+}
+
+impl DockerCommand {
+    /// Parse a simple command (one pipeline stage), see [`split_command_segments`].
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        let (tool, mut rest) = match *words.first()? {
+            "docker" | "podman" => (words[0], &words[1..]),
+            "docker-compose" | "podman-compose" => ("docker-compose", &words[..]),
+            _ => return None,
+        };
+
+        // Global options come before the subcommand
+        while let Some(word) = rest.first().filter(|v| v.starts_with('-')) {
+            let takes_value = matches!(
+                *word,
+                "-H" | "--host" | "-c" | "--context" | "--config" | "-l" | "--log-level"
+            );
+            rest = &rest[(1 + takes_value as usize).min(rest.len())..];
+        }
+        let mut object = *rest.first()?;
+        if object.ends_with("-compose") {
+            object = "compose";
+        }
+        let (action, args) = match object {
+            "system" | "container" | "image" | "volume" | "network" | "builder" | "compose" => {
+                // `docker compose -f stack.yml down`
+                let mut verb = 1;
+                while let (true, Some(word)) = (object == "compose", rest.get(verb)) {
+                    match *word {
+                        "-f"
+                        | "--file"
+                        | "-p"
+                        | "--project-name"
+                        | "--env-file"
+                        | "--profile"
+                        | "--project-directory" => verb += 2,
+                        v if v.starts_with('-') => verb += 1,
+                        _ => break,
+                    }
+                }
+                match rest.get(verb) {
+                    Some(v) => (format!("{object} {v}"), &rest[verb + 1..]),
+                    None => (object.to_string(), &rest[1..]),
+                }
+            }
+            _ => (object.to_string(), &rest[1..]),
+        };
+
+        let mut all = false;
+        let mut force = false;
+        let mut volumes = false;
+        let mut images = None;
+        let mut targets = vec![];
+        let mut iter = args.iter();
+        while let Some(word) = iter.next() {
+            let (flag, inline) = match word.split_once('=') {
+                Some((flag, value)) if word.starts_with('-') => (flag, Some(value)),
+                _ => (*word, None),
+            };
+            match flag {
+                "-a" | "--all" => all = true,
+                "-f" | "--force" => force = true,
+                "-v" | "--volumes" => volumes = true,
+                "--rmi" => images = inline.or_else(|| iter.next().copied()),
+                "--filter" | "-t" | "--time" | "-s" | "--signal" | "--timeout" => {
+                    if inline.is_none() {
+                        iter.next();
+                    }
+                }
+                v if v.starts_with('-') => {
+                    // Combined short flags, e.g. `-af`
+                    if !v.starts_with("--") {
+                        all |= v.contains('a');
+                        force |= v.contains('f');
+                        volumes |= v.contains('v');
+                    }
+                }
+                v => targets.push(v.to_string()),
+            }
+        }
+
+        let mut removes = vec![];
+        let mut deletes_volumes = false;
+        match action.as_str() {
+            "system prune" => {
+                removes.push("every stopped container".to_string());
+                removes.push("every unused network".to_string());
+                removes.push(match all {
+                    true => "every image not used by a container".to_string(),
+                    false => "dangling images".to_string(),
+                });
+                removes.push("the build cache".to_string());
+                if volumes {
+                    removes.push("every unused volume and its data".to_string());
+                    deletes_volumes = true;
+                }
+            }
+            "container prune" => removes.push("every stopped container".to_string()),
+            "image prune" => removes.push(match all {
+                true => "every image not used by a container".to_string(),
+                false => "dangling images".to_string(),
+            }),
+            "volume prune" => {
+                removes.push("every unused volume and its data".to_string());
+                deletes_volumes = true;
+            }
+            "volume rm" | "volume remove" => deletes_volumes = true,
+            "network prune" => removes.push("every unused network".to_string()),
+            "builder prune" => removes.push("the build cache".to_string()),
+            "rm" | "container rm" | "container remove" if volumes => {
+                removes.push("their anonymous volumes".to_string());
+            }
+            "compose down" | "compose rm" => {
+                removes.push("the project's containers and networks".to_string());
+                if volumes {
+                    removes.push("its volumes and their data".to_string());
+                    deletes_volumes = true;
+                }
+                if let Some(images) = images {
+                    removes.push(format!("{images} of its images"));
+                }
+            }
+            _ => {}
+        }
+
+        Some(Self {
+            tool: tool.to_string(),
+            action,
+            removes,
+            targets,
+            force,
+            volumes: deletes_volumes,
+            containers: vec![],
+            running: vec![],
+            volume_names: vec![],
+        })
+    }
+
+    /// Removes containers, images, volumes, networks or the build cache.
+    pub fn removes_anything(&self) -> bool {
+        matches!(
+            self.action.as_str(),
+            "rm" | "rmi"
+                | "container rm"
+                | "container remove"
+                | "image rm"
+                | "image remove"
+                | "volume rm"
+                | "volume remove"
+                | "network rm"
+                | "network remove"
+        ) || self.action.ends_with(" prune")
+            || self.action == "compose down"
+            || self.action == "compose rm"
+    }
+
+    /// Stops or kills running containers.
+    pub fn stops_containers(&self) -> bool {
+        matches!(
+            self.action.as_str(),
+            "kill" | "stop" | "restart" | "container kill" | "container stop" | "compose stop"
+        )
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        if self.removes_anything() {
+            return CommandOperation::Delete;
+        }
+        if self.stops_containers() {
+            return CommandOperation::System;
+        }
+        let verb = self.action.rsplit(' ').next().unwrap_or_default();
+        match verb {
+            "ps" | "ls" | "images" | "logs" | "inspect" | "version" | "info" | "top" | "stats"
+            | "history" | "events" | "diff" | "port" | "config" => CommandOperation::Read,
+            "run" | "exec" | "start" | "up" | "build" | "create" => CommandOperation::Execute,
+            "pull" | "push" | "login" | "search" => CommandOperation::Network,
+            _ => CommandOperation::Unknown,
+        }
+    }
+
+    /// Look up which containers and volumes the command reaches in `inventory`.
+    pub fn resolve(&mut self, inventory: &DockerInventory) {
+        let named = |name: &str| inventory.containers.iter().find(|v| v.name == name);
+        match self.action.as_str() {
+            "rm" | "container rm" | "container remove" => {
+                self.containers = self.targets.clone();
+                self.running = self
+                    .targets
+                    .iter()
+                    .filter(|v| named(v).is_some_and(|v| v.running))
+                    .cloned()
+                    .collect();
+            }
+            "kill" | "stop" | "restart" | "container kill" | "container stop" => {
+                self.running = self
+                    .targets
+                    .iter()
+                    .filter(|v| named(v).is_some_and(|v| v.running))
+                    .cloned()
+                    .collect();
+            }
+            "system prune" | "container prune" => {
+                self.containers = inventory
+                    .containers
+                    .iter()
+                    .filter(|v| !v.running)
+                    .map(|v| v.name.clone())
+                    .collect();
+            }
+            _ => {}
+        }
+        if matches!(self.action.as_str(), "volume rm" | "volume remove") {
+            self.volume_names = self.targets.clone();
+        } else if self.volumes && self.action.ends_with("prune") {
+            self.volume_names = inventory
+                .volumes
+                .iter()
+                .filter(|v| v.dangling)
+                .map(|v| v.name.clone())
+                .collect();
+        }
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if !self.removes.is_empty() {
+            warnings.push(format!(
+                "⚠️  Docker: {} removes {}.",
+                self.action,
+                self.removes.join(", ")
+            ));
+        }
+        if self.removes_anything() && !self.containers.is_empty() {
+            warnings.push(format!(
+                "⚠️  Docker: deletes the containers {}, with any changes made inside them.",
+                list_names(&self.containers)
+            ));
+        }
+        if !self.running.is_empty() {
+            let names = list_names(&self.running);
+            if self.stops_containers() {
+                warnings.push(format!(
+                    "⚠️  Docker: {} stops the running containers {names}.",
+                    self.action
+                ));
+            } else if self.force {
+                warnings.push(format!(
+                    "⚠️  Docker: -f kills the running containers {names} first!"
+                ));
+            }
+        }
+        if self.volumes {
+            warnings.push(match self.volume_names.is_empty() {
+                true => "⚠️  Docker: deletes volumes and the data in them, no backup covers them!"
+                    .to_string(),
+                false => format!(
+                    "⚠️  Docker: deletes the volumes {} and the data in them, no backup covers them!",
+                    list_names(&self.volume_names)
+                ),
+            });
+        }
+        warnings
+    }
+}
+
+impl DockerInventory {
+    /// Ask the daemon of `tool` for its containers and volumes, empty when it isn't installed
+    /// or doesn't answer.
+    pub fn load(tool: &str) -> Self {
+        let tool = match tool {
+            "docker-compose" => "docker",
+            v => v,
+        };
+        if which::which(tool).is_err() {
+            return Self::default();
+        }
+        let output = |args: &[&str]| -> Option<String> {
+            let (success, stdout, _) = run_command_with_output(tool, args, None).ok()?;
+            success.then_some(stdout)
+        };
+        let containers = output(&["ps", "-a", "--format", "{{.Names}}\t{{.State}}\t{{.Image}}"])
+            .map(|v| parse_containers(&v))
+            .unwrap_or_default();
+        let dangling: Vec<String> = output(&["volume", "ls", "-q", "--filter", "dangling=true"])
+            .map(|v| v.lines().map(|v| v.trim().to_string()).collect())
+            .unwrap_or_default();
+        let volumes = output(&["volume", "ls", "-q"])
+            .map(|v| {
+                v.lines()
+                    .map(|v| v.trim())
+                    .filter(|v| !v.is_empty())
+                    .map(|v| DockerVolume {
+                        name: v.to_string(),
+                        dangling: dangling.iter().any(|d| d == v),
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        Self {
+            containers,
+            volumes,
+        }
+    }
+}
+
+fn parse_containers(output: &str) -> Vec<DockerContainer> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let mut parts = line.split('\t');
+            let name = parts.next().filter(|v| !v.is_empty())?;
+            let state = parts.next().unwrap_or_default();
+            Some(DockerContainer {
+                name: name.to_string(),
+                running: matches!(state, "running" | "restarting" | "paused"),
+                image: parts.next().unwrap_or_default().to_string(),
+            })
+        })
+        .collect()
+}
+
+fn list_names(names: &[String]) -> String {
+    match names.len() {
+        n if n > MAX_LISTED => format!(
+            "{} and {} more",
+            names[..MAX_LISTED].join(", "),
+            n - MAX_LISTED
+        ),
+        _ => names.join(", "),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_docker() {
+        let cmd = DockerCommand::parse("docker system prune -a --volumes -f").unwrap();
+        assert_eq!(cmd.action, "system prune");
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+        assert!(cmd.volumes);
+        assert!(cmd
+            .removes
+            .contains(&"every image not used by a container".to_string()));
+
+        let cmd = DockerCommand::parse("docker compose -f stack.yml down -v").unwrap();
+        assert_eq!(cmd.action, "compose down");
+        assert!(cmd.volumes);
+
+        let cmd = DockerCommand::parse("sudo podman --context prod rm -f web db").unwrap();
+        assert_eq!(cmd.tool, "podman");
+        assert_eq!(cmd.targets, ["web", "db"]);
+        assert!(cmd.force);
+
+        let cmd = DockerCommand::parse("docker ps -a").unwrap();
+        assert_eq!(cmd.operation(), CommandOperation::Read);
+        assert!(cmd.warnings().is_empty());
+        assert!(DockerCommand::parse("dockerd --debug").is_none());
+    }
+
+    #[test]
+    fn test_resolve_docker() {
+        let inventory = DockerInventory {
+            containers: parse_containers("web\trunning\tnginx\nold\texited\tredis\n"),
+            volumes: vec![
+                DockerVolume {
+                    name: "pgdata".into(),
+                    dangling: false,
+                },
+                DockerVolume {
+                    name: "scratch".into(),
+                    dangling: true,
+                },
+            ],
+        };
+
+        let mut cmd = DockerCommand::parse("docker rm -f web old").unwrap();
+        cmd.resolve(&inventory);
+        assert_eq!(cmd.running, ["web"]);
+        assert!(cmd
+            .warnings()
+            .contains(&"⚠️  Docker: -f kills the running containers web first!".to_string()));
+
+        let mut cmd = DockerCommand::parse("docker system prune --volumes").unwrap();
+        cmd.resolve(&inventory);
+        assert_eq!(cmd.containers, ["old"]);
+        assert_eq!(cmd.volume_names, ["scratch"]);
+    }
+}
@@ -0,0 +1,255 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+/// Throughputs the estimates assume, on the slow side so "a few seconds" isn't a promise.
+const WALK_FILES_PER_SEC: u64 = 20_000;
+const COPY_BYTES_PER_SEC: u64 = 100 << 20;
+const COMPRESS_BYTES_PER_SEC: u64 = 25 << 20;
+const DOWNLOAD_BYTES_PER_SEC: u64 = 5 << 20;
+
+/// Estimates from this long on are worth running in the background.
+pub const LONG_RUNNING_SECS: u64 = 60;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum WorkKind {
+    /// `find` walking a tree.
+    Walk,
+    Compress,
+    Copy,
+    Download,
+}
+
+/// How long one step of a command takes, from the size of what it reads or fetches.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DurationEstimate {
+    pub tool: String,
+    pub kind: WorkKind,
+    /// Files a walk visits.
+    pub files: u64,
+    /// Bytes to compress, copy or download. Downloads start out unknown.
+    pub bytes: Option<u64>,
+    pub url: Option<String>,
+    /// The walk of the sources stopped early, the real numbers are higher.
+    pub truncated: bool,
+}
+
+impl DurationEstimate {
+    pub fn seconds(&self) -> Option<u64> {
+        match self.kind {
+            WorkKind::Walk => Some(self.files / WALK_FILES_PER_SEC),
+            WorkKind::Compress => self.bytes.map(|v| v / COMPRESS_BYTES_PER_SEC),
+            WorkKind::Copy => self.bytes.map(|v| v / COPY_BYTES_PER_SEC),
+            WorkKind::Download => self.bytes.map(|v| v / DOWNLOAD_BYTES_PER_SEC),
+        }
+    }
+
+    /// Long enough to run in the background. A walk that outgrew the count is.
+    pub fn is_long(&self) -> bool {
+        self.truncated || self.seconds().unwrap_or_default() >= LONG_RUNNING_SECS
+    }
+
+    /// `tar compresses 4.2 GB`, `find walks 310,000 files`
+    pub fn describe(&self) -> String {
+        let more = if self.truncated { "+" } else { "" };
+        let bytes = format_bytes(self.bytes.unwrap_or_default());
+        match self.kind {
+            WorkKind::Walk => format!(
+                "{} walks {}{more} files",
+                self.tool,
+                format_count(self.files)
+            ),
+            WorkKind::Compress => format!("{} compresses {bytes}{more}", self.tool),
+            WorkKind::Copy => format!("{} copies {bytes}{more}", self.tool),
+            WorkKind::Download => format!("{} downloads {bytes}", self.tool),
+        }
+    }
+}
+
+/// The slow steps of a command: walks of large trees, archives and copies of large sources
+/// and downloads. Download sizes are left for the caller to ask the server about.
+pub fn duration_estimates(
+    command: &str,
+    style: PathStyle,
+    shell: &ShellKind,
+    fs: &dyn FileSystem,
+) -> Vec<DurationEstimate> {
+    let mut output = vec![];
+    for segment in split_command_segments(command, shell) {
+        if let Some(estimate) = walk_estimate(&segment, style, fs) {
+            output.push(estimate);
+            continue;
+        }
+        for demand in space_demands(&segment, style, shell, fs) {
+            let kind = match demand.kind {
+                SpaceKind::Download => WorkKind::Download,
+                SpaceKind::Copy => WorkKind::Copy,
+                SpaceKind::Archive if compresses(&segment) => WorkKind::Compress,
+                SpaceKind::Archive => WorkKind::Copy,
+            };
+            output.push(DurationEstimate {
+                tool: demand.tool,
+                kind,
+                files: 0,
+                bytes: demand.bytes,
+                url: demand.url,
+                truncated: false,
+            });
+        }
+    }
+    output
+}
+
+/// The steps one after another, `None` while a download size is unknown.
+pub fn total_duration(estimates: &[DurationEstimate]) -> Option<u64> {
+    estimates.iter().map(|v| v.seconds()).sum()
+}
+
+/// `about 40 seconds`, `about 3 minutes`, `about 2 hours`
+pub fn format_duration(seconds: u64) -> String {
+    match seconds {
+        0..60 => format!("about {seconds} seconds"),
+        60..120 => "about a minute".to_string(),
+        120..3600 => format!("about {} minutes", seconds.div_ceil(60)),
+        3600..7200 => "about an hour".to_string(),
+        _ => format!("about {} hours", seconds.div_ceil(3600)),
+    }
+}
+
+/// The hint for a command worth running in the background, `None` for quick ones.
+pub fn duration_warning(estimates: &[DurationEstimate]) -> Option<String> {
+    let long: Vec<&DurationEstimate> = estimates.iter().filter(|v| v.is_long()).collect();
+    if long.is_empty() {
+        return None;
+    }
+    let known: u64 = estimates.iter().filter_map(|v| v.seconds()).sum();
+    let time = match long.iter().any(|v| v.truncated) || known < LONG_RUNNING_SECS {
+        true => "several minutes".to_string(),
+        false => format_duration(known),
+    };
+    let steps: Vec<String> = long.iter().map(|v| v.describe()).collect();
+    Some(format!(
+        "⏱  This may take {time}: {}. Run it in the background to keep the terminal.",
+        steps.join(", ")
+    ))
+}
+
+/// A `find` over the trees it starts from, when they are big enough to take a while.
+fn walk_estimate(segment: &str, style: PathStyle, fs: &dyn FileSystem) -> Option<DurationEstimate> {
+    let words = shell_words::split(segment).ok()?;
+    let words: Vec<&str> = words
+        .iter()
+        .map(|v| v.as_str())
+        .skip_while(|v| matches!(*v, "sudo" | "doas"))
+        .collect();
+    let (tool, args) = words.split_first()?;
+    if !matches!(*tool, "find" | "gfind") {
+        return None;
+    }
+    let args: Vec<&str> = args
+        .iter()
+        .copied()
+        .skip_while(|v| matches!(*v, "-H" | "-L" | "-P"))
+        .collect();
+    let count = args
+        .iter()
+        .position(|v| v.starts_with('-') || matches!(*v, "(" | "!" | "\\("))
+        .unwrap_or(args.len());
+    let mut paths: Vec<PathBuf> = args[..count]
+        .iter()
+        .map(|v| resolve_command_path(v, style, fs))
+        .collect();
+    if paths.is_empty() {
+        paths.push(resolve_command_path(".", style, fs));
+    }
+    let expression = &args[count..];
+    // A shallow search doesn't visit the whole tree the size is counted for
+    if expression.contains(&"-maxdepth") {
+        return None;
+    }
+    let size = fs.estimate_size(&paths);
+    let estimate = DurationEstimate {
+        tool: tool.to_string(),
+        kind: WorkKind::Walk,
+        files: size.files,
+        bytes: None,
+        url: None,
+        truncated: size.truncated,
+    };
+    Some(estimate).filter(|v| v.is_long())
+}
+
+/// `gzip`, `zip`, `7z` and `tar -z/-j/-J/--zstd` compress, plain `tar -c` only copies.
+fn compresses(segment: &str) -> bool {
+    let words: Vec<&str> = segment
+        .split_whitespace()
+        .skip_while(|v| matches!(*v, "sudo" | "doas"))
+        .collect();
+    let Some((tool, args)) = words.split_first() else {
+        return false;
+    };
+    if tool.rsplit(['/', '\\']).next() != Some("tar") {
+        return true;
+    }
+    // The mode comes first and may leave out the dash, `tar czf`
+    args.iter()
+        .enumerate()
+        .any(|(i, v)| match v.strip_prefix("--") {
+            Some(long) => matches!(
+                long,
+                "gzip" | "bzip2" | "xz" | "zstd" | "lzma" | "auto-compress"
+            ),
+            None => (i == 0 || v.starts_with('-')) && v.contains(['z', 'j', 'J', 'a']),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimates(command: &str, fs: &MemoryFs) -> Vec<DurationEstimate> {
+        duration_estimates(command, PathStyle::Unix, &ShellKind::Bash, fs)
+    }
+
+    #[test]
+    fn test_duration_estimates() {
+        let mut fs = MemoryFs::new().cwd("/home/alice").home("/home/alice");
+        for i in 0..30 {
+            fs = fs.file(format!("/data/videos/{i}.mp4"), 200 << 20);
+        }
+        let tar = estimates("tar -czf videos.tgz /data/videos", &fs);
+        assert_eq!(tar[0].kind, WorkKind::Compress);
+        assert_eq!(tar[0].seconds(), Some(240));
+        assert_eq!(
+            duration_warning(&tar).unwrap(),
+            "⏱  This may take about 4 minutes: tar compresses 5.9 GB. Run it in the background to keep the terminal."
+        );
+        let tar = estimates("tar cf videos.tar /data/videos", &fs);
+        assert_eq!(tar[0].kind, WorkKind::Copy);
+        assert_eq!(tar[0].seconds(), Some(60));
+        assert!(duration_warning(&estimates("cp /data/videos/1.mp4 /tmp/", &fs)).is_none());
+
+        let curl = estimates("curl -o ubuntu.iso https://example.com/ubuntu.iso", &fs);
+        assert_eq!(curl[0].kind, WorkKind::Download);
+        assert_eq!(total_duration(&curl), None);
+        let mut curl = curl;
+        curl[0].bytes = Some(3 << 30);
+        assert!(duration_warning(&curl)
+            .unwrap()
+            .contains("about 11 minutes: curl downloads 3.0 GB"));
+
+        assert!(estimates("find /data -name '*.mp4'", &fs).is_empty());
+        assert!(estimates("ls -la", &fs).is_empty());
+    }
+
+    #[test]
+    fn test_format_duration() {
+        assert_eq!(format_duration(42), "about 42 seconds");
+        assert_eq!(format_duration(75), "about a minute");
+        assert_eq!(format_duration(181), "about 4 minutes");
+        assert_eq!(format_duration(5400), "about an hour");
+        assert_eq!(format_duration(9000), "about 3 hours");
+    }
+}
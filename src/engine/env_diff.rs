@@ -0,0 +1,229 @@
+use super::*;
+
+use anyhow::{Context, Result};
+use std::{fs, path::Path};
+
+/// A field that differs between two machines.
+#[derive(Debug, Clone, PartialEq)]
+pub struct EnvDifference {
+    pub field: String,
+    pub left: String,
+    pub right: String,
+}
+
+impl EnvProfile {
+    /// Read a profile written by `--env export`.
+    pub fn load(path: &Path) -> Result<Self> {
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to read '{}'", path.display()))?;
+        serde_json::from_str(&content)
+            .with_context(|| format!("'{}' is not an exported environment", path.display()))
+    }
+
+    /// What differs from `other` in ways that change how a command behaves: the OS, shell,
+    /// package manager, tool flavors and versions, and the resources. The momentary CPU and
+    /// memory usage aren't compared.
+    pub fn diff(&self, other: &Self) -> Vec<EnvDifference> {
+        let mut output = vec![];
+        let mut compare = |field: &str, left: String, right: String| {
+            if left != right {
+                output.push(EnvDifference {
+                    field: field.to_string(),
+                    left,
+                    right,
+                });
+            }
+        };
+        compare("os", self.os.to_string(), other.os.to_string());
+        compare("shell", self.shell.to_string(), other.shell.to_string());
+        compare(
+            "package manager",
+            self.pkg.to_string(),
+            other.pkg.to_string(),
+        );
+        compare(
+            "cpu cores",
+            self.cpu_cores.to_string(),
+            other.cpu_cores.to_string(),
+        );
+        compare(
+            "memory",
+            format!("{} GB", self.memory_total_gb),
+            format!("{} GB", other.memory_total_gb),
+        );
+        compare(
+            "free disk",
+            format!("{} GB", self.disk_available_gb),
+            format!("{} GB", other.disk_available_gb),
+        );
+        compare(
+            "gpu",
+            self.gpu_name.clone().unwrap_or_else(|| "none".to_string()),
+            other.gpu_name.clone().unwrap_or_else(|| "none".to_string()),
+        );
+        let mut names: Vec<&str> = self
+            .toolchain
+            .iter()
+            .chain(&other.toolchain)
+            .map(|v| v.name.as_str())
+            .collect();
+        names.sort_unstable();
+        names.dedup();
+        for name in names {
+            compare(name, self.tool_summary(name), other.tool_summary(name));
+        }
+        output
+    }
+
+    /// `GNU, tar (GNU tar) 1.34` or `missing`.
+    fn tool_summary(&self, name: &str) -> String {
+        match self.toolchain.iter().find(|v| v.name == name) {
+            Some(tool) if tool.version.is_empty() => tool.flavor.to_string(),
+            Some(tool) => format!("{}, {}", tool.flavor, tool.version),
+            None => "missing".to_string(),
+        }
+    }
+}
+
+/// Two exported profiles, for questions about why a command behaves differently on them.
+#[derive(Debug, Clone)]
+pub struct EnvComparison {
+    /// The machine names, from the file names: `laptop` for `laptop.json`.
+    pub names: [String; 2],
+    pub profiles: [EnvProfile; 2],
+}
+
+impl EnvComparison {
+    pub fn load(left: &Path, right: &Path) -> Result<Self> {
+        let name = |path: &Path| {
+            path.file_stem()
+                .map(|v| v.to_string_lossy().to_string())
+                .unwrap_or_else(|| path.display().to_string())
+        };
+        let mut names = [name(left), name(right)];
+        if names[0] == names[1] {
+            names = [left.display().to_string(), right.display().to_string()];
+        }
+        Ok(Self {
+            names,
+            profiles: [EnvProfile::load(left)?, EnvProfile::load(right)?],
+        })
+    }
+
+    pub fn differences(&self) -> Vec<EnvDifference> {
+        self.profiles[0].diff(&self.profiles[1])
+    }
+
+    /// A table of the differences, for `--env diff`.
+    pub fn render(&self) -> String {
+        let differences = self.differences();
+        if differences.is_empty() {
+            return format!(
+                "No differences between {} and {}\n",
+                self.names[0], self.names[1]
+            );
+        }
+        let width = |values: Vec<&str>| values.iter().map(|v| v.chars().count()).max();
+        let field_width = width(differences.iter().map(|v| v.field.as_str()).collect())
+            .unwrap_or_default()
+            .max(5);
+        let left_width = width(differences.iter().map(|v| v.left.as_str()).collect())
+            .unwrap_or_default()
+            .max(self.names[0].chars().count());
+        let mut output = format!(
+            "{:field_width$}  {:left_width$}  {}\n",
+            "", self.names[0], self.names[1]
+        );
+        for difference in &differences {
+            output.push_str(&format!(
+                "{:field_width$}  {:left_width$}  {}\n",
+                difference.field, difference.left, difference.right
+            ));
+        }
+        output
+    }
+
+    /// Both environments and what differs, so the model can explain why a command works
+    /// on one machine and fails on the other.
+    pub fn to_prompt_context(&self) -> String {
+        let differences = self
+            .differences()
+            .iter()
+            .map(|v| {
+                format!(
+                    "- {}: {} on {}, {} on {}",
+                    v.field, v.left, self.names[0], v.right, self.names[1]
+                )
+            })
+            .collect::<Vec<_>>();
+        let differences = match differences.is_empty() {
+            true => "None of the recorded fields differ.".to_string(),
+            false => differences.join("\n"),
+        };
+        format!(
+            r#"<environment_comparison>
+The user runs commands on two machines, "{}" and "{}".
+<machine name="{}">
+{}
+</machine>
+<machine name="{}">
+{}
+</machine>
+Differences:
+{differences}
+When a command behaves differently on them, explain it with these concrete differences and give a command that works on both where one exists.
+</environment_comparison>"#,
+            self.names[0],
+            self.names[1],
+            self.names[0],
+            self.profiles[0].prompt_json(),
+            self.names[1],
+            self.profiles[1].prompt_json(),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_env_diff() {
+        let laptop = EnvProfile {
+            os: OSKind::MacOS,
+            shell: ShellKind::Zsh,
+            pkg: PackageManager::Brew,
+            cpu_cores: 8,
+            memory_total_gb: 16,
+            toolchain: vec![ToolInfo::from_version("sed", false, "", &OSKind::MacOS)],
+            ..Default::default()
+        };
+        let server = EnvProfile {
+            os: OSKind::Linux,
+            shell: ShellKind::Bash,
+            pkg: PackageManager::Apt,
+            cpu_cores: 8,
+            memory_total_gb: 16,
+            cpu_usage: 93.0,
+            toolchain: vec![
+                ToolInfo::from_version("sed", true, "sed (GNU sed) 4.9\n", &OSKind::Linux),
+                ToolInfo::from_version("tar", true, "tar (GNU tar) 1.35\n", &OSKind::Linux),
+            ],
+            ..Default::default()
+        };
+        let differences = laptop.diff(&server);
+        let fields: Vec<&str> = differences.iter().map(|v| v.field.as_str()).collect();
+        assert_eq!(fields, ["os", "shell", "package manager", "sed", "tar"]);
+        assert_eq!(differences[3].left, "BSD");
+        assert_eq!(differences[3].right, "GNU, sed (GNU sed) 4.9");
+        assert_eq!(differences[4].left, "missing");
+
+        let comparison = EnvComparison {
+            names: ["laptop".to_string(), "server".to_string()],
+            profiles: [laptop, server],
+        };
+        let context = comparison.to_prompt_context();
+        assert!(context.contains("- os: MacOS on laptop, Linux on server"));
+        assert!(comparison.render().starts_with("                 laptop"));
+    }
+}
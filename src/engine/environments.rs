@@ -0,0 +1,522 @@
+use serde::{Deserialize, Serialize};
+use std::{env, path::PathBuf, process::Command, sync::OnceLock};
+use sysinfo::{Disks, System};
+
+/// ================================
+///  Enum 定義
+/// ================================
+#[allow(clippy::upper_case_acronyms)]
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub enum OSKind {
+    MacOS,
+    Linux,
+    Windows,
+    WSL,
+    #[default]
+    Unknown,
+}
+
+impl OSKind {
+    pub fn detect() -> Self {
+        detect_os()
+    }
+}
+
+impl std::fmt::Display for OSKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Default, Debug, Clone, Serialize, Deserialize)]
+pub enum ShellKind {
+    Bash,
+    Zsh,
+    Fish,
+    Nushell,
+    PowerShell,
+    Cmd,
+    Msys,
+    #[default]
+    Unknown,
+}
+
+impl ShellKind {
+    pub fn from_name(name: &str) -> Self {
+        match name.to_lowercase().as_str() {
+            "bash" | "sh" => ShellKind::Bash,
+            "zsh" => ShellKind::Zsh,
+            "fish" => ShellKind::Fish,
+            "nu" | "nushell" => ShellKind::Nushell,
+            "powershell" | "pwsh" => ShellKind::PowerShell,
+            "cmd" => ShellKind::Cmd,
+            _ => ShellKind::Unknown,
+        }
+    }
+
+    /// Syntax guidance for the shell prompt, so generated commands avoid bash-isms
+    /// in shells that don't support them.
+    pub fn syntax_hint(&self) -> &'static str {
+        match self {
+            ShellKind::Fish => "If multiple steps are required, combine them using '; and'. Use fish syntax: '(cmd)' for command substitution instead of '$(cmd)', 'set -gx VAR value' instead of 'export VAR=value', and 'set VAR value' instead of 'VAR=value'.",
+            ShellKind::Nushell => "If multiple steps are required, combine them using ';' ('&&' is not supported). Use nushell syntax: '(cmd)' for subexpressions instead of '$(cmd)', '$env.VAR = value' instead of 'export VAR=value', '| save file' instead of '> file', and '^cmd' to force an external command.",
+            ShellKind::PowerShell => "If multiple steps are required, try to combine them using ';'.",
+            _ => "If multiple steps are required, try to combine them using '&&'.",
+        }
+    }
+}
+
+impl std::fmt::Display for ShellKind {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+#[derive(Default, Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum PackageManager {
+    Brew,
+    Apt,
+    Pacman,
+    Nix,
+    Choco,
+    Scoop,
+    Winget,
+    #[default]
+    Unknown,
+}
+
+impl std::fmt::Display for PackageManager {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "{:?}", self)
+    }
+}
+
+/// =====================================
+///  EnvProfile：主要的環境資訊結構
+/// =====================================
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct EnvProfile {
+    pub os: OSKind,
+    pub shell: ShellKind,
+    pub pkg: PackageManager,
+
+    // system info
+    pub cpu_cores: usize,
+    pub cpu_usage: f32,       // 整體 CPU 使用率
+    pub memory_total_gb: u64, // 改成 GB 比較直觀
+    pub memory_used_gb: u64,
+    pub disk_total_gb: u64,
+    pub disk_available_gb: u64,
+    /// Every mounted filesystem, for checks on the one a command writes to.
+    #[serde(default)]
+    pub mounts: Vec<MountSpace>,
+    /// Which implementation of the common command line tools is installed.
+    #[serde(default)]
+    pub toolchain: Vec<ToolInfo>,
+    pub gpu_name: Option<String>,
+    /// Memory of a dedicated NVIDIA GPU, for sizing local models.
+    #[serde(default)]
+    pub gpu_vram_gb: Option<u64>,
+}
+
+/// Tools whose flags differ between GNU, BSD and BusyBox. Only these are asked for their
+/// `--version` and `--help`, running an arbitrary program to learn its flags could run it.
+pub const TOOLCHAIN: [&str; 15] = [
+    "tar", "sed", "grep", "find", "date", "stat", "ls", "xargs", "cp", "du", "df", "base64",
+    "sort", "readlink", "head",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ToolFlavor {
+    Gnu,
+    Bsd,
+    Busybox,
+    Other,
+}
+
+impl std::fmt::Display for ToolFlavor {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ToolFlavor::Gnu => write!(f, "GNU"),
+            ToolFlavor::Bsd => write!(f, "BSD"),
+            ToolFlavor::Busybox => write!(f, "BusyBox"),
+            ToolFlavor::Other => write!(f, "other"),
+        }
+    }
+}
+
+/// An installed tool of [`TOOLCHAIN`].
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ToolInfo {
+    pub name: String,
+    pub flavor: ToolFlavor,
+    /// The first line of `--version`, empty when the tool has none (BSD).
+    pub version: String,
+}
+
+impl ToolInfo {
+    /// Tell the flavor from the `--version` output, BSD tools reject the flag.
+    pub fn from_version(name: &str, success: bool, output: &str, os: &OSKind) -> Self {
+        let version = match success {
+            true => output.lines().next().unwrap_or_default().trim().to_string(),
+            false => String::new(),
+        };
+        let lower = output.to_lowercase();
+        let flavor = if lower.contains("busybox") {
+            ToolFlavor::Busybox
+        } else if success && (lower.contains("gnu") || lower.contains("free software foundation")) {
+            ToolFlavor::Gnu
+        } else if lower.contains("bsd") || matches!(os, OSKind::MacOS) {
+            ToolFlavor::Bsd
+        } else {
+            ToolFlavor::Other
+        };
+        Self {
+            name: name.to_string(),
+            flavor,
+            version,
+        }
+    }
+}
+
+/// Size and free space of a mounted filesystem.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+pub struct MountSpace {
+    pub mount_point: PathBuf,
+    pub total_bytes: u64,
+    pub available_bytes: u64,
+    /// A USB stick, SD card or other media that can be unplugged.
+    #[serde(default)]
+    pub removable: bool,
+}
+
+impl EnvProfile {
+    pub fn detect() -> Self {
+        let os = detect_os();
+        let shell = detect_shell(&os);
+        let pkg = detect_pkg(&os);
+
+        // 偵測硬體資訊
+        let (cpu_cores, cpu_usage, mem_total, mem_used, disk_total, disk_avail) =
+            detect_system_info();
+
+        // 偵測 GPU (可能稍微耗時，但比測網速快得多)
+        let gpu_name = detect_gpu();
+
+        Self {
+            os,
+            shell,
+            pkg,
+            cpu_cores,
+            cpu_usage,
+            memory_total_gb: mem_total,
+            memory_used_gb: mem_used,
+            disk_total_gb: disk_total,
+            disk_available_gb: disk_avail,
+            mounts: detect_mounts(),
+            toolchain: detect_toolchain().to_vec(),
+            gpu_name,
+            gpu_vram_gb: detect_gpu_vram(),
+        }
+    }
+
+    /// 提供給 AI 的 JSON context
+    pub fn to_prompt_context(&self) -> String {
+        format!(
+            "<user_environment>\n{}\n</user_environment>",
+            self.prompt_json()
+        )
+    }
+
+    /// The fields of the prompt context, without the tag around them.
+    pub fn prompt_json(&self) -> String {
+        format!(
+            r#"{{
+  "os": "{}",
+  "shell": "{}",
+  "package_manager": "{}",
+  "cpu_cores": {},
+  "memory_total_gb": {},
+  "disk_available_gb": {},
+  "toolchain": "{}",
+  "gpu_name": "{}"
+}}"#,
+            self.os,
+            self.shell,
+            self.pkg,
+            self.cpu_cores,
+            self.memory_total_gb,
+            self.disk_available_gb,
+            self.toolchain
+                .iter()
+                .map(|v| format!("{} ({})", v.name, v.flavor))
+                .collect::<Vec<_>>()
+                .join(", "),
+            self.gpu_name
+                .clone()
+                .unwrap_or_else(|| "Unknown".to_string()),
+        )
+    }
+}
+
+/// ================================
+///  OS 偵測
+/// ================================
+fn detect_os() -> OSKind {
+    match env::consts::OS {
+        "macos" => OSKind::MacOS,
+        "linux" => {
+            if is_wsl_safe() {
+                OSKind::WSL
+            } else {
+                OSKind::Linux
+            }
+        }
+        "windows" => OSKind::Windows,
+        _ => OSKind::Unknown,
+    }
+}
+
+fn is_wsl_safe() -> bool {
+    std::fs::read_to_string("/proc/version")
+        .map(|content| content.to_lowercase().contains("microsoft"))
+        .unwrap_or(false)
+}
+
+/// ================================
+///  Shell 偵測
+/// ================================
+fn detect_shell(os: &OSKind) -> ShellKind {
+    match os {
+        OSKind::Windows => {
+            if env::var("PSModulePath").is_ok() {
+                return ShellKind::PowerShell;
+            }
+            if env::var("MSYSTEM").is_ok() || env::var("MINGW_PREFIX").is_ok() {
+                return ShellKind::Msys;
+            }
+            if let Ok(comspec) = env::var("ComSpec") {
+                if comspec.to_lowercase().ends_with("cmd.exe") {
+                    return ShellKind::Cmd;
+                }
+            }
+            ShellKind::Unknown
+        }
+
+        OSKind::Linux | OSKind::WSL | OSKind::MacOS => {
+            if let Ok(shell) = env::var("SHELL") {
+                let s = shell.to_lowercase();
+                if s.contains("bash") {
+                    return ShellKind::Bash;
+                }
+                if s.contains("zsh") {
+                    return ShellKind::Zsh;
+                }
+                if s.contains("fish") {
+                    return ShellKind::Fish;
+                }
+                if s.ends_with("/nu") || s.contains("nushell") {
+                    return ShellKind::Nushell;
+                }
+            }
+            ShellKind::Unknown
+        }
+
+        _ => ShellKind::Unknown,
+    }
+}
+
+/// ================================
+///  Package Manager 偵測
+/// ================================
+fn detect_pkg(os: &OSKind) -> PackageManager {
+    // 使用 which crate (v8.0.0)
+    match os {
+        OSKind::MacOS if which::which("brew").is_ok() => {
+            return PackageManager::Brew;
+        }
+        OSKind::Linux | OSKind::WSL => {
+            if which::which("apt-get").is_ok() {
+                return PackageManager::Apt;
+            }
+            if which::which("pacman").is_ok() {
+                return PackageManager::Pacman;
+            }
+            if which::which("nix").is_ok() {
+                return PackageManager::Nix;
+            }
+        }
+        OSKind::Windows => {
+            if which::which("choco").is_ok() {
+                return PackageManager::Choco;
+            }
+            if which::which("scoop").is_ok() {
+                return PackageManager::Scoop;
+            }
+            if which::which("winget").is_ok() {
+                return PackageManager::Winget;
+            }
+        }
+        _ => {}
+    }
+    PackageManager::Unknown
+}
+
+/// ================================
+///  System Info 偵測（CPU / RAM / Disk）
+/// ================================
+fn detect_system_info() -> (usize, f32, u64, u64, u64, u64) {
+    // 建立 System 物件但不載入所有資訊以節省時間
+    let mut sys = System::new();
+
+    // 只重新整理 CPU 和 Memory
+    sys.refresh_cpu_all();
+    sys.refresh_memory();
+
+    // CPU
+    let cores = sys.cpus().len();
+    let cpu_usage = sys.global_cpu_usage();
+
+    // Memory (Convert to GB)
+    let to_gb = |kb: u64| kb / 1024 / 1024 / 1024;
+    let mem_total = to_gb(sys.total_memory());
+    let mem_used = to_gb(sys.used_memory());
+
+    // Disks
+    let disks = Disks::new_with_refreshed_list();
+    // 嘗試找根目錄或第一個硬碟
+    let disk = disks
+        .iter()
+        .find(|d| d.mount_point() == std::path::Path::new("/"))
+        .or_else(|| disks.iter().next());
+
+    let (disk_total, disk_avail) = match disk {
+        Some(disk) => (to_gb(disk.total_space()), to_gb(disk.available_space())),
+        None => (0, 0),
+    };
+
+    (
+        cores, cpu_usage, mem_total, mem_used, disk_total, disk_avail,
+    )
+}
+
+/// The mounted filesystems, without the GPU and CPU probing of [`EnvProfile::detect`].
+pub fn detect_mounts() -> Vec<MountSpace> {
+    Disks::new_with_refreshed_list()
+        .iter()
+        .map(|v| MountSpace {
+            mount_point: v.mount_point().to_path_buf(),
+            total_bytes: v.total_space(),
+            available_bytes: v.available_space(),
+            removable: v.is_removable(),
+        })
+        .collect()
+}
+
+/// The system package manager, looked up once per process.
+pub fn detect_package_manager() -> &'static PackageManager {
+    static PACKAGE_MANAGER: OnceLock<PackageManager> = OnceLock::new();
+    PACKAGE_MANAGER.get_or_init(|| detect_pkg(&detect_os()))
+}
+
+/// The installed tools of [`TOOLCHAIN`], asked once per process. Empty on Windows.
+pub fn detect_toolchain() -> &'static [ToolInfo] {
+    static TOOLCHAIN_INFO: OnceLock<Vec<ToolInfo>> = OnceLock::new();
+    TOOLCHAIN_INFO.get_or_init(|| {
+        if cfg!(windows) {
+            return vec![];
+        }
+        let os = detect_os();
+        TOOLCHAIN
+            .iter()
+            .filter_map(|name| {
+                let output = Command::new(name).arg("--version").output().ok()?;
+                let text = format!(
+                    "{}{}",
+                    String::from_utf8_lossy(&output.stdout),
+                    String::from_utf8_lossy(&output.stderr)
+                );
+                Some(ToolInfo::from_version(
+                    name,
+                    output.status.success(),
+                    &text,
+                    &os,
+                ))
+            })
+            .collect()
+    })
+}
+
+/// ================================
+///  GPU 偵測（跨平台）
+/// ================================
+/// Memory of the largest NVIDIA GPU, `nvidia-smi` reports it in MiB.
+fn detect_gpu_vram() -> Option<u64> {
+    let output = std::process::Command::new("nvidia-smi")
+        .args(["--query-gpu=memory.total", "--format=csv,noheader,nounits"])
+        .output()
+        .ok()
+        .filter(|v| v.status.success())?;
+    String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter_map(|v| v.trim().parse::<u64>().ok())
+        .max()
+        .map(|mib| (mib as f64 / 1024.0).round() as u64)
+}
+
+fn detect_gpu() -> Option<String> {
+    let os = env::consts::OS;
+
+    match os {
+        "macos" => Some("Apple Silicon / Integrated".to_string()),
+        "linux" => {
+            // 嘗試 nvidia-smi
+            if let Ok(output) = std::process::Command::new("nvidia-smi")
+                .args(["--query-gpu=name", "--format=csv,noheader"])
+                .output()
+            {
+                if output.status.success() {
+                    let name = String::from_utf8_lossy(&output.stdout).trim().to_string();
+                    if !name.is_empty() {
+                        return Some(name);
+                    }
+                }
+            }
+            // Fallback to lspci (需要 pciutils)
+            if let Ok(output) = std::process::Command::new("lspci").output() {
+                let stdout = String::from_utf8_lossy(&output.stdout);
+                for line in stdout.lines() {
+                    if line.contains("VGA") || line.contains("3D") {
+                        // 簡單擷取顯卡型號
+                        let parts: Vec<&str> = line.split(':').collect();
+                        if parts.len() > 2 {
+                            return Some(parts[2].trim().to_string());
+                        }
+                    }
+                }
+            }
+            None
+        }
+        "windows" => {
+            if let Ok(output) = std::process::Command::new("wmic")
+                .args(["path", "win32_VideoController", "get", "name"])
+                .output()
+            {
+                if output.status.success() {
+                    let text = String::from_utf8_lossy(&output.stdout);
+                    let lines: Vec<_> = text
+                        .lines()
+                        .skip(1)
+                        .map(|l| l.trim())
+                        .filter(|l| !l.is_empty())
+                        .collect();
+                    if !lines.is_empty() {
+                        return Some(lines.join(", "));
+                    }
+                }
+            }
+            None
+        }
+        _ => None,
+    }
+}
@@ -0,0 +1,364 @@
+use super::*;
+
+use fancy_regex::Regex;
+use std::sync::LazyLock;
+
+/// `:(){ :|:& };:`, `bomb() { bomb | bomb & }; bomb`
+static FORK_BOMB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"([\w:.-]+)\s*\(\)\s*\{[^}]*?(?<![\w:.-])\1\s*\|\s*\1\s*&").unwrap()
+});
+/// `function bomb { bomb | bomb & }`
+static FUNCTION_FORK_BOMB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"\bfunction\s+([\w:.-]+)\s*(?:\(\))?\s*\{[^}]*?(?<![\w:.-])\1\s*\|\s*\1\s*&")
+        .unwrap()
+});
+/// `%0|%0` in batch files, `fork while fork` in perl, `while True: os.fork()` in python.
+static OTHER_FORK_BOMB_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)%0\s*\|\s*%0|\bfork\s+while\s+fork\b|\bwhile\s*\(?\s*(?:1|true)\s*\)?\s*[:{]?\s*(?:os\.)?fork\s*\(").unwrap()
+});
+/// `while true; do ...; done`, `while :`, `until false`, `for ((;;))` and PowerShell's
+/// `while ($true) { ... }`, with the loop body in the first group that matched.
+static ENDLESS_LOOP_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?is)\b(?:while\s+(?:true|:|\[\s*1\s*\]|\(\(\s*1\s*\)\))|until\s+false|for\s*\(\(\s*;\s*;\s*\)\))\s*;?\s*do\b(.*?)\bdone\b|\b(?:while\s*\(\s*\$true\s*\)|for\s*\(\s*;\s*;\s*\))\s*\{(.*)\}").unwrap()
+});
+/// A loop that can end, or only writes now and then like a heartbeat log.
+static LOOP_EXIT_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?i)\b(break|exit|return|sleep|start-sleep)\b").unwrap());
+
+/// Devices that can be read forever.
+const ENDLESS_DEVICES: [&str; 3] = ["/dev/zero", "/dev/urandom", "/dev/random"];
+
+/// Commands that read their input to the end, which never comes from `yes` or `/dev/zero`.
+const STREAM_READERS: [&str; 24] = [
+    "cat",
+    "sort",
+    "uniq",
+    "gzip",
+    "bzip2",
+    "xz",
+    "zstd",
+    "tr",
+    "sed",
+    "awk",
+    "grep",
+    "wc",
+    "base64",
+    "od",
+    "xxd",
+    "hexdump",
+    "nl",
+    "rev",
+    "cut",
+    "md5sum",
+    "sha256sum",
+    "xargs",
+    "pv",
+    "tac",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ExhaustionKind {
+    /// Processes spawning copies of themselves until the process table is full.
+    ForkBomb,
+    /// An endless source like `yes` piped into a command that reads until the input ends.
+    EndlessPipe,
+    /// Writing to a file without end, from `yes`, `/dev/zero` or an endless loop.
+    DiskFill,
+}
+
+/// A command that runs until a machine resource runs out: processes, CPU or disk.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ResourceExhaustion {
+    pub kind: ExhaustionKind,
+    /// What produces without end: `yes`, `/dev/zero`, `an endless loop`, the fork bomb itself.
+    pub source: String,
+    /// The file filled or the command kept reading.
+    pub target: Option<String>,
+}
+
+impl ResourceExhaustion {
+    pub fn detect(command: &str, style: PathStyle) -> Vec<Self> {
+        let mut output = vec![];
+        let fork_bomb = [
+            &*FORK_BOMB_RE,
+            &*FUNCTION_FORK_BOMB_RE,
+            &*OTHER_FORK_BOMB_RE,
+        ]
+        .iter()
+        .find_map(|re| re.find(command).ok().flatten());
+        if let Some(found) = fork_bomb {
+            output.push(Self {
+                kind: ExhaustionKind::ForkBomb,
+                source: found.as_str().to_string(),
+                target: None,
+            });
+        }
+
+        for pipeline in pipelines(&tokenize(command, style)) {
+            if let Some(exhaustion) = endless_pipeline(&pipeline) {
+                if !output.contains(&exhaustion) {
+                    output.push(exhaustion);
+                }
+            }
+        }
+
+        if let Ok(Some(captures)) = ENDLESS_LOOP_RE.captures(command) {
+            let body = captures
+                .get(1)
+                .or_else(|| captures.get(2))
+                .map(|v| v.as_str())
+                .unwrap_or_default();
+            let target = match LOOP_EXIT_RE.is_match(body).unwrap_or_default() {
+                true => None,
+                false => pipelines(&tokenize(body, style))
+                    .iter()
+                    .flatten()
+                    .find_map(|v| v.written_files().into_iter().next()),
+            };
+            if let Some(target) = target {
+                output.push(Self {
+                    kind: ExhaustionKind::DiskFill,
+                    source: "an endless loop".to_string(),
+                    target: Some(target),
+                });
+            }
+        }
+        output
+    }
+
+    /// What to call it in a prompt, e.g. `a fork bomb`.
+    pub fn summary(&self) -> String {
+        match self.kind {
+            ExhaustionKind::ForkBomb => "a fork bomb".to_string(),
+            ExhaustionKind::EndlessPipe => format!("{} piped without end", self.source),
+            ExhaustionKind::DiskFill => format!(
+                "{} filling {}",
+                self.source,
+                self.target.as_deref().unwrap_or("a file")
+            ),
+        }
+    }
+
+    pub fn warning(&self) -> String {
+        let target = self.target.as_deref().unwrap_or("a file");
+        let message = match self.kind {
+            ExhaustionKind::ForkBomb => format!(
+                "'{}' is a fork bomb, it spawns processes until none can be started and the machine has to be rebooted.",
+                self.source
+            ),
+            ExhaustionKind::EndlessPipe => format!(
+                "{} never stops and {target} reads until its input ends, the command runs at full CPU until it's killed.",
+                self.source
+            ),
+            ExhaustionKind::DiskFill => format!(
+                "{} writes to {target} without end, until the disk is full and other programs fail to write.",
+                self.source
+            ),
+        };
+        format!("⚠️  RESOURCE EXHAUSTION: {message}")
+    }
+}
+
+/// A simple command of a pipeline, with the files it reads and writes through redirects.
+#[derive(Debug, Default)]
+pub(super) struct Stage {
+    pub(super) words: Vec<String>,
+    pub(super) inputs: Vec<String>,
+    pub(super) outputs: Vec<String>,
+}
+
+impl Stage {
+    /// The command word, after `sudo`, `nohup` and shell keywords like `do`.
+    pub(super) fn tool(&self) -> Option<&str> {
+        let tool = self.args_from_tool().first()?.as_str();
+        tool.rsplit(['/', '\\']).next()
+    }
+
+    fn args_from_tool(&self) -> &[String] {
+        let skip = [
+            "sudo", "doas", "nohup", "exec", "time", "do", "then", "else", "{",
+        ];
+        let start = self
+            .words
+            .iter()
+            .position(|v| !skip.contains(&v.as_str()))
+            .unwrap_or(self.words.len());
+        &self.words[start..]
+    }
+
+    pub(super) fn args(&self) -> &[String] {
+        self.args_from_tool().get(1..).unwrap_or_default()
+    }
+
+    /// `yes`, `cat /dev/zero`, `dd if=/dev/zero` without `count=`, `< /dev/urandom`.
+    fn endless_source(&self) -> Option<String> {
+        let endless = |v: &str| ENDLESS_DEVICES.contains(&v);
+        if let Some(device) = self.inputs.iter().find(|v| endless(v)) {
+            return Some(device.clone());
+        }
+        let args = self.args();
+        match self.tool()? {
+            "yes" => Some("yes".to_string()),
+            "dd" => args
+                .iter()
+                .find_map(|v| v.strip_prefix("if="))
+                .filter(|v| endless(v))
+                .map(|v| v.to_string()),
+            "cat" | "pv" => args.iter().find(|v| endless(v)).cloned(),
+            _ => None,
+        }
+    }
+
+    /// Stops after a number of lines or bytes: `head`, `dd count=`, `timeout`.
+    fn is_bounded(&self) -> bool {
+        match self.tool() {
+            Some("head" | "timeout") => true,
+            Some("dd") => self.args().iter().any(|v| v.starts_with("count=")),
+            _ => false,
+        }
+    }
+
+    /// Files written through redirects, `tee`, `dd of=` or PowerShell's `Out-File` and
+    /// `Add-Content`. `/dev/null` and raw disks (see [`DiskCommand`]) aren't included.
+    fn written_files(&self) -> Vec<String> {
+        let args = self.args();
+        let mut files = self.outputs.clone();
+        match self.tool().map(|v| v.to_lowercase()).as_deref() {
+            Some("tee") => files.extend(args.iter().filter(|v| !v.starts_with('-')).cloned()),
+            Some("dd") => files.extend(
+                args.iter()
+                    .filter_map(|v| v.strip_prefix("of="))
+                    .map(|v| v.to_string()),
+            ),
+            Some("out-file" | "add-content" | "set-content") => {
+                files.extend(args.iter().find(|v| !v.starts_with('-')).cloned())
+            }
+            _ => {}
+        }
+        files.retain(|v| {
+            !matches!(v.as_str(), "/dev/null" | "$null" | "NUL" | "-") && !is_raw_device(v)
+        });
+        files
+    }
+}
+
+/// The pipelines of a command line, each a list of its stages.
+pub(super) fn pipelines(tokens: &[Token]) -> Vec<Vec<Stage>> {
+    let mut output = vec![];
+    let mut pipeline = vec![];
+    let mut stage = Stage::default();
+    let mut iter = tokens.iter().peekable();
+    while let Some(token) = iter.next() {
+        if token.is_word() {
+            stage.words.push(token.text.clone());
+        } else if matches!(token.text.as_str(), "|" | "|&") {
+            pipeline.push(std::mem::take(&mut stage));
+        } else if token.is_control() {
+            pipeline.push(std::mem::take(&mut stage));
+            output.push(std::mem::take(&mut pipeline));
+        } else if let Some(target) = iter.next_if(|v| v.is_word()) {
+            if token.is_output_redirect() {
+                stage.outputs.push(target.text.clone());
+            } else if token.text == "<" {
+                stage.inputs.push(target.text.clone());
+            }
+        }
+    }
+    pipeline.push(stage);
+    output.push(pipeline);
+    output
+}
+
+fn endless_pipeline(pipeline: &[Stage]) -> Option<ResourceExhaustion> {
+    let source = pipeline.first()?.endless_source()?;
+    if pipeline.iter().any(|v| v.is_bounded()) {
+        return None;
+    }
+    if let Some(target) = pipeline
+        .iter()
+        .find_map(|v| v.written_files().into_iter().next())
+    {
+        return Some(ResourceExhaustion {
+            kind: ExhaustionKind::DiskFill,
+            source,
+            target: Some(target),
+        });
+    }
+    let consumer = pipeline.get(1)?.tool()?;
+    STREAM_READERS
+        .contains(&consumer)
+        .then(|| ResourceExhaustion {
+            kind: ExhaustionKind::EndlessPipe,
+            source,
+            target: Some(consumer.to_string()),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(command: &str) -> Vec<(ExhaustionKind, Option<String>)> {
+        ResourceExhaustion::detect(command, PathStyle::Unix)
+            .into_iter()
+            .map(|v| (v.kind, v.target))
+            .collect()
+    }
+
+    #[test]
+    fn test_detect_fork_bomb() {
+        for command in [
+            ":(){ :|:& };:",
+            "bomb() { bomb | bomb & }; bomb",
+            "function f { f|f & }; f",
+            "perl -e 'fork while fork'",
+            "python3 -c 'import os\nwhile True: os.fork()'",
+        ] {
+            assert_eq!(
+                detect(command),
+                [(ExhaustionKind::ForkBomb, None)],
+                "{command}"
+            );
+        }
+        assert!(detect("f() { echo a | grep a & }; f").is_empty());
+    }
+
+    #[test]
+    fn test_detect_endless_output() {
+        assert_eq!(
+            detect("yes > /tmp/junk"),
+            [(ExhaustionKind::DiskFill, Some("/tmp/junk".into()))]
+        );
+        assert_eq!(
+            detect("dd if=/dev/zero of=fill.bin bs=1M"),
+            [(ExhaustionKind::DiskFill, Some("fill.bin".into()))]
+        );
+        assert_eq!(
+            detect("cat /dev/urandom | sudo tee /var/blob"),
+            [(ExhaustionKind::DiskFill, Some("/var/blob".into()))]
+        );
+        assert_eq!(
+            detect("yes | sort"),
+            [(ExhaustionKind::EndlessPipe, Some("sort".into()))]
+        );
+        assert!(detect("yes | sudo apt install nginx").is_empty());
+        assert!(detect("yes | head -n 100 > lines.txt").is_empty());
+        assert!(detect("dd if=/dev/zero of=swapfile bs=1M count=1024").is_empty());
+        assert!(detect("yes > /dev/null").is_empty());
+        assert!(detect("dd if=/dev/zero of=/dev/sdb").is_empty());
+    }
+
+    #[test]
+    fn test_detect_endless_loop() {
+        assert_eq!(
+            detect("while true; do echo x >> log.txt; done"),
+            [(ExhaustionKind::DiskFill, Some("log.txt".into()))]
+        );
+        assert_eq!(
+            detect("while ($true) { 'x' | Out-File -Append big.txt }"),
+            [(ExhaustionKind::DiskFill, Some("big.txt".into()))]
+        );
+        assert!(detect("while true; do date >> log.txt; sleep 60; done").is_empty());
+        assert!(detect("while true; do curl -s localhost:8080; done").is_empty());
+    }
+}
@@ -0,0 +1,147 @@
+use super::*;
+
+use std::{
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+/// Files modified more recently than this are likely part of active work.
+const RECENT_MODIFICATION: Duration = Duration::from_secs(60 * 60);
+
+/// Only the first files get checked, so wildcard commands stay fast.
+const MAX_ASSESSED_FILES: usize = 20;
+
+/// Signs that a file matters more than the command suggests.
+#[derive(Debug, Clone, PartialEq, Default)]
+pub struct FileRisk {
+    /// Time since the last modification, when within [`RECENT_MODIFICATION`].
+    pub modified_ago: Option<Duration>,
+    /// The owner, when it isn't the current user.
+    pub other_owner: Option<String>,
+    /// Tracked by git with uncommitted changes (for directories, anywhere inside).
+    pub uncommitted: bool,
+}
+
+impl FileRisk {
+    pub fn assess_in(fs: &dyn FileSystem, path: &Path) -> Option<Self> {
+        let info = fs.metadata(path)?;
+        let modified_ago = info
+            .modified
+            .and_then(|v| SystemTime::now().duration_since(v).ok())
+            .filter(|v| *v < RECENT_MODIFICATION);
+        let risk = Self {
+            modified_ago,
+            other_owner: info.uid.and_then(|v| fs.owner_name(v)),
+            uncommitted: fs.has_uncommitted_changes(path),
+        };
+        match risk == Self::default() {
+            true => None,
+            false => Some(risk),
+        }
+    }
+
+    pub fn warnings(&self, path: &Path) -> Vec<String> {
+        let path = path.display();
+        let mut warnings = vec![];
+        if let Some(ago) = self.modified_ago {
+            warnings.push(format!(
+                "⚠️  {path} was modified {} ago — are you sure?",
+                format_age(ago)
+            ));
+        }
+        if let Some(owner) = &self.other_owner {
+            warnings.push(format!("⚠️  {path} is owned by {owner}, not you."));
+        }
+        if self.uncommitted {
+            warnings.push(format!(
+                "⚠️  {path} has uncommitted changes that git can't bring back."
+            ));
+        }
+        warnings
+    }
+}
+
+/// Assess the affected files of a command, returning warnings for the risky ones.
+pub fn assess_file_risks(files: &[PathBuf], fs: &dyn FileSystem) -> Vec<String> {
+    files
+        .iter()
+        .take(MAX_ASSESSED_FILES)
+        .filter_map(|path| FileRisk::assess_in(fs, path).map(|risk| risk.warnings(path)))
+        .flatten()
+        .collect()
+}
+
+pub fn format_age(age: Duration) -> String {
+    let secs = age.as_secs();
+    let (value, unit) = match secs {
+        0..=59 => (secs, "second"),
+        60..=3599 => (secs / 60, "minute"),
+        3600..=86399 => (secs / 3600, "hour"),
+        _ => (secs / 86400, "day"),
+    };
+    match value {
+        1 => format!("1 {unit}"),
+        _ => format!("{value} {unit}s"),
+    }
+}
+
+/// The owner by name, `None` when it's the current user.
+#[cfg(unix)]
+pub(super) fn other_owner(uid: u32) -> Option<String> {
+    use std::str::FromStr;
+    use sysinfo::{ProcessRefreshKind, ProcessesToUpdate, System, Uid, UpdateKind, Users};
+
+    static CURRENT_UID: LazyLock<Option<u32>> = LazyLock::new(|| {
+        let pid = sysinfo::get_current_pid().ok()?;
+        let mut sys = System::new();
+        sys.refresh_processes_specifics(
+            ProcessesToUpdate::Some(&[pid]),
+            false,
+            ProcessRefreshKind::nothing().with_user(UpdateKind::Always),
+        );
+        sys.process(pid)?.user_id().map(|v| **v)
+    });
+
+    if (*CURRENT_UID)? == uid {
+        return None;
+    }
+    let name = Uid::from_str(&uid.to_string())
+        .ok()
+        .and_then(|id| {
+            Users::new_with_refreshed_list()
+                .get_user_by_id(&id)
+                .map(|v| v.name().to_string())
+        })
+        .unwrap_or_else(|| format!("uid {uid}"));
+    Some(name)
+}
+
+#[cfg(not(unix))]
+pub(super) fn other_owner(_uid: u32) -> Option<String> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_format_age() {
+        assert_eq!(format_age(Duration::from_secs(1)), "1 second");
+        assert_eq!(format_age(Duration::from_secs(185)), "3 minutes");
+        assert_eq!(format_age(Duration::from_secs(7200)), "2 hours");
+        assert_eq!(format_age(Duration::from_secs(86400 * 3)), "3 days");
+    }
+
+    #[test]
+    fn test_assess_recent_file() {
+        let path = std::env::temp_dir().join(format!("aichat-file-risk-{}", std::process::id()));
+        std::fs::write(&path, "draft").unwrap();
+        let risk = FileRisk::assess_in(&RealFs, &path).unwrap();
+        assert!(risk.modified_ago.is_some());
+        assert_eq!(risk.other_owner, None);
+        assert!(risk.warnings(&path)[0].contains("are you sure?"));
+        let _ = std::fs::remove_file(&path);
+        assert!(FileRisk::assess_in(&RealFs, &path).is_none());
+    }
+}
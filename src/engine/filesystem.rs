@@ -0,0 +1,893 @@
+use super::*;
+
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    fs,
+    path::{Component, Path},
+    sync::{Arc, LazyLock},
+    time::{Duration, Instant, SystemTime},
+};
+
+/// Entries visited at most when estimating a size, so huge trees stay fast.
+const MAX_SIZE_ENTRIES: usize = 100_000;
+/// Time spent at most estimating a size, for slow disks and network mounts.
+const MAX_SIZE_DURATION: Duration = Duration::from_secs(2);
+
+/// What the analyzer needs to know about a path.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct FileInfo {
+    pub is_dir: bool,
+    pub len: u64,
+    pub modified: Option<SystemTime>,
+    /// The owner, on Unix.
+    pub uid: Option<u32>,
+    /// Permission bits, on Unix.
+    pub mode: Option<u32>,
+}
+
+/// Files and bytes under a set of paths.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct SizeEstimate {
+    pub files: u64,
+    pub bytes: u64,
+    /// The biggest entry: a target, or for a directory target the biggest entry in it.
+    pub largest: Option<PathBuf>,
+    pub largest_bytes: u64,
+    /// Stopped after [`MAX_SIZE_ENTRIES`] entries or [`MAX_SIZE_DURATION`], the real
+    /// numbers are higher.
+    pub truncated: bool,
+}
+
+impl std::fmt::Display for SizeEstimate {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let more = if self.truncated { "+" } else { "" };
+        write!(
+            f,
+            "{}{more} files, {}{more}",
+            format_count(self.files),
+            format_bytes(self.bytes)
+        )
+    }
+}
+
+/// Whether a systemd unit runs and starts at boot, see [`FileSystem::unit_state`].
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct UnitState {
+    pub active: bool,
+    pub enabled: bool,
+    pub masked: bool,
+}
+
+/// Mount points of removable media, listed once since every analysis asks.
+static REMOVABLE_MOUNTS: LazyLock<Vec<PathBuf>> = LazyLock::new(|| {
+    detect_mounts()
+        .into_iter()
+        .filter(|v| v.removable)
+        .map(|v| v.mount_point)
+        .collect()
+});
+
+/// The filesystem and machine as seen by the command analyzer: path existence, glob
+/// expansion and size estimation, and the directories, processes, containers, tools and
+/// policy a command is judged against. [`RealFs`] is this machine; [`MemoryFs`] describes
+/// one in memory so the analysis of dangerous layouts can be tested deterministically.
+pub trait FileSystem {
+    /// Follows symlinks; `None` when the path doesn't exist.
+    fn metadata(&self, path: &Path) -> Option<FileInfo>;
+
+    /// Names of the entries of a directory, empty when it can't be read.
+    fn read_dir(&self, path: &Path) -> Vec<String>;
+
+    /// What the symlink at `path` points to, as stored; `None` when it isn't one.
+    fn read_link(&self, _path: &Path) -> Option<PathBuf> {
+        None
+    }
+
+    /// Tracked by git with uncommitted changes (for directories, anywhere inside).
+    fn has_uncommitted_changes(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// The directory relative paths are resolved against.
+    fn current_dir(&self) -> PathBuf;
+
+    /// Where `~` points.
+    fn home_dir(&self) -> Option<PathBuf>;
+
+    /// Mount points of removable media.
+    fn removable_mounts(&self) -> Vec<PathBuf>;
+
+    /// The owner `uid` by name, `None` when it's the current user.
+    fn owner_name(&self, uid: u32) -> Option<String>;
+
+    /// The running processes, to name the ones a kill hits.
+    fn processes(&self) -> Vec<ProcessInfo>;
+
+    /// The containers and volumes of the daemon behind `tool`.
+    fn docker_inventory(&self, tool: &str) -> DockerInventory;
+
+    /// The system package manager.
+    fn package_manager(&self) -> PackageManager;
+
+    /// `name` is installed on the `PATH`.
+    fn has_tool(&self, name: &str) -> bool;
+
+    /// `name` is installed by `tool` (`apt-get`, `pip`, `npm`, ...), globally with `global`
+    /// for the ones that install per project; `None` when it can't tell.
+    fn package_installed(&self, _tool: &str, _name: &str, _global: bool) -> Option<bool> {
+        None
+    }
+
+    /// The state of the systemd `unit`, of the user's manager with `user`; `None` when it
+    /// can't tell.
+    fn unit_state(&self, _unit: &str, _user: bool) -> Option<UnitState> {
+        None
+    }
+
+    /// Changes to `path` (for directories, anywhere inside) are staged in git; `None` when
+    /// it can't tell.
+    fn has_staged_changes(&self, _path: &Path) -> Option<bool> {
+        None
+    }
+
+    /// The branch checked out in the working directory.
+    fn git_branch(&self) -> Option<String>;
+
+    /// The kubeconfig's `current-context`.
+    fn kube_context(&self) -> Option<String>;
+
+    /// The rules of the safety policy.
+    fn safety_policy(&self) -> Arc<SafetyPolicy>;
+
+    /// The user's aliases and shell functions.
+    fn shell_aliases(&self) -> Arc<ShellAliases>;
+
+    /// The rules judging an analysis, see [`CommandRule`].
+    fn command_rules(&self) -> Arc<CommandRules>;
+
+    /// Quoting and word splitting bugs in `command`, by the built-in pass.
+    fn shell_issues(&self, command: &str, shell: &ShellKind) -> Vec<ShellIssue> {
+        lint_shell_command(command, shell)
+    }
+
+    /// `name` is set in the environment commands run with.
+    fn has_env_var(&self, name: &str) -> bool {
+        self.env_var(name).is_some()
+    }
+
+    /// The value of `name` in the environment commands run with.
+    fn env_var(&self, name: &str) -> Option<String>;
+
+    fn exists(&self, path: &Path) -> bool {
+        self.metadata(path).is_some()
+    }
+
+    fn is_file(&self, path: &Path) -> bool {
+        self.metadata(path).is_some_and(|v| !v.is_dir)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        self.metadata(path).is_some_and(|v| v.is_dir)
+    }
+
+    /// The existing paths matching `*` and `?` in any component of `pattern`. Like
+    /// shells, wildcards don't match a leading `.`.
+    fn expand_glob(&self, pattern: &Path) -> Vec<PathBuf> {
+        let mut paths = vec![PathBuf::new()];
+        for component in pattern.components() {
+            let text = component.as_os_str().to_string_lossy();
+            let is_glob = matches!(component, Component::Normal(_)) && text.contains(['*', '?']);
+            if !is_glob {
+                paths.iter_mut().for_each(|v| v.push(component));
+                continue;
+            }
+            paths = paths
+                .iter()
+                .flat_map(|dir| {
+                    self.read_dir(dir)
+                        .into_iter()
+                        .filter(|name| {
+                            (!name.starts_with('.') || text.starts_with('.'))
+                                && glob_matches(&text, name, PathStyle::current())
+                        })
+                        .map(|name| dir.join(name))
+                        .collect::<Vec<_>>()
+                })
+                .collect();
+        }
+        paths.retain(|v| self.exists(v));
+        paths.sort();
+        paths
+    }
+
+    /// Files and bytes under `paths`, recursing into directories, within
+    /// [`MAX_SIZE_ENTRIES`] entries and [`MAX_SIZE_DURATION`].
+    fn estimate_size(&self, paths: &[PathBuf]) -> SizeEstimate {
+        let mut estimate = SizeEstimate::default();
+        // Each file counts toward its top entry: the target, or its entry in a directory target
+        let mut tops: BTreeMap<PathBuf, u64> = BTreeMap::new();
+        let mut pending: Vec<(PathBuf, Option<PathBuf>)> =
+            paths.iter().map(|v| (v.clone(), None)).collect();
+        let deadline = Instant::now() + MAX_SIZE_DURATION;
+        let mut visited = 0;
+        while let Some((path, top)) = pending.pop() {
+            visited += 1;
+            if visited > MAX_SIZE_ENTRIES || Instant::now() > deadline {
+                estimate.truncated = true;
+                break;
+            }
+            let Some(info) = self.metadata(&path) else {
+                continue;
+            };
+            if info.is_dir {
+                pending.extend(self.read_dir(&path).into_iter().map(|v| {
+                    let child = path.join(v);
+                    (child.clone(), top.clone().or(Some(child)))
+                }));
+            } else {
+                estimate.files += 1;
+                estimate.bytes += info.len;
+                *tops.entry(top.unwrap_or(path)).or_default() += info.len;
+            }
+        }
+        if let Some((path, bytes)) = tops.into_iter().max_by_key(|(_, v)| *v) {
+            estimate.largest = Some(path);
+            estimate.largest_bytes = bytes;
+        }
+        estimate
+    }
+}
+
+/// Where `path` really is, with every symlink on the way replaced by its target and `..`
+/// taken after that, like the kernel resolves it, and the links followed in order. Gives
+/// up following after 40 links, as a loop would never end.
+pub fn resolve_symlinks(path: &Path, fs: &dyn FileSystem) -> (PathBuf, Vec<PathBuf>) {
+    let mut resolved = PathBuf::new();
+    let mut links = vec![];
+    let mut pending: Vec<PathBuf> = path
+        .components()
+        .rev()
+        .map(|v| PathBuf::from(v.as_os_str()))
+        .collect();
+    while let Some(part) = pending.pop() {
+        match part.components().next() {
+            Some(Component::ParentDir) => {
+                resolved.pop();
+            }
+            Some(Component::Normal(_)) => {
+                let next = resolved.join(&part);
+                match fs.read_link(&next).filter(|_| links.len() < 40) {
+                    // Relative targets start from the link's directory, `resolved`
+                    Some(target) => {
+                        links.push(next);
+                        pending.extend(target.components().rev().map(|v| v.as_os_str().into()));
+                    }
+                    None => resolved = next,
+                }
+            }
+            Some(Component::CurDir) | None => {}
+            Some(_) => resolved.push(part),
+        }
+    }
+    (resolved, links)
+}
+
+/// The local disk.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct RealFs;
+
+impl FileSystem for RealFs {
+    fn metadata(&self, path: &Path) -> Option<FileInfo> {
+        let metadata = path.metadata().ok()?;
+        #[cfg(unix)]
+        let (uid, mode) = {
+            use std::os::unix::fs::{MetadataExt, PermissionsExt};
+            (
+                Some(metadata.uid()),
+                Some(metadata.permissions().mode() & 0o7777),
+            )
+        };
+        #[cfg(not(unix))]
+        let (uid, mode) = (None, None);
+        Some(FileInfo {
+            is_dir: metadata.is_dir(),
+            len: metadata.len(),
+            modified: metadata.modified().ok(),
+            uid,
+            mode,
+        })
+    }
+
+    fn read_link(&self, path: &Path) -> Option<PathBuf> {
+        fs::read_link(path).ok()
+    }
+
+    fn read_dir(&self, path: &Path) -> Vec<String> {
+        // Symlinked directories aren't followed, which also keeps cycles out of walks.
+        if path.symlink_metadata().is_ok_and(|v| v.is_symlink()) {
+            return vec![];
+        }
+        fs::read_dir(path)
+            .map(|entries| {
+                entries
+                    .filter_map(|v| v.ok())
+                    .map(|v| v.file_name().to_string_lossy().to_string())
+                    .collect()
+            })
+            .unwrap_or_default()
+    }
+
+    fn has_uncommitted_changes(&self, path: &Path) -> bool {
+        let Ok(path) = path.canonicalize() else {
+            return false;
+        };
+        let dir = match path.is_dir() {
+            true => path.as_path(),
+            false => match path.parent() {
+                Some(v) => v,
+                None => return false,
+            },
+        };
+        let path = path.to_string_lossy();
+        let dir = dir.to_string_lossy();
+        match run_command_with_output(
+            "git",
+            &["-C", &dir, "status", "--porcelain", "--", &path],
+            None,
+        ) {
+            Ok((true, stdout, _)) => stdout
+                .lines()
+                .any(|v| !v.starts_with("??") && !v.starts_with("!!")),
+            _ => false,
+        }
+    }
+
+    fn current_dir(&self) -> PathBuf {
+        env::current_dir().unwrap_or_default()
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        dirs::home_dir()
+    }
+
+    fn removable_mounts(&self) -> Vec<PathBuf> {
+        REMOVABLE_MOUNTS.clone()
+    }
+
+    fn owner_name(&self, uid: u32) -> Option<String> {
+        other_owner(uid)
+    }
+
+    fn processes(&self) -> Vec<ProcessInfo> {
+        live_processes()
+    }
+
+    fn docker_inventory(&self, tool: &str) -> DockerInventory {
+        DockerInventory::load(tool)
+    }
+
+    fn package_manager(&self) -> PackageManager {
+        detect_package_manager().clone()
+    }
+
+    fn has_tool(&self, name: &str) -> bool {
+        which::which(name).is_ok()
+    }
+
+    fn package_installed(&self, tool: &str, name: &str, global: bool) -> Option<bool> {
+        let pattern = format!("^{name}$");
+        let (cmd, args): (&str, Vec<&str>) = match tool {
+            "apt" | "apt-get" => ("dpkg-query", vec!["-W", "-f=${Status}", name]),
+            "dnf" | "yum" => ("rpm", vec!["-q", name]),
+            "apk" => ("apk", vec!["info", "-e", name]),
+            "brew" => ("brew", vec!["list", name]),
+            "npm" if global => ("npm", vec!["ls", "-g", "--depth=0", name]),
+            "npm" => ("npm", vec!["ls", "--depth=0", name]),
+            "pip" | "pip3" => (tool, vec!["show", name]),
+            "cargo" => ("cargo", vec!["install", "--list"]),
+            "gem" => ("gem", vec!["list", "-i", &pattern]),
+            "snap" => ("snap", vec!["list", name]),
+            "pacman" => ("pacman", vec!["-Q", name]),
+            _ => return None,
+        };
+        let (success, stdout, _) = run_command_with_output(cmd, &args, None).ok()?;
+        Some(match tool {
+            "apt" | "apt-get" => stdout.contains("install ok installed"),
+            "cargo" => stdout
+                .lines()
+                .any(|v| v.split_whitespace().next() == Some(name)),
+            "gem" => stdout.trim() == "true",
+            _ => success,
+        })
+    }
+
+    fn unit_state(&self, unit: &str, user: bool) -> Option<UnitState> {
+        let query = |action: &str| {
+            let args = match user {
+                true => vec!["--user", action, unit],
+                false => vec![action, unit],
+            };
+            let (_, stdout, _) = run_command_with_output("systemctl", &args, None).ok()?;
+            Some(stdout.trim().to_string())
+        };
+        let active = query("is-active")?;
+        let enabled = query("is-enabled")?;
+        Some(UnitState {
+            active: active == "active",
+            enabled: enabled == "enabled",
+            masked: enabled.starts_with("masked"),
+        })
+    }
+
+    fn has_staged_changes(&self, path: &Path) -> Option<bool> {
+        let path = path.to_string_lossy();
+        let args = ["diff", "--cached", "--quiet", "--", &path];
+        // Exits with 1 when there are staged changes, other failures print why
+        match run_command_with_output("git", &args, None).ok()? {
+            (true, _, _) => Some(false),
+            (false, _, stderr) if stderr.trim().is_empty() => Some(true),
+            _ => None,
+        }
+    }
+
+    fn git_branch(&self) -> Option<String> {
+        current_git_branch()
+    }
+
+    fn kube_context(&self) -> Option<String> {
+        current_kube_context()
+    }
+
+    fn safety_policy(&self) -> Arc<SafetyPolicy> {
+        safety_policy()
+    }
+
+    fn shell_aliases(&self) -> Arc<ShellAliases> {
+        shell_aliases()
+    }
+
+    fn command_rules(&self) -> Arc<CommandRules> {
+        command_rules()
+    }
+
+    /// By `shellcheck` when it is installed.
+    fn shell_issues(&self, command: &str, shell: &ShellKind) -> Vec<ShellIssue> {
+        run_shellcheck(command, shell).unwrap_or_else(|| lint_shell_command(command, shell))
+    }
+
+    fn has_env_var(&self, name: &str) -> bool {
+        std::env::var_os(name).is_some()
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+}
+
+/// A filesystem layout and machine held in memory. Parent directories are created
+/// implicitly. Unless set, the working directory is `/`, there is no home, no process,
+/// container, tool, alias or environment variable, and the policy is the built-in one.
+#[derive(Debug, Clone)]
+pub struct MemoryFs {
+    entries: BTreeMap<PathBuf, FileInfo>,
+    links: BTreeMap<PathBuf, PathBuf>,
+    uncommitted: Vec<PathBuf>,
+    staged: Vec<PathBuf>,
+    cwd: PathBuf,
+    home: Option<PathBuf>,
+    removable: Vec<PathBuf>,
+    user: Option<u32>,
+    processes: Vec<ProcessInfo>,
+    docker: DockerInventory,
+    package_manager: PackageManager,
+    tools: Vec<String>,
+    packages: Vec<(String, String)>,
+    units: Vec<(String, UnitState)>,
+    git_branch: Option<String>,
+    kube_context: Option<String>,
+    policy: Arc<SafetyPolicy>,
+    aliases: Arc<ShellAliases>,
+    rules: Arc<CommandRules>,
+    env: Vec<(String, String)>,
+}
+
+impl Default for MemoryFs {
+    fn default() -> Self {
+        Self {
+            entries: BTreeMap::new(),
+            links: BTreeMap::new(),
+            uncommitted: vec![],
+            staged: vec![],
+            cwd: PathBuf::from("/"),
+            home: None,
+            removable: vec![],
+            user: None,
+            processes: vec![],
+            docker: DockerInventory::default(),
+            package_manager: PackageManager::Unknown,
+            tools: vec![],
+            packages: vec![],
+            units: vec![],
+            git_branch: None,
+            kube_context: None,
+            policy: Arc::new(SafetyPolicy::builtin()),
+            aliases: Arc::new(ShellAliases::default()),
+            rules: Arc::new(CommandRules::builtin()),
+            env: vec![],
+        }
+    }
+}
+
+impl MemoryFs {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn file(self, path: impl Into<PathBuf>, len: u64) -> Self {
+        self.insert(
+            path,
+            FileInfo {
+                len,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn dir(self, path: impl Into<PathBuf>) -> Self {
+        self.insert(
+            path,
+            FileInfo {
+                is_dir: true,
+                ..Default::default()
+            },
+        )
+    }
+
+    pub fn insert(mut self, path: impl Into<PathBuf>, info: FileInfo) -> Self {
+        let path = path.into();
+        for parent in path.ancestors().skip(1).filter(|v| v.parent().is_some()) {
+            self.entries
+                .entry(parent.to_path_buf())
+                .or_insert_with(|| FileInfo {
+                    is_dir: true,
+                    ..Default::default()
+                });
+        }
+        self.entries.insert(path, info);
+        self
+    }
+
+    /// A symlink at `path` pointing to `target`, relative to the link's directory unless
+    /// absolute.
+    pub fn symlink(mut self, path: impl Into<PathBuf>, target: impl Into<PathBuf>) -> Self {
+        let path = path.into();
+        if let Some(parent) = path.parent().filter(|v| v.parent().is_some()) {
+            self = self.dir(parent);
+        }
+        self.links.insert(path, target.into());
+        self
+    }
+
+    /// Mark a path as having uncommitted changes in git.
+    pub fn uncommitted(mut self, path: impl Into<PathBuf>) -> Self {
+        self.uncommitted.push(path.into());
+        self
+    }
+
+    /// Mark a path as having changes staged in git.
+    pub fn staged(mut self, path: impl Into<PathBuf>) -> Self {
+        self.staged.push(path.into());
+        self
+    }
+
+    pub fn cwd(mut self, path: impl Into<PathBuf>) -> Self {
+        self.cwd = path.into();
+        self
+    }
+
+    pub fn home(mut self, path: impl Into<PathBuf>) -> Self {
+        self.home = Some(path.into());
+        self
+    }
+
+    /// Mount removable media at `path`.
+    pub fn removable(mut self, path: impl Into<PathBuf>) -> Self {
+        self.removable.push(path.into());
+        self
+    }
+
+    /// Run as `uid`, files owned by anyone else get flagged.
+    pub fn user(mut self, uid: u32) -> Self {
+        self.user = Some(uid);
+        self
+    }
+
+    pub fn process(mut self, process: ProcessInfo) -> Self {
+        self.processes.push(process);
+        self
+    }
+
+    pub fn docker(mut self, inventory: DockerInventory) -> Self {
+        self.docker = inventory;
+        self
+    }
+
+    pub fn package_manager(mut self, manager: PackageManager) -> Self {
+        self.package_manager = manager;
+        self
+    }
+
+    /// Put `name` on the `PATH`.
+    pub fn tool(mut self, name: &str) -> Self {
+        self.tools.push(name.to_string());
+        self
+    }
+
+    /// `name` is installed by `tool`, every other package isn't.
+    pub fn package(mut self, tool: &str, name: &str) -> Self {
+        self.packages.push((tool.to_string(), name.to_string()));
+        self
+    }
+
+    /// The state of a systemd unit, the state of others is unknown.
+    pub fn unit(mut self, name: &str, state: UnitState) -> Self {
+        self.units.push((name.to_string(), state));
+        self
+    }
+
+    pub fn git_branch(mut self, branch: &str) -> Self {
+        self.git_branch = Some(branch.to_string());
+        self
+    }
+
+    pub fn kube_context(mut self, context: &str) -> Self {
+        self.kube_context = Some(context.to_string());
+        self
+    }
+
+    pub fn policy(mut self, policy: SafetyPolicy) -> Self {
+        self.policy = Arc::new(policy);
+        self
+    }
+
+    /// Adds `rule` after the built-in rules.
+    pub fn rule(mut self, rule: impl CommandRule + 'static) -> Self {
+        Arc::make_mut(&mut self.rules).push(rule);
+        self
+    }
+
+    pub fn aliases(mut self, aliases: ShellAliases) -> Self {
+        self.aliases = Arc::new(aliases);
+        self
+    }
+
+    /// Set the environment variable `name` to `value`.
+    pub fn env(mut self, name: &str, value: &str) -> Self {
+        self.env.push((name.to_string(), value.to_string()));
+        self
+    }
+}
+
+impl FileSystem for MemoryFs {
+    fn metadata(&self, path: &Path) -> Option<FileInfo> {
+        if path.parent().is_none() {
+            return Some(FileInfo {
+                is_dir: true,
+                ..Default::default()
+            });
+        }
+        let (path, _) = resolve_symlinks(path, self);
+        self.entries.get(&path).cloned()
+    }
+
+    fn read_link(&self, path: &Path) -> Option<PathBuf> {
+        self.links.get(path).cloned()
+    }
+
+    fn read_dir(&self, path: &Path) -> Vec<String> {
+        // Like the local disk, a symlinked directory isn't followed
+        if self.links.contains_key(path) {
+            return vec![];
+        }
+        self.entries
+            .keys()
+            .chain(self.links.keys())
+            .filter(|v| v.parent() == Some(path))
+            .filter_map(|v| v.file_name().map(|v| v.to_string_lossy().to_string()))
+            .collect()
+    }
+
+    fn has_uncommitted_changes(&self, path: &Path) -> bool {
+        self.uncommitted
+            .iter()
+            .any(|v| v.starts_with(path) || path.starts_with(v))
+    }
+
+    fn current_dir(&self) -> PathBuf {
+        self.cwd.clone()
+    }
+
+    fn home_dir(&self) -> Option<PathBuf> {
+        self.home.clone()
+    }
+
+    fn removable_mounts(&self) -> Vec<PathBuf> {
+        self.removable.clone()
+    }
+
+    fn owner_name(&self, uid: u32) -> Option<String> {
+        self.user
+            .filter(|v| *v != uid)
+            .map(|_| format!("uid {uid}"))
+    }
+
+    fn processes(&self) -> Vec<ProcessInfo> {
+        self.processes.clone()
+    }
+
+    fn docker_inventory(&self, _tool: &str) -> DockerInventory {
+        self.docker.clone()
+    }
+
+    fn package_manager(&self) -> PackageManager {
+        self.package_manager.clone()
+    }
+
+    fn has_tool(&self, name: &str) -> bool {
+        self.tools.iter().any(|v| v == name)
+    }
+
+    fn package_installed(&self, tool: &str, name: &str, _global: bool) -> Option<bool> {
+        Some(self.packages.iter().any(|(t, n)| t == tool && n == name))
+    }
+
+    fn unit_state(&self, unit: &str, _user: bool) -> Option<UnitState> {
+        self.units
+            .iter()
+            .find(|(name, _)| name == unit)
+            .map(|(_, state)| *state)
+    }
+
+    fn has_staged_changes(&self, path: &Path) -> Option<bool> {
+        Some(
+            self.staged
+                .iter()
+                .any(|v| v.starts_with(path) || path.starts_with(v)),
+        )
+    }
+
+    fn git_branch(&self) -> Option<String> {
+        self.git_branch.clone()
+    }
+
+    fn kube_context(&self) -> Option<String> {
+        self.kube_context.clone()
+    }
+
+    fn safety_policy(&self) -> Arc<SafetyPolicy> {
+        self.policy.clone()
+    }
+
+    fn shell_aliases(&self) -> Arc<ShellAliases> {
+        self.aliases.clone()
+    }
+
+    fn command_rules(&self) -> Arc<CommandRules> {
+        self.rules.clone()
+    }
+
+    fn env_var(&self, name: &str) -> Option<String> {
+        self.env
+            .iter()
+            .find(|(key, _)| key == name)
+            .map(|(_, value)| value.clone())
+    }
+}
+
+/// `*` and `?` match within one path component.
+pub fn glob_matches(pattern: &str, path: &str, style: PathStyle) -> bool {
+    let not_sep = match style {
+        PathStyle::Unix => "[^/]",
+        PathStyle::Windows => r"[^\\/]",
+    };
+    let regex: String = pattern
+        .chars()
+        .map(|c| match c {
+            '*' => format!("{not_sep}*"),
+            '?' => not_sep.to_string(),
+            c => fancy_regex::escape(&c.to_string()).to_string(),
+        })
+        .collect();
+    Regex::new(&format!("^{regex}$"))
+        .ok()
+        .and_then(|re| re.is_match(path).ok())
+        .unwrap_or_default()
+}
+
+/// `1284` -> `1,284`.
+pub fn format_count(count: u64) -> String {
+    let digits = count.to_string();
+    let mut output = String::new();
+    for (i, c) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            output.push(',');
+        }
+        output.push(c);
+    }
+    output
+}
+
+pub fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    match unit {
+        0 => format!("{bytes} B"),
+        _ => format!("{value:.1} {}", UNITS[unit]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn layout() -> MemoryFs {
+        MemoryFs::new()
+            .file("/etc/passwd", 2048)
+            .file("/etc/hosts", 512)
+            .file("/etc/.pwd.lock", 0)
+            .file("/var/log/app/1.log", 1 << 30)
+            .file("/var/log/app/2.log", 1 << 30)
+            .dir("/srv/empty")
+    }
+
+    #[test]
+    fn test_memory_fs() {
+        let fs = layout();
+        assert!(fs.is_dir(Path::new("/var/log")));
+        assert!(fs.is_file(Path::new("/etc/hosts")));
+        assert!(!fs.exists(Path::new("/etc/shadow")));
+        assert_eq!(fs.read_dir(Path::new("/srv")), ["empty"]);
+    }
+
+    #[test]
+    fn test_expand_glob() {
+        let fs = layout();
+        assert_eq!(
+            fs.expand_glob(Path::new("/etc/*")),
+            [PathBuf::from("/etc/hosts"), PathBuf::from("/etc/passwd")]
+        );
+        assert_eq!(
+            fs.expand_glob(Path::new("/var/*/app/?.log")),
+            [
+                PathBuf::from("/var/log/app/1.log"),
+                PathBuf::from("/var/log/app/2.log")
+            ]
+        );
+        assert_eq!(
+            fs.expand_glob(Path::new("/etc/.*")),
+            [PathBuf::from("/etc/.pwd.lock")]
+        );
+        assert!(fs.expand_glob(Path::new("/tmp/*.txt")).is_empty());
+    }
+
+    #[test]
+    fn test_estimate_size() {
+        let fs = layout();
+        let estimate = fs.estimate_size(&[PathBuf::from("/var"), PathBuf::from("/etc/hosts")]);
+        assert_eq!(estimate.files, 3);
+        assert_eq!(estimate.bytes, (2 << 30) + 512);
+        assert_eq!(estimate.to_string(), "3 files, 2.0 GB");
+        assert_eq!(estimate.largest, Some(PathBuf::from("/var/log")));
+        assert_eq!(estimate.largest_bytes, 2 << 30);
+        assert_eq!(format_bytes(512), "512 B");
+        assert_eq!(format_count(1_284), "1,284");
+        assert_eq!(format_count(100_000), "100,000");
+        assert_eq!(format_count(12), "12");
+    }
+}
@@ -0,0 +1,237 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+
+/// The primaries that run a command on each file `find` matches.
+const EXEC_PRIMARIES: [&str; 4] = ["-exec", "-execdir", "-ok", "-okdir"];
+
+/// A `find` invocation that acts on what it matches, not just lists it.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FindCommand {
+    pub command: String,
+    /// Where the search starts, `.` when none is given.
+    pub paths: Vec<String>,
+    /// `-delete` removes every match.
+    pub deletes: bool,
+    pub actions: Vec<FindAction>,
+}
+
+/// The command of an `-exec`, `-execdir`, `-ok` or `-okdir` primary.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FindAction {
+    pub primary: String,
+    /// As written, with `{}` for the matched file.
+    pub command: String,
+    /// `+` hands many files to one run instead of one run per file.
+    pub batched: bool,
+    /// What the command does, filled in by the analyzer.
+    pub operation: CommandOperation,
+}
+
+impl FindAction {
+    /// The command run on the start paths of the search, the files it's certain to reach.
+    pub fn applied_to(&self, paths: &[String]) -> String {
+        let words = shell_words::split(&self.command).unwrap_or_default();
+        let words: Vec<String> = words
+            .into_iter()
+            .flat_map(|v| match v == "{}" {
+                true => paths.to_vec(),
+                false => vec![v.replace("{}", &paths.join(" "))],
+            })
+            .collect();
+        shell_words::join(words)
+    }
+}
+
+impl FindCommand {
+    /// `None` for other tools and for a `find` that only prints its matches.
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment).ok()?;
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        if !matches!(*words.first()?, "find" | "gfind") {
+            return None;
+        }
+        let mut rest = &words[1..];
+        // `-H`, `-L`, `-P`, `-D debugopts` and `-O3` come before the paths
+        while let Some(word) = rest.first() {
+            match *word {
+                "-H" | "-L" | "-P" => rest = &rest[1..],
+                "-D" => rest = rest.get(2..).unwrap_or_default(),
+                v if v.starts_with("-O") => rest = &rest[1..],
+                _ => break,
+            }
+        }
+        let count = rest
+            .iter()
+            .position(|v| v.starts_with('-') || matches!(*v, "(" | "!" | "\\("))
+            .unwrap_or(rest.len());
+        let mut paths: Vec<String> = rest[..count].iter().map(|v| v.to_string()).collect();
+        if paths.is_empty() {
+            paths.push(".".to_string());
+        }
+
+        let mut deletes = false;
+        let mut actions = vec![];
+        let mut iter = rest[count..].iter();
+        while let Some(word) = iter.next() {
+            if *word == "-delete" {
+                deletes = true;
+                continue;
+            }
+            if !EXEC_PRIMARIES.contains(word) {
+                continue;
+            }
+            let mut payload = vec![];
+            let mut batched = false;
+            for v in iter.by_ref() {
+                if *v == ";" {
+                    break;
+                }
+                // `+` only ends the command right after `{}`
+                if *v == "+" && payload.last() == Some(&"{}") {
+                    batched = true;
+                    break;
+                }
+                payload.push(*v);
+            }
+            if !payload.is_empty() {
+                actions.push(FindAction {
+                    primary: word.to_string(),
+                    command: shell_words::join(payload),
+                    batched,
+                    operation: CommandOperation::Unknown,
+                });
+            }
+        }
+        if !deletes && actions.is_empty() {
+            return None;
+        }
+        Some(Self {
+            command: segment.trim().to_string(),
+            paths,
+            deletes,
+            actions,
+        })
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        let mut operation = match self.deletes {
+            true => CommandOperation::Delete,
+            false => CommandOperation::Read,
+        };
+        for action in &self.actions {
+            operation = CommandAnalysis::most_dangerous(operation, action.operation.clone());
+        }
+        operation
+    }
+
+    /// Deletes or changes files under a system root such as `/` or `/etc`.
+    pub fn changes_protected(&self, style: PathStyle) -> bool {
+        let operation = self.operation();
+        (operation.is_destructive() || operation.needs_backup())
+            && self.paths.iter().any(|v| is_protected_path(v, style))
+    }
+
+    /// The same search printing the files instead of acting on them.
+    pub fn dry_run_variant(&self) -> Option<String> {
+        let acts = self.deletes
+            || self
+                .actions
+                .iter()
+                .any(|v| v.operation.is_destructive() || v.operation.needs_backup());
+        if !acts {
+            return None;
+        }
+        let words = shell_words::split(&self.command).ok()?;
+        let mut output = vec![];
+        let mut iter = words.into_iter();
+        while let Some(word) = iter.next() {
+            if word == "-delete" {
+                continue;
+            }
+            if EXEC_PRIMARIES.contains(&word.as_str()) {
+                let mut last = String::new();
+                for v in iter.by_ref() {
+                    if v == ";" || (v == "+" && last == "{}") {
+                        break;
+                    }
+                    last = v;
+                }
+                continue;
+            }
+            output.push(word);
+        }
+        if !output.iter().any(|v| v == "-print") {
+            output.push("-print".to_string());
+        }
+        Some(shell_words::join(output))
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let paths = self.paths.join(", ");
+        let mut output = vec![];
+        if self.deletes {
+            output.push(format!(
+                "⚠️  find: -delete removes every match under {paths}, without a trash to restore from."
+            ));
+        }
+        for action in &self.actions {
+            let operation = &action.operation;
+            if operation.is_destructive() || operation.needs_backup() {
+                let runs = match action.batched {
+                    true => "on all matches",
+                    false => "once per match",
+                };
+                output.push(format!(
+                    "⚠️  find: {} runs `{}` {runs} under {paths}.",
+                    action.primary, action.command
+                ));
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_command() {
+        assert!(FindCommand::parse("find . -name '*.bak'").is_none());
+        assert!(FindCommand::parse("grep -r -delete .").is_none());
+
+        let find = FindCommand::parse("find . -name '*.bak' -delete").unwrap();
+        assert_eq!(
+            (find.paths.as_slice(), find.deletes),
+            (&[".".into()][..], true)
+        );
+        assert_eq!(find.operation(), CommandOperation::Delete);
+        assert_eq!(
+            find.dry_run_variant().unwrap(),
+            "find . -name '*.bak' -print"
+        );
+
+        let find =
+            FindCommand::parse(r"sudo find -L / /srv -type f -exec chmod 777 {} \;").unwrap();
+        assert_eq!(find.paths, ["/", "/srv"]);
+        assert_eq!(find.actions[0].command, "chmod 777 {}");
+        assert!(!find.actions[0].batched);
+        assert_eq!(find.actions[0].applied_to(&find.paths), "chmod 777 / /srv");
+
+        let mut find =
+            FindCommand::parse("find src -name '*.rs' -exec grep -l TODO {} + -print").unwrap();
+        assert_eq!(find.actions[0].command, "grep -l TODO {}");
+        assert!(find.actions[0].batched);
+        assert!(find.dry_run_variant().is_none());
+        find.actions[0].operation = CommandOperation::Modify;
+        assert_eq!(
+            find.dry_run_variant().unwrap(),
+            "find src -name '*.rs' -print"
+        );
+    }
+}
@@ -0,0 +1,489 @@
+use super::*;
+
+use serde::Serialize;
+use std::{
+    collections::BTreeMap,
+    path::{Path, PathBuf},
+};
+
+/// What a command does to a path.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FsChangeKind {
+    Deleted,
+    Created,
+    Overwritten,
+    Moved,
+}
+
+/// A predicted change to one path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct FsChange {
+    pub kind: FsChangeKind,
+    pub path: PathBuf,
+    /// Where a moved path ends up.
+    pub to: Option<PathBuf>,
+    pub is_dir: bool,
+    /// What is lost for deleted and overwritten paths, what is moved or copied otherwise.
+    pub size: SizeEstimate,
+}
+
+/// The filesystem diff a command would make, predicted without running it by replaying
+/// `rm`, `rmdir`, `mv`, `cp`, `mkdir`, `touch`, `tee`, `cd` and output redirections
+/// against the filesystem. Every command of a chain is assumed to run and succeed.
+#[derive(Debug, Clone, Default, Serialize)]
+pub struct ImpactPreview {
+    pub changes: Vec<FsChange>,
+    /// Commands that change files in ways that can't be predicted, e.g. scripts and `xargs rm`.
+    pub unpredicted: Vec<String>,
+}
+
+impl ImpactPreview {
+    pub fn simulate(
+        command: &str,
+        style: PathStyle,
+        shell: &ShellKind,
+        fs: &dyn FileSystem,
+    ) -> Self {
+        let mut simulation = Simulation {
+            fs,
+            style,
+            shell,
+            cwd: std::env::current_dir().unwrap_or_default(),
+            removed: vec![],
+            created: BTreeMap::new(),
+            preview: ImpactPreview::default(),
+        };
+        for segment in split_command_segments(command, shell) {
+            simulation.run(&segment);
+        }
+        simulation.preview
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.changes.is_empty() && self.unpredicted.is_empty()
+    }
+
+    pub fn display(&self) -> String {
+        let cwd = std::env::current_dir().unwrap_or_default();
+        let show = |path: &Path, is_dir: bool| {
+            let path = path.strip_prefix(&cwd).unwrap_or(path);
+            match is_dir {
+                true => format!("{}{}", path.display(), std::path::MAIN_SEPARATOR),
+                false => path.display().to_string(),
+            }
+        };
+        let mut output = String::new();
+
+        output.push_str(&format!("\n{}\n", "=".repeat(60)));
+        output.push_str("📂 Predicted Changes\n");
+        output.push_str(&format!("{}\n\n", "=".repeat(60)));
+
+        if self.changes.is_empty() {
+            output.push_str("No files would change.\n\n");
+        }
+        for change in &self.changes {
+            let (marker, label) = match change.kind {
+                FsChangeKind::Deleted => ('-', "deleted"),
+                FsChangeKind::Created => ('+', "created"),
+                FsChangeKind::Overwritten => ('~', "overwritten"),
+                FsChangeKind::Moved => ('>', "moved"),
+            };
+            let mut line = format!(
+                "  {marker} {label:<12}{}",
+                show(&change.path, change.is_dir)
+            );
+            if let Some(to) = &change.to {
+                line.push_str(&format!(" -> {}", show(to, change.is_dir)));
+            }
+            match (change.is_dir, change.size.files) {
+                (true, files) if files > 0 => line.push_str(&format!(" ({})", change.size)),
+                (false, _) if change.size.bytes > 0 => {
+                    line.push_str(&format!(" ({})", format_bytes(change.size.bytes)))
+                }
+                _ => {}
+            }
+            output.push_str(&format!("{line}\n"));
+        }
+        if !self.changes.is_empty() {
+            output.push('\n');
+        }
+
+        if !self.unpredicted.is_empty() {
+            output.push_str("Not predicted, these change files in ways that can't be simulated:\n");
+            for command in &self.unpredicted {
+                output.push_str(&format!("  {command}\n"));
+            }
+            output.push('\n');
+        }
+
+        output.push_str(&format!("{}\n", "=".repeat(60)));
+
+        accessible_text(&output)
+    }
+}
+
+/// The filesystem as earlier commands of the chain left it.
+struct Simulation<'a> {
+    fs: &'a dyn FileSystem,
+    style: PathStyle,
+    shell: &'a ShellKind,
+    cwd: PathBuf,
+    /// Deleted or moved away, along with everything inside.
+    removed: Vec<PathBuf>,
+    /// Created or moved here, `true` for directories.
+    created: BTreeMap<PathBuf, bool>,
+    preview: ImpactPreview,
+}
+
+impl Simulation<'_> {
+    fn run(&mut self, segment: &str) {
+        let tokens = tokenize(segment, self.style);
+        let mut words: Vec<&Token> = vec![];
+        let mut outputs: Vec<(PathBuf, bool)> = vec![];
+        let mut iter = tokens.iter().peekable();
+        while let Some(token) = iter.next() {
+            if token.is_word() {
+                words.push(token);
+                continue;
+            }
+            if token.is_output_redirect() {
+                if let Some(target) = iter.next_if(|v| v.is_word()) {
+                    outputs.push((self.resolve(&target.text), token.text.contains(">>")));
+                }
+            }
+        }
+        let skip = words
+            .iter()
+            .take_while(|v| matches!(v.text.as_str(), "sudo" | "doas"))
+            .count();
+        let words = &words[skip..];
+
+        for (path, append) in outputs {
+            self.write(path, append);
+        }
+        let Some(cmd) = words.first().map(|v| v.text.as_str()) else {
+            return;
+        };
+        let (flags, args) = self.split_flags(&words[1..]);
+        let flag = |names: &[&str], short: char| {
+            flags.iter().any(|v| {
+                names.contains(&v.as_str())
+                    || (v.starts_with('-') && !v.starts_with("--") && v.contains(short))
+            })
+        };
+        match cmd {
+            "cd" => {
+                if let Some(dir) = args.first().and_then(|v| self.expand(v).into_iter().next()) {
+                    if self.is_dir(&dir) {
+                        self.cwd = dir;
+                    }
+                }
+            }
+            "rm" | "del" | "erase" => {
+                let recursive = flag(&["--recursive", "/s", "/S"], 'r') || flag(&[], 'R');
+                let paths: Vec<PathBuf> = args.iter().flat_map(|v| self.expand(v)).collect();
+                for path in paths {
+                    if self.exists(&path) && (recursive || !self.is_dir(&path)) {
+                        self.delete(&path);
+                    }
+                }
+            }
+            "rmdir" | "rd" => {
+                let recursive = flags.iter().any(|v| v.eq_ignore_ascii_case("/s"));
+                let paths: Vec<PathBuf> = args.iter().flat_map(|v| self.expand(v)).collect();
+                for path in paths {
+                    if self.is_dir(&path) && (recursive || self.is_empty_dir(&path)) {
+                        self.delete(&path);
+                    }
+                }
+            }
+            "mv" | "move" | "cp" | "copy" => {
+                let copy = matches!(cmd, "cp" | "copy");
+                let recursive = !copy
+                    || cmd == "copy"
+                    || flag(&["--recursive", "--archive"], 'r')
+                    || flag(&[], 'R')
+                    || flag(&[], 'a');
+                let no_clobber = flag(&["--no-clobber"], 'n');
+                let Some((dest, sources)) = args.split_last() else {
+                    return;
+                };
+                let sources: Vec<PathBuf> = sources.iter().flat_map(|v| self.expand(v)).collect();
+                let dest = self.resolve(&dest.text);
+                let into_dir = sources.len() > 1 || self.is_dir(&dest);
+                for source in sources {
+                    if !self.exists(&source) || (self.is_dir(&source) && !recursive) {
+                        continue;
+                    }
+                    let target = match (into_dir, source.file_name()) {
+                        (true, Some(name)) => dest.join(name),
+                        _ => dest.clone(),
+                    };
+                    if target == source || (no_clobber && self.exists(&target)) {
+                        continue;
+                    }
+                    self.transfer(&source, &target, copy);
+                }
+            }
+            "mkdir" | "md" => {
+                let parents = cmd == "md" || flag(&["--parents"], 'p');
+                let paths: Vec<PathBuf> = args.iter().map(|v| self.resolve(&v.text)).collect();
+                for path in paths {
+                    let missing: Vec<PathBuf> = path
+                        .ancestors()
+                        .take_while(|v| !self.exists(v))
+                        .map(|v| v.to_path_buf())
+                        .collect();
+                    if missing.len() == 1 || (parents && !missing.is_empty()) {
+                        for dir in missing.into_iter().rev() {
+                            self.create(&dir, true, SizeEstimate::default());
+                        }
+                    }
+                }
+            }
+            "touch" if !flag(&["--no-create"], 'c') => {
+                let paths: Vec<PathBuf> = args.iter().flat_map(|v| self.expand(v)).collect();
+                for path in paths {
+                    if !self.exists(&path) {
+                        self.create(&path, false, SizeEstimate::default());
+                    }
+                }
+            }
+            "tee" => {
+                let append = flag(&["--append"], 'a');
+                let paths: Vec<PathBuf> = args.iter().map(|v| self.resolve(&v.text)).collect();
+                for path in paths {
+                    self.write(path, append);
+                }
+            }
+            _ if changes_files_unpredictably(words) => {
+                self.preview.unpredicted.push(segment.trim().to_string());
+            }
+            _ => {}
+        }
+    }
+
+    fn split_flags<'t>(&self, words: &[&'t Token]) -> (Vec<String>, Vec<&'t Token>) {
+        let mut flags = vec![];
+        let mut args = vec![];
+        let mut end_of_flags = false;
+        for word in words {
+            let windows_flag = matches!(self.shell, ShellKind::Cmd)
+                && word.text.starts_with('/')
+                && word.text.len() == 2;
+            match word.text.as_str() {
+                "--" if !end_of_flags => end_of_flags = true,
+                v if !end_of_flags && !word.quoted && (v.starts_with('-') || windows_flag) => {
+                    flags.push(v.to_string())
+                }
+                _ => args.push(*word),
+            }
+        }
+        (flags, args)
+    }
+
+    fn resolve(&self, word: &str) -> PathBuf {
+        let word = resolve_home_dir(word);
+        PathBuf::from(normalize_path(&word, &self.cwd, self.style))
+    }
+
+    /// An argument as the shell passes it: wildcards expanded, or as written when nothing
+    /// matches.
+    fn expand(&self, word: &Token) -> Vec<PathBuf> {
+        let path = self.resolve(&word.text);
+        if word.quoted || !word.text.contains(['*', '?']) {
+            return vec![path];
+        }
+        let matches: Vec<PathBuf> = self
+            .fs
+            .expand_glob(&path)
+            .into_iter()
+            .filter(|v| self.exists(v))
+            .collect();
+        match matches.is_empty() {
+            true => vec![path],
+            false => matches,
+        }
+    }
+
+    fn exists(&self, path: &Path) -> bool {
+        if self.created.contains_key(path) {
+            return true;
+        }
+        if self.removed.iter().any(|v| path.starts_with(v)) {
+            return false;
+        }
+        self.fs.exists(path)
+    }
+
+    fn is_dir(&self, path: &Path) -> bool {
+        match self.created.get(path) {
+            Some(is_dir) => *is_dir,
+            None => self.exists(path) && self.fs.is_dir(path),
+        }
+    }
+
+    fn is_empty_dir(&self, path: &Path) -> bool {
+        self.created.keys().all(|v| v.parent() != Some(path))
+            && (self.created.contains_key(path)
+                || self
+                    .fs
+                    .read_dir(path)
+                    .iter()
+                    .all(|v| !self.exists(&path.join(v))))
+    }
+
+    /// Files and bytes at `path` now, nothing for paths earlier commands created.
+    fn size(&self, path: &Path) -> SizeEstimate {
+        match self.created.contains_key(path) || !self.exists(path) {
+            true => SizeEstimate::default(),
+            false => self.fs.estimate_size(&[path.to_path_buf()]),
+        }
+    }
+
+    fn record(&mut self, kind: FsChangeKind, path: &Path, to: Option<PathBuf>, size: SizeEstimate) {
+        self.preview.changes.push(FsChange {
+            kind,
+            path: path.to_path_buf(),
+            to,
+            is_dir: self.is_dir(path),
+            size,
+        });
+    }
+
+    fn delete(&mut self, path: &Path) {
+        self.record(FsChangeKind::Deleted, path, None, self.size(path));
+        self.remove(path);
+    }
+
+    fn remove(&mut self, path: &Path) {
+        self.created.retain(|v, _| !v.starts_with(path));
+        self.removed.push(path.to_path_buf());
+    }
+
+    fn create(&mut self, path: &Path, is_dir: bool, size: SizeEstimate) {
+        self.removed.retain(|v| v != path);
+        self.created.insert(path.to_path_buf(), is_dir);
+        self.record(FsChangeKind::Created, path, None, size);
+    }
+
+    fn write(&mut self, path: PathBuf, append: bool) {
+        let device = path.starts_with("/dev")
+            || path
+                .file_name()
+                .is_some_and(|v| v.eq_ignore_ascii_case("nul"));
+        if device || self.is_dir(&path) {
+            return;
+        }
+        match (self.exists(&path), append) {
+            (false, _) => self.create(&path, false, SizeEstimate::default()),
+            (true, false) => self.record(FsChangeKind::Overwritten, &path, None, self.size(&path)),
+            (true, true) => {}
+        }
+    }
+
+    fn transfer(&mut self, source: &Path, target: &Path, copy: bool) {
+        let is_dir = self.is_dir(source);
+        let size = self.size(source);
+        if self.exists(target) && !self.is_dir(target) && !is_dir {
+            self.record(FsChangeKind::Overwritten, target, None, self.size(target));
+        }
+        if copy {
+            self.create(target, is_dir, size);
+            return;
+        }
+        self.record(
+            FsChangeKind::Moved,
+            source,
+            Some(target.to_path_buf()),
+            size,
+        );
+        self.remove(source);
+        self.removed.retain(|v| v != target);
+        self.created.insert(target.to_path_buf(), is_dir);
+    }
+}
+
+/// Commands known to change files whose effect the simulation doesn't model.
+fn changes_files_unpredictably(words: &[&Token]) -> bool {
+    let has = |names: &[&str]| words.iter().any(|v| names.contains(&v.text.as_str()));
+    let in_place = words
+        .iter()
+        .any(|v| v.text.starts_with("--in-place") || (v.text.starts_with("-i") && !v.quoted));
+    match words[0].text.as_str() {
+        "sh" | "bash" | "zsh" | "fish" | "python" | "python3" | "node" | "ruby" | "xargs"
+        | "rsync" | "dd" | "shred" | "unlink" | "truncate" | "install" | "ln" | "rename"
+        | "tar" | "unzip" | "make" => true,
+        "sed" | "perl" => in_place,
+        "find" => has(&["-delete", "-exec", "-execdir"]),
+        "git" => has(&[
+            "clean", "reset", "checkout", "restore", "switch", "rm", "mv",
+        ]),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn simulate(command: &str, fs: &MemoryFs) -> ImpactPreview {
+        ImpactPreview::simulate(command, PathStyle::Unix, &ShellKind::Bash, fs)
+    }
+
+    #[test]
+    fn test_simulate_changes() {
+        let fs = MemoryFs::new()
+            .file("/p/a.txt", 100)
+            .file("/p/b.txt", 200)
+            .file("/p/notes.txt", 300)
+            .file("/p/build/x.o", 1000)
+            .file("/p/build/y.o", 2000);
+
+        let preview = simulate(
+            "mkdir -p /p/out/logs && mv /p/a.txt /p/out/ && rm -r /p/build && echo hi > /p/notes.txt 2>&1 && cp /p/b.txt /p/c.txt",
+            &fs,
+        );
+        let changes: Vec<(FsChangeKind, &str, u64)> = preview
+            .changes
+            .iter()
+            .map(|v| (v.kind, v.path.to_str().unwrap(), v.size.bytes))
+            .collect();
+        assert_eq!(
+            changes,
+            [
+                (FsChangeKind::Created, "/p/out", 0),
+                (FsChangeKind::Created, "/p/out/logs", 0),
+                (FsChangeKind::Moved, "/p/a.txt", 100),
+                (FsChangeKind::Deleted, "/p/build", 3000),
+                (FsChangeKind::Overwritten, "/p/notes.txt", 300),
+                (FsChangeKind::Created, "/p/c.txt", 200),
+            ]
+        );
+        assert_eq!(preview.changes[2].to, Some(PathBuf::from("/p/out/a.txt")));
+        assert_eq!(preview.changes[3].size.files, 2);
+        assert!(preview.unpredicted.is_empty());
+    }
+
+    #[test]
+    fn test_simulate_follows_earlier_commands() {
+        let fs = MemoryFs::new()
+            .file("/p/a.txt", 100)
+            .file("/p/b.txt", 200)
+            .dir("/p/build");
+
+        // rm refuses directories without -r, and the moved file is gone when rm runs
+        let preview = simulate("mv /p/a.txt /p/b.txt; rm /p/build /p/a.txt", &fs);
+        let kinds: Vec<FsChangeKind> = preview.changes.iter().map(|v| v.kind).collect();
+        assert_eq!(kinds, [FsChangeKind::Overwritten, FsChangeKind::Moved]);
+
+        let preview = simulate("cd /p && rm *.txt", &fs);
+        assert_eq!(preview.changes.len(), 2);
+        assert_eq!(preview.changes[0].path, PathBuf::from("/p/a.txt"));
+
+        let preview = simulate("find /p -name '*.txt' | xargs rm", &fs);
+        assert!(preview.changes.is_empty());
+        assert_eq!(preview.unpredicted, ["xargs rm"]);
+    }
+}
@@ -0,0 +1,207 @@
+use super::*;
+
+use anyhow::{anyhow, bail, Context, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{fs, path::Path};
+
+const BTRFS_SNAPSHOT_DIR: &str = ".aichat-snapshots";
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SnapshotKind {
+    Zfs,
+    Btrfs,
+    Apfs,
+}
+
+/// A filesystem-level snapshot taken before a Critical command, recorded in the backup index.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct FsSnapshot {
+    pub kind: SnapshotKind,
+    /// `dataset@snap` for ZFS, the snapshot subvolume for btrfs, the local snapshot for APFS.
+    pub name: String,
+    /// The ZFS dataset, or the mount point of the btrfs subvolume / APFS volume.
+    pub source: String,
+    pub mount_point: PathBuf,
+}
+
+impl FsSnapshot {
+    /// Snapshot the filesystem containing `path`. `Ok(None)` when it doesn't support snapshots.
+    pub fn create(path: &Path) -> Result<Option<Self>> {
+        let Some((kind, source, mount_point)) = detect_filesystem(path) else {
+            return Ok(None);
+        };
+        let label = format!("aichat-{}", Local::now().format("%Y%m%d-%H%M%S"));
+        let name = match kind {
+            SnapshotKind::Zfs => {
+                let name = format!("{source}@{label}");
+                run_checked("zfs", &["snapshot", &name])?;
+                name
+            }
+            SnapshotKind::Btrfs => {
+                let dir = mount_point.join(BTRFS_SNAPSHOT_DIR);
+                fs::create_dir_all(&dir)
+                    .with_context(|| format!("Failed to create '{}'", dir.display()))?;
+                let name = dir.join(&label).display().to_string();
+                run_checked("btrfs", &["subvolume", "snapshot", "-r", &source, &name])?;
+                name
+            }
+            SnapshotKind::Apfs => {
+                let output = run_checked("tmutil", &["localsnapshot"])?;
+                let date = parse_tmutil_date(&output)
+                    .ok_or_else(|| anyhow!("Unexpected tmutil output: {}", output.trim()))?;
+                format!("com.apple.TimeMachine.{date}.local")
+            }
+        };
+        Ok(Some(Self {
+            kind,
+            name,
+            source,
+            mount_point,
+        }))
+    }
+
+    /// Commands that bring the files back, to show rather than run: rolling back a
+    /// whole filesystem also discards everything else that changed since.
+    pub fn rollback_instructions(&self) -> Vec<String> {
+        let mount = self.mount_point.display();
+        match self.kind {
+            SnapshotKind::Zfs => {
+                let snap = self.name.split_once('@').map(|v| v.1).unwrap_or_default();
+                vec![
+                    format!("# copy single files back from {mount}/.zfs/snapshot/{snap}/"),
+                    format!("zfs rollback -r {}", self.name),
+                ]
+            }
+            SnapshotKind::Btrfs => vec![
+                format!("# copy single files back from {}/", self.name),
+                format!(
+                    "btrfs subvolume snapshot {} {mount}.restored  # then swap it in for {mount}",
+                    self.name
+                ),
+            ],
+            SnapshotKind::Apfs => vec![
+                "mkdir -p /tmp/aichat-snapshot".into(),
+                format!(
+                    "mount_apfs -o ro -s {} {mount} /tmp/aichat-snapshot  # then copy files back",
+                    self.name
+                ),
+            ],
+        }
+    }
+}
+
+fn run_checked(cmd: &str, args: &[&str]) -> Result<String> {
+    let (success, stdout, stderr) =
+        run_command_with_output(cmd, args, None).with_context(|| format!("Failed to run {cmd}"))?;
+    if !success {
+        bail!("{cmd} failed: {}", stderr.trim());
+    }
+    Ok(stdout)
+}
+
+fn detect_filesystem(path: &Path) -> Option<(SnapshotKind, String, PathBuf)> {
+    let path = path.display().to_string();
+    if cfg!(target_os = "macos") {
+        let (_, df, _) = run_command_with_output("df", &[&path], None).ok()?;
+        let mount_point = df.lines().last()?.split_whitespace().last()?.to_string();
+        let (_, mount, _) = run_command_with_output::<&str>("mount", &[], None).ok()?;
+        let kind = parse_mount_kind(&mount, &mount_point)?;
+        Some((kind, mount_point.clone(), PathBuf::from(mount_point)))
+    } else {
+        let (success, output, _) = run_command_with_output(
+            "findmnt",
+            &["-n", "-o", "FSTYPE,SOURCE,TARGET", "--target", &path],
+            None,
+        )
+        .ok()?;
+        if !success {
+            return None;
+        }
+        parse_findmnt(&output)
+    }
+}
+
+fn parse_findmnt(output: &str) -> Option<(SnapshotKind, String, PathBuf)> {
+    let mut parts = output.split_whitespace();
+    let (fstype, source, target) = (parts.next()?, parts.next()?, parts.next()?);
+    match fstype {
+        "zfs" => Some((SnapshotKind::Zfs, source.to_string(), PathBuf::from(target))),
+        "btrfs" => Some((
+            SnapshotKind::Btrfs,
+            target.to_string(),
+            PathBuf::from(target),
+        )),
+        _ => None,
+    }
+}
+
+/// The filesystem of `mount_point` in macOS `mount` output, e.g. `/dev/disk3s5 on /System/Volumes/Data (apfs, ...)`.
+fn parse_mount_kind(output: &str, mount_point: &str) -> Option<SnapshotKind> {
+    output.lines().find_map(|line| {
+        let (_, rest) = line.split_once(" on ")?;
+        let (target, options) = rest.rsplit_once(" (")?;
+        (target == mount_point && options.starts_with("apfs")).then_some(SnapshotKind::Apfs)
+    })
+}
+
+/// `Created local snapshot with date: 2026-10-16-123456`
+fn parse_tmutil_date(output: &str) -> Option<String> {
+    output
+        .lines()
+        .find_map(|v| v.split_once("date:"))
+        .map(|(_, v)| v.trim().to_string())
+        .filter(|v| !v.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_filesystem() {
+        assert_eq!(
+            parse_findmnt("zfs    tank/home /home\n"),
+            Some((
+                SnapshotKind::Zfs,
+                "tank/home".into(),
+                PathBuf::from("/home")
+            ))
+        );
+        assert_eq!(
+            parse_findmnt("btrfs /dev/sda2[/@home] /home"),
+            Some((SnapshotKind::Btrfs, "/home".into(), PathBuf::from("/home")))
+        );
+        assert_eq!(parse_findmnt("ext4 /dev/sda1 /"), None);
+
+        let mount = "/dev/disk3s1s1 on / (apfs, sealed, local, read-only, journaled)\n\
+                     /dev/disk3s5 on /System/Volumes/Data (apfs, local, journaled, nobrowse)\n\
+                     /dev/disk4s1 on /Volumes/USB (msdos, local, nodev, nosuid)";
+        assert_eq!(
+            parse_mount_kind(mount, "/System/Volumes/Data"),
+            Some(SnapshotKind::Apfs)
+        );
+        assert_eq!(parse_mount_kind(mount, "/Volumes/USB"), None);
+        assert_eq!(
+            parse_tmutil_date("Created local snapshot with date: 2026-10-16-123456\n"),
+            Some("2026-10-16-123456".into())
+        );
+    }
+
+    #[test]
+    fn test_rollback_instructions() {
+        let snapshot = FsSnapshot {
+            kind: SnapshotKind::Zfs,
+            name: "tank/home@aichat-20261016-120000".into(),
+            source: "tank/home".into(),
+            mount_point: PathBuf::from("/home"),
+        };
+        let instructions = snapshot.rollback_instructions();
+        assert!(instructions[0].contains("/home/.zfs/snapshot/aichat-20261016-120000/"));
+        assert_eq!(
+            instructions[1],
+            "zfs rollback -r tank/home@aichat-20261016-120000"
+        );
+    }
+}
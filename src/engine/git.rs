@@ -0,0 +1,371 @@
+use super::*;
+
+use serde::Serialize;
+use std::{env, fs};
+
+/// A git invocation with the refs and paths it touches and, when it loses work, how.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct GitCommand {
+    /// The simple command as written, see [`split_command_segments`].
+    pub command: String,
+    pub subcommand: String,
+    /// What is lost, e.g. `force push rewrites remote history`.
+    pub destructive: Option<String>,
+    /// Branches, tags and remote refs, e.g. `origin/main`.
+    pub refs: Vec<String>,
+    /// Paths whose changes or untracked files are thrown away.
+    pub paths: Vec<String>,
+    /// The same job done without losing work, e.g. `--force-with-lease` for `--force`.
+    pub safer: Option<String>,
+}
+
+impl GitCommand {
+    /// Parse a simple command (one pipeline stage), see [`split_command_segments`].
+    pub fn parse(segment: &str) -> Option<Self> {
+        Self::parse_with(segment, current_git_branch)
+    }
+
+    /// [`Self::parse`] with the checked out branch, which a bare `git push` pushes, from
+    /// `current_branch`.
+    pub fn parse_with(
+        segment: &str,
+        current_branch: impl FnOnce() -> Option<String>,
+    ) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let git = words
+            .iter()
+            .position(|v| v.rsplit(['/', '\\']).next() == Some("git"))
+            .filter(|i| {
+                words[..*i]
+                    .iter()
+                    .all(|v| matches!(v.as_str(), "sudo" | "doas"))
+            })?;
+
+        // Global options come before the subcommand, `-C` and `-c` take a value
+        let mut i = git + 1;
+        while let Some(word) = words.get(i).filter(|v| v.starts_with('-')) {
+            i += match word.as_str() {
+                "-C" | "-c" => 2,
+                _ => 1,
+            };
+        }
+        let subcommand = words.get(i)?.clone();
+        let args = &words[i + 1..];
+        let dashdash = args.iter().position(|v| v == "--");
+        let mut flags: Vec<&String> = vec![];
+        let mut positionals: Vec<&String> = vec![];
+        for (j, arg) in args.iter().enumerate() {
+            match dashdash {
+                Some(d) if j == d => {}
+                Some(d) if j > d => positionals.push(arg),
+                _ if arg.starts_with('-') => flags.push(arg),
+                _ => positionals.push(arg),
+            }
+        }
+        let has = |names: &[&str]| flags.iter().any(|v| names.contains(&v.as_str()));
+        let short = |c: char| {
+            flags
+                .iter()
+                .any(|v| !v.starts_with("--") && v[1..].contains(c))
+        };
+        let git_prefix = shell_words::join(&words[..i]);
+        let prefix = shell_words::join(&words[..i + 1]);
+
+        let mut output = Self {
+            command: segment.trim().to_string(),
+            subcommand: subcommand.clone(),
+            destructive: None,
+            refs: vec![],
+            paths: vec![],
+            safer: None,
+        };
+        match subcommand.as_str() {
+            "push" => {
+                let remote = positionals.first().map(|v| v.as_str()).unwrap_or("origin");
+                let refspecs = positionals.get(1..).unwrap_or_default();
+                output.refs = match refspecs.is_empty() {
+                    true => current_branch()
+                        .map(|v| vec![format!("{remote}/{v}")])
+                        .unwrap_or_default(),
+                    false => refspecs
+                        .iter()
+                        .map(|v| {
+                            let v = v.trim_start_matches('+');
+                            let dst = v.rsplit(':').next().unwrap_or(v);
+                            format!("{remote}/{dst}")
+                        })
+                        .collect(),
+                };
+                let targets = match output.refs.is_empty() {
+                    true => "the upstream branch".to_string(),
+                    false => output.refs.join(", "),
+                };
+                let force = has(&["--force"]) || short('f');
+                let plus = refspecs.iter().any(|v| v.starts_with('+'));
+                if has(&["--mirror"]) {
+                    output.destructive = Some(format!(
+                        "mirror push overwrites or deletes every ref on {remote}"
+                    ));
+                } else if has(&["--delete"])
+                    || short('d')
+                    || refspecs.iter().any(|v| v.starts_with(':'))
+                {
+                    output.destructive = Some(format!("deletes {targets}"));
+                } else if force || plus {
+                    output.destructive = Some(format!(
+                        "force push rewrites the history of {targets}, commits others pushed there are lost"
+                    ));
+                    let mut safer = vec![prefix.clone(), "--force-with-lease".into()];
+                    for arg in args {
+                        match arg.as_str() {
+                            "--force" | "-f" => {}
+                            v if v.starts_with('-') && !v.starts_with("--") && v.contains('f') => {
+                                safer.push(v.replace('f', ""))
+                            }
+                            v => safer.push(quote_rev(v.trim_start_matches('+'))),
+                        }
+                    }
+                    output.safer = Some(safer.join(" "));
+                }
+            }
+            "reset" if has(&["--hard"]) => {
+                let target = positionals.first().map(|v| v.as_str()).unwrap_or("HEAD");
+                output.refs = vec![target.to_string()];
+                output.destructive = Some(format!(
+                    "discards every uncommitted change to tracked files, resetting to {target}"
+                ));
+                output.safer = Some(format!("{prefix} --keep {}", quote_rev(target)));
+            }
+            "clean"
+                if (has(&["--force"]) || short('f')) && !(has(&["--dry-run"]) || short('n')) =>
+            {
+                output.paths = positionals.iter().map(|v| v.to_string()).collect();
+                let mut what = "untracked files".to_string();
+                if short('d') {
+                    what.push_str(" and directories");
+                }
+                if short('x') {
+                    what.push_str(", ignored ones too (.env, build output)");
+                } else if short('X') {
+                    what = "ignored files (.env, build output)".to_string();
+                }
+                output.destructive = Some(format!("deletes {what}, git has no copy of them"));
+            }
+            "checkout" | "restore" => {
+                let staged_only = subcommand == "restore"
+                    && (has(&["--staged"]) || short('S'))
+                    && !(has(&["--worktree"]) || short('W'));
+                let paths: Vec<String> = match (subcommand.as_str(), dashdash) {
+                    ("checkout", Some(d)) => args[d + 1..].to_vec(),
+                    ("checkout", None) => positionals
+                        .iter()
+                        .filter(|v| v.as_str() == ".")
+                        .map(|v| v.to_string())
+                        .collect(),
+                    _ => positionals.iter().map(|v| v.to_string()).collect(),
+                };
+                if !staged_only && !paths.is_empty() {
+                    output.destructive = Some(format!(
+                        "discards the uncommitted changes to {}",
+                        paths.join(", ")
+                    ));
+                    let from_source = has(&["--source"])
+                        || flags.iter().any(|v| v.starts_with("--source="))
+                        || (subcommand == "checkout" && dashdash.is_some_and(|v| v > 0));
+                    if !from_source {
+                        let paths: Vec<_> = paths.iter().map(|v| shell_words::quote(v)).collect();
+                        output.safer =
+                            Some(format!("{git_prefix} stash push -- {}", paths.join(" ")));
+                    }
+                    output.paths = paths;
+                } else if subcommand == "checkout" && (has(&["--force"]) || short('f')) {
+                    output.destructive =
+                        Some("switches branches discarding uncommitted changes".to_string());
+                }
+            }
+            "branch" if has(&["-D"]) || (has(&["--delete", "-d"]) && has(&["--force", "-f"])) => {
+                output.refs = positionals.iter().map(|v| v.to_string()).collect();
+                output.destructive = Some(format!(
+                    "deletes {} even if not merged, its commits only survive in the reflog",
+                    output.refs.join(", ")
+                ));
+                output.safer = Some(format!("{prefix} -d {}", shell_words::join(&output.refs)));
+            }
+            "stash"
+                if matches!(
+                    positionals.first().map(|v| v.as_str()),
+                    Some("drop" | "clear")
+                ) =>
+            {
+                output.refs = positionals[1..].iter().map(|v| v.to_string()).collect();
+                output.destructive = Some(match positionals[0].as_str() {
+                    "clear" => "drops every stash".to_string(),
+                    _ => "drops a stash".to_string(),
+                });
+            }
+            "filter-branch" | "filter-repo" => {
+                output.destructive = Some("rewrites the whole history of the repository".into());
+            }
+            "reflog" if positionals.first().map(|v| v.as_str()) == Some("expire") => {
+                output.destructive =
+                    Some("expires the reflog, the last way to recover lost commits".into());
+            }
+            "gc" if flags.iter().any(|v| v.as_str() == "--prune=now") => {
+                output.destructive =
+                    Some("prunes unreachable commits, the last way to recover lost work".into());
+            }
+            _ => {}
+        }
+        Some(output)
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        match (self.destructive.is_some(), self.subcommand.as_str()) {
+            (true, _) => CommandOperation::Delete,
+            (
+                _,
+                "status" | "log" | "diff" | "show" | "blame" | "grep" | "ls-files" | "shortlog"
+                | "describe" | "rev-parse" | "reflog",
+            ) => CommandOperation::Read,
+            (_, "fetch" | "pull" | "push" | "clone" | "ls-remote") => CommandOperation::Network,
+            _ => CommandOperation::Unknown,
+        }
+    }
+
+    /// A `git clean -n` listing what `git clean -f` would delete.
+    pub fn dry_run_variant(&self) -> Option<String> {
+        if self.subcommand != "clean" || self.destructive.is_none() {
+            return None;
+        }
+        let words = shell_words::split(&self.command).ok()?;
+        let words: Vec<String> = words
+            .into_iter()
+            .map(|v| match v.as_str() {
+                "--force" => "--dry-run".to_string(),
+                v if v.starts_with('-') && !v.starts_with("--") => v.replace('f', "n"),
+                _ => v,
+            })
+            .collect();
+        Some(shell_words::join(words))
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let Some(reason) = &self.destructive else {
+            return vec![];
+        };
+        let mut output = vec![format!("⚠️  Git: {reason}.")];
+        if let Some(safer) = &self.safer {
+            output.push(format!("💡 Safer: {safer}"));
+        }
+        output
+    }
+}
+
+/// The branch checked out in the repository of the working directory.
+/// Quote a revision for a suggested command, leaving `HEAD~1`, `main^2` and `origin/main`
+/// as they are since `~` and `^` only mean something to the shell at the start of a word.
+fn quote_rev(rev: &str) -> String {
+    let plain = rev
+        .chars()
+        .all(|c| c.is_ascii_alphanumeric() || "-_./~^:@+".contains(c));
+    match plain {
+        true if rev.starts_with('~') => format!("'{rev}'"),
+        true => rev.to_string(),
+        false => shell_words::quote(rev).into(),
+    }
+}
+
+pub fn current_git_branch() -> Option<String> {
+    let cwd = env::current_dir().ok()?;
+    let git = cwd
+        .ancestors()
+        .map(|v| v.join(".git"))
+        .find(|v| v.exists())?;
+    let head = fs::read_to_string(git.join("HEAD")).ok()?;
+    head.trim()
+        .strip_prefix("ref: refs/heads/")
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_git_push() {
+        let cmd = GitCommand::parse("git push -f origin main").unwrap();
+        assert_eq!(cmd.refs, ["origin/main"]);
+        assert!(cmd.destructive.unwrap().starts_with("force push"));
+        assert_eq!(
+            cmd.safer.as_deref(),
+            Some("git push --force-with-lease origin main")
+        );
+
+        let cmd = GitCommand::parse("git push -fu upstream +feature:release").unwrap();
+        assert_eq!(cmd.refs, ["upstream/release"]);
+        assert_eq!(
+            cmd.safer.as_deref(),
+            Some("git push --force-with-lease -u upstream feature:release")
+        );
+
+        let cmd = GitCommand::parse("git push origin :old-branch").unwrap();
+        assert_eq!(
+            cmd.destructive.as_deref(),
+            Some("deletes origin/old-branch")
+        );
+        assert!(GitCommand::parse("git push --force-with-lease origin main")
+            .unwrap()
+            .destructive
+            .is_none());
+    }
+
+    #[test]
+    fn test_parse_git_discards() {
+        let cmd = GitCommand::parse("git reset --hard HEAD~1").unwrap();
+        assert_eq!(cmd.refs, ["HEAD~1"]);
+        assert_eq!(cmd.safer.as_deref(), Some("git reset --keep HEAD~1"));
+        let cmd = GitCommand::parse("git reset --hard 'main^2'").unwrap();
+        assert_eq!(cmd.safer.as_deref(), Some("git reset --keep main^2"));
+        let cmd = GitCommand::parse("git reset --hard '~tag'").unwrap();
+        assert_eq!(cmd.safer.as_deref(), Some("git reset --keep '~tag'"));
+        assert!(GitCommand::parse("git reset HEAD~1")
+            .unwrap()
+            .destructive
+            .is_none());
+
+        let cmd = GitCommand::parse("git clean -fdx").unwrap();
+        assert!(cmd.destructive.unwrap().contains("ignored ones too"));
+        assert!(GitCommand::parse("git clean -n")
+            .unwrap()
+            .destructive
+            .is_none());
+
+        let cmd = GitCommand::parse("git checkout -- src/main.rs README.md").unwrap();
+        assert_eq!(cmd.paths, ["src/main.rs", "README.md"]);
+        assert_eq!(
+            cmd.safer.as_deref(),
+            Some("git stash push -- src/main.rs README.md")
+        );
+        let cmd = GitCommand::parse("git -C app checkout .").unwrap();
+        assert_eq!(cmd.safer.as_deref(), Some("git -C app stash push -- ."));
+        let cmd = GitCommand::parse("git checkout main -- Cargo.lock").unwrap();
+        assert_eq!(cmd.paths, ["Cargo.lock"]);
+        assert_eq!(cmd.safer, None);
+        assert!(GitCommand::parse("git checkout main")
+            .unwrap()
+            .destructive
+            .is_none());
+        assert!(GitCommand::parse("git restore --staged a.txt")
+            .unwrap()
+            .destructive
+            .is_none());
+
+        let cmd = GitCommand::parse("git branch -D wip").unwrap();
+        assert_eq!(cmd.safer.as_deref(), Some("git branch -d wip"));
+        assert_eq!(
+            GitCommand::parse("git status").unwrap().operation(),
+            CommandOperation::Read
+        );
+    }
+}
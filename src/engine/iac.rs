@@ -0,0 +1,153 @@
+#[derive(Debug, Clone, PartialEq)]
+pub enum IacTool {
+    Terraform,
+    Pulumi,
+    Ansible,
+}
+
+/// A Terraform/OpenTofu, Pulumi or Ansible invocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct IacCommand {
+    pub tool: IacTool,
+    pub action: String,
+    /// Changes real infrastructure (as opposed to `plan`, `preview` or `--check`).
+    pub applies: bool,
+    /// Tears down resources, e.g. `terraform destroy` or `pulumi destroy`.
+    pub destroys: bool,
+    words: Vec<String>,
+}
+
+impl IacCommand {
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let tool = match words.first()?.as_str() {
+            "terraform" | "tofu" => IacTool::Terraform,
+            "pulumi" => IacTool::Pulumi,
+            "ansible-playbook" => IacTool::Ansible,
+            _ => return None,
+        };
+        let has = |flags: &[&str]| words.iter().any(|v| flags.contains(&v.as_str()));
+        let action = match tool {
+            IacTool::Ansible => "playbook".to_string(),
+            _ => words[1..]
+                .iter()
+                .find(|v| !v.starts_with('-'))
+                .cloned()
+                .unwrap_or_default(),
+        };
+        let (applies, destroys) = match (&tool, action.as_str()) {
+            (IacTool::Terraform, "apply") => (true, has(&["-destroy", "--destroy"])),
+            (IacTool::Terraform, "destroy") => (true, true),
+            (IacTool::Terraform, "import" | "taint" | "untaint") => (true, false),
+            (IacTool::Terraform, "state") => (has(&["rm", "mv", "push"]), false),
+            (IacTool::Pulumi, "up" | "update") => (true, false),
+            (IacTool::Pulumi, "destroy") => (!has(&["--preview-only"]), !has(&["--preview-only"])),
+            (IacTool::Ansible, _) => (!has(&["--check", "-C"]), false),
+            _ => (false, false),
+        };
+        Some(Self {
+            tool,
+            action,
+            applies,
+            destroys,
+            words,
+        })
+    }
+
+    /// The equivalent command that only shows what would change.
+    pub fn dry_run_variant(&self) -> Option<String> {
+        if !self.applies {
+            return None;
+        }
+        let mut words = self.words.clone();
+        match (&self.tool, self.action.as_str()) {
+            (IacTool::Terraform, "apply" | "destroy") => {
+                words.retain(|v| !matches!(v.as_str(), "-auto-approve" | "--auto-approve"));
+                let index = words.iter().position(|v| *v == self.action)?;
+                words[index] = "plan".into();
+                if self.destroys && !words.iter().any(|v| v.ends_with("-destroy")) {
+                    words.insert(index + 1, "-destroy".into());
+                }
+            }
+            (IacTool::Pulumi, "up" | "update") => {
+                words.retain(|v| !matches!(v.as_str(), "-y" | "--yes" | "--skip-preview"));
+                let index = words.iter().position(|v| *v == self.action)?;
+                words[index] = "preview".into();
+            }
+            (IacTool::Pulumi, "destroy") => {
+                words.retain(|v| !matches!(v.as_str(), "-y" | "--yes"));
+                words.push("--preview-only".into());
+            }
+            (IacTool::Ansible, _) => {
+                words.push("--check".into());
+                words.push("--diff".into());
+            }
+            _ => return None,
+        }
+        let words: Vec<_> = words
+            .iter()
+            .map(
+                |v| match v.contains(|c: char| c.is_whitespace() || "'\"$`\\".contains(c)) {
+                    true => shell_words::quote(v).to_string(),
+                    false => v.clone(),
+                },
+            )
+            .collect();
+        Some(words.join(" "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_terraform() {
+        let cmd = IacCommand::parse("terraform apply -auto-approve -var env=prod").unwrap();
+        assert!(cmd.applies);
+        assert!(!cmd.destroys);
+        assert_eq!(
+            cmd.dry_run_variant().as_deref(),
+            Some("terraform plan -var env=prod")
+        );
+
+        let cmd = IacCommand::parse("terraform destroy -auto-approve").unwrap();
+        assert!(cmd.destroys);
+        assert_eq!(
+            cmd.dry_run_variant().as_deref(),
+            Some("terraform plan -destroy")
+        );
+
+        let cmd = IacCommand::parse("terraform plan").unwrap();
+        assert!(!cmd.applies);
+        assert_eq!(cmd.dry_run_variant(), None);
+    }
+
+    #[test]
+    fn test_parse_pulumi_and_ansible() {
+        let cmd = IacCommand::parse("pulumi up --yes -s prod").unwrap();
+        assert_eq!(
+            cmd.dry_run_variant().as_deref(),
+            Some("pulumi preview -s prod")
+        );
+        assert!(IacCommand::parse("pulumi destroy").unwrap().destroys);
+        assert!(
+            !IacCommand::parse("pulumi destroy --preview-only")
+                .unwrap()
+                .applies
+        );
+
+        let cmd = IacCommand::parse("ansible-playbook -i hosts site.yml").unwrap();
+        assert!(cmd.applies);
+        assert_eq!(
+            cmd.dry_run_variant().as_deref(),
+            Some("ansible-playbook -i hosts site.yml --check --diff")
+        );
+        assert!(
+            !IacCommand::parse("ansible-playbook site.yml --check")
+                .unwrap()
+                .applies
+        );
+    }
+}
@@ -0,0 +1,264 @@
+use serde::{Deserialize, Serialize};
+
+/// A command rewriting files where they are: `sed -i`, `perl -i`, `gawk -i inplace`,
+/// `sponge`, `truncate` and `ex`, `vim` or `ed` run from a script.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct InPlaceEdit {
+    pub tool: String,
+    pub files: Vec<String>,
+    /// The copy the tool keeps itself, `.bak` for `sed -i.bak`.
+    pub backup_suffix: Option<String>,
+    /// `truncate -s 0` empties the files.
+    pub empties: bool,
+}
+
+impl InPlaceEdit {
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment).ok()?;
+        let words: Vec<&str> = words.iter().map(|v| v.as_str()).collect();
+        Self::from_words(&words)
+    }
+
+    /// From the words of a simple command, `sudo` allowed in front.
+    pub fn from_words(words: &[&str]) -> Option<Self> {
+        let words: Vec<&str> = words
+            .iter()
+            .copied()
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        let (tool, args) = words.split_first()?;
+        let tool = tool.rsplit(['/', '\\']).next().unwrap_or(tool);
+        let mut edit = Self {
+            tool: tool.to_string(),
+            files: vec![],
+            backup_suffix: None,
+            empties: false,
+        };
+        // Whether the first operand is the script, not a file
+        let mut script_given = false;
+        let mut in_place = false;
+        let mut operands = vec![];
+        let mut iter = args.iter().copied().peekable();
+        while let Some(word) = iter.next() {
+            if word == "--" {
+                operands.extend(iter.by_ref());
+                break;
+            }
+            let short = word.len() > 1 && word.starts_with('-') && !word.starts_with("--");
+            match tool {
+                "sed" | "gsed" => match word {
+                    "-e" | "--expression" | "-f" | "--file" => {
+                        iter.next();
+                        script_given = true;
+                    }
+                    v if v.starts_with("--in-place") => {
+                        in_place = true;
+                        edit.backup_suffix = v.strip_prefix("--in-place=").map(String::from);
+                    }
+                    v if short && v.contains('i') => {
+                        in_place = true;
+                        let suffix = &v[v.find('i').unwrap_or_default() + 1..];
+                        match suffix.is_empty() {
+                            // BSD sed takes the suffix as the next word, `-i ''`
+                            true if iter.peek() == Some(&"") => {
+                                iter.next();
+                            }
+                            true => {}
+                            false => edit.backup_suffix = Some(suffix.to_string()),
+                        }
+                    }
+                    v if short && v.contains(['e', 'f']) => {
+                        iter.next();
+                        script_given = true;
+                    }
+                    v if v.starts_with('-') => {}
+                    v => operands.push(v),
+                },
+                "perl" => match word {
+                    v if short => {
+                        // `-i` may sit in a cluster, `-pi.bak`, after switches without values
+                        let switches = &v[1..];
+                        let value_at = switches.find(['e', 'E', 'M', 'm', 'I', 'x', 'C', 'd']);
+                        if let Some(at) = switches
+                            .find('i')
+                            .filter(|i| value_at.is_none_or(|v| *i < v))
+                        {
+                            in_place = true;
+                            let suffix = &switches[at + 1..];
+                            edit.backup_suffix = (!suffix.is_empty()).then(|| suffix.to_string());
+                        } else if let Some(at) = value_at {
+                            if matches!(&switches[at..at + 1], "e" | "E") {
+                                script_given = true;
+                            }
+                            if switches.len() == at + 1 {
+                                iter.next();
+                            }
+                        }
+                    }
+                    v if v.starts_with('-') => {}
+                    v => operands.push(v),
+                },
+                "awk" | "gawk" => match word {
+                    "-i" | "--include" => in_place |= iter.next() == Some("inplace"),
+                    "-iinplace" | "--include=inplace" => in_place = true,
+                    "-f" | "--file" => {
+                        iter.next();
+                        script_given = true;
+                    }
+                    "-v" | "-F" | "--assign" | "--field-separator" => {
+                        iter.next();
+                    }
+                    v if v.starts_with('-') => {}
+                    v => operands.push(v),
+                },
+                "sponge" => {
+                    in_place = true;
+                    script_given = true;
+                    if !word.starts_with('-') {
+                        operands.push(word);
+                    }
+                }
+                "truncate" => {
+                    in_place = true;
+                    script_given = true;
+                    match word {
+                        "-s" | "--size" => edit.empties = iter.next() == Some("0"),
+                        "-r" | "--reference" => {
+                            iter.next();
+                        }
+                        v if v.starts_with("-s") || v.starts_with("--size=") => {
+                            edit.empties = matches!(v, "-s0" | "--size=0")
+                        }
+                        v if v.starts_with('-') => {}
+                        v => operands.push(v),
+                    }
+                }
+                "ex" | "vim" | "vi" | "nvim" | "ed" => {
+                    script_given = true;
+                    match word {
+                        "--cmd" | "-S" | "-u" | "-i" | "-T" => {
+                            iter.next();
+                        }
+                        "--headless" => in_place = true,
+                        v if v.starts_with('+') => in_place |= tool == "ex",
+                        v if short => {
+                            // `-es` runs without a UI, `-c cmd` runs a command, `-sc cmd` both
+                            in_place |= v.contains('s') || (tool == "ex" && v.contains('c'));
+                            if v.ends_with('c') {
+                                iter.next();
+                            }
+                        }
+                        v if v.starts_with('-') => {}
+                        v => operands.push(v),
+                    }
+                }
+                _ => return None,
+            }
+        }
+        // `ed` only takes its commands from stdin, it's always a script
+        in_place |= tool == "ed";
+        if !in_place {
+            return None;
+        }
+        let files = match script_given || operands.is_empty() {
+            true => &operands[..],
+            false => &operands[1..],
+        };
+        edit.files = files.iter().map(|v| v.to_string()).collect();
+        Some(edit)
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        let files = self.files.join(", ");
+        if files.is_empty() {
+            return None;
+        }
+        match self.tool.as_str() {
+            "truncate" if self.empties => Some(format!(
+                "⚠️  truncate: empties {files}, their contents are gone."
+            )),
+            "truncate" => Some(format!(
+                "⚠️  truncate: resizes {files}, whatever is past the new size is cut off."
+            )),
+            "sponge" => Some(format!(
+                "⚠️  sponge: replaces {files} with the output of the pipeline."
+            )),
+            "ex" | "vim" | "vi" | "nvim" | "ed" => Some(format!(
+                "⚠️  {}: the editor runs a script on {files} and saves it.",
+                self.tool
+            )),
+            _ => self
+                .backup_suffix
+                .as_ref()
+                .map(|suffix| format!("💡 {} keeps the originals as *{suffix}.", self.tool)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> Option<(String, Vec<String>, Option<String>)> {
+        InPlaceEdit::parse(command).map(|v| (v.tool, v.files, v.backup_suffix))
+    }
+
+    #[test]
+    fn test_in_place_edit() {
+        let edit = |tool: &str, files: &[&str], suffix: Option<&str>| {
+            Some((
+                tool.to_string(),
+                files.iter().map(|v| v.to_string()).collect(),
+                suffix.map(String::from),
+            ))
+        };
+        assert_eq!(parse("sed 's/a/b/' notes.txt"), None);
+        assert_eq!(
+            parse("sed -i 's/a/b/' notes.txt"),
+            edit("sed", &["notes.txt"], None)
+        );
+        assert_eq!(
+            parse("sed -i '' -e 's/a/b/' a.txt b.txt"),
+            edit("sed", &["a.txt", "b.txt"], None)
+        );
+        assert_eq!(
+            parse("sudo sed -i.bak 's/a/b/' /etc/hosts"),
+            edit("sed", &["/etc/hosts"], Some(".bak"))
+        );
+        assert_eq!(
+            parse("perl -pi -e 's/foo/bar/g' src/main.c"),
+            edit("perl", &["src/main.c"], None)
+        );
+        assert_eq!(
+            parse("perl -i.orig -pe 's/foo/bar/' a.c"),
+            edit("perl", &["a.c"], Some(".orig"))
+        );
+        assert_eq!(parse("perl -e 'print 1' a.c"), None);
+        assert_eq!(
+            parse("gawk -i inplace '{ print $1 }' data.csv"),
+            edit("gawk", &["data.csv"], None)
+        );
+        assert_eq!(parse("awk '{ print $1 }' data.csv"), None);
+        assert_eq!(
+            parse("sponge config.json"),
+            edit("sponge", &["config.json"], None)
+        );
+        assert_eq!(
+            parse("ex -sc '%s/a/b/g|x' notes.txt"),
+            edit("ex", &["notes.txt"], None)
+        );
+        assert_eq!(
+            parse("vim -es -c '%s/a/b/g' -c wq notes.txt"),
+            edit("vim", &["notes.txt"], None)
+        );
+        assert_eq!(parse("vim notes.txt"), None);
+
+        let truncate = InPlaceEdit::parse("truncate -s 0 /var/log/app.log").unwrap();
+        assert_eq!(truncate.files, ["/var/log/app.log"]);
+        assert!(truncate.empties);
+        assert!(truncate
+            .warning()
+            .unwrap()
+            .contains("empties /var/log/app.log"));
+    }
+}
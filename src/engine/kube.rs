@@ -0,0 +1,267 @@
+use super::*;
+
+use fancy_regex::Regex;
+use std::{env, fs, path::PathBuf};
+
+/// Context names matching this are treated as production unless `kube_production_pattern` is set.
+pub const DEFAULT_KUBE_PRODUCTION_PATTERN: &str = "(?i)prod";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum KubeTool {
+    Kubectl,
+    Helm,
+}
+
+/// A kubectl/helm invocation with the cluster it targets.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KubeCommand {
+    pub tool: KubeTool,
+    pub verb: String,
+    /// From `--context`/`--kube-context`, or the kubeconfig's `current-context`.
+    pub context: Option<String>,
+    /// From `-n`/`--namespace`, `*` for `--all-namespaces`.
+    pub namespace: Option<String>,
+    /// Why the command is destructive, e.g. `scale to 0`.
+    pub destructive: Option<String>,
+    /// What a delete or uninstall removes, e.g. `namespace payments`, `every pod`.
+    pub resources: Vec<String>,
+}
+
+impl KubeCommand {
+    /// Parse a simple command (one pipeline stage), see [`split_command_segments`].
+    pub fn parse(segment: &str) -> Option<Self> {
+        Self::parse_with(segment, current_kube_context)
+    }
+
+    /// [`Self::parse`] with the context used when the command names none from
+    /// `current_context`.
+    pub fn parse_with(
+        segment: &str,
+        current_context: impl FnOnce() -> Option<String>,
+    ) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let tool = match words.first()?.as_str() {
+            "kubectl" | "oc" => KubeTool::Kubectl,
+            "helm" => KubeTool::Helm,
+            _ => return None,
+        };
+
+        let mut context = None;
+        let mut namespace = None;
+        let mut replicas = None;
+        let mut force = false;
+        let mut all = false;
+        let mut files = vec![];
+        let mut args = vec![];
+        let mut iter = words[1..].iter();
+        while let Some(word) = iter.next() {
+            let (flag, inline) = match word.split_once('=') {
+                Some((flag, value)) if word.starts_with('-') => (flag, Some(value.to_string())),
+                _ => (word.as_str(), None),
+            };
+            let mut value = || inline.clone().or_else(|| iter.next().cloned());
+            match flag {
+                "--context" | "--kube-context" => context = value(),
+                "-n" | "--namespace" => namespace = value(),
+                "-A" | "--all-namespaces" => namespace = Some("*".into()),
+                "--replicas" => replicas = value(),
+                "--force" => force = true,
+                "--all" => all = true,
+                "-f" | "--filename" => files.extend(value()),
+                "-l" | "--selector" | "-o" | "--output" | "--kubeconfig" => {
+                    value();
+                }
+                v if v.starts_with('-') => {}
+                v => args.push(v.to_string()),
+            }
+        }
+
+        let verb = args.first().cloned().unwrap_or_default();
+        let destructive = match (&tool, verb.as_str()) {
+            (KubeTool::Kubectl, "delete") => Some("delete".to_string()),
+            (KubeTool::Kubectl, "drain") => Some("drain".to_string()),
+            (KubeTool::Kubectl, "scale") if replicas.as_deref() == Some("0") => {
+                Some("scale to 0".to_string())
+            }
+            (KubeTool::Kubectl, "replace") if force => Some("forced replace".to_string()),
+            (KubeTool::Helm, "uninstall" | "delete") => Some("uninstall".to_string()),
+            (KubeTool::Helm, "rollback") => Some("rollback".to_string()),
+            _ => None,
+        };
+
+        let resources = match destructive.as_deref() {
+            Some("delete") => {
+                let mut resources = delete_targets(&args[1..], all);
+                resources.extend(files.iter().map(|v| format!("everything in {v}")));
+                resources
+            }
+            Some("uninstall") => args[1..].iter().map(|v| format!("release {v}")).collect(),
+            _ => vec![],
+        };
+
+        Some(Self {
+            tool,
+            verb,
+            context: context.or_else(current_context),
+            namespace,
+            destructive,
+            resources,
+        })
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        match (self.destructive.is_some(), self.verb.as_str()) {
+            (true, _) => CommandOperation::Delete,
+            (_, "get" | "describe" | "logs" | "top" | "list" | "status" | "history") => {
+                CommandOperation::Read
+            }
+            (_, "apply" | "patch" | "edit" | "scale" | "upgrade" | "install" | "set") => {
+                CommandOperation::Modify
+            }
+            (_, "exec" | "run" | "port-forward") => CommandOperation::Execute,
+            _ => CommandOperation::Unknown,
+        }
+    }
+
+    /// Whether the target context matches the production pattern.
+    pub fn is_production(&self, pattern: &str) -> bool {
+        let Some(context) = &self.context else {
+            return false;
+        };
+        Regex::new(pattern)
+            .ok()
+            .and_then(|re| re.is_match(context).ok())
+            .unwrap_or_default()
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        let reason = self.destructive.as_ref()?;
+        let mut output = format!("⚠️  Kubernetes {reason} targets {}", self.target());
+        if !self.resources.is_empty() {
+            output.push_str(&format!(": {}", self.resources.join(", ")));
+        }
+        if self.resources.iter().any(|v| v.starts_with("namespace ")) {
+            output
+                .push_str(", with every deployment, pod, service, secret and volume claim in it!");
+        } else if self.tool == KubeTool::Helm && !self.resources.is_empty() {
+            output.push_str(", with every resource it installed");
+        }
+        Some(output)
+    }
+
+    pub fn target(&self) -> String {
+        let context = self.context.as_deref().unwrap_or("<unknown context>");
+        match &self.namespace {
+            Some(ns) if ns == "*" => format!("{context} (all namespaces)"),
+            Some(ns) => format!("{context}/{ns}"),
+            None => context.to_string(),
+        }
+    }
+}
+
+/// The resources of `kubectl delete <kind> <name>...` or `<kind>/<name>...`, kinds unabbreviated.
+fn delete_targets(args: &[String], all: bool) -> Vec<String> {
+    let Some(first) = args.first() else {
+        return vec![];
+    };
+    if first.contains('/') {
+        return args
+            .iter()
+            .filter_map(|v| v.split_once('/'))
+            .map(|(kind, name)| format!("{} {name}", resource_kind(kind)))
+            .collect();
+    }
+    let kinds: Vec<&str> = first.split(',').map(resource_kind).collect();
+    if all {
+        return kinds.iter().map(|v| format!("every {v}")).collect();
+    }
+    kinds
+        .iter()
+        .flat_map(|kind| args[1..].iter().map(move |name| format!("{kind} {name}")))
+        .collect()
+}
+
+fn resource_kind(kind: &str) -> &str {
+    let lower = kind.to_lowercase();
+    match lower.strip_suffix('s').unwrap_or(&lower) {
+        "n" | "namespace" => "namespace",
+        "po" | "pod" => "pod",
+        "deploy" | "deployment" => "deployment",
+        "svc" | "service" => "service",
+        "pvc" | "persistentvolumeclaim" => "persistentvolumeclaim",
+        "pv" | "persistentvolume" => "persistentvolume",
+        "st" | "statefulset" => "statefulset",
+        "cm" | "configmap" => "configmap",
+        "secret" => "secret",
+        "no" | "node" => "node",
+        _ => kind,
+    }
+}
+
+/// The `current-context` of the first kubeconfig in `$KUBECONFIG` or `~/.kube/config`.
+pub fn current_kube_context() -> Option<String> {
+    let path = match env::var("KUBECONFIG") {
+        Ok(v) => env::split_paths(&v).next()?,
+        Err(_) => dirs::home_dir()?.join(".kube").join("config"),
+    };
+    read_kube_context(&path)
+}
+
+fn read_kube_context(path: &PathBuf) -> Option<String> {
+    let content = fs::read_to_string(path).ok()?;
+    let value: serde_yaml::Value = serde_yaml::from_str(&content).ok()?;
+    value
+        .get("current-context")?
+        .as_str()
+        .filter(|v| !v.is_empty())
+        .map(|v| v.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_kubectl() {
+        let cmd =
+            KubeCommand::parse("kubectl --context prod-eu -n payments delete pod api-1").unwrap();
+        assert_eq!(cmd.verb, "delete");
+        assert_eq!(cmd.context.as_deref(), Some("prod-eu"));
+        assert_eq!(cmd.namespace.as_deref(), Some("payments"));
+        assert_eq!(cmd.destructive.as_deref(), Some("delete"));
+        assert_eq!(cmd.resources, ["pod api-1"]);
+        assert!(cmd.is_production(DEFAULT_KUBE_PRODUCTION_PATTERN));
+
+        let cmd =
+            KubeCommand::parse("kubectl scale deploy/api --replicas=0 --context=staging").unwrap();
+        assert_eq!(cmd.destructive.as_deref(), Some("scale to 0"));
+        assert!(!cmd.is_production(DEFAULT_KUBE_PRODUCTION_PATTERN));
+
+        let cmd = KubeCommand::parse("kubectl delete ns payments --context prod-eu").unwrap();
+        assert_eq!(cmd.resources, ["namespace payments"]);
+        assert!(cmd.warning().unwrap().contains("every deployment, pod"));
+
+        let cmd = KubeCommand::parse("kubectl get pods -A --context dev").unwrap();
+        assert_eq!(cmd.operation(), CommandOperation::Read);
+        assert_eq!(cmd.target(), "dev (all namespaces)");
+        assert!(KubeCommand::parse("ls -la").is_none());
+    }
+
+    #[test]
+    fn test_parse_helm() {
+        let cmd = KubeCommand::parse("helm uninstall api --kube-context prod -n web").unwrap();
+        assert_eq!(cmd.tool, KubeTool::Helm);
+        assert_eq!(cmd.destructive.as_deref(), Some("uninstall"));
+        assert_eq!(cmd.resources, ["release api"]);
+        assert_eq!(cmd.target(), "prod/web");
+    }
+
+    #[test]
+    fn test_read_kube_context() {
+        let path = env::temp_dir().join(format!("aichat-kubeconfig-{}", std::process::id()));
+        fs::write(&path, "apiVersion: v1\ncurrent-context: prod-us\n").unwrap();
+        assert_eq!(read_kube_context(&path).as_deref(), Some("prod-us"));
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,493 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::ops::Range;
+
+/// Control and redirection operators, longest first so `&&` wins over `&`.
+const OPERATORS: [&str; 19] = [
+    "<<<", "&&", "||", "|&", ";;", "&>", ">>", ">&", ">|", "<<", "<&", "<>", "|", "&", ";", ">",
+    "<", "(", ")",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum TokenKind {
+    Word,
+    /// `|`, `&&`, `;`, `>`, `2>&1`, ... and line breaks.
+    Operator,
+}
+
+/// A word or operator of a command line.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Token {
+    pub kind: TokenKind,
+    /// Words after quote removal and escape processing, operators as written.
+    pub text: String,
+    /// Where the token is in the command line, in bytes.
+    pub span: Range<usize>,
+    /// Part of the word was quoted or escaped, so wildcards in it are literal.
+    pub quoted: bool,
+}
+
+impl Token {
+    pub fn is_word(&self) -> bool {
+        self.kind == TokenKind::Word
+    }
+
+    /// `|`, `&&`, `;`, ... which end a simple command.
+    pub fn is_control(&self) -> bool {
+        self.kind == TokenKind::Operator
+            && matches!(
+                self.text.as_str(),
+                "|" | "|&" | "||" | "&&" | "&" | ";" | ";;" | "\n" | "(" | ")"
+            )
+    }
+
+    pub fn is_redirect(&self) -> bool {
+        self.kind == TokenKind::Operator && !self.is_control()
+    }
+
+    /// A redirection writing to the file named by the next word. `>&2` and `2>&1`
+    /// duplicate a descriptor, `>& file` writes to the file.
+    pub fn is_output_redirect(&self) -> bool {
+        self.is_redirect()
+            && self.text.contains('>')
+            && !self.text.starts_with('<')
+            && (!self.text.contains(">&") || self.text.ends_with('&'))
+    }
+
+    /// The token as written, quotes included.
+    pub fn raw<'a>(&self, line: &'a str) -> &'a str {
+        &line[self.span.clone()]
+    }
+}
+
+/// Split a command line into words and operators like a POSIX shell does, before
+/// expansions: single quotes are literal, double quotes honor `\"`, `\\`, `\$` and
+/// `` \` ``, a backslash escapes the next character, and `#` starts a comment. `$(...)`,
+/// `${...}` and backticks stay inside their word.
+///
+/// Backslash is the path separator on Windows, so there it is kept verbatim.
+pub fn tokenize(command: &str, style: PathStyle) -> Vec<Token> {
+    let chars: Vec<(usize, char)> = command.char_indices().collect();
+    let offset = |i: usize| chars.get(i).map(|v| v.0).unwrap_or(command.len());
+    let mut tokens = vec![];
+    let mut word = String::new();
+    let mut start: Option<usize> = None;
+    let mut quoted = false;
+    let mut i = 0;
+
+    macro_rules! end_word {
+        ($end:expr) => {
+            if let Some(begin) = start.take() {
+                tokens.push(Token {
+                    kind: TokenKind::Word,
+                    text: std::mem::take(&mut word),
+                    span: offset(begin)..offset($end),
+                    quoted: std::mem::take(&mut quoted),
+                });
+            }
+        };
+    }
+
+    while i < chars.len() {
+        let c = chars[i].1;
+        match c {
+            '\'' => {
+                start.get_or_insert(i);
+                quoted = true;
+                let end = find_char(&chars, i + 1, '\'');
+                word.extend(chars[i + 1..end].iter().map(|v| v.1));
+                i = end + 1;
+            }
+            '"' => {
+                start.get_or_insert(i);
+                quoted = true;
+                i += 1;
+                while i < chars.len() && chars[i].1 != '"' {
+                    let c = chars[i].1;
+                    let next = chars.get(i + 1).map(|v| v.1);
+                    match (c, next) {
+                        ('\\', Some('\n')) if style == PathStyle::Unix => i += 1,
+                        ('\\', Some(v @ ('"' | '\\' | '$' | '`'))) if style == PathStyle::Unix => {
+                            word.push(v);
+                            i += 1;
+                        }
+                        ('$', Some('(')) => {
+                            let end = find_closing(&chars, i + 1, '(', ')');
+                            word.extend(chars[i..=end.min(chars.len() - 1)].iter().map(|v| v.1));
+                            i = end;
+                        }
+                        _ => word.push(c),
+                    }
+                    i += 1;
+                }
+                i += 1;
+            }
+            '\\' if style == PathStyle::Unix => {
+                match chars.get(i + 1).map(|v| v.1) {
+                    // Line continuation
+                    Some('\n') => {}
+                    Some(v) => {
+                        start.get_or_insert(i);
+                        quoted = true;
+                        word.push(v);
+                    }
+                    None => {
+                        start.get_or_insert(i);
+                        word.push(c);
+                    }
+                }
+                i += 2;
+            }
+            '$' if matches!(chars.get(i + 1).map(|v| v.1), Some('(' | '{')) => {
+                start.get_or_insert(i);
+                let (open, close) = match chars[i + 1].1 {
+                    '(' => ('(', ')'),
+                    _ => ('{', '}'),
+                };
+                let end = find_closing(&chars, i + 1, open, close);
+                word.extend(chars[i..=end.min(chars.len() - 1)].iter().map(|v| v.1));
+                i = end + 1;
+            }
+            '$' if chars.get(i + 1).map(|v| v.1) == Some('\'') => {
+                // ANSI-C quoting
+                start.get_or_insert(i);
+                quoted = true;
+                let end = find_char(&chars, i + 2, '\'');
+                word.push_str(&unescape_ansi_c(
+                    &chars[i + 2..end].iter().map(|v| v.1).collect::<String>(),
+                ));
+                i = end + 1;
+            }
+            '`' => {
+                start.get_or_insert(i);
+                let end = find_char(&chars, i + 1, '`');
+                word.extend(chars[i..=end.min(chars.len() - 1)].iter().map(|v| v.1));
+                i = end + 1;
+            }
+            '#' if start.is_none() => {
+                i = find_char(&chars, i, '\n');
+            }
+            '\n' => {
+                end_word!(i);
+                tokens.push(Token {
+                    kind: TokenKind::Operator,
+                    text: "\n".into(),
+                    span: offset(i)..offset(i + 1),
+                    quoted: false,
+                });
+                i += 1;
+            }
+            c if c.is_whitespace() => {
+                end_word!(i);
+                i += 1;
+            }
+            _ => {
+                let rest = &command[offset(i)..];
+                let Some(operator) = OPERATORS.iter().find(|v| rest.starts_with(**v)) else {
+                    start.get_or_insert(i);
+                    word.push(c);
+                    i += 1;
+                    continue;
+                };
+                // `2>`, `2>&1`: a file descriptor number belongs to the redirection.
+                let is_redirect = operator.starts_with(['>', '<']);
+                let begin = match start {
+                    Some(begin)
+                        if is_redirect && !quoted && word.chars().all(|v| v.is_ascii_digit()) =>
+                    {
+                        start = None;
+                        word.clear();
+                        begin
+                    }
+                    _ => {
+                        end_word!(i);
+                        i
+                    }
+                };
+                let mut end = i + operator.chars().count();
+                if operator.ends_with('&') && is_redirect {
+                    while end < chars.len()
+                        && (chars[end].1.is_ascii_digit() || chars[end].1 == '-')
+                    {
+                        end += 1;
+                    }
+                }
+                tokens.push(Token {
+                    kind: TokenKind::Operator,
+                    text: command[offset(begin)..offset(end)].to_string(),
+                    span: offset(begin)..offset(end),
+                    quoted: false,
+                });
+                i = end;
+            }
+        }
+    }
+    end_word!(chars.len());
+    tokens
+}
+
+/// The words of a command line, see [`tokenize`].
+pub fn split_command_words(command: &str, style: PathStyle) -> Vec<String> {
+    tokenize(command, style)
+        .into_iter()
+        .filter(|v| v.is_word())
+        .map(|v| v.text)
+        .collect()
+}
+
+/// A here-document: the lines after `<<EOF` up to the line `EOF`, fed to the command as
+/// its input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Heredoc {
+    /// The line the body is fed to, `cat <<EOF > config.yml`.
+    pub command: String,
+    pub delimiter: String,
+    /// `<<EOF` expands `$var`, `$(...)` and backticks in the body, `<<'EOF'` keeps it as
+    /// written.
+    pub expands: bool,
+    /// `<<-EOF` strips the leading tabs of the body and the delimiter line.
+    pub strip_tabs: bool,
+    pub body: String,
+    /// No line is the delimiter, the body runs to the end.
+    pub unterminated: bool,
+}
+
+impl Heredoc {
+    pub fn warning(&self) -> Option<String> {
+        if self.unterminated {
+            return Some(format!(
+                "⚠️  `{}`: no line is `{}`, the here-document takes in everything after it.",
+                self.command, self.delimiter
+            ));
+        }
+        if self.expands && (self.body.contains("$(") || self.body.contains('`')) {
+            return Some(format!(
+                "⚠️  `{}`: the here-document runs the `$(...)` and backticks in its body, quote the delimiter, <<'{}', to keep them as text.",
+                self.command, self.delimiter
+            ));
+        }
+        None
+    }
+}
+
+/// Take the here-document bodies out of a multi-line command and join its `\`
+/// continuations, so every line left is one command line.
+pub fn split_heredocs(command: &str) -> (String, Vec<Heredoc>) {
+    if !command.contains('\n') {
+        return (command.to_string(), vec![]);
+    }
+    let mut script = vec![];
+    let mut heredocs = vec![];
+    let mut pending = String::new();
+    let mut lines = command.lines();
+    while let Some(line) = lines.next() {
+        // An odd number of trailing backslashes escapes the line break
+        if (line.len() - line.trim_end_matches('\\').len()) % 2 == 1 {
+            pending.push_str(&line[..line.len() - 1]);
+            continue;
+        }
+        pending.push_str(line);
+        let line = std::mem::take(&mut pending);
+        let tokens = tokenize(&line, PathStyle::Unix);
+        for (i, token) in tokens.iter().enumerate() {
+            if token.is_word()
+                || token.text.trim_start_matches(|c: char| c.is_ascii_digit()) != "<<"
+            {
+                continue;
+            }
+            // `<<-EOF`, and `<<- EOF` where the dash is a word of its own
+            let mut words = tokens[i + 1..].iter().take_while(|v| v.is_word());
+            let Some(mut word) = words.next() else {
+                continue;
+            };
+            let strip_tabs = word.text.starts_with('-');
+            if word.text == "-" {
+                match words.next() {
+                    Some(v) => word = v,
+                    None => continue,
+                }
+            }
+            let delimiter = word.text.strip_prefix('-').unwrap_or(&word.text);
+            let mut body = vec![];
+            let mut unterminated = true;
+            for text in lines.by_ref() {
+                let text = match strip_tabs {
+                    true => text.trim_start_matches('\t'),
+                    false => text,
+                };
+                if text == delimiter {
+                    unterminated = false;
+                    break;
+                }
+                body.push(text);
+            }
+            heredocs.push(Heredoc {
+                command: line.trim().to_string(),
+                delimiter: delimiter.to_string(),
+                expands: !word.quoted,
+                strip_tabs,
+                body: body.join("\n"),
+                unterminated,
+            });
+        }
+        script.push(line);
+    }
+    if !pending.is_empty() {
+        script.push(pending);
+    }
+    (script.join("\n"), heredocs)
+}
+
+/// Index of the next `c` at or after `from`, or the end.
+fn find_char(chars: &[(usize, char)], from: usize, c: char) -> usize {
+    chars[from.min(chars.len())..]
+        .iter()
+        .position(|v| v.1 == c)
+        .map(|v| from + v)
+        .unwrap_or(chars.len())
+}
+
+/// Index of the `close` matching the `open` at `start`, skipping quoted text.
+fn find_closing(chars: &[(usize, char)], start: usize, open: char, close: char) -> usize {
+    let mut depth = 0;
+    let mut quote: Option<char> = None;
+    for (i, (_, c)) in chars.iter().enumerate().skip(start) {
+        match quote {
+            Some(q) if *c == q => quote = None,
+            Some(_) => {}
+            None if *c == '\'' || *c == '"' => quote = Some(*c),
+            None if *c == open => depth += 1,
+            None if *c == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return i;
+                }
+            }
+            None => {}
+        }
+    }
+    chars.len()
+}
+
+fn unescape_ansi_c(text: &str) -> String {
+    let mut output = String::new();
+    let mut chars = text.chars();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            output.push(c);
+            continue;
+        }
+        match chars.next() {
+            Some('n') => output.push('\n'),
+            Some('t') => output.push('\t'),
+            Some('r') => output.push('\r'),
+            Some('e') => output.push('\x1b'),
+            Some('0') => output.push('\0'),
+            Some(v) => output.push(v),
+            None => output.push('\\'),
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn words(command: &str) -> Vec<String> {
+        split_command_words(command, PathStyle::Unix)
+    }
+
+    fn operators(command: &str) -> Vec<String> {
+        tokenize(command, PathStyle::Unix)
+            .into_iter()
+            .filter(|v| !v.is_word())
+            .map(|v| v.text)
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_quotes() {
+        assert_eq!(words(r#"rm "my file.txt""#), ["rm", "my file.txt"]);
+        assert_eq!(words("grep 'a | b' log"), ["grep", "a | b", "log"]);
+        assert_eq!(words(r#"echo "say \"hi\" \n""#), ["echo", r#"say "hi" \n"#]);
+        assert_eq!(
+            words(r"rm my\ file.txt 'a b'"),
+            ["rm", "my file.txt", "a b"]
+        );
+        assert_eq!(words("echo pre'fix'\"ed\""), ["echo", "prefixed"]);
+        assert_eq!(words("printf $'a\\tb'"), ["printf", "a\tb"]);
+        assert_eq!(words("echo ''"), ["echo", ""]);
+        assert_eq!(words("rm -rf build # clean up"), ["rm", "-rf", "build"]);
+        assert_eq!(words("echo a#b"), ["echo", "a#b"]);
+        assert_eq!(
+            words("tar czf \\\n  out.tgz src"),
+            ["tar", "czf", "out.tgz", "src"]
+        );
+    }
+
+    #[test]
+    fn test_tokenize_substitutions() {
+        assert_eq!(
+            words(r#"rm "$(ls | head -1)" ${DIR}/x `pwd`"#),
+            ["rm", "$(ls | head -1)", "${DIR}/x", "`pwd`"]
+        );
+        let tokens = tokenize("rm '*.log' *.tmp", PathStyle::Unix);
+        assert!(tokens[1].quoted);
+        assert!(!tokens[2].quoted);
+    }
+
+    #[test]
+    fn test_tokenize_operators() {
+        assert_eq!(
+            operators("make 2>&1 | tee log && echo ok>>done; ls >/dev/null 2> err"),
+            ["2>&1", "|", "&&", ">>", ";", ">", "2>"]
+        );
+        assert_eq!(words("echo hi>out.txt"), ["echo", "hi", "out.txt"]);
+        assert_eq!(words("echo '>' \"a;b\""), ["echo", ">", "a;b"]);
+        let command = "cat <<< 'x y' | wc";
+        let tokens = tokenize(command, PathStyle::Unix);
+        assert!(tokens[1].is_redirect());
+        assert_eq!(tokens[2].raw(command), "'x y'");
+        assert!(tokens[3].is_control());
+    }
+
+    #[test]
+    fn test_split_heredocs() {
+        let command = "cat <<EOF > config.yml\nname: $(whoami)\nEOF\ntar czf \\\n  out.tgz src";
+        let (script, heredocs) = split_heredocs(command);
+        assert_eq!(script, "cat <<EOF > config.yml\ntar czf   out.tgz src");
+        assert_eq!(heredocs[0].command, "cat <<EOF > config.yml");
+        assert_eq!(heredocs[0].body, "name: $(whoami)");
+        assert!(heredocs[0].expands);
+        assert!(heredocs[0].warning().unwrap().contains("<<'EOF'"));
+
+        let command = "sudo tee -a /etc/hosts <<-'END' >/dev/null\n\t10.0.0.1 db\n\tEND\nls";
+        let (script, heredocs) = split_heredocs(command);
+        assert_eq!(script, "sudo tee -a /etc/hosts <<-'END' >/dev/null\nls");
+        assert_eq!(heredocs[0].delimiter, "END");
+        assert_eq!(heredocs[0].body, "10.0.0.1 db");
+        assert!(!heredocs[0].expands && heredocs[0].strip_tabs);
+        assert!(heredocs[0].warning().is_none());
+
+        let (_, heredocs) = split_heredocs("cat <<EOF\nrm -rf /\n");
+        assert!(heredocs[0].unterminated);
+        assert_eq!(split_heredocs("cat <<< 'x'\nls").1, []);
+    }
+
+    #[test]
+    fn test_tokenize_windows() {
+        assert_eq!(
+            split_command_words(
+                r#"del "C:\Program Files\app\log.txt" /q"#,
+                PathStyle::Windows
+            ),
+            [r"del", r"C:\Program Files\app\log.txt", "/q"]
+        );
+        assert_eq!(
+            split_command_words(r"type C:\temp\a.txt", PathStyle::Windows),
+            ["type", r"C:\temp\a.txt"]
+        );
+    }
+}
@@ -0,0 +1,243 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+
+/// Models and quantizations, see `assets/local-models.yaml`.
+const LOCAL_MODELS: &str = include_str!("../../assets/local-models.yaml");
+
+static CATALOG: LazyLock<LocalModelCatalog> =
+    LazyLock::new(|| serde_yaml::from_str(LOCAL_MODELS).expect("invalid local-models.yaml"));
+
+/// Gigabytes on top of the weights for the context cache and the runtime.
+const RUNTIME_OVERHEAD_GB: f64 = 1.5;
+
+#[derive(Debug, Clone, Deserialize)]
+struct LocalModelCatalog {
+    models: Vec<LocalModel>,
+    quantizations: Vec<Quantization>,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct LocalModel {
+    name: String,
+    tag: String,
+    params: f64,
+    context: String,
+}
+
+#[derive(Debug, Clone, Deserialize)]
+struct Quantization {
+    name: String,
+    bits: f64,
+}
+
+/// Where a model's weights live while it runs, fastest first.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ModelFit {
+    /// In the memory of a dedicated GPU.
+    Gpu,
+    /// In the memory Apple Silicon shares between CPU and GPU.
+    Unified,
+    /// Split between the GPU and system memory.
+    Offloaded,
+    /// In system memory, computed by the CPU.
+    Cpu,
+}
+
+impl std::fmt::Display for ModelFit {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let text = match self {
+            ModelFit::Gpu => "GPU, fast",
+            ModelFit::Unified => "GPU (unified memory), fast",
+            ModelFit::Offloaded => "GPU + RAM, slower",
+            ModelFit::Cpu => "CPU only, slow",
+        };
+        write!(f, "{text}")
+    }
+}
+
+/// A model in the best quantization that runs well on the machine.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ModelRecommendation {
+    pub name: String,
+    /// The Ollama tag with the quantization, `llama3.1:8b-instruct-q4_K_M`.
+    pub tag: String,
+    pub quantization: String,
+    /// Memory it takes to run, weights and context cache.
+    pub memory_gb: f64,
+    pub fit: ModelFit,
+    pub context: String,
+}
+
+impl ModelRecommendation {
+    pub fn pull_command(&self) -> String {
+        format!("ollama pull {}", self.tag)
+    }
+}
+
+impl EnvProfile {
+    /// Local models that run well here: each in the largest quantization fitting the
+    /// fastest memory it can, the biggest models first. CPU-only models beyond what the
+    /// cores compute at a usable speed are left out.
+    pub fn recommend_local_models(&self) -> Vec<ModelRecommendation> {
+        let ram = self.memory_total_gb as f64;
+        let apple = matches!(self.os, OSKind::MacOS)
+            && self
+                .gpu_name
+                .as_deref()
+                .is_some_and(|v| v.contains("Apple"));
+        let vram = self.gpu_vram_gb.map(|v| v as f64).unwrap_or_default();
+        // What is left beside the OS and the applications running now
+        let free_ram = (ram - self.memory_used_gb as f64).max(ram * 0.5) * 0.8;
+        let fit = |memory: f64| {
+            if apple && memory <= ram * 0.7 {
+                Some(ModelFit::Unified)
+            } else if vram > 0.0 && memory <= vram * 0.9 {
+                Some(ModelFit::Gpu)
+            } else if vram > 0.0 && memory <= vram * 0.9 + free_ram {
+                Some(ModelFit::Offloaded)
+            } else if memory <= free_ram {
+                Some(ModelFit::Cpu)
+            } else {
+                None
+            }
+        };
+        let mut output = vec![];
+        for model in &CATALOG.models {
+            let options: Vec<(ModelFit, &Quantization, f64)> = CATALOG
+                .quantizations
+                .iter()
+                .filter_map(|quant| {
+                    let memory = model.params * quant.bits / 8.0 + RUNTIME_OVERHEAD_GB;
+                    fit(memory).map(|fit| (fit, quant, memory))
+                })
+                .collect();
+            // The fastest placement, then the most bits in it
+            let Some((fit, quant, memory)) = options
+                .iter()
+                .min_by(|a, b| a.0.cmp(&b.0).then(b.1.bits.total_cmp(&a.1.bits)))
+            else {
+                continue;
+            };
+            let usable = match fit {
+                ModelFit::Cpu => model.params <= self.cpu_cores.max(1) as f64,
+                ModelFit::Offloaded => model.params <= vram * 3.0,
+                _ => true,
+            };
+            if !usable {
+                continue;
+            }
+            output.push(ModelRecommendation {
+                name: model.name.clone(),
+                tag: format!("{}-{}", model.tag, quant.name),
+                quantization: quant.name.clone(),
+                memory_gb: (memory * 10.0).round() / 10.0,
+                fit: *fit,
+                context: model.context.clone(),
+            });
+        }
+        output.sort_by(|a, b| a.fit.cmp(&b.fit).then(b.memory_gb.total_cmp(&a.memory_gb)));
+        output
+    }
+
+    /// How to install Ollama with the package manager of the machine.
+    pub fn ollama_install_command(&self) -> String {
+        let nvidia = self
+            .gpu_name
+            .as_deref()
+            .is_some_and(|v| v.to_lowercase().contains("nvidia"));
+        match self.pkg {
+            PackageManager::Brew => "brew install ollama".to_string(),
+            PackageManager::Pacman if nvidia => "sudo pacman -S ollama-cuda".to_string(),
+            PackageManager::Pacman => "sudo pacman -S ollama".to_string(),
+            PackageManager::Nix => "nix-env -iA nixpkgs.ollama".to_string(),
+            PackageManager::Winget => "winget install Ollama.Ollama".to_string(),
+            PackageManager::Scoop => "scoop install ollama".to_string(),
+            PackageManager::Choco => "choco install ollama".to_string(),
+            PackageManager::Apt | PackageManager::Unknown if matches!(self.os, OSKind::Linux) => {
+                "curl -fsSL https://ollama.com/install.sh -o install-ollama.sh && less install-ollama.sh && sh install-ollama.sh".to_string()
+            }
+            _ => "Download it from https://ollama.com/download".to_string(),
+        }
+    }
+
+    /// `16 GB RAM, 8 cores, NVIDIA GeForce RTX 3060 (12 GB)`
+    pub fn hardware_summary(&self) -> String {
+        let mut output = format!("{} GB RAM, {} cores", self.memory_total_gb, self.cpu_cores);
+        if let Some(gpu) = &self.gpu_name {
+            output.push_str(&format!(", {gpu}"));
+            if let Some(vram) = self.gpu_vram_gb {
+                output.push_str(&format!(" ({vram} GB)"));
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_recommend_local_models() {
+        let gaming = EnvProfile {
+            os: OSKind::Linux,
+            pkg: PackageManager::Pacman,
+            cpu_cores: 8,
+            memory_total_gb: 32,
+            memory_used_gb: 8,
+            gpu_name: Some("NVIDIA GeForce RTX 3060".to_string()),
+            gpu_vram_gb: Some(12),
+            ..Default::default()
+        };
+        let models = gaming.recommend_local_models();
+        assert_eq!(models[0].fit, ModelFit::Gpu);
+        let llama = models.iter().find(|v| v.name == "Llama 3.1 8B").unwrap();
+        assert_eq!(llama.tag, "llama3.1:8b-instruct-q8_0");
+        assert_eq!(
+            llama.pull_command(),
+            "ollama pull llama3.1:8b-instruct-q8_0"
+        );
+        let coder = models
+            .iter()
+            .find(|v| v.name == "Qwen 2.5 Coder 14B")
+            .unwrap();
+        assert_eq!(
+            (coder.fit, coder.quantization.as_str()),
+            (ModelFit::Gpu, "q4_K_M")
+        );
+        assert!(models.iter().all(|v| v.name != "Llama 3.3 70B"));
+        assert_eq!(
+            gaming.ollama_install_command(),
+            "sudo pacman -S ollama-cuda"
+        );
+
+        let laptop = EnvProfile {
+            os: OSKind::Linux,
+            pkg: PackageManager::Apt,
+            cpu_cores: 4,
+            memory_total_gb: 8,
+            memory_used_gb: 4,
+            ..Default::default()
+        };
+        let models = laptop.recommend_local_models();
+        let names: Vec<_> = models.iter().map(|v| v.name.as_str()).collect();
+        assert_eq!(names, ["Llama 3.2 3B"]);
+        assert_eq!(models[0].fit, ModelFit::Cpu);
+
+        let mac = EnvProfile {
+            os: OSKind::MacOS,
+            pkg: PackageManager::Brew,
+            cpu_cores: 10,
+            memory_total_gb: 32,
+            gpu_name: Some("Apple Silicon / Integrated".to_string()),
+            ..Default::default()
+        };
+        let models = mac.recommend_local_models();
+        assert!(models.iter().all(|v| v.fit == ModelFit::Unified));
+        assert!(models.iter().any(|v| v.name == "Qwen 2.5 Coder 32B"));
+        assert_eq!(mac.ollama_install_command(), "brew install ollama");
+    }
+}
@@ -0,0 +1,210 @@
+//! The command safety engine: analysis, tutoring, backups and environment detection.
+//!
+//! Nothing here depends on the CLI, so the engine builds with `default-features = false`
+//! and can be embedded through [`SafetyEngine`].
+
+mod accessibility;
+mod aliases;
+mod backup;
+mod command;
+mod command_analyzer;
+mod command_lists;
+mod command_tutor;
+mod consequence;
+mod disk;
+mod disk_space;
+mod docker;
+mod duration;
+mod env_diff;
+pub mod environments;
+mod exfiltration;
+mod exhaustion;
+mod file_risk;
+mod filesystem;
+mod find;
+mod fs_impact;
+mod fs_snapshot;
+mod git;
+mod iac;
+mod inplace;
+mod kube;
+mod lexer;
+mod local_models;
+mod network;
+mod outputs;
+mod packages;
+mod patch;
+mod path;
+mod path_scope;
+mod permissions;
+mod persistence;
+mod pipe_to_shell;
+mod placeholder;
+mod policy;
+mod ports;
+mod process_impact;
+mod quantities;
+mod remote;
+mod risk;
+mod rules;
+mod safety_engine;
+mod script;
+mod shell_path;
+mod shellcheck;
+mod sql;
+mod summary;
+mod supervisor;
+mod symlinks;
+mod system_config;
+mod theme;
+mod tldr;
+mod tool_flags;
+mod transfer;
+mod tutor_knowledge;
+mod undo;
+mod variables;
+mod windows;
+mod xargs;
+
+pub use self::accessibility::*;
+pub use self::aliases::*;
+pub use self::backup::*;
+pub use self::command::*;
+pub use self::command_analyzer::*;
+pub use self::command_lists::*;
+pub use self::command_tutor::*;
+pub use self::disk::*;
+pub use self::disk_space::*;
+pub use self::docker::*;
+pub use self::duration::*;
+pub use self::env_diff::*;
+pub use self::environments::{
+    detect_mounts, detect_package_manager, detect_toolchain, EnvProfile, MountSpace, OSKind,
+    PackageManager, ShellKind, ToolFlavor, ToolInfo, TOOLCHAIN,
+};
+pub use self::exfiltration::*;
+pub use self::exhaustion::*;
+pub use self::file_risk::*;
+pub use self::filesystem::*;
+pub use self::find::*;
+pub use self::fs_impact::*;
+pub use self::fs_snapshot::*;
+pub use self::git::*;
+pub use self::iac::*;
+pub use self::inplace::*;
+pub use self::kube::*;
+pub use self::lexer::*;
+pub use self::local_models::*;
+pub use self::network::*;
+pub use self::outputs::*;
+pub use self::packages::*;
+pub use self::patch::*;
+pub use self::path::*;
+pub use self::path_scope::*;
+pub use self::permissions::*;
+pub use self::persistence::*;
+pub use self::pipe_to_shell::*;
+pub use self::placeholder::*;
+pub use self::policy::*;
+pub use self::ports::*;
+pub use self::process_impact::*;
+pub use self::quantities::*;
+pub use self::remote::*;
+pub use self::risk::*;
+pub use self::rules::*;
+pub use self::safety_engine::*;
+pub use self::script::*;
+pub use self::shell_path::*;
+pub use self::shellcheck::*;
+pub use self::sql::*;
+pub use self::summary::*;
+pub use self::supervisor::*;
+pub use self::symlinks::*;
+pub use self::system_config::*;
+pub use self::theme::*;
+pub use self::tldr::*;
+pub use self::tool_flags::*;
+pub use self::transfer::*;
+pub use self::tutor_knowledge::*;
+pub use self::undo::*;
+pub use self::variables::*;
+pub use self::windows::*;
+pub use self::xargs::*;
+
+use fancy_regex::Regex;
+use is_terminal::IsTerminal;
+use std::sync::LazyLock;
+use std::{env, path::PathBuf, process};
+
+pub static IS_STDOUT_TERMINAL: LazyLock<bool> = LazyLock::new(|| std::io::stdout().is_terminal());
+pub static NO_COLOR: LazyLock<bool> = LazyLock::new(|| {
+    env::var("NO_COLOR")
+        .ok()
+        .and_then(|v| parse_bool(&v))
+        .unwrap_or_default()
+        || !*IS_STDOUT_TERMINAL
+});
+
+pub fn now() -> String {
+    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
+}
+
+pub fn now_timestamp() -> i64 {
+    chrono::Local::now().timestamp()
+}
+
+pub fn get_env_name(key: &str) -> String {
+    format!("{}_{key}", env!("CARGO_CRATE_NAME"),).to_ascii_uppercase()
+}
+
+pub fn parse_bool(value: &str) -> Option<bool> {
+    match value {
+        "1" | "true" => Some(true),
+        "0" | "false" => Some(false),
+        _ => None,
+    }
+}
+
+pub fn error_text(input: &str) -> String {
+    color_text(input, theme().error.0)
+}
+
+pub fn warning_text(input: &str) -> String {
+    color_text(input, theme().warning.0)
+}
+
+pub fn success_text(input: &str) -> String {
+    color_text(input, theme().success.0)
+}
+
+pub fn color_text(input: &str, color: nu_ansi_term::Color) -> String {
+    if is_accessible() {
+        return accessible_text(input);
+    }
+    if *NO_COLOR {
+        return input.to_string();
+    }
+    nu_ansi_term::Style::new()
+        .fg(color)
+        .paint(input)
+        .to_string()
+}
+
+pub fn dimmed_text(input: &str) -> String {
+    if is_accessible() {
+        return accessible_text(input);
+    }
+    if *NO_COLOR {
+        return input.to_string();
+    }
+    nu_ansi_term::Style::new().dimmed().paint(input).to_string()
+}
+
+pub fn temp_file(prefix: &str, suffix: &str) -> PathBuf {
+    env::temp_dir().join(format!(
+        "{}-{}{prefix}{}{suffix}",
+        env!("CARGO_CRATE_NAME").to_lowercase(),
+        process::id(),
+        uuid::Uuid::new_v4()
+    ))
+}
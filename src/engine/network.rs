@@ -0,0 +1,208 @@
+/// A host a network command talks to.
+#[derive(Debug, Clone, PartialEq)]
+pub struct NetworkDestination {
+    pub host: String,
+    /// The full URL for curl/wget.
+    pub url: Option<String>,
+}
+
+impl std::fmt::Display for NetworkDestination {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match &self.url {
+            Some(url) => write!(f, "{url}"),
+            None => write!(f, "{}", self.host),
+        }
+    }
+}
+
+/// Extract the destinations of curl, wget, ssh, scp, rsync, nc and telnet commands.
+pub fn extract_network_destinations(segment: &str) -> Vec<NetworkDestination> {
+    let words = shell_words::split(segment)
+        .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+    let Some(cmd) = words.first() else {
+        return vec![];
+    };
+    // Flags whose next word is a value, not a destination.
+    let value_flags: &[&str] = match cmd.as_str() {
+        "curl" => &[
+            "-o",
+            "-d",
+            "-H",
+            "-X",
+            "-u",
+            "-A",
+            "-e",
+            "-F",
+            "-T",
+            "--data",
+            "--header",
+            "--output",
+            "--request",
+            "--user",
+            "--form",
+        ],
+        "wget" => &["-O", "-o", "-P", "--header", "--user", "--password"],
+        "ssh" => &[
+            "-p", "-i", "-o", "-l", "-J", "-F", "-L", "-R", "-D", "-b", "-c", "-E",
+        ],
+        "scp" => &["-P", "-i", "-o", "-F", "-J", "-l", "-c"],
+        "rsync" => &["-e", "--rsh", "--exclude", "--include", "--port"],
+        "nc" | "ncat" | "netcat" => &["-p", "-s", "-w", "-i"],
+        "telnet" => &[],
+        _ => return vec![],
+    };
+
+    let mut positional = vec![];
+    let mut iter = words[1..].iter();
+    while let Some(word) = iter.next() {
+        if value_flags.contains(&word.as_str()) {
+            iter.next();
+        } else if !word.starts_with('-') {
+            positional.push(word.as_str());
+        }
+    }
+
+    let mut destinations = vec![];
+    match cmd.as_str() {
+        "curl" | "wget" => {
+            for word in positional {
+                if let Some(host) = url_host(word) {
+                    destinations.push(NetworkDestination {
+                        host,
+                        url: Some(word.to_string()),
+                    });
+                }
+            }
+        }
+        "ssh" | "nc" | "ncat" | "netcat" | "telnet" => {
+            if let Some(word) = positional.first() {
+                destinations.push(NetworkDestination {
+                    host: strip_user(word).to_string(),
+                    url: None,
+                });
+            }
+        }
+        _ => {
+            // scp/rsync: `[user@]host:path` or `rsync://host/module`
+            for word in positional {
+                let host = match word.contains("://") {
+                    true => url_host(word),
+                    false => word
+                        .split_once(':')
+                        .filter(|(host, _)| !host.is_empty() && !host.contains('/'))
+                        .map(|(host, _)| strip_user(host).to_string()),
+                };
+                if let Some(host) = host {
+                    destinations.push(NetworkDestination { host, url: None });
+                }
+            }
+        }
+    }
+    destinations
+}
+
+fn strip_user(value: &str) -> &str {
+    value.rsplit_once('@').map(|(_, v)| v).unwrap_or(value)
+}
+
+/// The host of `scheme://[user@]host[:port]/path`, or of a bare `host/path` for curl.
+fn url_host(value: &str) -> Option<String> {
+    let rest = match value.split_once("://") {
+        Some((_, rest)) => rest,
+        None if value.contains('.') && !value.starts_with('.') && !value.starts_with('/') => value,
+        None => return None,
+    };
+    let authority = rest.split(['/', '?', '#']).next()?;
+    let host = strip_user(authority);
+    let host = match host.strip_prefix('[') {
+        Some(v) => v.split(']').next()?,
+        None => host.split(':').next()?,
+    };
+    match host.is_empty() {
+        true => None,
+        false => Some(host.to_lowercase()),
+    }
+}
+
+/// Host allowlist/denylist from `network_allowlist` and `network_denylist`.
+///
+/// Patterns are host names, optionally with a leading `*.` wildcard matching any subdomain.
+/// A non-empty allowlist blocks every host it doesn't match.
+#[derive(Debug, Clone, Default)]
+pub struct NetworkPolicy {
+    pub allow: Vec<String>,
+    pub deny: Vec<String>,
+}
+
+impl NetworkPolicy {
+    /// The reason the host is blocked, if it is.
+    pub fn check(&self, host: &str) -> Option<String> {
+        if let Some(pattern) = self.deny.iter().find(|v| host_matches(v, host)) {
+            return Some(format!("'{host}' is denied by '{pattern}'"));
+        }
+        if !self.allow.is_empty() && !self.allow.iter().any(|v| host_matches(v, host)) {
+            return Some(format!("'{host}' is not in the network allowlist"));
+        }
+        None
+    }
+}
+
+fn host_matches(pattern: &str, host: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let host = host.to_lowercase();
+    match pattern.strip_prefix("*.") {
+        Some(domain) => host == domain || host.ends_with(&format!(".{domain}")),
+        None => pattern == "*" || pattern == host,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn hosts(segment: &str) -> Vec<String> {
+        extract_network_destinations(segment)
+            .into_iter()
+            .map(|v| v.host)
+            .collect()
+    }
+
+    #[test]
+    fn test_extract_network_destinations() {
+        assert_eq!(
+            hosts("curl -H 'X-A: b' -o out.sh https://Paste.example.com:8443/raw?x=1"),
+            vec!["paste.example.com"]
+        );
+        assert_eq!(
+            hosts("wget -O- get.example.org/install.sh"),
+            vec!["get.example.org"]
+        );
+        assert_eq!(
+            hosts("ssh -p 2222 -i key deploy@10.0.0.5 uptime"),
+            vec!["10.0.0.5"]
+        );
+        assert_eq!(
+            hosts("scp ./a.txt user@backup.internal:/srv/ b.txt"),
+            vec!["backup.internal"]
+        );
+        assert_eq!(
+            hosts("rsync -av -e 'ssh -p 22' dist/ web1:/var/www"),
+            vec!["web1"]
+        );
+        assert_eq!(hosts("nc -w 3 db.internal 5432"), vec!["db.internal"]);
+        assert!(hosts("ls -la").is_empty());
+    }
+
+    #[test]
+    fn test_network_policy() {
+        let policy = NetworkPolicy {
+            allow: vec!["*.corp.example.com".into(), "github.com".into()],
+            deny: vec!["*.paste.example.org".into()],
+        };
+        assert!(policy.check("registry.corp.example.com").is_none());
+        assert!(policy.check("github.com").is_none());
+        assert!(policy.check("random-paste-site.net").is_some());
+        assert!(policy.check("x.paste.example.org").is_some());
+        assert!(NetworkPolicy::default().check("anything.net").is_none());
+    }
+}
@@ -0,0 +1,334 @@
+use super::*;
+
+use serde::Serialize;
+use std::path::PathBuf;
+
+/// A path a command writes to, whether or not it exists yet, see [`command_outputs`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct OutputPath {
+    /// The program writing it, `>` for redirects.
+    pub tool: String,
+    pub path: PathBuf,
+    pub exists: bool,
+    /// An existing directory (or a path ending in a separator) the inputs go into.
+    pub into_dir: bool,
+    /// Existing files the command replaces, the destination itself or files inside it.
+    pub overwrites: Vec<PathBuf>,
+}
+
+impl OutputPath {
+    fn new(tool: &str, word: &str, style: PathStyle, fs: &dyn FileSystem) -> Self {
+        let path = resolve_command_path(word, style, fs);
+        Self {
+            tool: tool.to_string(),
+            exists: fs.exists(&path),
+            into_dir: fs.is_dir(&path) || word.ends_with(|c| style.is_separator(c)),
+            overwrites: vec![],
+            path,
+        }
+    }
+
+    /// Replaces the destination when it is an existing file.
+    fn replacing(mut self, fs: &dyn FileSystem) -> Self {
+        if fs.is_file(&self.path) {
+            self.overwrites.push(self.path.clone());
+        }
+        self
+    }
+
+    /// Like `mv`/`cp`: into an existing directory, the inputs replace files of the same name.
+    fn receiving(mut self, inputs: &[&str], style: PathStyle, fs: &dyn FileSystem) -> Self {
+        if !self.into_dir || !self.exists {
+            return self.replacing(fs);
+        }
+        for input in inputs {
+            let input = resolve_command_path(input, style, fs);
+            let Some(name) = input.file_name() else {
+                continue;
+            };
+            let target = self.path.join(name);
+            if fs.is_file(&target) && !self.overwrites.contains(&target) {
+                self.overwrites.push(target);
+            }
+        }
+        self
+    }
+
+    /// A warning when existing files would be replaced, or when the destination's parent
+    /// is missing and the command will fail.
+    pub fn warning(&self, fs: &dyn FileSystem) -> Option<String> {
+        match self.overwrites.as_slice() {
+            [] => {}
+            [path] => {
+                return Some(format!(
+                    "⚠️  {} overwrites the existing file {}.",
+                    self.tool,
+                    path.display()
+                ))
+            }
+            paths => {
+                return Some(format!(
+                    "⚠️  {} overwrites {} existing files in {}.",
+                    self.tool,
+                    paths.len(),
+                    self.path.display()
+                ))
+            }
+        }
+        if self.exists {
+            return None;
+        }
+        let missing = match self.into_dir {
+            true => Some(self.path.as_path()),
+            false => self.path.parent().filter(|v| !fs.exists(v)),
+        };
+        missing.map(|dir| {
+            format!(
+                "⚠️  {} doesn't exist, {} will fail unless it creates it.",
+                dir.display(),
+                self.tool
+            )
+        })
+    }
+}
+
+/// Where a command writes, existing or not: the destination of `mv`/`cp`/`install`/`rsync`/
+/// `scp` (local ones), the archive of `tar -c`/`zip`, `-o` files of compilers,
+/// `curl`, `sort` and friends, `wget -O`, `dd of=`, `tee` and `>`/`>>` redirects.
+pub fn command_outputs(
+    command: &str,
+    style: PathStyle,
+    shell: &ShellKind,
+    fs: &dyn FileSystem,
+) -> Vec<OutputPath> {
+    let mut outputs: Vec<OutputPath> = vec![];
+    for segment in split_command_segments(command, shell) {
+        let tokens = tokenize(&segment, style);
+        let mut iter = tokens.iter().peekable();
+        while let Some(token) = iter.next() {
+            if !token.is_output_redirect() {
+                continue;
+            }
+            if let Some(target) = iter.next_if(|v| v.is_word()) {
+                if is_raw_device(&target.text) || target.text == "/dev/null" {
+                    continue;
+                }
+                let output = OutputPath::new(">", &target.text, style, fs);
+                outputs.push(match token.text.contains(">>") {
+                    true => output,
+                    false => output.replacing(fs),
+                });
+            }
+        }
+        let words: Vec<&str> = tokens
+            .iter()
+            .filter(|v| v.is_word())
+            .map(|v| v.text.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas") || v.contains('='))
+            .collect();
+        let Some((tool, args)) = words.split_first() else {
+            continue;
+        };
+        outputs.extend(tool_outputs(tool, args, style, fs));
+    }
+    let mut seen: Vec<PathBuf> = vec![];
+    outputs.retain(|v| {
+        let new = !seen.contains(&v.path);
+        seen.push(v.path.clone());
+        new
+    });
+    outputs
+}
+
+fn tool_outputs(
+    tool: &str,
+    args: &[&str],
+    style: PathStyle,
+    fs: &dyn FileSystem,
+) -> Vec<OutputPath> {
+    let output = |word: &str| OutputPath::new(tool, word, style, fs);
+    let operands: Vec<&str> = args
+        .iter()
+        .copied()
+        .filter(|v| !v.starts_with('-'))
+        .collect();
+    match tool {
+        "mv" | "cp" | "install" | "rsync" | "scp" => {
+            // `-t DIR` names the destination up front
+            let target = args.iter().enumerate().find_map(|(i, v)| match *v {
+                "-t" => args.get(i + 1).copied(),
+                v => v.strip_prefix("--target-directory="),
+            });
+            let (destination, inputs) = match target {
+                Some(target) => (
+                    target,
+                    operands.iter().filter(|v| **v != target).copied().collect(),
+                ),
+                None => match operands.split_last() {
+                    Some((last, rest)) if !rest.is_empty() => (*last, rest.to_vec()),
+                    _ => return vec![],
+                },
+            };
+            if is_remote(destination, style) {
+                return vec![];
+            }
+            vec![output(destination).receiving(&inputs, style, fs)]
+        }
+        "tar" | "bsdtar" => {
+            let mut create = false;
+            let mut file = None;
+            for (i, arg) in args.iter().enumerate() {
+                if let Some(value) = arg.strip_prefix("--file=") {
+                    file = Some(value);
+                } else if *arg == "--create" {
+                    create = true;
+                } else if *arg == "--file" {
+                    file = args.get(i + 1).copied();
+                } else if !arg.starts_with("--") && (i == 0 || arg.starts_with('-')) {
+                    // Bundled letters: `-czf out.tgz`, `cvf out.tar`
+                    let letters = arg.trim_start_matches('-');
+                    let head = match letters.find('f') {
+                        Some(at) => &letters[..at],
+                        None => letters,
+                    };
+                    create |= head.contains('c');
+                    if let Some(at) = letters.find('f') {
+                        let rest = &letters[at + 1..];
+                        file = match rest.is_empty() {
+                            true => args.get(i + 1).copied(),
+                            false => Some(&arg[arg.len() - rest.len()..]),
+                        };
+                    }
+                }
+            }
+            match (create, file) {
+                (true, Some(file)) if file != "-" => vec![output(file).replacing(fs)],
+                _ => vec![],
+            }
+        }
+        // Adds to an existing archive instead of replacing it
+        "zip" | "7z" | "7za" => {
+            let archive = match tool {
+                "zip" => operands.first(),
+                _ => operands
+                    .first()
+                    .filter(|v| **v == "a")
+                    .and_then(|_| operands.get(1)),
+            };
+            archive.map(|v| output(v)).into_iter().collect()
+        }
+        "tee" => {
+            let append = args.iter().any(|v| *v == "-a" || *v == "--append");
+            operands
+                .iter()
+                .map(|v| match append {
+                    true => output(v),
+                    false => output(v).replacing(fs),
+                })
+                .collect()
+        }
+        "dd" => args
+            .iter()
+            .filter_map(|v| v.strip_prefix("of="))
+            .filter(|v| !is_raw_device(v))
+            .map(|v| output(v).replacing(fs))
+            .collect(),
+        _ => {
+            let flags: &[&str] = match tool {
+                "curl" => &["-o", "--output"],
+                "wget" => &["-O", "--output-document"],
+                "gcc" | "g++" | "cc" | "c++" | "clang" | "clang++" | "rustc" | "go" | "sort"
+                | "pandoc" => &["-o", "--output"],
+                "openssl" => &["-out"],
+                _ => return vec![],
+            };
+            let mut outputs = vec![];
+            for (i, arg) in args.iter().enumerate() {
+                let value = match flags.contains(arg) {
+                    true => args.get(i + 1).copied(),
+                    false => flags
+                        .iter()
+                        .filter(|v| v.starts_with("--"))
+                        .find_map(|v| arg.strip_prefix(&format!("{v}="))),
+                };
+                if let Some(value) = value.filter(|v| *v != "-") {
+                    outputs.push(output(value).replacing(fs));
+                }
+            }
+            outputs
+        }
+    }
+}
+
+/// `host:path` and `user@host:path` of scp/rsync, not `C:\dir`.
+pub(super) fn is_remote(word: &str, style: PathStyle) -> bool {
+    let Some((host, _)) = word.split_once(':') else {
+        return false;
+    };
+    let drive = style == PathStyle::Windows && host.len() == 1;
+    !drive && !host.is_empty() && !host.contains(['/', '\\'])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn outputs(command: &str, fs: &MemoryFs) -> Vec<OutputPath> {
+        command_outputs(command, PathStyle::Unix, &ShellKind::Bash, fs)
+    }
+
+    #[test]
+    fn test_command_outputs() {
+        let fs = MemoryFs::new()
+            .cwd("/work")
+            .file("/work/data.csv", 10)
+            .file("/work/src/main.rs", 10)
+            .file("/work/backup.tar", 10)
+            .file("/srv/data.csv", 10)
+            .dir("/tmp");
+
+        let mv = outputs("mv data.csv /tmp/old/", &fs);
+        assert_eq!(mv.len(), 1);
+        assert_eq!(mv[0].path, PathBuf::from("/tmp/old"));
+        assert!(!mv[0].exists && mv[0].into_dir);
+        assert_eq!(
+            mv[0].warning(&fs).unwrap(),
+            "⚠️  /tmp/old doesn't exist, mv will fail unless it creates it."
+        );
+
+        let cp = outputs("cp -v data.csv /srv", &fs);
+        assert_eq!(cp[0].overwrites, [PathBuf::from("/srv/data.csv")]);
+        assert_eq!(
+            cp[0].warning(&fs).unwrap(),
+            "⚠️  cp overwrites the existing file /srv/data.csv."
+        );
+
+        let tar = outputs("tar -czf backup.tar src/", &fs);
+        assert_eq!(tar[0].path, PathBuf::from("/work/backup.tar"));
+        assert_eq!(tar[0].overwrites, [PathBuf::from("/work/backup.tar")]);
+        let tar = outputs("tar cvf new.tar src", &fs);
+        assert_eq!(tar[0].path, PathBuf::from("/work/new.tar"));
+        assert!(tar[0].warning(&fs).is_none());
+        assert!(outputs("tar -xf backup.tar", &fs).is_empty());
+
+        let curl = outputs(
+            "curl -sL https://example.com/x.csv -o data.csv | tee -a log.txt",
+            &fs,
+        );
+        let paths: Vec<_> = curl.iter().map(|v| v.path.clone()).collect();
+        assert_eq!(
+            paths,
+            [
+                PathBuf::from("/work/data.csv"),
+                PathBuf::from("/work/log.txt")
+            ]
+        );
+        assert_eq!(curl[0].overwrites.len(), 1);
+
+        let redirect = outputs("sort data.csv >> sorted.csv 2>/dev/null", &fs);
+        assert_eq!(redirect.len(), 1);
+        assert!(redirect[0].overwrites.is_empty());
+        assert!(outputs("scp data.csv host:/tmp/", &fs).is_empty());
+        assert!(outputs("rm data.csv", &fs).is_empty());
+    }
+}
@@ -0,0 +1,424 @@
+use super::*;
+
+use serde::Serialize;
+
+/// Packages without which the system no longer boots, logs in or runs a shell.
+const ESSENTIAL_PACKAGES: [&str; 16] = [
+    "bash",
+    "coreutils",
+    "dpkg",
+    "apt",
+    "glibc",
+    "libc6",
+    "linux",
+    "linux-firmware",
+    "openssh-server",
+    "pacman",
+    "python3",
+    "sudo",
+    "systemd",
+    "grub",
+    "grub2",
+    "util-linux",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PackageAction {
+    Install,
+    Upgrade,
+    Remove,
+    /// Removes the configuration or unneeded dependencies too.
+    Purge,
+    Read,
+    Other,
+}
+
+/// An invocation of a system or language package manager.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PackageCommand {
+    pub tool: String,
+    /// The system package manager `tool` belongs to, `None` for dnf, snap, pip, npm and the like.
+    pub manager: Option<PackageManager>,
+    pub action: PackageAction,
+    /// Named packages; empty for `autoremove` and upgrades of everything.
+    pub packages: Vec<String>,
+    /// Run through sudo/doas.
+    pub privileged: bool,
+    /// `npm -g`, `yarn global`: installed for the whole machine, not the project.
+    pub global: bool,
+    /// This machine's package manager when `tool` belongs to another one and isn't installed,
+    /// see [`PackageCommand::resolve`].
+    pub mismatch: Option<PackageManager>,
+}
+
+impl PackageCommand {
+    /// Parse a simple command (one pipeline stage), see [`split_command_segments`].
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let privileged = matches!(words.first().map(|v| v.as_str()), Some("sudo" | "doas"));
+        let mut words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        // `python3 -m pip uninstall x`
+        if words.len() > 2 && words[0].starts_with("python") && words[1] == "-m" {
+            words.drain(..2);
+        }
+        let tool = words.first()?.rsplit(['/', '\\']).next()?.to_string();
+        let args = &words[1..];
+        let flags: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|v| v.starts_with('-'))
+            .collect();
+        let positionals: Vec<&str> = args
+            .iter()
+            .copied()
+            .filter(|v| !v.starts_with('-'))
+            .collect();
+        let has = |names: &[&str]| flags.iter().any(|v| names.contains(v));
+        let verb = positionals.first().copied().unwrap_or_default();
+        let mut packages: Vec<String> = positionals.iter().skip(1).map(|v| v.to_string()).collect();
+        let mut global = false;
+
+        let (manager, action) = match tool.as_str() {
+            "apt" | "apt-get" | "aptitude" => (
+                Some(PackageManager::Apt),
+                match verb {
+                    "install" | "reinstall" => PackageAction::Install,
+                    "remove" if has(&["--purge"]) => PackageAction::Purge,
+                    "remove" => PackageAction::Remove,
+                    "purge" | "autoremove" | "autopurge" => PackageAction::Purge,
+                    "upgrade" | "dist-upgrade" | "full-upgrade" => PackageAction::Upgrade,
+                    "list" | "search" | "show" | "policy" | "depends" | "rdepends" => {
+                        PackageAction::Read
+                    }
+                    _ => PackageAction::Other,
+                },
+            ),
+            "dpkg" => {
+                packages = positionals.iter().map(|v| v.to_string()).collect();
+                let action = if has(&["-P", "--purge"]) {
+                    PackageAction::Purge
+                } else if has(&["-r", "--remove"]) {
+                    PackageAction::Remove
+                } else if has(&["-i", "--install"]) {
+                    PackageAction::Install
+                } else {
+                    PackageAction::Read
+                };
+                (Some(PackageManager::Apt), action)
+            }
+            "brew" => (
+                Some(PackageManager::Brew),
+                match verb {
+                    "install" | "reinstall" => PackageAction::Install,
+                    "uninstall" | "remove" | "rm" if has(&["--zap"]) => PackageAction::Purge,
+                    "uninstall" | "remove" | "rm" => PackageAction::Remove,
+                    "autoremove" | "cleanup" => PackageAction::Purge,
+                    "upgrade" => PackageAction::Upgrade,
+                    "list" | "info" | "search" | "outdated" | "deps" | "leaves" => {
+                        PackageAction::Read
+                    }
+                    _ => PackageAction::Other,
+                },
+            ),
+            "pacman" | "yay" | "paru" => {
+                packages = positionals.iter().map(|v| v.to_string()).collect();
+                let operation = flags
+                    .iter()
+                    .find(|v| !v.starts_with("--") && v.len() > 1)
+                    .copied()
+                    .unwrap_or_default();
+                let action = match operation.chars().nth(1) {
+                    Some('R') if operation.contains(['n', 's']) => PackageAction::Purge,
+                    Some('R') => PackageAction::Remove,
+                    Some('S') if operation.contains(['s', 'i']) => PackageAction::Read,
+                    Some('S') if operation.contains('u') && packages.is_empty() => {
+                        PackageAction::Upgrade
+                    }
+                    Some('S' | 'U') => PackageAction::Install,
+                    Some('Q' | 'F') => PackageAction::Read,
+                    _ => PackageAction::Other,
+                };
+                (Some(PackageManager::Pacman), action)
+            }
+            "nix-env" => {
+                packages = positionals.iter().map(|v| v.to_string()).collect();
+                let action = if has(&["-e", "--uninstall"]) {
+                    PackageAction::Remove
+                } else if has(&["-i", "--install"]) {
+                    PackageAction::Install
+                } else if has(&["-u", "--upgrade"]) {
+                    PackageAction::Upgrade
+                } else {
+                    PackageAction::Read
+                };
+                (Some(PackageManager::Nix), action)
+            }
+            "choco" | "scoop" | "winget" => (
+                Some(match tool.as_str() {
+                    "choco" => PackageManager::Choco,
+                    "scoop" => PackageManager::Scoop,
+                    _ => PackageManager::Winget,
+                }),
+                match verb {
+                    "install" | "add" => PackageAction::Install,
+                    "uninstall" | "remove" => PackageAction::Remove,
+                    "upgrade" | "update" => PackageAction::Upgrade,
+                    "list" | "search" | "info" | "show" => PackageAction::Read,
+                    _ => PackageAction::Other,
+                },
+            ),
+            "dnf" | "yum" | "zypper" | "apk" | "snap" | "flatpak" | "pip" | "pip3" | "pipx"
+            | "gem" | "cargo" | "npm" | "pnpm" | "yarn" => {
+                // `yarn global remove x`, `npm rm -g x`
+                let (verb, rest) = match (tool.as_str(), verb) {
+                    ("yarn", "global") => {
+                        global = true;
+                        (positionals.get(1).copied().unwrap_or_default(), 2)
+                    }
+                    _ => (verb, 1),
+                };
+                packages = positionals
+                    .iter()
+                    .skip(rest)
+                    .map(|v| v.to_string())
+                    .collect();
+                global |= has(&["-g", "--global"]);
+                let action = match verb {
+                    "install" | "in" | "i" | "add" if tool != "cargo" || !packages.is_empty() => {
+                        PackageAction::Install
+                    }
+                    "remove" | "rm" | "uninstall" | "un" | "erase" | "del" | "r" => {
+                        match has(&["--purge"]) {
+                            true => PackageAction::Purge,
+                            false => PackageAction::Remove,
+                        }
+                    }
+                    "autoremove" | "prune" => PackageAction::Purge,
+                    "upgrade" | "update" | "up" => PackageAction::Upgrade,
+                    "list" | "ls" | "search" | "info" | "show" | "freeze" | "outdated" => {
+                        PackageAction::Read
+                    }
+                    _ => return None,
+                };
+                (None, action)
+            }
+            _ => return None,
+        };
+
+        Some(Self {
+            tool,
+            manager,
+            action,
+            packages,
+            privileged,
+            global,
+            mismatch: None,
+        })
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        match self.action {
+            PackageAction::Remove | PackageAction::Purge => CommandOperation::Delete,
+            PackageAction::Install | PackageAction::Upgrade | PackageAction::Other
+                if self.privileged || self.global =>
+            {
+                CommandOperation::System
+            }
+            PackageAction::Install | PackageAction::Upgrade => CommandOperation::Execute,
+            PackageAction::Read => CommandOperation::Read,
+            PackageAction::Other => CommandOperation::Unknown,
+        }
+    }
+
+    /// Removed packages the system can't run without.
+    pub fn essential_packages(&self) -> Vec<&str> {
+        if !matches!(self.action, PackageAction::Remove | PackageAction::Purge)
+            || self.manager.is_none()
+        {
+            return vec![];
+        }
+        self.packages
+            .iter()
+            .map(|v| v.as_str())
+            .filter(|name| {
+                let base = name.split([':', '=']).next().unwrap_or(name);
+                ESSENTIAL_PACKAGES.contains(&base)
+                    || base.starts_with("linux-image")
+                    || base.starts_with("grub-")
+            })
+            .collect()
+    }
+
+    /// `detected` when `tool` belongs to another package manager and isn't installed here.
+    pub fn check_manager(
+        &self,
+        detected: &PackageManager,
+        installed: bool,
+    ) -> Option<PackageManager> {
+        let manager = self.manager.as_ref()?;
+        match installed || manager == detected || *detected == PackageManager::Unknown {
+            true => None,
+            false => Some(detected.clone()),
+        }
+    }
+
+    /// The same operation with the package manager `other`, package names kept.
+    pub fn equivalent(&self, other: &PackageManager) -> Option<String> {
+        let command = match (other, self.action) {
+            (PackageManager::Apt, PackageAction::Install) => "sudo apt install",
+            (PackageManager::Apt, PackageAction::Remove) => "sudo apt remove",
+            (PackageManager::Apt, PackageAction::Purge) => "sudo apt purge",
+            (PackageManager::Apt, PackageAction::Upgrade) => "sudo apt upgrade",
+            (PackageManager::Brew, PackageAction::Install) => "brew install",
+            (PackageManager::Brew, PackageAction::Remove | PackageAction::Purge) => {
+                "brew uninstall"
+            }
+            (PackageManager::Brew, PackageAction::Upgrade) => "brew upgrade",
+            (PackageManager::Pacman, PackageAction::Install) => "sudo pacman -S",
+            (PackageManager::Pacman, PackageAction::Remove) => "sudo pacman -R",
+            (PackageManager::Pacman, PackageAction::Purge) => "sudo pacman -Rns",
+            (PackageManager::Pacman, PackageAction::Upgrade) => "sudo pacman -Syu",
+            (PackageManager::Nix, PackageAction::Install) => "nix-env -i",
+            (PackageManager::Nix, PackageAction::Remove | PackageAction::Purge) => "nix-env -e",
+            (PackageManager::Nix, PackageAction::Upgrade) => "nix-env -u",
+            (PackageManager::Choco, PackageAction::Install) => "choco install",
+            (PackageManager::Choco, PackageAction::Remove | PackageAction::Purge) => {
+                "choco uninstall"
+            }
+            (PackageManager::Choco, PackageAction::Upgrade) => "choco upgrade",
+            (PackageManager::Scoop, PackageAction::Install) => "scoop install",
+            (PackageManager::Scoop, PackageAction::Remove | PackageAction::Purge) => {
+                "scoop uninstall"
+            }
+            (PackageManager::Scoop, PackageAction::Upgrade) => "scoop update",
+            (PackageManager::Winget, PackageAction::Install) => "winget install",
+            (PackageManager::Winget, PackageAction::Remove | PackageAction::Purge) => {
+                "winget uninstall"
+            }
+            (PackageManager::Winget, PackageAction::Upgrade) => "winget upgrade",
+            _ => return None,
+        };
+        Some(
+            [command.to_string(), shell_words::join(&self.packages)]
+                .join(" ")
+                .trim_end()
+                .to_string(),
+        )
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        let packages = match self.packages.is_empty() {
+            true => String::new(),
+            false => format!(" {}", self.packages.join(", ")),
+        };
+        match self.action {
+            PackageAction::Remove if !self.packages.is_empty() => warnings.push(format!(
+                "⚠️  Packages: {} removes{packages}{}.",
+                self.tool,
+                if self.global {
+                    " from the whole machine"
+                } else {
+                    ""
+                }
+            )),
+            PackageAction::Purge if self.packages.is_empty() => warnings.push(format!(
+                "⚠️  Packages: {} removes every package nothing depends on anymore.",
+                self.tool
+            )),
+            PackageAction::Purge => warnings.push(format!(
+                "⚠️  Packages: {} removes{packages} with {}.",
+                self.tool,
+                match self.manager {
+                    Some(PackageManager::Pacman) =>
+                        "dependencies nothing else needs and their config files",
+                    _ => "their configuration files",
+                }
+            )),
+            _ => {}
+        }
+        let essential = self.essential_packages();
+        if !essential.is_empty() {
+            warnings.push(format!(
+                "⚠️  Packages: the system can't boot, log in or run a shell without {}!",
+                essential.join(", ")
+            ));
+        }
+        if let Some(manager) = &self.mismatch {
+            warnings.push(match self.equivalent(manager) {
+                Some(command) => format!(
+                    "⚠️  Packages: {} isn't installed, this machine uses {manager}: {command} (package names may differ)",
+                    self.tool
+                ),
+                None => format!(
+                    "⚠️  Packages: {} isn't installed, this machine uses {manager}.",
+                    self.tool
+                ),
+            });
+        }
+        warnings
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_package_command() {
+        let cmd = PackageCommand::parse("sudo apt-get remove --purge -y nginx").unwrap();
+        assert_eq!(cmd.action, PackageAction::Purge);
+        assert_eq!(cmd.packages, ["nginx"]);
+        assert!(cmd.privileged);
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+
+        let cmd = PackageCommand::parse("sudo pacman -Rns systemd").unwrap();
+        assert_eq!(cmd.action, PackageAction::Purge);
+        assert_eq!(cmd.essential_packages(), ["systemd"]);
+
+        let cmd = PackageCommand::parse("npm uninstall -g typescript").unwrap();
+        assert_eq!(cmd.action, PackageAction::Remove);
+        assert!(cmd.global);
+
+        let cmd = PackageCommand::parse("python3 -m pip uninstall requests").unwrap();
+        assert_eq!(cmd.tool, "pip");
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+
+        let cmd = PackageCommand::parse("sudo brew install jq").unwrap();
+        assert_eq!(cmd.operation(), CommandOperation::System);
+        assert_eq!(
+            PackageCommand::parse("brew install jq")
+                .unwrap()
+                .operation(),
+            CommandOperation::Execute
+        );
+        assert_eq!(
+            PackageCommand::parse("pacman -Ss vim").unwrap().action,
+            PackageAction::Read
+        );
+        assert!(PackageCommand::parse("npm run build").is_none());
+    }
+
+    #[test]
+    fn test_check_package_manager() {
+        let mut cmd = PackageCommand::parse("sudo apt remove jq").unwrap();
+        assert_eq!(
+            cmd.check_manager(&PackageManager::Brew, false),
+            Some(PackageManager::Brew)
+        );
+        cmd.mismatch = Some(PackageManager::Brew);
+        assert!(cmd
+            .warnings()
+            .iter()
+            .any(|v| v.contains("this machine uses Brew: brew uninstall jq")));
+        assert_eq!(cmd.check_manager(&PackageManager::Brew, true), None);
+        assert_eq!(cmd.check_manager(&PackageManager::Apt, false), None);
+        assert_eq!(cmd.check_manager(&PackageManager::Unknown, false), None);
+    }
+}
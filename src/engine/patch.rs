@@ -0,0 +1,439 @@
+use super::*;
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+};
+
+/// Lines a hunk may have drifted from where the diff says it starts.
+const MAX_HUNK_OFFSET: usize = 200;
+
+/// File edits proposed as unified diffs, applied all together or not at all.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct PatchSet {
+    pub patches: Vec<FilePatch>,
+}
+
+/// The diff of one file. Created when the old side is `/dev/null`, deleted when the new
+/// one is.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FilePatch {
+    pub path: PathBuf,
+    pub creates: bool,
+    pub deletes: bool,
+    hunks: Vec<Hunk>,
+    /// The diff as the model wrote it, for showing.
+    pub diff: String,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Hunk {
+    old_start: usize,
+    lines: Vec<HunkLine>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum HunkLine {
+    Context(String),
+    Remove(String),
+    Add(String),
+}
+
+/// What [`PatchSet::apply`] changed.
+#[derive(Debug, Clone, Default)]
+pub struct PatchOutcome {
+    pub backup: Option<BackupEntry>,
+    pub changed: Vec<PathBuf>,
+}
+
+impl PatchSet {
+    /// The diffs in `text`, in ```` ```diff ```` blocks or bare. `a/` and `b/` prefixes are
+    /// dropped and paths are relative to `dir`.
+    pub fn parse(text: &str, dir: &Path) -> Result<Self> {
+        let mut patches: Vec<FilePatch> = vec![];
+        let lines: Vec<&str> = text.lines().collect();
+        let mut i = 0;
+        while i < lines.len() {
+            if !(lines[i].starts_with("--- ")
+                && lines.get(i + 1).is_some_and(|v| v.starts_with("+++ ")))
+            {
+                i += 1;
+                continue;
+            }
+            let old = diff_path(&lines[i][4..]);
+            let new = diff_path(&lines[i + 1][4..]);
+            let start = i;
+            i += 2;
+            let mut hunks = vec![];
+            while let Some(header) = lines.get(i).filter(|v| v.starts_with("@@")) {
+                let old_start = parse_hunk_header(header)
+                    .ok_or_else(|| anyhow!("Invalid hunk header '{header}'"))?;
+                i += 1;
+                let mut hunk = Hunk {
+                    old_start,
+                    lines: vec![],
+                };
+                while let Some(line) = lines.get(i) {
+                    let line = match line.chars().next() {
+                        Some(' ') => HunkLine::Context(line[1..].to_string()),
+                        Some('-') if !line.starts_with("--- ") => {
+                            HunkLine::Remove(line[1..].to_string())
+                        }
+                        Some('+') if !line.starts_with("+++ ") => {
+                            HunkLine::Add(line[1..].to_string())
+                        }
+                        Some('\\') => {
+                            i += 1;
+                            continue;
+                        }
+                        // Models drop the space of empty context lines
+                        None => HunkLine::Context(String::new()),
+                        _ => break,
+                    };
+                    hunk.lines.push(line);
+                    i += 1;
+                }
+                // A blank line between a diff and the closing fence isn't context
+                while hunk.lines.last() == Some(&HunkLine::Context(String::new())) {
+                    hunk.lines.pop();
+                }
+                hunks.push(hunk);
+            }
+            let (path, creates, deletes) = match (old, new) {
+                (None, Some(new)) => (new, true, false),
+                (Some(old), None) => (old, false, true),
+                (Some(_), Some(new)) => (new, false, false),
+                (None, None) => bail!("Invalid diff header '{}'", lines[start]),
+            };
+            if hunks.is_empty() && !deletes {
+                bail!("The diff of {path} has no hunks");
+            }
+            patches.push(FilePatch {
+                path: dir.join(path),
+                creates,
+                deletes,
+                hunks,
+                diff: lines[start..i].join("\n"),
+            });
+        }
+        if patches.is_empty() {
+            bail!("No unified diff found");
+        }
+        Ok(Self { patches })
+    }
+
+    pub fn paths(&self) -> Vec<PathBuf> {
+        self.patches.iter().map(|v| v.path.clone()).collect()
+    }
+
+    /// `src/main.rs +3 -1` per file, the change set recorded in the audit log.
+    pub fn summary(&self) -> Vec<String> {
+        self.patches
+            .iter()
+            .map(|v| {
+                let (added, removed) = v.stats();
+                let kind = match (v.creates, v.deletes) {
+                    (true, _) => " (new)",
+                    (_, true) => " (deleted)",
+                    _ => "",
+                };
+                format!("{}{kind} +{added} -{removed}", v.path.display())
+            })
+            .collect()
+    }
+
+    /// The diffs with added lines green and removed ones red.
+    pub fn display(&self) -> String {
+        let theme = theme();
+        let mut output = vec![];
+        for patch in &self.patches {
+            for line in patch.diff.lines() {
+                let line = if line.starts_with("+++ ") || line.starts_with("--- ") {
+                    match *NO_COLOR || is_accessible() {
+                        true => line.to_string(),
+                        false => nu_ansi_term::Style::new().bold().paint(line).to_string(),
+                    }
+                } else if line.starts_with('+') {
+                    color_text(line, theme.success.0)
+                } else if line.starts_with('-') {
+                    color_text(line, theme.error.0)
+                } else if line.starts_with("@@") {
+                    color_text(line, theme.info.0)
+                } else {
+                    line.to_string()
+                };
+                output.push(line);
+            }
+        }
+        output.join("\n")
+    }
+
+    /// Apply every diff or none: the new contents are computed first, the files that exist
+    /// are backed up, then written, and the ones already written are put back when a later
+    /// write fails.
+    pub fn apply(&self, backup_manager: &BackupManager, label: &str) -> Result<PatchOutcome> {
+        let _span = tracing::info_span!("apply_patch", files = self.patches.len()).entered();
+        let mut staged = vec![];
+        for patch in &self.patches {
+            let original = match fs::read_to_string(&patch.path) {
+                Ok(_) if patch.creates => {
+                    bail!("{} already exists", patch.path.display())
+                }
+                Ok(content) => Some(content),
+                Err(_) if patch.creates => None,
+                Err(err) => bail!("Failed to read {}: {err}", patch.path.display()),
+            };
+            let content = match patch.deletes {
+                true => None,
+                false => Some(
+                    patch
+                        .apply_to(original.as_deref().unwrap_or_default())
+                        .with_context(|| format!("Failed to patch {}", patch.path.display()))?,
+                ),
+            };
+            staged.push((patch, original, content));
+        }
+
+        let existing: Vec<PathBuf> = staged
+            .iter()
+            .filter(|v| v.1.is_some())
+            .map(|v| v.0.path.clone())
+            .collect();
+        let backup = match existing.is_empty() {
+            true => None,
+            false => Some(
+                backup_manager
+                    .create_backup(label, existing)
+                    .context("Backup failed, nothing was changed")?,
+            ),
+        };
+
+        let mut written: Vec<(&FilePatch, &Option<String>)> = vec![];
+        for (patch, original, content) in &staged {
+            if let Err(err) = write_patched(&patch.path, content.as_deref()) {
+                tracing::warn!(path = %patch.path.display(), "write failed, rolling back");
+                let mut rollback_errors = vec![];
+                for (patch, original) in written.iter().rev() {
+                    if let Err(err) = write_patched(&patch.path, original.as_deref()) {
+                        rollback_errors.push(format!("{}: {err}", patch.path.display()));
+                    }
+                }
+                let restored = match rollback_errors.is_empty() {
+                    true => "the other files were restored".to_string(),
+                    false => format!(
+                        "restoring failed for {}, see the backup",
+                        rollback_errors.join(", ")
+                    ),
+                };
+                bail!(
+                    "Failed to write {}: {err}, {restored}",
+                    patch.path.display()
+                );
+            }
+            written.push((patch, original));
+        }
+        tracing::info!(files = written.len(), "applied patch");
+
+        Ok(PatchOutcome {
+            backup,
+            changed: self.paths(),
+        })
+    }
+}
+
+impl FilePatch {
+    /// Lines added and removed.
+    pub fn stats(&self) -> (usize, usize) {
+        let lines = self.hunks.iter().flat_map(|v| v.lines.iter());
+        lines.fold((0, 0), |(added, removed), line| match line {
+            HunkLine::Add(_) => (added + 1, removed),
+            HunkLine::Remove(_) => (added, removed + 1),
+            HunkLine::Context(_) => (added, removed),
+        })
+    }
+
+    /// `content` with the hunks applied. A hunk whose lines moved is looked for near where
+    /// it says it starts, the whole diff fails when one can't be found.
+    pub fn apply_to(&self, content: &str) -> Result<String> {
+        let mut lines: Vec<String> = content.lines().map(|v| v.to_string()).collect();
+        // Later hunks go first so the line numbers of earlier ones stay valid
+        let mut hunks: Vec<&Hunk> = self.hunks.iter().collect();
+        hunks.sort_by_key(|v| std::cmp::Reverse(v.old_start));
+        for hunk in hunks {
+            let old: Vec<&str> = hunk
+                .lines
+                .iter()
+                .filter_map(|v| match v {
+                    HunkLine::Context(v) | HunkLine::Remove(v) => Some(v.as_str()),
+                    HunkLine::Add(_) => None,
+                })
+                .collect();
+            let new: Vec<String> = hunk
+                .lines
+                .iter()
+                .filter_map(|v| match v {
+                    HunkLine::Context(v) | HunkLine::Add(v) => Some(v.clone()),
+                    HunkLine::Remove(_) => None,
+                })
+                .collect();
+            let at = find_hunk(&lines, &old, hunk.old_start.saturating_sub(1))
+                .ok_or_else(|| anyhow!("hunk at line {} doesn't match the file", hunk.old_start))?;
+            lines.splice(at..at + old.len(), new);
+        }
+        let mut output = lines.join("\n");
+        if !lines.is_empty() && (content.is_empty() || content.ends_with('\n')) {
+            output.push('\n');
+        }
+        Ok(output)
+    }
+}
+
+/// Where `old` is in `lines`, the nearest match to `expected` first.
+fn find_hunk(lines: &[String], old: &[&str], expected: usize) -> Option<usize> {
+    let matches = |at: usize| {
+        at + old.len() <= lines.len()
+            && old
+                .iter()
+                .zip(&lines[at..])
+                .all(|(a, b)| a.trim_end() == b.trim_end())
+    };
+    if old.is_empty() {
+        return Some(expected.min(lines.len()));
+    }
+    (0..=MAX_HUNK_OFFSET).find_map(|offset| {
+        let after = expected + offset;
+        let before = expected.checked_sub(offset);
+        if matches(after) {
+            Some(after)
+        } else {
+            before.filter(|v| matches(*v))
+        }
+    })
+}
+
+/// Write `content` next to `path` and rename it over, remove `path` for `None`.
+fn write_patched(path: &Path, content: Option<&str>) -> Result<()> {
+    let Some(content) = content else {
+        if path.exists() {
+            fs::remove_file(path)?;
+        }
+        return Ok(());
+    };
+    if let Some(parent) = path.parent() {
+        fs::create_dir_all(parent)?;
+    }
+    let name = path
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid file name"))?
+        .to_string_lossy();
+    let temp = path.with_file_name(format!(".{name}.aichat-patch"));
+    fs::write(&temp, content)?;
+    if let Ok(metadata) = fs::metadata(path) {
+        fs::set_permissions(&temp, metadata.permissions())?;
+    }
+    fs::rename(&temp, path).inspect_err(|_| {
+        let _ = fs::remove_file(&temp);
+    })?;
+    Ok(())
+}
+
+/// The path of a `---`/`+++` line, `None` for `/dev/null`.
+fn diff_path(value: &str) -> Option<String> {
+    // `git diff` appends a tab and a timestamp to some headers
+    let value = value.split('\t').next().unwrap_or_default().trim();
+    if value == "/dev/null" {
+        return None;
+    }
+    let value = value
+        .strip_prefix("a/")
+        .or_else(|| value.strip_prefix("b/"))
+        .unwrap_or(value);
+    Some(value.to_string())
+}
+
+/// The old start line of `@@ -12,7 +12,8 @@`.
+fn parse_hunk_header(header: &str) -> Option<usize> {
+    let old = header.trim_start_matches('@').split_whitespace().next()?;
+    let old = old.strip_prefix('-')?;
+    old.split(',').next()?.parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const REPLY: &str = r#"Rename the flag and add a changelog:
+
+```diff
+--- a/src/main.rs
++++ b/src/main.rs
+@@ -1,4 +1,4 @@
+ fn main() {
+-    let verbose = false;
++    let quiet = false;
+     run();
+ }
+--- /dev/null
++++ b/CHANGELOG.md
+@@ -0,0 +1,2 @@
++# Changelog
++- Rename verbose to quiet
+```
+"#;
+
+    #[test]
+    fn test_parse_patch() {
+        let dir = Path::new("/work");
+        let set = PatchSet::parse(REPLY, dir).unwrap();
+        assert_eq!(
+            set.paths(),
+            [dir.join("src/main.rs"), dir.join("CHANGELOG.md")]
+        );
+        assert!(set.patches[1].creates);
+        assert_eq!(
+            set.summary(),
+            ["/work/src/main.rs +1 -1", "/work/CHANGELOG.md (new) +2 -0"]
+        );
+        // The hunk moved down two lines and still applies
+        let content = "// app\n\nfn main() {\n    let verbose = false;\n    run();\n}\n";
+        assert_eq!(
+            set.patches[0].apply_to(content).unwrap(),
+            "// app\n\nfn main() {\n    let quiet = false;\n    run();\n}\n"
+        );
+        assert_eq!(
+            set.patches[1].apply_to("").unwrap(),
+            "# Changelog\n- Rename verbose to quiet\n"
+        );
+        assert!(set.patches[0].apply_to("fn other() {}\n").is_err());
+        assert!(PatchSet::parse("no diff here", dir).is_err());
+    }
+
+    #[test]
+    fn test_apply_patch_rolls_back() {
+        let dir = std::env::temp_dir().join(format!("aichat-patch-{}", std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(dir.join("src")).unwrap();
+        let main = "fn main() {\n    let verbose = false;\n    run();\n}\n";
+        fs::write(dir.join("src/main.rs"), main).unwrap();
+        let manager = BackupManager::with_dir(dir.join("backups")).unwrap();
+
+        // CHANGELOG.md exists, so nothing may change
+        fs::write(dir.join("CHANGELOG.md"), "old\n").unwrap();
+        let set = PatchSet::parse(REPLY, &dir).unwrap();
+        assert!(set.apply(&manager, "patch").is_err());
+        assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), main);
+
+        fs::remove_file(dir.join("CHANGELOG.md")).unwrap();
+        let outcome = set.apply(&manager, "patch").unwrap();
+        assert_eq!(outcome.changed.len(), 2);
+        assert!(fs::read_to_string(dir.join("src/main.rs"))
+            .unwrap()
+            .contains("let quiet"));
+        let backup = outcome.backup.unwrap();
+        assert_eq!(backup.files.len(), 1);
+        manager.restore_backup(&backup.id).unwrap();
+        assert_eq!(fs::read_to_string(dir.join("src/main.rs")).unwrap(), main);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
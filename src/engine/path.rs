@@ -30,7 +30,7 @@ pub fn safe_join_path<T1: AsRef<Path>, T2: AsRef<Path>>(
     }
 }
 
-pub async fn expand_glob_paths<T: AsRef<str>>(
+pub fn expand_glob_paths<T: AsRef<str>>(
     paths: &[T],
     bail_non_exist: bool,
 ) -> Result<IndexSet<String>> {
@@ -43,8 +43,7 @@ pub async fn expand_glob_paths<T: AsRef<str>>(
             suffixes.as_ref(),
             current_only,
             bail_non_exist,
-        )
-        .await?;
+        )?;
     }
     Ok(new_paths)
 }
@@ -151,8 +150,7 @@ fn parse_glob(path_str: &str) -> Result<(String, Option<Vec<String>>, bool)> {
     }
 }
 
-#[async_recursion::async_recursion]
-async fn list_files(
+fn list_files(
     files: &mut IndexSet<String>,
     entry_path: &Path,
     suffixes: Option<&Vec<String>>,
@@ -167,12 +165,11 @@ async fn list_files(
         }
     }
     if entry_path.is_dir() {
-        let mut reader = tokio::fs::read_dir(entry_path).await?;
-        while let Some(entry) = reader.next_entry().await? {
-            let path = entry.path();
+        for entry in std::fs::read_dir(entry_path)? {
+            let path = entry?.path();
             if path.is_dir() {
                 if !current_only {
-                    list_files(files, &path, suffixes, current_only, bail_non_exist).await?;
+                    list_files(files, &path, suffixes, current_only, bail_non_exist)?;
                 }
             } else {
                 add_file(files, suffixes, &path);
@@ -0,0 +1,177 @@
+use super::*;
+
+use serde::Serialize;
+use std::path::Path;
+
+/// Directories where a stray change can break the machine.
+const UNIX_SYSTEM_DIRS: [&str; 17] = [
+    "/bin",
+    "/boot",
+    "/dev",
+    "/etc",
+    "/lib",
+    "/lib32",
+    "/lib64",
+    "/opt",
+    "/proc",
+    "/sbin",
+    "/sys",
+    "/usr",
+    "/var",
+    "/System",
+    "/Library",
+    "/private/etc",
+    "/private/var",
+];
+
+/// Scratch space inside the system directories.
+const UNIX_TEMP_DIRS: [&str; 3] = ["/var/tmp", "/var/folders", "/private/var/folders"];
+
+/// Where desktops mount USB sticks and SD cards, for when the mount isn't flagged removable.
+const UNIX_REMOVABLE_DIRS: [&str; 3] = ["/media", "/run/media", "/Volumes"];
+
+const WINDOWS_SYSTEM_DIRS: [&str; 4] = [
+    "C:\\Windows",
+    "C:\\Program Files",
+    "C:\\Program Files (x86)",
+    "C:\\ProgramData",
+];
+
+/// Where a path is relative to the user, from the least to the most surprising place for a
+/// command to change.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PathScope {
+    /// Inside the current working directory.
+    WorkingTree,
+    /// Inside the home directory, outside the working directory.
+    Home,
+    /// Outside both, like `/tmp` or another user's files.
+    Elsewhere,
+    /// On a USB stick, SD card or other media that can be unplugged.
+    Removable,
+    /// `/etc`, `/usr`, `/boot`, `C:\Windows` and the like.
+    System,
+}
+
+impl std::fmt::Display for PathScope {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PathScope::WorkingTree => write!(f, "working directory"),
+            PathScope::Home => write!(f, "home"),
+            PathScope::Elsewhere => write!(f, "outside home"),
+            PathScope::Removable => write!(f, "removable media"),
+            PathScope::System => write!(f, "system"),
+        }
+    }
+}
+
+/// The directories paths are classified against.
+#[derive(Debug, Clone, Default)]
+pub struct ScopeContext {
+    cwd: PathBuf,
+    home: Option<PathBuf>,
+    /// Mount points of removable media.
+    removable: Vec<PathBuf>,
+}
+
+impl ScopeContext {
+    pub fn new(fs: &dyn FileSystem) -> Self {
+        Self {
+            cwd: fs.current_dir(),
+            home: fs.home_dir(),
+            removable: fs.removable_mounts(),
+        }
+    }
+
+    pub fn scope(&self, path: &Path, style: PathStyle) -> PathScope {
+        let path = path.to_string_lossy();
+        let within = |dir: &str| path_within(&path, dir, style);
+        let within_path = |dir: &Path| within(&dir.to_string_lossy());
+        let system = match style {
+            PathStyle::Unix => {
+                UNIX_SYSTEM_DIRS.iter().any(|v| within(v))
+                    && !UNIX_TEMP_DIRS.iter().any(|v| within(v))
+            }
+            PathStyle::Windows => WINDOWS_SYSTEM_DIRS.iter().any(|v| within(v)),
+        };
+        // The working directory at `/` or `C:\` covers everything, it isn't a working tree
+        let cwd = self.cwd.to_string_lossy();
+        let in_cwd = cwd
+            .trim_end_matches(['/', '\\'])
+            .trim_end_matches(':')
+            .len()
+            > 1
+            && within(&cwd);
+        // Service accounts live under `/var`, their home is still theirs
+        let in_home = self
+            .home
+            .as_deref()
+            .filter(|v| v.to_string_lossy().len() > 1)
+            .is_some_and(within_path);
+        if in_home && in_cwd {
+            PathScope::WorkingTree
+        } else if in_home {
+            PathScope::Home
+        } else if system {
+            PathScope::System
+        } else if in_cwd {
+            PathScope::WorkingTree
+        } else if self.removable.iter().any(|v| within_path(v))
+            || (style == PathStyle::Unix
+                && UNIX_REMOVABLE_DIRS.iter().any(|v| within(v))
+                && !within("/Volumes/Macintosh HD"))
+        {
+            PathScope::Removable
+        } else {
+            PathScope::Elsewhere
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_path_scope() {
+        let context = ScopeContext {
+            cwd: "/home/me/project".into(),
+            home: Some("/home/me".into()),
+            removable: vec!["/mnt/usb".into()],
+        };
+        let scope = |path: &str| context.scope(Path::new(path), PathStyle::Unix);
+        assert_eq!(
+            scope("/home/me/project/src/main.rs"),
+            PathScope::WorkingTree
+        );
+        assert_eq!(scope("/home/me/project"), PathScope::WorkingTree);
+        assert_eq!(scope("/home/me/project-old"), PathScope::Home);
+        assert_eq!(scope("/tmp/build"), PathScope::Elsewhere);
+        assert_eq!(scope("/var/tmp/cache"), PathScope::Elsewhere);
+        assert_eq!(scope("/mnt/usb/photos"), PathScope::Removable);
+        assert_eq!(scope("/run/media/me/SDCARD"), PathScope::Removable);
+        assert_eq!(scope("/etc/hosts"), PathScope::System);
+        assert_eq!(scope("/usr/local/bin/tool"), PathScope::System);
+
+        let context = ScopeContext {
+            cwd: "/etc/nginx".into(),
+            home: Some("/var/lib/jenkins".into()),
+            removable: vec![],
+        };
+        let scope = |path: &str| context.scope(Path::new(path), PathStyle::Unix);
+        assert_eq!(scope("/etc/nginx/nginx.conf"), PathScope::System);
+        assert_eq!(scope("/var/lib/jenkins/workspace"), PathScope::Home);
+
+        let context = ScopeContext {
+            cwd: "C:\\Users\\me\\repo".into(),
+            home: Some("C:\\Users\\me".into()),
+            removable: vec!["E:\\".into()],
+        };
+        let scope = |path: &str| context.scope(Path::new(path), PathStyle::Windows);
+        assert_eq!(scope("c:\\users\\me\\repo\\a.txt"), PathScope::WorkingTree);
+        assert_eq!(scope("C:\\Users\\me\\Desktop"), PathScope::Home);
+        assert_eq!(scope("E:\\backup"), PathScope::Removable);
+        assert_eq!(scope("C:\\Windows\\System32\\drivers"), PathScope::System);
+    }
+}
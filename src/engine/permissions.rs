@@ -0,0 +1,348 @@
+use super::*;
+
+use std::path::{Path, PathBuf};
+
+/// What a chmod/chown/chgrp invocation changes.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PermissionKind {
+    /// An octal (`755`) or symbolic (`u+x,go-w`) mode.
+    Mode(String),
+    Owner {
+        user: Option<String>,
+        group: Option<String>,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct PermissionChange {
+    pub tool: String,
+    pub kind: PermissionKind,
+    pub files: Vec<String>,
+    pub recursive: bool,
+}
+
+impl PermissionChange {
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| *v == "sudo")
+            .collect();
+        let tool = *words.first()?;
+        if !matches!(tool, "chmod" | "chown" | "chgrp") {
+            return None;
+        }
+
+        let mut recursive = false;
+        let mut spec: Option<&str> = None;
+        let mut files = vec![];
+        for word in &words[1..] {
+            match *word {
+                "-R" | "--recursive" => recursive = true,
+                // `chmod -w file` is a mode, not an option
+                v if tool == "chmod" && spec.is_none() && is_symbolic_mode(v) => spec = Some(v),
+                v if v.starts_with('-') => {
+                    if v.starts_with("--reference") {
+                        return None;
+                    }
+                    if !v.starts_with("--") && v.contains('R') {
+                        recursive = true;
+                    }
+                }
+                v if spec.is_none() => spec = Some(v),
+                v => files.push(v.to_string()),
+            }
+        }
+
+        let spec = spec?;
+        let kind = match tool {
+            "chmod" => {
+                apply_mode(spec, 0, false)?;
+                PermissionKind::Mode(spec.to_string())
+            }
+            "chgrp" => PermissionKind::Owner {
+                user: None,
+                group: Some(spec.to_string()),
+            },
+            _ => {
+                let (user, group) = match spec.split_once([':', '.']) {
+                    Some((user, group)) => (user, group),
+                    None => (spec, ""),
+                };
+                let non_empty = |v: &str| (!v.is_empty()).then(|| v.to_string());
+                PermissionKind::Owner {
+                    user: non_empty(user),
+                    group: non_empty(group),
+                }
+            }
+        };
+        Some(Self {
+            tool: tool.to_string(),
+            kind,
+            files,
+            recursive,
+        })
+    }
+
+    /// The mode always leaves the file writable by everyone.
+    pub fn makes_world_writable(&self) -> bool {
+        match &self.kind {
+            PermissionKind::Mode(spec) => apply_mode(spec, 0, false)
+                .map(|v| v & 0o002 != 0)
+                .unwrap_or_default(),
+            PermissionKind::Owner { .. } => false,
+        }
+    }
+
+    /// The mode always leaves the owner without read access.
+    pub fn removes_own_access(&self) -> bool {
+        match &self.kind {
+            PermissionKind::Mode(spec) => apply_mode(spec, 0o777, false)
+                .map(|v| v & 0o400 == 0)
+                .unwrap_or_default(),
+            PermissionKind::Owner { .. } => false,
+        }
+    }
+
+    /// A recursive change rooted at a system directory such as `/` or `/etc`.
+    pub fn is_recursive_on_protected(&self, style: PathStyle) -> bool {
+        self.recursive && self.files.iter().any(|v| is_protected_path(v, style))
+    }
+
+    /// A recursive change of the whole home directory, `~/.ssh` included.
+    pub fn is_recursive_on_home(&self, style: PathStyle, fs: &dyn FileSystem) -> bool {
+        let Some(home) = fs.home_dir() else {
+            return false;
+        };
+        self.recursive
+            && self
+                .files
+                .iter()
+                .any(|v| resolve_command_path(v, style, fs) == home)
+    }
+
+    pub fn changes_owner(&self) -> bool {
+        matches!(self.kind, PermissionKind::Owner { .. })
+    }
+
+    /// chown/chgrp of files in system directories, which services and sudo only trust
+    /// when root owns them.
+    pub fn changes_system_ownership(&self, style: PathStyle, fs: &dyn FileSystem) -> bool {
+        if !self.changes_owner() {
+            return false;
+        }
+        let context = ScopeContext::new(fs);
+        self.files
+            .iter()
+            .any(|v| context.scope(&resolve_command_path(v, style, fs), style) == PathScope::System)
+    }
+
+    /// How many files a recursive change reaches.
+    pub fn estimate(&self, style: PathStyle, fs: &dyn FileSystem) -> Option<SizeEstimate> {
+        if !self.recursive {
+            return None;
+        }
+        let paths: Vec<PathBuf> = self
+            .files
+            .iter()
+            .map(|v| resolve_command_path(v, style, fs))
+            .collect();
+        Some(fs.estimate_size(&paths)).filter(|v| v.files > 0)
+    }
+
+    /// One line per file describing the change, with the current and resulting modes
+    /// for files that exist.
+    pub fn describe(&self) -> Vec<String> {
+        let suffix = if self.recursive { " (recursive)" } else { "" };
+        self.files
+            .iter()
+            .map(|file| {
+                let change = match &self.kind {
+                    PermissionKind::Mode(spec) => match file_mode(Path::new(file)) {
+                        Some((mode, is_dir)) => match apply_mode(spec, mode, is_dir) {
+                            Some(new_mode) => {
+                                format!("{} → {}", format_mode(mode), format_mode(new_mode))
+                            }
+                            None => format!("mode {spec}"),
+                        },
+                        None => match spec.chars().all(|c| c.is_ascii_digit()) {
+                            true => format!(
+                                "mode → {}",
+                                format_mode(apply_mode(spec, 0, false).unwrap_or_default())
+                            ),
+                            false => format!("mode {spec}"),
+                        },
+                    },
+                    PermissionKind::Owner { user, group } => {
+                        let mut parts = vec![];
+                        if let Some(user) = user {
+                            parts.push(format!("owner → {user}"));
+                        }
+                        if let Some(group) = group {
+                            parts.push(format!("group → {group}"));
+                        }
+                        parts.join(", ")
+                    }
+                };
+                format!("{file}: {change}{suffix}")
+            })
+            .collect()
+    }
+}
+
+fn is_symbolic_mode(value: &str) -> bool {
+    value.len() > 1 && value.starts_with('-') && value[1..].chars().all(|c| "rwxXst".contains(c))
+}
+
+/// Apply an octal or symbolic mode to `mode`, as chmod would (ignoring the umask).
+fn apply_mode(spec: &str, mode: u32, is_dir: bool) -> Option<u32> {
+    if !spec.is_empty() && spec.chars().all(|c| c.is_ascii_digit()) {
+        return u32::from_str_radix(spec, 8).ok().filter(|v| *v <= 0o7777);
+    }
+    let mut mode = mode;
+    for clause in spec.split(',') {
+        let op_index = clause.find(['+', '-', '='])?;
+        let who = &clause[..op_index];
+        if !who.chars().all(|c| "ugoa".contains(c)) {
+            return None;
+        }
+        let mask = match who.is_empty() || who.contains('a') {
+            true => 0o7777,
+            false => who.chars().fold(0, |acc, c| {
+                acc | match c {
+                    'u' => 0o4700,
+                    'g' => 0o2070,
+                    _ => 0o1007,
+                }
+            }),
+        };
+        let mut rest = &clause[op_index..];
+        while let Some(op) = rest.chars().next() {
+            let end = rest[1..]
+                .find(['+', '-', '='])
+                .map_or(rest.len(), |v| v + 1);
+            let perms = &rest[1..end];
+            rest = &rest[end..];
+            let mut bits = 0;
+            for c in perms.chars() {
+                bits |= match c {
+                    'r' => 0o444,
+                    'w' => 0o222,
+                    'x' => 0o111,
+                    'X' if is_dir || mode & 0o111 != 0 => 0o111,
+                    'X' => 0,
+                    's' => 0o6000,
+                    't' => 0o1000,
+                    'u' => copy_bits((mode >> 6) & 0o7),
+                    'g' => copy_bits((mode >> 3) & 0o7),
+                    'o' => copy_bits(mode & 0o7),
+                    _ => return None,
+                };
+            }
+            let bits = bits & mask;
+            mode = match op {
+                '+' => mode | bits,
+                '-' => mode & !bits,
+                _ => (mode & !(mask & 0o777)) | bits,
+            };
+        }
+    }
+    Some(mode)
+}
+
+/// Spread `rwx` bits to all three classes, for `g=u` style modes.
+fn copy_bits(bits: u32) -> u32 {
+    bits | (bits << 3) | (bits << 6)
+}
+
+fn format_mode(mode: u32) -> String {
+    let mut output = format!("{:04o} ", mode & 0o7777);
+    for shift in [6, 3, 0] {
+        let bits = (mode >> shift) & 0o7;
+        output.push(if bits & 4 != 0 { 'r' } else { '-' });
+        output.push(if bits & 2 != 0 { 'w' } else { '-' });
+        output.push(if bits & 1 != 0 { 'x' } else { '-' });
+    }
+    output
+}
+
+#[cfg(unix)]
+fn file_mode(path: &Path) -> Option<(u32, bool)> {
+    use std::os::unix::fs::PermissionsExt;
+    let metadata = path.metadata().ok()?;
+    Some((metadata.permissions().mode() & 0o7777, metadata.is_dir()))
+}
+
+#[cfg(not(unix))]
+fn file_mode(_path: &Path) -> Option<(u32, bool)> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_apply_mode() {
+        assert_eq!(apply_mode("755", 0, false), Some(0o755));
+        assert_eq!(apply_mode("u+x,go-w", 0o666, false), Some(0o744));
+        assert_eq!(apply_mode("a=r", 0o777, false), Some(0o444));
+        assert_eq!(apply_mode("+X", 0o644, true), Some(0o755));
+        assert_eq!(apply_mode("+X", 0o644, false), Some(0o644));
+        assert_eq!(apply_mode("g=u", 0o700, false), Some(0o770));
+        assert_eq!(apply_mode("u+s", 0o755, false), Some(0o4755));
+        assert_eq!(apply_mode("q+x", 0, false), None);
+        assert_eq!(format_mode(0o754), "0754 rwxr-xr--");
+    }
+
+    #[test]
+    fn test_parse_permission_change() {
+        let change = PermissionChange::parse("chmod -R 777 /srv/www").unwrap();
+        assert!(change.recursive);
+        assert!(change.makes_world_writable());
+        assert_eq!(change.files, vec!["/srv/www"]);
+
+        let change = PermissionChange::parse("chmod -w notes.txt").unwrap();
+        assert_eq!(change.kind, PermissionKind::Mode("-w".into()));
+        assert!(!change.removes_own_access());
+        assert!(PermissionChange::parse("chmod u-r secret")
+            .unwrap()
+            .removes_own_access());
+        assert!(PermissionChange::parse("chmod 044 secret")
+            .unwrap()
+            .removes_own_access());
+
+        let change = PermissionChange::parse("sudo chown -R alice:staff /").unwrap();
+        assert_eq!(
+            change.kind,
+            PermissionKind::Owner {
+                user: Some("alice".into()),
+                group: Some("staff".into())
+            }
+        );
+        assert!(change.is_recursive_on_protected(PathStyle::Unix));
+        assert_eq!(
+            change.describe(),
+            vec!["/: owner → alice, group → staff (recursive)"]
+        );
+        assert!(PermissionChange::parse("chmod --reference=a b").is_none());
+
+        let fs = MemoryFs::new()
+            .home("/home/me")
+            .file("/home/me/.ssh/id_ed25519", 400)
+            .file("/home/me/notes.txt", 100)
+            .file("/etc/sudoers", 100);
+        let change = PermissionChange::parse("chmod -R 700 ~").unwrap();
+        assert!(change.is_recursive_on_home(PathStyle::Unix, &fs));
+        assert_eq!(change.estimate(PathStyle::Unix, &fs).unwrap().files, 2);
+        assert!(!change.changes_system_ownership(PathStyle::Unix, &fs));
+        assert!(PermissionChange::parse("chown me /etc/sudoers")
+            .unwrap()
+            .changes_system_ownership(PathStyle::Unix, &fs));
+        assert!(!PermissionChange::parse("chown me notes.txt")
+            .unwrap()
+            .changes_system_ownership(PathStyle::Unix, &fs));
+    }
+}
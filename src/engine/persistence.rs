@@ -0,0 +1,336 @@
+use super::*;
+
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+
+const SYSTEMD_UNIT_DIRS: [&str; 4] = [
+    "/etc/systemd/system",
+    "/run/systemd/system",
+    "/lib/systemd/system",
+    "/usr/lib/systemd/system",
+];
+
+const SYSTEMD_UNIT_SUFFIXES: [&str; 6] = [
+    ".service", ".timer", ".socket", ".path", ".mount", ".target",
+];
+
+/// A change that keeps affecting the machine after the command exits.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum PersistentChange {
+    /// Replaces or removes the crontab of the user (`None` for the current user).
+    Crontab { user: Option<String>, removes: bool },
+    /// Installs, edits or (de)activates a systemd unit.
+    SystemdUnit { unit: String, path: Option<PathBuf> },
+    /// Queues a one-off job with `at`/`batch`, or removes queued ones with `atrm`.
+    AtJob { removes: bool },
+    /// Creates, changes or deletes a Windows scheduled task.
+    ScheduledTask {
+        name: Option<String>,
+        action: ScheduledTaskAction,
+    },
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ScheduledTaskAction {
+    Create,
+    Change,
+    Delete,
+}
+
+impl PersistentChange {
+    /// Cron, at and the Task Scheduler run jobs on their own, later.
+    pub fn is_scheduled(&self) -> bool {
+        !matches!(self, PersistentChange::SystemdUnit { .. })
+    }
+
+    pub fn warning(&self) -> String {
+        match self {
+            PersistentChange::Crontab {
+                user,
+                removes: true,
+            } => format!(
+                "⚠️  Scheduled jobs: crontab -r removes every cron job of {} at once, without asking.",
+                user.as_deref().unwrap_or("the current user")
+            ),
+            PersistentChange::Crontab { user, .. } => format!(
+                "⚠️  Persistent change: edits the crontab of {}, scheduled jobs keep running after this session.",
+                user.as_deref().unwrap_or("the current user")
+            ),
+            PersistentChange::SystemdUnit { unit, .. } => format!(
+                "⚠️  Persistent change: modifies systemd unit {unit}, it keeps running in the background and across reboots."
+            ),
+            PersistentChange::AtJob { removes: true } => {
+                "⚠️  Scheduled jobs: removes queued at jobs, they won't run.".to_string()
+            }
+            PersistentChange::AtJob { removes: false } => {
+                "⚠️  Scheduled jobs: queues an at job, it runs later on its own, after this session. `atq` lists it, `atrm` removes it.".to_string()
+            }
+            PersistentChange::ScheduledTask { name, action } => {
+                let name = name
+                    .as_deref()
+                    .map(|v| format!(" {v}"))
+                    .unwrap_or_default();
+                match action {
+                    ScheduledTaskAction::Delete => format!(
+                        "⚠️  Scheduled jobs: deletes the scheduled task{name}, export it first with `schtasks /query /xml /tn <name>`."
+                    ),
+                    _ => format!(
+                        "⚠️  Scheduled jobs: the scheduled task{name} runs on its own, across reboots and logins."
+                    ),
+                }
+            }
+        }
+    }
+
+    /// `crontab -r` is a notorious typo of `crontab -e`, the keys are neighbours.
+    pub fn typo_fix(&self, segment: &str) -> Option<String> {
+        if !matches!(self, PersistentChange::Crontab { removes: true, .. }) {
+            return None;
+        }
+        let words: Vec<&str> = segment.split_whitespace().collect();
+        if !words.contains(&"crontab") {
+            return None;
+        }
+        // Only the flag is replaced, the rest of the segment stays as written
+        let flag = words
+            .iter()
+            .find(|v| v.starts_with('-') && !v.starts_with("--") && v.contains('r'))?;
+        let fixed = flag.replace('r', "e").replace('i', "");
+        Some(words.join(" ").replacen(flag, &fixed, 1))
+    }
+}
+
+/// Detect crontab, at and scheduled task changes and systemd unit changes in a command.
+pub fn detect_persistent_changes(command: &str) -> Vec<PersistentChange> {
+    let shell = ShellKind::from_name(&SHELL.name);
+    let mut changes = vec![];
+    for segment in split_command_segments(command, &shell) {
+        let words = shell_words::split(&segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| *v == "sudo")
+            .collect();
+        let Some(cmd) = words.first() else {
+            continue;
+        };
+        let change = match cmd.to_lowercase().as_str() {
+            "crontab" => parse_crontab(&words[1..]),
+            "systemctl" => parse_systemctl(&words[1..]),
+            "at" | "batch" => parse_at(&words[1..]),
+            "atrm" => Some(PersistentChange::AtJob { removes: true }),
+            "schtasks" | "schtasks.exe" => parse_schtasks(&words[1..]),
+            "register-scheduledtask" | "set-scheduledtask" | "unregister-scheduledtask" => {
+                parse_scheduled_task_cmdlet(&cmd.to_lowercase(), &words[1..])
+            }
+            _ => parse_unit_install(&words),
+        };
+        if let Some(change) = change.filter(|v| !changes.contains(v)) {
+            changes.push(change);
+        }
+    }
+    changes
+}
+
+fn parse_crontab(args: &[&str]) -> Option<PersistentChange> {
+    let mut user = None;
+    let mut read_only = false;
+    let mut removes = false;
+    let mut iter = args.iter();
+    while let Some(arg) = iter.next() {
+        match *arg {
+            "-u" => user = iter.next().map(|v| v.to_string()),
+            "-l" => read_only = true,
+            // `-r`, `-ri` and `-ir`
+            v if v.starts_with('-') && !v.starts_with("--") && v.contains('r') => removes = true,
+            _ => {}
+        }
+    }
+    match read_only {
+        true => None,
+        false => Some(PersistentChange::Crontab { user, removes }),
+    }
+}
+
+fn parse_at(args: &[&str]) -> Option<PersistentChange> {
+    match args.first().copied() {
+        // List the queue, show a job
+        Some("-l" | "-c") => None,
+        Some("-r" | "-d") => Some(PersistentChange::AtJob { removes: true }),
+        _ => Some(PersistentChange::AtJob { removes: false }),
+    }
+}
+
+/// `schtasks /create /tn Backup ...`, `/change` and `/delete`, `/query` and `/run` change
+/// nothing.
+fn parse_schtasks(args: &[&str]) -> Option<PersistentChange> {
+    let option = |v: &str| v.trim_start_matches(['/', '-']).to_lowercase();
+    let action = args.iter().find_map(|v| match option(v).as_str() {
+        "create" => Some(ScheduledTaskAction::Create),
+        "change" => Some(ScheduledTaskAction::Change),
+        "delete" => Some(ScheduledTaskAction::Delete),
+        _ => None,
+    })?;
+    let name = args
+        .iter()
+        .position(|v| option(v) == "tn")
+        .and_then(|i| args.get(i + 1))
+        .map(|v| v.to_string());
+    Some(PersistentChange::ScheduledTask { name, action })
+}
+
+fn parse_scheduled_task_cmdlet(cmdlet: &str, args: &[&str]) -> Option<PersistentChange> {
+    let action = match cmdlet {
+        "register-scheduledtask" => ScheduledTaskAction::Create,
+        "set-scheduledtask" => ScheduledTaskAction::Change,
+        _ => ScheduledTaskAction::Delete,
+    };
+    let name = args
+        .iter()
+        .position(|v| v.eq_ignore_ascii_case("-TaskName"))
+        .map(|i| i + 1)
+        .or_else(|| (!args.first()?.starts_with('-')).then_some(0))
+        .and_then(|i| args.get(i))
+        .map(|v| v.to_string());
+    Some(PersistentChange::ScheduledTask { name, action })
+}
+
+fn parse_systemctl(args: &[&str]) -> Option<PersistentChange> {
+    let mut positional = args.iter().filter(|v| !v.starts_with('-'));
+    let verb = positional.next()?;
+    if !matches!(
+        *verb,
+        "enable" | "disable" | "mask" | "unmask" | "edit" | "link" | "revert" | "set-property"
+    ) {
+        return None;
+    }
+    let unit = positional.next()?.to_string();
+    let path = if Path::new(&unit).is_absolute() {
+        Some(PathBuf::from(&unit))
+    } else {
+        find_unit_file(&unit)
+    };
+    Some(PersistentChange::SystemdUnit { unit, path })
+}
+
+/// `cp app.service /etc/systemd/system/`, `tee /etc/systemd/system/app.service`, ...
+fn parse_unit_install(words: &[&str]) -> Option<PersistentChange> {
+    let is_unit = |v: &str| SYSTEMD_UNIT_SUFFIXES.iter().any(|s| v.ends_with(s));
+    let in_unit_dir = |v: &str| {
+        let v = v.trim_start_matches('>');
+        SYSTEMD_UNIT_DIRS.iter().any(|d| v.starts_with(d)) || v.contains("/.config/systemd/")
+    };
+    let target = words.iter().skip(1).find(|v| in_unit_dir(v))?;
+    let target = target.trim_start_matches('>');
+    let path = if is_unit(target) {
+        PathBuf::from(target)
+    } else {
+        let source = words.iter().skip(1).find(|v| is_unit(v))?;
+        Path::new(target).join(Path::new(source).file_name()?)
+    };
+    let unit = path.file_name()?.to_string_lossy().to_string();
+    Some(PersistentChange::SystemdUnit {
+        unit,
+        path: Some(path),
+    })
+}
+
+fn find_unit_file(unit: &str) -> Option<PathBuf> {
+    let mut dirs: Vec<PathBuf> = vec![];
+    if let Some(config_dir) = dirs::config_dir() {
+        dirs.push(config_dir.join("systemd").join("user"));
+    }
+    dirs.extend(SYSTEMD_UNIT_DIRS.iter().map(PathBuf::from));
+    let unit = if unit.contains('.') {
+        unit.to_string()
+    } else {
+        format!("{unit}.service")
+    };
+    dirs.into_iter()
+        .map(|dir| dir.join(&unit))
+        .find(|path| path.is_file())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_crontab() {
+        assert_eq!(
+            detect_persistent_changes("crontab -e"),
+            vec![PersistentChange::Crontab {
+                user: None,
+                removes: false
+            }]
+        );
+        assert_eq!(
+            detect_persistent_changes("sudo crontab -u www jobs.txt"),
+            vec![PersistentChange::Crontab {
+                user: Some("www".into()),
+                removes: false
+            }]
+        );
+        assert!(detect_persistent_changes("crontab -l | grep backup").is_empty());
+
+        let changes = detect_persistent_changes("crontab -u www -r");
+        assert!(changes[0]
+            .warning()
+            .contains("removes every cron job of www"));
+        assert_eq!(
+            changes[0].typo_fix("crontab -u www -r").unwrap(),
+            "crontab -u www -e"
+        );
+        assert_eq!(
+            detect_persistent_changes("crontab -ri")[0].typo_fix("crontab -ri"),
+            Some("crontab -e".into())
+        );
+    }
+
+    #[test]
+    fn test_detect_scheduled_jobs() {
+        assert_eq!(
+            detect_persistent_changes("echo 'reboot' | at 03:00"),
+            vec![PersistentChange::AtJob { removes: false }]
+        );
+        assert_eq!(
+            detect_persistent_changes("atrm 4"),
+            vec![PersistentChange::AtJob { removes: true }]
+        );
+        assert!(detect_persistent_changes("at -l").is_empty());
+        assert_eq!(
+            detect_persistent_changes("schtasks /Delete /TN Backup /F"),
+            vec![PersistentChange::ScheduledTask {
+                name: Some("Backup".into()),
+                action: ScheduledTaskAction::Delete
+            }]
+        );
+        assert!(detect_persistent_changes("schtasks /query /fo LIST").is_empty());
+        assert!(matches!(
+            detect_persistent_changes("Unregister-ScheduledTask -TaskName Sync -Confirm:$false").as_slice(),
+            [PersistentChange::ScheduledTask { name: Some(name), action: ScheduledTaskAction::Delete }] if name == "Sync"
+        ));
+        assert!(detect_persistent_changes("crontab -e")[0].is_scheduled());
+    }
+
+    #[test]
+    fn test_detect_systemd_units() {
+        assert_eq!(
+            detect_persistent_changes(
+                "sudo cp app.service /etc/systemd/system/ && sudo systemctl daemon-reload"
+            ),
+            vec![PersistentChange::SystemdUnit {
+                unit: "app.service".into(),
+                path: Some(PathBuf::from("/etc/systemd/system/app.service")),
+            }]
+        );
+        assert!(matches!(
+            detect_persistent_changes("systemctl --user enable --now sync.timer").as_slice(),
+            [PersistentChange::SystemdUnit { unit, .. }] if unit == "sync.timer"
+        ));
+        assert!(detect_persistent_changes("systemctl status nginx").is_empty());
+    }
+}
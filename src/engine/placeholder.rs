@@ -0,0 +1,349 @@
+use fancy_regex::Regex;
+use serde::Serialize;
+use std::sync::LazyLock;
+
+static ANGLE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?<![<\w])<([A-Za-z][A-Za-z0-9_-]*(?: [A-Za-z0-9_-]+)*)>").unwrap()
+});
+static BRACES_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\s*([A-Za-z][\w-]*)\s*\}\}").unwrap());
+static VARIABLE_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\$(?:\{([A-Z][A-Z0-9_]+)\}|([A-Z][A-Z0-9_]+)\b)").unwrap());
+static WORD_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?<![\w$-])((?:YOUR|MY)_[A-Z0-9_]+|(?:your|my)-[a-z0-9-]+)(?![\w-])").unwrap()
+});
+/// `NAME=`, `export NAME=`, `for NAME in`, `read NAME`: variables the command sets itself.
+static ASSIGNED_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?:^|[\s;&|(])(?:(?:(?:export|local|declare|readonly)\s+)?([A-Z][A-Z0-9_]+)=|(?:for|read(?:\s+-\w+)*)\s+([A-Z][A-Z0-9_]+)\b)",
+    )
+    .unwrap()
+});
+
+/// Variables every shell sets, whether or not they are exported.
+const SHELL_VARIABLES: [&str; 16] = [
+    "HOME", "USER", "PATH", "PWD", "OLDPWD", "SHELL", "HOSTNAME", "UID", "EUID", "PPID", "RANDOM",
+    "SECONDS", "LINENO", "IFS", "TERM", "LANG",
+];
+
+/// How a placeholder is written.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaceholderSyntax {
+    /// `<your-bucket>`
+    Angle,
+    /// `{{bucket}}`
+    Braces,
+    /// `$PROJECT_ID` or `${PROJECT_ID}`, not set in the environment or by the command.
+    Variable,
+    /// `YOUR_API_KEY`, `your-bucket`
+    Word,
+}
+
+/// What a placeholder stands for, judged by its name, and so what a value must look like.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PlaceholderKind {
+    Bucket,
+    Project,
+    Region,
+    Host,
+    Ip,
+    Port,
+    Email,
+    Path,
+    Number,
+    Name,
+    Text,
+}
+
+impl PlaceholderKind {
+    fn of(name: &str) -> Self {
+        let name = name.to_lowercase();
+        let has = |words: &[&str]| words.iter().any(|v| name.contains(v));
+        if has(&["bucket"]) {
+            Self::Bucket
+        } else if has(&["project"]) {
+            Self::Project
+        } else if has(&["region", "zone"]) {
+            Self::Region
+        } else if has(&["email", "mail"]) {
+            Self::Email
+        } else if has(&["port"]) {
+            Self::Port
+        } else if name == "ip" || has(&["ip_", "ip-", "_ip", "-ip", "address"]) {
+            Self::Ip
+        } else if has(&["host", "domain", "server", "url"]) {
+            Self::Host
+        } else if has(&["path", "dir", "file", "folder"]) {
+            Self::Path
+        } else if has(&["count", "number", "size", "num"]) {
+            Self::Number
+        } else if has(&["name", "user", "id", "branch", "tag"]) {
+            Self::Name
+        } else {
+            Self::Text
+        }
+    }
+
+    /// The pattern a value has to match.
+    pub fn pattern(&self) -> &'static str {
+        match self {
+            Self::Bucket => r"^[a-z0-9][a-z0-9._-]{1,61}[a-z0-9]$",
+            Self::Project => r"^[a-z][a-z0-9-]{4,28}[a-z0-9]$",
+            Self::Region => r"^[a-z]{2,}(-[a-z0-9]+)+$",
+            Self::Host => r"^[A-Za-z0-9]([A-Za-z0-9:/._-]*[A-Za-z0-9/])?$",
+            Self::Ip => r"^(\d{1,3}(\.\d{1,3}){3}|[0-9a-fA-F:]+:[0-9a-fA-F:]*)$",
+            Self::Port => r"^\d{1,5}$",
+            Self::Email => r"^[^@\s]+@[^@\s]+\.[^@\s]+$",
+            Self::Path => r"^[^\x00\n]+$",
+            Self::Number => r"^\d+$",
+            Self::Name => r"^[\w.@:/-]+$",
+            Self::Text => r"^[^\n]+$",
+        }
+    }
+
+    /// What a valid value looks like, for prompts.
+    pub fn hint(&self) -> &'static str {
+        match self {
+            Self::Bucket => "lowercase letters, digits, dots and dashes, 3 to 63 characters",
+            Self::Project => "a project id: lowercase letters, digits and dashes, 6 to 30",
+            Self::Region => "a region or zone, e.g. us-east-1 or europe-west1-b",
+            Self::Host => "a host name or URL",
+            Self::Ip => "an IPv4 or IPv6 address",
+            Self::Port => "a port from 1 to 65535",
+            Self::Email => "an email address",
+            Self::Path => "a path",
+            Self::Number => "a whole number",
+            Self::Name => "a name without spaces",
+            Self::Text => "any text on one line",
+        }
+    }
+}
+
+/// A value the model left for the user to fill in, see [`detect_placeholders`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Placeholder {
+    /// As written, e.g. `<your-bucket>` or `$PROJECT_ID`.
+    pub text: String,
+    /// Without its syntax, e.g. `your-bucket` or `PROJECT_ID`.
+    pub name: String,
+    pub syntax: PlaceholderSyntax,
+    pub kind: PlaceholderKind,
+}
+
+impl Placeholder {
+    fn new(text: &str, name: &str, syntax: PlaceholderSyntax) -> Self {
+        Self {
+            text: text.to_string(),
+            name: name.to_string(),
+            syntax,
+            kind: PlaceholderKind::of(name),
+        }
+    }
+
+    /// Why `value` can't fill the placeholder, `None` when it can.
+    pub fn validate(&self, value: &str) -> Option<String> {
+        let value = value.trim();
+        if value.is_empty() {
+            return Some(format!("{} needs a value", self.text));
+        }
+        let nested = detect_placeholders(value, &|_| true);
+        if nested.iter().any(|v| {
+            matches!(
+                v.syntax,
+                PlaceholderSyntax::Angle | PlaceholderSyntax::Braces
+            )
+        }) {
+            return Some("That is a placeholder too".to_string());
+        }
+        let valid = Regex::new(self.kind.pattern())
+            .ok()
+            .and_then(|v| v.is_match(value).ok())
+            .unwrap_or_default();
+        if !valid
+            || (self.kind == PlaceholderKind::Port
+                && !matches!(value.parse::<u32>(), Ok(1..=65535)))
+        {
+            return Some(format!("Expected {}", self.kind.hint()));
+        }
+        None
+    }
+}
+
+/// The placeholders of a command: `<your-bucket>`, `{{bucket}}`, `YOUR_API_KEY`, and
+/// upper-case variables like `$PROJECT_ID` that neither `is_set` (the environment) nor the
+/// command itself defines. Running the command as is would pass them on literally or empty.
+pub fn detect_placeholders(command: &str, is_set: &dyn Fn(&str) -> bool) -> Vec<Placeholder> {
+    let mut output: Vec<Placeholder> = vec![];
+    let mut push = |placeholder: Placeholder| {
+        if !output.iter().any(|v| v.name == placeholder.name) {
+            output.push(placeholder);
+        }
+    };
+    let literal = single_quoted_ranges(command);
+    for caps in ANGLE_RE.captures_iter(command).flatten() {
+        push(Placeholder::new(
+            &caps[0],
+            &caps[1],
+            PlaceholderSyntax::Angle,
+        ));
+    }
+    for caps in BRACES_RE.captures_iter(command).flatten() {
+        push(Placeholder::new(
+            &caps[0],
+            &caps[1],
+            PlaceholderSyntax::Braces,
+        ));
+    }
+    for caps in WORD_RE.captures_iter(command).flatten() {
+        push(Placeholder::new(
+            &caps[1],
+            &caps[1],
+            PlaceholderSyntax::Word,
+        ));
+    }
+    let assigned: Vec<String> = ASSIGNED_RE
+        .captures_iter(command)
+        .flatten()
+        .filter_map(|v| v.get(1).or(v.get(2)).map(|v| v.as_str().to_string()))
+        .collect();
+    for caps in VARIABLE_RE.captures_iter(command).flatten() {
+        let start = caps.get(0).map(|v| v.start()).unwrap_or_default();
+        let name = caps
+            .get(1)
+            .or(caps.get(2))
+            .map(|v| v.as_str())
+            .unwrap_or_default();
+        if literal.iter().any(|v| v.contains(&start))
+            || SHELL_VARIABLES.contains(&name)
+            || assigned.iter().any(|v| v == name)
+            || is_set(name)
+        {
+            continue;
+        }
+        push(Placeholder::new(
+            &format!("${name}"),
+            name,
+            PlaceholderSyntax::Variable,
+        ));
+    }
+    output
+}
+
+/// `command` with each placeholder replaced by its value, shell-quoted when needed.
+pub fn fill_placeholders(command: &str, values: &[(Placeholder, String)]) -> String {
+    let mut output = command.to_string();
+    for (placeholder, value) in values {
+        let value = shell_words::quote(value.trim()).to_string();
+        output = match placeholder.syntax {
+            PlaceholderSyntax::Variable => {
+                let name = fancy_regex::escape(&placeholder.name);
+                let re = Regex::new(&format!(r"\$(?:\{{{name}\}}|{name}\b)")).unwrap();
+                re.replace_all(&output, value.as_str()).to_string()
+            }
+            PlaceholderSyntax::Braces => {
+                let name = fancy_regex::escape(&placeholder.name);
+                let re = Regex::new(&format!(r"\{{\{{\s*{name}\s*\}}\}}")).unwrap();
+                re.replace_all(&output, value.as_str()).to_string()
+            }
+            _ => output.replace(&placeholder.text, &value),
+        };
+    }
+    output
+}
+
+/// Byte ranges inside single quotes, where `$` is literal.
+fn single_quoted_ranges(command: &str) -> Vec<std::ops::Range<usize>> {
+    let mut ranges = vec![];
+    let mut start = None;
+    let mut double = false;
+    for (i, c) in command.char_indices() {
+        match (c, start) {
+            ('"', None) => double = !double,
+            ('\'', None) if !double => start = Some(i),
+            ('\'', Some(begin)) => {
+                ranges.push(begin..i);
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    ranges
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_placeholders() {
+        let is_set = |name: &str| name == "AWS_PROFILE";
+        let placeholders = detect_placeholders(
+            "gsutil rm -r gs://<your-bucket>/logs && gcloud config set project $PROJECT_ID --region ${REGION} --account YOUR_EMAIL",
+            &is_set,
+        );
+        let texts: Vec<_> = placeholders.iter().map(|v| v.text.as_str()).collect();
+        assert_eq!(
+            texts,
+            ["<your-bucket>", "YOUR_EMAIL", "$PROJECT_ID", "$REGION"]
+        );
+        let kinds: Vec<_> = placeholders.iter().map(|v| v.kind).collect();
+        assert_eq!(
+            kinds,
+            [
+                PlaceholderKind::Bucket,
+                PlaceholderKind::Email,
+                PlaceholderKind::Project,
+                PlaceholderKind::Region
+            ]
+        );
+
+        let none = [
+            "echo $HOME $AWS_PROFILE",
+            "for F in *.log; do gzip $F; done",
+            "export TAG=v1 && docker push app:$TAG",
+            "echo '$NOT_EXPANDED'",
+            "sort <in.txt >out.txt",
+            "cat <<EOF",
+            "diff <(ls a) <(ls b)",
+        ];
+        for command in none {
+            assert!(
+                detect_placeholders(command, &is_set).is_empty(),
+                "{command}"
+            );
+        }
+    }
+
+    #[test]
+    fn test_fill_placeholders() {
+        let command =
+            "aws s3 rm s3://<bucket-name>/tmp --recursive --profile ${PROFILE} # $PROFILE";
+        let placeholders = detect_placeholders(command, &|_| false);
+        assert_eq!(placeholders.len(), 2);
+        let bucket = &placeholders[0];
+        assert_eq!(
+            bucket.validate(""),
+            Some("<bucket-name> needs a value".into())
+        );
+        assert!(bucket
+            .validate("My Bucket")
+            .unwrap()
+            .starts_with("Expected"));
+        assert!(bucket.validate("<bucket>").is_some());
+        assert_eq!(bucket.validate("team-logs"), None);
+        let values = vec![
+            (placeholders[0].clone(), "team-logs".to_string()),
+            (placeholders[1].clone(), "prod admin".to_string()),
+        ];
+        assert_eq!(
+            fill_placeholders(command, &values),
+            "aws s3 rm s3://team-logs/tmp --recursive --profile 'prod admin' # 'prod admin'"
+        );
+
+        let port = Placeholder::new("<port>", "port", PlaceholderSyntax::Angle);
+        assert!(port.validate("70000").is_some());
+        assert_eq!(port.validate("8080"), None);
+    }
+}
@@ -0,0 +1,343 @@
+use super::*;
+
+use anyhow::{bail, Context, Result};
+use fancy_regex::Regex;
+use parking_lot::RwLock;
+use serde::Deserialize;
+use std::{
+    fs,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+/// Rules shipped with aichat, see `assets/policy.yaml`.
+const BUILTIN_POLICY: &str = include_str!("../../assets/policy.yaml");
+
+static POLICY: LazyLock<RwLock<Arc<SafetyPolicy>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(SafetyPolicy::builtin())));
+
+/// The policy the analyzer applies, the built-in rules until [`set_safety_policy`] runs.
+pub fn safety_policy() -> Arc<SafetyPolicy> {
+    POLICY.read().clone()
+}
+
+pub fn set_safety_policy(policy: SafetyPolicy) {
+    *POLICY.write() = Arc::new(policy);
+}
+
+/// Rules mapping commands to a safety level, a warning or a block, for environments where
+/// the analyzer's heuristics are too strict or too lax.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct SafetyPolicy {
+    #[serde(default)]
+    pub rules: Vec<PolicyRule>,
+}
+
+/// A rule matches when every matcher it sets matches: `commands`, `flags` and `paths` on
+/// the same simple command of a pipeline or chain, `regex` on the whole command line.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct PolicyRule {
+    pub name: String,
+    /// Command names, `*` wildcards allowed, e.g. `mkfs.*`.
+    #[serde(default)]
+    pub commands: Vec<String>,
+    /// Flags that must all be present, short ones also inside groups like `-rf`.
+    #[serde(default)]
+    pub flags: Vec<String>,
+    /// Any argument is, or is inside, one of these paths. `*` wildcards allowed.
+    #[serde(default)]
+    pub paths: Vec<String>,
+    pub regex: Option<String>,
+    /// Replaces the analyzer's level for a single command, and can only raise it in
+    /// pipelines and chains.
+    pub level: Option<SafetyLevel>,
+    pub warning: Option<String>,
+    /// Refuse to run the command at all.
+    #[serde(default)]
+    pub block: bool,
+    /// Run the command without asking, unless it is Critical or scores `risk_confirm_score`.
+    #[serde(default)]
+    pub allow: bool,
+    /// Switch off a built-in rule of the same name.
+    #[serde(default)]
+    pub disabled: bool,
+    #[serde(skip)]
+    compiled: Option<Regex>,
+}
+
+impl SafetyPolicy {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_POLICY).expect("Invalid built-in policy")
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self> {
+        let mut policy: Self = serde_yaml::from_str(yaml)?;
+        for rule in policy.rules.iter_mut() {
+            if rule.commands.is_empty()
+                && rule.flags.is_empty()
+                && rule.paths.is_empty()
+                && rule.regex.is_none()
+                && !rule.disabled
+            {
+                bail!(
+                    "Rule '{}' matches nothing, set commands, flags, paths or regex",
+                    rule.name
+                );
+            }
+            if let Some(regex) = &rule.regex {
+                rule.compiled = Some(
+                    Regex::new(regex)
+                        .with_context(|| format!("Invalid regex in rule '{}'", rule.name))?,
+                );
+            }
+        }
+        Ok(policy)
+    }
+
+    /// The built-in rules merged with the rules of the file at `path`, if it exists.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut policy = Self::builtin();
+        if !path.exists() {
+            return Ok(policy);
+        }
+        let content = fs::read_to_string(path)
+            .with_context(|| format!("Failed to load policy at {}", path.display()))?;
+        let custom = Self::parse(&content)
+            .with_context(|| format!("Failed to load policy at {}", path.display()))?;
+        for rule in custom.rules {
+            policy.rules.retain(|v| v.name != rule.name);
+            policy.rules.push(rule);
+        }
+        policy.rules.retain(|v| !v.disabled);
+        Ok(policy)
+    }
+
+    /// Append a rule allowing exactly `command` to the policy file at `path`, keeping the
+    /// rest of the file as written. Returns the name of the rule.
+    pub fn append_allow_rule(path: &Path, command: &str) -> Result<String> {
+        let command = command.trim();
+        let content = match path.exists() {
+            true => fs::read_to_string(path)
+                .with_context(|| format!("Failed to load policy at {}", path.display()))?,
+            false => String::new(),
+        };
+        let existing = Self::parse(&content)
+            .with_context(|| format!("Failed to load policy at {}", path.display()))?;
+        let slug: String = command
+            .chars()
+            .map(|c| match c.is_ascii_alphanumeric() {
+                true => c.to_ascii_lowercase(),
+                false => '-',
+            })
+            .collect();
+        let slug = slug
+            .split('-')
+            .filter(|v| !v.is_empty())
+            .collect::<Vec<_>>();
+        let base = format!("allow-{}", slug.join("-"));
+        let mut name = base.clone();
+        let mut index = 2;
+        while existing.rules.iter().any(|v| v.name == name) {
+            name = format!("{base}-{index}");
+            index += 1;
+        }
+
+        // Items are indented like the existing ones, or the list is started
+        let indent = content
+            .lines()
+            .find(|v| v.trim_start().starts_with("- "))
+            .map(|v| &v[..v.len() - v.trim_start().len()])
+            .unwrap_or("  ");
+        let mut updated = content.clone();
+        if !updated.is_empty() && !updated.ends_with('\n') {
+            updated.push('\n');
+        }
+        if existing.rules.is_empty() && !content.lines().any(|v| v.starts_with("rules:")) {
+            updated.push_str("rules:\n");
+        }
+        let quote = |v: &str| serde_json::to_string(v).unwrap_or_default();
+        let regex = format!(r"^\s*{}\s*$", fancy_regex::escape(command));
+        updated.push_str(&format!(
+            "{indent}- name: {}\n{indent}  regex: {}\n{indent}  allow: true\n",
+            quote(&name),
+            quote(&regex),
+        ));
+        let parsed = Self::parse(&updated)
+            .ok()
+            .filter(|v| v.rules.len() == existing.rules.len() + 1);
+        if parsed.is_none() {
+            bail!(
+                "Failed to add rule '{name}' to {}, add it by hand",
+                path.display()
+            );
+        }
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        fs::write(path, updated)
+            .with_context(|| format!("Failed to write policy at {}", path.display()))?;
+        Ok(name)
+    }
+
+    /// The enabled rules matching `command`, its relative paths resolved in `fs`.
+    pub fn matching(
+        &self,
+        command: &str,
+        style: PathStyle,
+        shell: &ShellKind,
+        fs: &dyn FileSystem,
+    ) -> Vec<&PolicyRule> {
+        let segments = split_command_segments(command, shell);
+        self.rules
+            .iter()
+            .filter(|rule| !rule.disabled && rule.matches(command, &segments, style, fs))
+            .collect()
+    }
+}
+
+impl PolicyRule {
+    fn matches(
+        &self,
+        command: &str,
+        segments: &[String],
+        style: PathStyle,
+        fs: &dyn FileSystem,
+    ) -> bool {
+        if let Some(regex) = &self.compiled {
+            if !regex.is_match(command).unwrap_or_default() {
+                return false;
+            }
+        }
+        if self.commands.is_empty() && self.flags.is_empty() && self.paths.is_empty() {
+            return true;
+        }
+        segments
+            .iter()
+            .any(|segment| self.matches_segment(segment, style, fs))
+    }
+
+    fn matches_segment(&self, segment: &str, style: PathStyle, fs: &dyn FileSystem) -> bool {
+        let words = split_command_words(segment, style);
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        let Some((name, args)) = words.split_first() else {
+            return false;
+        };
+        let name = name.rsplit(['/', '\\']).next().unwrap_or(name);
+        if !self.commands.is_empty() && !self.commands.iter().any(|v| glob_matches(v, name, style))
+        {
+            return false;
+        }
+        let has_flag = |flag: &str| {
+            args.iter().any(|v| {
+                *v == flag
+                    || v.starts_with(&format!("{flag}="))
+                    || (flag.len() == 2
+                        && flag.starts_with('-')
+                        && v.starts_with('-')
+                        && !v.starts_with("--")
+                        && v[1..].contains(&flag[1..]))
+            })
+        };
+        if !self.flags.iter().all(|v| has_flag(v)) {
+            return false;
+        }
+        if self.paths.is_empty() {
+            return true;
+        }
+        args.iter()
+            .filter(|v| !v.starts_with('-'))
+            .map(|v| resolve_command_path(v, style, fs))
+            .any(|path| {
+                self.paths.iter().any(|pattern| {
+                    let pattern = expand_tilde(pattern, fs);
+                    path.ancestors()
+                        .any(|v| glob_matches(&pattern, &v.to_string_lossy(), style))
+                })
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn matching(policy: &SafetyPolicy, command: &str) -> Vec<String> {
+        policy
+            .matching(command, PathStyle::Unix, &ShellKind::Bash, &MemoryFs::new())
+            .into_iter()
+            .map(|v| v.name.clone())
+            .collect()
+    }
+
+    #[test]
+    fn test_builtin_policy() {
+        let policy = SafetyPolicy::builtin();
+        assert_eq!(matching(&policy, "shred -u secrets.txt"), ["shred"]);
+        assert_eq!(matching(&policy, "crontab -ri"), ["crontab-remove"]);
+    }
+
+    #[test]
+    fn test_custom_rules() {
+        let policy = SafetyPolicy::parse(
+            r#"
+rules:
+  - name: prod-data
+    paths: ['/srv/prod']
+    level: critical
+    block: true
+  - name: force-delete
+    commands: [rm]
+    flags: [-r, -f]
+    warning: Recursive force delete.
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            matching(&policy, "cat /srv/prod/app/db.sqlite"),
+            ["prod-data"]
+        );
+        assert!(matching(&policy, "cat /srv/production").is_empty());
+        assert_eq!(matching(&policy, "rm -rf build"), ["force-delete"]);
+        assert!(matching(&policy, "rm --recursive -f build").is_empty());
+        assert!(matching(&policy, "rm -r build").is_empty());
+
+        assert!(SafetyPolicy::parse("rules: [{ name: empty }]").is_err());
+        assert!(SafetyPolicy::parse("rules: [{ name: bad, regex: '(' }]").is_err());
+    }
+
+    #[test]
+    fn test_append_allow_rule() {
+        let path = std::env::temp_dir().join(format!("aichat-policy-{}.yaml", std::process::id()));
+        assert_eq!(
+            SafetyPolicy::append_allow_rule(&path, "docker compose down ").unwrap(),
+            "allow-docker-compose-down"
+        );
+        assert_eq!(
+            SafetyPolicy::append_allow_rule(&path, "docker compose down").unwrap(),
+            "allow-docker-compose-down-2"
+        );
+        let policy = SafetyPolicy::parse(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert!(policy.rules.iter().all(|v| v.allow));
+        assert_eq!(
+            matching(&policy, "docker compose down"),
+            ["allow-docker-compose-down", "allow-docker-compose-down-2"]
+        );
+        assert!(matching(&policy, "docker compose down -v").is_empty());
+
+        fs::write(&path, "# mine\nrules:\n- name: keep\n  commands: [make]\n").unwrap();
+        SafetyPolicy::append_allow_rule(&path, "make clean").unwrap();
+        let content = fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# mine\n"));
+        let policy = SafetyPolicy::parse(&content).unwrap();
+        assert_eq!(
+            matching(&policy, "make clean"),
+            ["keep", "allow-make-clean"]
+        );
+        fs::remove_file(&path).unwrap();
+    }
+}
@@ -0,0 +1,242 @@
+use std::collections::HashMap;
+use std::process::Command;
+
+/// A TCP port in the listening state or a bound UDP port.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ListeningPort {
+    pub protocol: String,
+    pub address: String,
+    pub port: u16,
+    /// Unknown when the socket belongs to another user and we aren't root.
+    pub pid: Option<u32>,
+}
+
+/// The listening ports of the machine, sorted by port, from `/proc/net` on Linux, `lsof` on
+/// macOS and `netstat` on Windows.
+pub fn listening_ports() -> Vec<ListeningPort> {
+    let mut ports = match cfg!(target_os = "linux") {
+        true => proc_net_ports(),
+        false if cfg!(windows) => command_output("netstat", &["-ano"])
+            .map(|v| parse_netstat(&v))
+            .unwrap_or_default(),
+        false => command_output("lsof", &["-nP", "-iTCP", "-sTCP:LISTEN", "-iUDP"])
+            .map(|v| parse_lsof(&v))
+            .unwrap_or_default(),
+    };
+    ports.sort_by_key(|v| (v.port, v.protocol.clone(), v.address.clone()));
+    ports.dedup();
+    ports
+}
+
+fn proc_net_ports() -> Vec<ListeningPort> {
+    let owners = socket_owners();
+    ["tcp", "tcp6", "udp", "udp6"]
+        .iter()
+        .filter_map(|protocol| {
+            let content = std::fs::read_to_string(format!("/proc/net/{protocol}")).ok()?;
+            Some(parse_proc_net(&content, protocol, &owners))
+        })
+        .flatten()
+        .collect()
+}
+
+/// Socket inodes mapped to the pid holding them, from the `/proc/<pid>/fd` links we may read.
+fn socket_owners() -> HashMap<u64, u32> {
+    let mut owners = HashMap::new();
+    let Ok(entries) = std::fs::read_dir("/proc") else {
+        return owners;
+    };
+    for entry in entries.flatten() {
+        let Some(pid) = entry
+            .file_name()
+            .to_str()
+            .and_then(|v| v.parse::<u32>().ok())
+        else {
+            continue;
+        };
+        let Ok(fds) = std::fs::read_dir(entry.path().join("fd")) else {
+            continue;
+        };
+        for fd in fds.flatten() {
+            let Ok(link) = std::fs::read_link(fd.path()) else {
+                continue;
+            };
+            let inode = link
+                .to_str()
+                .and_then(|v| v.strip_prefix("socket:["))
+                .and_then(|v| v.strip_suffix(']'))
+                .and_then(|v| v.parse::<u64>().ok());
+            if let Some(inode) = inode {
+                owners.entry(inode).or_insert(pid);
+            }
+        }
+    }
+    owners
+}
+
+/// Parse `/proc/net/{tcp,udp}[6]`, keeping listening TCP sockets (state `0A`) and unconnected
+/// UDP ones (state `07`).
+fn parse_proc_net(content: &str, protocol: &str, owners: &HashMap<u64, u32>) -> Vec<ListeningPort> {
+    let state = match protocol.starts_with("tcp") {
+        true => "0A",
+        false => "07",
+    };
+    content
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            if fields.get(3) != Some(&state) {
+                return None;
+            }
+            let (address, port) = fields.get(1)?.split_once(':')?;
+            let port = u16::from_str_radix(port, 16).ok()?;
+            let inode = fields.get(9)?.parse::<u64>().ok()?;
+            Some(ListeningPort {
+                protocol: protocol.trim_end_matches('6').to_string(),
+                address: decode_proc_address(address)?,
+                port,
+                pid: owners.get(&inode).copied(),
+            })
+        })
+        .collect()
+}
+
+/// `/proc/net` addresses are hex words printed in host byte order, four of them for IPv6.
+fn decode_proc_address(hex: &str) -> Option<String> {
+    let words = (0..hex.len() / 8)
+        .map(|i| u32::from_str_radix(&hex[i * 8..i * 8 + 8], 16))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?;
+    match words.as_slice() {
+        [v] => Some(std::net::Ipv4Addr::from(v.to_ne_bytes()).to_string()),
+        [a, b, c, d] => {
+            let mut octets = [0u8; 16];
+            for (i, v) in [a, b, c, d].iter().enumerate() {
+                octets[i * 4..i * 4 + 4].copy_from_slice(&v.to_ne_bytes());
+            }
+            Some(std::net::Ipv6Addr::from(octets).to_string())
+        }
+        _ => None,
+    }
+}
+
+/// Parse `lsof -nP -iTCP -sTCP:LISTEN -iUDP`.
+fn parse_lsof(output: &str) -> Vec<ListeningPort> {
+    output
+        .lines()
+        .skip(1)
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let pid = fields.get(1)?.parse::<u32>().ok()?;
+            let protocol = fields.get(7)?.to_lowercase();
+            let name = fields.get(8)?;
+            if name.contains("->") {
+                return None;
+            }
+            let (address, port) = name.rsplit_once(':')?;
+            Some(ListeningPort {
+                protocol,
+                address: address.trim_matches(['[', ']']).to_string(),
+                port: port.parse().ok()?,
+                pid: Some(pid),
+            })
+        })
+        .collect()
+}
+
+/// Parse `netstat -ano` on Windows, UDP rows have no state column.
+fn parse_netstat(output: &str) -> Vec<ListeningPort> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let fields: Vec<&str> = line.split_whitespace().collect();
+            let protocol = fields.first()?.to_lowercase();
+            let pid = match (protocol.as_str(), fields.len()) {
+                ("tcp", 5) if fields[3] == "LISTENING" => fields[4],
+                ("udp", 4) => fields[3],
+                _ => return None,
+            };
+            let (address, port) = fields[1].rsplit_once(':')?;
+            Some(ListeningPort {
+                protocol,
+                address: address.trim_matches(['[', ']']).to_string(),
+                port: port.parse().ok()?,
+                pid: pid.parse().ok(),
+            })
+        })
+        .collect()
+}
+
+fn command_output(program: &str, args: &[&str]) -> Option<String> {
+    let output = Command::new(program).args(args).output().ok()?;
+    Some(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn port(protocol: &str, address: &str, port: u16, pid: Option<u32>) -> ListeningPort {
+        ListeningPort {
+            protocol: protocol.into(),
+            address: address.into(),
+            port,
+            pid,
+        }
+    }
+
+    #[test]
+    fn test_parse_proc_net() {
+        let content = "  sl  local_address rem_address   st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000:1F90 00000000:0000 0A 00000000:00000000 00:00000000 00000000  1000        0 41234 1 0000000000000000 100 0 0 10 0
+   1: 0100007F:0CEA 00000000:0000 0A 00000000:00000000 00:00000000 00000000   999        0 51234 1 0000000000000000 100 0 0 10 0
+   2: 0100007F:A2C4 0100007F:1F90 01 00000000:00000000 00:00000000 00000000  1000        0 61234 1 0000000000000000 20 4 30 10 -1";
+        let owners = HashMap::from([(41234, 4242)]);
+        assert_eq!(
+            parse_proc_net(content, "tcp", &owners),
+            [
+                port("tcp", "0.0.0.0", 8080, Some(4242)),
+                port("tcp", "127.0.0.1", 3306, None)
+            ]
+        );
+        let content = "  sl  local_address                         remote_address                        st tx_queue rx_queue tr tm->when retrnsmt   uid  timeout inode
+   0: 00000000000000000000000001000000:0016 00000000000000000000000000000000:0000 0A 00000000:00000000 00:00000000 00000000     0        0 23456 1 0000000000000000 100 0 0 10 0";
+        assert_eq!(
+            parse_proc_net(content, "tcp6", &HashMap::new()),
+            [port("tcp", "::1", 22, None)]
+        );
+    }
+
+    #[test]
+    fn test_parse_lsof_and_netstat() {
+        let output = "COMMAND   PID USER   FD   TYPE             DEVICE SIZE/OFF NODE NAME
+node    51234 bob    23u  IPv6 0x1234567890abcdef      0t0  TCP *:8080 (LISTEN)
+postgres  812 bob     7u  IPv4 0x1234567890abcdee      0t0  TCP 127.0.0.1:5432 (LISTEN)
+mDNSRespo 391 root    9u  IPv4 0x1234567890abcded      0t0  UDP *:5353";
+        assert_eq!(
+            parse_lsof(output),
+            [
+                port("tcp", "*", 8080, Some(51234)),
+                port("tcp", "127.0.0.1", 5432, Some(812)),
+                port("udp", "*", 5353, Some(391))
+            ]
+        );
+        let output = "
+Active Connections
+
+  Proto  Local Address          Foreign Address        State           PID
+  TCP    0.0.0.0:135            0.0.0.0:0              LISTENING       1020
+  TCP    127.0.0.1:49712        127.0.0.1:49713        ESTABLISHED     4312
+  TCP    [::]:445               [::]:0                 LISTENING       4
+  UDP    0.0.0.0:5353           *:*                                    2188";
+        assert_eq!(
+            parse_netstat(output),
+            [
+                port("tcp", "0.0.0.0", 135, Some(1020)),
+                port("tcp", "::", 445, Some(4)),
+                port("udp", "0.0.0.0", 5353, Some(2188))
+            ]
+        );
+    }
+}
@@ -0,0 +1,444 @@
+use fancy_regex::Regex;
+use sysinfo::{
+    ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users, MINIMUM_CPU_UPDATE_INTERVAL,
+};
+
+/// Pattern commands matching more processes than this get a warning.
+const MANY_PROCESSES: usize = 5;
+
+/// Processes whose death takes down the session or the whole machine.
+const CRITICAL_PROCESSES: [&str; 17] = [
+    "init",
+    "systemd",
+    "launchd",
+    "kernel_task",
+    "sshd",
+    "dbus-daemon",
+    "systemd-journald",
+    "systemd-logind",
+    "Xorg",
+    "WindowServer",
+    "loginwindow",
+    "csrss.exe",
+    "wininit.exe",
+    "winlogon.exe",
+    "lsass.exe",
+    "services.exe",
+    "smss.exe",
+];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ProcessSelector {
+    Pid(u32),
+    /// `kill -1` / `kill 0`: every process the user can signal, or the whole process group.
+    All,
+    /// A `killall`/`taskkill /IM` process name, `*` wildcards allowed.
+    Name(String),
+    /// A `pkill`/`killall -r` regex, matched against the full command line with `full`.
+    Pattern {
+        pattern: String,
+        full: bool,
+    },
+}
+
+/// A process from the live process table.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessInfo {
+    pub pid: u32,
+    pub name: String,
+    pub user: Option<String>,
+    pub cmdline: String,
+}
+
+/// A process with its CPU and resident memory use.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ProcessUsage {
+    pub process: ProcessInfo,
+    /// Percent of one core, above 100 for multithreaded processes.
+    pub cpu: f32,
+    /// Bytes.
+    pub memory: u64,
+}
+
+/// A kill/pkill/killall/taskkill invocation.
+#[derive(Debug, Clone, PartialEq)]
+pub struct KillCommand {
+    pub tool: String,
+    pub signal: Option<String>,
+    pub selectors: Vec<ProcessSelector>,
+    /// `-u`/`/FI "USERNAME eq ..."`: only processes of this user.
+    pub user: Option<String>,
+    /// The processes that would be signaled, see [`KillCommand::resolve`].
+    pub processes: Vec<ProcessInfo>,
+}
+
+impl KillCommand {
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| *v == "sudo")
+            .collect();
+        let tool = *words.first()?;
+        let mut signal = None;
+        let mut user = None;
+        let mut selectors = vec![];
+        let mut full = false;
+        let mut regex = false;
+        let mut iter = words[1..].iter();
+        match tool {
+            "kill" => {
+                while let Some(word) = iter.next() {
+                    match *word {
+                        "-l" | "-L" | "--list" => return None,
+                        "-s" | "-n" | "--signal" => signal = iter.next().map(|v| v.to_string()),
+                        "--" => {}
+                        "-1" | "0" if signal.is_some() || *word == "0" => {
+                            selectors.push(ProcessSelector::All)
+                        }
+                        v if v.starts_with('-') && signal.is_none() && selectors.is_empty() => {
+                            signal = Some(v[1..].to_string())
+                        }
+                        // A negative pid signals the process group
+                        // Job specs like `%1` belong to the shell and are skipped
+                        v => {
+                            if let Ok(pid) = v.trim_start_matches('-').parse() {
+                                selectors.push(ProcessSelector::Pid(pid));
+                            }
+                        }
+                    }
+                }
+                // `kill -1` alone
+                if selectors.is_empty() && signal.as_deref() == Some("1") {
+                    signal = None;
+                    selectors.push(ProcessSelector::All);
+                }
+            }
+            "pkill" | "killall" => {
+                let mut names = vec![];
+                while let Some(word) = iter.next() {
+                    match *word {
+                        "-u" | "--user" => user = iter.next().map(|v| v.to_string()),
+                        "-s" | "--signal" => signal = iter.next().map(|v| v.to_string()),
+                        "-f" | "--full" => full = true,
+                        "-r" | "--regexp" => regex = true,
+                        "-x" | "--exact" | "-e" | "-n" | "-o" | "-i" | "-v" | "-q" | "-w" => {}
+                        "-g" | "-G" | "-P" | "-t" | "-U" | "-y" => {
+                            iter.next();
+                        }
+                        v if v.starts_with("--signal=") => {
+                            signal = Some(v["--signal=".len()..].to_string())
+                        }
+                        v if v.starts_with('-') => signal = Some(v[1..].to_string()),
+                        v => names.push(v.to_string()),
+                    }
+                }
+                selectors.extend(names.into_iter().map(|v| match (tool, regex) {
+                    ("pkill", _) | (_, true) => ProcessSelector::Pattern { pattern: v, full },
+                    _ => ProcessSelector::Name(v),
+                }));
+            }
+            "taskkill" => {
+                signal = Some("TERM".into());
+                while let Some(word) = iter.next() {
+                    match word.to_uppercase().as_str() {
+                        "/PID" => selectors.extend(
+                            iter.next()
+                                .and_then(|v| v.parse().ok())
+                                .map(ProcessSelector::Pid),
+                        ),
+                        "/IM" => selectors
+                            .extend(iter.next().map(|v| ProcessSelector::Name(v.to_string()))),
+                        "/F" => signal = Some("KILL".into()),
+                        "/FI" => {
+                            let filter = iter.next().map(|v| v.to_string()).unwrap_or_default();
+                            if let Some(name) = filter
+                                .split_once(" eq ")
+                                .filter(|(key, _)| key.eq_ignore_ascii_case("USERNAME"))
+                                .map(|(_, v)| v.trim().to_string())
+                            {
+                                user = Some(name);
+                            }
+                        }
+                        _ => {}
+                    }
+                }
+            }
+            _ => return None,
+        }
+        if selectors.is_empty() {
+            return None;
+        }
+        Some(Self {
+            tool: tool.to_string(),
+            signal,
+            selectors,
+            user,
+            processes: vec![],
+        })
+    }
+
+    /// Fill [`KillCommand::processes`] with the processes the command would signal.
+    pub fn resolve(&mut self, processes: &[ProcessInfo]) {
+        self.processes = processes
+            .iter()
+            .filter(|p| match &self.user {
+                Some(user) => p.user.as_deref() == Some(user.as_str()),
+                None => true,
+            })
+            .filter(|p| self.selectors.iter().any(|s| selector_matches(s, p)))
+            .cloned()
+            .collect();
+    }
+
+    pub fn targets_all(&self) -> bool {
+        self.selectors.contains(&ProcessSelector::All)
+    }
+
+    /// Matched processes that take the system or the login session with them.
+    pub fn critical_processes(&self) -> Vec<&ProcessInfo> {
+        self.processes
+            .iter()
+            .filter(|p| {
+                p.pid == 1
+                    || p.pid == std::process::id()
+                    || CRITICAL_PROCESSES
+                        .iter()
+                        .any(|v| v.eq_ignore_ascii_case(&p.name))
+            })
+            .collect()
+    }
+
+    /// A name or pattern matched far more processes than the one or two usually meant.
+    pub fn matches_many(&self) -> bool {
+        let by_pattern = self
+            .selectors
+            .iter()
+            .any(|v| !matches!(v, ProcessSelector::Pid(_)));
+        by_pattern && self.processes.len() > MANY_PROCESSES
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut warnings = vec![];
+        if self.targets_all() {
+            warnings.push(format!(
+                "⚠️  Processes: {} signals every process you can reach, including your shell!",
+                self.tool
+            ));
+        }
+        let critical = self.critical_processes();
+        if !critical.is_empty() {
+            let names: Vec<_> = critical
+                .iter()
+                .map(|p| format!("{} ({})", p.name, p.pid))
+                .collect();
+            warnings.push(format!(
+                "⚠️  Processes: signals critical system processes: {}",
+                names.join(", ")
+            ));
+        }
+        if self.matches_many() {
+            warnings.push(format!(
+                "⚠️  Processes: the pattern matches {} processes, more than expected.",
+                self.processes.len()
+            ));
+        }
+        if !self.targets_all() && self.processes.is_empty() {
+            warnings.push(format!(
+                "💡 {} matches no running process right now.",
+                self.tool
+            ));
+        }
+        warnings
+    }
+}
+
+fn selector_matches(selector: &ProcessSelector, process: &ProcessInfo) -> bool {
+    match selector {
+        ProcessSelector::Pid(pid) => process.pid == *pid,
+        ProcessSelector::All => true,
+        ProcessSelector::Name(name) => wildcard_matches(name, &process.name),
+        ProcessSelector::Pattern { pattern, full } => {
+            let haystack = if *full {
+                &process.cmdline
+            } else {
+                &process.name
+            };
+            match Regex::new(pattern) {
+                Ok(re) => re.is_match(haystack).unwrap_or_default(),
+                Err(_) => haystack.contains(pattern.as_str()),
+            }
+        }
+    }
+}
+
+/// Case-insensitive match with `*` wildcards, as taskkill does for image names.
+fn wildcard_matches(pattern: &str, name: &str) -> bool {
+    let pattern = pattern.to_lowercase();
+    let name = name.to_lowercase();
+    let parts: Vec<&str> = pattern.split('*').collect();
+    if parts.len() == 1 {
+        return pattern == name;
+    }
+    let mut rest = name.as_str();
+    for (i, part) in parts.iter().enumerate() {
+        if i == 0 {
+            match rest.strip_prefix(part) {
+                Some(v) => rest = v,
+                None => return false,
+            }
+        } else if i == parts.len() - 1 {
+            return rest.ends_with(part);
+        } else {
+            match rest.find(part) {
+                Some(index) => rest = &rest[index + part.len()..],
+                None => return false,
+            }
+        }
+    }
+    true
+}
+
+/// Snapshot of the running processes with their user and command line.
+pub fn live_processes() -> Vec<ProcessInfo> {
+    let mut sys = System::new();
+    sys.refresh_processes_specifics(
+        ProcessesToUpdate::All,
+        true,
+        ProcessRefreshKind::nothing()
+            .with_user(UpdateKind::Always)
+            .with_cmd(UpdateKind::Always),
+    );
+    let users = Users::new_with_refreshed_list();
+    let mut processes: Vec<ProcessInfo> = sys
+        .processes()
+        .iter()
+        .filter(|(_, p)| p.thread_kind().is_none())
+        .map(|(pid, p)| {
+            let cmdline = p
+                .cmd()
+                .iter()
+                .map(|v| v.to_string_lossy())
+                .collect::<Vec<_>>()
+                .join(" ");
+            ProcessInfo {
+                pid: pid.as_u32(),
+                name: p.name().to_string_lossy().to_string(),
+                user: p
+                    .user_id()
+                    .and_then(|uid| users.get_user_by_id(uid))
+                    .map(|v| v.name().to_string()),
+                cmdline,
+            }
+        })
+        .collect();
+    processes.sort_by_key(|v| v.pid);
+    processes
+}
+
+/// The running processes, busiest first. Takes [`MINIMUM_CPU_UPDATE_INTERVAL`] to measure
+/// the CPU use.
+pub fn process_usage() -> Vec<ProcessUsage> {
+    let mut sys = System::new();
+    let refresh_kind = ProcessRefreshKind::nothing()
+        .with_cpu()
+        .with_memory()
+        .with_user(UpdateKind::OnlyIfNotSet)
+        .with_cmd(UpdateKind::OnlyIfNotSet);
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh_kind);
+    std::thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+    sys.refresh_processes_specifics(ProcessesToUpdate::All, true, refresh_kind);
+    let users = Users::new_with_refreshed_list();
+    let mut processes: Vec<ProcessUsage> = sys
+        .processes()
+        .iter()
+        .filter(|(_, p)| p.thread_kind().is_none())
+        .map(|(pid, p)| ProcessUsage {
+            process: ProcessInfo {
+                pid: pid.as_u32(),
+                name: p.name().to_string_lossy().to_string(),
+                user: p
+                    .user_id()
+                    .and_then(|uid| users.get_user_by_id(uid))
+                    .map(|v| v.name().to_string()),
+                cmdline: p
+                    .cmd()
+                    .iter()
+                    .map(|v| v.to_string_lossy())
+                    .collect::<Vec<_>>()
+                    .join(" "),
+            },
+            cpu: p.cpu_usage(),
+            memory: p.memory(),
+        })
+        .collect();
+    processes.sort_by(|a, b| b.cpu.total_cmp(&a.cpu).then(b.memory.cmp(&a.memory)));
+    processes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn process(pid: u32, name: &str, user: &str, cmdline: &str) -> ProcessInfo {
+        ProcessInfo {
+            pid,
+            name: name.into(),
+            user: Some(user.into()),
+            cmdline: cmdline.into(),
+        }
+    }
+
+    fn table() -> Vec<ProcessInfo> {
+        let mut processes = vec![
+            process(1, "systemd", "root", "/sbin/init"),
+            process(812, "sshd", "root", "sshd: /usr/sbin/sshd -D"),
+            process(4021, "node", "alice", "node server.js --port 3000"),
+            process(4022, "node", "bob", "node worker.js"),
+        ];
+        processes.extend((0..6).map(|i| process(5000 + i, "python3", "alice", "python3 job.py")));
+        processes
+    }
+
+    #[test]
+    fn test_parse_kill() {
+        let cmd = KillCommand::parse("kill -9 4021 4022").unwrap();
+        assert_eq!(cmd.signal.as_deref(), Some("9"));
+        assert_eq!(
+            cmd.selectors,
+            vec![ProcessSelector::Pid(4021), ProcessSelector::Pid(4022)]
+        );
+        assert!(KillCommand::parse("kill -s TERM -1").unwrap().targets_all());
+        assert!(KillCommand::parse("kill -1").unwrap().targets_all());
+        assert!(!KillCommand::parse("kill -1 812").unwrap().targets_all());
+        assert!(KillCommand::parse("kill -l").is_none());
+        assert!(KillCommand::parse("ls").is_none());
+    }
+
+    #[test]
+    fn test_resolve_kill_targets() {
+        let mut cmd = KillCommand::parse("pkill -f 'server.js'").unwrap();
+        cmd.resolve(&table());
+        assert_eq!(cmd.processes.len(), 1);
+        assert_eq!(cmd.processes[0].pid, 4021);
+
+        let mut cmd = KillCommand::parse("killall -u alice node").unwrap();
+        cmd.resolve(&table());
+        assert_eq!(cmd.processes.len(), 1);
+
+        let mut cmd = KillCommand::parse("sudo killall sshd").unwrap();
+        cmd.resolve(&table());
+        assert_eq!(cmd.critical_processes().len(), 1);
+
+        let mut cmd = KillCommand::parse("pkill python").unwrap();
+        cmd.resolve(&table());
+        assert!(cmd.matches_many());
+
+        let mut cmd = KillCommand::parse("taskkill /F /IM pyth*").unwrap();
+        cmd.resolve(&table());
+        assert_eq!(cmd.signal.as_deref(), Some("KILL"));
+        assert_eq!(cmd.processes.len(), 6);
+    }
+}
@@ -0,0 +1,403 @@
+use super::*;
+
+use fancy_regex::Regex;
+use std::sync::LazyLock;
+
+const NUMBER: &str = r"\d+(?:[.,]\d+)*|a couple of|an?|one|two|three|four|five|six|seven|eight|nine|ten|eleven|twelve|fifteen|twenty|thirty";
+
+/// Languages writing `1,5` for one and a half.
+const DECIMAL_COMMA_LANGUAGES: [&str; 19] = [
+    "de", "fr", "es", "it", "pt", "nl", "ru", "pl", "tr", "sv", "da", "fi", "nb", "no", "cs", "uk",
+    "el", "id", "vi",
+];
+
+static AGE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(older than|newer than|younger than|more than|less than|over|under|within|in the (?:last|past)|last|past)\s+(?:({NUMBER})\s*)?(minute|min|hour|hr|day|week|fortnight|month|year)s?\b"
+    ))
+    .unwrap()
+});
+static SIZE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(&format!(
+        r"(?i)\b(larger than|bigger than|greater than|more than|over|above|at least|smaller than|less than|under|below|at most)\s+({NUMBER})\s*(bytes?|[kmgt]i?b|kilobytes?|megabytes?|gigabytes?|terabytes?)\b"
+    ))
+    .unwrap()
+});
+
+/// Which side of the threshold the request selects.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bound {
+    /// Older or larger.
+    Above,
+    /// Newer or smaller.
+    Below,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QuantityValue {
+    Age { minutes: u64 },
+    Size { bytes: u64 },
+}
+
+/// A relative date or size in a natural language request, e.g. "older than two weeks",
+/// computed locally so the model doesn't have to do the arithmetic.
+#[derive(Debug, Clone, PartialEq)]
+pub struct RequestQuantity {
+    /// The phrase as the user wrote it.
+    pub text: String,
+    pub bound: Bound,
+    pub value: QuantityValue,
+}
+
+impl RequestQuantity {
+    /// The exact value and the `find` predicate that selects it.
+    pub fn hint(&self) -> String {
+        match (self.value, self.bound) {
+            (QuantityValue::Age { minutes }, bound) => {
+                let sign = if bound == Bound::Above { '+' } else { '-' };
+                let mut hint = format!("\"{}\" = {minutes} minutes", self.text);
+                if minutes % 1440 == 0 {
+                    let days = minutes / 1440;
+                    hint = format!("\"{}\" = {days} days = {minutes} minutes", self.text);
+                    let mtime = if bound == Bound::Above {
+                        days - 1
+                    } else {
+                        days
+                    };
+                    hint.push_str(&format!(
+                        ". With find use `-mmin {sign}{minutes}` or `-mtime {sign}{mtime}` (-mtime counts whole days, +N means at least N+1 days)"
+                    ));
+                } else {
+                    hint.push_str(&format!(". With find use `-mmin {sign}{minutes}`"));
+                }
+                hint
+            }
+            (QuantityValue::Size { bytes }, bound) => {
+                let sign = if bound == Bound::Above { '+' } else { '-' };
+                format!(
+                    "\"{}\" = {bytes} bytes (1 KB = 1024 bytes). With find use `-size {sign}{bytes}c` (without the c suffix -size counts 512-byte blocks, and -size -1M only matches empty files)",
+                    self.text
+                )
+            }
+        }
+    }
+}
+
+/// Relative dates ("older than two weeks", "in the last 3 days") and sizes ("larger than
+/// 1,5 GB") in a request. `decimal_comma` reads `1,5` as 1.5 and `1.500` as 1500, see
+/// [`locale_uses_decimal_comma`]. Months are 30 days and years 365.
+pub fn parse_request_quantities(request: &str, decimal_comma: bool) -> Vec<RequestQuantity> {
+    let mut quantities = vec![];
+    for caps in AGE_RE.captures_iter(request).flatten() {
+        let number = match caps.get(2) {
+            Some(v) => parse_number(v.as_str(), decimal_comma),
+            None => Some(1.0),
+        };
+        let unit = match caps[3].to_lowercase().as_str() {
+            "minute" | "min" => 1,
+            "hour" | "hr" => 60,
+            "day" => 1440,
+            "week" => 7 * 1440,
+            "fortnight" => 14 * 1440,
+            "month" => 30 * 1440,
+            _ => 365 * 1440,
+        };
+        let Some(number) = number else {
+            continue;
+        };
+        let bound = match caps[1].to_lowercase().as_str() {
+            "older than" | "more than" | "over" => Bound::Above,
+            _ => Bound::Below,
+        };
+        quantities.push(RequestQuantity {
+            text: caps[0].to_string(),
+            bound,
+            value: QuantityValue::Age {
+                minutes: (number * unit as f64).round() as u64,
+            },
+        });
+    }
+    for caps in SIZE_RE.captures_iter(request).flatten() {
+        let Some(number) = parse_number(&caps[2], decimal_comma) else {
+            continue;
+        };
+        let unit: u64 = match caps[3].to_lowercase().chars().next() {
+            Some('k') => 1 << 10,
+            Some('m') => 1 << 20,
+            Some('g') => 1 << 30,
+            Some('t') => 1 << 40,
+            _ => 1,
+        };
+        let bound = match caps[1].to_lowercase().as_str() {
+            "smaller than" | "less than" | "under" | "below" | "at most" => Bound::Below,
+            _ => Bound::Above,
+        };
+        quantities.push(RequestQuantity {
+            text: caps[0].to_string(),
+            bound,
+            value: QuantityValue::Size {
+                bytes: (number * unit as f64).round() as u64,
+            },
+        });
+    }
+    quantities
+}
+
+/// Whether numbers in `locale` (e.g. `de-DE`) use a decimal comma.
+pub fn locale_uses_decimal_comma(locale: &str) -> bool {
+    let language = locale
+        .split(['-', '_'])
+        .next()
+        .unwrap_or_default()
+        .to_lowercase();
+    DECIMAL_COMMA_LANGUAGES.contains(&language.as_str())
+}
+
+/// Text appended to the request so the model uses the computed values.
+pub fn quantities_prompt(quantities: &[RequestQuantity]) -> Option<String> {
+    if quantities.is_empty() {
+        return None;
+    }
+    let lines: Vec<String> = quantities
+        .iter()
+        .map(|v| format!("- {}", v.hint()))
+        .collect();
+    Some(format!(
+        "Exact values computed from the request, use them as is:\n{}",
+        lines.join("\n")
+    ))
+}
+
+/// Warnings for `find` time and size predicates in `command` that select something other
+/// than the request asked for, e.g. `-mtime -14` for "older than two weeks" or `-size +100`
+/// (512-byte blocks) for "larger than 100 MB". Off by less than a tenth, or a day for
+/// `-mtime`, passes.
+pub fn check_command_quantities(command: &str, quantities: &[RequestQuantity]) -> Vec<String> {
+    let words = split_command_words(command, PathStyle::Unix);
+    let mut warnings = vec![];
+    for pair in words.windows(2) {
+        let (predicate, arg) = (pair[0].as_str(), pair[1].as_str());
+        let Some((bound, value, tolerance)) = parse_predicate(predicate, arg) else {
+            continue;
+        };
+        let candidates: Vec<&RequestQuantity> = quantities
+            .iter()
+            .filter(|v| std::mem::discriminant(&v.value) == std::mem::discriminant(&value))
+            .collect();
+        let matches = candidates.iter().any(|v| {
+            let (expected, actual) = match (v.value, value) {
+                (QuantityValue::Age { minutes: a }, QuantityValue::Age { minutes: b }) => (a, b),
+                (QuantityValue::Size { bytes: a }, QuantityValue::Size { bytes: b }) => (a, b),
+                _ => return false,
+            };
+            // A day of slack for -mtime only when the request is in days
+            let tolerance = if expected >= tolerance { tolerance } else { 0 };
+            v.bound == bound && expected.abs_diff(actual) <= tolerance.max(expected / 10)
+        });
+        let Some(expected) = candidates
+            .iter()
+            .find(|v| v.bound == bound)
+            .or(candidates.first())
+        else {
+            continue;
+        };
+        if !matches {
+            warnings.push(format!(
+                "⚠️  `{predicate} {arg}` selects {}, but \"{}\" means {}",
+                describe_selection(bound, value),
+                expected.text,
+                describe_selection(expected.bound, expected.value),
+            ));
+        }
+    }
+    warnings
+}
+
+/// The bound, threshold and rounding tolerance of a `find` predicate.
+fn parse_predicate(predicate: &str, arg: &str) -> Option<(Bound, QuantityValue, u64)> {
+    let (bound, number) = match arg.strip_prefix('+') {
+        Some(v) => (Bound::Above, v),
+        None => (Bound::Below, arg.strip_prefix('-')?),
+    };
+    match predicate {
+        "-mtime" | "-ctime" | "-atime" => {
+            let days: u64 = number.parse().ok()?;
+            // -mtime +N: more than N whole days, i.e. at least N+1
+            let days = if bound == Bound::Above {
+                days + 1
+            } else {
+                days
+            };
+            Some((
+                bound,
+                QuantityValue::Age {
+                    minutes: days * 1440,
+                },
+                1440,
+            ))
+        }
+        "-mmin" | "-cmin" | "-amin" => {
+            let minutes = number.parse().ok()?;
+            Some((bound, QuantityValue::Age { minutes }, 1))
+        }
+        "-size" => {
+            let split = number
+                .find(|c: char| !c.is_ascii_digit())
+                .unwrap_or(number.len());
+            let count: u64 = number[..split].parse().ok()?;
+            let unit: u64 = match &number[split..] {
+                "" | "b" => 512,
+                "c" => 1,
+                "w" => 2,
+                "k" => 1 << 10,
+                "M" => 1 << 20,
+                "G" => 1 << 30,
+                _ => return None,
+            };
+            // Sizes are rounded up to the unit, so -size -N matches up to N-1 units
+            let bytes = match bound {
+                Bound::Above => count * unit,
+                Bound::Below => count.saturating_sub(1) * unit + 1,
+            };
+            Some((bound, QuantityValue::Size { bytes }, 1))
+        }
+        _ => None,
+    }
+}
+
+fn describe_selection(bound: Bound, value: QuantityValue) -> String {
+    match (bound, value) {
+        (Bound::Above, QuantityValue::Age { minutes }) => {
+            format!("files older than {}", describe_minutes(minutes))
+        }
+        (Bound::Below, QuantityValue::Age { minutes }) => {
+            format!("files newer than {}", describe_minutes(minutes))
+        }
+        (Bound::Above, QuantityValue::Size { bytes }) => {
+            format!("files larger than {}", format_bytes(bytes))
+        }
+        (Bound::Below, QuantityValue::Size { bytes: 0 | 1 }) => "only empty files".to_string(),
+        (Bound::Below, QuantityValue::Size { bytes }) => {
+            format!("files smaller than {}", format_bytes(bytes))
+        }
+    }
+}
+
+fn describe_minutes(minutes: u64) -> String {
+    match minutes {
+        1440 => "1 day".to_string(),
+        60 => "1 hour".to_string(),
+        v if v % 1440 == 0 => format!("{} days", v / 1440),
+        v if v % 60 == 0 => format!("{} hours", v / 60),
+        _ => format!("{minutes} minutes"),
+    }
+}
+
+fn parse_number(text: &str, decimal_comma: bool) -> Option<f64> {
+    let value = match text.to_lowercase().as_str() {
+        "a" | "an" | "one" => 1,
+        "two" | "a couple of" => 2,
+        "three" => 3,
+        "four" => 4,
+        "five" => 5,
+        "six" => 6,
+        "seven" => 7,
+        "eight" => 8,
+        "nine" => 9,
+        "ten" => 10,
+        "eleven" => 11,
+        "twelve" => 12,
+        "fifteen" => 15,
+        "twenty" => 20,
+        "thirty" => 30,
+        _ => {
+            let (group, decimal) = if decimal_comma {
+                ('.', ',')
+            } else {
+                (',', '.')
+            };
+            return text.replace(group, "").replace(decimal, ".").parse().ok();
+        }
+    };
+    Some(value as f64)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_request_quantities() {
+        let quantities = parse_request_quantities(
+            "delete logs older than two weeks and larger than 1.5 GB",
+            false,
+        );
+        assert_eq!(
+            quantities
+                .iter()
+                .map(|v| (v.bound, v.value))
+                .collect::<Vec<_>>(),
+            [
+                (Bound::Above, QuantityValue::Age { minutes: 14 * 1440 }),
+                (Bound::Above, QuantityValue::Size { bytes: 3 << 29 }),
+            ]
+        );
+        assert_eq!(quantities[0].text, "older than two weeks");
+
+        let quantities =
+            parse_request_quantities("files changed in the last hour, under 1,5 MB", true);
+        assert_eq!(
+            quantities
+                .iter()
+                .map(|v| (v.bound, v.value))
+                .collect::<Vec<_>>(),
+            [
+                (Bound::Below, QuantityValue::Age { minutes: 60 }),
+                (Bound::Below, QuantityValue::Size { bytes: 3 << 19 }),
+            ]
+        );
+        assert!(parse_request_quantities("list my files", false).is_empty());
+    }
+
+    #[test]
+    fn test_check_command_quantities() {
+        let quantities = parse_request_quantities("delete logs older than two weeks", false);
+        for command in [
+            "find /var/log -name '*.log' -mtime +13 -delete",
+            "find /var/log -mtime +14 -delete",
+            "find . -mmin +20160 -delete",
+        ] {
+            assert!(
+                check_command_quantities(command, &quantities).is_empty(),
+                "{command}"
+            );
+        }
+        let warnings = check_command_quantities("find . -mtime -14 -delete", &quantities);
+        assert_eq!(
+            warnings,
+            ["⚠️  `-mtime -14` selects files newer than 14 days, but \"older than two weeks\" means files older than 14 days"]
+        );
+        assert_eq!(
+            check_command_quantities("find . -mtime +2 -delete", &quantities).len(),
+            1
+        );
+
+        let quantities = parse_request_quantities("files smaller than 1 GB", false);
+        assert!(check_command_quantities("find . -size -1073741824c", &quantities).is_empty());
+        let warnings = check_command_quantities("find . -size -1G", &quantities);
+        assert!(warnings[0].contains("selects only empty files"));
+
+        let quantities = parse_request_quantities("files bigger than 100MB", false);
+        assert!(check_command_quantities("find . -size +100M", &quantities).is_empty());
+        assert_eq!(
+            check_command_quantities("find . -size +100", &quantities).len(),
+            1
+        );
+    }
+
+    #[test]
+    fn test_locale_uses_decimal_comma() {
+        assert!(locale_uses_decimal_comma("de-DE"));
+        assert!(!locale_uses_decimal_comma("en_US"));
+    }
+}
@@ -0,0 +1,275 @@
+use super::*;
+
+use anyhow::{bail, Context, Result};
+use serde::{Deserialize, Serialize};
+use std::{path::Path, process::Command};
+
+/// Probe run on the remote host, prints `key=value` lines parsed by [`EnvProfile::from_probe`].
+const PROBE_SCRIPT: &str = r#"echo "os=$(uname -s)"
+echo "kernel=$(uname -r)"
+echo "shell=$SHELL"
+for p in apt-get pacman nix brew; do
+  if command -v $p >/dev/null 2>&1; then echo "pkg=$p"; break; fi
+done
+echo "cpus=$(getconf _NPROCESSORS_ONLN 2>/dev/null)"
+free -b 2>/dev/null | awk '/^Mem:/ {print "mem_total="$2; print "mem_used="$3}'
+df -Pk / 2>/dev/null | awk 'NR==2 {print "disk_total="$2*1024; print "disk_avail="$4*1024}'
+"#;
+
+/// A host that generated commands can run on, configured under `ssh_targets`:
+///
+/// ```yaml
+/// ssh_targets:
+///   - name: web1
+///     host: web1.example.com
+///     user: deploy
+///     policy:
+///       block_level: dangerous
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SshTarget {
+    pub name: String,
+    pub host: String,
+    #[serde(default)]
+    pub user: Option<String>,
+    #[serde(default)]
+    pub port: Option<u16>,
+    #[serde(default)]
+    pub identity_file: Option<String>,
+    #[serde(default)]
+    pub policy: RemotePolicy,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct RemotePolicy {
+    /// Refuse to run commands at or above this safety level.
+    #[serde(default)]
+    pub block_level: Option<SafetyLevel>,
+    /// Copy affected remote files back with scp before running destructive commands.
+    #[serde(default = "default_backup")]
+    pub backup: bool,
+}
+
+impl Default for RemotePolicy {
+    fn default() -> Self {
+        Self {
+            block_level: None,
+            backup: default_backup(),
+        }
+    }
+}
+
+fn default_backup() -> bool {
+    true
+}
+
+/// The active remote target together with its probed environment.
+#[derive(Debug, Clone)]
+pub struct RemoteSession {
+    pub target: SshTarget,
+    pub env: EnvProfile,
+}
+
+impl SshTarget {
+    /// `user@host` or just `host`.
+    pub fn destination(&self) -> String {
+        match &self.user {
+            Some(user) => format!("{user}@{}", self.host),
+            None => self.host.clone(),
+        }
+    }
+
+    fn ssh_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(port) = self.port {
+            args.extend(["-p".into(), port.to_string()]);
+        }
+        if let Some(identity_file) = &self.identity_file {
+            args.extend(["-i".into(), identity_file.clone()]);
+        }
+        args
+    }
+
+    fn scp_args(&self) -> Vec<String> {
+        let mut args = vec![];
+        if let Some(port) = self.port {
+            args.extend(["-P".into(), port.to_string()]);
+        }
+        if let Some(identity_file) = &self.identity_file {
+            args.extend(["-i".into(), identity_file.clone()]);
+        }
+        args
+    }
+
+    /// Arguments for `ssh` to run `command` through a POSIX shell on the remote host.
+    pub fn command_args(&self, command: &str) -> Vec<String> {
+        let mut args = self.ssh_args();
+        args.push(self.destination());
+        args.push("--".into());
+        args.push(command.to_string());
+        args
+    }
+
+    /// Run the command interactively and return its exit code.
+    pub fn run(&self, command: &str) -> Result<i32> {
+        debug!("ssh {:?}", self.command_args(command));
+        run_command("ssh", &self.command_args(command), None)
+            .with_context(|| format!("Failed to connect to '{}'", self.name))
+    }
+
+    fn output(&self, script: &str) -> Result<String> {
+        let command = format!("sh -c {}", shell_words::quote(script));
+        let output = Command::new("ssh")
+            .args(self.command_args(&command))
+            .output()
+            .with_context(|| format!("Failed to connect to '{}'", self.name))?;
+        if !output.status.success() {
+            bail!(
+                "Remote '{}' failed: {}",
+                self.name,
+                String::from_utf8_lossy(&output.stderr).trim()
+            );
+        }
+        Ok(String::from_utf8_lossy(&output.stdout).to_string())
+    }
+
+    pub fn probe(&self) -> Result<EnvProfile> {
+        let output = self.output(PROBE_SCRIPT)?;
+        Ok(EnvProfile::from_probe(&output))
+    }
+
+    /// Filter `paths` down to regular files that exist on the remote host.
+    pub fn existing_files(&self, paths: &[String]) -> Result<Vec<String>> {
+        if paths.is_empty() {
+            return Ok(vec![]);
+        }
+        let quoted: Vec<_> = paths.iter().map(|v| shell_words::quote(v)).collect();
+        let script = format!(
+            r#"for f in {}; do if [ -f "$f" ]; then echo "$f"; fi; done"#,
+            quoted.join(" ")
+        );
+        let output = self.output(&script)?;
+        Ok(output.lines().map(|v| v.to_string()).collect())
+    }
+
+    pub fn download(&self, remote_path: &str, local_path: &Path) -> Result<()> {
+        let mut args = self.scp_args();
+        args.push(format!("{}:{remote_path}", self.destination()));
+        args.push(local_path.display().to_string());
+        self.scp(args)
+    }
+
+    pub fn upload(&self, local_path: &Path, remote_path: &str) -> Result<()> {
+        let mut args = self.scp_args();
+        args.push(local_path.display().to_string());
+        args.push(format!("{}:{remote_path}", self.destination()));
+        self.scp(args)
+    }
+
+    fn scp(&self, args: Vec<String>) -> Result<()> {
+        let code = run_command("scp", &args, None)
+            .with_context(|| format!("Failed to copy files with '{}'", self.name))?;
+        if code != 0 {
+            bail!("scp exited with {code}");
+        }
+        Ok(())
+    }
+
+    /// Apply the host's safety policy to an analyzed command.
+    pub fn check(&self, analysis: &CommandAnalysis) -> Result<()> {
+        if let Some(level) = &self.policy.block_level {
+            if analysis.safety_level >= *level {
+                bail!(
+                    "'{}' blocks {:?} commands on this host",
+                    self.name,
+                    analysis.safety_level
+                );
+            }
+        }
+        Ok(())
+    }
+}
+
+impl EnvProfile {
+    /// Build a profile from the `key=value` output of the remote probe script.
+    pub fn from_probe(output: &str) -> Self {
+        let mut profile = EnvProfile::default();
+        let mut kernel = String::new();
+        let to_gb = |v: &str| v.trim().parse::<u64>().unwrap_or_default() / 1024 / 1024 / 1024;
+        for line in output.lines() {
+            let Some((key, value)) = line.split_once('=') else {
+                continue;
+            };
+            match key.trim() {
+                "os" => {
+                    profile.os = match value.trim() {
+                        "Linux" => OSKind::Linux,
+                        "Darwin" => OSKind::MacOS,
+                        _ => OSKind::Unknown,
+                    }
+                }
+                "kernel" => kernel = value.to_lowercase(),
+                "shell" => {
+                    let name = Path::new(value.trim())
+                        .file_stem()
+                        .and_then(|v| v.to_str())
+                        .unwrap_or_default();
+                    profile.shell = ShellKind::from_name(name);
+                }
+                "pkg" => {
+                    profile.pkg = match value.trim() {
+                        "apt-get" => PackageManager::Apt,
+                        "pacman" => PackageManager::Pacman,
+                        "nix" => PackageManager::Nix,
+                        "brew" => PackageManager::Brew,
+                        _ => PackageManager::Unknown,
+                    }
+                }
+                "cpus" => profile.cpu_cores = value.trim().parse().unwrap_or_default(),
+                "mem_total" => profile.memory_total_gb = to_gb(value),
+                "mem_used" => profile.memory_used_gb = to_gb(value),
+                "disk_total" => profile.disk_total_gb = to_gb(value),
+                "disk_avail" => profile.disk_available_gb = to_gb(value),
+                _ => {}
+            }
+        }
+        if matches!(profile.os, OSKind::Linux) && kernel.contains("microsoft") {
+            profile.os = OSKind::WSL;
+        }
+        profile
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_probe() {
+        let output = "os=Linux\nkernel=6.1.0\nshell=/usr/bin/fish\npkg=apt-get\ncpus=8\nmem_total=17179869184\nmem_used=4294967296\ndisk_total=107374182400\ndisk_avail=53687091200\n";
+        let profile = EnvProfile::from_probe(output);
+        assert!(matches!(profile.os, OSKind::Linux));
+        assert!(matches!(profile.shell, ShellKind::Fish));
+        assert!(matches!(profile.pkg, PackageManager::Apt));
+        assert_eq!(profile.cpu_cores, 8);
+        assert_eq!(profile.memory_total_gb, 16);
+        assert_eq!(profile.disk_available_gb, 50);
+    }
+
+    #[test]
+    fn test_command_args() {
+        let target: SshTarget = serde_yaml::from_str(
+            "name: web1\nhost: web1.example.com\nuser: deploy\nport: 2222\npolicy:\n  block_level: dangerous\n",
+        )
+        .unwrap();
+        assert_eq!(
+            target.command_args("ls"),
+            vec!["-p", "2222", "deploy@web1.example.com", "--", "ls"]
+        );
+        assert!(target.policy.backup);
+        assert!(target.check(&CommandAnalysis::analyze("ls")).is_ok());
+        assert!(target
+            .check(&CommandAnalysis::analyze("rm -rf build"))
+            .is_err());
+    }
+}
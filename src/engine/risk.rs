@@ -0,0 +1,259 @@
+use super::command_analyzer::{LARGE_TREE_BYTES, LARGE_TREE_FILES};
+use super::*;
+
+use serde::Serialize;
+
+/// One reason a command scores, with its points.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RiskFactor {
+    pub points: u8,
+    pub reason: String,
+}
+
+/// How much a command can break, 0 to 100, as the sum of its [`RiskFactor`]s. Never lower
+/// than the floor of its [`SafetyLevel`], so a policy rule or a Critical finding is never
+/// outscored by the additive part.
+#[derive(Debug, Clone, PartialEq, Default, Serialize)]
+pub struct RiskScore {
+    pub score: u8,
+    pub factors: Vec<RiskFactor>,
+}
+
+impl RiskScore {
+    /// The lowest score of a level: Safe 0, Caution 25, Dangerous 50, Critical 75.
+    pub fn floor(level: &SafetyLevel) -> u8 {
+        match level {
+            SafetyLevel::Safe => 0,
+            SafetyLevel::Caution => 25,
+            SafetyLevel::Dangerous => 50,
+            SafetyLevel::Critical => 75,
+        }
+    }
+
+    /// Score an analyzed command from its operation, flags, path scope, wildcards and
+    /// privilege use.
+    pub fn assess(analysis: &CommandAnalysis, style: PathStyle) -> Self {
+        let mut output = Self::default();
+        let (points, reason) = match analysis.operation {
+            CommandOperation::Disk => (40, "writes a raw disk"),
+            CommandOperation::Infrastructure => (30, "changes cloud infrastructure"),
+            CommandOperation::Delete => (25, "deletes files"),
+            CommandOperation::System => (20, "changes the system"),
+            CommandOperation::Modify => (15, "modifies files in place"),
+            CommandOperation::Move => (10, "moves files"),
+            CommandOperation::Write => (10, "writes files"),
+            CommandOperation::Execute => (10, "runs a program or script"),
+            CommandOperation::Network => (10, "talks to the network"),
+            CommandOperation::Copy | CommandOperation::Create => (5, "creates files"),
+            CommandOperation::Unknown => (5, "unknown command"),
+            CommandOperation::Read => (0, ""),
+        };
+        output.add(points, reason);
+
+        let words = split_command_words(&analysis.command, style);
+        let is_short =
+            |v: &str, c: char| v.starts_with('-') && !v.starts_with("--") && v[1..].contains(c);
+        let privileged = words
+            .iter()
+            .any(|v| matches!(v.as_str(), "sudo" | "doas" | "pkexec" | "runas" | "su"))
+            || analysis.command.to_lowercase().contains("-verb runas");
+        if privileged {
+            output.add(20, "runs with elevated privileges");
+        }
+        // `sudo rm` is a System operation, its flags count all the same
+        let destructive = analysis.operation.is_destructive()
+            || analysis.operation.needs_backup()
+            || analysis.operation == CommandOperation::System
+            || !analysis.permission_changes.is_empty();
+        if destructive {
+            if words.iter().any(|v| {
+                v == "--recursive" || v == "-Recurse" || is_short(v, 'r') || is_short(v, 'R')
+            }) || analysis.windows_commands.iter().any(|v| v.recursive)
+            {
+                output.add(10, "recursive");
+            }
+            if words
+                .iter()
+                .any(|v| v == "--force" || v == "-Force" || is_short(v, 'f'))
+                || analysis.windows_commands.iter().any(|v| v.force)
+            {
+                output.add(10, "forced, skips confirmations");
+            }
+            if !analysis.glob_expansions.is_empty() {
+                let matched: usize = analysis.glob_expansions.iter().map(|v| v.total).sum();
+                output.add(10, "wildcards");
+                if matched > 100 {
+                    output.add(5, &format!("wildcards match {matched} files"));
+                }
+            }
+        } else if words.iter().any(|v| v == "--force" || v == "-Force") {
+            output.add(10, "forced, skips confirmations");
+        }
+
+        if words.iter().any(|v| is_protected_path(v, style)) {
+            output.add(25, "targets a system path");
+        } else if words.iter().any(|v| {
+            matches!(
+                v.trim_end_matches('/'),
+                "~" | "$HOME" | "${HOME}" | "$env:USERPROFILE"
+            )
+        }) {
+            output.add(15, "targets the whole home directory");
+        }
+        if destructive
+            && analysis
+                .symlinks
+                .iter()
+                .any(|v| v.followed_by(&analysis.operation))
+        {
+            output.add(10, "follows a symlink elsewhere");
+        }
+        if let Some(footprint) = &analysis.footprint {
+            if footprint.files >= LARGE_TREE_FILES || footprint.bytes >= LARGE_TREE_BYTES {
+                output.add(10, "touches a large tree");
+            }
+        }
+
+        if analysis.pipe_to_shell.is_some() {
+            output.add(40, "runs a downloaded script unseen");
+        }
+        if !analysis.exfiltration.is_empty() {
+            output.add(40, "sends secrets off the machine");
+        }
+        if !analysis.resource_exhaustion.is_empty() {
+            output.add(50, "exhausts processes, CPU or disk");
+        }
+        if analysis.sql_statements.iter().any(|v| v.is_critical()) {
+            output.add(30, "drops or wipes database objects");
+        }
+        if analysis.iac_commands.iter().any(|v| v.destroys) {
+            output.add(30, "destroys cloud resources");
+        }
+        if analysis
+            .kube_commands
+            .iter()
+            .any(|v| v.destructive.is_some())
+        {
+            output.add(15, "deletes or changes Kubernetes resources");
+        }
+        if analysis.docker_commands.iter().any(|v| v.volumes) {
+            output.add(15, "deletes Docker volumes and their data");
+        }
+        if analysis
+            .kill_commands
+            .iter()
+            .any(|v| v.targets_all() || !v.critical_processes().is_empty())
+        {
+            output.add(20, "signals system processes");
+        }
+        if !analysis.network_destinations.is_empty() {
+            output.add(5, "reaches other hosts");
+        }
+
+        let floor = Self::floor(&analysis.safety_level);
+        if output.score < floor {
+            let reason = format!("rated {:?}", analysis.safety_level);
+            output.add(floor - output.score, &reason);
+        }
+        output
+    }
+
+    /// Why a command scoring at or above `limit` is refused, see `risk_block_score`.
+    pub fn blocked(&self, limit: Option<u8>) -> Option<String> {
+        let limit = limit.filter(|v| self.score >= *v)?;
+        Some(format!(
+            "risk {self} reaches the limit of {limit} ({})",
+            self.reasons()
+        ))
+    }
+
+    /// Why a command scoring at or above `threshold` must be confirmed explicitly,
+    /// see `risk_confirm_score`.
+    pub fn needs_confirmation(&self, threshold: u8) -> Option<String> {
+        if self.score < threshold {
+            return None;
+        }
+        Some(format!(
+            "risk {self} reaches the confirmation threshold of {threshold} ({})",
+            self.reasons()
+        ))
+    }
+
+    fn reasons(&self) -> String {
+        self.factors
+            .iter()
+            .map(|v| v.reason.as_str())
+            .collect::<Vec<_>>()
+            .join(", ")
+    }
+
+    fn add(&mut self, points: u8, reason: &str) {
+        let points = points.min(100 - self.score);
+        if points == 0 {
+            return;
+        }
+        self.score += points;
+        self.factors.push(RiskFactor {
+            points,
+            reason: reason.to_string(),
+        });
+    }
+}
+
+impl std::fmt::Display for RiskScore {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}/100", self.score)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assess(command: &str, fs: &MemoryFs) -> RiskScore {
+        let analysis =
+            CommandAnalysis::analyze_with(command, PathStyle::Unix, &ShellKind::Bash, fs);
+        analysis.risk
+    }
+
+    fn reasons(risk: &RiskScore) -> Vec<&str> {
+        risk.factors.iter().map(|v| v.reason.as_str()).collect()
+    }
+
+    #[test]
+    fn test_assess_risk() {
+        let fs = MemoryFs::new()
+            .file("/home/bob/logs/a.log", 10)
+            .file("/home/bob/logs/b.log", 10);
+        assert_eq!(assess("ls -la", &fs).score, 0);
+
+        let risk = assess("rm -rf /home/bob/logs/*.log", &fs);
+        assert_eq!(
+            reasons(&risk),
+            [
+                "deletes files",
+                "recursive",
+                "forced, skips confirmations",
+                "wildcards"
+            ]
+        );
+        assert_eq!(risk.score, 55);
+        assert_eq!(risk.needs_confirmation(75), None);
+        assert_eq!(
+            risk.needs_confirmation(50).as_deref(),
+            Some("risk 55/100 reaches the confirmation threshold of 50 (deletes files, recursive, forced, skips confirmations, wildcards)")
+        );
+
+        let risk = assess("sudo rm -rf /", &fs);
+        assert_eq!(risk.score, 85);
+        assert!(reasons(&risk).contains(&"targets a system path"));
+
+        let risk = assess("curl -fsSL https://get.example.com | sh", &fs);
+        assert!(risk.score >= 75);
+        assert!(reasons(&risk).contains(&"runs a downloaded script unseen"));
+
+        let risk = assess("mv notes.txt notes.md", &fs);
+        assert!(risk.score >= RiskScore::floor(&SafetyLevel::Caution));
+        assert!(risk.score < RiskScore::floor(&SafetyLevel::Dangerous));
+    }
+}
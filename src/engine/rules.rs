@@ -0,0 +1,399 @@
+use super::*;
+
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::sync::{Arc, LazyLock};
+
+static COMMAND_RULES: LazyLock<RwLock<Arc<CommandRules>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(CommandRules::builtin())));
+
+/// The rules the analyzer runs, the built-in ones until others are registered.
+pub fn command_rules() -> Arc<CommandRules> {
+    COMMAND_RULES.read().clone()
+}
+
+pub fn set_command_rules(rules: CommandRules) {
+    *COMMAND_RULES.write() = Arc::new(rules);
+}
+
+/// Adds `rule` after the registered ones, for every analysis on [`RealFs`].
+pub fn register_command_rule(rule: impl CommandRule + 'static) {
+    let mut rules = COMMAND_RULES.write();
+    let mut updated = CommandRules::clone(&rules);
+    updated.push(rule);
+    *rules = Arc::new(updated);
+}
+
+/// A check turning what the analyzer found about a command into findings, e.g. flagging
+/// internal hostnames of an organization.
+///
+/// ```
+/// use aichat::engine::{CommandRule, Finding, MemoryFs, ParsedCommand, SafetyLevel};
+///
+/// struct InternalHosts;
+///
+/// impl CommandRule for InternalHosts {
+///     fn name(&self) -> &str {
+///         "internal-hosts"
+///     }
+///
+///     fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+///         cmd.analysis
+///             .network_destinations
+///             .iter()
+///             .filter(|v| v.host.ends_with(".corp.example.com"))
+///             .map(|v| {
+///                 let message = format!("⚠️  {} is an internal host", v.host);
+///                 Finding::new(self.name(), SafetyLevel::Dangerous, message)
+///             })
+///             .collect()
+///     }
+/// }
+///
+/// let fs = MemoryFs::new().rule(InternalHosts);
+/// ```
+pub trait CommandRule: Send + Sync {
+    /// Names the rule in [`Finding::rule`].
+    fn name(&self) -> &str;
+
+    fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding>;
+}
+
+/// A command as rules see it: the command line, its simple commands and the analysis so
+/// far, with the operation, targets, scope, network destinations and detected tools.
+pub struct ParsedCommand<'a> {
+    pub command: &'a str,
+    /// Pipeline stages, chained commands and substitutions, see [`split_command_segments`].
+    pub segments: &'a [String],
+    pub style: PathStyle,
+    pub shell: &'a ShellKind,
+    pub fs: &'a dyn FileSystem,
+    pub analysis: &'a CommandAnalysis,
+}
+
+impl ParsedCommand<'_> {
+    /// The words of each segment, quotes removed.
+    pub fn words(&self) -> Vec<Vec<String>> {
+        self.segments
+            .iter()
+            .map(|v| {
+                tokenize(v, self.style)
+                    .into_iter()
+                    .filter(|v| v.is_word())
+                    .map(|v| v.text)
+                    .collect()
+            })
+            .collect()
+    }
+}
+
+/// What a rule found: the analysis is raised to `level` and shows `message`.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Finding {
+    pub rule: String,
+    pub level: SafetyLevel,
+    pub message: String,
+}
+
+impl Finding {
+    pub fn new(rule: &str, level: SafetyLevel, message: impl Into<String>) -> Self {
+        Self {
+            rule: rule.to_string(),
+            level,
+            message: message.into(),
+        }
+    }
+}
+
+/// The rules of an analysis, run in order.
+#[derive(Clone, Default)]
+pub struct CommandRules {
+    rules: Vec<Arc<dyn CommandRule>>,
+}
+
+impl std::fmt::Debug for CommandRules {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list()
+            .entries(self.rules.iter().map(|v| v.name()))
+            .finish()
+    }
+}
+
+impl CommandRules {
+    /// The analyzer's own judgement: [`SeverityRule`], [`PathScopeRule`],
+    /// [`UnsetVariableRule`], [`SystemConfigRule`] and [`ScheduledJobRule`].
+    pub fn builtin() -> Self {
+        let mut rules = Self::default();
+        rules.push(SeverityRule);
+        rules.push(PathScopeRule);
+        rules.push(UnsetVariableRule);
+        rules.push(SystemConfigRule);
+        rules.push(ScheduledJobRule);
+        rules
+    }
+
+    pub fn push(&mut self, rule: impl CommandRule + 'static) {
+        self.rules.push(Arc::new(rule));
+    }
+
+    /// Without the rule named `name`, to replace a built-in one.
+    pub fn remove(&mut self, name: &str) {
+        self.rules.retain(|v| v.name() != name);
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.rules.iter().map(|v| v.name()).collect()
+    }
+
+    pub fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+        self.rules.iter().flat_map(|v| v.evaluate(cmd)).collect()
+    }
+}
+
+/// The level of the operation, or Critical for piped installers, leaked credentials,
+/// destroyed disks, runaway resource use and system-wide changes, with the reason.
+pub struct SeverityRule;
+
+impl CommandRule for SeverityRule {
+    fn name(&self) -> &str {
+        "severity"
+    }
+
+    fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+        let analysis = cmd.analysis;
+        let critical =
+            |message: &str| vec![Finding::new(self.name(), SafetyLevel::Critical, message)];
+        if let Some(pipe) = &analysis.pipe_to_shell {
+            return critical(&pipe.warning());
+        }
+        if !analysis.exfiltration.is_empty() {
+            return critical(
+                "⚠️  CRITICAL: This command sends keys or credentials from this machine over the network!",
+            );
+        }
+        if analysis.disk_commands.iter().any(|v| !v.read_only) {
+            return critical(
+                "⚠️  CRITICAL: This command destroys data on a disk, no undo or backup can bring it back!",
+            );
+        }
+        if !analysis.resource_exhaustion.is_empty() {
+            return critical(
+                "⚠️  CRITICAL: This command runs until the machine is out of processes, CPU or disk space!",
+            );
+        }
+        let removes_root = analysis.operation == CommandOperation::Delete
+            && (split_command_words(cmd.command, cmd.style)
+                .iter()
+                .any(|v| is_protected_path(v, cmd.style))
+                || analysis.symlinks.iter().any(|v| {
+                    v.followed_by(&analysis.operation) && v.reaches_protected(cmd.style, cmd.fs)
+                }));
+        let system_wide = cmd.command.contains("sudo")
+            || removes_root
+            || analysis.sql_statements.iter().any(|v| v.is_critical())
+            || analysis.iac_commands.iter().any(|v| v.destroys)
+            || analysis.permission_changes.iter().any(|v| {
+                v.is_recursive_on_protected(cmd.style)
+                    || v.changes_system_ownership(cmd.style, cmd.fs)
+            })
+            || analysis
+                .kill_commands
+                .iter()
+                .any(|v| v.targets_all() || !v.critical_processes().is_empty())
+            || analysis
+                .package_commands
+                .iter()
+                .any(|v| !v.essential_packages().is_empty())
+            || analysis.windows_commands.iter().any(|v| v.critical)
+            || analysis
+                .find_commands
+                .iter()
+                .any(|v| v.changes_protected(cmd.style))
+            || analysis
+                .transfer_commands
+                .iter()
+                .any(|v| v.deletes_protected(cmd.style));
+        if system_wide {
+            return critical(
+                "⚠️  CRITICAL: This command requires elevated privileges or affects system files!",
+            );
+        }
+        if analysis.operation.is_destructive() {
+            return vec![Finding::new(
+                self.name(),
+                SafetyLevel::Dangerous,
+                "⚠️  DANGEROUS: This operation cannot be easily undone!",
+            )];
+        }
+        if analysis.operation.needs_backup() {
+            return vec![Finding::new(
+                self.name(),
+                SafetyLevel::Caution,
+                "⚠️  CAUTION: This operation will modify files.",
+            )];
+        }
+        vec![]
+    }
+}
+
+/// A command run in a project is rarely meant to change anything outside of it: system
+/// directories are Critical, anything else outside the working tree Dangerous.
+pub struct PathScopeRule;
+
+impl CommandRule for PathScopeRule {
+    fn name(&self) -> &str {
+        "path-scope"
+    }
+
+    fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+        match cmd.analysis.scope {
+            Some(PathScope::System) => vec![Finding::new(
+                self.name(),
+                SafetyLevel::Critical,
+                "⚠️  CRITICAL: This command changes files in system directories!",
+            )],
+            Some(scope) if scope > PathScope::WorkingTree => vec![Finding::new(
+                self.name(),
+                SafetyLevel::Dangerous,
+                format!("⚠️  This changes files outside the working directory ({scope})."),
+            )],
+            _ => vec![],
+        }
+    }
+}
+
+/// `rm -rf $UNSET/` is `rm -rf /`: a changing command losing a variable is Dangerous, and
+/// Critical when what is left is a protected path.
+pub struct UnsetVariableRule;
+
+impl CommandRule for UnsetVariableRule {
+    fn name(&self) -> &str {
+        "unset-variables"
+    }
+
+    fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+        let operation = &cmd.analysis.operation;
+        if !operation.is_destructive() && !operation.needs_backup() {
+            return vec![];
+        }
+        cmd.analysis
+            .unset_variables
+            .iter()
+            .map(|unset| {
+                let level = match is_protected_path(&unset.expanded, cmd.style) {
+                    true => SafetyLevel::Critical,
+                    false => SafetyLevel::Dangerous,
+                };
+                Finding::new(self.name(), level, unset.warning())
+            })
+            .collect()
+    }
+}
+
+/// sudoers, the account files and fstab are Critical, a mistake in them locks users out
+/// or stops the machine from booting. Service units and the registry are Dangerous, the
+/// machine's registry Critical.
+pub struct SystemConfigRule;
+
+impl CommandRule for SystemConfigRule {
+    fn name(&self) -> &str {
+        "system-config"
+    }
+
+    fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+        cmd.analysis
+            .system_config_edits
+            .iter()
+            .map(|v| Finding::new(self.name(), v.level(), v.warning()))
+            .collect()
+    }
+}
+
+/// Cron jobs, at jobs and scheduled tasks run later, unattended, and `crontab -r` takes
+/// every job of the user with it: changing them is Dangerous.
+pub struct ScheduledJobRule;
+
+impl CommandRule for ScheduledJobRule {
+    fn name(&self) -> &str {
+        "scheduled-jobs"
+    }
+
+    fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+        cmd.analysis
+            .persistent_changes
+            .iter()
+            .filter(|v| v.is_scheduled())
+            .map(|v| Finding::new(self.name(), SafetyLevel::Dangerous, v.warning()))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct InternalHosts;
+
+    impl CommandRule for InternalHosts {
+        fn name(&self) -> &str {
+            "internal-hosts"
+        }
+
+        fn evaluate(&self, cmd: &ParsedCommand) -> Vec<Finding> {
+            cmd.words()
+                .iter()
+                .flatten()
+                .filter(|v| v.contains(".corp.example.com"))
+                .map(|v| {
+                    Finding::new(
+                        self.name(),
+                        SafetyLevel::Dangerous,
+                        format!("⚠️  {v} is an internal host"),
+                    )
+                })
+                .collect()
+        }
+    }
+
+    #[test]
+    fn test_command_rules() {
+        assert_eq!(
+            CommandRules::builtin().names(),
+            [
+                "severity",
+                "path-scope",
+                "unset-variables",
+                "system-config",
+                "scheduled-jobs"
+            ]
+        );
+
+        let fs = MemoryFs::new()
+            .cwd("/work")
+            .dir("/tmp/x")
+            .rule(InternalHosts);
+        let analysis = CommandAnalysis::analyze_with(
+            "ssh deploy@db.corp.example.com uptime",
+            PathStyle::Unix,
+            &ShellKind::Bash,
+            &fs,
+        );
+        assert_eq!(analysis.safety_level, SafetyLevel::Dangerous);
+        assert_eq!(
+            analysis.findings,
+            [Finding::new(
+                "internal-hosts",
+                SafetyLevel::Dangerous,
+                "⚠️  deploy@db.corp.example.com is an internal host"
+            )]
+        );
+        assert!(analysis
+            .warnings
+            .contains(&"⚠️  deploy@db.corp.example.com is an internal host".to_string()));
+
+        let analysis =
+            CommandAnalysis::analyze_with("rm -rf /tmp/x", PathStyle::Unix, &ShellKind::Bash, &fs);
+        let rules: Vec<_> = analysis.findings.iter().map(|v| v.rule.as_str()).collect();
+        assert_eq!(rules, ["severity", "path-scope"]);
+    }
+}
@@ -0,0 +1,176 @@
+use super::*;
+
+use anyhow::Result;
+use std::path::PathBuf;
+
+/// Entry point for embedding the engine: analysis, tutoring and backups configured once
+/// for a shell, a path style, an environment and a filesystem.
+///
+/// ```no_run
+/// use aichat::engine::{SafetyEngine, SafetyLevel, ShellKind};
+///
+/// let engine = SafetyEngine::builder().shell(ShellKind::Bash).build();
+/// let analysis = engine.analyze("rm -rf ./build");
+/// if analysis.safety_level >= SafetyLevel::Dangerous {
+///     println!("{}", analysis.display());
+/// }
+/// ```
+pub struct SafetyEngine {
+    shell: ShellKind,
+    path_style: PathStyle,
+    environment: EnvProfile,
+    filesystem: Box<dyn FileSystem + Send + Sync>,
+    backup_dir: Option<PathBuf>,
+}
+
+/// Every setting defaults to what the local machine uses.
+#[derive(Default)]
+pub struct SafetyEngineBuilder {
+    shell: Option<ShellKind>,
+    path_style: Option<PathStyle>,
+    environment: Option<EnvProfile>,
+    filesystem: Option<Box<dyn FileSystem + Send + Sync>>,
+    backup_dir: Option<PathBuf>,
+}
+
+impl SafetyEngineBuilder {
+    /// The shell commands are written for, defaults to the detected one.
+    pub fn shell(mut self, shell: ShellKind) -> Self {
+        self.shell = Some(shell);
+        self
+    }
+
+    pub fn path_style(mut self, style: PathStyle) -> Self {
+        self.path_style = Some(style);
+        self
+    }
+
+    /// The machine tutorials are written for, skips [`EnvProfile::detect`].
+    pub fn environment(mut self, environment: EnvProfile) -> Self {
+        self.environment = Some(environment);
+        self
+    }
+
+    /// Where paths are checked and measured, e.g. a [`MemoryFs`] in tests.
+    pub fn filesystem(mut self, fs: impl FileSystem + Send + Sync + 'static) -> Self {
+        self.filesystem = Some(Box::new(fs));
+        self
+    }
+
+    /// Defaults to `~/.aichat_backups`.
+    pub fn backup_dir(mut self, dir: impl Into<PathBuf>) -> Self {
+        self.backup_dir = Some(dir.into());
+        self
+    }
+
+    pub fn build(self) -> SafetyEngine {
+        let mut environment = self.environment.unwrap_or_else(EnvProfile::detect);
+        if let Some(shell) = self.shell {
+            environment.shell = shell;
+        }
+        SafetyEngine {
+            shell: environment.shell.clone(),
+            path_style: self.path_style.unwrap_or_else(PathStyle::current),
+            environment,
+            filesystem: self.filesystem.unwrap_or_else(|| Box::new(RealFs)),
+            backup_dir: self.backup_dir,
+        }
+    }
+}
+
+impl SafetyEngine {
+    pub fn builder() -> SafetyEngineBuilder {
+        SafetyEngineBuilder::default()
+    }
+
+    pub fn shell(&self) -> &ShellKind {
+        &self.shell
+    }
+
+    pub fn environment(&self) -> &EnvProfile {
+        &self.environment
+    }
+
+    pub fn analyze(&self, command: &str) -> CommandAnalysis {
+        CommandAnalysis::analyze_with(
+            command,
+            self.path_style,
+            &self.shell,
+            self.filesystem.as_ref(),
+        )
+    }
+
+    /// The files `command` would delete, create, overwrite or move, see [`ImpactPreview`].
+    pub fn simulate(&self, command: &str) -> ImpactPreview {
+        ImpactPreview::simulate(
+            command,
+            self.path_style,
+            &self.shell,
+            self.filesystem.as_ref(),
+        )
+    }
+
+    pub fn analyze_script(&self, content: &str) -> CommandAnalysis {
+        CommandAnalysis::analyze_script(content, &self.shell)
+    }
+
+    pub fn tutorial(&self, command: &str) -> CommandTutorial {
+        CommandTutorial::analyze(command, &self.environment)
+    }
+
+    pub fn annotate_script(&self, script: &str) -> String {
+        annotate_script(script, &self.shell)
+    }
+
+    /// The command that reverses `command`, see [`inverse_command`].
+    pub fn undo_command(&self, command: &str) -> Option<String> {
+        inverse_command(command, &self.shell, self.filesystem.as_ref())
+    }
+
+    pub fn backups(&self) -> Result<BackupManager> {
+        match &self.backup_dir {
+            Some(dir) => BackupManager::with_dir(dir),
+            None => BackupManager::new(),
+        }
+    }
+
+    /// Back up the existing files `command` targets, `None` when it targets none.
+    pub fn backup(&self, command: &str) -> Result<Option<BackupEntry>> {
+        let paths = command_target_paths(command, self.path_style, self.filesystem.as_ref());
+        if paths.is_empty() {
+            return Ok(None);
+        }
+        self.backups()?.create_backup(command, paths).map(Some)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_safety_engine() {
+        let fs = MemoryFs::new()
+            .file("/srv/app/config.yaml", 512)
+            .file("/srv/app/data.db", 4096);
+        let engine = SafetyEngine::builder()
+            .shell(ShellKind::Bash)
+            .path_style(PathStyle::Unix)
+            .environment(EnvProfile::default())
+            .filesystem(fs)
+            .build();
+        assert!(matches!(engine.shell(), ShellKind::Bash));
+
+        let analysis = engine.analyze("rm -rf /srv/app");
+        assert!(analysis.safety_level >= SafetyLevel::Dangerous);
+        assert_eq!(
+            analysis.footprint.map(|v| v.to_string()),
+            Some("2 files, 4.5 KB".into())
+        );
+        assert_eq!(
+            engine.undo_command("mkdir /srv/logs"),
+            Some("rmdir /srv/logs".into())
+        );
+        assert!(!engine.tutorial("ls -la").structure.is_empty());
+    }
+}
@@ -0,0 +1,600 @@
+use super::*;
+
+use serde_json::{json, Value};
+use std::path::PathBuf;
+
+/// A command found in a document, spanning `line..=end_line`.
+#[derive(Debug, Clone)]
+pub struct DocumentCommand {
+    pub line: usize,
+    pub end_line: usize,
+    pub command: String,
+    pub shell: ShellKind,
+    /// The functions, conditionals and loops it runs in, outermost first.
+    pub blocks: Vec<String>,
+}
+
+impl DocumentCommand {
+    pub fn analyze(&self) -> CommandAnalysis {
+        let style = match self.shell {
+            ShellKind::PowerShell | ShellKind::Cmd => PathStyle::Windows,
+            _ => PathStyle::Unix,
+        };
+        CommandAnalysis::analyze_for(&self.command, style, &self.shell)
+    }
+}
+
+/// Every command of a script analyzed, with the blocks it runs in, for reviewing a whole
+/// script before running it.
+#[derive(Debug, Clone)]
+pub struct ScriptReport {
+    pub path: String,
+    pub statements: Vec<(DocumentCommand, CommandAnalysis)>,
+}
+
+impl ScriptReport {
+    /// A shell script, or the shell code blocks of a markdown file.
+    pub fn analyze(path: &str, text: &str) -> Self {
+        let language = language_of_path(path).unwrap_or("shellscript");
+        let statements = extract_document_commands(text, language)
+            .into_iter()
+            .map(|v| {
+                let analysis = v.analyze();
+                (v, analysis)
+            })
+            .collect();
+        Self {
+            path: path.to_string(),
+            statements,
+        }
+    }
+
+    /// The script is as dangerous as its worst command.
+    pub fn safety_level(&self) -> SafetyLevel {
+        self.statements
+            .iter()
+            .map(|(_, analysis)| analysis.safety_level.clone())
+            .max()
+            .unwrap_or(SafetyLevel::Safe)
+    }
+
+    /// The commands that aren't Safe, in script order.
+    pub fn findings(&self) -> impl Iterator<Item = &(DocumentCommand, CommandAnalysis)> {
+        self.statements
+            .iter()
+            .filter(|(_, analysis)| analysis.safety_level > SafetyLevel::Safe)
+    }
+
+    pub fn count(&self, level: SafetyLevel) -> usize {
+        self.statements
+            .iter()
+            .filter(|(_, analysis)| analysis.safety_level == level)
+            .count()
+    }
+
+    /// Files the risky commands touch, each once.
+    pub fn affected_files(&self) -> Vec<PathBuf> {
+        let mut output: Vec<PathBuf> = vec![];
+        for (_, analysis) in self.findings() {
+            for file in &analysis.affected_files {
+                if !output.contains(file) {
+                    output.push(file.clone());
+                }
+            }
+        }
+        output
+    }
+
+    pub fn network_destinations(&self) -> Vec<NetworkDestination> {
+        let mut output: Vec<NetworkDestination> = vec![];
+        for (_, analysis) in &self.statements {
+            for destination in &analysis.network_destinations {
+                if !output.contains(destination) {
+                    output.push(destination.clone());
+                }
+            }
+        }
+        output
+    }
+
+    pub fn display(&self) -> String {
+        let level_text = |level: &SafetyLevel| match is_accessible() {
+            true => level.label(),
+            false => color_text(&format!("{level:?}"), level.color()),
+        };
+        let mut output = String::new();
+        output.push_str(&format!("\n{}\n", "=".repeat(60)));
+        output.push_str(&format!("📜 Script Analysis: {}\n", self.path));
+        output.push_str(&format!("{}\n\n", "=".repeat(60)));
+        output.push_str(&format!(
+            "Safety Level: {}\n",
+            level_text(&self.safety_level())
+        ));
+        output.push_str(&format!(
+            "Commands: {} ({} critical, {} dangerous, {} caution)\n\n",
+            self.statements.len(),
+            self.count(SafetyLevel::Critical),
+            self.count(SafetyLevel::Dangerous),
+            self.count(SafetyLevel::Caution)
+        ));
+
+        let files = self.affected_files();
+        if !files.is_empty() {
+            output.push_str("Affected Files:\n");
+            for file in files {
+                output.push_str(&format!("  - {}\n", file.display()));
+            }
+            output.push('\n');
+        }
+        let destinations = self.network_destinations();
+        if !destinations.is_empty() {
+            output.push_str("Network Destinations:\n");
+            for destination in destinations {
+                output.push_str(&format!("  - {destination}\n"));
+            }
+            output.push('\n');
+        }
+
+        if self.findings().next().is_some() {
+            output.push_str("Findings:\n");
+            for (command, analysis) in self.findings() {
+                let level = &analysis.safety_level;
+                output.push_str(&format!(
+                    "  line {} [{}] {}\n",
+                    command.line + 1,
+                    level_text(level),
+                    command.command
+                ));
+                if !command.blocks.is_empty() {
+                    output.push_str(&format!("       in {}\n", command.blocks.join(" > ")));
+                }
+                for warning in &analysis.warnings {
+                    output.push_str(&format!("       {}\n", note_text(warning, level)));
+                }
+            }
+        }
+        output
+    }
+
+    pub fn to_json(&self) -> Value {
+        json!({
+            "path": self.path,
+            "safety_level": self.safety_level(),
+            "statements": self.statements.iter().map(|(command, analysis)| json!({
+                "line": command.line + 1,
+                "end_line": command.end_line + 1,
+                "blocks": command.blocks,
+                "analysis": analysis.to_json(),
+            })).collect::<Vec<_>>(),
+        })
+    }
+}
+
+/// The document language of a file from its extension, `None` for shell scripts.
+pub fn language_of_path(path: &str) -> Option<&'static str> {
+    let ext = path.rsplit_once('.')?.1.to_lowercase();
+    match ext.as_str() {
+        "md" | "markdown" => Some("markdown"),
+        "ps1" | "psm1" => Some("powershell"),
+        "bat" | "cmd" => Some("bat"),
+        _ => None,
+    }
+}
+
+/// The commands of a script, or of the shell code blocks in markdown.
+pub fn extract_document_commands(text: &str, language: &str) -> Vec<DocumentCommand> {
+    let lines: Vec<&str> = text.lines().collect();
+    match language {
+        "markdown" => {
+            let mut output = vec![];
+            let mut block: Option<(usize, String)> = None;
+            for (i, line) in lines.iter().enumerate() {
+                let trimmed = line.trim_start();
+                if !trimmed.starts_with("```") {
+                    continue;
+                }
+                match block.take() {
+                    Some((start, fence)) => {
+                        let Some(shell) = shell_of_language(&fence) else {
+                            continue;
+                        };
+                        let console = matches!(fence.as_str(), "console" | "shell-session");
+                        output.extend(extract_script_commands(
+                            &lines[start + 1..i],
+                            start + 1,
+                            shell,
+                            console,
+                        ));
+                    }
+                    None => {
+                        let fence = trimmed.trim_start_matches('`').trim();
+                        let fence = fence.split_whitespace().next().unwrap_or_default();
+                        block = Some((i, fence.to_lowercase()));
+                    }
+                }
+            }
+            output
+        }
+        _ => {
+            let shell = shell_of_language(language).unwrap_or(ShellKind::Bash);
+            extract_script_commands(&lines, 0, shell, false)
+        }
+    }
+}
+
+fn shell_of_language(language: &str) -> Option<ShellKind> {
+    match language {
+        "powershell" | "pwsh" | "ps1" => Some(ShellKind::PowerShell),
+        "cmd" | "bat" => Some(ShellKind::Cmd),
+        "zsh" => Some(ShellKind::Zsh),
+        "fish" => Some(ShellKind::Fish),
+        "shellscript" | "sh" | "bash" | "shell" | "console" | "shell-session" => {
+            Some(ShellKind::Bash)
+        }
+        _ => None,
+    }
+}
+
+/// Join `\` continuations, skip comments and heredoc bodies. In `console` blocks only
+/// lines after a `$ ` prompt are commands, the rest is output.
+fn extract_script_commands(
+    lines: &[&str],
+    offset: usize,
+    shell: ShellKind,
+    console: bool,
+) -> Vec<DocumentCommand> {
+    let mut output: Vec<DocumentCommand> = vec![];
+    let mut pending: Option<DocumentCommand> = None;
+    let mut heredoc: Option<String> = None;
+    let mut tracker = BlockTracker::default();
+    for (i, line) in lines.iter().enumerate() {
+        let line_no = offset + i;
+        if let Some(terminator) = &heredoc {
+            if line.trim() == terminator {
+                heredoc = None;
+            }
+            continue;
+        }
+        let mut text = line.trim();
+        if pending.is_none() {
+            if console {
+                match text.strip_prefix("$ ").or_else(|| text.strip_prefix("# ")) {
+                    Some(v) => text = v.trim(),
+                    None => continue,
+                }
+            }
+            if text.is_empty() || text.starts_with('#') || text.starts_with("::") {
+                continue;
+            }
+            if text.to_lowercase().starts_with("rem ") && matches!(shell, ShellKind::Cmd) {
+                continue;
+            }
+        }
+        let continuation = match shell {
+            ShellKind::PowerShell => '`',
+            ShellKind::Cmd => '^',
+            _ => '\\',
+        };
+        let (text, continues) = match text.strip_suffix(continuation) {
+            Some(v) => (v.trim_end(), true),
+            None => (text, false),
+        };
+        let command = match pending.as_mut() {
+            Some(command) => {
+                command.command.push(' ');
+                command.command.push_str(text);
+                command.end_line = line_no;
+                command
+            }
+            None => pending.insert(DocumentCommand {
+                line: line_no,
+                end_line: line_no,
+                command: text.to_string(),
+                shell: shell.clone(),
+                blocks: vec![],
+            }),
+        };
+        if continues {
+            continue;
+        }
+        heredoc = heredoc_terminator(&command.command);
+        output.extend(pending.take().into_iter().flat_map(|v| tracker.split(v)));
+    }
+    output.extend(pending.into_iter().flat_map(|v| tracker.split(v)));
+    output
+}
+
+/// The blocks open at the current line of a POSIX-like script. Their keywords aren't
+/// commands, what they enclose is analyzed on its own.
+#[derive(Debug, Default)]
+struct BlockTracker {
+    blocks: Vec<String>,
+    /// `deploy()` without the `{`, the next line opens the body.
+    function_header: bool,
+}
+
+impl BlockTracker {
+    /// The commands of one line once `if ...; then`, `do`, `fi`, `name() {` and `case`
+    /// patterns are taken off. PowerShell and cmd lines stay whole.
+    fn split(&mut self, command: DocumentCommand) -> Vec<DocumentCommand> {
+        if matches!(command.shell, ShellKind::PowerShell | ShellKind::Cmd) {
+            return vec![command];
+        }
+        split_statements(&command.command)
+            .into_iter()
+            .filter_map(|v| self.statement(v))
+            .map(|(text, blocks)| DocumentCommand {
+                command: text,
+                blocks,
+                ..command.clone()
+            })
+            .collect()
+    }
+
+    /// The command of a statement with the blocks it runs in. Conditions run in the
+    /// blocks around their `if` or `while`.
+    fn statement(&mut self, statement: &str) -> Option<(String, Vec<String>)> {
+        let mut text = statement.trim();
+        loop {
+            let (word, rest) = first_word(text);
+            match word {
+                "then" | "do" => {}
+                "else" => {
+                    if let Some(top) = self.blocks.last_mut() {
+                        if top.starts_with("if ") || top.starts_with("elif ") {
+                            *top = format!("else of {top}");
+                        }
+                    }
+                }
+                "{" => {
+                    if !std::mem::take(&mut self.function_header) {
+                        self.blocks.push("{ ... }".to_string());
+                    }
+                }
+                "fi" | "done" | "esac" | "}" => {
+                    self.close(word);
+                    return None;
+                }
+                _ => break,
+            }
+            text = rest;
+        }
+        if text.is_empty() {
+            return None;
+        }
+        let outer = self.blocks.clone();
+        let (word, rest) = first_word(text);
+        match word {
+            "if" | "elif" | "while" | "until" => {
+                if word == "elif" {
+                    self.blocks.pop();
+                }
+                self.blocks.push(format!("{word} {rest}"));
+                return Some((rest.to_string(), outer));
+            }
+            "for" | "select" => {
+                self.blocks.push(text.to_string());
+                return None;
+            }
+            "case" => {
+                let subject = rest.strip_suffix(" in").unwrap_or(rest);
+                self.blocks.push(format!("case {subject}"));
+                return None;
+            }
+            "function" => {
+                let (name, rest) = first_word(rest);
+                return self.open_function(name.trim_end_matches("()"), rest);
+            }
+            _ => {}
+        }
+        if let Some((name, rest)) = text.split_once("()") {
+            let name = name.trim();
+            if !name.is_empty() && !name.contains(char::is_whitespace) {
+                return self.open_function(name, rest);
+            }
+        }
+        // `start)` opens an arm of the case, up to the next pattern or `esac`
+        if self.in_case() {
+            if let Some((pattern, body)) = text.split_once(')') {
+                let pattern = pattern.trim();
+                if !pattern.contains(['(', ' ']) {
+                    if self.in_case_arm() {
+                        self.blocks.pop();
+                    }
+                    self.blocks.push(format!("{pattern})"));
+                    let body = body.trim();
+                    return (!body.is_empty()).then(|| (body.to_string(), self.blocks.clone()));
+                }
+            }
+        }
+        Some((text.to_string(), outer))
+    }
+
+    /// `name() {` or `function name {`, with the body when it's on the same line.
+    fn open_function(&mut self, name: &str, rest: &str) -> Option<(String, Vec<String>)> {
+        self.blocks.push(format!("function {name}"));
+        let Some(body) = rest.trim().strip_prefix('{') else {
+            self.function_header = true;
+            return None;
+        };
+        self.function_header = false;
+        let body = body.trim();
+        (!body.is_empty()).then(|| (body.to_string(), self.blocks.clone()))
+    }
+
+    /// Pops the block `keyword` ends, when it's the one open. A stray `}` of a
+    /// `{ ...; }` group inside a chain leaves the blocks alone.
+    fn close(&mut self, keyword: &str) {
+        if keyword == "esac" && self.in_case_arm() {
+            self.blocks.pop();
+        }
+        let Some(top) = self.blocks.last() else {
+            return;
+        };
+        let closes = match keyword {
+            "fi" => ["if ", "elif ", "else of "]
+                .iter()
+                .any(|v| top.starts_with(v)),
+            "done" => ["for ", "while ", "until ", "select "]
+                .iter()
+                .any(|v| top.starts_with(v)),
+            "esac" => top.starts_with("case "),
+            _ => top.starts_with("function ") || top == "{ ... }",
+        };
+        if closes {
+            self.blocks.pop();
+        }
+    }
+
+    fn in_case(&self) -> bool {
+        self.blocks.last().is_some_and(|v| v.starts_with("case ")) || self.in_case_arm()
+    }
+
+    fn in_case_arm(&self) -> bool {
+        match self.blocks.as_slice() {
+            [.., case, arm] => case.starts_with("case ") && arm.ends_with(')'),
+            _ => false,
+        }
+    }
+}
+
+fn first_word(text: &str) -> (&str, &str) {
+    let text = text.trim();
+    match text.split_once(char::is_whitespace) {
+        Some((word, rest)) => (word, rest.trim()),
+        None => (text, ""),
+    }
+}
+
+/// A line split at the top-level `;` and `;;`, keeping `&&` chains, pipelines and
+/// subshells whole.
+fn split_statements(text: &str) -> Vec<&str> {
+    let mut output = vec![];
+    let mut start = 0;
+    let mut depth = 0usize;
+    for token in tokenize(text, PathStyle::Unix) {
+        match (token.is_word(), token.raw(text)) {
+            (false, "(") => depth += 1,
+            (false, ")") => depth = depth.saturating_sub(1),
+            (false, ";" | ";;") if depth == 0 => {
+                output.push(text[start..token.span.start].trim());
+                start = token.span.end;
+            }
+            _ => {}
+        }
+    }
+    output.push(text[start..].trim());
+    output.retain(|v| !v.is_empty());
+    output
+}
+
+fn heredoc_terminator(command: &str) -> Option<String> {
+    let (_, rest) = command.split_once("<<")?;
+    if rest.starts_with('<') {
+        return None; // here-string
+    }
+    let word = rest.trim_start_matches('-').trim_start();
+    let word: String = word
+        .chars()
+        .take_while(|c| !c.is_whitespace() && !matches!(c, ';' | '|' | '&' | '>' | ')'))
+        .filter(|c| !matches!(c, '\'' | '"'))
+        .collect();
+    (!word.is_empty()).then_some(word)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_extract_script_commands() {
+        let script = "#!/bin/sh\n# clean up\nrm -rf build \\\n  dist\ncat > a.txt <<'EOF'\nrm -rf /\nEOF\nls\n";
+        let commands = extract_document_commands(script, "shellscript");
+        let commands: Vec<_> = commands
+            .iter()
+            .map(|v| (v.line, v.end_line, v.command.as_str()))
+            .collect();
+        assert_eq!(
+            commands,
+            [
+                (2, 3, "rm -rf build dist"),
+                (4, 4, "cat > a.txt <<'EOF'"),
+                (7, 7, "ls"),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_script_blocks() {
+        let script = r#"#!/bin/bash
+cleanup() {
+  if [ -d "$BUILD" ]; then
+    rm -rf "$BUILD"
+  else
+    echo nothing
+  fi
+}
+for f in *.log; do gzip "$f"; done
+case "$1" in
+  start) systemctl start app ;;
+  purge)
+    rm -rf /var/lib/app
+    ;;
+esac
+if [ -f .env ]; then source .env; fi
+"#;
+        let commands: Vec<_> = extract_document_commands(script, "shellscript")
+            .into_iter()
+            .map(|v| (v.line, v.command, v.blocks.join(" > ")))
+            .collect();
+        let expected = [
+            (2, r#"[ -d "$BUILD" ]"#, "function cleanup"),
+            (
+                3,
+                r#"rm -rf "$BUILD""#,
+                r#"function cleanup > if [ -d "$BUILD" ]"#,
+            ),
+            (
+                5,
+                "echo nothing",
+                r#"function cleanup > else of if [ -d "$BUILD" ]"#,
+            ),
+            (8, r#"gzip "$f""#, "for f in *.log"),
+            (10, "systemctl start app", r#"case "$1" > start)"#),
+            (12, "rm -rf /var/lib/app", r#"case "$1" > purge)"#),
+            (15, "[ -f .env ]", ""),
+            (15, "source .env", "if [ -f .env ]"),
+        ];
+        let expected: Vec<_> = expected
+            .iter()
+            .map(|(line, command, blocks)| (*line, command.to_string(), blocks.to_string()))
+            .collect();
+        assert_eq!(commands, expected);
+    }
+
+    #[test]
+    fn test_script_report() {
+        let script = "#!/bin/sh\nset -e\ndeploy() { rsync -a --delete dist/ /var/www/; }\ncurl -fsSL https://example.com/hook\n";
+        let report = ScriptReport::analyze("deploy.sh", script);
+        assert_eq!(report.statements.len(), 3);
+        assert_eq!(report.safety_level(), SafetyLevel::Dangerous);
+        let (command, _) = report.findings().next().unwrap();
+        assert_eq!(
+            (command.line, command.blocks.as_slice()),
+            (2, &["function deploy".to_string()][..])
+        );
+        assert_eq!(report.to_json()["statements"][1]["line"], 3);
+        assert!(report.display().contains("in function deploy"));
+    }
+
+    #[test]
+    fn test_extract_markdown_commands() {
+        let markdown = "# Setup\n```bash\nmake install\n```\n\n```console\n$ rm -rf ~/.cache\nremoved\n```\n```python\nimport os\n```\n";
+        let commands = extract_document_commands(markdown, "markdown");
+        let commands: Vec<_> = commands
+            .iter()
+            .map(|v| (v.line, v.command.as_str()))
+            .collect();
+        assert_eq!(commands, [(2, "make install"), (6, "rm -rf ~/.cache")]);
+    }
+}
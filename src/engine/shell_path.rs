@@ -0,0 +1,264 @@
+use super::{expand_variables, FileSystem, OSKind};
+
+use std::path::{Path, PathBuf};
+
+/// Path conventions used to interpret file arguments inside shell commands.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    Unix,
+    Windows,
+}
+
+impl PathStyle {
+    pub fn from_os(os: &OSKind) -> Self {
+        match os {
+            OSKind::Windows => PathStyle::Windows,
+            _ => PathStyle::Unix,
+        }
+    }
+
+    pub fn current() -> Self {
+        Self::from_os(&OSKind::detect())
+    }
+
+    pub(super) fn is_separator(&self, c: char) -> bool {
+        match self {
+            PathStyle::Unix => c == '/',
+            PathStyle::Windows => c == '/' || c == '\\',
+        }
+    }
+
+    fn separator(&self) -> char {
+        match self {
+            PathStyle::Unix => '/',
+            PathStyle::Windows => '\\',
+        }
+    }
+}
+
+const UNIX_PROTECTED_PATHS: [&str; 13] = [
+    "/", "/bin", "/boot", "/dev", "/etc", "/home", "/lib", "/proc", "/root", "/sbin", "/sys",
+    "/usr", "/var",
+];
+
+const WINDOWS_PROTECTED_PATHS: [&str; 6] = [
+    "\\",
+    "\\Windows",
+    "\\Program Files",
+    "\\Program Files (x86)",
+    "\\ProgramData",
+    "\\Users",
+];
+
+/// Split a path into its prefix (`C:`, `\\server\share`, or empty) and the rest.
+fn split_prefix(path: &str, style: PathStyle) -> (String, &str) {
+    if style == PathStyle::Unix {
+        return (String::new(), path);
+    }
+    let bytes = path.as_bytes();
+    if path.len() > 2
+        && style.is_separator(bytes[0] as char)
+        && style.is_separator(bytes[1] as char)
+    {
+        // UNC path: \\server\share\rest
+        let rest = &path[2..];
+        let mut parts = rest.splitn(3, |c| style.is_separator(c));
+        let server = parts.next().unwrap_or_default();
+        let share = parts.next().unwrap_or_default();
+        let prefix = format!("\\\\{server}\\{share}");
+        let consumed = 2 + server.len() + share.len() + usize::from(!share.is_empty());
+        return (prefix, &path[consumed.min(path.len())..]);
+    }
+    if bytes.len() >= 2 && bytes[0].is_ascii_alphabetic() && bytes[1] == b':' {
+        let drive = format!("{}:", (bytes[0] as char).to_ascii_uppercase());
+        return (drive, &path[2..]);
+    }
+    (String::new(), path)
+}
+
+/// Lexically normalize a path: unify separators, resolve `.` and `..`, and anchor
+/// relative and drive-relative paths at `cwd`.
+pub fn normalize_path(path: &str, cwd: &Path, style: PathStyle) -> String {
+    let sep = style.separator();
+    let (mut prefix, rest) = split_prefix(path, style);
+    let absolute = rest.starts_with(|c| style.is_separator(c));
+    let cwd = cwd.to_string_lossy();
+    let (cwd_prefix, cwd_rest) = split_prefix(&cwd, style);
+
+    let mut components: Vec<&str> = vec![];
+    if !absolute {
+        // Relative (`x`) or drive-relative (`C:x`) paths resolve against the cwd,
+        // the latter only when the cwd is on the same drive.
+        if prefix.is_empty() || prefix.eq_ignore_ascii_case(&cwd_prefix) {
+            prefix = cwd_prefix.clone();
+            components.extend(cwd_rest.split(|c| style.is_separator(c)));
+        }
+    } else if prefix.is_empty() && style == PathStyle::Windows {
+        // Root-relative (`\x`) paths live on the cwd's drive.
+        prefix = cwd_prefix.clone();
+    }
+    components.extend(rest.split(|c| style.is_separator(c)));
+
+    let mut parts: Vec<&str> = vec![];
+    for component in components {
+        match component {
+            "" | "." => {}
+            ".." => {
+                parts.pop();
+            }
+            v => parts.push(v),
+        }
+    }
+    format!("{prefix}{sep}{}", parts.join(&sep.to_string()))
+}
+
+/// Compare two normalized paths, case-insensitively on Windows.
+pub fn paths_equal(a: &str, b: &str, style: PathStyle) -> bool {
+    match style {
+        PathStyle::Unix => a == b,
+        PathStyle::Windows => a.to_lowercase() == b.to_lowercase(),
+    }
+}
+
+/// Whether the normalized `path` is `dir` or inside it, case-insensitively on Windows.
+pub fn path_within(path: &str, dir: &str, style: PathStyle) -> bool {
+    let dir = dir.trim_end_matches(|c| style.is_separator(c));
+    let (path, dir) = match style {
+        PathStyle::Unix => (path.to_string(), dir.to_string()),
+        PathStyle::Windows => (path.to_lowercase(), dir.to_lowercase()),
+    };
+    match path.strip_prefix(&dir) {
+        Some(rest) => rest.is_empty() || rest.starts_with(|c| style.is_separator(c)),
+        None => false,
+    }
+}
+
+/// Whether the command argument targets a system root such as `/`, `/etc/*` or `C:\Windows`.
+pub fn is_protected_path(word: &str, style: PathStyle) -> bool {
+    if !word.starts_with(|c| style.is_separator(c)) && split_prefix(word, style).0.is_empty() {
+        return false;
+    }
+    let word = word
+        .trim_end_matches('*')
+        .trim_end_matches(|c| style.is_separator(c));
+    let sep = style.separator();
+    match style {
+        PathStyle::Unix => {
+            let word = if word.is_empty() { "/" } else { word };
+            UNIX_PROTECTED_PATHS.contains(&word)
+        }
+        PathStyle::Windows => {
+            let (prefix, rest) = split_prefix(word, style);
+            if prefix.starts_with("\\\\") {
+                return rest.is_empty();
+            }
+            if prefix.is_empty() && !rest.starts_with(|c| style.is_separator(c)) {
+                return false;
+            }
+            let rest = rest.replace('/', &sep.to_string());
+            let rest = if rest.is_empty() {
+                sep.to_string()
+            } else {
+                rest
+            };
+            WINDOWS_PROTECTED_PATHS
+                .iter()
+                .any(|v| paths_equal(v, &rest, style))
+        }
+    }
+}
+
+/// Normalize a command argument into an absolute path in the given style, relative to the
+/// working directory of `fs`, with its variables and `~` expanded.
+pub fn resolve_command_path(word: &str, style: PathStyle, fs: &dyn FileSystem) -> PathBuf {
+    PathBuf::from(normalize_path(
+        &expand_tilde(&expand_variables(word, fs), fs),
+        &fs.current_dir(),
+        style,
+    ))
+}
+
+/// `~` and `~/x` under the home directory of `fs`, like the shell expands an unquoted word.
+/// `~user` and words without a home directory stay as they are.
+pub fn expand_tilde(word: &str, fs: &dyn FileSystem) -> String {
+    let Some(rest) = word
+        .strip_prefix('~')
+        .filter(|v| v.is_empty() || v.starts_with(['/', '\\']))
+    else {
+        return word.to_string();
+    };
+    match fs.home_dir() {
+        Some(home) => format!("{}{rest}", home.display()),
+        None => word.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_normalize_windows_path() {
+        let cwd = Path::new(r"C:\Users\me");
+        let style = PathStyle::Windows;
+        assert_eq!(
+            normalize_path(r"c:\Users\me\..\me\file.txt", cwd, style),
+            r"C:\Users\me\file.txt"
+        );
+        assert_eq!(
+            normalize_path("file.txt", cwd, style),
+            r"C:\Users\me\file.txt"
+        );
+        assert_eq!(
+            normalize_path("C:file.txt", cwd, style),
+            r"C:\Users\me\file.txt"
+        );
+        assert_eq!(normalize_path("D:file.txt", cwd, style), r"D:\file.txt");
+        assert_eq!(normalize_path(r"\tmp\x", cwd, style), r"C:\tmp\x");
+        assert_eq!(
+            normalize_path(r"\\server\share\dir/x.txt", cwd, style),
+            r"\\server\share\dir\x.txt"
+        );
+    }
+
+    #[test]
+    fn test_normalize_unix_path() {
+        let cwd = Path::new("/home/me");
+        assert_eq!(
+            normalize_path("./a/../b.txt", cwd, PathStyle::Unix),
+            "/home/me/b.txt"
+        );
+        assert_eq!(
+            normalize_path("/etc//hosts", cwd, PathStyle::Unix),
+            "/etc/hosts"
+        );
+    }
+
+    #[test]
+    fn test_paths_equal() {
+        assert!(paths_equal(
+            r"C:\Users\Me",
+            r"c:\users\me",
+            PathStyle::Windows
+        ));
+        assert!(!paths_equal("/home/Me", "/home/me", PathStyle::Unix));
+    }
+
+    #[test]
+    fn test_is_protected_path() {
+        assert!(is_protected_path("/", PathStyle::Unix));
+        assert!(is_protected_path("/*", PathStyle::Unix));
+        assert!(is_protected_path("/etc/", PathStyle::Unix));
+        assert!(!is_protected_path("/etc/hosts", PathStyle::Unix));
+        assert!(is_protected_path(r"C:\", PathStyle::Windows));
+        assert!(is_protected_path(r"c:\windows\*", PathStyle::Windows));
+        assert!(is_protected_path("C:/Program Files", PathStyle::Windows));
+        assert!(is_protected_path(r"\\server\share", PathStyle::Windows));
+        assert!(!is_protected_path(
+            r"C:\Users\me\file.txt",
+            PathStyle::Windows
+        ));
+        assert!(!is_protected_path("file.txt", PathStyle::Windows));
+        assert!(!is_protected_path("*", PathStyle::Unix));
+    }
+}
@@ -0,0 +1,277 @@
+use super::*;
+
+use fancy_regex::Regex;
+use parking_lot::Mutex;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    io::Write,
+    process::{Command, Stdio},
+    sync::LazyLock,
+};
+
+static LS_LOOP_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\bfor\s+\w+\s+in\s+(\$\(|`)\s*ls\b").unwrap());
+
+/// `shellcheck` runs once per command line and shell, the analysis runs again and again.
+static SHELLCHECK_CACHE: LazyLock<Mutex<ShellCheckCache>> = LazyLock::new(Default::default);
+
+type ShellCheckCache = HashMap<(String, &'static str), Vec<ShellIssue>>;
+
+/// A quoting, word splitting or other bug ShellCheck, or the built-in pass standing in
+/// for it, finds in a command.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShellIssue {
+    /// The ShellCheck code, 2086 for SC2086.
+    pub code: u32,
+    /// `error`, `warning`, `info` or `style`.
+    pub level: String,
+    pub message: String,
+    /// Where in the command it is, counting from 1.
+    pub column: Option<usize>,
+}
+
+impl ShellIssue {
+    fn new(code: u32, level: &str, message: &str, column: Option<usize>) -> Self {
+        Self {
+            code,
+            level: level.to_string(),
+            message: message.to_string(),
+            column,
+        }
+    }
+
+    /// `SC2086 (info, col 4): Double quote to prevent globbing and word splitting.`
+    pub fn describe(&self) -> String {
+        match self.column {
+            Some(column) => format!(
+                "SC{} ({}, col {column}): {}",
+                self.code, self.level, self.message
+            ),
+            None => format!("SC{} ({}): {}", self.code, self.level, self.message),
+        }
+    }
+}
+
+/// The ShellCheck dialect of `shell`, `None` for shells it can't check.
+fn shellcheck_dialect(shell: &ShellKind) -> Option<&'static str> {
+    match shell {
+        ShellKind::Bash | ShellKind::Msys | ShellKind::Unknown => Some("bash"),
+        // Close enough for one-liners, zsh-only syntax shows up as parse errors
+        ShellKind::Zsh => Some("bash"),
+        _ => None,
+    }
+}
+
+/// The findings of `shellcheck` on `command`, `None` when it isn't installed or the shell
+/// is one it can't check.
+pub fn run_shellcheck(command: &str, shell: &ShellKind) -> Option<Vec<ShellIssue>> {
+    let dialect = shellcheck_dialect(shell)?;
+    let key = (command.to_string(), dialect);
+    if let Some(issues) = SHELLCHECK_CACHE.lock().get(&key) {
+        return Some(issues.clone());
+    }
+    let mut child = Command::new("shellcheck")
+        .args(["--format=json1", "--shell", dialect, "-"])
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .ok()?;
+    child
+        .stdin
+        .take()?
+        .write_all(format!("{command}\n").as_bytes())
+        .ok()?;
+    let output = child.wait_with_output().ok()?;
+    let issues = parse_shellcheck_output(&String::from_utf8_lossy(&output.stdout))?;
+    SHELLCHECK_CACHE.lock().insert(key, issues.clone());
+    Some(issues)
+}
+
+/// The comments of `shellcheck --format=json1`. Multi-line commands report their line in
+/// the column.
+pub fn parse_shellcheck_output(output: &str) -> Option<Vec<ShellIssue>> {
+    #[derive(Deserialize)]
+    struct Report {
+        comments: Vec<Comment>,
+    }
+    #[derive(Deserialize)]
+    struct Comment {
+        line: usize,
+        column: usize,
+        level: String,
+        code: u32,
+        message: String,
+    }
+    let report: Report = serde_json::from_str(output).ok()?;
+    Some(
+        report
+            .comments
+            .into_iter()
+            .map(|v| {
+                let column = (v.line == 1).then_some(v.column);
+                ShellIssue::new(v.code, &v.level, &v.message, column)
+            })
+            .collect(),
+    )
+}
+
+/// A few ShellCheck checks for when it isn't installed, the ones that turn a command
+/// into something else than it reads: unquoted expansions, `rm` of `$VAR/` that may be
+/// `/`, an unchecked `cd` and looping over `ls`.
+pub fn lint_shell_command(command: &str, shell: &ShellKind) -> Vec<ShellIssue> {
+    if shellcheck_dialect(shell).is_none() {
+        return vec![];
+    }
+    let mut issues = vec![];
+    let tokens = tokenize(command, PathStyle::Unix);
+    let mut tool: Option<&str> = None;
+    let mut after_cd = false;
+    for (i, token) in tokens.iter().enumerate() {
+        if !token.is_word() {
+            if token.is_control() {
+                tool = None;
+            }
+            // `cd dir; rm -rf *` runs in the wrong directory when cd fails
+            if after_cd && matches!(token.text.as_str(), ";" | "\n") {
+                let more = tokens[i + 1..].iter().any(|v| v.is_word());
+                if more {
+                    issues.push(ShellIssue::new(
+                        2164,
+                        "warning",
+                        "Use 'cd ... || exit' or 'cd ... && ...' in case cd fails.",
+                        None,
+                    ));
+                }
+            }
+            if token.is_control() {
+                after_cd = false;
+            }
+            continue;
+        }
+        let raw = token.raw(command);
+        let column = Some(command[..token.span.start].chars().count() + 1);
+        let Some(name) = tool else {
+            // Assignments and the command word don't split
+            if !token.text.contains('=') || token.quoted {
+                tool = Some(token.text.as_str());
+                after_cd = token.text == "cd";
+            }
+            continue;
+        };
+        // The words of a `for` loop are meant to split
+        if name == "for" {
+            continue;
+        }
+        let (variable, substitution) = unquoted_expansions(raw);
+        if name == "rm" && (raw.starts_with("$") || raw.starts_with("\"$")) {
+            let unguarded = !raw.contains(":?") && raw.contains('/');
+            if unguarded {
+                issues.push(ShellIssue::new(
+                    2115,
+                    "warning",
+                    "Use \"${var:?}\" to ensure this never expands to / .",
+                    column,
+                ));
+            }
+        }
+        if substitution {
+            issues.push(ShellIssue::new(
+                2046,
+                "warning",
+                "Quote this to prevent word splitting.",
+                column,
+            ));
+        } else if variable {
+            issues.push(ShellIssue::new(
+                2086,
+                "info",
+                "Double quote to prevent globbing and word splitting.",
+                column,
+            ));
+        }
+    }
+    if let Ok(Some(found)) = LS_LOOP_RE.find(command) {
+        issues.push(ShellIssue::new(
+            2045,
+            "error",
+            "Iterating over ls output is fragile. Use globs.",
+            Some(command[..found.start()].chars().count() + 1),
+        ));
+    }
+    issues
+}
+
+/// Whether `raw` has a `$VAR`/`${VAR}` and a `$(...)`/backtick substitution outside of
+/// quotes.
+fn unquoted_expansions(raw: &str) -> (bool, bool) {
+    let chars: Vec<char> = raw.chars().collect();
+    let (mut variable, mut substitution) = (false, false);
+    let (mut single, mut double) = (false, false);
+    let mut i = 0;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' if !single => i += 1,
+            '\'' if !double => single = !single,
+            '"' if !single => double = !double,
+            '`' if !single && !double => substitution = true,
+            '$' if !single && !double => match chars.get(i + 1) {
+                Some('(') => substitution = true,
+                Some(c) if c.is_ascii_alphanumeric() || matches!(c, '_' | '{' | '@' | '*') => {
+                    variable = true
+                }
+                _ => {}
+            },
+            _ => {}
+        }
+        i += 1;
+    }
+    (variable, substitution)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn codes(command: &str) -> Vec<u32> {
+        lint_shell_command(command, &ShellKind::Bash)
+            .iter()
+            .map(|v| v.code)
+            .collect()
+    }
+
+    #[test]
+    fn test_lint_shell_command() {
+        assert_eq!(codes("rm -rf $BUILD_DIR/"), [2115, 2086]);
+        assert_eq!(codes(r#"rm -rf "${BUILD_DIR:?}/""#), Vec::<u32>::new());
+        assert_eq!(codes(r#"cp "$SRC" "$DEST""#), Vec::<u32>::new());
+        assert_eq!(codes("chmod 644 $(find . -name '*.conf')"), [2046]);
+        assert_eq!(codes("cd /tmp/build; rm -rf *"), [2164]);
+        assert!(codes("cd /tmp/build && rm -rf *").is_empty());
+        assert_eq!(codes("for f in $(ls *.log); do gzip \"$f\"; done"), [2045]);
+        assert!(codes("DIR=$HOME/x make install").is_empty());
+        assert!(lint_shell_command("rm $x", &ShellKind::PowerShell).is_empty());
+
+        let issue = &lint_shell_command("echo $HOME", &ShellKind::Bash)[0];
+        assert_eq!(
+            issue.describe(),
+            "SC2086 (info, col 6): Double quote to prevent globbing and word splitting."
+        );
+    }
+
+    #[test]
+    fn test_parse_shellcheck_output() {
+        let output = r#"{"comments":[{"file":"-","line":1,"endLine":1,"column":8,"endColumn":12,"level":"info","code":2086,"message":"Double quote to prevent globbing and word splitting.","fix":null}]}"#;
+        assert_eq!(
+            parse_shellcheck_output(output).unwrap(),
+            [ShellIssue::new(
+                2086,
+                "info",
+                "Double quote to prevent globbing and word splitting.",
+                Some(8)
+            )]
+        );
+        assert!(parse_shellcheck_output("not json").is_none());
+    }
+}
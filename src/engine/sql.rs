@@ -0,0 +1,251 @@
+use super::*;
+
+use std::{fs, path::Path};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum SqlKind {
+    Select,
+    Insert,
+    Update,
+    Delete,
+    Drop,
+    Truncate,
+    Alter,
+    Create,
+    Other,
+}
+
+/// One statement from a `psql -c`, `mysql -e`, `sqlite3 db "..."` or `.sql` file input.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SqlStatement {
+    pub kind: SqlKind,
+    /// The table (or database/schema for DROP) the statement targets.
+    pub target: Option<String>,
+    pub has_where: bool,
+    /// The object type of DROP statements, e.g. `TABLE` or `DATABASE`.
+    pub object: Option<String>,
+}
+
+impl SqlStatement {
+    pub fn parse(sql: &str) -> Option<Self> {
+        let words: Vec<String> = sql
+            .split_whitespace()
+            .map(|v| v.trim_matches(|c| c == '(' || c == ')').to_string())
+            .collect();
+        let upper: Vec<String> = words.iter().map(|v| v.to_uppercase()).collect();
+        let first = upper.first()?;
+        let word_after = |keyword: &str| {
+            upper
+                .iter()
+                .position(|v| v == keyword)
+                .and_then(|i| words.get(i + 1))
+                .map(|v| v.trim_end_matches(';').to_string())
+        };
+        let has_where = upper.iter().any(|v| v == "WHERE");
+        let (kind, target, object) = match first.as_str() {
+            "SELECT" | "WITH" | "SHOW" | "EXPLAIN" | "DESCRIBE" => (SqlKind::Select, None, None),
+            "INSERT" => (SqlKind::Insert, word_after("INTO"), None),
+            "UPDATE" => (SqlKind::Update, words.get(1).cloned(), None),
+            "DELETE" => (SqlKind::Delete, word_after("FROM"), None),
+            "TRUNCATE" => {
+                let target = match upper.get(1).map(|v| v.as_str()) {
+                    Some("TABLE") => words.get(2).cloned(),
+                    _ => words.get(1).cloned(),
+                };
+                (SqlKind::Truncate, target, None)
+            }
+            "DROP" => {
+                let object = upper.get(1).cloned();
+                let mut i = 2;
+                if upper.get(i).map(|v| v.as_str()) == Some("IF") {
+                    i += 2;
+                }
+                (SqlKind::Drop, words.get(i).cloned(), object)
+            }
+            "ALTER" => (SqlKind::Alter, words.get(2).cloned(), upper.get(1).cloned()),
+            "CREATE" => (SqlKind::Create, None, None),
+            _ => (SqlKind::Other, None, None),
+        };
+        Some(Self {
+            kind,
+            target: target.map(|v| v.trim_end_matches(';').to_string()),
+            has_where,
+            object,
+        })
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        match self.kind {
+            SqlKind::Select => CommandOperation::Read,
+            SqlKind::Delete | SqlKind::Drop | SqlKind::Truncate => CommandOperation::Delete,
+            SqlKind::Update | SqlKind::Insert | SqlKind::Alter => CommandOperation::Modify,
+            SqlKind::Create => CommandOperation::Create,
+            SqlKind::Other => CommandOperation::Unknown,
+        }
+    }
+
+    /// Dropping a whole database or schema is critical, not just dangerous.
+    pub fn is_critical(&self) -> bool {
+        self.kind == SqlKind::Drop && matches!(self.object.as_deref(), Some("DATABASE" | "SCHEMA"))
+    }
+
+    /// A warning describing the estimated scope, for statements that lose data.
+    pub fn risk(&self) -> Option<String> {
+        let target = self.target.as_deref().unwrap_or("<unknown>");
+        match self.kind {
+            SqlKind::Delete if !self.has_where => Some(format!(
+                "DELETE without WHERE removes every row in {target}"
+            )),
+            SqlKind::Update if !self.has_where => Some(format!(
+                "UPDATE without WHERE rewrites every row in {target}"
+            )),
+            SqlKind::Truncate => Some(format!("TRUNCATE removes every row in {target}")),
+            SqlKind::Drop => {
+                let object = self.object.as_deref().unwrap_or("object");
+                Some(format!(
+                    "DROP {object} {target} removes it along with all its data"
+                ))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Split SQL text into statements, ignoring `--` and `/* */` comments and `;` in strings.
+pub fn parse_sql(sql: &str) -> Vec<SqlStatement> {
+    let mut statements = vec![];
+    let mut current = String::new();
+    let mut quote: Option<char> = None;
+    let chars: Vec<char> = sql.chars().collect();
+    let mut i = 0;
+    while i < chars.len() {
+        let c = chars[i];
+        match quote {
+            Some(q) => {
+                if c == q {
+                    quote = None;
+                }
+                current.push(c);
+            }
+            None => match c {
+                '\'' | '"' | '`' => {
+                    quote = Some(c);
+                    current.push(c);
+                }
+                '-' if chars.get(i + 1) == Some(&'-') => {
+                    while i < chars.len() && chars[i] != '\n' {
+                        i += 1;
+                    }
+                    current.push(' ');
+                }
+                '/' if chars.get(i + 1) == Some(&'*') => {
+                    i += 2;
+                    while i + 1 < chars.len() && !(chars[i] == '*' && chars[i + 1] == '/') {
+                        i += 1;
+                    }
+                    i += 1;
+                    current.push(' ');
+                }
+                ';' => statements.push(std::mem::take(&mut current)),
+                _ => current.push(c),
+            },
+        }
+        i += 1;
+    }
+    statements.push(current);
+    statements
+        .iter()
+        .filter_map(|v| SqlStatement::parse(v))
+        .collect()
+}
+
+/// Extract the SQL a database client command will run: inline `-c`/`-e`/`--command`
+/// statements, `-f` files, `< file.sql` redirection, or sqlite3's positional SQL.
+pub fn extract_sql(segment: &str) -> Option<String> {
+    let words = shell_words::split(segment).ok()?;
+    let client = words.first()?.as_str();
+    let inline_flags: &[&str] = match client {
+        "psql" => &["-c", "--command"],
+        "mysql" | "mariadb" => &["-e", "--execute"],
+        "sqlcmd" => &["-Q", "-q"],
+        "sqlite3" => &[],
+        _ => return None,
+    };
+    let file_flags: &[&str] = match client {
+        "psql" => &["-f", "--file"],
+        "sqlcmd" => &["-i"],
+        _ => &[],
+    };
+
+    let mut sql = vec![];
+    let mut positional = vec![];
+    let mut iter = words[1..].iter();
+    while let Some(word) = iter.next() {
+        let (flag, inline) = match word.split_once('=') {
+            Some((flag, value)) if word.starts_with("--") => (flag, Some(value.to_string())),
+            _ => (word.as_str(), None),
+        };
+        let mut value = || inline.clone().or_else(|| iter.next().cloned());
+        if inline_flags.contains(&flag) {
+            sql.extend(value());
+        } else if file_flags.contains(&flag) || flag == "<" {
+            sql.extend(value().and_then(|v| fs::read_to_string(Path::new(&v)).ok()));
+        } else if let Some(file) = word.strip_prefix('<').filter(|v| !v.is_empty()) {
+            sql.extend(fs::read_to_string(Path::new(file)).ok());
+        } else if !word.starts_with('-') {
+            positional.push(word.clone());
+        }
+    }
+    // sqlite3 DB "SQL"
+    if client == "sqlite3" && positional.len() > 1 {
+        sql.push(positional[1..].join(" "));
+    }
+    match sql.is_empty() {
+        true => None,
+        false => Some(sql.join(";\n")),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_sql() {
+        let statements = parse_sql(
+            "-- cleanup\nDELETE FROM users; UPDATE orders SET paid = 1 WHERE id = 3;\n/* ; */ DROP TABLE IF EXISTS logs; SELECT ';'",
+        );
+        assert_eq!(statements.len(), 4);
+        assert_eq!(statements[0].kind, SqlKind::Delete);
+        assert_eq!(statements[0].target.as_deref(), Some("users"));
+        assert!(statements[0].risk().is_some());
+        assert!(statements[1].has_where);
+        assert!(statements[1].risk().is_none());
+        assert_eq!(statements[2].target.as_deref(), Some("logs"));
+        assert_eq!(statements[3].kind, SqlKind::Select);
+        assert!(parse_sql("DROP DATABASE app")[0].is_critical());
+    }
+
+    #[test]
+    fn test_extract_sql() {
+        assert_eq!(
+            extract_sql(r#"psql -d app -c "DELETE FROM users""#).as_deref(),
+            Some("DELETE FROM users")
+        );
+        assert_eq!(
+            extract_sql("mysql --execute='TRUNCATE sessions' app").as_deref(),
+            Some("TRUNCATE sessions")
+        );
+        assert_eq!(
+            extract_sql(r#"sqlite3 app.db "DROP TABLE t""#).as_deref(),
+            Some("DROP TABLE t")
+        );
+        assert_eq!(extract_sql("psql -d app"), None);
+
+        let path = std::env::temp_dir().join(format!("aichat-{}.sql", std::process::id()));
+        fs::write(&path, "TRUNCATE TABLE events;").unwrap();
+        let sql = extract_sql(&format!("mysql app < {}", path.display())).unwrap();
+        assert_eq!(parse_sql(&sql)[0].kind, SqlKind::Truncate);
+        let _ = fs::remove_file(&path);
+    }
+}
@@ -0,0 +1,146 @@
+use super::*;
+
+use serde::Serialize;
+
+/// Analyze a command for the current shell and platform against the local disk. The
+/// REPL, the execute flow and embedders share this one [`CommandAnalysis`] and read it
+/// through its accessors rather than the display text; [`SafetyEngine`] picks another
+/// shell, platform or file system.
+pub fn analyze(command: &str) -> CommandAnalysis {
+    CommandAnalysis::analyze(command)
+}
+
+/// The paths a command acts on in one way, see [`CommandAnalysis::paths_by_operation`].
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct PathGroup {
+    pub operation: CommandOperation,
+    pub paths: Vec<PathBuf>,
+}
+
+/// Questions the REPL, the execute flow and the server ask of an analysis, answered from
+/// its fields so none of them has to read the display text.
+impl CommandAnalysis {
+    /// The paths the command acts on, grouped by what it does to them, most dangerous
+    /// first. Every chain step and nested command counts with its own operation, written
+    /// files with Write, or Create while they don't exist. A path named more than once is
+    /// in the most dangerous group only.
+    pub fn paths_by_operation(&self) -> Vec<PathGroup> {
+        let mut parts: Vec<(&CommandOperation, &[PathBuf])> = match self.chain.is_empty() {
+            true => vec![(&self.operation, &self.targets)],
+            false => self
+                .chain
+                .iter()
+                .map(|v| (&v.operation, v.targets.as_slice()))
+                .collect(),
+        };
+        parts.extend(
+            self.nested
+                .iter()
+                .map(|v| (&v.operation, v.targets.as_slice())),
+        );
+        let mut found: Vec<(CommandOperation, &PathBuf)> = parts
+            .into_iter()
+            .flat_map(|(operation, paths)| paths.iter().map(|v| (operation.clone(), v)))
+            .collect();
+        for output in &self.outputs {
+            let operation = match output.exists {
+                true => CommandOperation::Write,
+                false => CommandOperation::Create,
+            };
+            found.push((operation, &output.path));
+        }
+        found.sort_by_key(|(operation, _)| std::cmp::Reverse(operation.danger_rank()));
+
+        let mut groups: Vec<PathGroup> = vec![];
+        let mut seen: Vec<&PathBuf> = vec![];
+        for (operation, path) in found {
+            if operation == CommandOperation::Unknown || seen.contains(&path) {
+                continue;
+            }
+            seen.push(path);
+            match groups.iter_mut().find(|v| v.operation == operation) {
+                Some(group) => group.paths.push(path.clone()),
+                None => groups.push(PathGroup {
+                    operation,
+                    paths: vec![path.clone()],
+                }),
+            }
+        }
+        groups
+    }
+
+    /// The worst level of the command and of every chain step and nested command in it.
+    pub fn max_severity(&self) -> SafetyLevel {
+        self.chain
+            .iter()
+            .map(|v| &v.safety_level)
+            .chain(self.nested.iter().map(|v| &v.safety_level))
+            .fold(self.safety_level.clone(), |worst, v| worst.max(v.clone()))
+    }
+
+    /// The existing files to back up before the command runs: the ones it deletes,
+    /// overwrites, modifies or moves. Empty when it leaves files as they are.
+    pub fn required_backups(&self) -> Vec<PathBuf> {
+        match self.operation.needs_backup() {
+            true => self.affected_files.clone(),
+            false => vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_analysis_summary() {
+        let fs = MemoryFs::new()
+            .cwd("/home/me/project")
+            .file("/home/me/project/notes.txt", 100)
+            .file("/home/me/project/old.log", 100)
+            .dir("/home/me/project/build");
+        let analyze = |command: &str| {
+            CommandAnalysis::analyze_with(command, PathStyle::Unix, &ShellKind::Bash, &fs)
+        };
+
+        let analysis =
+            analyze("echo done >> notes.txt && echo hi > summary.txt && rm -rf build old.log");
+        let groups = analysis.paths_by_operation();
+        let groups: Vec<(CommandOperation, Vec<&str>)> = groups
+            .iter()
+            .map(|v| {
+                let paths = v.paths.iter().map(|v| v.to_str().unwrap()).collect();
+                (v.operation.clone(), paths)
+            })
+            .collect();
+        assert_eq!(
+            groups,
+            [
+                (
+                    CommandOperation::Delete,
+                    vec!["/home/me/project/build", "/home/me/project/old.log"]
+                ),
+                (CommandOperation::Write, vec!["/home/me/project/notes.txt"]),
+                (
+                    CommandOperation::Create,
+                    vec!["/home/me/project/summary.txt"]
+                ),
+            ]
+        );
+        assert_eq!(analysis.max_severity(), SafetyLevel::Dangerous);
+        assert_eq!(
+            analysis.required_backups(),
+            [
+                PathBuf::from("/home/me/project/notes.txt"),
+                PathBuf::from("/home/me/project/old.log")
+            ]
+        );
+
+        let analysis = analyze("cat notes.txt");
+        assert!(analysis.required_backups().is_empty());
+        assert_eq!(
+            analysis.paths_by_operation()[0].operation,
+            CommandOperation::Read
+        );
+    }
+}
@@ -0,0 +1,268 @@
+use super::*;
+
+use fancy_regex::Regex;
+use std::{
+    collections::VecDeque,
+    sync::LazyLock,
+    time::{Duration, Instant},
+};
+
+/// Lines of output kept for summaries and for the model.
+const TAIL_LINES: usize = 40;
+/// An error is reported once this many lines followed it, or after [`ERROR_SETTLE`], so the
+/// model sees what the tool printed about it.
+const ERROR_CONTEXT_LINES: usize = 15;
+const ERROR_SETTLE: Duration = Duration::from_secs(2);
+
+static ERROR_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)^\s*(error(\[\w+\])?:|fatal:|fatal error|npm err!|\[error\]|build failed|build failure|traceback \(most recent call last\)|exception in thread|.*\bpanicked at\b|.*\berror TS\d+:|(?-i:.*\bFAILED\b))").unwrap()
+});
+/// `[12/40]`, `45%`, `Step 3/9`, `(7/12)`
+static PROGRESS_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(r"(?i)(\[\s*\d+\s*/\s*\d+\s*\]|\b\d{1,3}(\.\d+)?%|\bstep \d+/\d+|\(\d+/\d+\))")
+        .unwrap()
+});
+
+/// Builds, test suites and database migrations: commands worth watching while they run.
+pub fn is_long_running(command: &str, shell: &ShellKind) -> bool {
+    split_command_segments(command, shell)
+        .iter()
+        .any(|segment| {
+            let words = shell_words::split(segment)
+                .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+            let words: Vec<&str> = words
+                .iter()
+                .map(|v| v.as_str())
+                .skip_while(|v| matches!(*v, "sudo" | "doas" | "time" | "nohup"))
+                .collect();
+            let Some((tool, args)) = words.split_first() else {
+                return false;
+            };
+            let tool = tool.rsplit(['/', '\\']).next().unwrap_or(tool);
+            let has = |names: &[&str]| args.iter().any(|v| names.contains(v));
+            match tool {
+                "make" | "gmake" | "ninja" | "mvn" | "mvnw" | "gradle" | "gradlew" | "msbuild" => {
+                    true
+                }
+                "cargo" => has(&["build", "b", "test", "t", "install", "bench"]),
+                "go" => has(&["build", "test", "install"]),
+                "npm" | "yarn" | "pnpm" | "bun" => has(&["build", "ci", "install", "test"]),
+                "docker" | "podman" | "buildah" | "docker-compose" => has(&["build", "buildx"]),
+                "cmake" => has(&["--build"]),
+                "bazel" | "bazelisk" => has(&["build", "test"]),
+                "dotnet" => has(&["build", "test", "publish", "ef"]),
+                "rails" | "rake" => args.iter().any(|v| v.starts_with("db:migrate")),
+                "python" | "python3" => has(&["manage.py"]) && has(&["migrate"]),
+                "alembic" => has(&["upgrade", "downgrade"]),
+                "prisma" | "flyway" => has(&["migrate"]),
+                "knex" | "sequelize" => args.iter().any(|v| v.contains("migrate")),
+                "diesel" => has(&["migration"]),
+                "liquibase" => has(&["update"]),
+                _ => false,
+            }
+        })
+}
+
+/// Something in a supervised command's output worth asking the model about.
+#[derive(Debug, Clone, PartialEq)]
+pub enum Anomaly {
+    Error(String),
+    /// No output for this long.
+    Stalled(Duration),
+}
+
+/// Watches the output of a running command: what it printed last, how far it got, what
+/// failed and whether it has gone quiet.
+#[derive(Debug, Clone)]
+pub struct OutputMonitor {
+    started: Instant,
+    last_output: Instant,
+    lines: usize,
+    tail: VecDeque<String>,
+    progress: Option<String>,
+    errors: Vec<String>,
+    /// The error waiting for its context lines, with when it came and the lines since.
+    pending_error: Option<(String, Instant, usize)>,
+    stall_reported: bool,
+    exit: Option<(i32, Duration)>,
+}
+
+impl OutputMonitor {
+    pub fn new(now: Instant) -> Self {
+        Self {
+            started: now,
+            last_output: now,
+            lines: 0,
+            tail: VecDeque::new(),
+            progress: None,
+            errors: vec![],
+            pending_error: None,
+            stall_reported: false,
+            exit: None,
+        }
+    }
+
+    pub fn feed(&mut self, line: &str, now: Instant) {
+        let line = line.trim_end();
+        self.lines += 1;
+        self.last_output = now;
+        self.stall_reported = false;
+        self.tail.push_back(line.to_string());
+        if self.tail.len() > TAIL_LINES {
+            self.tail.pop_front();
+        }
+        if PROGRESS_RE.is_match(line).unwrap_or_default() {
+            self.progress = Some(line.trim().to_string());
+        }
+        if let Some((_, _, since)) = self.pending_error.as_mut() {
+            *since += 1;
+        }
+        if ERROR_RE.is_match(line).unwrap_or_default() {
+            self.errors.push(line.trim().to_string());
+            if self.pending_error.is_none() {
+                self.pending_error = Some((line.trim().to_string(), now, 0));
+            }
+        }
+    }
+
+    /// An error once the lines explaining it are in, or the output going quiet for
+    /// `stall_after`. Each stall is reported once.
+    pub fn poll(&mut self, now: Instant, stall_after: Duration) -> Option<Anomaly> {
+        if let Some((line, at, since)) = &self.pending_error {
+            if *since >= ERROR_CONTEXT_LINES || now.duration_since(*at) >= ERROR_SETTLE {
+                let line = line.clone();
+                self.pending_error = None;
+                return Some(Anomaly::Error(line));
+            }
+        }
+        let quiet = now.duration_since(self.last_output);
+        if self.exit.is_none() && !self.stall_reported && quiet >= stall_after {
+            self.stall_reported = true;
+            return Some(Anomaly::Stalled(quiet));
+        }
+        None
+    }
+
+    pub fn finish(&mut self, code: i32, now: Instant) {
+        self.exit = Some((code, now.duration_since(self.started)));
+    }
+
+    pub fn exit_code(&self) -> Option<i32> {
+        self.exit.map(|(code, _)| code)
+    }
+
+    pub fn summary(&self, now: Instant) -> String {
+        let mut output = match self.exit {
+            Some((code, took)) => format!(
+                "Exited with {code} after {}, {} lines of output.",
+                format_age(took),
+                self.lines
+            ),
+            None => format!(
+                "Running for {}, {} lines of output, the last {} ago.",
+                format_age(now.duration_since(self.started)),
+                self.lines,
+                format_age(now.duration_since(self.last_output))
+            ),
+        };
+        if let Some(progress) = &self.progress {
+            output.push_str(&format!("\nProgress: {progress}"));
+        }
+        match self.errors.as_slice() {
+            [] => {}
+            [error] => output.push_str(&format!("\nError: {error}")),
+            [.., last] => {
+                output.push_str(&format!("\n{} errors, the last: {last}", self.errors.len()))
+            }
+        }
+        output
+    }
+
+    pub fn tail(&self) -> String {
+        self.tail.iter().cloned().collect::<Vec<_>>().join("\n")
+    }
+
+    /// Asks the model how far `command` got and what's left.
+    pub fn progress_prompt(&self, command: &str, now: Instant) -> String {
+        format!(
+            "I'm running `{command}`. {}\n\nIts last lines of output:\n```\n{}\n```\n\nIn a few sentences: how far has it got, does anything look wrong, and roughly what's left?",
+            self.summary(now),
+            self.tail()
+        )
+    }
+
+    /// Asks the model whether an anomaly is fatal and what to do about it.
+    pub fn anomaly_prompt(&self, command: &str, anomaly: &Anomaly, now: Instant) -> String {
+        let question = match anomaly {
+            Anomaly::Error(line) => format!(
+                "It printed this error:\n```\n{line}\n```\nIn two or three sentences: what went wrong, will the command fail or carry on, and how do I fix it?"
+            ),
+            Anomaly::Stalled(quiet) => format!(
+                "It hasn't printed anything for {}. In two or three sentences: is that normal at this point (linking, a lock, the network) or is it stuck, and what should I check?",
+                format_age(*quiet)
+            ),
+        };
+        format!(
+            "I'm running `{command}`. {}\n\nIts last lines of output:\n```\n{}\n```\n\n{question}",
+            self.summary(now),
+            self.tail()
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_long_running() {
+        let shell = ShellKind::Bash;
+        assert!(is_long_running("cargo build --release", &shell));
+        assert!(is_long_running("cd web && npm run build", &shell));
+        assert!(is_long_running(
+            "bin/rails db:migrate RAILS_ENV=production",
+            &shell
+        ));
+        assert!(is_long_running("python manage.py migrate", &shell));
+        assert!(is_long_running("sudo docker build -t app .", &shell));
+        assert!(!is_long_running("cargo fmt", &shell));
+        assert!(!is_long_running("python manage.py shell", &shell));
+        assert!(!is_long_running("ls -la", &shell));
+    }
+
+    #[test]
+    fn test_output_monitor() {
+        let start = Instant::now();
+        let stall = Duration::from_secs(60);
+        let mut monitor = OutputMonitor::new(start);
+        monitor.feed("   Compiling serde v1.0.200", start);
+        monitor.feed("test result: ok. 12 passed; 0 failed", start);
+        monitor.feed("[42/120] Building CXX object", start);
+        monitor.feed("error[E0425]: cannot find value `x` in this scope", start);
+        monitor.feed("  --> src/main.rs:2:5", start);
+        assert_eq!(monitor.poll(start, stall), None);
+        assert_eq!(
+            monitor.poll(start + ERROR_SETTLE, stall),
+            Some(Anomaly::Error(
+                "error[E0425]: cannot find value `x` in this scope".into()
+            ))
+        );
+        assert_eq!(monitor.poll(start + ERROR_SETTLE, stall), None);
+        assert!(monitor
+            .summary(start + ERROR_SETTLE)
+            .contains("Progress: [42/120] Building CXX object"));
+
+        let later = start + Duration::from_secs(90);
+        assert_eq!(
+            monitor.poll(later, stall),
+            Some(Anomaly::Stalled(Duration::from_secs(90)))
+        );
+        assert_eq!(monitor.poll(later, stall), None);
+
+        monitor.finish(101, later);
+        assert_eq!(monitor.exit_code(), Some(101));
+        assert!(monitor
+            .summary(later)
+            .starts_with("Exited with 101 after 1 minute"));
+    }
+}
@@ -0,0 +1,141 @@
+use super::*;
+
+use serde::Serialize;
+
+/// A path of the command leading through a symlink, so the command may act somewhere
+/// other than where the path suggests.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SymlinkHop {
+    /// As written, `data/`.
+    pub path: String,
+    /// The first link on the way.
+    pub link: PathBuf,
+    /// Where the path really leads.
+    pub target: PathBuf,
+    /// The path names the link itself: `rm` and `mv` act on the link, not on what it
+    /// points to. A trailing `/` or a wildcard goes inside.
+    pub link_only: bool,
+}
+
+impl SymlinkHop {
+    /// Whether `operation` reaches the target rather than the link.
+    pub fn followed_by(&self, operation: &CommandOperation) -> bool {
+        !self.link_only || !matches!(operation, CommandOperation::Delete | CommandOperation::Move)
+    }
+
+    /// The target is `/`, a system directory or the whole home directory.
+    pub fn reaches_protected(&self, style: PathStyle, fs: &dyn FileSystem) -> bool {
+        is_protected_path(&self.target.to_string_lossy(), style)
+            || fs.home_dir().as_ref() == Some(&self.target)
+    }
+
+    pub fn warning(&self, operation: &CommandOperation) -> Option<String> {
+        if !operation.is_destructive() && !operation.needs_backup() {
+            return None;
+        }
+        let target = self.target.display();
+        if !self.followed_by(operation) {
+            return Some(format!(
+                "💡 {} is a symlink to {target}, only the link itself is affected.",
+                self.path
+            ));
+        }
+        match self.link_only {
+            true => Some(format!(
+                "⚠️  {} is a symlink, the command changes {target} it points to.",
+                self.path
+            )),
+            false => Some(format!(
+                "⚠️  {} leads through the symlink {} to {target}, the command acts there.",
+                self.path,
+                self.link.display()
+            )),
+        }
+    }
+}
+
+/// The path arguments of the command that lead through symlinks, resolved the way the
+/// kernel does: `data/../x` with `data` a link goes up from the link's target.
+pub fn command_symlinks(command: &str, style: PathStyle, fs: &dyn FileSystem) -> Vec<SymlinkHop> {
+    if style != PathStyle::Unix {
+        return vec![];
+    }
+    let cwd = fs.current_dir();
+    let mut output: Vec<SymlinkHop> = vec![];
+    for word in command_path_candidates(command, style) {
+        let expanded = expand_tilde(&expand_variables(&word, fs), fs);
+        // A wildcard matches inside the directory before it
+        let glob_at = expanded.find(['*', '?']);
+        let dir = match glob_at {
+            Some(at) => expanded[..at].rsplit_once('/').map(|v| v.0).unwrap_or("."),
+            None => &expanded,
+        };
+        let raw = cwd.join(dir);
+        let (target, links) = resolve_symlinks(&raw, fs);
+        let Some(link) = links.first() else {
+            continue;
+        };
+        let lexical = PathBuf::from(normalize_path(dir, &cwd, style));
+        let hop = SymlinkHop {
+            path: word.clone(),
+            link: link.clone(),
+            link_only: glob_at.is_none() && !word.ends_with('/') && *link == lexical,
+            target,
+        };
+        if !output.contains(&hop) {
+            output.push(hop);
+        }
+    }
+    output
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::Path;
+
+    #[test]
+    fn test_command_symlinks() {
+        let fs = MemoryFs::new()
+            .cwd("/home/me/project")
+            .home("/home/me")
+            .dir("/home/me/project/src")
+            .file("/srv/shared/cache/a.bin", 10)
+            .symlink("/home/me/project/data", "/home/me")
+            .symlink("/home/me/project/cache", "../../../srv/shared/cache");
+        let symlinks = |command: &str| command_symlinks(command, PathStyle::Unix, &fs);
+
+        assert!(symlinks("rm -rf src/").is_empty());
+
+        let hops = symlinks("rm -rf data/");
+        assert_eq!(hops[0].target, PathBuf::from("/home/me"));
+        assert!(!hops[0].link_only);
+        assert!(hops[0].reaches_protected(PathStyle::Unix, &fs));
+        assert!(hops[0]
+            .warning(&CommandOperation::Delete)
+            .unwrap()
+            .starts_with("⚠️  data/ leads through the symlink /home/me/project/data to /home/me"));
+
+        let hops = symlinks("rm -rf data");
+        assert!(hops[0].link_only);
+        assert!(hops[0]
+            .warning(&CommandOperation::Delete)
+            .unwrap()
+            .starts_with("💡 data is a symlink to /home/me"));
+        assert!(hops[0].followed_by(&CommandOperation::Modify));
+
+        let hops = symlinks("rm cache/*.bin");
+        assert_eq!(hops[0].target, PathBuf::from("/srv/shared/cache"));
+        assert!(!hops[0].link_only);
+
+        // `..` after a link climbs from where the link points
+        let hops = symlinks("rm -rf data/../other");
+        assert_eq!(hops[0].target, PathBuf::from("/home/other"));
+
+        assert_eq!(
+            resolve_symlinks(Path::new("/home/me/project/cache/a.bin"), &fs).0,
+            PathBuf::from("/srv/shared/cache/a.bin")
+        );
+        assert!(fs.is_file(Path::new("/home/me/project/cache/a.bin")));
+    }
+}
@@ -0,0 +1,349 @@
+use super::*;
+
+use serde::Serialize;
+use std::path::Path;
+
+/// Programs that open a file to edit it, or change it in place.
+const EDITORS: [&str; 16] = [
+    "vi",
+    "vim",
+    "nvim",
+    "nano",
+    "pico",
+    "emacs",
+    "ed",
+    "ex",
+    "micro",
+    "joe",
+    "mcedit",
+    "code",
+    "gedit",
+    "kate",
+    "notepad",
+    "notepad.exe",
+];
+
+/// Programs that replace, truncate or remove the files they name.
+const FILE_CHANGERS: [&str; 7] = ["rm", "truncate", "chmod", "chown", "chattr", "ln", "shred"];
+
+const SHELL_RC_FILES: [&str; 14] = [
+    ".bashrc",
+    ".bash_profile",
+    ".bash_login",
+    ".profile",
+    ".zshrc",
+    ".zprofile",
+    ".zshenv",
+    ".zlogin",
+    ".cshrc",
+    ".tcshrc",
+    ".kshrc",
+    "config.fish",
+    "Microsoft.PowerShell_profile.ps1",
+    "profile.ps1",
+];
+
+const SYSTEM_SHELL_RC_FILES: [&str; 6] = [
+    "/etc/profile",
+    "/etc/bash.bashrc",
+    "/etc/bashrc",
+    "/etc/zshrc",
+    "/etc/zsh/zshrc",
+    "/etc/environment",
+];
+
+/// Under `/`, `/System` and the home directory alike.
+const LAUNCHD_DIRS: [&str; 2] = ["/Library/LaunchDaemons/", "/Library/LaunchAgents/"];
+
+/// Configuration the machine can't do without: a mistake in it locks users out, stops
+/// it from booting or breaks every shell.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SystemConfigKind {
+    /// `/etc/sudoers` and `/etc/sudoers.d/`.
+    Sudoers,
+    /// `/etc/passwd`, `/etc/shadow`, `/etc/group` and `/etc/gshadow`.
+    Accounts,
+    Fstab,
+    /// Startup files of bash, zsh, fish, csh and PowerShell, the user's and the system's.
+    ShellRc,
+    SystemdUnit,
+    LaunchdPlist,
+    /// `reg add`/`reg delete` and the PowerShell registry drive.
+    Registry,
+}
+
+/// A command changing one of the [`SystemConfigKind`] files or registry keys.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SystemConfigEdit {
+    pub kind: SystemConfigKind,
+    /// The file, or the registry key.
+    pub path: String,
+    /// The program changing it, `>` for redirects.
+    pub tool: String,
+    /// Outside of the user's account: `/etc`, `/Library` or `HKLM`.
+    pub machine_wide: bool,
+}
+
+impl SystemConfigEdit {
+    pub fn level(&self) -> SafetyLevel {
+        match self.kind {
+            SystemConfigKind::Sudoers | SystemConfigKind::Accounts | SystemConfigKind::Fstab => {
+                SafetyLevel::Critical
+            }
+            SystemConfigKind::Registry if self.machine_wide => SafetyLevel::Critical,
+            SystemConfigKind::ShellRc if !self.machine_wide => SafetyLevel::Caution,
+            _ => SafetyLevel::Dangerous,
+        }
+    }
+
+    pub fn warning(&self) -> String {
+        let consequence = match self.kind {
+            SystemConfigKind::Sudoers => "a syntax error in it locks everyone out of sudo",
+            SystemConfigKind::Accounts => "a broken entry can stop every login",
+            SystemConfigKind::Fstab => "a broken entry can stop the machine from booting",
+            SystemConfigKind::ShellRc => "every new shell runs it, an error breaks them all",
+            SystemConfigKind::SystemdUnit => "systemd starts services from it",
+            SystemConfigKind::LaunchdPlist => "launchd starts services from it",
+            SystemConfigKind::Registry => "Windows and applications read their settings from it",
+        };
+        format!(
+            "⚠️  System config: {} changes {}, {consequence}.",
+            self.tool, self.path
+        )
+    }
+
+    /// The tool or check that makes the edit safe.
+    pub fn recommendation(&self) -> String {
+        match self.kind {
+            SystemConfigKind::Sudoers if self.path == "/etc/sudoers" => {
+                "💡 Edit it with `sudo visudo`, which refuses to save a file sudo can't read."
+                    .to_string()
+            }
+            SystemConfigKind::Sudoers => format!(
+                "💡 Edit it with `sudo visudo -f {}`, which refuses to save a file sudo can't read.",
+                self.path
+            ),
+            SystemConfigKind::Accounts => {
+                "💡 Use `usermod`/`passwd`, or edit it with `sudo vipw` (`vigr` for groups), which lock and check the file."
+                    .to_string()
+            }
+            SystemConfigKind::Fstab => {
+                "💡 Check it with `sudo findmnt --verify` before rebooting.".to_string()
+            }
+            SystemConfigKind::ShellRc => {
+                "💡 Open a new shell to check it before closing this one.".to_string()
+            }
+            SystemConfigKind::SystemdUnit => {
+                let unit = Path::new(&self.path)
+                    .file_name()
+                    .map(|v| v.to_string_lossy().to_string())
+                    .unwrap_or_default();
+                format!(
+                    "💡 Override it with `sudo systemctl edit {unit}`, and check it with `systemd-analyze verify` before restarting."
+                )
+            }
+            SystemConfigKind::LaunchdPlist => format!(
+                "💡 Check it with `plutil -lint {}` before loading it with `launchctl`.",
+                self.path
+            ),
+            SystemConfigKind::Registry => format!(
+                "💡 Export the key first: reg export \"{}\" backup.reg",
+                self.path
+            ),
+        }
+    }
+}
+
+/// Edits of critical system configuration: files written by `outputs` or opened by an
+/// editor, `sed -i` and the like, and registry keys changed by `windows`.
+pub fn detect_system_config_edits(
+    command: &str,
+    style: PathStyle,
+    shell: &ShellKind,
+    fs: &dyn FileSystem,
+    outputs: &[OutputPath],
+    windows: &[WindowsCommand],
+) -> Vec<SystemConfigEdit> {
+    let mut edits: Vec<SystemConfigEdit> = vec![];
+    let mut push = |edit: SystemConfigEdit| {
+        if !edits
+            .iter()
+            .any(|v| v.kind == edit.kind && v.path == edit.path)
+        {
+            edits.push(edit);
+        }
+    };
+    for output in outputs {
+        if let Some(edit) = classify(&output.path, &output.tool, fs) {
+            push(edit);
+        }
+    }
+    for segment in split_command_segments(command, shell) {
+        let words: Vec<String> = tokenize(&segment, style)
+            .into_iter()
+            .filter(|v| v.is_word())
+            .map(|v| v.text)
+            .collect();
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas") || v.contains('='))
+            .collect();
+        let Some((tool, args)) = words.split_first() else {
+            continue;
+        };
+        let in_place = match *tool {
+            "sed" | "gsed" => args
+                .iter()
+                .any(|v| v.starts_with("-i") || v.starts_with("--in-place")),
+            "perl" => args
+                .iter()
+                .any(|v| v.starts_with("-i") || v.starts_with("-pi")),
+            tool => EDITORS.contains(&tool) || FILE_CHANGERS.contains(&tool),
+        };
+        if !in_place {
+            continue;
+        }
+        for arg in args.iter().filter(|v| !v.starts_with('-')) {
+            if arg.eq_ignore_ascii_case("$PROFILE") {
+                push(SystemConfigEdit {
+                    kind: SystemConfigKind::ShellRc,
+                    path: arg.to_string(),
+                    tool: tool.to_string(),
+                    machine_wide: false,
+                });
+                continue;
+            }
+            let path = resolve_command_path(arg, style, fs);
+            if let Some(edit) = classify(&path, tool, fs) {
+                push(edit);
+            }
+        }
+    }
+    for windows in windows {
+        let operation = windows.operation();
+        if matches!(operation, CommandOperation::Read) {
+            continue;
+        }
+        for target in &windows.targets {
+            if let Some(hive) = registry_hive(target) {
+                push(SystemConfigEdit {
+                    kind: SystemConfigKind::Registry,
+                    path: target.clone(),
+                    tool: windows.name.clone(),
+                    machine_wide: hive == Hive::Machine,
+                });
+            }
+        }
+    }
+    edits
+}
+
+fn classify(path: &Path, tool: &str, fs: &dyn FileSystem) -> Option<SystemConfigEdit> {
+    let text = path.to_string_lossy().replace('\\', "/");
+    let name = path.file_name()?.to_string_lossy().to_string();
+    let home = fs
+        .home_dir()
+        .map(|v| v.to_string_lossy().replace('\\', "/"));
+    let in_home = home.as_deref().is_some_and(|v| text.starts_with(v));
+    let kind = match text.as_str() {
+        "/etc/sudoers" => SystemConfigKind::Sudoers,
+        v if v.starts_with("/etc/sudoers.d/") => SystemConfigKind::Sudoers,
+        "/etc/passwd" | "/etc/shadow" | "/etc/group" | "/etc/gshadow" => SystemConfigKind::Accounts,
+        "/etc/fstab" => SystemConfigKind::Fstab,
+        v if SYSTEM_SHELL_RC_FILES.contains(&v) || v.starts_with("/etc/profile.d/") => {
+            SystemConfigKind::ShellRc
+        }
+        _ if SHELL_RC_FILES.contains(&name.as_str()) => SystemConfigKind::ShellRc,
+        v if name.ends_with(".plist") && LAUNCHD_DIRS.iter().any(|d| v.contains(d)) => {
+            SystemConfigKind::LaunchdPlist
+        }
+        v if is_systemd_unit_path(v) => SystemConfigKind::SystemdUnit,
+        _ => return None,
+    };
+    Some(SystemConfigEdit {
+        kind,
+        path: path.display().to_string(),
+        tool: tool.to_string(),
+        machine_wide: !in_home,
+    })
+}
+
+fn is_systemd_unit_path(path: &str) -> bool {
+    let in_unit_dir = [
+        "/etc/systemd/",
+        "/run/systemd/system/",
+        "/lib/systemd/system/",
+        "/usr/lib/systemd/system/",
+    ]
+    .iter()
+    .any(|v| path.starts_with(v))
+        || path.contains("/.config/systemd/");
+    in_unit_dir && path.rsplit('/').next().is_some_and(|v| v.contains('.'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edits(command: &str) -> Vec<SystemConfigEdit> {
+        let fs = MemoryFs::new()
+            .home("/home/me")
+            .file("/etc/sudoers", 900)
+            .file("/etc/fstab", 700)
+            .file("/home/me/.bashrc", 3000);
+        CommandAnalysis::analyze_with(command, PathStyle::Unix, &ShellKind::Bash, &fs)
+            .system_config_edits
+    }
+
+    #[test]
+    fn test_detect_system_config_edits() {
+        let found = edits("echo 'me ALL=(ALL) NOPASSWD: ALL' | sudo tee -a /etc/sudoers");
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].kind, SystemConfigKind::Sudoers);
+        assert_eq!(found[0].level(), SafetyLevel::Critical);
+        assert_eq!(
+            found[0].recommendation(),
+            "💡 Edit it with `sudo visudo`, which refuses to save a file sudo can't read."
+        );
+        assert_eq!(
+            edits("sudo sed -i 's/defaults/noatime/' /etc/fstab")[0].kind,
+            SystemConfigKind::Fstab
+        );
+        assert_eq!(
+            edits("sudo nano /etc/sudoers.d/deploy")[0].recommendation(),
+            "💡 Edit it with `sudo visudo -f /etc/sudoers.d/deploy`, which refuses to save a file sudo can't read."
+        );
+        let rc = edits("echo 'export PATH=$HOME/bin:$PATH' >> ~/.bashrc");
+        assert_eq!(
+            (rc[0].kind, rc[0].machine_wide, rc[0].level()),
+            (SystemConfigKind::ShellRc, false, SafetyLevel::Caution)
+        );
+        assert_eq!(
+            edits("sudo vim /etc/systemd/system/app.service")[0].kind,
+            SystemConfigKind::SystemdUnit
+        );
+        assert_eq!(
+            edits("sudo cp agent.plist /Library/LaunchDaemons/com.example.agent.plist")[0].kind,
+            SystemConfigKind::LaunchdPlist
+        );
+        assert!(edits("cat /etc/passwd").is_empty());
+        assert!(edits("sudo visudo").is_empty());
+        assert!(edits("grep -n Defaults /etc/sudoers").is_empty());
+
+        let fs = MemoryFs::new();
+        let analysis = CommandAnalysis::analyze_with(
+            r"reg add HKLM\Software\Policies\Vendor /v Enabled /t REG_DWORD /d 0 /f",
+            PathStyle::Windows,
+            &ShellKind::Cmd,
+            &fs,
+        );
+        let registry = &analysis.system_config_edits[0];
+        assert_eq!(
+            (registry.kind, registry.machine_wide),
+            (SystemConfigKind::Registry, true)
+        );
+        assert_eq!(analysis.safety_level, SafetyLevel::Critical);
+    }
+}
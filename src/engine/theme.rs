@@ -0,0 +1,310 @@
+use anyhow::{anyhow, Context, Result};
+use nu_ansi_term::Color;
+use parking_lot::RwLock;
+use serde::{de, Deserialize, Deserializer, Serialize, Serializer};
+use std::{
+    fs,
+    path::Path,
+    str::FromStr,
+    sync::{Arc, LazyLock},
+};
+
+use super::SafetyLevel;
+
+static THEME: LazyLock<RwLock<Arc<Theme>>> = LazyLock::new(|| RwLock::new(Arc::new(Theme::dark())));
+
+/// The active UI theme, the dark preset until [`set_theme`] runs.
+pub fn theme() -> Arc<Theme> {
+    THEME.read().clone()
+}
+
+pub fn set_theme(theme: Theme) {
+    *THEME.write() = Arc::new(theme);
+}
+
+/// Color a display note by its leading marker: `⚠` notes in the color of their level,
+/// see [`note_level`], `✓` notes the success color and anything else is left as-is.
+pub fn note_text(note: &str, context: &SafetyLevel) -> String {
+    let trimmed = note.trim_start();
+    if let Some(level) = note_level(note, context) {
+        super::color_text(note, level.color())
+    } else if trimmed.starts_with('✓') {
+        super::color_text(note, theme().success.0)
+    } else {
+        note.to_string()
+    }
+}
+
+/// The level a `⚠` note speaks for: its own `CRITICAL:`, `DANGEROUS:` or `CAUTION:` label,
+/// else the level of the command or step it belongs to, at least Caution. `None` for notes
+/// that aren't warnings.
+pub fn note_level(note: &str, context: &SafetyLevel) -> Option<SafetyLevel> {
+    let text = note.trim_start().strip_prefix('⚠')?;
+    let label = text
+        .trim_start_matches('\u{fe0f}')
+        .trim_start()
+        .split_once(':')
+        .map(|v| v.0);
+    let level = match label {
+        Some("CRITICAL") => SafetyLevel::Critical,
+        Some("DANGEROUS" | "DANGER") => SafetyLevel::Dangerous,
+        Some("CAUTION") => SafetyLevel::Caution,
+        _ => context.clone().max(SafetyLevel::Caution),
+    };
+    Some(level)
+}
+
+/// Colors used for everything aichat prints outside of syntax-highlighted code.
+///
+/// A preset is picked from the detected terminal background; any subset of the slots
+/// can be overridden in `<config_dir>/dark.theme.yaml` or `light.theme.yaml`, e.g.
+///
+/// ```yaml
+/// critical: "#ff5f5f"
+/// prompt: light_cyan
+/// role: 114
+/// ```
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Theme {
+    pub error: ThemeColor,
+    pub warning: ThemeColor,
+    pub success: ThemeColor,
+    pub info: ThemeColor,
+    /// Suggested commands and other things the user must look at.
+    pub accent: ThemeColor,
+    /// Hotkey letters in option prompts.
+    pub key: ThemeColor,
+    /// Role/session/agent names in the REPL prompt.
+    pub role: ThemeColor,
+    /// The prompt glyph (`>` or `)`).
+    pub prompt: ThemeColor,
+    pub safe: ThemeColor,
+    pub caution: ThemeColor,
+    pub dangerous: ThemeColor,
+    pub critical: ThemeColor,
+}
+
+impl Theme {
+    pub fn dark() -> Self {
+        Self {
+            error: ThemeColor(Color::Red),
+            warning: ThemeColor(Color::Yellow),
+            success: ThemeColor(Color::Green),
+            info: ThemeColor(Color::DarkGray),
+            accent: ThemeColor(Color::Rgb(255, 165, 0)),
+            key: ThemeColor(Color::Cyan),
+            role: ThemeColor(Color::Green),
+            prompt: ThemeColor(Color::Cyan),
+            safe: ThemeColor(Color::Green),
+            caution: ThemeColor(Color::Yellow),
+            dangerous: ThemeColor(Color::LightRed),
+            critical: ThemeColor(Color::Red),
+        }
+    }
+
+    pub fn light() -> Self {
+        Self {
+            error: ThemeColor(Color::Red),
+            warning: ThemeColor(Color::Rgb(175, 95, 0)),
+            success: ThemeColor(Color::Rgb(0, 135, 0)),
+            info: ThemeColor(Color::LightGray),
+            accent: ThemeColor(Color::Rgb(204, 102, 0)),
+            key: ThemeColor(Color::Blue),
+            role: ThemeColor(Color::Rgb(0, 135, 0)),
+            prompt: ThemeColor(Color::Blue),
+            safe: ThemeColor(Color::Rgb(0, 135, 0)),
+            caution: ThemeColor(Color::Rgb(175, 95, 0)),
+            dangerous: ThemeColor(Color::Rgb(215, 0, 0)),
+            critical: ThemeColor(Color::Rgb(175, 0, 0)),
+        }
+    }
+
+    /// Load the preset for the terminal background, then apply overrides from `path`.
+    pub fn load(light: bool, path: &Path) -> Result<Self> {
+        let preset = if light { Self::light() } else { Self::dark() };
+        if !path.exists() {
+            return Ok(preset);
+        }
+        let ctx = || format!("Invalid theme at '{}'", path.display());
+        let content = fs::read_to_string(path).with_context(ctx)?;
+        Self::merge(preset, &content).with_context(ctx)
+    }
+
+    fn merge(preset: Self, content: &str) -> Result<Self> {
+        let mut value = serde_yaml::to_value(preset)?;
+        let overrides: serde_yaml::Value = serde_yaml::from_str(content)?;
+        if let (Some(base), Some(overrides)) = (value.as_mapping_mut(), overrides.as_mapping()) {
+            for (key, color) in overrides {
+                if !base.contains_key(key) {
+                    return Err(anyhow!("Unknown theme key {key:?}"));
+                }
+                base.insert(key.clone(), color.clone());
+            }
+        }
+        Ok(serde_yaml::from_value(value)?)
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ThemeColor(pub Color);
+
+impl FromStr for ThemeColor {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        let value = value.trim().to_lowercase();
+        if let Some(hex) = value.strip_prefix('#') {
+            let parse = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or_default(), 16);
+            return match (hex.len(), parse(0), parse(2), parse(4)) {
+                (6, Ok(r), Ok(g), Ok(b)) => Ok(Self(Color::Rgb(r, g, b))),
+                _ => Err(anyhow!("Invalid color '#{hex}'")),
+            };
+        }
+        if let Ok(index) = value.parse::<u8>() {
+            return Ok(Self(Color::Fixed(index)));
+        }
+        let color = match value.as_str() {
+            "black" => Color::Black,
+            "dark_gray" => Color::DarkGray,
+            "red" => Color::Red,
+            "light_red" => Color::LightRed,
+            "green" => Color::Green,
+            "light_green" => Color::LightGreen,
+            "yellow" => Color::Yellow,
+            "light_yellow" => Color::LightYellow,
+            "blue" => Color::Blue,
+            "light_blue" => Color::LightBlue,
+            "purple" => Color::Purple,
+            "light_purple" => Color::LightPurple,
+            "magenta" => Color::Magenta,
+            "light_magenta" => Color::LightMagenta,
+            "cyan" => Color::Cyan,
+            "light_cyan" => Color::LightCyan,
+            "white" => Color::White,
+            "light_gray" => Color::LightGray,
+            _ => return Err(anyhow!("Invalid color '{value}'")),
+        };
+        Ok(Self(color))
+    }
+}
+
+impl std::fmt::Display for ThemeColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self.0 {
+            Color::Black => "black",
+            Color::DarkGray => "dark_gray",
+            Color::Red => "red",
+            Color::LightRed => "light_red",
+            Color::Green => "green",
+            Color::LightGreen => "light_green",
+            Color::Yellow => "yellow",
+            Color::LightYellow => "light_yellow",
+            Color::Blue => "blue",
+            Color::LightBlue => "light_blue",
+            Color::Purple => "purple",
+            Color::LightPurple => "light_purple",
+            Color::Magenta => "magenta",
+            Color::LightMagenta => "light_magenta",
+            Color::Cyan => "cyan",
+            Color::LightCyan => "light_cyan",
+            Color::White => "white",
+            Color::LightGray => "light_gray",
+            Color::Fixed(index) => return write!(f, "{index}"),
+            Color::Rgb(r, g, b) => return write!(f, "#{r:02x}{g:02x}{b:02x}"),
+            Color::Default => "default",
+        };
+        write!(f, "{name}")
+    }
+}
+
+impl Serialize for ThemeColor {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.serialize_str(&self.to_string())
+    }
+}
+
+impl<'de> Deserialize<'de> for ThemeColor {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        let value = serde_yaml::Value::deserialize(deserializer)?;
+        let value = match value {
+            serde_yaml::Value::String(v) => v,
+            serde_yaml::Value::Number(v) => v.to_string(),
+            _ => return Err(de::Error::custom("Invalid color")),
+        };
+        value.parse().map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_theme_color() {
+        assert_eq!("red".parse::<ThemeColor>().unwrap().0, Color::Red);
+        assert_eq!(
+            "Light_Cyan".parse::<ThemeColor>().unwrap().0,
+            Color::LightCyan
+        );
+        assert_eq!(
+            "#ff8800".parse::<ThemeColor>().unwrap().0,
+            Color::Rgb(255, 136, 0)
+        );
+        assert_eq!("114".parse::<ThemeColor>().unwrap().0, Color::Fixed(114));
+        assert!("#ff88".parse::<ThemeColor>().is_err());
+        assert!("orange".parse::<ThemeColor>().is_err());
+    }
+
+    #[test]
+    fn test_note_level() {
+        let level = |note: &str, context: SafetyLevel| note_level(note, &context);
+        assert_eq!(
+            level(
+                "⚠️  CRITICAL: This command changes files in system directories!",
+                SafetyLevel::Caution
+            ),
+            Some(SafetyLevel::Critical)
+        );
+        assert_eq!(
+            level(
+                "⚠️  CAUTION: This operation will modify files.",
+                SafetyLevel::Critical
+            ),
+            Some(SafetyLevel::Caution)
+        );
+        assert_eq!(
+            level(
+                "⚠️  Recursive delete - will remove directories and all contents!",
+                SafetyLevel::Dangerous
+            ),
+            Some(SafetyLevel::Dangerous)
+        );
+        assert_eq!(
+            level(
+                "⚠️  sponge: replaces config.json with the output of the pipeline.",
+                SafetyLevel::Safe
+            ),
+            Some(SafetyLevel::Caution)
+        );
+        assert_eq!(
+            level("💡 `xargs -p` asks before each run.", SafetyLevel::Critical),
+            None
+        );
+        assert_eq!(
+            level(
+                "✓ Backup will be created automatically before execution.",
+                SafetyLevel::Safe
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn test_merge_theme() {
+        let theme = Theme::merge(Theme::dark(), "critical: '#ff5f5f'\nrole: 114\n").unwrap();
+        assert_eq!(theme.critical.0, Color::Rgb(255, 95, 95));
+        assert_eq!(theme.role.0, Color::Fixed(114));
+        assert_eq!(theme.error.0, Theme::dark().error.0);
+        assert!(Theme::merge(Theme::dark(), "unknown: red").is_err());
+    }
+}
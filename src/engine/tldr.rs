@@ -0,0 +1,198 @@
+use fancy_regex::Regex;
+use parking_lot::RwLock;
+use serde::Serialize;
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::LazyLock,
+};
+
+static TLDR_DIR: RwLock<Option<PathBuf>> = RwLock::new(None);
+
+/// `{{[-a|--all]}}`, a flag written both ways in the newer pages.
+static RE_FLAG_ALTERNATIVES: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"\{\{\[(-[^\]{}]+)\]\}\}").unwrap());
+/// `{{path/to/file}}`, a placeholder.
+static RE_PLACEHOLDER: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\{\{.*?\}\}").unwrap());
+
+/// Where tldr pages are cached, `<dir>/<platform>/<command>.md`; none until
+/// [`set_tldr_dir`] runs, and then the tutor reads pages from there.
+pub fn tldr_dir() -> Option<PathBuf> {
+    TLDR_DIR.read().clone()
+}
+
+pub fn set_tldr_dir(dir: Option<PathBuf>) {
+    *TLDR_DIR.write() = dir;
+}
+
+/// The tldr-pages directories for this OS, most specific first.
+pub fn tldr_platforms() -> Vec<&'static str> {
+    let platform = match std::env::consts::OS {
+        "macos" => "osx",
+        "linux" => "linux",
+        "windows" => "windows",
+        "android" => "android",
+        "freebsd" => "freebsd",
+        "netbsd" => "netbsd",
+        "openbsd" => "openbsd",
+        _ => return vec!["common"],
+    };
+    vec![platform, "common"]
+}
+
+/// Whether `name` can be a page, so a command like `../x` never becomes a path.
+pub fn is_tldr_name(name: &str) -> bool {
+    !name.is_empty()
+        && !name.starts_with(['.', '-'])
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '.' | '_' | '+' | '-'))
+}
+
+/// The cached page of `name` in `dir`: `Some(None)` when it is cached as missing from
+/// tldr-pages, `None` when it was never fetched.
+pub fn cached_tldr_page(dir: &Path, name: &str) -> Option<Option<TldrPage>> {
+    let name = name.to_lowercase();
+    if !is_tldr_name(&name) {
+        return Some(None);
+    }
+    tldr_platforms().into_iter().find_map(|platform| {
+        let text = fs::read_to_string(dir.join(platform).join(format!("{name}.md"))).ok()?;
+        Some(TldrPage::parse(&text))
+    })
+}
+
+/// A page of tldr-pages, the community's short examples of a command.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TldrPage {
+    pub name: String,
+    pub description: String,
+    pub examples: Vec<TldrExample>,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct TldrExample {
+    pub description: String,
+    /// `{{placeholders}}` as in the page.
+    pub command: String,
+}
+
+impl TldrPage {
+    /// `None` for anything but a page, like the empty file marking a command tldr-pages
+    /// doesn't have.
+    pub fn parse(text: &str) -> Option<Self> {
+        let mut name = None;
+        let mut description = vec![];
+        let mut examples: Vec<TldrExample> = vec![];
+        let mut example = None;
+        for line in text.lines().map(|v| v.trim()) {
+            if let Some(v) = line.strip_prefix("# ") {
+                name = Some(v.trim().to_string());
+            } else if let Some(v) = line.strip_prefix("> ") {
+                if !v.starts_with("More information:") && !v.starts_with("See also:") {
+                    description.push(v.trim_end_matches('.').to_string());
+                }
+            } else if let Some(v) = line.strip_prefix("- ") {
+                example = Some(v.trim_end_matches(':').to_string());
+            } else if let Some(command) = line.strip_prefix('`').and_then(|v| v.strip_suffix('`')) {
+                if let Some(description) = example.take() {
+                    examples.push(TldrExample {
+                        description,
+                        command: command.to_string(),
+                    });
+                }
+            }
+        }
+        Some(Self {
+            name: name?,
+            description: description.join(". "),
+            examples,
+        })
+    }
+
+    /// Flags and long options described by the examples that use only one of them, the
+    /// first example of each.
+    pub fn flag_descriptions(&self) -> Vec<(String, String)> {
+        let mut output: Vec<(String, String)> = vec![];
+        for example in &self.examples {
+            let mut flags: Vec<Vec<String>> = RE_FLAG_ALTERNATIVES
+                .captures_iter(&example.command)
+                .flatten()
+                .map(|v| v[1].split('|').map(|v| v.to_string()).collect())
+                .collect();
+            let rest = RE_FLAG_ALTERNATIVES.replace_all(&example.command, "");
+            let rest = RE_PLACEHOLDER.replace_all(&rest, "");
+            flags.extend(
+                rest.split_whitespace()
+                    .filter(|v| v.starts_with('-') && v.len() > 1 && *v != "--")
+                    .map(|v| vec![v.split('=').next().unwrap_or(v).to_string()]),
+            );
+            if let [alternatives] = flags.as_slice() {
+                for flag in alternatives {
+                    if !output.iter().any(|(v, _)| v == flag) {
+                        output.push((flag.clone(), example.description.clone()));
+                    }
+                }
+            }
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAGE: &str = "# hyperfine
+
+> Command-line benchmarking tool.
+> More information: <https://github.com/sharkdp/hyperfine>.
+
+- Run a basic benchmark, performing at least 10 runs:
+
+`hyperfine '{{make}}'`
+
+- Perform benchmark warmup runs before timing:
+
+`hyperfine {{[-w|--warmup]}} {{3}} '{{make}}'`
+
+- Export the results as JSON:
+
+`hyperfine --export-json {{path/to/results.json}} '{{make}}'`
+
+- Run a command before each run, and ignore failures:
+
+`hyperfine --prepare '{{make clean}}' -i '{{make}}'`
+";
+
+    #[test]
+    fn test_tldr_page() {
+        let page = TldrPage::parse(PAGE).unwrap();
+        assert_eq!(page.name, "hyperfine");
+        assert_eq!(page.description, "Command-line benchmarking tool");
+        assert_eq!(page.examples.len(), 4);
+        assert_eq!(
+            page.examples[1].command,
+            "hyperfine {{[-w|--warmup]}} {{3}} '{{make}}'"
+        );
+        assert_eq!(
+            page.flag_descriptions(),
+            [
+                (
+                    "-w".into(),
+                    "Perform benchmark warmup runs before timing".into()
+                ),
+                (
+                    "--warmup".into(),
+                    "Perform benchmark warmup runs before timing".into()
+                ),
+                ("--export-json".into(), "Export the results as JSON".into()),
+            ]
+        );
+        assert_eq!(TldrPage::parse(""), None);
+
+        assert!(is_tldr_name("g++"));
+        assert!(!is_tldr_name("../passwd"));
+        assert!(!is_tldr_name("-rf"));
+    }
+}
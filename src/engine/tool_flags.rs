@@ -0,0 +1,418 @@
+use super::*;
+
+use fancy_regex::Regex;
+use std::{collections::BTreeSet, sync::LazyLock};
+
+static LONG_FLAG_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"(?<![\w-])--[a-zA-Z][\w-]*").unwrap());
+
+/// Help texts listing fewer long options are usage lines (bsdtar, BSD sed), too short to tell
+/// a missing option from an unlisted one.
+const MIN_LONG_FLAGS: usize = 5;
+
+/// A flag the installed tool doesn't have.
+#[derive(Debug, Clone, PartialEq)]
+pub struct FlagIssue {
+    pub tool: String,
+    pub flavor: ToolFlavor,
+    /// The argument as written, e.g. `-ri` or `--max-depth=1`.
+    pub token: String,
+    /// What to write instead, empty to drop it. None when there is no drop-in replacement.
+    pub replacement: Option<String>,
+    pub note: String,
+}
+
+impl FlagIssue {
+    pub fn warning(&self) -> String {
+        let fix = match self.replacement.as_deref() {
+            Some("") => format!(", drop {}", self.token),
+            Some(v) => format!(", use {v}"),
+            None => String::new(),
+        };
+        format!(
+            "⚠️  Flags: your {} is {} {}, {}{fix}.",
+            self.tool, self.flavor, self.tool, self.note
+        )
+    }
+}
+
+/// Flags of one flavor that are spelled differently, or missing, in another.
+struct FlagFix {
+    tool: &'static str,
+    flavor: ToolFlavor,
+    flag: &'static str,
+    replacement: Option<&'static str>,
+    note: &'static str,
+}
+
+const FLAG_FIXES: &[FlagFix] = &[
+    FlagFix {
+        tool: "sed",
+        flavor: ToolFlavor::Bsd,
+        flag: "-r",
+        replacement: Some("-E"),
+        note: "it has no -r",
+    },
+    FlagFix {
+        tool: "grep",
+        flavor: ToolFlavor::Bsd,
+        flag: "-P",
+        replacement: None,
+        note: "it has no Perl regexes, use -E or GNU grep (ggrep)",
+    },
+    FlagFix {
+        tool: "ls",
+        flavor: ToolFlavor::Bsd,
+        flag: "--color",
+        replacement: Some("-G"),
+        note: "it has no --color",
+    },
+    FlagFix {
+        tool: "du",
+        flavor: ToolFlavor::Bsd,
+        flag: "--max-depth",
+        replacement: Some("-d"),
+        note: "it has no --max-depth",
+    },
+    FlagFix {
+        tool: "stat",
+        flavor: ToolFlavor::Bsd,
+        flag: "-c",
+        replacement: None,
+        note: "formats go with -f and other letters (%z size, %m mtime)",
+    },
+    FlagFix {
+        tool: "date",
+        flavor: ToolFlavor::Bsd,
+        flag: "-d",
+        replacement: None,
+        note: "dates are parsed with -j -f <format> <date>",
+    },
+    FlagFix {
+        tool: "xargs",
+        flavor: ToolFlavor::Bsd,
+        flag: "-r",
+        replacement: Some(""),
+        note: "it skips empty input already",
+    },
+    FlagFix {
+        tool: "xargs",
+        flavor: ToolFlavor::Bsd,
+        flag: "--no-run-if-empty",
+        replacement: Some(""),
+        note: "it skips empty input already",
+    },
+    FlagFix {
+        tool: "base64",
+        flavor: ToolFlavor::Bsd,
+        flag: "-w",
+        replacement: Some("-b"),
+        note: "it has no -w",
+    },
+    FlagFix {
+        tool: "cp",
+        flavor: ToolFlavor::Bsd,
+        flag: "--parents",
+        replacement: None,
+        note: "it has no --parents, use rsync -R",
+    },
+    FlagFix {
+        tool: "find",
+        flavor: ToolFlavor::Bsd,
+        flag: "-printf",
+        replacement: None,
+        note: "it has no -printf, use -exec stat -f '...' {} +",
+    },
+    FlagFix {
+        tool: "ls",
+        flavor: ToolFlavor::Gnu,
+        flag: "-G",
+        replacement: None,
+        note: "-G hides the group column, colors come from --color",
+    },
+    FlagFix {
+        tool: "stat",
+        flavor: ToolFlavor::Gnu,
+        flag: "-f",
+        replacement: None,
+        note: "-f reports the filesystem, formats go with -c (%s size, %Y mtime)",
+    },
+    FlagFix {
+        tool: "date",
+        flavor: ToolFlavor::Gnu,
+        flag: "-j",
+        replacement: None,
+        note: "it has no -j, dates are parsed with -d",
+    },
+    FlagFix {
+        tool: "base64",
+        flavor: ToolFlavor::Gnu,
+        flag: "-b",
+        replacement: Some("-w"),
+        note: "it has no -b",
+    },
+    FlagFix {
+        tool: "find",
+        flavor: ToolFlavor::Gnu,
+        flag: "-E",
+        replacement: None,
+        note: "extended regexes take -regextype posix-extended after the path",
+    },
+];
+
+/// The long options named in `--help` or man page text. Man pages from `man` without a pager
+/// overstrike bold text with backspaces, those are removed first.
+pub fn parse_help_flags(text: &str) -> BTreeSet<String> {
+    let mut plain = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\u{8}' => {
+                plain.pop();
+            }
+            c => plain.push(c),
+        }
+    }
+    LONG_FLAG_RE
+        .find_iter(&plain)
+        .flatten()
+        .map(|v| v.as_str().to_string())
+        .collect()
+}
+
+/// Flags of `command` that the installed tools of `toolchain` don't have. `help` gives the long
+/// options a tool lists in its `--help` and man page.
+pub fn check_tool_flags(
+    command: &str,
+    shell: &ShellKind,
+    toolchain: &[ToolInfo],
+    help: &mut dyn FnMut(&ToolInfo) -> Option<BTreeSet<String>>,
+) -> Vec<FlagIssue> {
+    let mut output = vec![];
+    for segment in split_command_segments(command, shell) {
+        let words = shell_words::split(&segment)
+            .unwrap_or_else(|_| segment.split_whitespace().map(|v| v.to_string()).collect());
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas" | "command"))
+            .collect();
+        let Some((name, args)) = words.split_first() else {
+            continue;
+        };
+        let name = name.rsplit('/').next().unwrap_or(name);
+        let Some(tool) = toolchain.iter().find(|v| v.name == name) else {
+            continue;
+        };
+        let args: Vec<&str> = args.iter().copied().take_while(|v| *v != "--").collect();
+
+        // `sed -i ''` is how BSD sed edits without a backup, GNU sed reads '' as the script
+        if name == "sed" && tool.flavor == ToolFlavor::Gnu {
+            if let Some(i) = args.iter().position(|v| *v == "-i") {
+                if args.get(i + 1) == Some(&"") {
+                    output.push(FlagIssue {
+                        tool: tool.name.clone(),
+                        flavor: tool.flavor,
+                        token: "-i ''".into(),
+                        replacement: Some("-i".into()),
+                        note: "the backup suffix goes right after -i (-i.bak), `-i ''` reads '' as the script".into(),
+                    });
+                }
+            }
+        }
+
+        let mut long_flags = None;
+        for arg in &args {
+            if let Some(issue) = known_fix(tool, arg) {
+                output.push(issue);
+                continue;
+            }
+            let Some(flag) = arg
+                .strip_prefix("--")
+                .map(|v| v.split('=').next().unwrap_or(v))
+            else {
+                continue;
+            };
+            // find has single-dash long options, anything after `-exec` belongs to another tool
+            if flag.is_empty() || matches!(name, "find") {
+                continue;
+            }
+            let known = long_flags.get_or_insert_with(|| help(tool).unwrap_or_default());
+            if known.len() < MIN_LONG_FLAGS {
+                continue;
+            }
+            // GNU getopt takes any unambiguous prefix, `--recur` for `--recursive`
+            let flag = format!("--{flag}");
+            if known.iter().any(|v| v.starts_with(&flag)) {
+                continue;
+            }
+            let closest = known
+                .iter()
+                .map(|v| (edit_distance(v, &flag), v))
+                .filter(|(d, _)| *d <= 2)
+                .min();
+            output.push(FlagIssue {
+                tool: tool.name.clone(),
+                flavor: tool.flavor,
+                token: arg.to_string(),
+                replacement: closest.map(|(_, v)| match arg.split_once('=') {
+                    Some((_, value)) => format!("{v}={value}"),
+                    None => v.clone(),
+                }),
+                note: format!("this version has no {flag}"),
+            });
+        }
+    }
+    output
+}
+
+/// `command` with the flags of `issues` replaced, when every one of them has a replacement.
+pub fn apply_flag_fixes(command: &str, issues: &[FlagIssue]) -> Option<String> {
+    if issues.is_empty() {
+        return None;
+    }
+    let mut output = command.to_string();
+    for issue in issues {
+        let replacement = issue.replacement.as_deref()?;
+        let pattern = match issue.token.as_str() {
+            "-i ''" => r#"\s-i\s+(''|"")(?=\s|$)"#.to_string(),
+            token => format!(r"\s{}(?=\s|$)", fancy_regex::escape(token)),
+        };
+        let re = Regex::new(&pattern).ok()?;
+        let replacement = match replacement {
+            "" => String::new(),
+            v => format!(" {v}"),
+        };
+        let fixed = re.replace(&output, replacement.as_str()).to_string();
+        if fixed == output {
+            return None;
+        }
+        output = fixed;
+    }
+    Some(output)
+}
+
+fn known_fix(tool: &ToolInfo, arg: &str) -> Option<FlagIssue> {
+    let (flag, value) = match arg.split_once('=') {
+        Some((flag, value)) if arg.starts_with("--") => (flag, Some(value)),
+        _ => (arg, None),
+    };
+    let fixes = FLAG_FIXES
+        .iter()
+        .filter(|v| v.tool == tool.name && v.flavor == tool.flavor);
+    for fix in fixes {
+        let short = fix.flag.len() == 2 && !fix.flag.starts_with("--");
+        // A short flag also counts inside a cluster like `-rn`, except for `find`'s words
+        let cluster = short
+            && tool.name != "find"
+            && flag.len() > 2
+            && flag.starts_with('-')
+            && !flag.starts_with("--")
+            && flag[1..].chars().all(|c| c.is_ascii_alphabetic())
+            && flag.contains(&fix.flag[1..]);
+        if flag != fix.flag && !cluster {
+            continue;
+        }
+        let replacement = fix.replacement.and_then(|v| match (cluster, v) {
+            (false, v) => Some(match value {
+                Some(value) if v.starts_with("--") => format!("{v}={value}"),
+                Some(value) if !v.is_empty() => format!("{v} {value}"),
+                _ => v.to_string(),
+            }),
+            (true, "") => Some(flag.replace(&fix.flag[1..], "")),
+            (true, v) if v.len() == 2 => Some(flag.replace(&fix.flag[1..], &v[1..])),
+            _ => None,
+        });
+        return Some(FlagIssue {
+            tool: tool.name.clone(),
+            flavor: tool.flavor,
+            token: arg.to_string(),
+            replacement,
+            note: fix.note.to_string(),
+        });
+    }
+    None
+}
+
+fn edit_distance(a: &str, b: &str) -> usize {
+    let b: Vec<char> = b.chars().collect();
+    let mut row: Vec<usize> = (0..=b.len()).collect();
+    for (i, ca) in a.chars().enumerate() {
+        let mut prev = row[0];
+        row[0] = i + 1;
+        for (j, cb) in b.iter().enumerate() {
+            let current = row[j + 1];
+            row[j + 1] = match ca == *cb {
+                true => prev,
+                false => 1 + prev.min(row[j]).min(row[j + 1]),
+            };
+            prev = current;
+        }
+    }
+    row[b.len()]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tool(name: &str, flavor: ToolFlavor) -> ToolInfo {
+        ToolInfo {
+            name: name.into(),
+            flavor,
+            version: String::new(),
+        }
+    }
+
+    fn check(command: &str, toolchain: &[ToolInfo]) -> Vec<FlagIssue> {
+        let mut help = |tool: &ToolInfo| {
+            match tool.name.as_str() {
+            "tar" => Some(parse_help_flags(
+                "  -c, --create\n  -x, --extract\n  -z, --gzip\n  -j, --bzip2\n  -J, --xz\n      --exclude=PATTERN\n  -f, --file=ARCHIVE\n",
+            )),
+            "cp" => Some(parse_help_flags("usage: cp [-R [-H | -L | -P]] [-fi | -n]")),
+            _ => None,
+        }
+        };
+        check_tool_flags(command, &ShellKind::Bash, toolchain, &mut help)
+    }
+
+    #[test]
+    fn test_check_tool_flags() {
+        let bsd = [
+            tool("sed", ToolFlavor::Bsd),
+            tool("du", ToolFlavor::Bsd),
+            tool("xargs", ToolFlavor::Bsd),
+            tool("cp", ToolFlavor::Bsd),
+        ];
+        let command = "sed -ri 's/a+/b/' notes.txt && du --max-depth=1 . | xargs -r echo";
+        let issues = check(command, &bsd);
+        let tokens: Vec<_> = issues.iter().map(|v| v.token.as_str()).collect();
+        assert_eq!(tokens, ["-ri", "--max-depth=1", "-r"]);
+        assert_eq!(
+            issues[0].warning(),
+            "⚠️  Flags: your sed is BSD sed, it has no -r, use -Ei."
+        );
+        assert_eq!(
+            apply_flag_fixes(command, &issues).as_deref(),
+            Some("sed -Ei 's/a+/b/' notes.txt && du -d 1 . | xargs echo")
+        );
+        assert!(check("cp --archive a b", &bsd).is_empty());
+
+        let gnu = [tool("sed", ToolFlavor::Gnu), tool("tar", ToolFlavor::Gnu)];
+        let issues = check("sed -i '' 's/a/b/' f.txt", &gnu);
+        assert_eq!(
+            apply_flag_fixes("sed -i '' 's/a/b/' f.txt", &issues).as_deref(),
+            Some("sed -i 's/a/b/' f.txt")
+        );
+        let issues = check("tar --exlude=node_modules --zstd -cf a.tar src", &gnu);
+        assert_eq!(issues.len(), 2);
+        assert_eq!(
+            issues[0].replacement.as_deref(),
+            Some("--exclude=node_modules")
+        );
+        assert!(issues[1]
+            .warning()
+            .contains("your tar is GNU tar, this version has no --zstd"));
+        assert!(check("tar --ex=node_modules -czf a.tar src", &gnu).is_empty());
+    }
+}
@@ -0,0 +1,276 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+
+/// rsync options whose value is the next word when not given as `--opt=value`.
+const RSYNC_VALUE_FLAGS: [&str; 20] = [
+    "-e",
+    "--rsh",
+    "-f",
+    "--filter",
+    "--exclude",
+    "--include",
+    "--exclude-from",
+    "--include-from",
+    "--files-from",
+    "--port",
+    "-T",
+    "--temp-dir",
+    "--backup-dir",
+    "--partial-dir",
+    "--compare-dest",
+    "--copy-dest",
+    "--link-dest",
+    "--log-file",
+    "--chmod",
+    "--chown",
+];
+
+const SCP_VALUE_FLAGS: [&str; 8] = ["-P", "-i", "-o", "-F", "-J", "-l", "-c", "-S"];
+
+/// An rsync or scp copy, and what it deletes or overwrites at the destination.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TransferCommand {
+    pub tool: String,
+    pub command: String,
+    pub sources: Vec<String>,
+    pub destination: String,
+    /// `host:path`, `host::module` or `rsync://host/module`.
+    pub remote_destination: bool,
+    /// The `--delete*` flag removing what the sources don't have.
+    pub delete_flag: Option<String>,
+    /// `--remove-source-files`, the copy is a move.
+    pub removes_sources: bool,
+    /// `-b`/`--backup` keeps what is deleted or replaced.
+    pub backup: bool,
+    /// `-n`/`--dry-run` only lists what would change.
+    pub dry_run: bool,
+}
+
+impl TransferCommand {
+    pub fn parse(segment: &str, style: PathStyle) -> Option<Self> {
+        let words = shell_words::split(segment).ok()?;
+        let words: Vec<&str> = words
+            .iter()
+            .map(|v| v.as_str())
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        let tool = *words.first()?;
+        let value_flags: &[&str] = match tool {
+            "rsync" => &RSYNC_VALUE_FLAGS,
+            "scp" => &SCP_VALUE_FLAGS,
+            _ => return None,
+        };
+        let mut operands = vec![];
+        let mut delete_flag = None;
+        let (mut removes_sources, mut backup, mut dry_run) = (false, false, false);
+        let mut iter = words[1..].iter();
+        while let Some(word) = iter.next() {
+            if value_flags.contains(word) {
+                iter.next();
+                continue;
+            }
+            match *word {
+                "--remove-source-files" => removes_sources = true,
+                "--backup" => backup = true,
+                "--dry-run" => dry_run = true,
+                v if v == "--del" || v.starts_with("--delete") => {
+                    delete_flag.get_or_insert_with(|| v.to_string());
+                }
+                v if v.starts_with("--") => {}
+                v if v.starts_with('-') && v.len() > 1 => {
+                    if tool == "rsync" {
+                        let letters = v.trim_start_matches('-');
+                        backup |= letters.contains('b');
+                        dry_run |= letters.contains('n');
+                    }
+                }
+                v => operands.push(v.to_string()),
+            }
+        }
+        let (destination, sources) = operands.split_last()?;
+        if sources.is_empty() {
+            return None;
+        }
+        Some(Self {
+            tool: tool.to_string(),
+            command: segment.trim().to_string(),
+            sources: sources.to_vec(),
+            destination: destination.clone(),
+            remote_destination: is_remote(destination, style),
+            delete_flag,
+            removes_sources,
+            backup,
+            dry_run,
+        })
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        if self.dry_run {
+            return CommandOperation::Read;
+        }
+        if self.delete_flag.is_some() {
+            return CommandOperation::Delete;
+        }
+        if self.removes_sources {
+            return CommandOperation::Move;
+        }
+        match self.remote_destination {
+            true => CommandOperation::Network,
+            false => CommandOperation::Copy,
+        }
+    }
+
+    /// Where `--delete` removes files: the destination itself for `src/`, `dst/src` for
+    /// `src` without the trailing slash, since rsync copies the directory, not its contents.
+    pub fn delete_roots(&self) -> Vec<String> {
+        if self.delete_flag.is_none() || self.dry_run {
+            return vec![];
+        }
+        let destination = self.destination.trim_end_matches('/');
+        self.sources
+            .iter()
+            .map(|source| match source.ends_with('/') {
+                true => self.destination.clone(),
+                false => {
+                    let name = source.rsplit(['/', ':']).next().unwrap_or(source);
+                    format!("{destination}/{name}")
+                }
+            })
+            .collect()
+    }
+
+    /// The local files the command deletes, replaces or moves away, for the affected files.
+    pub fn local_targets(&self) -> Vec<String> {
+        let mut output = vec![];
+        if self.dry_run {
+            return output;
+        }
+        if !self.remote_destination {
+            match self.delete_flag.is_some() {
+                true => output.extend(self.delete_roots()),
+                false => output.push(self.destination.clone()),
+            }
+        }
+        if self.removes_sources {
+            output.extend(self.sources.iter().filter(|v| !v.contains(':')).cloned());
+        }
+        output
+    }
+
+    /// Deletes under a system root such as `/` or `/etc`, or the whole home directory.
+    pub fn deletes_protected(&self, style: PathStyle) -> bool {
+        self.delete_roots().iter().any(|v| {
+            let path = v.rsplit_once(':').map(|(_, v)| v).unwrap_or(v);
+            is_protected_path(path, style)
+                || matches!(path.trim_end_matches('/'), "~" | "$HOME" | "${HOME}")
+        })
+    }
+
+    /// rsync with `--dry-run --itemize-changes`, listing every copy and deletion.
+    pub fn dry_run_variant(&self) -> Option<String> {
+        if self.tool != "rsync" || self.dry_run || self.operation() != CommandOperation::Delete {
+            return None;
+        }
+        let mut words = shell_words::split(&self.command).ok()?;
+        let at = words.iter().position(|v| v == "rsync")? + 1;
+        words.insert(at, "--itemize-changes".to_string());
+        words.insert(at, "--dry-run".to_string());
+        Some(shell_words::join(words))
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        if self.dry_run {
+            return vec![];
+        }
+        let mut output = vec![];
+        if let Some(flag) = &self.delete_flag {
+            let sources = self.sources.join(", ");
+            for root in self.delete_roots() {
+                output.push(format!(
+                    "⚠️  {}: {flag} removes everything in {root} that isn't in {sources}!",
+                    self.tool
+                ));
+            }
+            if self.backup {
+                output.push("💡 --backup keeps the deleted files next to them.".to_string());
+            }
+        }
+        if self.tool == "rsync" {
+            let directories: Vec<&String> = self
+                .sources
+                .iter()
+                .filter(|v| !v.ends_with('/') && !v.contains(['*', '?']))
+                .collect();
+            if self.delete_flag.is_some() && !directories.is_empty() {
+                let destination = self.destination.trim_end_matches('/');
+                output.push(format!(
+                    "💡 Without a trailing slash rsync copies {} itself into {destination}/, add `/` to sync its contents instead.",
+                    directories[0]
+                ));
+            }
+        }
+        if self.removes_sources {
+            output.push(format!(
+                "⚠️  {}: --remove-source-files deletes the sources once they are copied.",
+                self.tool
+            ));
+        }
+        if self.remote_destination && self.tool == "scp" {
+            output.push(format!(
+                "⚠️  scp: overwrites {} without asking, there is no backup on the remote side.",
+                self.destination
+            ));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> TransferCommand {
+        TransferCommand::parse(command, PathStyle::Unix).unwrap()
+    }
+
+    #[test]
+    fn test_transfer_command() {
+        assert!(TransferCommand::parse("rsync -av src/", PathStyle::Unix).is_none());
+        assert!(TransferCommand::parse("cp a b", PathStyle::Unix).is_none());
+
+        let rsync = parse("rsync -a --delete src/ dst/");
+        assert_eq!(rsync.operation(), CommandOperation::Delete);
+        assert_eq!(rsync.delete_roots(), ["dst/"]);
+        assert_eq!(
+            rsync.dry_run_variant().unwrap(),
+            "rsync --dry-run --itemize-changes -a --delete src/ dst/"
+        );
+
+        let rsync = parse("rsync -a --delete-after -e 'ssh -p 2222' build web1:/var/www");
+        assert!(rsync.remote_destination);
+        assert_eq!(rsync.delete_roots(), ["web1:/var/www/build"]);
+        assert!(rsync.local_targets().is_empty());
+        assert!(rsync.warnings()[1].contains("copies build itself into web1:/var/www/"));
+
+        assert!(parse("rsync -a --delete empty/ ~/").deletes_protected(PathStyle::Unix));
+        assert!(parse("sudo rsync -a --delete empty/ /etc").deletes_protected(PathStyle::Unix));
+        assert_eq!(
+            parse("rsync -avn --delete a/ b/").operation(),
+            CommandOperation::Read
+        );
+
+        let rsync = parse("rsync -a --remove-source-files logs/ /mnt/archive/");
+        assert_eq!(rsync.operation(), CommandOperation::Move);
+        assert_eq!(rsync.local_targets(), ["/mnt/archive/", "logs/"]);
+
+        let scp = parse("scp -P 2222 app.conf deploy@web1:/etc/app.conf");
+        assert_eq!(scp.operation(), CommandOperation::Network);
+        assert_eq!(scp.sources, ["app.conf"]);
+        assert!(scp.warnings()[0].starts_with("⚠️  scp: overwrites deploy@web1:/etc/app.conf"));
+        assert_eq!(
+            parse("scp web1:/var/log/app.log .").operation(),
+            CommandOperation::Copy
+        );
+    }
+}
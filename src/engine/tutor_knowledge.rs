@@ -0,0 +1,175 @@
+use anyhow::{Context, Result};
+use parking_lot::RwLock;
+use serde::Deserialize;
+use std::{
+    collections::HashMap,
+    fs,
+    path::Path,
+    sync::{Arc, LazyLock},
+};
+
+/// What the tutor knows out of the box, see `assets/tutor.yaml`. YAML like the config and
+/// the other built-in assets, rather than a format of its own.
+const BUILTIN_TUTOR_KNOWLEDGE: &str = include_str!("../../assets/tutor.yaml");
+
+static KNOWLEDGE: LazyLock<RwLock<Arc<TutorKnowledge>>> =
+    LazyLock::new(|| RwLock::new(Arc::new(TutorKnowledge::builtin())));
+
+/// The knowledge the tutor explains commands with, the built-in one until
+/// [`set_tutor_knowledge`] runs.
+pub fn tutor_knowledge() -> Arc<TutorKnowledge> {
+    KNOWLEDGE.read().clone()
+}
+
+pub fn set_tutor_knowledge(knowledge: TutorKnowledge) {
+    *KNOWLEDGE.write() = Arc::new(knowledge);
+}
+
+/// Descriptions of commands, their subcommands, flags and long options.
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct TutorKnowledge {
+    /// Long options of any command, unless the command describes them itself.
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+    #[serde(default)]
+    pub commands: HashMap<String, CommandKnowledge>,
+}
+
+#[derive(Debug, Clone, Default, Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct CommandKnowledge {
+    #[serde(default)]
+    pub description: String,
+    /// Shown under the environment context, e.g. which systems a package manager belongs to.
+    pub note: Option<String>,
+    /// The first argument, like `commit` of `git commit`.
+    #[serde(default)]
+    pub subcommands: HashMap<String, String>,
+    /// Short flags, or single-dash words like `-name` of `find`.
+    #[serde(default)]
+    pub flags: HashMap<String, String>,
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+impl TutorKnowledge {
+    pub fn builtin() -> Self {
+        Self::parse(BUILTIN_TUTOR_KNOWLEDGE).expect("Invalid built-in tutor knowledge")
+    }
+
+    pub fn parse(yaml: &str) -> Result<Self> {
+        Ok(serde_yaml::from_str(yaml)?)
+    }
+
+    /// The built-in knowledge extended with the file at `path`, if it exists.
+    pub fn load(path: &Path) -> Result<Self> {
+        let mut knowledge = Self::builtin();
+        if !path.exists() {
+            return Ok(knowledge);
+        }
+        let custom = fs::read_to_string(path)
+            .map_err(anyhow::Error::from)
+            .and_then(|v| Self::parse(&v))
+            .with_context(|| format!("Failed to load tutor knowledge at {}", path.display()))?;
+        knowledge.merge(custom);
+        Ok(knowledge)
+    }
+
+    /// Entries of `other` replace the ones with the same name, a command's description and
+    /// note only when `other` sets them.
+    pub fn merge(&mut self, other: TutorKnowledge) {
+        self.options.extend(other.options);
+        for (name, custom) in other.commands {
+            let command = self.commands.entry(name).or_default();
+            if !custom.description.is_empty() {
+                command.description = custom.description;
+            }
+            if custom.note.is_some() {
+                command.note = custom.note;
+            }
+            command.subcommands.extend(custom.subcommands);
+            command.flags.extend(custom.flags);
+            command.options.extend(custom.options);
+        }
+    }
+
+    /// The command by name, also when it is run by path like `/usr/bin/rm`.
+    pub fn command(&self, name: &str) -> Option<&CommandKnowledge> {
+        self.commands
+            .get(name)
+            .or_else(|| self.commands.get(name.rsplit(['/', '\\']).next()?))
+    }
+
+    pub fn description(&self, command: &str) -> Option<&str> {
+        self.command(command)
+            .map(|v| v.description.as_str())
+            .filter(|v| !v.is_empty())
+    }
+
+    pub fn subcommand(&self, command: &str, word: &str) -> Option<&str> {
+        self.command(command)?
+            .subcommands
+            .get(word)
+            .map(|v| v.as_str())
+    }
+
+    pub fn flag(&self, command: &str, flag: &str) -> Option<&str> {
+        self.command(command)?.flags.get(flag).map(|v| v.as_str())
+    }
+
+    /// A long option, with or without its `=value`.
+    pub fn option(&self, command: &str, option: &str) -> Option<&str> {
+        let name = option.split('=').next().unwrap_or(option);
+        self.command(command)
+            .and_then(|v| v.options.get(name))
+            .or_else(|| self.options.get(name))
+            .map(|v| v.as_str())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_tutor_knowledge() {
+        let mut knowledge = TutorKnowledge::builtin();
+        assert!(knowledge.commands.len() > 300);
+        assert_eq!(
+            knowledge.description("/bin/rm"),
+            Some("Remove files or directories")
+        );
+        assert_eq!(knowledge.flag("ls", "-1"), Some("One entry per line"));
+        assert_eq!(
+            knowledge.option("ls", "--color=never"),
+            Some("When to color the entries by type")
+        );
+        assert_eq!(
+            knowledge.option("rm", "--help"),
+            Some("Display help information")
+        );
+        assert_eq!(
+            knowledge.subcommand("git", "stash"),
+            Some("Set aside uncommitted changes")
+        );
+
+        let custom = TutorKnowledge::parse(
+            "commands:\n  rm:\n    flags: {-f: Never ask}\n  deploy:\n    description: Ship to staging\n",
+        )
+        .unwrap();
+        knowledge.merge(custom);
+        assert_eq!(knowledge.flag("rm", "-f"), Some("Never ask"));
+        assert_eq!(
+            knowledge.flag("rm", "-r"),
+            Some("Recursive - remove directories and contents")
+        );
+        assert_eq!(
+            knowledge.description("rm"),
+            Some("Remove files or directories")
+        );
+        assert_eq!(knowledge.description("deploy"), Some("Ship to staging"));
+
+        assert!(TutorKnowledge::parse("commands:\n  rm:\n    flag: {-f: x}\n").is_err());
+    }
+}
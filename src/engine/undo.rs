@@ -0,0 +1,490 @@
+use super::*;
+
+use std::path::Path;
+
+/// The command that reverses `command`, synthesized before it runs (afterwards the
+/// state it depends on, e.g. whether a target existed, is gone). Only changes the prior
+/// state shows will happen are reversed: an install of a package that's missing, a start
+/// of a unit that's stopped, a stash of uncommitted changes. Commands chained with `&&`
+/// are reversed back to front. `None` when any part can't be reversed reliably.
+pub fn inverse_command(command: &str, shell: &ShellKind, fs: &dyn FileSystem) -> Option<String> {
+    if matches!(shell, ShellKind::PowerShell | ShellKind::Cmd) {
+        return None;
+    }
+    // Pipelines, output redirections and substitutions have effects we can't see.
+    if command.contains(['|', '>', '`']) || command.contains("$(") {
+        return None;
+    }
+    // After `;`, `||` or `&` a part may have run or not whatever the others did.
+    if has_unconditional_chain(command.trim()) {
+        return None;
+    }
+    let mut inverses = split_command_segments(command, shell)
+        .iter()
+        .filter(|v| !v.trim().is_empty())
+        .map(|v| inverse_segment(v, fs))
+        .collect::<Option<Vec<_>>>()?;
+    if inverses.is_empty() {
+        return None;
+    }
+    inverses.reverse();
+    Some(inverses.join(" && "))
+}
+
+fn inverse_segment(segment: &str, fs: &dyn FileSystem) -> Option<String> {
+    let mut words = shell_words::split(segment).ok()?;
+    let sudo = words.first().is_some_and(|v| v == "sudo");
+    if sudo {
+        words.remove(0);
+    }
+    let inverse = match words.first()?.as_str() {
+        "mv" => inverse_mv(&words, fs)?,
+        "cp" => inverse_cp(&words, fs)?,
+        "mkdir" => {
+            let (flags, args) = split_flags(&words[1..]);
+            if !flags.is_empty() || args.is_empty() || args.iter().any(|v| fs.exists(Path::new(v)))
+            {
+                return None;
+            }
+            ["rmdir"]
+                .into_iter()
+                .chain(args)
+                .map(String::from)
+                .collect()
+        }
+        "touch" => {
+            let (flags, args) = split_flags(&words[1..]);
+            // Touching an existing file only bumps its timestamps.
+            if !flags.is_empty() || args.is_empty() || args.iter().any(|v| fs.exists(Path::new(v)))
+            {
+                return None;
+            }
+            ["rm"].into_iter().chain(args).map(String::from).collect()
+        }
+        "ln" => {
+            let (flags, args) = split_flags(&words[1..]);
+            if flags.iter().any(|v| v.contains('f')) || args.len() != 2 {
+                return None;
+            }
+            let link = target_path(fs, args[0], args[1]);
+            if fs.exists(Path::new(&link)) {
+                return None;
+            }
+            vec!["rm".into(), link]
+        }
+        "patch" => toggle_reverse(&words, 1)?,
+        "git" => inverse_git(&words, fs)?,
+        "systemctl" => inverse_systemctl(&words, fs)?,
+        "chmod" => inverse_chmod(&words, fs)?,
+        _ => inverse_package(&words, fs)?,
+    };
+    let inverse = join_words(&inverse);
+    Some(match sudo {
+        true => format!("sudo {inverse}"),
+        false => inverse,
+    })
+}
+
+fn inverse_mv(words: &[String], fs: &dyn FileSystem) -> Option<Vec<String>> {
+    let (flags, args) = split_flags(&words[1..]);
+    if flags.iter().any(|v| !matches!(*v, "-v" | "-i" | "-n")) || args.len() != 2 {
+        return None;
+    }
+    let (source, target) = (args[0], args[1]);
+    // Moving over an existing file loses it, which only a file backup can bring back, and
+    // that goes for a file of the same name in a target directory too.
+    let moved = target_path(fs, source, target);
+    if fs.exists(Path::new(&moved)) {
+        return None;
+    }
+    Some(vec!["mv".into(), moved, source.into()])
+}
+
+fn inverse_cp(words: &[String], fs: &dyn FileSystem) -> Option<Vec<String>> {
+    let (flags, args) = split_flags(&words[1..]);
+    if flags
+        .iter()
+        .any(|v| !matches!(*v, "-v" | "-r" | "-R" | "-a" | "-p" | "-rv" | "-av"))
+        || args.len() != 2
+    {
+        return None;
+    }
+    let copy = target_path(fs, args[0], args[1]);
+    if fs.exists(Path::new(&copy)) {
+        return None;
+    }
+    let mut inverse = vec!["rm".to_string()];
+    if !flags.is_empty() && flags.iter().any(|v| v.contains(['r', 'R', 'a'])) {
+        inverse.push("-r".into());
+    }
+    inverse.push(copy);
+    Some(inverse)
+}
+
+fn inverse_git(words: &[String], fs: &dyn FileSystem) -> Option<Vec<String>> {
+    let sub = words.get(1)?.as_str();
+    let (flags, args) = split_flags(&words[2..]);
+    let cwd = fs.current_dir();
+    let inverse: Vec<&str> = match sub {
+        "apply" => return toggle_reverse(words, 2),
+        "commit" if !flags.contains(&"--amend") => {
+            vec!["git", "reset", "--soft", "HEAD~1"]
+        }
+        // With nothing to stash no stash is made, and popping would take an older one
+        "stash" if args.is_empty() || args == ["push"] => match fs.has_uncommitted_changes(&cwd) {
+            true => vec!["git", "stash", "pop"],
+            false => return None,
+        },
+        "tag" if flags.is_empty() && args.len() == 1 => vec!["git", "tag", "-d", args[0]],
+        "branch" if flags.is_empty() && args.len() == 1 => vec!["git", "branch", "-d", args[0]],
+        // Unstaging would also drop what was staged before
+        "add" if !args.is_empty() => {
+            let staged = args
+                .iter()
+                .map(|v| fs.has_staged_changes(&cwd.join(v)))
+                .collect::<Option<Vec<_>>>()?;
+            if staged.contains(&true) {
+                return None;
+            }
+            ["git", "restore", "--staged"]
+                .into_iter()
+                .chain(args)
+                .collect()
+        }
+        _ => return None,
+    };
+    Some(inverse.into_iter().map(String::from).collect())
+}
+
+/// Reverses a change of the units' state only when all of them are in the state it
+/// changes, e.g. every unit started is stopped now.
+fn inverse_systemctl(words: &[String], fs: &dyn FileSystem) -> Option<Vec<String>> {
+    let action = words
+        .iter()
+        .position(|v| !v.starts_with('-') && v != "systemctl")?;
+    let (flags, units) = split_flags(&words[action + 1..]);
+    if units.is_empty() {
+        return None;
+    }
+    let user = flags.contains(&"--user");
+    let states = units
+        .iter()
+        .map(|v| fs.unit_state(v, user))
+        .collect::<Option<Vec<_>>>()?;
+    let all = |f: fn(&UnitState) -> bool| states.iter().all(f);
+    let (inverse, changes, now_changes) = match words[action].as_str() {
+        "start" => ("stop", all(|v| !v.active), false),
+        "stop" => ("start", all(|v| v.active), false),
+        "enable" => (
+            "disable",
+            all(|v| !v.enabled && !v.masked),
+            all(|v| !v.active),
+        ),
+        "disable" => ("enable", all(|v| v.enabled), all(|v| v.active)),
+        "mask" => ("unmask", all(|v| !v.masked), false),
+        "unmask" => ("mask", all(|v| v.masked), false),
+        _ => return None,
+    };
+    if !changes {
+        return None;
+    }
+    let mut words = words.to_vec();
+    words[action] = inverse.into();
+    // `--now` is reversed too when it started or stopped every unit
+    if !now_changes {
+        words.retain(|v| v != "--now");
+    }
+    Some(words)
+}
+
+/// Restores the current mode of a single file.
+fn inverse_chmod(words: &[String], fs: &dyn FileSystem) -> Option<Vec<String>> {
+    let (flags, args) = split_flags(&words[1..]);
+    if !flags.is_empty() || args.len() != 2 {
+        return None;
+    }
+    let mode = fs.metadata(Path::new(args[1]))?.mode?;
+    Some(vec!["chmod".into(), format!("{mode:o}"), args[1].into()])
+}
+
+/// Uninstalls the packages an install adds, when none of them is installed yet.
+fn inverse_package(words: &[String], fs: &dyn FileSystem) -> Option<Vec<String>> {
+    let tool = words.first()?.as_str();
+    let action = words.get(1)?.as_str();
+    let (flags, args) = split_flags(&words[2..]);
+    let (remove, keep_flags): (&[&str], &[&str]) = match (tool, action) {
+        ("apt" | "apt-get", "install") => (&["remove"], &["-y"]),
+        ("dnf" | "yum", "install") => (&["remove"], &["-y"]),
+        ("apk", "add") => (&["del"], &[]),
+        ("brew", "install") => (&["uninstall"], &["--cask"]),
+        ("npm", "install" | "i" | "add") => (&["uninstall"], &["-g", "--global"]),
+        ("yarn" | "pnpm", "add") => (&["remove"], &["-g", "--global"]),
+        ("pip" | "pip3", "install") => (&["uninstall", "-y"], &["--user"]),
+        ("cargo", "install") => (&["uninstall"], &[]),
+        ("gem", "install") => (&["uninstall"], &[]),
+        ("snap", "install") => (&["remove"], &[]),
+        ("choco", "install") => (&["uninstall"], &["-y"]),
+        ("pacman", "-S") => (&["-R"], &["--noconfirm"]),
+        _ => return None,
+    };
+    // Installs from requirement files, paths or URLs don't name the packages.
+    if args.is_empty()
+        || flags
+            .iter()
+            .any(|v| matches!(*v, "-r" | "-e" | "--requirement"))
+        || args
+            .iter()
+            .any(|v| v.contains(['/', ':']) && !v.starts_with('@'))
+    {
+        return None;
+    }
+    let global = flags.iter().any(|v| matches!(*v, "-g" | "--global"));
+    for arg in &args {
+        if fs.package_installed(tool, package_name(arg), global) != Some(false) {
+            return None;
+        }
+    }
+    let mut inverse = vec![tool.to_string()];
+    inverse.extend(remove.iter().map(|v| v.to_string()));
+    inverse.extend(
+        flags
+            .iter()
+            .filter(|v| keep_flags.contains(v))
+            .map(|v| v.to_string()),
+    );
+    inverse.extend(args.iter().map(|v| package_name(v).to_string()));
+    Some(inverse)
+}
+
+/// `requests==2.31`, `lodash@4` or `@types/node@20` without the version.
+fn package_name(spec: &str) -> &str {
+    let spec = spec
+        .split(['=', '<', '>', '~', '!', '['])
+        .next()
+        .unwrap_or(spec);
+    match spec.rfind('@') {
+        Some(i) if i > 0 => &spec[..i],
+        _ => spec,
+    }
+}
+
+/// An unquoted `;`, `||`, `&` or line break between commands.
+fn has_unconditional_chain(command: &str) -> bool {
+    let mut quote = None;
+    let mut chars = command.chars().peekable();
+    while let Some(c) = chars.next() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), _) => {}
+            (None, '\'' | '"') => quote = Some(c),
+            (None, '\\') => {
+                chars.next();
+            }
+            (None, ';' | '\n') => return true,
+            (None, '|') if chars.peek() == Some(&'|') => return true,
+            (None, '&') => match chars.peek() {
+                Some('&') => {
+                    chars.next();
+                }
+                _ => return true,
+            },
+            _ => {}
+        }
+    }
+    false
+}
+
+/// Add `-R` after the first `index` words, or drop it when it's already there.
+fn toggle_reverse(words: &[String], index: usize) -> Option<Vec<String>> {
+    let mut words = words.to_vec();
+    let reversed = words.len();
+    words.retain(|v| !matches!(v.as_str(), "-R" | "--reverse"));
+    if words.len() == reversed {
+        words.insert(index.min(words.len()), "-R".into());
+    }
+    Some(words)
+}
+
+/// Flags and positional arguments; everything after `--` is positional.
+fn split_flags(words: &[String]) -> (Vec<&str>, Vec<&str>) {
+    let mut flags = vec![];
+    let mut args = vec![];
+    let mut positional = false;
+    for word in words {
+        if word == "--" {
+            positional = true;
+        } else if !positional && word.starts_with('-') && word.len() > 1 {
+            flags.push(word.as_str());
+        } else {
+            args.push(word.as_str());
+        }
+    }
+    (flags, args)
+}
+
+/// Where `source` ends up when copied or moved to `target`.
+fn target_path(fs: &dyn FileSystem, source: &str, target: &str) -> String {
+    if !fs.is_dir(Path::new(target)) {
+        return target.to_string();
+    }
+    let name = Path::new(source.trim_end_matches('/'))
+        .file_name()
+        .map(|v| v.to_string_lossy().to_string())
+        .unwrap_or_default();
+    format!("{}/{name}", target.trim_end_matches('/'))
+}
+
+fn join_words(words: &[String]) -> String {
+    words
+        .iter()
+        .map(|v| {
+            match v.is_empty() || v.contains(|c: char| c.is_whitespace() || "'\"$`\\".contains(c)) {
+                true => shell_words::quote(v).to_string(),
+                false => v.clone(),
+            }
+        })
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn inverse(command: &str) -> Option<String> {
+        inverse_command(command, &ShellKind::Bash, &RealFs)
+    }
+
+    #[test]
+    fn test_inverse_command() {
+        let dir = env::temp_dir().join(format!("aichat-undo-{}", process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let dir = dir.display().to_string();
+
+        assert_eq!(
+            inverse(&format!("mv {dir}/a.txt {dir}/b.txt")),
+            Some(format!("mv {dir}/b.txt {dir}/a.txt"))
+        );
+        assert_eq!(
+            inverse(&format!("mv notes.md {dir}")),
+            Some(format!("mv {dir}/notes.md notes.md"))
+        );
+        assert_eq!(
+            inverse(&format!("mkdir {dir}/new && touch '{dir}/new/a b'")),
+            Some(format!("rm '{dir}/new/a b' && rmdir {dir}/new"))
+        );
+        assert_eq!(inverse(&format!("mkdir {dir}")), None);
+        assert_eq!(
+            inverse("git apply fix.patch"),
+            Some("git apply -R fix.patch".into())
+        );
+        assert_eq!(
+            inverse("patch -p1 -R < fix.diff"),
+            Some("patch -p1 < fix.diff".into())
+        );
+        assert_eq!(
+            inverse("git commit -m 'wip'"),
+            Some("git reset --soft HEAD~1".into())
+        );
+        assert_eq!(inverse("pip install -r requirements.txt"), None);
+        assert_eq!(inverse("npm install"), None);
+        assert_eq!(inverse("rm -rf build"), None);
+        assert_eq!(inverse("mkdir out && make > out/log"), None);
+        assert_eq!(
+            inverse_command("git commit -m x", &ShellKind::PowerShell, &RealFs),
+            None
+        );
+        let _ = std::fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn test_inverse_needs_prior_state() {
+        let inverse = |command: &str, fs: &MemoryFs| inverse_command(command, &ShellKind::Bash, fs);
+        let running = UnitState {
+            active: true,
+            enabled: true,
+            masked: false,
+        };
+        let fs = MemoryFs::new()
+            .cwd("/repo")
+            .file("/repo/notes.md", 10)
+            .file("/repo/docs/notes.md", 10)
+            .package("apt-get", "curl")
+            .unit("nginx", UnitState::default())
+            .unit("sshd", running);
+
+        // Only packages that aren't installed yet are uninstalled
+        assert_eq!(
+            inverse("sudo apt-get install -y nginx", &fs),
+            Some("sudo apt-get remove -y nginx".into())
+        );
+        assert_eq!(inverse("sudo apt-get install -y nginx curl", &fs), None);
+        assert_eq!(
+            inverse("pip install requests==2.31 'rich~=13'", &fs),
+            Some("pip uninstall -y requests rich".into())
+        );
+        assert_eq!(
+            inverse("npm install -g @angular/cli@17 -D", &fs),
+            Some("npm uninstall -g @angular/cli".into())
+        );
+
+        // Units are only stopped again when they were stopped
+        assert_eq!(
+            inverse("sudo systemctl start nginx", &fs),
+            Some("sudo systemctl stop nginx".into())
+        );
+        assert_eq!(inverse("sudo systemctl start sshd", &fs), None);
+        assert_eq!(
+            inverse("sudo systemctl enable --now nginx", &fs),
+            Some("sudo systemctl disable --now nginx".into())
+        );
+        assert_eq!(
+            inverse("sudo systemctl disable --now sshd", &fs),
+            Some("sudo systemctl enable --now sshd".into())
+        );
+        assert_eq!(inverse("sudo systemctl enable sshd", &fs), None);
+        assert_eq!(inverse("systemctl start cron", &fs), None);
+
+        // A stash is only made with changes to stash
+        assert_eq!(inverse("git stash", &fs), None);
+        let dirty = fs.clone().uncommitted("/repo/notes.md");
+        assert_eq!(inverse("git stash", &dirty), Some("git stash pop".into()));
+
+        // Unstaging would drop what was staged before
+        assert_eq!(
+            inverse("git add docs", &fs),
+            Some("git restore --staged docs".into())
+        );
+        let staged = fs.clone().staged("/repo/docs/notes.md");
+        assert_eq!(inverse("git add docs", &staged), None);
+
+        // A file of the same name in the target directory would be overwritten
+        assert_eq!(inverse("mv /repo/notes.md /repo/docs", &fs), None);
+        assert_eq!(
+            inverse("mv /repo/notes.md /repo/archive.md", &fs),
+            Some("mv /repo/archive.md /repo/notes.md".into())
+        );
+
+        // Parts after `;`, `||` and `&` may never have run
+        assert_eq!(inverse("mkdir /repo/a; mkdir /repo/b", &fs), None);
+        assert_eq!(inverse("mkdir /repo/a || mkdir /repo/b", &fs), None);
+        assert_eq!(inverse("mkdir /repo/a & mkdir /repo/b", &fs), None);
+        assert_eq!(inverse("mkdir /repo/a\nmkdir /repo/b", &fs), None);
+        assert_eq!(
+            inverse("mkdir /repo/a && mkdir /repo/b", &fs),
+            Some("rmdir /repo/b && rmdir /repo/a".into())
+        );
+        assert_eq!(
+            inverse("git commit -m 'a; b'", &fs),
+            Some("git reset --soft HEAD~1".into())
+        );
+    }
+
+    #[test]
+    fn test_package_name() {
+        assert_eq!(package_name("lodash@4.17"), "lodash");
+        assert_eq!(package_name("@types/node"), "@types/node");
+        assert_eq!(package_name("@types/node@20"), "@types/node");
+        assert_eq!(package_name("uvicorn[standard]==0.30"), "uvicorn");
+    }
+}
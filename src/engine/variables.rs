@@ -0,0 +1,170 @@
+use super::*;
+
+use fancy_regex::{Captures, Regex};
+use serde::Serialize;
+use std::sync::LazyLock;
+
+/// `$env:NAME` (PowerShell), `${NAME}`, `${NAME:-default}` and `$NAME`.
+static VARIABLE_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"\$(?:env:([A-Za-z_][A-Za-z0-9_]*)|\{([A-Za-z_][A-Za-z0-9_]*)(?::?-([^}]*))?\}|([A-Za-z_][A-Za-z0-9_]*))",
+    )
+    .unwrap()
+});
+
+/// A variable in a path argument that isn't set, so the shell drops it from the path.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct UnsetVariable {
+    pub name: String,
+    /// The argument as written, `$BUILD_DIR/cache`.
+    pub word: String,
+    /// What it expands to, `/cache`.
+    pub expanded: String,
+}
+
+impl UnsetVariable {
+    pub fn warning(&self) -> String {
+        match self.expanded.is_empty() {
+            true => format!(
+                "⚠️  ${} is not set, so `{}` expands to nothing!",
+                self.name, self.word
+            ),
+            false => format!(
+                "⚠️  ${} is not set, so `{}` expands to `{}`!",
+                self.name, self.word, self.expanded
+            ),
+        }
+    }
+}
+
+/// `word` with its variables replaced by their values in `fs`, unset ones by nothing or
+/// their `${NAME:-default}`, like the shell expands an unquoted word.
+pub fn expand_variables(word: &str, fs: &dyn FileSystem) -> String {
+    if !word.contains('$') {
+        return word.to_string();
+    }
+    VARIABLE_RE
+        .replace_all(word, |caps: &Captures| {
+            let name = variable_name(caps);
+            match (variable_value(name, fs), caps.get(3)) {
+                (Some(value), Some(default)) if value.is_empty() => default.as_str().to_string(),
+                (Some(value), _) => value,
+                (None, Some(default)) => default.as_str().to_string(),
+                (None, None) => String::new(),
+            }
+        })
+        .to_string()
+}
+
+/// The path arguments of `command` that lose a variable because it isn't set in `fs` or
+/// by the command itself. Single-quoted words are literal.
+pub fn unset_path_variables(
+    command: &str,
+    style: PathStyle,
+    fs: &dyn FileSystem,
+) -> Vec<UnsetVariable> {
+    let mut output: Vec<UnsetVariable> = vec![];
+    let tokens = tokenize(command, style);
+    for token in tokens.iter().filter(|v| v.is_word()) {
+        if !token.text.contains('$')
+            || token.text.starts_with('-')
+            || token.raw(command).starts_with('\'')
+            || is_assignment_word(&token.text)
+        {
+            continue;
+        }
+        for caps in VARIABLE_RE.captures_iter(&token.text).flatten() {
+            let name = variable_name(&caps);
+            if caps.get(3).is_some()
+                || variable_value(name, fs).is_some()
+                || assigns(command, name)
+                || output.iter().any(|v| v.word == token.text)
+            {
+                continue;
+            }
+            output.push(UnsetVariable {
+                name: name.to_string(),
+                word: token.text.clone(),
+                expanded: expand_variables(&token.text, fs),
+            });
+        }
+    }
+    output
+}
+
+fn variable_name<'a>(caps: &'a Captures) -> &'a str {
+    caps.get(1)
+        .or(caps.get(2))
+        .or(caps.get(4))
+        .map(|v| v.as_str())
+        .unwrap_or_default()
+}
+
+/// The shell sets `HOME` and `PWD` itself, from the machine `fs` describes.
+fn variable_value(name: &str, fs: &dyn FileSystem) -> Option<String> {
+    match name {
+        "HOME" => fs
+            .home_dir()
+            .map(|v| v.display().to_string())
+            .or_else(|| fs.env_var(name)),
+        "PWD" => Some(fs.current_dir().display().to_string()),
+        _ => fs.env_var(name),
+    }
+}
+
+/// `NAME=value` before a command or on its own.
+fn is_assignment_word(word: &str) -> bool {
+    word.split_once('=').is_some_and(|(name, _)| {
+        !name.is_empty() && name.chars().all(|c| c.is_ascii_alphanumeric() || c == '_')
+    })
+}
+
+/// `NAME=`, `export NAME=`, `for NAME in` or `read NAME` earlier in the command.
+fn assigns(command: &str, name: &str) -> bool {
+    let name = fancy_regex::escape(name);
+    Regex::new(&format!(
+        r"(?:^|[\s;&|(])(?:(?:export|local|declare|readonly)\s+)?{name}=|\b(?:for|read(?:\s+-\w+)*)\s+{name}\b"
+    ))
+    .is_ok_and(|re| re.is_match(command).unwrap_or_default())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_variables() {
+        let fs = MemoryFs::new()
+            .home("/home/me")
+            .cwd("/work")
+            .env("BUILD_DIR", "/work/build")
+            .env("EMPTY", "");
+        assert_eq!(
+            expand_variables("$BUILD_DIR/cache", &fs),
+            "/work/build/cache"
+        );
+        assert_eq!(expand_variables("${HOME}/.cache", &fs), "/home/me/.cache");
+        assert_eq!(expand_variables("${OUT:-dist}/app", &fs), "dist/app");
+        assert_eq!(expand_variables("${EMPTY:-x}", &fs), "x");
+        assert_eq!(expand_variables("$UNSET/", &fs), "/");
+        assert_eq!(expand_variables(r"$env:BUILD_DIR\x", &fs), r"/work/build\x");
+
+        let unset = unset_path_variables(
+            "rm -rf $UNSET/ '$LITERAL' ${OUT:-dist} $BUILD_DIR && TMP=/tmp; rm $TMP/x",
+            PathStyle::Unix,
+            &fs,
+        );
+        assert_eq!(
+            unset,
+            [UnsetVariable {
+                name: "UNSET".to_string(),
+                word: "$UNSET/".to_string(),
+                expanded: "/".to_string(),
+            }]
+        );
+        assert_eq!(
+            unset[0].warning(),
+            "⚠️  $UNSET is not set, so `$UNSET/` expands to `/`!"
+        );
+    }
+}
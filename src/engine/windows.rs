@@ -0,0 +1,436 @@
+use super::*;
+
+use serde::Serialize;
+
+/// PowerShell aliases of the cmdlets below, as defined out of the box.
+const POWERSHELL_ALIASES: [(&str, &str); 31] = [
+    ("ri", "Remove-Item"),
+    ("rm", "Remove-Item"),
+    ("rmdir", "Remove-Item"),
+    ("rd", "Remove-Item"),
+    ("del", "Remove-Item"),
+    ("erase", "Remove-Item"),
+    ("rp", "Remove-ItemProperty"),
+    ("clc", "Clear-Content"),
+    ("mi", "Move-Item"),
+    ("mv", "Move-Item"),
+    ("move", "Move-Item"),
+    ("rni", "Rename-Item"),
+    ("ren", "Rename-Item"),
+    ("cpi", "Copy-Item"),
+    ("cp", "Copy-Item"),
+    ("copy", "Copy-Item"),
+    ("ni", "New-Item"),
+    ("sc", "Set-Content"),
+    ("ac", "Add-Content"),
+    ("sp", "Set-ItemProperty"),
+    ("iex", "Invoke-Expression"),
+    ("iwr", "Invoke-WebRequest"),
+    ("curl", "Invoke-WebRequest"),
+    ("wget", "Invoke-WebRequest"),
+    ("irm", "Invoke-RestMethod"),
+    ("saps", "Start-Process"),
+    ("start", "Start-Process"),
+    ("spps", "Stop-Process"),
+    ("gci", "Get-ChildItem"),
+    ("ls", "Get-ChildItem"),
+    ("dir", "Get-ChildItem"),
+];
+
+/// PowerShell switches, which never take the next word as their value.
+const POWERSHELL_SWITCHES: [&str; 9] = [
+    "recurse",
+    "force",
+    "append",
+    "whatif",
+    "confirm",
+    "nonewline",
+    "verbose",
+    "passthru",
+    "wait",
+];
+
+/// A PowerShell cmdlet or cmd.exe builtin that changes files, the registry or the machine,
+/// parsed only when the shell is PowerShell or cmd.exe: `Remove-Item -Recurse -Force`,
+/// `del /s /q`, `rd /s`, `Set-ExecutionPolicy`, `reg delete`, `bcdedit` and the like.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct WindowsCommand {
+    /// The cmdlet with its alias resolved, e.g. `Remove-Item` for `rm`, or the builtin.
+    pub name: String,
+    /// `-Recurse`, `/s`.
+    pub recursive: bool,
+    /// `-Force`, `-Confirm:$false`, `/q`, `/f`: read-only and hidden items go too, unasked.
+    pub force: bool,
+    /// `-WhatIf`: only tells what would happen.
+    pub what_if: bool,
+    /// Replaces the contents of an existing file: `Set-Content`, `Out-File` without `-Append`.
+    pub overwrites: bool,
+    /// Paths, registry keys and values named on the command line.
+    pub targets: Vec<String>,
+    /// What it does beyond its operation, e.g. `turns off the script execution checks`.
+    pub hazard: Option<String>,
+    /// Weakens or breaks the whole machine: Critical whatever else the command does.
+    pub critical: bool,
+}
+
+impl WindowsCommand {
+    /// Parse a simple command (one pipeline stage) run by `shell`, see
+    /// [`split_command_segments`].
+    pub fn parse(segment: &str, shell: &ShellKind) -> Option<Self> {
+        let powershell = match shell {
+            ShellKind::PowerShell => true,
+            ShellKind::Cmd => false,
+            _ => return None,
+        };
+        let tokens = tokenize(segment, PathStyle::Windows);
+        let words: Vec<&str> = tokens
+            .iter()
+            .filter(|v| v.is_word())
+            .map(|v| v.text.as_str())
+            .collect();
+        let first = words.first()?.to_lowercase();
+        let first = first.trim_end_matches(".exe");
+        let name = match powershell {
+            true => POWERSHELL_ALIASES
+                .iter()
+                .find(|(alias, _)| *alias == first)
+                .map(|(_, cmdlet)| cmdlet.to_string())
+                .or_else(|| canonical_cmdlet(first))
+                .or_else(|| is_external(first).then(|| first.to_string()))?,
+            false => match first {
+                "del" | "erase" | "rd" | "rmdir" | "move" | "ren" | "rename" | "copy" | "xcopy"
+                | "md" | "mkdir" | "type" | "dir" => first.to_string(),
+                v if is_external(v) => v.to_string(),
+                _ => return None,
+            },
+        };
+
+        let mut output = Self {
+            name,
+            recursive: false,
+            force: false,
+            what_if: false,
+            overwrites: false,
+            targets: vec![],
+            hazard: None,
+            critical: false,
+        };
+        let mut params: Vec<(String, String)> = vec![];
+        let mut iter = words[1..].iter().peekable();
+        while let Some(word) = iter.next() {
+            if let Some(switch) = word.strip_prefix('/').filter(|v| !v.contains(['/', '\\'])) {
+                match switch.to_lowercase().as_str() {
+                    "s" | "mir" | "purge" => output.recursive = true,
+                    "q" | "f" => output.force = true,
+                    _ => {}
+                }
+                params.push((switch.to_lowercase(), String::new()));
+                continue;
+            }
+            let Some(param) = word.strip_prefix('-').filter(|_| powershell) else {
+                output.targets.push(word.to_string());
+                continue;
+            };
+            let (param, value) = match param.split_once(':') {
+                Some((param, value)) => (param.to_lowercase(), Some(value.to_string())),
+                None => (param.to_lowercase(), None),
+            };
+            let switch = POWERSHELL_SWITCHES
+                .iter()
+                .find(|v| !param.is_empty() && v.starts_with(param.as_str()))
+                .filter(|v| param.len() >= 2 || **v == "recurse");
+            match switch {
+                Some(&"recurse") => output.recursive = true,
+                Some(&"force") => output.force = true,
+                Some(&"whatif") => output.what_if = true,
+                Some(&"confirm") if value.as_deref() == Some("$false") => output.force = true,
+                _ => {}
+            }
+            let value = match (switch, value) {
+                (_, Some(value)) => value,
+                (None, None) => iter
+                    .next_if(|v| !v.starts_with('-'))
+                    .map(|v| v.to_string())
+                    .unwrap_or_default(),
+                (Some(_), None) => String::new(),
+            };
+            if matches!(
+                param.as_str(),
+                "path" | "literalpath" | "filepath" | "destination"
+            ) {
+                output.targets.push(value.clone());
+            }
+            // Switches are recorded by their full name, `-app` as `append`
+            params.push((switch.map(|v| v.to_string()).unwrap_or(param), value));
+        }
+        let param = |name: &str| {
+            params
+                .iter()
+                .find(|(param, _)| param == name)
+                .map(|(_, value)| value.to_lowercase())
+        };
+
+        output.overwrites = match output.name.as_str() {
+            "Set-Content" => true,
+            "Out-File" => param("append").is_none(),
+            _ => false,
+        };
+        let registry = output.targets.iter().find_map(|v| registry_hive(v));
+        let (hazard, critical) = match output.name.as_str() {
+            "Set-ExecutionPolicy" => {
+                let policy = param("executionpolicy")
+                    .or_else(|| output.targets.first().map(|v| v.to_lowercase()))
+                    .unwrap_or_default();
+                match (policy.as_str(), param("scope").as_deref()) {
+                    ("unrestricted" | "bypass", Some("process")) => (
+                        Some("turns off the script execution checks for this session"),
+                        false,
+                    ),
+                    ("unrestricted" | "bypass", Some("currentuser")) => (
+                        Some("turns off the script execution checks for your account"),
+                        false,
+                    ),
+                    ("unrestricted" | "bypass", _) => (
+                        Some("turns off the script execution checks for every user"),
+                        true,
+                    ),
+                    _ => (Some("changes which scripts may run"), false),
+                }
+            }
+            "Set-MpPreference" if params.iter().any(|(v, _)| v.starts_with("disable")) => {
+                (Some("turns off Microsoft Defender protection"), true)
+            }
+            "Add-MpPreference" if params.iter().any(|(v, _)| v.starts_with("exclusion")) => {
+                (Some("excludes files from Microsoft Defender scans"), true)
+            }
+            "bcdedit" if !params.iter().any(|(v, _)| v == "enum" || v == "v") => {
+                (Some("changes the boot configuration"), true)
+            }
+            "Stop-Computer" => (Some("shuts the machine down"), false),
+            "Restart-Computer" => (Some("restarts the machine"), false),
+            "shutdown" if !params.iter().any(|(v, _)| v == "a" || v == "?") => {
+                (Some("shuts down or restarts the machine"), false)
+            }
+            "robocopy" if output.recursive => (
+                Some("deletes the files of the destination missing from the source"),
+                false,
+            ),
+            "Clear-RecycleBin" => (Some("empties the recycle bin for good"), false),
+            _ => match registry {
+                Some(Hive::Machine) if output.operation() == CommandOperation::Delete => {
+                    (Some("deletes registry keys of the whole machine"), true)
+                }
+                Some(Hive::Machine) => (Some("changes the registry of the whole machine"), false),
+                Some(Hive::User) if output.operation() == CommandOperation::Delete => {
+                    (Some("deletes registry keys of your account"), false)
+                }
+                _ => (None, false),
+            },
+        };
+        output.hazard = hazard.map(|v| v.to_string());
+        output.critical = critical && !output.what_if;
+        Some(output)
+    }
+
+    pub fn operation(&self) -> CommandOperation {
+        if self.what_if {
+            return CommandOperation::Read;
+        }
+        match self.name.as_str() {
+            "Remove-Item"
+            | "Remove-ItemProperty"
+            | "Clear-Content"
+            | "Clear-RecycleBin"
+            | "del"
+            | "erase"
+            | "rd"
+            | "rmdir" => CommandOperation::Delete,
+            "reg"
+                if self
+                    .targets
+                    .first()
+                    .is_some_and(|v| v.eq_ignore_ascii_case("delete")) =>
+            {
+                CommandOperation::Delete
+            }
+            "robocopy" if self.recursive => CommandOperation::Delete,
+            "Move-Item" | "Rename-Item" | "move" | "ren" | "rename" => CommandOperation::Move,
+            "Copy-Item" | "copy" | "xcopy" | "robocopy" => CommandOperation::Copy,
+            "New-Item" | "md" | "mkdir" => CommandOperation::Create,
+            "Set-Content" | "Add-Content" | "Out-File" => CommandOperation::Write,
+            "Invoke-Expression" | "Start-Process" => CommandOperation::Execute,
+            "Invoke-WebRequest" | "Invoke-RestMethod" | "Start-BitsTransfer" => {
+                CommandOperation::Network
+            }
+            "Set-ExecutionPolicy"
+            | "Set-MpPreference"
+            | "Add-MpPreference"
+            | "Stop-Computer"
+            | "Restart-Computer"
+            | "Stop-Service"
+            | "Restart-Service"
+            | "Set-Service"
+            | "Stop-Process"
+            | "Set-ItemProperty"
+            | "New-ItemProperty"
+            | "reg"
+            | "bcdedit"
+            | "shutdown" => CommandOperation::System,
+            "type" | "dir" => CommandOperation::Read,
+            name if name.starts_with("Get-") || name.starts_with("Test-") => CommandOperation::Read,
+            _ => CommandOperation::Unknown,
+        }
+    }
+
+    pub fn warning(&self) -> Option<String> {
+        let hazard = self.hazard.as_ref()?;
+        Some(format!("⚠️  Windows: {} {hazard}.", self.name))
+    }
+
+    /// The same cmdlet with `-WhatIf`, for cmdlets that change files.
+    pub fn dry_run_variant(&self, segment: &str) -> Option<String> {
+        let changes = matches!(
+            self.name.as_str(),
+            "Remove-Item" | "Move-Item" | "Rename-Item" | "Copy-Item" | "Clear-Content"
+        );
+        (changes && !self.what_if).then(|| format!("{} -WhatIf", segment.trim()))
+    }
+}
+
+/// Whose registry a key belongs to.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub(super) enum Hive {
+    Machine,
+    User,
+}
+
+pub(super) fn registry_hive(path: &str) -> Option<Hive> {
+    let path = path.to_uppercase();
+    let path = path.strip_prefix("REGISTRY::").unwrap_or(&path);
+    let key = path.split([':', '\\']).next().unwrap_or_default();
+    match key {
+        "HKLM" | "HKEY_LOCAL_MACHINE" | "HKCR" | "HKEY_CLASSES_ROOT" => Some(Hive::Machine),
+        "HKCU" | "HKEY_CURRENT_USER" => Some(Hive::User),
+        _ => None,
+    }
+}
+
+/// `remove-item` -> `Remove-Item` for the cmdlets [`WindowsCommand::operation`] knows,
+/// any `Get-`/`Test-` cmdlet included.
+fn canonical_cmdlet(word: &str) -> Option<String> {
+    const CMDLETS: [&str; 26] = [
+        "Remove-Item",
+        "Remove-ItemProperty",
+        "Clear-Content",
+        "Clear-RecycleBin",
+        "Move-Item",
+        "Rename-Item",
+        "Copy-Item",
+        "New-Item",
+        "Set-Content",
+        "Add-Content",
+        "Out-File",
+        "Invoke-Expression",
+        "Start-Process",
+        "Invoke-WebRequest",
+        "Invoke-RestMethod",
+        "Start-BitsTransfer",
+        "Set-ExecutionPolicy",
+        "Set-MpPreference",
+        "Add-MpPreference",
+        "Stop-Computer",
+        "Restart-Computer",
+        "Stop-Service",
+        "Restart-Service",
+        "Set-Service",
+        "Stop-Process",
+        "Set-ItemProperty",
+    ];
+    if let Some(cmdlet) = CMDLETS.iter().find(|v| v.eq_ignore_ascii_case(word)) {
+        return Some(cmdlet.to_string());
+    }
+    let (verb, noun) = word.split_once('-')?;
+    if !matches!(verb, "get" | "test") || noun.is_empty() {
+        return None;
+    }
+    let title = |v: &str| {
+        let mut chars = v.chars();
+        chars
+            .next()
+            .map(|c| c.to_uppercase().chain(chars).collect::<String>())
+            .unwrap_or_default()
+    };
+    Some(format!("{}-{}", title(verb), title(noun)))
+}
+
+/// Windows programs both shells run the same way.
+fn is_external(word: &str) -> bool {
+    matches!(
+        word,
+        "reg" | "bcdedit" | "shutdown" | "robocopy" | "new-itemproperty"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(command: &str) -> WindowsCommand {
+        WindowsCommand::parse(command, &ShellKind::PowerShell).unwrap()
+    }
+
+    #[test]
+    fn test_parse_powershell() {
+        let cmd = parse(r"rm -r -fo C:\build");
+        assert_eq!(cmd.name, "Remove-Item");
+        assert!(cmd.recursive && cmd.force);
+        assert_eq!(cmd.targets, [r"C:\build"]);
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+        assert_eq!(
+            cmd.dry_run_variant(r"rm -r -fo C:\build").unwrap(),
+            r"rm -r -fo C:\build -WhatIf"
+        );
+
+        let cmd = parse(r"Remove-Item -Path .\logs -Recurse -Confirm:$false -WhatIf");
+        assert_eq!(cmd.targets, [r".\logs"]);
+        assert!(cmd.force);
+        assert_eq!(cmd.operation(), CommandOperation::Read);
+
+        let cmd = parse("Set-ExecutionPolicy Unrestricted -Scope LocalMachine");
+        assert!(cmd.critical);
+        assert_eq!(
+            cmd.warning().unwrap(),
+            "⚠️  Windows: Set-ExecutionPolicy turns off the script execution checks for every user."
+        );
+        assert!(!parse("Set-ExecutionPolicy -ExecutionPolicy Bypass -Scope Process").critical);
+
+        let cmd = parse(r"Remove-Item -Path HKLM:\Software\Vendor -Recurse");
+        assert!(cmd.critical);
+        assert!(parse("Set-Content notes.txt hi").overwrites);
+        assert!(!parse("Out-File -FilePath log.txt -Append").overwrites);
+        assert_eq!(parse("get-childitem").name, "Get-Childitem");
+        assert!(WindowsCommand::parse("frobnicate x", &ShellKind::PowerShell).is_none());
+        assert!(WindowsCommand::parse("rm -rf x", &ShellKind::Bash).is_none());
+    }
+
+    #[test]
+    fn test_parse_cmd() {
+        let cmd = WindowsCommand::parse(r"rd /s /q C:\temp\old", &ShellKind::Cmd).unwrap();
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+        assert!(cmd.recursive && cmd.force);
+        assert_eq!(cmd.targets, [r"C:\temp\old"]);
+
+        let cmd =
+            WindowsCommand::parse(r"reg delete HKLM\Software\Vendor /f", &ShellKind::Cmd).unwrap();
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+        assert!(cmd.critical);
+
+        let cmd = WindowsCommand::parse(r"robocopy src dst /MIR", &ShellKind::Cmd).unwrap();
+        assert_eq!(cmd.operation(), CommandOperation::Delete);
+        assert!(cmd.hazard.is_some());
+        assert!(WindowsCommand::parse("bcdedit /enum", &ShellKind::Cmd)
+            .unwrap()
+            .hazard
+            .is_none());
+    }
+}
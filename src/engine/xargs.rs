@@ -0,0 +1,187 @@
+use super::*;
+
+use serde::{Deserialize, Serialize};
+
+/// Options whose value is the next word when not attached, `-n 1` or `-n1`.
+const VALUE_FLAGS: [&str; 11] = [
+    "-I", "-J", "-n", "-L", "-P", "-s", "-d", "-E", "-a", "-R", "-S",
+];
+
+/// An `xargs` run, and the command it runs on the items of its input.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct XargsCommand {
+    pub command: String,
+    /// As written, `echo` when none is given.
+    pub payload: String,
+    /// `-I {}`: one run per input line, with the string replaced by it.
+    pub replace: Option<String>,
+    /// `-0` or `-d`: names with spaces and quotes arrive whole.
+    pub delimited: bool,
+    /// `-n`/`-L`: at most this many items per run.
+    pub max_args: Option<usize>,
+    /// `-P`: runs in parallel, 0 for as many as possible.
+    pub max_procs: Option<usize>,
+    /// `-p` asks before every run.
+    pub prompts: bool,
+    /// What the payload does, filled in by the analyzer.
+    pub operation: CommandOperation,
+}
+
+impl XargsCommand {
+    pub fn parse(segment: &str) -> Option<Self> {
+        let words = shell_words::split(segment).ok()?;
+        let words: Vec<&str> = words.iter().map(|v| v.as_str()).collect();
+        let mut xargs = Self::from_words(&words)?;
+        xargs.command = segment.trim().to_string();
+        Some(xargs)
+    }
+
+    /// From the words of a simple command, `sudo` allowed in front.
+    pub fn from_words(words: &[&str]) -> Option<Self> {
+        let words: Vec<&str> = words
+            .iter()
+            .copied()
+            .skip_while(|v| matches!(*v, "sudo" | "doas"))
+            .collect();
+        let (tool, args) = words.split_first()?;
+        if tool.rsplit('/').next() != Some("xargs") {
+            return None;
+        }
+        let mut xargs = Self {
+            command: shell_words::join(&words),
+            payload: String::new(),
+            replace: None,
+            delimited: false,
+            max_args: None,
+            max_procs: None,
+            prompts: false,
+            operation: CommandOperation::Unknown,
+        };
+        let mut payload: Vec<&str> = vec![];
+        let mut iter = args.iter();
+        while let Some(word) = iter.next() {
+            if *word == "--" || !word.starts_with('-') || *word == "-" {
+                if *word != "--" {
+                    payload.push(word);
+                }
+                payload.extend(iter.by_ref());
+                break;
+            }
+            let (flag, attached) = match word.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag, Some(value)),
+                _ if !word.starts_with("--") && word.len() > 2 => match &word[..2] {
+                    v @ ("-i" | "-l" | "-e") => (v, Some(&word[2..])),
+                    v if VALUE_FLAGS.contains(&v) => (v, Some(&word[2..])),
+                    _ => (*word, None),
+                },
+                _ => (*word, None),
+            };
+            let mut value = || attached.or_else(|| iter.next().copied());
+            match flag {
+                "-I" | "-J" => xargs.replace = value().map(|v| v.to_string()),
+                // `-i` and `--replace` take no separate value
+                "-i" | "--replace" => xargs.replace = Some(attached.unwrap_or("{}").to_string()),
+                "-n" | "--max-args" | "-L" | "--max-lines" => {
+                    xargs.max_args = value().and_then(|v| v.parse().ok())
+                }
+                "-l" => xargs.max_args = attached.and_then(|v| v.parse().ok()).or(Some(1)),
+                "-P" | "--max-procs" => xargs.max_procs = value().and_then(|v| v.parse().ok()),
+                "-d" | "--delimiter" => {
+                    value();
+                    xargs.delimited = true;
+                }
+                "-s" | "--max-chars" | "-E" | "--eof" | "-a" | "--arg-file" | "-R" | "-S"
+                | "--process-slot-var" => {
+                    value();
+                }
+                "--null" => xargs.delimited = true,
+                "--interactive" => xargs.prompts = true,
+                v if !v.starts_with("--") => {
+                    xargs.delimited |= v.contains('0');
+                    xargs.prompts |= v.contains('p');
+                }
+                _ => {}
+            }
+        }
+        xargs.payload = match payload.is_empty() {
+            true => "echo".to_string(),
+            false => shell_words::join(payload),
+        };
+        Some(xargs)
+    }
+
+    fn acts(&self) -> bool {
+        self.operation.is_destructive() || self.operation.needs_backup()
+    }
+
+    /// The same run printing the commands instead, `xargs echo rm` for `xargs rm`.
+    pub fn dry_run_variant(&self) -> Option<String> {
+        if !self.acts() || self.prompts {
+            return None;
+        }
+        let mut words = shell_words::split(&self.command).ok()?;
+        let payload = shell_words::split(&self.payload).ok()?;
+        let at = words.len().checked_sub(payload.len())?;
+        words.insert(at, "echo".to_string());
+        Some(shell_words::join(words))
+    }
+
+    pub fn warnings(&self) -> Vec<String> {
+        let mut output = vec![];
+        if !self.acts() {
+            return output;
+        }
+        if !self.delimited && self.replace.is_none() {
+            output.push(format!(
+                "⚠️  xargs: splits its input at spaces and quotes, `my file.txt` reaches `{}` as `my` and `file.txt`; pair `find -print0` with `xargs -0`.",
+                self.payload
+            ));
+        }
+        if !self.prompts {
+            output.push(format!(
+                "💡 `xargs -p` asks before each run of `{}`.",
+                self.payload
+            ));
+        }
+        output
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_xargs_command() {
+        assert!(XargsCommand::parse("find . -name '*.tmp'").is_none());
+        assert_eq!(XargsCommand::parse("xargs").unwrap().payload, "echo");
+
+        let xargs = XargsCommand::parse("xargs -0 -n 10 -P4 rm -f").unwrap();
+        assert_eq!(xargs.payload, "rm -f");
+        assert_eq!(
+            (xargs.delimited, xargs.max_args, xargs.max_procs),
+            (true, Some(10), Some(4))
+        );
+
+        let mut xargs = XargsCommand::parse("sudo xargs -I{} mv {} /tmp/trash/").unwrap();
+        assert_eq!(xargs.payload, "mv {} /tmp/trash/");
+        assert_eq!(xargs.replace.as_deref(), Some("{}"));
+        xargs.operation = CommandOperation::Move;
+        assert_eq!(
+            xargs.dry_run_variant().unwrap(),
+            "sudo xargs -I{} echo mv {} /tmp/trash/"
+        );
+        assert_eq!(xargs.warnings().len(), 1);
+
+        let xargs = XargsCommand::parse("xargs -d '\\n' -I FILE grep -l TODO FILE").unwrap();
+        assert_eq!(xargs.payload, "grep -l TODO FILE");
+        assert_eq!(xargs.replace.as_deref(), Some("FILE"));
+        assert!(xargs.dry_run_variant().is_none());
+
+        let mut xargs = XargsCommand::parse("xargs -rp rm").unwrap();
+        xargs.operation = CommandOperation::Delete;
+        assert!(xargs.prompts);
+        assert!(xargs.dry_run_variant().is_none());
+        assert!(xargs.warnings()[0].contains("reaches `rm` as `my` and `file.txt`"));
+    }
+}
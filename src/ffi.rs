@@ -0,0 +1,157 @@
+//! C ABI over the engine for non-Rust tooling, built into the `cdylib`
+//! (`libaichat.so`, `libaichat.dylib`, `aichat.dll`) on request with
+//! `cargo rustc --release --lib --no-default-features --crate-type cdylib`. Every function takes NUL-terminated
+//! UTF-8 and returns a JSON string owned by the caller, to be released with
+//! [`aichat_free_string`]. Failures are returned as `{"error": "..."}`.
+//!
+//! `scripts/bindings/aichat.h` declares the functions and
+//! `scripts/bindings/aichat_engine.py` wraps them for Python.
+
+use crate::engine::{
+    annotate_script, CommandAnalysis, CommandTutorial, EnvProfile, PathStyle, ShellKind, SHELL,
+};
+
+use anyhow::{anyhow, Result};
+use serde_json::{json, Value};
+use std::{
+    ffi::{c_char, CStr, CString},
+    panic,
+    sync::LazyLock,
+};
+
+static ENV_PROFILE: LazyLock<EnvProfile> = LazyLock::new(EnvProfile::detect);
+
+/// Analyze `command` as written for `shell` (`bash`, `zsh`, `fish`, `nu`, `pwsh`, `cmd`),
+/// or the detected shell when `shell` is null.
+///
+/// # Safety
+///
+/// `command` must be a valid NUL-terminated string, `shell` one or null.
+#[no_mangle]
+pub unsafe extern "C" fn aichat_analyze(
+    command: *const c_char,
+    shell: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let command = read_str(command)?;
+        let shell = read_shell(shell)?;
+        Ok(CommandAnalysis::analyze_for(&command, PathStyle::current(), &shell).to_json())
+    })
+}
+
+/// Analyze a multi-line script as a whole.
+///
+/// # Safety
+///
+/// `content` must be a valid NUL-terminated string, `shell` one or null.
+#[no_mangle]
+pub unsafe extern "C" fn aichat_analyze_script(
+    content: *const c_char,
+    shell: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let content = read_str(content)?;
+        let shell = read_shell(shell)?;
+        Ok(CommandAnalysis::analyze_script(&content, &shell).to_json())
+    })
+}
+
+/// Explain `command` part by part for the local environment.
+///
+/// # Safety
+///
+/// `command` must be a valid NUL-terminated string.
+#[no_mangle]
+pub unsafe extern "C" fn aichat_tutor(command: *const c_char) -> *mut c_char {
+    respond(|| {
+        let command = read_str(command)?;
+        Ok(CommandTutorial::analyze(&command, &ENV_PROFILE).to_json())
+    })
+}
+
+/// The script with the tutor's comments, as `{"script": "..."}`.
+///
+/// # Safety
+///
+/// `script` must be a valid NUL-terminated string, `shell` one or null.
+#[no_mangle]
+pub unsafe extern "C" fn aichat_annotate_script(
+    script: *const c_char,
+    shell: *const c_char,
+) -> *mut c_char {
+    respond(|| {
+        let script = read_str(script)?;
+        let shell = read_shell(shell)?;
+        Ok(json!({ "script": annotate_script(&script, &shell) }))
+    })
+}
+
+/// Release a string returned by the functions above.
+///
+/// # Safety
+///
+/// `value` must come from this library and not be freed twice; null is ignored.
+#[no_mangle]
+pub unsafe extern "C" fn aichat_free_string(value: *mut c_char) {
+    if !value.is_null() {
+        drop(CString::from_raw(value));
+    }
+}
+
+unsafe fn read_str(value: *const c_char) -> Result<String> {
+    if value.is_null() {
+        return Err(anyhow!("Unexpected null string"));
+    }
+    Ok(CStr::from_ptr(value).to_str()?.to_string())
+}
+
+unsafe fn read_shell(value: *const c_char) -> Result<ShellKind> {
+    match value.is_null() {
+        true => Ok(ShellKind::from_name(&SHELL.name)),
+        false => Ok(ShellKind::from_name(&read_str(value)?)),
+    }
+}
+
+/// Panics must not unwind into C.
+fn respond(f: impl FnOnce() -> Result<Value> + panic::UnwindSafe) -> *mut c_char {
+    let value = match panic::catch_unwind(f) {
+        Ok(Ok(value)) => value,
+        Ok(Err(err)) => json!({ "error": err.to_string() }),
+        Err(_) => json!({ "error": "The analyzer panicked" }),
+    };
+    // serde_json escapes NUL, so the JSON never contains one.
+    CString::new(value.to_string())
+        .unwrap_or_default()
+        .into_raw()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn call(f: impl FnOnce() -> *mut c_char) -> Value {
+        let output = f();
+        let text = unsafe { CStr::from_ptr(output) }
+            .to_str()
+            .unwrap()
+            .to_string();
+        unsafe { aichat_free_string(output) };
+        serde_json::from_str(&text).unwrap()
+    }
+
+    #[test]
+    fn test_ffi() {
+        let command = CString::new("sudo rm -rf /").unwrap();
+        let shell = CString::new("bash").unwrap();
+        let analysis = call(|| unsafe { aichat_analyze(command.as_ptr(), shell.as_ptr()) });
+        assert_eq!(analysis["safety_level"], "critical");
+        assert_eq!(analysis["operation"], "system");
+
+        let command = CString::new("ls -la").unwrap();
+        let tutorial = call(|| unsafe { aichat_tutor(command.as_ptr()) });
+        assert_eq!(tutorial["structure"][0]["text"], "ls");
+
+        let error = call(|| unsafe { aichat_analyze(std::ptr::null(), std::ptr::null()) });
+        assert_eq!(error["error"], "Unexpected null string");
+    }
+}
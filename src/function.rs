@@ -1,10 +1,11 @@
 use crate::{
-    config::{Agent, Config, GlobalConfig},
+    config::{execution_refusal, Agent, BudgetUsage, Config, GlobalConfig},
     utils::*,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use indexmap::IndexMap;
+use inquire::Confirm;
 use serde::{Deserialize, Serialize};
 use serde_json::{json, Value};
 use std::{
@@ -29,6 +30,7 @@ pub fn eval_tool_calls(config: &GlobalConfig, mut calls: Vec<ToolCall>) -> Resul
     }
     let mut is_all_null = true;
     for call in calls {
+        charge_budget(config, &call)?;
         let mut result = call.eval(config)?;
         if result.is_null() {
             result = json!("DONE");
@@ -43,6 +45,39 @@ pub fn eval_tool_calls(config: &GlobalConfig, mut calls: Vec<ToolCall>) -> Resul
     Ok(output)
 }
 
+/// Enforce the `budget` caps before a tool call runs. Past them the call only runs once
+/// the user authorizes a fresh budget.
+fn charge_budget(config: &GlobalConfig, call: &ToolCall) -> Result<()> {
+    let caps = config.read().budget.clone();
+    if caps.is_empty() {
+        return Ok(());
+    }
+    let risk = call
+        .command()
+        .map(|v| CommandAnalysis::analyze(&v).risk.score as u32)
+        .unwrap_or_default();
+    let exceeded = config.read().budget_usage.exceeded(&caps, risk);
+    if let Some(reason) = exceeded {
+        let renew = *IS_STDOUT_TERMINAL
+            && Confirm::new(&format!(
+                "Budget cap reached ({reason}). Authorize a new budget for '{}'?",
+                call.name
+            ))
+            .with_default(false)
+            .prompt()
+            .unwrap_or_default();
+        if !renew {
+            bail!(
+                "Budget cap reached ({reason}), stopped before calling '{}'.",
+                call.name
+            );
+        }
+        config.write().budget_usage = BudgetUsage::default();
+    }
+    config.write().budget_usage.record_command(risk);
+    Ok(())
+}
+
 #[derive(Debug, Clone, Deserialize, Serialize)]
 pub struct ToolResult {
     pub call: ToolCall,
@@ -86,6 +121,14 @@ impl Functions {
         self.declarations.iter().any(|v| v.name == name)
     }
 
+    pub fn extend(&mut self, declarations: Vec<FunctionDeclaration>) {
+        for declaration in declarations {
+            if !self.contains(&declaration.name) {
+                self.declarations.push(declaration);
+            }
+        }
+    }
+
     pub fn declarations(&self) -> &[FunctionDeclaration] {
         &self.declarations
     }
@@ -143,6 +186,17 @@ pub struct ToolCall {
 type CallConfig = (String, String, Vec<String>, HashMap<String, String>);
 
 impl ToolCall {
+    /// The shell command in the arguments of command-running tools such as `execute_command`.
+    pub fn command(&self) -> Option<String> {
+        let arguments = match &self.arguments {
+            Value::String(v) => serde_json::from_str(v).ok()?,
+            v => v.clone(),
+        };
+        ["command", "cmd", "script"]
+            .iter()
+            .find_map(|v| arguments[v].as_str().map(|v| v.to_string()))
+    }
+
     pub fn dedup(calls: Vec<Self>) -> Vec<Self> {
         let mut new_calls = vec![];
         let mut seen_ids = HashSet::new();
@@ -171,6 +225,32 @@ impl ToolCall {
     }
 
     pub fn eval(&self, config: &GlobalConfig) -> Result<Value> {
+        let command = self.command();
+        let analysis = command.as_deref().map(CommandAnalysis::analyze);
+        let what = format!("'{}'", self.name);
+        if let Some(reason) = config.read().read_only_refusal(&what, analysis.as_ref()) {
+            return Ok(json!({ "error": format!("Execution blocked: {reason}") }));
+        }
+        if let Some(analysis) = analysis.as_ref().filter(|v| !v.placeholders.is_empty()) {
+            let texts: Vec<&str> = analysis
+                .placeholders
+                .iter()
+                .map(|v| v.text.as_str())
+                .collect();
+            return Ok(json!({
+                "error": format!("Execution blocked: ask the user for {} first", texts.join(", "))
+            }));
+        }
+        // The same refusals as commands the user runs, before any function or plugin
+        if let (Some(command), Some(analysis)) = (command, &analysis) {
+            if let Some(refusal) = execution_refusal(config, &SHELL, &command, analysis)? {
+                if let Ok(runtime) = tokio::runtime::Handle::try_current() {
+                    let (config, refusal) = (config.clone(), refusal.clone());
+                    runtime.spawn(async move { refusal.notify(&config, &command).await });
+                }
+                return Ok(json!({ "error": format!("Execution blocked: {}", refusal.reason) }));
+            }
+        }
         let (call_name, cmd_name, mut cmd_args, envs) = match &config.read().agent {
             Some(agent) => self.extract_call_config_from_agent(config, agent)?,
             None => self.extract_call_config_from_config(config)?,
@@ -190,6 +270,19 @@ impl ToolCall {
             );
         };
 
+        // Functions take precedence over plugins, as in the declarations the model sees
+        let is_agent_function = match &config.read().agent {
+            Some(agent) => agent.functions().contains(&self.name),
+            None => false,
+        };
+        let plugin = match is_agent_function {
+            true => None,
+            false => config.read().plugins.find(&call_name).cloned(),
+        };
+        if let Some(plugin) = plugin {
+            return plugin.run(&json_data);
+        }
+
         cmd_args.push(json_data.to_string());
 
         let output = match run_llm_function(cmd_name, cmd_args, envs)? {
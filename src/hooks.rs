@@ -0,0 +1,151 @@
+use anyhow::{anyhow, Context, Result};
+use rhai::{Dynamic, Engine, Scope, AST};
+use std::{fs, path::Path, sync::Arc};
+
+/// User scripting hooks loaded from `<config_dir>/hooks.rhai`.
+///
+/// Every hook is an optional Rhai function:
+///
+/// ```rhai
+/// // Rewrite a suggested command, return () to keep it unchanged.
+/// fn on_command_suggested(command) {
+///     command.replace("kubectl ", "kubectl --context=dev ");
+///     command
+/// }
+///
+/// // Return false or a reason string to block the execution.
+/// fn on_before_execute(command) {
+///     if command.contains("prod-db") { return "prod-db is off limits"; }
+///     true
+/// }
+///
+/// // Observe every model response.
+/// fn on_response(text) {}
+/// ```
+#[derive(Debug, Clone)]
+pub struct Hooks {
+    engine: Arc<Engine>,
+    ast: Arc<AST>,
+}
+
+pub const ON_COMMAND_SUGGESTED: &str = "on_command_suggested";
+pub const ON_BEFORE_EXECUTE: &str = "on_before_execute";
+pub const ON_RESPONSE: &str = "on_response";
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum HookVerdict {
+    Allow,
+    Block(String),
+}
+
+impl Hooks {
+    pub fn init(path: &Path) -> Result<Option<Self>> {
+        if !path.exists() {
+            return Ok(None);
+        }
+        let script = fs::read_to_string(path)
+            .with_context(|| format!("Failed to load hooks at {}", path.display()))?;
+        Self::from_script(&script)
+            .with_context(|| format!("Failed to load hooks at {}", path.display()))
+            .map(Some)
+    }
+
+    pub fn from_script(script: &str) -> Result<Self> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(1_000_000);
+        engine.on_print(|text| println!("{text}"));
+        engine.on_debug(|text, _, _| debug!("hooks: {text}"));
+        let ast = engine.compile(script).map_err(|err| anyhow!("{err}"))?;
+        Ok(Self {
+            engine: Arc::new(engine),
+            ast: Arc::new(ast),
+        })
+    }
+
+    pub fn on_command_suggested(&self, command: &str) -> Result<Option<String>> {
+        match self.call(ON_COMMAND_SUGGESTED, command)? {
+            Some(value) if value.is_string() => Ok(Some(value.into_string().unwrap_or_default())),
+            _ => Ok(None),
+        }
+    }
+
+    pub fn on_before_execute(&self, command: &str) -> Result<HookVerdict> {
+        let verdict = match self.call(ON_BEFORE_EXECUTE, command)? {
+            Some(value) if value.is_string() => {
+                HookVerdict::Block(value.into_string().unwrap_or_default())
+            }
+            Some(value) if value.as_bool() == Ok(false) => {
+                HookVerdict::Block(format!("Blocked by the {ON_BEFORE_EXECUTE} hook"))
+            }
+            _ => HookVerdict::Allow,
+        };
+        Ok(verdict)
+    }
+
+    pub fn on_response(&self, text: &str) -> Result<()> {
+        self.call(ON_RESPONSE, text)?;
+        Ok(())
+    }
+
+    fn call(&self, name: &str, arg: &str) -> Result<Option<Dynamic>> {
+        if !self.ast.iter_functions().any(|f| f.name == name) {
+            return Ok(None);
+        }
+        let mut scope = Scope::new();
+        let value = self
+            .engine
+            .call_fn::<Dynamic>(&mut scope, &self.ast, name, (arg.to_string(),))
+            .map_err(|err| anyhow!("Hook '{name}' failed, {err}"))?;
+        if value.is_unit() {
+            Ok(None)
+        } else {
+            Ok(Some(value))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_hooks() {
+        let hooks = Hooks::from_script(
+            r#"
+fn on_command_suggested(command) {
+    if command.starts_with("docker ") {
+        return "team-docker " + command.sub_string(7);
+    }
+}
+
+fn on_before_execute(command) {
+    if command.contains("prod.example.com") {
+        return "prod.example.com is off limits";
+    }
+    !command.contains("shutdown")
+}
+"#,
+        )
+        .unwrap();
+        assert_eq!(
+            hooks.on_command_suggested("docker ps").unwrap(),
+            Some("team-docker ps".into())
+        );
+        assert_eq!(hooks.on_command_suggested("ls").unwrap(), None);
+        assert_eq!(
+            hooks.on_before_execute("ssh prod.example.com").unwrap(),
+            HookVerdict::Block("prod.example.com is off limits".into())
+        );
+        assert!(matches!(
+            hooks.on_before_execute("sudo shutdown now").unwrap(),
+            HookVerdict::Block(_)
+        ));
+        assert_eq!(hooks.on_before_execute("ls").unwrap(), HookVerdict::Allow);
+        assert!(hooks.on_response("hello").is_ok());
+    }
+
+    #[test]
+    fn test_invalid_hooks() {
+        assert!(Hooks::from_script("fn on_response(text) {").is_err());
+    }
+}
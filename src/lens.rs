@@ -0,0 +1,201 @@
+//! A JSON-RPC server on stdio for editor integrations, framed like LSP
+//! (`Content-Length` headers), exposing the analyzer and the tutor.
+//!
+//! Methods:
+//! - `initialize` -> the server info and methods
+//! - `analyze` `{command}` -> the analysis of one command
+//! - `lenses` `{text, language?, uri?}` -> the analysis of every command in a script or
+//!   in the shell code blocks of a markdown document, with 0-based line numbers
+//! - `tutorial` `{command}` -> the structure breakdown and notes of the tutor
+//! - `shutdown`, `exit`
+
+use crate::config::{Config, EnvProfile};
+use crate::utils::*;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde_json::{json, Value};
+use std::io::{self, BufRead, Write};
+
+const PARSE_ERROR: i64 = -32700;
+const METHOD_NOT_FOUND: i64 = -32601;
+const INVALID_PARAMS: i64 = -32602;
+
+pub fn run() -> Result<()> {
+    set_tldr_dir(Some(Config::tldr_dir()));
+    let stdin = io::stdin();
+    let mut reader = stdin.lock();
+    let mut writer = io::stdout().lock();
+    while let Some(message) = read_message(&mut reader)? {
+        let request: Value = match serde_json::from_str(&message) {
+            Ok(v) => v,
+            Err(err) => {
+                let response = error_response(Value::Null, PARSE_ERROR, &err.to_string());
+                write_message(&mut writer, &response)?;
+                continue;
+            }
+        };
+        let method = request["method"].as_str().unwrap_or_default();
+        if method == "exit" {
+            break;
+        }
+        // Notifications have no id and get no response.
+        let Some(id) = request.get("id").cloned() else {
+            continue;
+        };
+        let response = match handle(method, &request["params"]) {
+            Ok(result) => json!({ "jsonrpc": "2.0", "id": id, "result": result }),
+            Err((code, message)) => error_response(id, code, &message),
+        };
+        write_message(&mut writer, &response)?;
+    }
+    Ok(())
+}
+
+fn handle(method: &str, params: &Value) -> Result<Value, (i64, String)> {
+    let param = |name: &str| {
+        params[name]
+            .as_str()
+            .ok_or_else(|| (INVALID_PARAMS, format!("Missing string param '{name}'")))
+    };
+    match method {
+        "initialize" => Ok(json!({
+            "serverInfo": { "name": env!("CARGO_CRATE_NAME"), "version": env!("CARGO_PKG_VERSION") },
+            "methods": ["analyze", "lenses", "tutorial", "shutdown", "exit"],
+        })),
+        "analyze" => {
+            let command = param("command")?;
+            Ok(analysis_json(&CommandAnalysis::analyze(command)))
+        }
+        "lenses" => {
+            let text = param("text")?;
+            let language = params["language"]
+                .as_str()
+                .or_else(|| params["uri"].as_str().and_then(language_of_path))
+                .unwrap_or("shellscript");
+            let lenses: Vec<Value> = extract_document_commands(text, language)
+                .into_iter()
+                .map(|v| {
+                    let analysis = v.analyze();
+                    json!({
+                        "line": v.line,
+                        "end_line": v.end_line,
+                        "command": v.command,
+                        "blocks": v.blocks,
+                        "analysis": analysis_json(&analysis),
+                    })
+                })
+                .collect();
+            Ok(json!(lenses))
+        }
+        "tutorial" => {
+            let command = param("command")?;
+            let tutorial = CommandTutorial::analyze(command, &EnvProfile::detect());
+            let parts: Vec<Value> = tutorial
+                .structure
+                .iter()
+                .map(|v| {
+                    json!({
+                        "text": v.text,
+                        "description": v.description,
+                        "type": format!("{:?}", v.part_type).to_lowercase(),
+                    })
+                })
+                .collect();
+            Ok(json!({
+                "command": tutorial.command,
+                "parts": parts,
+                "environment_notes": tutorial.environment_notes,
+                "safety_notes": tutorial.safety_notes,
+                "man_page_ref": tutorial.man_page_ref,
+                "examples": tutorial.examples,
+            }))
+        }
+        "shutdown" => Ok(Value::Null),
+        _ => Err((METHOD_NOT_FOUND, format!("Unknown method '{method}'"))),
+    }
+}
+
+fn analysis_json(analysis: &CommandAnalysis) -> Value {
+    json!({
+        "safety_level": analysis.safety_level,
+        "max_severity": analysis.max_severity(),
+        "operation": format!("{:?}", analysis.operation).to_lowercase(),
+        "affected_files": analysis.affected_files,
+        "paths_by_operation": analysis.paths_by_operation(),
+        "required_backups": analysis.required_backups(),
+        "warnings": analysis.warnings,
+    })
+}
+
+fn error_response(id: Value, code: i64, message: &str) -> Value {
+    json!({ "jsonrpc": "2.0", "id": id, "error": { "code": code, "message": message } })
+}
+
+/// `None` at the end of the input.
+fn read_message(reader: &mut impl BufRead) -> Result<Option<String>> {
+    let mut length = None;
+    loop {
+        let mut line = String::new();
+        if reader.read_line(&mut line)? == 0 {
+            return Ok(None);
+        }
+        let line = line.trim_end();
+        if line.is_empty() {
+            break;
+        }
+        if let Some((name, value)) = line.split_once(':') {
+            if name.eq_ignore_ascii_case("content-length") {
+                length = Some(
+                    value
+                        .trim()
+                        .parse::<usize>()
+                        .context("Invalid Content-Length")?,
+                );
+            }
+        }
+    }
+    let Some(length) = length else {
+        bail!("Missing Content-Length header");
+    };
+    let mut body = vec![0; length];
+    reader.read_exact(&mut body)?;
+    String::from_utf8(body)
+        .map(Some)
+        .map_err(|_| anyhow!("Message is not UTF-8"))
+}
+
+fn write_message(writer: &mut impl Write, message: &Value) -> Result<()> {
+    let body = serde_json::to_string(message)?;
+    write!(writer, "Content-Length: {}\r\n\r\n{body}", body.len())?;
+    writer.flush()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_message_framing() {
+        let mut output = vec![];
+        write_message(&mut output, &json!({"id": 1})).unwrap();
+        assert_eq!(output, b"Content-Length: 8\r\n\r\n{\"id\":1}");
+        let mut reader = io::Cursor::new(output);
+        assert_eq!(read_message(&mut reader).unwrap().unwrap(), r#"{"id":1}"#);
+        assert!(read_message(&mut reader).unwrap().is_none());
+    }
+
+    #[test]
+    fn test_handle() {
+        let result = handle("analyze", &json!({"command": "rm -rf /tmp/x"})).unwrap();
+        assert_eq!(result["operation"], "delete");
+        assert_eq!(handle("analyze", &json!({})).unwrap_err().0, INVALID_PARAMS);
+        assert_eq!(handle("hover", &json!({})).unwrap_err().0, METHOD_NOT_FOUND);
+        let lenses = handle(
+            "lenses",
+            &json!({"text": "```sh\nsudo reboot\n```", "uri": "file:///README.md"}),
+        )
+        .unwrap();
+        assert_eq!(lenses[0]["analysis"]["safety_level"], "critical");
+    }
+}
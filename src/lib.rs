@@ -0,0 +1,14 @@
+//! The command safety engine behind aichat's shell assistant, for embedding in other
+//! Rust tools without shelling out to the binary. Depend on the crate with
+//! `default-features = false` to leave out the CLI and start from
+//! [`engine::SafetyEngine`]. Non-Rust tools load the `cdylib` through the C ABI in
+//! [`ffi`].
+
+// `CommandAnalysis::to_json` outgrows the default for `json!`
+#![recursion_limit = "256"]
+
+#[macro_use]
+extern crate log;
+
+pub mod engine;
+pub mod ffi;
@@ -0,0 +1,151 @@
+//! `--lint`: run every command of shell scripts (or of the shell code blocks in markdown)
+//! through the analyzer, e.g. as a pre-commit hook for ops repositories.
+
+use crate::utils::*;
+
+use anyhow::{Context, Result};
+use std::fs;
+
+const REVIEW_PROMPT: &str = r#"Review the shell scripts below as an experienced operator. The static findings are listed first; confirm or dismiss each one, point out risks they miss (ordering, missing error handling such as `set -e`, unquoted variables, race conditions) and suggest safer alternatives. Cite file:line for every point.
+
+"#;
+
+#[derive(Debug, Clone)]
+pub struct LintFinding {
+    pub path: String,
+    /// 1-based, like editors and compilers show it.
+    pub line: usize,
+    pub command: String,
+    pub safety_level: SafetyLevel,
+    pub warnings: Vec<String>,
+}
+
+#[derive(Debug, Default)]
+pub struct LintReport {
+    pub files: Vec<(String, String)>,
+    pub findings: Vec<LintFinding>,
+}
+
+impl LintReport {
+    pub fn check(paths: &[String]) -> Result<Self> {
+        let mut report = Self::default();
+        for path in paths {
+            let content =
+                fs::read_to_string(path).with_context(|| format!("Failed to read '{path}'"))?;
+            report.findings.extend(lint_text(path, &content));
+            report.files.push((path.clone(), content));
+        }
+        report
+            .findings
+            .sort_by(|a, b| b.safety_level.cmp(&a.safety_level));
+        Ok(report)
+    }
+
+    /// Whether any finding is at or above `threshold`.
+    pub fn fails(&self, threshold: &SafetyLevel) -> bool {
+        self.findings.iter().any(|v| v.safety_level >= *threshold)
+    }
+
+    pub fn display(&self) -> String {
+        let mut output = String::new();
+        for finding in &self.findings {
+            let level = color_text(&finding.safety_level.label(), finding.safety_level.color());
+            output.push_str(&format!(
+                "{}:{}: {level} {}\n",
+                finding.path, finding.line, finding.command
+            ));
+            for warning in &finding.warnings {
+                output.push_str(&format!(
+                    "    {}\n",
+                    note_text(warning, &finding.safety_level)
+                ));
+            }
+        }
+        let count = |level: SafetyLevel| {
+            self.findings
+                .iter()
+                .filter(|v| v.safety_level == level)
+                .count()
+        };
+        output.push_str(&format!(
+            "{} critical, {} dangerous, {} caution in {} file(s)\n",
+            count(SafetyLevel::Critical),
+            count(SafetyLevel::Dangerous),
+            count(SafetyLevel::Caution),
+            self.files.len()
+        ));
+        accessible_text(&output)
+    }
+
+    /// The scripts with line numbers and the static findings, for an LLM review.
+    pub fn review_prompt(&self) -> String {
+        let mut output = REVIEW_PROMPT.to_string();
+        output.push_str("Findings:\n");
+        for finding in &self.findings {
+            output.push_str(&format!(
+                "- {}:{}: {:?} `{}` {}\n",
+                finding.path,
+                finding.line,
+                finding.safety_level,
+                finding.command,
+                finding.warnings.join(" ")
+            ));
+        }
+        for (path, content) in &self.files {
+            output.push_str(&format!("\n{path}:\n```\n"));
+            for (i, line) in content.lines().enumerate() {
+                output.push_str(&format!("{:>4} {line}\n", i + 1));
+            }
+            output.push_str("```\n");
+        }
+        output
+    }
+}
+
+/// Findings for the commands of one file that aren't Safe.
+fn lint_text(path: &str, content: &str) -> Vec<LintFinding> {
+    let language = language_of_path(path).unwrap_or("shellscript");
+    extract_document_commands(content, language)
+        .into_iter()
+        .filter_map(|v| {
+            let analysis = v.analyze();
+            (analysis.safety_level > SafetyLevel::Safe).then(|| LintFinding {
+                path: path.to_string(),
+                line: v.line + 1,
+                command: v.command,
+                safety_level: analysis.safety_level,
+                warnings: analysis.warnings,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lint_text() {
+        let script =
+            "#!/bin/bash\nset -e\nls -la\n\nrm -rf \"$BUILD_DIR\"\nsudo systemctl restart nginx\n";
+        let findings = lint_text("deploy.sh", script);
+        let lines: Vec<_> = findings
+            .iter()
+            .map(|v| (v.line, v.safety_level.clone()))
+            .collect();
+        assert_eq!(
+            lines,
+            [(5, SafetyLevel::Dangerous), (6, SafetyLevel::Critical)]
+        );
+
+        let report = LintReport {
+            files: vec![("deploy.sh".into(), script.into())],
+            findings,
+        };
+        assert!(report.fails(&SafetyLevel::Dangerous));
+        assert!(report.fails(&SafetyLevel::Critical));
+        assert!(report
+            .review_prompt()
+            .contains("   6 sudo systemctl restart nginx"));
+    }
+}
@@ -2,6 +2,10 @@ mod cli;
 mod client;
 mod config;
 mod function;
+mod hooks;
+mod lens;
+mod lint;
+mod plugin;
 mod rag;
 mod render;
 mod repl;
@@ -14,28 +18,69 @@ extern crate log;
 
 use crate::cli::Cli;
 use crate::client::{
-    call_chat_completions, call_chat_completions_streaming, list_models, ModelType,
+    call_chat_completions, call_chat_completions_streaming, list_models, MessageRole, ModelType,
 };
 use crate::config::{
-    ensure_parent_exists, list_agents, load_env_file, macro_execute, Config, GlobalConfig, Input,
-    WorkingMode, CODE_ROLE, SHELL_ROLE, TEMP_SESSION_NAME,
+    audit, check_execution, encrypt_existing, ensure_parent_exists, entries_between,
+    format_objections, list_agents, load_env_file, macro_execute, prepare_execution, project_root,
+    run_setup_wizard, run_team, search_entries, suggest_rule, supervise, AuditDecision, AuditEntry,
+    AuditLog, Config, GlobalConfig, Guard, GuardState, HabitReport, Input, Postmortem,
+    PostmortemScope, Recipe, RecipeBook, RecordedRun, SessionReplay, ShellKind, Stats,
+    SupervisedJob, TrustScope, TrustStore, WorkingMode, CODE_ROLE, PATCH_ROLE, SHELL_ROLE,
+    TEMP_SESSION_NAME,
 };
-use crate::render::render_error;
-use crate::repl::Repl;
+use crate::lint::LintReport;
+use crate::render::{render_error, MarkdownRender};
+use crate::repl::{compare_answers, execute_suggested_command, Repl};
 use crate::utils::*;
 
-use anyhow::{bail, Result};
+use anyhow::{bail, Context, Result};
 use clap::Parser;
-use inquire::Text;
+use inquire::{Confirm, Text};
+use log::LevelFilter;
 use parking_lot::RwLock;
-use simplelog::{format_description, ConfigBuilder, LevelFilter, SimpleLogger, WriteLogger};
 use std::{env, process, sync::Arc};
+use tracing::Instrument;
 
 #[tokio::main]
 async fn main() -> Result<()> {
     load_env_file()?;
     let cli = Cli::parse();
+    if cli.lens {
+        // Editors start the server per workspace, so it doesn't need a config.
+        return lens::run();
+    }
+    if cli.init {
+        let config_path = Config::config_file();
+        if config_path.exists()
+            && !Confirm::new(&format!(
+                "Replace the config at '{}'?",
+                config_path.display()
+            ))
+            .with_default(false)
+            .prompt()?
+        {
+            return Ok(());
+        }
+        return run_setup_wizard(&config_path).await;
+    }
+    if let Some(path) = &cli.analyze_script {
+        let text =
+            std::fs::read_to_string(path).with_context(|| format!("Failed to read '{path}'"))?;
+        let report = ScriptReport::analyze(path, &text);
+        print!("{}", accessible_text(&report.display()));
+        process::exit((report.safety_level() >= cli.fail_on) as i32);
+    }
+    if !cli.lint.is_empty() && !cli.review {
+        let report = LintReport::check(&cli.lint)?;
+        print!("{}", report.display());
+        process::exit(report.fails(&cli.fail_on) as i32);
+    }
     let text = cli.text()?;
+    // A question after `--env diff` goes to the model, see `run`
+    if !cli.env.is_empty() && (text.is_none() || cli.env[0] != "diff") {
+        return manage_env(&cli.env);
+    }
     let working_mode = if cli.serve.is_some() {
         WorkingMode::Serve
     } else if text.is_none() && cli.file.is_empty() {
@@ -46,12 +91,24 @@ async fn main() -> Result<()> {
     let info_flag = cli.info
         || cli.sync_models
         || cli.list_models
+        || cli.recommend_models
         || cli.list_roles
         || cli.list_agents
         || cli.list_rags
         || cli.list_macros
-        || cli.list_sessions;
-    setup_logger(working_mode.is_serve())?;
+        || cli.list_sessions
+        || !cli.trust.is_empty()
+        || !cli.recipe.is_empty()
+        || !cli.audit.is_empty()
+        || cli.replay.is_some()
+        || cli.stats.is_some()
+        || cli.habits.is_some()
+        || cli.encrypt_existing
+        || cli.self_update;
+    setup_logger(working_mode.is_serve(), cli.verbose)?;
+    if cli.offline {
+        set_offline(true);
+    }
     let config = Arc::new(RwLock::new(Config::init(working_mode, info_flag).await?));
     if let Err(err) = run(config, cli, text).await {
         render_error(err);
@@ -74,6 +131,9 @@ async fn run(config: GlobalConfig, cli: Cli, text: Option<String>) -> Result<()>
         }
         return Ok(());
     }
+    if cli.recommend_models {
+        return recommend_models();
+    }
     if cli.list_roles {
         let roles = Config::list_roles(true).join("\n");
         println!("{roles}");
@@ -95,10 +155,74 @@ async fn run(config: GlobalConfig, cli: Cli, text: Option<String>) -> Result<()>
         return Ok(());
     }
 
+    if !cli.trust.is_empty() {
+        return manage_trust(&cli.trust);
+    }
+    if !cli.recipe.is_empty() {
+        return manage_recipes(&config, &cli.recipe).await;
+    }
+    if !cli.audit.is_empty() {
+        return show_audit(&cli.audit);
+    }
+    if let Some(name) = &cli.replay {
+        return replay_session(&config, name, &cli.replay_exec).await;
+    }
+    if cli.encrypt_existing {
+        let count = encrypt_existing(&config.read())?;
+        println!("✓ Encrypted {count} file(s) with `encrypt_at_rest`");
+        return Ok(());
+    }
+    if cli.self_update {
+        let (enabled, public_key) = {
+            let config = config.read();
+            (config.self_update, config.self_update_public_key.clone())
+        };
+        if !enabled {
+            bail!("Self-update is disabled by the `self_update` setting, update through your administrator");
+        }
+        return self_update(public_key.as_deref()).await;
+    }
+    if let Some(file) = &cli.stats {
+        let stats = Stats::collect()?;
+        if stats.is_empty() {
+            bail!("Nothing was recorded in the last 30 days");
+        }
+        match file {
+            Some(file) => {
+                std::fs::write(file, stats.to_html())
+                    .with_context(|| format!("Failed to write '{file}'"))?;
+                println!("✓ Saved stats to '{file}'");
+            }
+            None => print!("{}", stats.table()),
+        }
+        return Ok(());
+    }
+    if let Some(file) = &cli.habits {
+        let report = HabitReport::collect()?;
+        match file {
+            Some(file) => {
+                std::fs::write(file, report.to_markdown())
+                    .with_context(|| format!("Failed to write '{file}'"))?;
+                println!("✓ Saved habits to '{file}'");
+            }
+            None => print!("{}", report.table()),
+        }
+        return Ok(());
+    }
+
     if cli.dry_run {
         config.write().dry_run = true;
     }
 
+    if let Some(name) = &cli.remote {
+        config.write().use_remote(name)?;
+    }
+    if let [_, left, right] = cli.env.as_slice() {
+        let comparison =
+            EnvComparison::load(std::path::Path::new(left), std::path::Path::new(right))?;
+        config.write().env_comparison = Some(comparison);
+    }
+
     if let Some(agent) = &cli.agent {
         let session = cli.session.as_ref().map(|v| match v {
             Some(v) => v.as_str(),
@@ -125,6 +249,8 @@ async fn run(config: GlobalConfig, cli: Cli, text: Option<String>) -> Result<()>
             config.write().use_role(SHELL_ROLE)?;
         } else if cli.code {
             config.write().use_role(CODE_ROLE)?;
+        } else if cli.patch {
+            config.write().use_role(PATCH_ROLE)?;
         }
         if let Some(session) = &cli.session {
             config
@@ -157,6 +283,29 @@ async fn run(config: GlobalConfig, cli: Cli, text: Option<String>) -> Result<()>
         println!("{info}");
         return Ok(());
     }
+    if !cli.lint.is_empty() {
+        let report = LintReport::check(&cli.lint)?;
+        print!("{}", report.display());
+        let input = Input::from_str(&config, &report.review_prompt(), None);
+        start_directive(&config, input, false, abort_signal).await?;
+        process::exit(report.fails(&cli.fail_on) as i32);
+    }
+    if let Some(target) = &cli.postmortem {
+        let scope = PostmortemScope::parse(target, chrono::Local::now());
+        let postmortem = Postmortem::collect(&config.read(), &scope)?;
+        if postmortem.is_empty() {
+            bail!("Nothing was recorded for '{target}'");
+        }
+        println!("{}", postmortem.timeline());
+        let input = Input::from_str(&config, &postmortem.prompt(), None);
+        return start_directive(&config, input, false, abort_signal).await;
+    }
+    if !cli.models.is_empty() {
+        let Some(text) = text else {
+            bail!("No prompt to compare the models on");
+        };
+        return compare_answers(&config, &text, &cli.models, abort_signal).await;
+    }
     if let Some(addr) = cli.serve {
         return serve::run(config, addr).await;
     }
@@ -173,9 +322,13 @@ async fn run(config: GlobalConfig, cli: Cli, text: Option<String>) -> Result<()>
     }
     if cli.execute && !is_repl {
         let input = create_input(&config, text, &cli.file, abort_signal.clone()).await?;
-        shell_execute(&config, &SHELL, input, abort_signal.clone()).await?;
+        shell_execute(&config, &SHELL, input, cli.team, abort_signal.clone()).await?;
         return Ok(());
     }
+    if cli.patch && !is_repl {
+        let input = create_input(&config, text, &cli.file, abort_signal.clone()).await?;
+        return patch_files(&config, input).await;
+    }
     config.write().apply_prelude()?;
     match is_repl {
         false => {
@@ -242,12 +395,35 @@ async fn shell_execute(
     config: &GlobalConfig,
     shell: &Shell,
     mut input: Input,
+    team: bool,
     abort_signal: AbortSignal,
 ) -> Result<()> {
     let client = input.create_client()?;
     config.write().before_chat_completion(&input)?;
-    let (eval_str, _) =
-        call_chat_completions(&input, false, true, client.as_ref(), abort_signal.clone()).await?;
+    // Relative dates and sizes are computed here, the model gets the exact values
+    let decimal_comma = locale_uses_decimal_comma(&sys_locale::get_locale().unwrap_or_default());
+    let quantities = parse_request_quantities(&input.text(), decimal_comma);
+    let mut prompt_input = input.clone();
+    if let Some(hint) = quantities_prompt(&quantities) {
+        prompt_input.set_text(format!("{}\n\n{hint}", input.text()));
+    }
+    let (mut eval_str, objections) = match team {
+        true => {
+            let plan = run_team(config, &prompt_input.text(), abort_signal.clone()).await?;
+            (plan.command, plan.objections)
+        }
+        false => {
+            let (output, _) = call_chat_completions(
+                &prompt_input,
+                false,
+                true,
+                client.as_ref(),
+                abort_signal.clone(),
+            )
+            .await?;
+            (output, vec![])
+        }
+    };
 
     config
         .write()
@@ -255,23 +431,106 @@ async fn shell_execute(
     if eval_str.is_empty() {
         bail!("No command generated");
     }
+    let hooks = config.read().hooks.clone();
+    if let Some(command) = hooks
+        .as_ref()
+        .map(|v| v.on_command_suggested(&eval_str))
+        .transpose()?
+        .flatten()
+    {
+        eval_str = command;
+    }
     if config.read().dry_run {
         config.read().print_markdown(&eval_str)?;
         return Ok(());
     }
     if *IS_STDOUT_TERMINAL {
-        let options = ["preview", "execute", "revise", "tutor", "copy", "quit"];
-        let command = color_text(eval_str.trim(), nu_ansi_term::Color::Rgb(255, 165, 0));
-        let first_letter_color = nu_ansi_term::Color::Cyan;
+        let options = [
+            "preview", "execute", "always", "revise", "tutor", "copy", "quit",
+        ];
+        let first_letter_color = theme().key.0;
         let prompt_text = options
             .iter()
             .map(|v| format!("{}{}", color_text(&v[0..1], first_letter_color), &v[1..]))
             .collect::<Vec<String>>()
             .join(&dimmed_text(" | "));
+        let mut guard = GuardState::picked();
+        let mut decision = AuditDecision::Approved;
+        let shell_kind = ShellKind::from_name(&shell.name);
+        let command_lists = config.read().command_lists();
+        let risk_confirm_score = config.read().risk_confirm_score;
+        let mut analysis = CommandAnalysis::analyze(&eval_str);
+        // Trusted and always allowed commands skip the menu, except on remote targets, when
+        // risky enough to confirm or when the reviewer objects
+        let mut trusted = match config.read().remote.is_some() || !objections.is_empty() {
+            true => None,
+            false => match command_lists.allows(&eval_str, &shell_kind) {
+                true => Some("always_allowed_commands".to_string()),
+                false => match &analysis.allowed_by_policy {
+                    Some(rule) => Some(format!("policy rule '{rule}'")),
+                    None => TrustStore::load()?
+                        .find(&eval_str)
+                        .map(|(scope, pattern)| format!("'{pattern}' ({scope})")),
+                },
+            }
+            .filter(|_| analysis.risk.score < risk_confirm_score),
+        };
         loop {
-            println!("{command}");
-            let answer_char =
-                read_single_key(&['p', 'e', 'r', 't', 'c', 'q'], 'e', &format!("{prompt_text}: "))?;
+            // Revisions replace the command, analyze each version once
+            if analysis.command != eval_str {
+                analysis = CommandAnalysis::analyze(&eval_str);
+            }
+            let annotated = annotate(config, shell, &eval_str);
+            let script = annotated.as_deref().unwrap_or(eval_str.trim());
+            println!("{}", color_text(script, theme().accent.0));
+            for warning in check_command_quantities(&eval_str, &quantities) {
+                println!("{}", warning_text(&warning));
+            }
+            if !objections.is_empty() {
+                println!(
+                    "{}",
+                    warning_text(&format!(
+                        "⚠ The reviewer objects:\n{}",
+                        format_objections(&objections)
+                    ))
+                );
+            }
+            if let Some(consequence) = analysis.consequence() {
+                println!("{}", warning_text(&consequence));
+            }
+            let answer_char = match trusted.take() {
+                Some(source) => {
+                    println!(
+                        "{}",
+                        dimmed_text(&format!("✓ Trusted by {source}, executing."))
+                    );
+                    decision = AuditDecision::Trusted;
+                    guard.trusted = true;
+                    'e'
+                }
+                None => read_single_key(
+                    &['p', 'e', 'a', 'r', 't', 'c', 'q'],
+                    'e',
+                    &format!("{prompt_text}: "),
+                )?,
+            };
+            let answer_char = match answer_char {
+                'a' => {
+                    let mut store = TrustStore::load()?;
+                    let scope = match project_root() {
+                        Some(root) => TrustScope::Project(root),
+                        None => TrustScope::Global,
+                    };
+                    store.add(&eval_str, &scope);
+                    store.save()?;
+                    println!(
+                        "{}",
+                        dimmed_text(&format!("✓ Trusted the command ({scope})."))
+                    );
+                    'e'
+                }
+                v => v,
+            };
 
             match answer_char {
                 'p' => {
@@ -282,41 +541,91 @@ async fn shell_execute(
                     continue;
                 }
                 'e' => {
-                    // Create backup before execution if needed
-                    let backup_manager = BackupManager::new()?;
-                    let file_paths = extract_file_paths_from_command(&eval_str);
-
-                    let mut backup_id = None;
-                    if !file_paths.is_empty() {
-                        match backup_manager.create_backup(&eval_str, file_paths) {
-                            Ok(backup) => {
-                                println!("{}", dimmed_text(&format!("✓ Backup created: {}", backup.id)));
-                                backup_id = Some(backup.id);
-                            }
-                            Err(e) => {
-                                eprintln!("{}", dimmed_text(&format!("⚠ Backup failed: {}", e)));
-                                println!("{}", dimmed_text("Continue anyway? [y/N]"));
-                                let mut response = String::new();
-                                std::io::stdin().read_line(&mut response)?;
-                                if !response.trim().eq_ignore_ascii_case("y") {
-                                    println!("{}", dimmed_text("Execution cancelled."));
-                                    continue;
-                                }
+                    let justification =
+                        match check_execution(config, shell, &mut eval_str, &analysis, &mut guard)
+                            .await?
+                        {
+                            Guard::Run(justification) => justification,
+                            Guard::Again | Guard::Stop => continue,
+                        };
+                    let remote = config.read().remote.clone();
+
+                    let suggest = decision == AuditDecision::Approved
+                        && remote.is_none()
+                        && config.read().suggest_rules
+                        && analysis.risk.score < risk_confirm_score;
+                    if suggest {
+                        if let Some(count) = suggest_rule(&AuditLog::read()?, &eval_str) {
+                            let ans = Confirm::new(&format!(
+                                "You've approved '{}' {count} times, auto-approve it?",
+                                eval_str.trim()
+                            ))
+                            .with_default(false)
+                            .prompt()?;
+                            if ans {
+                                let path = Config::policy_file();
+                                let name = SafetyPolicy::append_allow_rule(&path, &eval_str)?;
+                                println!(
+                                    "{}",
+                                    dimmed_text(&format!(
+                                        "✓ Added rule '{name}' to {}.",
+                                        path.display()
+                                    ))
+                                );
+                            } else {
+                                audit(
+                                    config,
+                                    AuditEntry::new(&eval_str, AuditDecision::RuleDeclined),
+                                );
                             }
                         }
                     }
 
-                    debug!("{} {:?}", shell.cmd, &[&shell.arg, &eval_str]);
-                    let code = run_command(&shell.cmd, &[&shell.arg, &eval_str], None)?;
+                    let Some(prepared) = prepare_execution(config, &eval_str, &analysis).await?
+                    else {
+                        continue;
+                    };
+
+                    let span = tracing::info_span!("execute", command = eval_str.as_str());
+                    let code = match &remote {
+                        Some(remote) => span.in_scope(|| remote.target.run(&eval_str))?,
+                        None if is_long_running(&eval_str, &shell_kind) => {
+                            let note = "Supervising the output, errors and stalls get explained.";
+                            println!("{}", dimmed_text(note));
+                            let job = SupervisedJob::new(&eval_str);
+                            supervise(config, shell, &job, true)
+                                .instrument(span.clone())
+                                .await?
+                        }
+                        None => span.in_scope(|| {
+                            debug!("{} {:?}", shell.cmd, &[&shell.arg, &eval_str]);
+                            run_command(&shell.cmd, &[&shell.arg, &eval_str], None)
+                        })?,
+                    };
+                    span.in_scope(|| tracing::info!(code, "command exited"));
+                    drop(prepared.exec_lock);
 
-                    if code != 0 && backup_id.is_some() {
-                        println!("\n{}", dimmed_text(&format!(
-                            "⚠ Command failed! To restore backup, run: .backup restore {}",
-                            backup_id.unwrap()
-                        )));
+                    let mut entry = AuditEntry::new(&eval_str, decision)
+                        .with_safety_level(analysis.safety_level.clone())
+                        .with_exit_code(code)
+                        .with_undo(analysis.undo_command.clone().filter(|_| remote.is_none()))
+                        .with_backups(prepared.backup_ids);
+                    if let Some(justification) = &justification {
+                        entry = entry.with_reason(justification);
                     }
+                    audit(config, entry);
 
-                    if code == 0 && config.read().save_shell_history {
+                    if let Some(backup_id) = prepared.backup_id.filter(|_| code != 0) {
+                        println!(
+                            "\n{}",
+                            dimmed_text(&format!(
+                                "⚠ Command failed! To restore backup, run: .backup restore {}",
+                                backup_id
+                            ))
+                        );
+                    }
+
+                    if code == 0 && remote.is_none() && config.read().save_shell_history {
                         let _ = append_to_shell_history(&shell.name, &eval_str, code);
                     }
                     process::exit(code);
@@ -325,26 +634,405 @@ async fn shell_execute(
                     let revision = Text::new("Enter your revision:").prompt()?;
                     let text = format!("{}\n{revision}", input.text());
                     input.set_text(text);
-                    return shell_execute(config, shell, input, abort_signal.clone()).await;
+                    return shell_execute(config, shell, input, team, abort_signal.clone()).await;
                 }
                 't' => {
                     // Command Tutor Mode - enhanced describe
-                    if let Err(e) = show_command_tutorial(&eval_str, config) {
+                    if let Err(e) = fetch_tldr_pages(&eval_str).await {
+                        debug!("tldr pages: {e}");
+                    }
+                    if let Err(e) = show_command_tutorial(&eval_str) {
                         eprintln!("Tutorial error: {}", e);
                     }
                     println!();
                     continue;
                 }
                 'c' => {
-                    set_text(&eval_str)?;
+                    set_text(script)?;
                     println!("{}", dimmed_text("✓ Copied the command."));
+                    audit(
+                        config,
+                        AuditEntry::new(&eval_str, AuditDecision::Analyzed)
+                            .with_safety_level(analysis.safety_level.clone())
+                            .with_reason("copied"),
+                    );
+                }
+                'q' => {
+                    audit(config, AuditEntry::new(&eval_str, AuditDecision::Denied));
                 }
                 _ => {}
             }
             break;
         }
     } else {
-        println!("{eval_str}");
+        for warning in check_command_quantities(&eval_str, &quantities) {
+            eprintln!("{warning}");
+        }
+        if !objections.is_empty() {
+            eprintln!("The reviewer objects:\n{}", format_objections(&objections));
+        }
+        match annotate(config, shell, &eval_str) {
+            Some(script) => println!("{script}"),
+            None => println!("{eval_str}"),
+        }
+        let level = CommandAnalysis::analyze(&eval_str).safety_level;
+        audit(
+            config,
+            AuditEntry::new(&eval_str, AuditDecision::Analyzed)
+                .with_safety_level(level)
+                .with_reason("printed"),
+        );
+    }
+    Ok(())
+}
+
+/// The annotated script when `annotate_scripts` is on and the command spans several lines.
+fn annotate(config: &GlobalConfig, shell: &Shell, command: &str) -> Option<String> {
+    let multi_line = command.trim().lines().count() > 1;
+    (config.read().annotate_scripts && multi_line)
+        .then(|| annotate_script(command, &ShellKind::from_name(&shell.name)))
+}
+
+/// Show the diffs the model proposes and apply them all together, after a backup.
+async fn patch_files(config: &GlobalConfig, input: Input) -> Result<()> {
+    let text = input.fetch_chat_text().await?;
+    let cwd = env::current_dir()?;
+    let set = match PatchSet::parse(&text, &cwd) {
+        Ok(set) => set,
+        Err(err) => {
+            config.read().print_markdown(&text)?;
+            bail!("{err} in the reply");
+        }
+    };
+    println!("{}\n", set.display());
+    let summary = set.summary();
+    for line in &summary {
+        println!("{}", dimmed_text(line));
+    }
+    let label = format!("patch {}", summary.join(", "));
+    if config.read().read_only {
+        let reason = "read-only mode doesn't change files";
+        audit(
+            config,
+            AuditEntry::new(&label, AuditDecision::Blocked).with_reason(reason),
+        );
+        bail!("Not applied, {reason}");
+    }
+    let apply = Confirm::new(&format!("Apply the changes to {} files?", summary.len()))
+        .with_default(false)
+        .prompt()?;
+    if !apply {
+        println!("{}", dimmed_text("Nothing was changed."));
+        audit(config, AuditEntry::new(&label, AuditDecision::Denied));
+        return Ok(());
+    }
+    let entry = AuditEntry::new(&label, AuditDecision::Approved).with_changes(summary);
+    let outcome = BackupManager::new().and_then(|manager| {
+        if let Some(fallback) = manager.fallback() {
+            eprintln!("{}", warning_text(fallback));
+        }
+        set.apply(&manager, &label)
+    });
+    match outcome {
+        Ok(outcome) => {
+            let backups: Vec<String> = outcome.backup.iter().map(|v| v.id.clone()).collect();
+            if let Some(id) = backups.first() {
+                println!("{}", dimmed_text(&format!("✓ Backup created: {id}")));
+            }
+            println!(
+                "{}",
+                success_text(&format!("✓ Applied to {} files", outcome.changed.len()))
+            );
+            audit(config, entry.with_exit_code(0).with_backups(backups));
+            Ok(())
+        }
+        Err(err) => {
+            audit(
+                config,
+                entry.with_exit_code(1).with_reason(&format!("{err:#}")),
+            );
+            Err(err)
+        }
+    }
+}
+
+fn manage_trust(args: &[String]) -> Result<()> {
+    let mut store = TrustStore::load()?;
+    let pattern = args.get(1).map(|v| v.as_str());
+    match (args[0].as_str(), pattern) {
+        ("list", None) => {
+            for (scope, pattern) in store.list() {
+                println!("{pattern}\t({scope})");
+            }
+        }
+        ("add", Some(pattern)) => {
+            let scope = match project_root() {
+                Some(root) => TrustScope::Project(root),
+                None => TrustScope::Global,
+            };
+            store.add(pattern, &scope);
+            store.save()?;
+            println!("✓ Trusted '{pattern}' ({scope})");
+        }
+        ("remove", Some(pattern)) => {
+            if !store.remove(pattern) {
+                bail!("'{pattern}' is not trusted in this project or globally");
+            }
+            store.save()?;
+            println!("✓ Removed '{pattern}'");
+        }
+        _ => bail!("Usage: --trust list | --trust add <PATTERN> | --trust remove <PATTERN>"),
+    }
+    Ok(())
+}
+
+fn show_audit(args: &[String]) -> Result<()> {
+    let entries = AuditLog::read()?;
+    let args: Vec<&str> = args.iter().map(|v| v.as_str()).collect();
+    let found = match args.as_slice() {
+        ["list"] | ["list", _] => {
+            let range = args.get(1).copied().unwrap_or("7d");
+            match PostmortemScope::parse(range, chrono::Local::now()) {
+                PostmortemScope::Range { from, until } => entries_between(&entries, from, until),
+                PostmortemScope::Session(_) => {
+                    bail!("Invalid range '{range}', expected e.g. 2h, 7d or 2026-10-09..")
+                }
+            }
+        }
+        ["search", query] => search_entries(&entries, query),
+        _ => bail!("Usage: --audit list [RANGE] | --audit search <QUERY>"),
+    };
+    if found.is_empty() {
+        println!("No audit entries found in '{}'", AuditLog::path().display());
+    }
+    for entry in found {
+        println!("{}", entry.summary());
+    }
+    Ok(())
+}
+
+async fn replay_session(config: &GlobalConfig, name: &str, execute: &[usize]) -> Result<()> {
+    let replay = SessionReplay::load(&config.read(), name)?;
+    if let Some(step) = execute.iter().find(|v| **v == 0 || **v > replay.steps()) {
+        bail!("No step {step}, '{name}' has {} steps", replay.steps());
+    }
+    let mut render = MarkdownRender::init(config.read().render_options()?)?;
+    let total = replay.turns.len();
+    println!(
+        "Replaying '{}': {total} turns, {} steps, executing {}\n",
+        replay.name,
+        replay.steps(),
+        match execute.is_empty() {
+            true => "none".to_string(),
+            false => execute
+                .iter()
+                .map(|v| v.to_string())
+                .collect::<Vec<_>>()
+                .join(", "),
+        }
+    );
+    for (i, turn) in replay.turns.iter().enumerate() {
+        let role = match turn.role {
+            MessageRole::User => "You",
+            MessageRole::Tool => "Tool",
+            _ => "Assistant",
+        };
+        println!(
+            "{}",
+            dimmed_text(&format!("── Turn {}/{total} · {role}", i + 1))
+        );
+        if !turn.text.trim().is_empty() {
+            match turn.role.is_assistant() {
+                true => println!("{}", render.render(&turn.text)),
+                false => println!("{}", turn.text.trim_end()),
+            }
+        }
+        for step in &turn.steps {
+            // Analyzed on this machine and right now, not as it was back then
+            let analysis = CommandAnalysis::analyze(&step.command);
+            println!("\n[{}] $ {}", step.number, step.command);
+            if !execute.contains(&step.number) {
+                let level = &analysis.safety_level;
+                println!("{}", level.color().paint(format!("Now {}", level.label())));
+                println!("{}", dimmed_text(&step.recorded.stub()));
+                continue;
+            }
+            println!("{}", analysis.display());
+            if let RecordedRun::Executed(entry) = &step.recorded {
+                let warning = format!(
+                    "⚠️  This step was executed at {}, its changes may already be in place.",
+                    entry.timestamp
+                );
+                println!("{}", warning_text(&warning));
+            }
+            if !execute_suggested_command(config, &step.command, &analysis, false).await? {
+                println!("{}", dimmed_text(&format!("Skipped step {}.", step.number)));
+            }
+        }
+        if *IS_STDOUT_TERMINAL
+            && i + 1 < total
+            && !Confirm::new("Next turn?").with_default(true).prompt()?
+        {
+            return Ok(());
+        }
+        println!();
+    }
+    Ok(())
+}
+
+fn manage_env(args: &[String]) -> Result<()> {
+    let args: Vec<&str> = args.iter().map(|v| v.as_str()).collect();
+    match args.as_slice() {
+        ["export"] => println!("{}", serde_json::to_string_pretty(&EnvProfile::detect())?),
+        ["export", file] => {
+            std::fs::write(file, serde_json::to_string_pretty(&EnvProfile::detect())?)
+                .with_context(|| format!("Failed to write '{file}'"))?;
+            println!("✓ Saved this machine's environment to '{file}'");
+        }
+        ["diff", left, right] => {
+            let comparison =
+                EnvComparison::load(std::path::Path::new(left), std::path::Path::new(right))?;
+            print!("{}", comparison.render());
+        }
+        _ => bail!("Usage: --env export [FILE] | --env diff <A> <B> [QUESTION]"),
+    }
+    Ok(())
+}
+
+fn recommend_models() -> Result<()> {
+    let env = EnvProfile::detect();
+    let models = env.recommend_local_models();
+    println!("This machine: {}\n", env.hardware_summary());
+    if models.is_empty() {
+        bail!("None of the bundled local models runs well with this memory, use a hosted model");
+    }
+    let width = models
+        .iter()
+        .map(|v| v.name.chars().count())
+        .max()
+        .unwrap_or_default();
+    println!(
+        "{:<width$} {:<7} {:>7} {:<26} PULL",
+        "MODEL", "QUANT", "MEMORY", "RUNS ON"
+    );
+    for model in &models {
+        println!(
+            "{:<width$} {:<7} {:>6.1}G {:<26} {}",
+            model.name,
+            model.quantization,
+            model.memory_gb,
+            model.fit.to_string(),
+            model.pull_command()
+        );
+    }
+    if !RealFs.has_tool("ollama") {
+        println!(
+            "\n💡 Install Ollama to run them: {}",
+            env.ollama_install_command()
+        );
+    }
+    Ok(())
+}
+
+async fn manage_recipes(config: &GlobalConfig, args: &[String]) -> Result<()> {
+    let mut book = RecipeBook::load()?;
+    let name = args.get(1).map(|v| v.as_str());
+    match (args[0].as_str(), name) {
+        ("list", None) => {
+            for recipe in book.all() {
+                let (level, risk) = recipe.verdict();
+                let source = match recipe.builtin {
+                    true => " (built-in)",
+                    false => "",
+                };
+                println!(
+                    "{}\t[{level:?}, risk {risk}/100]{source} {}",
+                    recipe.name, recipe.description
+                );
+            }
+        }
+        ("add", Some(path)) => {
+            let recipe = Recipe::from_file(std::path::Path::new(path))?;
+            for (i, step) in recipe.steps.iter().enumerate() {
+                let level = step.level.clone().unwrap_or(SafetyLevel::Safe);
+                println!(
+                    "{}. [{level:?}, risk {}/100] {}",
+                    i + 1,
+                    step.risk.unwrap_or_default(),
+                    step.command
+                );
+                for warning in &step.warnings {
+                    println!("   {}", note_text(warning, &level));
+                }
+            }
+            let name = recipe.name.clone();
+            book.add(recipe);
+            book.save()?;
+            println!("✓ Added recipe '{name}'");
+        }
+        ("run", Some(name)) => {
+            let recipe = book
+                .find(name)
+                .with_context(|| format!("No recipe named '{name}', see `--recipe list`"))?;
+            if !recipe.description.is_empty() {
+                println!("{}", dimmed_text(&recipe.description));
+            }
+            let mut values = indexmap::IndexMap::new();
+            for param in &recipe.params {
+                let message = format!("{}:", param.name);
+                let mut prompt = Text::new(&message);
+                if let Some(description) = &param.description {
+                    prompt = prompt.with_help_message(description);
+                }
+                if let Some(default) = &param.default {
+                    prompt = prompt.with_default(default);
+                }
+                let value = prompt.prompt()?;
+                if value.trim().is_empty() {
+                    bail!("No value for '{}'", param.name);
+                }
+                values.insert(param.name.clone(), value);
+            }
+            let commands = recipe.fill(&values)?;
+            let total = commands.len();
+            for (i, (step, command)) in recipe.steps.iter().zip(commands).enumerate() {
+                println!("\nStep {}/{total}: {command}", i + 1);
+                // Analyzed again with the values filled in, on this machine and right now
+                let mut command = command;
+                let mut analysis = CommandAnalysis::analyze(&command);
+                if !analysis.placeholders.is_empty() {
+                    match fill_placeholders_interactively(&command, &analysis.placeholders)? {
+                        Some(filled) => {
+                            command = filled;
+                            analysis = CommandAnalysis::analyze(&command);
+                        }
+                        None => {
+                            println!("{}", dimmed_text(&format!("Stopped before step {}.", i + 1)));
+                            return Ok(());
+                        }
+                    }
+                }
+                println!("{}", analysis.display());
+                if let Some(warning) = step.drift(&analysis) {
+                    println!("{}", warning_text(&warning));
+                }
+                if !execute_suggested_command(config, &command, &analysis, false).await? {
+                    println!("{}", dimmed_text(&format!("Stopped before step {}.", i + 1)));
+                    return Ok(());
+                }
+            }
+            println!("✓ Recipe '{}' finished", recipe.name);
+        }
+        ("remove", Some(name)) => {
+            if !book.remove(name) {
+                bail!("'{name}' is not an added recipe");
+            }
+            book.save()?;
+            println!("✓ Removed recipe '{name}'");
+        }
+        _ => bail!(
+            "Usage: --recipe list | --recipe add <FILE> | --recipe run <NAME> | --recipe remove <NAME>"
+        ),
     }
     Ok(())
 }
@@ -373,8 +1061,15 @@ async fn create_input(
     Ok(input)
 }
 
-fn setup_logger(is_serve: bool) -> Result<()> {
-    let (log_level, log_path) = Config::log_config(is_serve)?;
+fn setup_logger(is_serve: bool, verbose: bool) -> Result<()> {
+    let (mut log_level, mut log_path) = Config::log_config(is_serve)?;
+    if verbose {
+        log_level = log_level.max(LevelFilter::Debug);
+        // `--verbose` is for watching the safety pipeline as it runs
+        if std::env::var(get_env_name("log_path")).is_err() {
+            log_path = None;
+        }
+    }
     if log_level == LevelFilter::Off {
         return Ok(());
     }
@@ -386,22 +1081,24 @@ fn setup_logger(is_serve: bool) -> Result<()> {
             false => crate_name.into(),
         },
     };
-    let config = ConfigBuilder::new()
-        .add_filter_allow(log_filter)
-        .set_time_format_custom(format_description!(
-            "[year]-[month]-[day]T[hour]:[minute]:[second].[subsecond digits:3]Z"
-        ))
-        .set_thread_level(LevelFilter::Off)
-        .build();
-    match log_path {
-        None => {
-            SimpleLogger::init(log_level, config)?;
-        }
-        Some(log_path) => {
+    let json = std::env::var(get_env_name("log_format")).is_ok_and(|v| v == "json");
+    let builder = tracing_subscriber::fmt()
+        .with_env_filter(tracing_subscriber::EnvFilter::try_new(format!(
+            "{log_filter}={log_level}"
+        ))?)
+        .with_writer(std::io::stderr);
+    let result = match (log_path, json) {
+        (None, false) => builder.try_init(),
+        (None, true) => builder.json().try_init(),
+        (Some(log_path), json) => {
             ensure_parent_exists(&log_path)?;
-            let log_file = std::fs::File::create(log_path)?;
-            WriteLogger::init(log_level, config, log_file)?;
+            let log_file = std::sync::Mutex::new(std::fs::File::create(log_path)?);
+            let builder = builder.with_ansi(false).with_writer(log_file);
+            match json {
+                true => builder.json().try_init(),
+                false => builder.try_init(),
+            }
         }
-    }
-    Ok(())
+    };
+    result.map_err(|err| anyhow::anyhow!(err))
 }
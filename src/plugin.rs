@@ -0,0 +1,533 @@
+use crate::{
+    function::{FunctionDeclaration, Functions, JsonSchema},
+    utils::*,
+};
+
+use anyhow::{anyhow, bail, Context, Result};
+use indexmap::IndexMap;
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use std::{
+    fs,
+    io::Write,
+    path::{Path, PathBuf},
+    process::{Command, Stdio},
+};
+
+const MANIFEST_EXT: &str = "json";
+const WASM_RUNTIME: &str = "wasmtime";
+
+/// Plugins are external executables living in `<config_dir>/plugins/`.
+///
+/// Each plugin is described by a `<name>.json` manifest next to its executable:
+///
+/// ```json
+/// {
+///   "name": "weather",
+///   "description": "Get the current weather for a city",
+///   "parameters": { "type": "object", "properties": { "city": { "type": "string" } } },
+///   "command": "weather.sh"
+/// }
+/// ```
+///
+/// The plugin is spawned with the call arguments as a JSON object on stdin and must
+/// write its result to stdout. JSON output is passed through as-is, anything else is
+/// wrapped as `{"output": "..."}`.
+///
+/// Functions come first: a plugin named like a function in `functions.json` is skipped
+/// with a warning, and a name an agent declares runs the agent's function.
+///
+/// A manifest may point to a WASI module with `"wasm": "analyzer.wasm"` instead of a
+/// native command. Such plugins run in an external WASI runtime (`wasmtime` by default,
+/// see `AICHAT_WASM_RUNTIME`) rather than one embedded in aichat, which keeps wasmtime's
+/// large dependency tree out of the build; the runtime must be installed for them. The
+/// manifest asks for capabilities, directories inside the plugins dir only:
+///
+/// ```json
+/// "capabilities": { "dirs": ["data"], "env": ["LANG"], "network": false }
+/// ```
+///
+/// The plugin only gets the ones the user also grants under `plugin_grants` in the config:
+///
+/// ```yaml
+/// plugin_grants:
+///   lint: { dirs: [data], env: [LANG] }
+/// ```
+#[derive(Debug, Clone, Default)]
+pub struct Plugins {
+    plugins: Vec<Plugin>,
+}
+
+impl Plugins {
+    pub fn init(plugins_dir: &Path, grants: &IndexMap<String, PluginCapabilities>) -> Result<Self> {
+        let mut plugins = vec![];
+        let Ok(rd) = fs::read_dir(plugins_dir) else {
+            return Ok(Self { plugins });
+        };
+        let mut paths: Vec<PathBuf> = rd
+            .flatten()
+            .map(|v| v.path())
+            .filter(|v| v.extension().map(|v| v == MANIFEST_EXT).unwrap_or_default())
+            .collect();
+        paths.sort_unstable();
+        for path in paths {
+            match Plugin::load(&path, grants) {
+                Ok(plugin) => {
+                    if plugins.iter().any(|v: &Plugin| v.name() == plugin.name()) {
+                        warn!(
+                            "Skip duplicated plugin '{}' at {}",
+                            plugin.name(),
+                            path.display()
+                        );
+                        continue;
+                    }
+                    plugins.push(plugin)
+                }
+                Err(err) => warn!("{err:?}"),
+            }
+        }
+        Ok(Self { plugins })
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Plugin> {
+        self.plugins.iter().find(|v| v.name() == name)
+    }
+
+    pub fn list(&self) -> &[Plugin] {
+        &self.plugins
+    }
+
+    pub fn declarations(&self) -> Vec<FunctionDeclaration> {
+        self.plugins.iter().map(|v| v.declaration()).collect()
+    }
+
+    /// Drop the plugins named like one of `functions`, so a name always runs what its
+    /// declaration describes.
+    pub fn skip_functions(&mut self, functions: &Functions) {
+        self.plugins.retain(|plugin| {
+            let taken = functions.contains(plugin.name());
+            if taken {
+                warn!(
+                    "Skip plugin '{}', a function has the same name",
+                    plugin.name()
+                );
+            }
+            !taken
+        });
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PluginManifest {
+    pub name: String,
+    pub description: String,
+    #[serde(default = "default_parameters")]
+    pub parameters: JsonSchema,
+    #[serde(default)]
+    pub command: Option<String>,
+    #[serde(default)]
+    pub wasm: Option<String>,
+    #[serde(default)]
+    pub capabilities: PluginCapabilities,
+}
+
+/// What a sandboxed (WASM) plugin is allowed to touch.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct PluginCapabilities {
+    /// Host directories to preopen, relative to the plugins dir.
+    #[serde(default)]
+    pub dirs: Vec<String>,
+    /// Names of environment variables forwarded to the plugin.
+    #[serde(default)]
+    pub env: Vec<String>,
+    #[serde(default)]
+    pub network: bool,
+}
+
+impl PluginCapabilities {
+    /// The capabilities asked for that `grant` allows too, none without a grant.
+    pub fn granted(&self, grant: Option<&PluginCapabilities>) -> Self {
+        let Some(grant) = grant else {
+            return Self::default();
+        };
+        let dir_key = |v: &String| v.trim_start_matches("./").trim_end_matches('/').to_string();
+        let granted_dirs: Vec<String> = grant.dirs.iter().map(dir_key).collect();
+        Self {
+            dirs: self
+                .dirs
+                .iter()
+                .filter(|v| granted_dirs.contains(&dir_key(v)))
+                .cloned()
+                .collect(),
+            env: self
+                .env
+                .iter()
+                .filter(|v| grant.env.contains(v))
+                .cloned()
+                .collect(),
+            network: self.network && grant.network,
+        }
+    }
+}
+
+fn default_parameters() -> JsonSchema {
+    JsonSchema {
+        type_value: Some("object".into()),
+        description: None,
+        properties: Some(Default::default()),
+        items: None,
+        any_of: None,
+        enum_value: None,
+        default: None,
+        required: None,
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct Plugin {
+    manifest: PluginManifest,
+    runtime: PluginRuntime,
+    dir: PathBuf,
+    /// The manifest's capabilities the user granted.
+    capabilities: PluginCapabilities,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum PluginRuntime {
+    Executable(PathBuf),
+    Wasm(PathBuf),
+}
+
+impl Plugin {
+    pub fn load(
+        manifest_path: &Path,
+        grants: &IndexMap<String, PluginCapabilities>,
+    ) -> Result<Self> {
+        let ctx = || format!("Failed to load plugin at {}", manifest_path.display());
+        let content = fs::read_to_string(manifest_path).with_context(ctx)?;
+        let manifest: PluginManifest = serde_json::from_str(&content).with_context(ctx)?;
+        if manifest.name.is_empty() || manifest.name.contains(char::is_whitespace) {
+            bail!(
+                "Invalid plugin name '{}' at {}",
+                manifest.name,
+                manifest_path.display()
+            );
+        }
+        let dir = manifest_path
+            .parent()
+            .unwrap_or(Path::new("."))
+            .to_path_buf();
+        if let Some(dir) = manifest
+            .capabilities
+            .dirs
+            .iter()
+            .find(|v| safe_join_path(&dir, v).is_none())
+        {
+            bail!(
+                "Plugin '{}' at {} asks for '{dir}', only directories inside the plugins dir can be preopened",
+                manifest.name,
+                manifest_path.display()
+            );
+        }
+        let capabilities = manifest.capabilities.granted(grants.get(&manifest.name));
+        if capabilities != manifest.capabilities {
+            debug!(
+                "Plugin '{}' runs with {capabilities:?}, not granted all of {:?}",
+                manifest.name, manifest.capabilities
+            );
+        }
+        let runtime = match (&manifest.wasm, &manifest.command) {
+            (Some(module), _) => PluginRuntime::Wasm(dir.join(module)),
+            (None, Some(command)) => PluginRuntime::Executable(dir.join(command)),
+            (None, None) => PluginRuntime::Executable(manifest_path.with_extension("")),
+        };
+        Ok(Self {
+            manifest,
+            runtime,
+            dir,
+            capabilities,
+        })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.name
+    }
+
+    pub fn description(&self) -> &str {
+        &self.manifest.description
+    }
+
+    pub fn runtime(&self) -> &PluginRuntime {
+        &self.runtime
+    }
+
+    fn command(&self) -> Command {
+        match self.runtime() {
+            PluginRuntime::Executable(path) => Command::new(path),
+            PluginRuntime::Wasm(module) => {
+                let runtime = std::env::var(get_env_name("wasm_runtime"))
+                    .unwrap_or_else(|_| WASM_RUNTIME.into());
+                let mut command = Command::new(runtime);
+                command.env_clear().args(self.wasm_args(module));
+                if let Ok(path) = std::env::var("PATH") {
+                    command.env("PATH", path);
+                }
+                // Values go through the runtime's environment, never its command line
+                command.envs(self.wasm_env());
+                command
+            }
+        }
+    }
+
+    fn wasm_args(&self, module: &Path) -> Vec<String> {
+        let capabilities = &self.capabilities;
+        let mut args = vec!["run".to_string()];
+        for dir in &capabilities.dirs {
+            let path = self.dir.join(dir);
+            let guest = format!("/{}", dir.trim_start_matches("./"));
+            args.push("--dir".into());
+            args.push(format!("{}::{guest}", path.display()));
+        }
+        for (name, _) in self.wasm_env() {
+            args.push("--env".into());
+            args.push(name);
+        }
+        if capabilities.network {
+            args.extend(
+                ["-S", "inherit-network=y", "-S", "allow-ip-name-lookup=y"].map(String::from),
+            );
+        }
+        args.push(module.display().to_string());
+        args
+    }
+
+    /// The granted environment variables that are set, which `--env NAME` forwards from
+    /// the runtime's environment to the plugin.
+    fn wasm_env(&self) -> Vec<(String, String)> {
+        self.capabilities
+            .env
+            .iter()
+            .filter_map(|name| Some((name.clone(), std::env::var(name).ok()?)))
+            .collect()
+    }
+
+    pub fn declaration(&self) -> FunctionDeclaration {
+        FunctionDeclaration {
+            name: self.manifest.name.clone(),
+            description: self.manifest.description.clone(),
+            parameters: self.manifest.parameters.clone(),
+            agent: false,
+        }
+    }
+
+    pub fn run(&self, arguments: &Value) -> Result<Value> {
+        if *IS_STDOUT_TERMINAL {
+            println!(
+                "{}",
+                dimmed_text(&format!("Call plugin {} {arguments}", self.name()))
+            );
+        }
+        let mut child = self
+            .command()
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped())
+            .spawn()
+            .map_err(|err| anyhow!("Unable to run plugin {}, {err}", self.name()))?;
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin
+                .write_all(arguments.to_string().as_bytes())
+                .with_context(|| format!("Failed to send input to plugin {}", self.name()))?;
+        }
+        let output = child.wait_with_output()?;
+        if !output.status.success() {
+            let code = output.status.code().unwrap_or_default();
+            let stderr = String::from_utf8_lossy(&output.stderr);
+            bail!("Plugin {} exit with {code}: {}", self.name(), stderr.trim());
+        }
+        let stdout = String::from_utf8_lossy(&output.stdout);
+        let stdout = stdout.trim();
+        if stdout.is_empty() {
+            return Ok(Value::Null);
+        }
+        Ok(serde_json::from_str(stdout).unwrap_or_else(|_| json!({"output": stdout})))
+    }
+}
+
+/// Parse the arguments of a plugin `.`-command.
+///
+/// Accepts either a JSON object or `key=value` pairs; values that look like JSON
+/// (numbers, booleans, arrays) are decoded, everything else is kept as a string.
+pub fn parse_plugin_args(args: Option<&str>) -> Result<Value> {
+    let args = args.unwrap_or_default().trim();
+    if args.starts_with('{') {
+        return serde_json::from_str(args).map_err(|err| anyhow!("Invalid JSON arguments, {err}"));
+    }
+    let (words, _) = crate::repl::split_args_text(args, cfg!(windows));
+    let mut map = serde_json::Map::new();
+    for word in words {
+        let (key, value) = word
+            .split_once('=')
+            .ok_or_else(|| anyhow!("Invalid argument '{word}', expect <key>=<value>"))?;
+        let value = serde_json::from_str(value).unwrap_or_else(|_| json!(value));
+        map.insert(key.to_string(), value);
+    }
+    Ok(Value::Object(map))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_load_plugins() {
+        let dir = temp_file("-plugins-", "");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("weather.json"),
+            r#"{"name":"weather","description":"Get weather","parameters":{"type":"object","properties":{"city":{"type":"string"}}}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("echo.json"),
+            r#"{"name":"echo","description":"Echo input","command":"bin/echo.sh"}"#,
+        )
+        .unwrap();
+        fs::write(dir.join("broken.json"), "not json").unwrap();
+
+        let mut plugins = Plugins::init(&dir, &IndexMap::new()).unwrap();
+        assert_eq!(plugins.list().len(), 2);
+        let weather = plugins.find("weather").unwrap();
+        assert_eq!(
+            weather.runtime(),
+            &PluginRuntime::Executable(dir.join("weather"))
+        );
+        assert_eq!(weather.declaration().description, "Get weather");
+        let echo = plugins.find("echo").unwrap();
+        assert_eq!(
+            echo.runtime(),
+            &PluginRuntime::Executable(dir.join("bin/echo.sh"))
+        );
+        assert!(echo.declaration().parameters.is_empty_properties());
+
+        // A function of the same name keeps it
+        let functions_path = dir.join("functions.json");
+        fs::write(
+            &functions_path,
+            r#"[{"name":"weather","description":"Forecast","parameters":{"type":"object"}}]"#,
+        )
+        .unwrap();
+        let mut functions = Functions::init(&functions_path).unwrap();
+        plugins.skip_functions(&functions);
+        assert!(plugins.find("weather").is_none());
+        functions.extend(plugins.declarations());
+        let names: Vec<&str> = functions
+            .declarations()
+            .iter()
+            .map(|v| v.name.as_str())
+            .collect();
+        assert_eq!(names, ["weather", "echo"]);
+        assert_eq!(functions.find("weather").unwrap().description, "Forecast");
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_wasm_plugin_args() {
+        let dir = temp_file("-plugins-", "");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("lint.json"),
+            r#"{"name":"lint","description":"Lint","wasm":"lint.wasm","capabilities":{"dirs":["data"],"env":["AICHAT_TEST_UNSET_VAR","AICHAT_TEST_PLUGIN_SECRET"]}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("escape.json"),
+            r#"{"name":"escape","description":"Escape","wasm":"escape.wasm","capabilities":{"dirs":["../.."]}}"#,
+        )
+        .unwrap();
+        fs::write(
+            dir.join("root.json"),
+            r#"{"name":"root","description":"Root","wasm":"root.wasm","capabilities":{"dirs":["/"]}}"#,
+        )
+        .unwrap();
+        let module = dir.join("lint.wasm");
+
+        let plugins = Plugins::init(&dir, &IndexMap::new()).unwrap();
+        assert!(plugins.find("escape").is_none());
+        assert!(plugins.find("root").is_none());
+        let plugin = plugins.find("lint").unwrap();
+        assert_eq!(plugin.runtime(), &PluginRuntime::Wasm(module.clone()));
+        assert_eq!(
+            plugin.wasm_args(&module),
+            vec!["run".to_string(), module.display().to_string()]
+        );
+
+        let grant = PluginCapabilities {
+            dirs: vec!["./data".into(), "cache".into()],
+            env: vec!["AICHAT_TEST_PLUGIN_SECRET".into()],
+            network: true,
+        };
+        std::env::set_var("AICHAT_TEST_PLUGIN_SECRET", "s3cret");
+        let plugins = Plugins::init(&dir, &IndexMap::from([("lint".into(), grant)])).unwrap();
+        let plugin = plugins.find("lint").unwrap();
+        assert_eq!(
+            plugin.wasm_args(&module),
+            vec![
+                "run".to_string(),
+                "--dir".into(),
+                format!("{}::/data", dir.join("data").display()),
+                "--env".into(),
+                "AICHAT_TEST_PLUGIN_SECRET".into(),
+                module.display().to_string(),
+            ]
+        );
+        // The value is only in the runtime's environment, not in its visible arguments
+        let command = plugin.command();
+        assert!(command
+            .get_envs()
+            .any(|(k, v)| k == "AICHAT_TEST_PLUGIN_SECRET" && v == Some("s3cret".as_ref())));
+        assert!(!command
+            .get_args()
+            .any(|v| v.to_string_lossy().contains("s3cret")));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    #[cfg(unix)]
+    fn test_run_plugin() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = temp_file("-plugins-", "");
+        fs::create_dir_all(&dir).unwrap();
+        fs::write(
+            dir.join("echo.json"),
+            r#"{"name":"echo","description":"Echo"}"#,
+        )
+        .unwrap();
+        let script = dir.join("echo");
+        fs::write(&script, "#!/bin/sh\ncat\n").unwrap();
+        fs::set_permissions(&script, fs::Permissions::from_mode(0o755)).unwrap();
+
+        let plugins = Plugins::init(&dir, &IndexMap::new()).unwrap();
+        let output = plugins.find("echo").unwrap().run(&json!({"a": 1})).unwrap();
+        assert_eq!(output, json!({"a": 1}));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_parse_plugin_args() {
+        assert_eq!(parse_plugin_args(None).unwrap(), json!({}));
+        assert_eq!(
+            parse_plugin_args(Some(r#"{"city": "Taipei"}"#)).unwrap(),
+            json!({"city": "Taipei"})
+        );
+        assert_eq!(
+            parse_plugin_args(Some(r#"city="New York" days=3 metric=true"#)).unwrap(),
+            json!({"city": "New York", "days": 3, "metric": true})
+        );
+        assert!(parse_plugin_args(Some("city")).is_err());
+    }
+}
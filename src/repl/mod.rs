@@ -9,18 +9,26 @@ use crate::render::{MarkdownRender, RenderOptions};
 
 use crate::client::{call_chat_completions, call_chat_completions_streaming};
 use crate::config::{
-    macro_execute, AgentVariables, AssertState, Config, GlobalConfig, Input, LastMessage,
-    StateFlags,
+    check_execution, compare_models, find_undoable, interpret_progress, is_rag_document,
+    last_executed, macro_execute, prepare_execution, reload_config, render_answers, safest_answer,
+    search_notes, supervise, AgentVariables, AssertState, AuditDecision, AuditEntry, AuditLog,
+    Config, ConfigWatcher, GlobalConfig, Guard, GuardState, Input, LastMessage, SessionReport,
+    StateFlags, SupervisedJob, UNDO_REASON,
 };
+use crate::plugin::parse_plugin_args;
 use crate::render::render_error;
 use crate::utils::{
-    abortable_run_with_spinner, create_abort_signal, dimmed_text, set_text, temp_file, AbortSignal,
-    BackupManager,
+    abortable_run_with_spinner, accessible_text, capture_clipboard, capture_ports,
+    capture_processes, capture_tmux_pane, color_text, confirm_typo_fix, create_abort_signal,
+    dimmed_text, fill_placeholders_interactively, fuzzy_filter, is_long_running, run_command,
+    set_text, temp_file, theme, warning_text, AbortSignal, BackupManager, Capture, CommandAnalysis,
+    ExecLock, ShellKind, IS_STDOUT_TERMINAL, SHELL,
 };
 
 use anyhow::{anyhow, bail, Context, Result};
 use crossterm::cursor::SetCursorStyle;
 use fancy_regex::Regex;
+use inquire::Select;
 use reedline::CursorConfig;
 use reedline::{
     default_emacs_keybindings, default_vi_insert_keybindings, default_vi_normal_keybindings,
@@ -29,14 +37,21 @@ use reedline::{
 };
 use reedline::{MenuBuilder, Signal};
 use std::sync::LazyLock;
+use std::time::Instant;
 use std::{env, process};
+use tracing::Instrument;
 
 const MENU_NAME: &str = "completion_menu";
 
-static REPL_COMMANDS: LazyLock<[ReplCommand; 38]> = LazyLock::new(|| {
+static REPL_COMMANDS: LazyLock<[ReplCommand; 52]> = LazyLock::new(|| {
     [
         ReplCommand::new(".help", "Show this help guide", AssertState::pass()),
         ReplCommand::new(".info", "Show system info", AssertState::pass()),
+        ReplCommand::new(
+            ".status",
+            "Show the execution lock and the budget usage",
+            AssertState::pass(),
+        ),
         ReplCommand::new(
             ".edit config",
             "Modify configuration file",
@@ -179,15 +194,84 @@ static REPL_COMMANDS: LazyLock<[ReplCommand; 38]> = LazyLock::new(|| {
             AssertState::pass(),
         ),
         ReplCommand::new(".copy", "Copy last response", AssertState::pass()),
+        ReplCommand::new(
+            ".paste",
+            "Attach the clipboard to the next prompt",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".pane",
+            "Attach the visible tmux pane to the next prompt",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".ps",
+            "Attach the running processes to the next prompt",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".ports",
+            "Attach the listening ports to the next prompt",
+            AssertState::pass(),
+        ),
         ReplCommand::new(".set", "Modify runtime settings", AssertState::pass()),
+        ReplCommand::new(
+            ".mode",
+            "Switch to read-only mode for the rest of the session",
+            AssertState::pass(),
+        ),
         ReplCommand::new(
             ".delete",
             "Delete roles, sessions, RAGs, or agents",
             AssertState::pass(),
         ),
+        ReplCommand::new(
+            ".reload",
+            "Reload the config, hooks, theme and functions",
+            AssertState::pass(),
+        ),
         ReplCommand::new(".exit", "Exit REPL", AssertState::pass()),
-        ReplCommand::new(".export", "Export current session as markdown file", AssertState::pass()),
-        ReplCommand::new(".backup", "Manage command execution backups", AssertState::pass()),
+        ReplCommand::new(
+            ".export",
+            "Export current session as markdown file",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".backup",
+            "Manage command execution backups",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".report",
+            "Summarize the commands executed in this session",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".undo",
+            "Run the inverse of the last reversible command",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".note",
+            "Note why the last executed command was run",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(".notes", "Search the notes", AssertState::pass()),
+        ReplCommand::new(
+            ".cmds",
+            "Pick a command suggested in this session to run or copy",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".compare",
+            "Ask several models at once and compare their answers",
+            AssertState::pass(),
+        ),
+        ReplCommand::new(
+            ".progress",
+            "Summarize how far the supervised command got",
+            AssertState::pass(),
+        ),
     ]
 });
 static COMMAND_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"^\s*(\.\S*)\s*").unwrap());
@@ -199,6 +283,7 @@ pub struct Repl {
     editor: Reedline,
     prompt: ReplPrompt,
     abort_signal: AbortSignal,
+    watcher: ConfigWatcher,
 }
 
 impl Repl {
@@ -207,12 +292,14 @@ impl Repl {
 
         let prompt = ReplPrompt::new(config);
         let abort_signal = create_abort_signal();
+        let watcher = ConfigWatcher::new(&config.read());
 
         Ok(Self {
             config: config.clone(),
             editor,
             prompt,
             abort_signal,
+            watcher,
         })
     }
 
@@ -233,10 +320,13 @@ Type ".help" for additional help.
             if self.abort_signal.aborted_ctrld() {
                 break;
             }
+            // e.g. after `.edit config`
+            self.reload_changed_files().await;
             let sig = self.editor.read_line(&self.prompt);
             match sig {
                 Ok(Signal::Success(line)) => {
                     self.abort_signal.reset();
+                    self.reload_changed_files().await;
                     match run_repl_command(&self.config, self.abort_signal.clone(), &line).await {
                         Ok(exit) => {
                             if exit {
@@ -264,6 +354,30 @@ Type ".help" for additional help.
         Ok(())
     }
 
+    /// Pick up edits made to the config files while waiting for input.
+    async fn reload_changed_files(&mut self) {
+        let changed = self.watcher.changed_files(&self.config.read());
+        let (documents, files): (Vec<_>, Vec<_>) = changed
+            .into_iter()
+            .partition(|v| is_rag_document(&self.config.read(), v));
+        if !files.is_empty() {
+            match reload_config(&self.config, &files) {
+                Ok(report) => println!("{}\n", dimmed_text(&report)),
+                Err(err) => {
+                    render_error(err.context("Failed to reload, keeping the previous config"));
+                    println!()
+                }
+            }
+        }
+        if !documents.is_empty() {
+            println!("{}", dimmed_text("RAG documents changed, rebuilding"));
+            if let Err(err) = Config::rebuild_rag(&self.config, self.abort_signal.clone()).await {
+                render_error(err);
+            }
+            println!()
+        }
+    }
+
     fn create_editor(config: &GlobalConfig) -> Result<Reedline> {
         let completer = ReplCompleter::new(config);
         let highlighter = ReplHighlighter::new(config);
@@ -388,7 +502,7 @@ pub async fn run_repl_command(
     match parse_command(line) {
         Some((cmd, args)) => match cmd {
             ".help" => {
-                dump_repl_help();
+                dump_repl_help(config);
             }
             ".info" => match args {
                 Some("role") => {
@@ -413,6 +527,9 @@ pub async fn run_repl_command(
                     print!("{output}");
                 }
             },
+            ".status" => {
+                dump_status(config)?;
+            }
             ".model" => match args {
                 Some(name) => {
                     config.write().set_model(name)?;
@@ -656,6 +773,20 @@ pub async fn run_repl_command(
                     println!("Usage: .set <key> <value>...")
                 }
             },
+            ".mode" => match args {
+                Some("readonly" | "read-only") => {
+                    config.write().read_only = true;
+                    println!("Read-only mode: only Safe commands run until the REPL exits.");
+                }
+                Some(_) if config.read().read_only => {
+                    bail!("Read-only mode lasts until the REPL exits")
+                }
+                Some(mode) => bail!("Unknown mode '{mode}'. Use: readonly"),
+                None => match config.read().read_only {
+                    true => println!("readonly"),
+                    false => println!("normal, `.mode readonly` allows only Safe commands"),
+                },
+            },
             ".delete" => match args {
                 Some(args) => {
                     Config::delete(config, args)?;
@@ -676,13 +807,89 @@ pub async fn run_repl_command(
                     None => bail!("No chat response to copy"),
                 };
                 set_text(&output).context("Failed to copy the last chat response")?;
-            },
+            }
+            ".paste" => {
+                let limit = config.read().capture_limit;
+                let capture = capture_clipboard(limit)?;
+                attach_capture(config, capture, args, abort_signal.clone()).await?;
+            }
+            ".pane" => {
+                let (target, text) = match args.and_then(|v| v.strip_prefix("-t ")) {
+                    Some(v) => match split_first_arg(Some(v.trim())) {
+                        Some((target, text)) => (Some(target), text),
+                        None => (None, None),
+                    },
+                    None => (None, args),
+                };
+                let limit = config.read().capture_limit;
+                let capture = capture_tmux_pane(target, limit)?;
+                attach_capture(config, capture, text, abort_signal.clone()).await?;
+            }
+            ".ps" => {
+                let (filter, text) = match args.and_then(|v| v.strip_prefix("-f ")) {
+                    Some(v) => match split_first_arg(Some(v.trim())) {
+                        Some((filter, text)) => (Some(filter), text),
+                        None => (None, None),
+                    },
+                    None => (None, args),
+                };
+                let limit = config.read().capture_limit;
+                let capture = capture_processes(filter, limit)?;
+                attach_capture(config, capture, text, abort_signal.clone()).await?;
+            }
+            ".ports" => {
+                let limit = config.read().capture_limit;
+                let capture = capture_ports(limit)?;
+                attach_capture(config, capture, args, abort_signal.clone()).await?;
+            }
             ".export" => {
                 export_session_markdown(config, args)?;
-            },
+            }
             ".backup" => {
                 handle_backup_command(config, args)?;
+            }
+            ".report" => {
+                handle_report_command(args)?;
+            }
+            ".undo" => {
+                undo_last_command(config).await?;
+            }
+            ".note" => match args {
+                Some(text) => add_note(text)?,
+                None => println!("Usage: .note <text>"),
             },
+            ".notes" => {
+                list_notes(args)?;
+            }
+            ".cmds" => {
+                pick_suggested_command(config, args).await?;
+            }
+            ".compare" => match args.and_then(|v| v.split_once(char::is_whitespace)) {
+                Some((models, text)) => {
+                    let models: Vec<String> = models.split(',').map(|v| v.to_string()).collect();
+                    compare_answers(config, text.trim(), &models, abort_signal.clone()).await?;
+                }
+                None => println!("Usage: .compare <MODEL,MODEL,...> <text>"),
+            },
+            ".progress" => {
+                let job = config.read().supervised_job.clone();
+                let Some(job) = job else {
+                    println!("No command has run under supervision yet, `.cmds` runs builds and migrations that way.");
+                    return Ok(false);
+                };
+                println!("{}", dimmed_text(&job.command));
+                println!("{}", job.monitor.lock().summary(Instant::now()));
+                let text = abortable_run_with_spinner(
+                    interpret_progress(config, &job),
+                    "Reading the output",
+                    abort_signal.clone(),
+                )
+                .await?;
+                config.read().print_markdown(&text)?;
+            }
+            ".reload" => {
+                println!("{}", reload_config(config, &[])?);
+            }
             ".exit" => match args {
                 Some("role") => {
                     config.write().exit_role()?;
@@ -711,10 +918,32 @@ pub async fn run_repl_command(
                 }
                 _ => unknown_command()?,
             },
-            _ => unknown_command()?,
+            _ => {
+                let plugin = config.read().plugins.find(&cmd[1..]).cloned();
+                match plugin {
+                    Some(plugin) => {
+                        let what = format!("'.{}'", plugin.name());
+                        if let Some(reason) = config.read().read_only_refusal(&what, None) {
+                            bail!("Execution blocked: {reason}");
+                        }
+                        let arguments = parse_plugin_args(args)?;
+                        let output = plugin.run(&arguments)?;
+                        match output.as_str() {
+                            Some(text) => println!("{text}"),
+                            None => println!("{}", serde_json::to_string_pretty(&output)?),
+                        }
+                    }
+                    None => unknown_command()?,
+                }
+            }
         },
         None => {
-            let input = Input::from_str(config, line, None);
+            let capture = config.write().pending_capture.take();
+            let line = match &capture {
+                Some(capture) => capture.prompt(line),
+                None => line.to_string(),
+            };
+            let input = Input::from_str(config, &line, None);
             ask(config, abort_signal.clone(), input, true).await?;
         }
     }
@@ -768,16 +997,49 @@ async fn ask(
     }
 }
 
+/// Ask about the capture right away when there is a question, otherwise keep it for the
+/// next prompt.
+async fn attach_capture(
+    config: &GlobalConfig,
+    capture: Capture,
+    text: Option<&str>,
+    abort_signal: AbortSignal,
+) -> Result<()> {
+    println!("✓ {}.", capture.summary());
+    match text.filter(|v| !v.is_empty()) {
+        Some(text) => {
+            let input = Input::from_str(config, &capture.prompt(text), None);
+            ask(config, abort_signal, input, true).await
+        }
+        None => {
+            println!("It will be attached to your next prompt.");
+            config.write().pending_capture = Some(capture);
+            Ok(())
+        }
+    }
+}
+
 fn unknown_command() -> Result<()> {
     bail!(r#"Unknown command. Type ".help" for additional help."#);
 }
 
-fn dump_repl_help() {
-    let head = REPL_COMMANDS
+fn dump_repl_help(config: &GlobalConfig) {
+    let mut head = REPL_COMMANDS
         .iter()
         .map(|cmd| format!("{:<24} {}", cmd.name, cmd.description))
         .collect::<Vec<String>>()
         .join("\n");
+    let plugins = config
+        .read()
+        .plugins
+        .list()
+        .iter()
+        .map(|v| format!("{:<24} {}", format!(".{}", v.name()), v.description()))
+        .collect::<Vec<String>>();
+    if !plugins.is_empty() {
+        head.push_str("\n\nPlugins:\n");
+        head.push_str(&plugins.join("\n"));
+    }
     println!(
         r###"{head}
 
@@ -787,6 +1049,24 @@ Press Ctrl+C to cancel the response, Ctrl+D to exit the REPL."###,
     );
 }
 
+fn dump_status(config: &GlobalConfig) -> Result<()> {
+    let cwd = env::current_dir()?;
+    let exec_lock = match ExecLock::holder(&cwd) {
+        Some(holder) => format!("held by {holder}"),
+        None => "free".into(),
+    };
+    println!("{:<24}{}", "working_dir", cwd.display());
+    println!("{:<24}{}", "exec_lock", exec_lock);
+    let config = config.read();
+    if !config.budget.is_empty() {
+        println!(
+            "{:<24}{} (caps: {})",
+            "budget", config.budget_usage, config.budget
+        );
+    }
+    Ok(())
+}
+
 fn parse_command(line: &str) -> Option<(&str, Option<&str>)> {
     match COMMAND_RE.captures(line) {
         Ok(Some(captures)) => {
@@ -878,6 +1158,444 @@ pub fn split_args_text(line: &str, is_win: bool) -> (Vec<String>, &str) {
     (words, text)
 }
 
+fn export_session_markdown(config: &GlobalConfig, args: Option<&str>) -> Result<()> {
+    // Fix: Create a longer-lived binding to avoid temporary value issue
+    let config_guard = config.read();
+    let session = match config_guard.session.as_ref() {
+        Some(s) => s,
+        None => {
+            println!("No active session.");
+            return Ok(());
+        }
+    };
+
+    // output filename
+    let filename = match args {
+        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
+        _ => format!("session-{}.md", session.name()),
+    };
+
+    // Initialize renderer with default theme + settings
+    let mut renderer = MarkdownRender::init(RenderOptions::default())?;
+
+    // Use the built-in session.render() to generate markdown
+    let md = session.render(&mut renderer, &None)?;
+
+    // Drop the guard before writing to file
+    drop(config_guard);
+
+    std::fs::write(&filename, md)?;
+    println!("✓ Exported session to {}", filename);
+
+    Ok(())
+}
+
+fn handle_backup_command(config: &GlobalConfig, args: Option<&str>) -> Result<()> {
+    let backup_manager = BackupManager::new()?;
+
+    let (subcommand, rest) = match args {
+        Some(a) => {
+            let parts: Vec<&str> = a.splitn(2, ' ').collect();
+            (parts.first().copied(), parts.get(1).copied())
+        }
+        None => (None, None),
+    };
+
+    if config.read().read_only && subcommand.is_some_and(|v| v != "list") {
+        bail!(
+            "'.backup {}' is not available in read-only mode",
+            subcommand.unwrap_or_default()
+        );
+    }
+
+    match subcommand {
+        Some("list") | None => {
+            // List all backups
+            let backups = backup_manager.list_backups()?;
+            if backups.is_empty() {
+                println!("No backups found.");
+                return Ok(());
+            }
+
+            let mut output = format!("\n{}\n", "=".repeat(80));
+            output.push_str("📦 Backup History\n");
+            output.push_str(&format!("{}\n", "=".repeat(80)));
+
+            for (i, backup) in backups.iter().enumerate() {
+                output.push_str(&format!(
+                    "\n[{}] ID: {}\n",
+                    i + 1,
+                    color_text(&backup.id, theme().accent.0)
+                ));
+                output.push_str(&format!("    Time: {}\n", backup.timestamp));
+                output.push_str(&format!("    Command: {}\n", backup.command));
+                output.push_str(&format!("    Files: {} backed up\n", backup.files.len()));
+                for file in &backup.files {
+                    output.push_str(&format!("      - {}\n", file.original_path.display()));
+                }
+                if let Some(snapshot) = &backup.snapshot {
+                    output.push_str(&format!("    Snapshot: {}\n", snapshot.name));
+                }
+            }
+            output.push_str(&format!("\n{}\n", "=".repeat(80)));
+            output.push_str(
+                "Usage: .backup restore <id> | .backup delete <id> | .backup cleanup [count]",
+            );
+            println!("{}", accessible_text(&output));
+        }
+
+        Some("restore") => {
+            let backup_id = rest.ok_or_else(|| anyhow!("Please specify backup ID"))?;
+            let report = backup_manager.restore_backup(backup_id)?;
+            if let Some((name, instructions)) = &report.snapshot {
+                println!(
+                    "{}",
+                    warning_text(&format!(
+                        "⚠ {name} is a filesystem snapshot, restore it yourself with:"
+                    ))
+                );
+                for line in instructions {
+                    println!("  {line}");
+                }
+                return Ok(());
+            }
+            for path in &report.restored {
+                println!("✓ Restored: {path}");
+            }
+            for path in &report.missing {
+                eprintln!(
+                    "{}",
+                    warning_text(&format!("⚠ Backup file not found: {}", path.display()))
+                );
+            }
+            println!("✓ Backup {backup_id} restored successfully");
+        }
+
+        Some("delete") => {
+            let backup_id = rest.ok_or_else(|| anyhow!("Please specify backup ID"))?;
+            backup_manager.delete_backup(backup_id)?;
+            println!("✓ Backup {backup_id} deleted");
+        }
+
+        Some("cleanup") => {
+            let keep_count = rest.and_then(|s| s.parse::<usize>().ok()).unwrap_or(50);
+            let deleted = backup_manager.cleanup_old_backups(keep_count)?;
+            if deleted > 0 {
+                println!("✓ Cleaned up {deleted} old backups");
+            }
+        }
+
+        Some(cmd) => {
+            bail!(
+                "Unknown backup subcommand: {}. Use: list, restore, delete, or cleanup",
+                cmd
+            );
+        }
+    }
+
+    Ok(())
+}
+
+fn handle_report_command(args: Option<&str>) -> Result<()> {
+    let report = SessionReport::collect()?;
+    let (format, path) = match split_first_arg(args) {
+        Some((format, path)) => (Some(format), path),
+        None => (None, None),
+    };
+    let output = match format {
+        None => {
+            println!("{}", accessible_text(&report.table()));
+            return Ok(());
+        }
+        Some("json") => serde_json::to_string_pretty(&report.to_json())?,
+        Some("markdown" | "md") => report.to_markdown(),
+        Some(format) => bail!("Unknown report format: {format}. Use: json or markdown"),
+    };
+    match path {
+        Some(path) => {
+            std::fs::write(path, output).with_context(|| format!("Failed to write '{path}'"))?;
+            println!("✓ Report saved to '{path}'");
+        }
+        None => println!("{output}"),
+    }
+    Ok(())
+}
+
+/// Attach a note to the command this session executed last, in the audit log.
+fn add_note(text: &str) -> Result<()> {
+    let entries = AuditLog::read()?;
+    let Some(entry) = last_executed(&entries, process::id()) else {
+        bail!("No command has been executed in this session yet");
+    };
+    AuditLog::append(&AuditEntry::note(entry, text))?;
+    println!("✓ Noted on: {}", entry.command);
+    Ok(())
+}
+
+fn list_notes(query: Option<&str>) -> Result<()> {
+    let entries = AuditLog::read()?;
+    let notes = search_notes(&entries, query);
+    if notes.is_empty() {
+        match query {
+            Some(query) => println!("No note matches '{query}'."),
+            None => println!("No notes yet, `.note <text>` adds one after executing a command."),
+        }
+        return Ok(());
+    }
+    for entry in notes {
+        let time = chrono::DateTime::parse_from_rfc3339(&entry.timestamp)
+            .map(|v| v.format("%Y-%m-%d %H:%M").to_string())
+            .unwrap_or_else(|_| entry.timestamp.clone());
+        println!(
+            "{} {}",
+            dimmed_text(&time),
+            entry.note.as_deref().unwrap_or_default()
+        );
+        println!("  {}", color_text(&entry.command, theme().accent.0));
+        if !entry.backups.is_empty() {
+            println!(
+                "  {}",
+                dimmed_text(&format!("backups: {}", entry.backups.join(", ")))
+            );
+        }
+    }
+    Ok(())
+}
+
+/// Offer the inverse command recorded with the last reversible execution in the working
+/// directory, for changes a file backup can't revert (moves, commits, installs, ...). It
+/// runs through the same checks, confirmation and backups as any other command.
+async fn undo_last_command(config: &GlobalConfig) -> Result<()> {
+    if config.read().remote.is_some() {
+        bail!("'.undo' is not available while executing on a remote host");
+    }
+    if config.read().read_only {
+        bail!("'.undo' is not available in read-only mode");
+    }
+    let cwd = env::current_dir()?.display().to_string();
+    let entries = AuditLog::read()?;
+    let Some(entry) = find_undoable(&entries, &cwd) else {
+        println!("Nothing to undo in '{cwd}'.");
+        return Ok(());
+    };
+    let undo = entry.undo.clone().unwrap_or_default();
+    println!("Last reversible command: {}", entry.command);
+    println!("Undo with: {}", color_text(&undo, theme().accent.0));
+    let analysis = CommandAnalysis::analyze(&undo);
+    println!("{}", analysis.display());
+    execute_checked(config, &undo, &analysis, false, Some(UNDO_REASON)).await?;
+    Ok(())
+}
+
+/// Pick one of the commands the model suggested, show its analysis, then run or copy it.
+async fn pick_suggested_command(config: &GlobalConfig, query: Option<&str>) -> Result<()> {
+    let mut commands: Vec<String> = config.read().suggested_commands.iter().cloned().collect();
+    if commands.is_empty() {
+        println!("No commands suggested in this session yet.");
+        return Ok(());
+    }
+    if let Some(query) = query {
+        commands = fuzzy_filter(commands, |v| v.as_str(), query);
+        if commands.is_empty() {
+            println!("No suggested command matches '{query}'.");
+            return Ok(());
+        }
+    }
+    let index = match commands.len() {
+        1 => 0,
+        _ => {
+            // Multi-line scripts are listed on one line
+            let labels: Vec<String> = commands
+                .iter()
+                .map(|v| v.lines().collect::<Vec<_>>().join(" ⏎ "))
+                .collect();
+            Select::new("Suggested command:", labels)
+                .with_page_size(15)
+                .raw_prompt()?
+                .index
+        }
+    };
+    act_on_command(config, commands[index].clone()).await
+}
+
+/// Show the answers of `models` to `text` side by side and offer to run or copy one of
+/// the commands they suggest, which `.cmds` lists as well.
+pub async fn compare_answers(
+    config: &GlobalConfig,
+    text: &str,
+    models: &[String],
+    abort_signal: AbortSignal,
+) -> Result<()> {
+    let answers = compare_models(config, text, models, abort_signal).await?;
+    let width = crossterm::terminal::size()
+        .map(|(v, _)| v as usize)
+        .unwrap_or(120);
+    println!("{}", render_answers(&answers, width));
+    let mut commands = vec![];
+    for answer in &answers {
+        for (command, analysis) in &answer.commands {
+            let label = command.lines().collect::<Vec<_>>().join(" ⏎ ");
+            let label = format!(
+                "{} [{}] {label}",
+                analysis.safety_level.label(),
+                answer.model_id
+            );
+            commands.push((label, command.clone()));
+        }
+    }
+    config
+        .write()
+        .add_suggested_commands(commands.iter().rev().map(|(_, v)| v.clone()).collect());
+    if commands.is_empty() || !*IS_STDOUT_TERMINAL {
+        return Ok(());
+    }
+    // The safest answer's commands first
+    if let Some(safest) = safest_answer(&answers) {
+        let model_id = &answers[safest].model_id;
+        commands.sort_by_key(|(label, _)| !label.contains(&format!("[{model_id}]")));
+    }
+    let mut labels: Vec<String> = commands.iter().map(|(label, _)| label.clone()).collect();
+    labels.push("none".to_string());
+    let index = Select::new("Pick a suggestion:", labels)
+        .with_page_size(15)
+        .raw_prompt()?
+        .index;
+    match commands.get(index) {
+        Some((_, command)) => act_on_command(config, command.clone()).await,
+        None => Ok(()),
+    }
+}
+
+async fn act_on_command(config: &GlobalConfig, command: String) -> Result<()> {
+    let mut command = command;
+    let mut analysis = CommandAnalysis::analyze(&command);
+    if !analysis.placeholders.is_empty() {
+        println!("{}", color_text(&command, theme().accent.0));
+        let Some(filled) = fill_placeholders_interactively(&command, &analysis.placeholders)?
+        else {
+            return Ok(());
+        };
+        command = filled;
+        analysis = CommandAnalysis::analyze(&command);
+    }
+    if let Some(fix) = confirm_typo_fix(&analysis)? {
+        command = fix;
+        analysis = CommandAnalysis::analyze(&command);
+    }
+    println!("{}", analysis.display());
+    match Select::new("Action:", vec!["execute", "copy", "cancel"]).prompt()? {
+        "execute" => execute_suggested_command(config, &command, &analysis, true)
+            .await
+            .map(|_| ()),
+        "copy" => {
+            set_text(&command)?;
+            println!("{}", dimmed_text("✓ Copied the command."));
+            Ok(())
+        }
+        _ => Ok(()),
+    }
+}
+
+/// Run a command after its checks and confirmation, with backups and the audit log. Builds
+/// and migrations run under supervision, in the background with `background`. Returns
+/// whether the command ran.
+pub async fn execute_suggested_command(
+    config: &GlobalConfig,
+    command: &str,
+    analysis: &CommandAnalysis,
+    background: bool,
+) -> Result<bool> {
+    execute_checked(config, command, analysis, background, None).await
+}
+
+/// [`execute_suggested_command`], recording `reason` with the run in the audit log.
+async fn execute_checked(
+    config: &GlobalConfig,
+    command: &str,
+    analysis: &CommandAnalysis,
+    background: bool,
+    reason: Option<&str>,
+) -> Result<bool> {
+    if config.read().remote.is_some() {
+        bail!("Suggested commands can't execute while executing on a remote host, copy the command instead");
+    }
+    // The same checks as the execute flow, revisions are checked again
+    let mut command = command.to_string();
+    let mut analysis = analysis.clone();
+    let mut state = GuardState::default();
+    let justification = loop {
+        if analysis.command != command {
+            analysis = CommandAnalysis::analyze(&command);
+        }
+        match check_execution(config, &SHELL, &mut command, &analysis, &mut state).await? {
+            Guard::Run(justification) => break justification,
+            Guard::Again => continue,
+            Guard::Stop => return Ok(false),
+        }
+    };
+    let Some(prepared) = prepare_execution(config, &command, &analysis).await? else {
+        return Ok(false);
+    };
+    let command = command.as_str();
+    let _exec_lock = prepared.exec_lock;
+    let mut entry = AuditEntry::new(command, AuditDecision::Approved)
+        .with_safety_level(analysis.safety_level.clone())
+        .with_undo(analysis.undo_command.clone())
+        .with_backups(prepared.backup_ids);
+    if let Some(reason) = reason.or(justification.as_deref()) {
+        entry = entry.with_reason(reason);
+    }
+    if background && is_long_running(command, &ShellKind::from_name(&SHELL.name)) {
+        let running = config.read().supervised_job.clone();
+        if let Some(running) = running.filter(|v| v.is_running()) {
+            bail!(
+                "'{}' is still running, see `.progress` before starting another one",
+                running.command
+            );
+        }
+        let job = SupervisedJob::new(command);
+        config.write().supervised_job = Some(job.clone());
+        let note = "Running in the background under supervision, `.progress` shows how far it got.";
+        println!("{}", dimmed_text(note));
+        let config = config.clone();
+        tokio::spawn(async move {
+            let _exec_lock = _exec_lock;
+            let code = match supervise(&config, &SHELL, &job, false).await {
+                Ok(code) => code,
+                Err(err) => {
+                    eprintln!("\n{}", warning_text(&format!("⚠ {err}")));
+                    return;
+                }
+            };
+            if let Err(err) = AuditLog::append(&entry.with_exit_code(code)) {
+                warn!("Failed to write the audit log: {err}");
+            }
+            let summary = job.monitor.lock().summary(Instant::now());
+            let message = format!("'{}' finished. {summary}", job.command);
+            match code {
+                0 => println!("\n{}", dimmed_text(&format!("✓ {message}"))),
+                _ => eprintln!("\n{}", warning_text(&format!("⚠ {message}"))),
+            }
+        });
+        return Ok(true);
+    }
+    let span = tracing::info_span!("execute", command);
+    let code = match is_long_running(command, &ShellKind::from_name(&SHELL.name)) {
+        true => {
+            supervise(config, &SHELL, &SupervisedJob::new(command), true)
+                .instrument(span.clone())
+                .await?
+        }
+        false => span.in_scope(|| run_command(&SHELL.cmd, &[&SHELL.arg, command], None))?,
+    };
+    span.in_scope(|| tracing::info!(code, "command exited"));
+    AuditLog::append(&entry.with_exit_code(code))?;
+    if code != 0 {
+        bail!("The command exited with {code}");
+    }
+    Ok(true)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -955,98 +1673,35 @@ mod tests {
             (vec![".\\file.txt".into(), "C:\\dir\\file.txt".into()], "")
         );
     }
-}
 
-fn export_session_markdown(config: &GlobalConfig, args: Option<&str>) -> Result<()> {
-    // Fix: Create a longer-lived binding to avoid temporary value issue
-    let config_guard = config.read();
-    let session = match config_guard.session.as_ref() {
-        Some(s) => s,
-        None => {
-            println!("No active session.");
-            return Ok(());
-        }
-    };
-
-    // output filename
-    let filename = match args {
-        Some(name) if !name.trim().is_empty() => name.trim().to_string(),
-        _ => format!("session-{}.md", session.name()),
-    };
-
-    // Initialize renderer with default theme + settings
-    let mut renderer = MarkdownRender::init(RenderOptions::default())?;
-
-    // Use the built-in session.render() to generate markdown
-    let md = session.render(&mut renderer, &None)?;
-
-    // Drop the guard before writing to file
-    drop(config_guard);
-
-    std::fs::write(&filename, md)?;
-    println!("✓ Exported session to {}", filename);
-
-    Ok(())
-}
-
-fn handle_backup_command(_config: &GlobalConfig, args: Option<&str>) -> Result<()> {
-    let backup_manager = BackupManager::new()?;
-
-    let (subcommand, rest) = match args {
-        Some(a) => {
-            let parts: Vec<&str> = a.splitn(2, ' ').collect();
-            (parts.get(0).copied(), parts.get(1).copied())
-        }
-        None => (None, None),
-    };
-
-    match subcommand {
-        Some("list") | None => {
-            // List all backups
-            let backups = backup_manager.list_backups()?;
-            if backups.is_empty() {
-                println!("No backups found.");
-                return Ok(());
-            }
-
-            println!("\n{}", "=".repeat(80));
-            println!("📦 Backup History");
-            println!("{}", "=".repeat(80));
-
-            for (i, backup) in backups.iter().enumerate() {
-                println!("\n[{}] ID: {}", i + 1, backup.id);
-                println!("    Time: {}", backup.timestamp);
-                println!("    Command: {}", backup.command);
-                println!("    Files: {} backed up", backup.files.len());
-                for file in &backup.files {
-                    println!("      - {}", file.original_path.display());
-                }
-            }
-            println!("\n{}", "=".repeat(80));
-            println!("Usage: .backup restore <id> | .backup delete <id> | .backup cleanup [count]");
-        }
-
-        Some("restore") => {
-            let backup_id = rest.ok_or_else(|| anyhow!("Please specify backup ID"))?;
-            backup_manager.restore_backup(backup_id)?;
-        }
-
-        Some("delete") => {
-            let backup_id = rest.ok_or_else(|| anyhow!("Please specify backup ID"))?;
-            backup_manager.delete_backup(backup_id)?;
-        }
+    #[tokio::test]
+    async fn test_canary_blocks_suggested_command() {
+        let dir = env::temp_dir().join(format!("aichat-canary-{}", process::id()));
+        let canary = dir.join("canary");
+        std::fs::create_dir_all(&canary).unwrap();
+        crate::config::use_temp_config_dir();
+        let config = Config {
+            canary_paths: vec![canary.display().to_string()],
+            ..Default::default()
+        };
+        let config: GlobalConfig = std::sync::Arc::new(parking_lot::RwLock::new(config));
 
-        Some("cleanup") => {
-            let keep_count = rest
-                .and_then(|s| s.parse::<usize>().ok())
-                .unwrap_or(50);
-            backup_manager.cleanup_old_backups(keep_count)?;
-        }
+        // Recipes and replays run steps this way; a prompt would fail without a terminal,
+        // so the step is refused before anything is asked
+        let command = format!("rm -rf {}", dir.display());
+        let analysis = CommandAnalysis::analyze(&command);
+        let ran = execute_suggested_command(&config, &command, &analysis, false)
+            .await
+            .unwrap();
+        assert!(!ran);
+        assert!(canary.exists());
+        let entries = AuditLog::read().unwrap();
+        assert!(entries.iter().any(|v| v.command == command
+            && v.decision == AuditDecision::Blocked
+            && v.reason
+                .as_deref()
+                .is_some_and(|v| v.contains("canary path"))));
 
-        Some(cmd) => {
-            bail!("Unknown backup subcommand: {}. Use: list, restore, delete, or cleanup", cmd);
-        }
+        std::fs::remove_dir_all(&dir).unwrap();
     }
-
-    Ok(())
-}
\ No newline at end of file
+}
@@ -33,6 +33,7 @@ use tokio_graceful::Shutdown;
 use tokio_stream::wrappers::UnboundedReceiverStream;
 
 const DEFAULT_MODEL_NAME: &str = "default";
+const CONFIG_POLL_INTERVAL: std::time::Duration = std::time::Duration::from_secs(2);
 const PLAYGROUND_HTML: &[u8] = include_bytes!("../assets/playground.html");
 const ARENA_HTML: &[u8] = include_bytes!("../assets/arena.html");
 
@@ -53,7 +54,8 @@ pub async fn run(config: GlobalConfig, addr: Option<String>) -> Result<()> {
     };
     let server = Arc::new(Server::new(&config));
     let listener = TcpListener::bind(&addr).await?;
-    let stop_server = server.run(listener).await?;
+    let stop_server = server.clone().run(listener).await?;
+    tokio::spawn(watch_config(config.clone(), server));
     println!("Chat Completions API: http://{addr}/v1/chat/completions");
     println!("Embeddings API:       http://{addr}/v1/embeddings");
     println!("Rerank API:           http://{addr}/v1/rerank");
@@ -64,14 +66,34 @@ pub async fn run(config: GlobalConfig, addr: Option<String>) -> Result<()> {
     Ok(())
 }
 
-struct Server {
+/// Reload the config when its files change, for the requests that come after.
+async fn watch_config(config: GlobalConfig, server: Arc<Server>) {
+    let mut watcher = ConfigWatcher::new(&config.read());
+    let mut interval = tokio::time::interval(CONFIG_POLL_INTERVAL);
+    loop {
+        interval.tick().await;
+        let files = watcher.changed_files(&config.read());
+        if files.is_empty() {
+            continue;
+        }
+        match reload_config(&config, &files) {
+            Ok(report) => {
+                println!("{report}");
+                server.refresh(&config);
+            }
+            Err(err) => eprintln!("Failed to reload, keeping the previous config: {err:?}"),
+        }
+    }
+}
+
+struct ServerState {
     config: Config,
     models: Vec<Value>,
     roles: Vec<Role>,
     rags: Vec<String>,
 }
 
-impl Server {
+impl ServerState {
     fn new(config: &GlobalConfig) -> Self {
         let mut config = config.read().clone();
         config.functions = Functions::default();
@@ -105,6 +127,26 @@ impl Server {
             rags: Config::list_rags(),
         }
     }
+}
+
+struct Server {
+    state: RwLock<ServerState>,
+}
+
+impl Server {
+    fn new(config: &GlobalConfig) -> Self {
+        Self {
+            state: RwLock::new(ServerState::new(config)),
+        }
+    }
+
+    fn refresh(&self, config: &GlobalConfig) {
+        *self.state.write() = ServerState::new(config);
+    }
+
+    fn config(&self) -> Config {
+        self.state.read().config.clone()
+    }
 
     async fn run(self: Arc<Self>, listener: TcpListener) -> Result<oneshot::Sender<()>> {
         let (tx, rx) = oneshot::channel();
@@ -210,7 +252,7 @@ impl Server {
     }
 
     fn list_models(&self) -> Result<AppResponse> {
-        let data = json!({ "data": self.models });
+        let data = json!({ "data": self.state.read().models });
         let res = Response::builder()
             .header("Content-Type", "application/json; charset=utf-8")
             .body(Full::new(Bytes::from(data.to_string())).boxed())?;
@@ -218,7 +260,7 @@ impl Server {
     }
 
     fn list_roles(&self) -> Result<AppResponse> {
-        let data = json!({ "data": self.roles });
+        let data = json!({ "data": self.state.read().roles });
         let res = Response::builder()
             .header("Content-Type", "application/json; charset=utf-8")
             .body(Full::new(Bytes::from(data.to_string())).boxed())?;
@@ -226,7 +268,7 @@ impl Server {
     }
 
     fn list_rags(&self) -> Result<AppResponse> {
-        let data = json!({ "data": self.rags });
+        let data = json!({ "data": self.state.read().rags });
         let res = Response::builder()
             .header("Content-Type", "application/json; charset=utf-8")
             .body(Full::new(Bytes::from(data.to_string())).boxed())?;
@@ -242,7 +284,7 @@ impl Server {
         let SearchRagReqBody { name, input } = serde_json::from_value(req_body)
             .map_err(|err| anyhow!("Invalid request body, {err}"))?;
 
-        let config = Arc::new(RwLock::new(self.config.clone()));
+        let config = Arc::new(RwLock::new(self.config()));
 
         let abort_signal = create_abort_signal();
 
@@ -282,7 +324,7 @@ impl Server {
 
         let functions = parse_tools(tools).map_err(|err| anyhow!("Invalid request body, {err}"))?;
 
-        let config = self.config.clone();
+        let config = self.config();
 
         let default_model = config.model.clone();
 
@@ -481,7 +523,7 @@ impl Server {
             model: embedding_model_id,
         } = req_body;
 
-        let config = Arc::new(RwLock::new(self.config.clone()));
+        let config = Arc::new(RwLock::new(self.config()));
 
         let embedding_model =
             Model::retrieve_model(&config.read(), &embedding_model_id, ModelType::Embedding)?;
@@ -541,7 +583,7 @@ impl Server {
 
         let top_n = top_n.unwrap_or(documents.len());
 
-        let config = Arc::new(RwLock::new(self.config.clone()));
+        let config = Arc::new(RwLock::new(self.config()));
 
         let reranker_model =
             Model::retrieve_model(&config.read(), &reranker_model_id, ModelType::Reranker)?;
@@ -882,7 +924,7 @@ fn parse_messages(message: Vec<Value>) -> Result<Vec<Message>> {
                     if tool_calls.len() == tool_values.len() {
                         let mut list = vec![];
                         for ((id, name, arguments), (value, tool_call_id)) in
-                            tool_calls.into_iter().zip(tool_values.into_iter())
+                            tool_calls.into_iter().zip(tool_values)
                         {
                             if id != tool_call_id {
                                 return Err(err());
@@ -0,0 +1,134 @@
+use super::*;
+
+use anyhow::Result;
+use chrono::Local;
+use serde_json::json;
+use std::time::Duration;
+
+/// The first canary path the command touches: named directly or, when `covers_contents`
+/// (the command deletes, moves or rewrites what it's given), inside a directory or
+/// wildcard the command operates on.
+pub fn find_canary(
+    command: &str,
+    canaries: &[String],
+    covers_contents: bool,
+    style: PathStyle,
+) -> Option<String> {
+    if canaries.is_empty() {
+        return None;
+    }
+    let cwd = env::current_dir().unwrap_or_default();
+    let normalize = |v: &str| {
+        let path = normalize_path(&resolve_home_dir(v), &cwd, style);
+        match style {
+            PathStyle::Windows => path.to_lowercase(),
+            PathStyle::Unix => path,
+        }
+    };
+    let canaries: Vec<(String, String)> =
+        canaries.iter().map(|v| (v.clone(), normalize(v))).collect();
+    for word in command_path_candidates(command, style) {
+        let target = normalize(&word);
+        let is_glob = word.contains(['*', '?']);
+        for (canary, normalized) in &canaries {
+            let hit = match (is_glob, covers_contents) {
+                (false, _) if target == *normalized => true,
+                (false, true) => is_within(normalized, &target, style),
+                (true, true) => {
+                    ancestors(normalized, style).any(|v| glob_matches(&target, v, style))
+                }
+                (true, false) => glob_matches(&target, normalized, style),
+                _ => false,
+            };
+            if hit {
+                return Some(canary.clone());
+            }
+        }
+    }
+    None
+}
+
+fn is_within(path: &str, dir: &str, style: PathStyle) -> bool {
+    match path.strip_prefix(dir) {
+        Some(rest) => {
+            rest.is_empty()
+                || dir.ends_with(separators(style))
+                || rest.starts_with(separators(style))
+        }
+        None => false,
+    }
+}
+
+/// `path` and its parent directories, down to the root.
+fn ancestors(path: &str, style: PathStyle) -> impl Iterator<Item = &str> {
+    let sep = separators(style);
+    std::iter::once(path).chain(path.rmatch_indices(sep).map(move |(i, _)| {
+        if i == 0 || path[..i].ends_with(':') {
+            &path[..=i]
+        } else {
+            &path[..i]
+        }
+    }))
+}
+
+fn separators(style: PathStyle) -> &'static [char] {
+    match style {
+        PathStyle::Unix => &['/'],
+        PathStyle::Windows => &['/', '\\'],
+    }
+}
+
+/// POST a JSON alert to the configured `canary_webhook`.
+pub async fn fire_canary_webhook(url: &str, command: &str, canary: &str) -> Result<()> {
+    ensure_network("The canary webhook", url)?;
+    let body = json!({
+        "event": "canary_tripped",
+        "command": command,
+        "canary": canary,
+        "cwd": env::current_dir().ok().map(|v| v.display().to_string()),
+        "host": sysinfo::System::host_name(),
+        "timestamp": Local::now().to_rfc3339(),
+    });
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(10))
+        .build()?;
+    client
+        .post(url)
+        .json(&body)
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_find_canary() {
+        let canaries = vec!["/srv/app/.canary".to_string()];
+        let find =
+            |command: &str, covers: bool| find_canary(command, &canaries, covers, PathStyle::Unix);
+        assert!(find("cat /srv/app/.canary", false).is_some());
+        assert!(find("rm -rf /srv/app", true).is_some());
+        assert!(find("rm -rf /srv/*", true).is_some());
+        assert!(find("rm -rf /", true).is_some());
+        assert!(find("ls /srv/app", false).is_none());
+        assert!(find("rm -rf /srv/other", true).is_none());
+        assert!(find("rm /srv/app/.c*", false).is_some());
+        assert!(find_canary("rm -rf /", &[], true, PathStyle::Unix).is_none());
+    }
+
+    #[test]
+    fn test_find_canary_windows() {
+        let canaries = vec![r"C:\Users\me\Documents\canary.docx".to_string()];
+        assert!(find_canary(
+            r"rd /s /q c:\users\me\documents",
+            &canaries,
+            true,
+            PathStyle::Windows
+        )
+        .is_some());
+    }
+}
@@ -22,6 +22,14 @@ mod internal {
         }
     }
 
+    pub fn get_text() -> anyhow::Result<String> {
+        let mut clipboard = CLIPBOARD.lock().unwrap();
+        match clipboard.as_mut() {
+            Some(clipboard) => Ok(clipboard.get_text()?),
+            None => Err(anyhow::anyhow!("No clipboard available")),
+        }
+    }
+
     /// Attempts to set text to clipboard with OSC52 escape sequence
     /// Works in many modern terminals, including over SSH.
     fn set_text_osc52(text: &str) -> anyhow::Result<()> {
@@ -42,8 +50,16 @@ mod internal {
     pub fn set_text(_text: &str) -> anyhow::Result<()> {
         Err(anyhow::anyhow!("No clipboard available"))
     }
+
+    pub fn get_text() -> anyhow::Result<String> {
+        Err(anyhow::anyhow!("No clipboard available"))
+    }
 }
 
 pub fn set_text(text: &str) -> anyhow::Result<()> {
     internal::set_text(text).context("Failed to copy")
 }
+
+pub fn get_text() -> anyhow::Result<String> {
+    internal::get_text().context("Failed to paste")
+}
@@ -0,0 +1,182 @@
+use super::*;
+
+use crate::config::Config;
+
+use anyhow::bail;
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs::{self, File, OpenOptions, TryLockError},
+    io::{Read, Seek, SeekFrom, Write},
+    path::Path,
+    time::{Duration, Instant},
+};
+
+const LOCKS_DIR_NAME: &str = "locks";
+const POLL_INTERVAL: Duration = Duration::from_millis(250);
+
+/// Who holds the execution lock of a directory, written into the lock file.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LockHolder {
+    pub pid: u32,
+    pub command: String,
+    pub cwd: String,
+    pub since: String,
+}
+
+impl std::fmt::Display for LockHolder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(
+            f,
+            "pid {} since {} running `{}`",
+            self.pid, self.since, self.command
+        )
+    }
+}
+
+/// An advisory lock keyed by working directory, held while aichat runs a file-modifying
+/// command there so two instances (or an agent loop) don't modify the same tree at once.
+/// Released when dropped, or by the OS when the process dies.
+#[derive(Debug)]
+pub struct ExecLock {
+    file: File,
+}
+
+impl ExecLock {
+    /// Take the lock of `cwd`. With a `wait` of zero it fails as soon as another process
+    /// holds it, otherwise it queues for up to `wait` without blocking the runtime.
+    pub async fn acquire(cwd: &Path, command: &str, wait: Duration) -> Result<Self> {
+        Self::acquire_in(&Self::locks_dir(), cwd, command, wait).await
+    }
+
+    /// The current holder of the lock of `cwd`, if any.
+    pub fn holder(cwd: &Path) -> Option<LockHolder> {
+        Self::holder_in(&Self::locks_dir(), cwd)
+    }
+
+    fn locks_dir() -> PathBuf {
+        Config::local_path(LOCKS_DIR_NAME)
+    }
+
+    fn lock_path(dir: &Path, cwd: &Path) -> PathBuf {
+        let cwd = cwd.canonicalize().unwrap_or_else(|_| cwd.to_path_buf());
+        let key = sha256(&cwd.to_string_lossy());
+        dir.join(format!("{}.lock", &key[..16]))
+    }
+
+    async fn acquire_in(dir: &Path, cwd: &Path, command: &str, wait: Duration) -> Result<Self> {
+        fs::create_dir_all(dir).with_context(|| format!("Failed to create '{}'", dir.display()))?;
+        let path = Self::lock_path(dir, cwd);
+        let mut file = OpenOptions::new()
+            .read(true)
+            .write(true)
+            .create(true)
+            .truncate(false)
+            .open(&path)
+            .with_context(|| format!("Failed to open '{}'", path.display()))?;
+        let start = Instant::now();
+        let mut notified = false;
+        loop {
+            match file.try_lock() {
+                Ok(()) => break,
+                Err(TryLockError::WouldBlock) => {
+                    let holder = read_holder(&mut file)
+                        .map(|v| format!(" ({v})"))
+                        .unwrap_or_default();
+                    if start.elapsed() >= wait {
+                        bail!(
+                            "Another aichat instance is executing in '{}'{holder}, try again once it finishes",
+                            cwd.display()
+                        );
+                    }
+                    if !notified {
+                        eprintln!(
+                            "{}",
+                            dimmed_text(&format!(
+                                "Waiting for another aichat instance in '{}'{holder}...",
+                                cwd.display()
+                            ))
+                        );
+                        notified = true;
+                    }
+                    tokio::time::sleep(POLL_INTERVAL).await;
+                }
+                Err(TryLockError::Error(err)) => {
+                    return Err(err).with_context(|| format!("Failed to lock '{}'", path.display()))
+                }
+            }
+        }
+        let holder = LockHolder {
+            pid: process::id(),
+            command: command.trim().to_string(),
+            cwd: cwd.display().to_string(),
+            since: Local::now().format("%Y-%m-%d %H:%M:%S").to_string(),
+        };
+        file.set_len(0)?;
+        file.seek(SeekFrom::Start(0))?;
+        file.write_all(serde_json::to_string(&holder)?.as_bytes())?;
+        file.flush()?;
+        Ok(Self { file })
+    }
+
+    fn holder_in(dir: &Path, cwd: &Path) -> Option<LockHolder> {
+        let mut file = File::open(Self::lock_path(dir, cwd)).ok()?;
+        match file.try_lock_shared() {
+            Ok(()) => {
+                let _ = file.unlock();
+                None
+            }
+            Err(_) => read_holder(&mut file),
+        }
+    }
+}
+
+impl Drop for ExecLock {
+    fn drop(&mut self) {
+        let _ = self.file.set_len(0);
+        let _ = self.file.unlock();
+    }
+}
+
+fn read_holder(file: &mut File) -> Option<LockHolder> {
+    let mut content = String::new();
+    file.seek(SeekFrom::Start(0)).ok()?;
+    file.read_to_string(&mut content).ok()?;
+    serde_json::from_str(&content).ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_exec_lock() {
+        let dir = env::temp_dir().join(format!("aichat-locks-{}", process::id()));
+        let cwd = env::temp_dir();
+        assert!(ExecLock::holder_in(&dir, &cwd).is_none());
+
+        let lock = ExecLock::acquire_in(&dir, &cwd, "rm -rf build", Duration::ZERO)
+            .await
+            .unwrap();
+        let holder = ExecLock::holder_in(&dir, &cwd).unwrap();
+        assert_eq!(holder.pid, process::id());
+        assert_eq!(holder.command, "rm -rf build");
+
+        let err = ExecLock::acquire_in(&dir, &cwd, "make", Duration::ZERO)
+            .await
+            .unwrap_err();
+        assert!(err.to_string().contains("rm -rf build"));
+        assert!(
+            ExecLock::acquire_in(&dir, Path::new("/"), "make", Duration::ZERO)
+                .await
+                .is_ok()
+        );
+
+        drop(lock);
+        assert!(ExecLock::holder_in(&dir, &cwd).is_none());
+        assert!(ExecLock::acquire_in(&dir, &cwd, "make", Duration::ZERO)
+            .await
+            .is_ok());
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
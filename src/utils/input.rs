@@ -1,6 +1,9 @@
+use super::*;
+
 use anyhow::Result;
 use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyModifiers};
 use crossterm::terminal::{disable_raw_mode, enable_raw_mode};
+use inquire::{validator::Validation, Confirm, Text};
 use std::io::{stdout, Write};
 
 /// Reads a single character from stdin without requiring Enter
@@ -20,11 +23,8 @@ pub fn read_single_key(valid_chars: &[char], default: char, prompt: &str) -> Res
                 KeyCode::Char('c') if modifiers.contains(KeyModifiers::CONTROL) => {
                     break Err(anyhow::anyhow!("Interrupted"));
                 }
-                KeyCode::Char(c) => {
-                    if valid_chars.contains(&c) {
-                        break Ok(c);
-                    }
-                    // Invalid character, continue loop
+                KeyCode::Char(c) if valid_chars.contains(&c) => {
+                    break Ok(c);
                 }
                 KeyCode::Enter => {
                     break Ok(default);
@@ -45,3 +45,81 @@ pub fn read_single_key(valid_chars: &[char], default: char, prompt: &str) -> Res
 
     result
 }
+
+/// Walk through a Dangerous or Critical command before it runs: offer to list the files it
+/// changes, have each warning acknowledged on its own and, for deletes, the target typed.
+/// Returns why the user backed out, if they did.
+pub fn acknowledge_warnings(
+    analysis: &CommandAnalysis,
+    shell: &ShellKind,
+) -> Result<Option<String>> {
+    let preview = ImpactPreview::simulate(&analysis.command, PathStyle::current(), shell, &RealFs);
+    if !preview.changes.is_empty() || !preview.unpredicted.is_empty() {
+        let ans = Confirm::new("Show the affected files first?")
+            .with_default(true)
+            .prompt()?;
+        if ans {
+            println!("{}", preview.display());
+        }
+    }
+    let hazards = analysis.hazards();
+    for (i, hazard) in hazards.iter().enumerate() {
+        println!("{}", warning_text(hazard));
+        let ans = Confirm::new(&format!("Acknowledge warning {}/{}?", i + 1, hazards.len()))
+            .with_default(false)
+            .prompt()?;
+        if !ans {
+            let hazard = hazard.trim_start_matches(|c: char| !c.is_alphanumeric());
+            return Ok(Some(format!("warning not acknowledged: {hazard}")));
+        }
+    }
+    if let Some(expected) = analysis.delete_confirmation() {
+        let prompt = match analysis.affected_files.len() {
+            1 => format!("Type '{expected}' to delete it:"),
+            n => format!("Type the number of files ({n}) to delete them:"),
+        };
+        if Text::new(&prompt).prompt()?.trim() != expected {
+            return Ok(Some("delete confirmation did not match".to_string()));
+        }
+    }
+    Ok(None)
+}
+
+/// Ask for the value of each placeholder, validated against its kind, and return the
+/// command with the values filled in. `None` when the user gives up (Esc).
+/// Offer the command a typo was likely meant as, like `crontab -e` for `crontab -r`.
+/// `None` runs the command as written.
+pub fn confirm_typo_fix(analysis: &CommandAnalysis) -> Result<Option<String>> {
+    let Some(fix) = &analysis.typo_fix else {
+        return Ok(None);
+    };
+    let ans = Confirm::new(&format!("Did you mean `{fix}`?"))
+        .with_help_message("`crontab -r` removes every cron job at once, `-e` edits them")
+        .with_default(true)
+        .prompt()?;
+    Ok(ans.then(|| fix.clone()))
+}
+
+pub fn fill_placeholders_interactively(
+    command: &str,
+    placeholders: &[Placeholder],
+) -> Result<Option<String>> {
+    let mut values = vec![];
+    for placeholder in placeholders {
+        let check = placeholder.clone();
+        let value = Text::new(&format!("{}:", placeholder.text))
+            .with_help_message(placeholder.kind.hint())
+            .with_validator(move |input: &str| {
+                Ok(match check.validate(input) {
+                    Some(reason) => Validation::Invalid(reason.into()),
+                    None => Validation::Valid,
+                })
+            })
+            .prompt_skippable()?;
+        let Some(value) = value else {
+            return Ok(None);
+        };
+        values.push((placeholder.clone(), value));
+    }
+    Ok(Some(fill_placeholders(command, &values)))
+}
@@ -0,0 +1,98 @@
+//! API keys kept in the OS credential store instead of a file, through the `security`
+//! CLI on macOS and libsecret's `secret-tool` on Linux. The env file refers to them as
+//! `OPENAI_API_KEY=keychain:OPENAI_API_KEY`.
+
+use anyhow::{anyhow, bail, Context, Result};
+use std::{
+    io::Write,
+    process::{Command, Stdio},
+};
+
+const KEYCHAIN_SERVICE: &str = "aichat";
+pub const KEYCHAIN_PREFIX: &str = "keychain:";
+
+/// The credential store tool of this OS, if it is installed.
+fn keychain_tool() -> Option<&'static str> {
+    let tool = match std::env::consts::OS {
+        "macos" => "security",
+        "linux" | "freebsd" => "secret-tool",
+        _ => return None,
+    };
+    which::which(tool).ok().map(|_| tool)
+}
+
+pub fn keychain_available() -> bool {
+    keychain_tool().is_some()
+}
+
+pub fn keychain_store(account: &str, secret: &str) -> Result<()> {
+    let Some(tool) = keychain_tool() else {
+        bail!("No keychain available on this system");
+    };
+    let mut command = Command::new(tool);
+    match tool {
+        "security" => command.args([
+            "add-generic-password",
+            "-U",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account,
+            "-w",
+            secret,
+        ]),
+        _ => command.args([
+            "store",
+            &format!("--label={KEYCHAIN_SERVICE} {account}"),
+            "service",
+            KEYCHAIN_SERVICE,
+            "account",
+            account,
+        ]),
+    };
+    let mut child = command
+        .stdin(Stdio::piped())
+        .stdout(Stdio::null())
+        .spawn()
+        .with_context(|| format!("Failed to run {tool}"))?;
+    // secret-tool reads the secret from stdin
+    if let Some(mut stdin) = child.stdin.take() {
+        if tool == "secret-tool" {
+            stdin.write_all(secret.as_bytes())?;
+        }
+    }
+    let status = child.wait()?;
+    if !status.success() {
+        bail!("Failed to store '{account}' in the keychain");
+    }
+    Ok(())
+}
+
+pub fn keychain_lookup(account: &str) -> Result<String> {
+    let Some(tool) = keychain_tool() else {
+        bail!("No keychain available on this system");
+    };
+    let mut command = Command::new(tool);
+    match tool {
+        "security" => command.args([
+            "find-generic-password",
+            "-s",
+            KEYCHAIN_SERVICE,
+            "-a",
+            account,
+            "-w",
+        ]),
+        _ => command.args(["lookup", "service", KEYCHAIN_SERVICE, "account", account]),
+    };
+    let output = command
+        .stderr(Stdio::null())
+        .output()
+        .with_context(|| format!("Failed to run {tool}"))?;
+    let secret = String::from_utf8_lossy(&output.stdout)
+        .trim_end()
+        .to_string();
+    if !output.status.success() || secret.is_empty() {
+        return Err(anyhow!("No '{account}' in the keychain"));
+    }
+    Ok(secret)
+}
@@ -1,39 +1,42 @@
 mod abort_signal;
-mod backup;
+mod canary;
+mod capture;
 mod clipboard;
-mod command;
-mod command_analyzer;
-mod command_tutor;
 mod crypto;
+mod exec_lock;
 mod html_to_md;
 mod input;
+mod keychain;
 mod loader;
-mod path;
+mod preflight;
 mod render_prompt;
 mod request;
+mod self_update;
 mod spinner;
 mod variables;
 
+pub use aichat::engine::*;
+
 pub use self::abort_signal::*;
-pub use self::backup::*;
-pub use self::clipboard::set_text;
-pub use self::command::*;
-pub use self::command_analyzer::*;
-pub use self::command_tutor::*;
+pub use self::canary::*;
+pub use self::capture::*;
+pub use self::clipboard::{get_text, set_text};
 pub use self::crypto::*;
+pub use self::exec_lock::*;
 pub use self::html_to_md::*;
 pub use self::input::*;
+pub use self::keychain::*;
 pub use self::loader::*;
-pub use self::path::*;
+pub use self::preflight::*;
 pub use self::render_prompt::render_prompt;
 pub use self::request::*;
+pub use self::self_update::self_update;
 pub use self::spinner::*;
 pub use self::variables::*;
 
 use anyhow::{Context, Result};
 use fancy_regex::Regex;
 use fuzzy_matcher::{skim::SkimMatcherV2, FuzzyMatcher};
-use is_terminal::IsTerminal;
 use std::borrow::Cow;
 use std::sync::LazyLock;
 use std::{env, path::PathBuf, process};
@@ -41,41 +44,18 @@ use unicode_segmentation::UnicodeSegmentation;
 
 pub static CODE_BLOCK_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?ms)```\w*(.*)```").unwrap());
+static SHELL_BLOCK_RE: LazyLock<Regex> = LazyLock::new(|| {
+    Regex::new(
+        r"(?ms)^\s*```(?:sh|bash|zsh|shell|console|fish|nu|powershell|pwsh|ps1|cmd|bat)[ \t]*\n(.*?)^\s*```",
+    )
+    .unwrap()
+});
 pub static THINK_TAG_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"(?s)^\s*<think>.*?</think>(\s*|$)").unwrap());
-pub static IS_STDOUT_TERMINAL: LazyLock<bool> = LazyLock::new(|| std::io::stdout().is_terminal());
-pub static NO_COLOR: LazyLock<bool> = LazyLock::new(|| {
-    env::var("NO_COLOR")
-        .ok()
-        .and_then(|v| parse_bool(&v))
-        .unwrap_or_default()
-        || !*IS_STDOUT_TERMINAL
-});
-
-pub fn now() -> String {
-    chrono::Local::now().to_rfc3339_opts(chrono::SecondsFormat::Secs, false)
-}
-
-pub fn now_timestamp() -> i64 {
-    chrono::Local::now().timestamp()
-}
-
-pub fn get_env_name(key: &str) -> String {
-    format!("{}_{key}", env!("CARGO_CRATE_NAME"),).to_ascii_uppercase()
-}
-
 pub fn normalize_env_name(value: &str) -> String {
     value.replace('-', "_").to_ascii_uppercase()
 }
 
-pub fn parse_bool(value: &str) -> Option<bool> {
-    match value {
-        "1" | "true" => Some(true),
-        "0" | "false" => Some(false),
-        _ => None,
-    }
-}
-
 pub fn estimate_token_length(text: &str) -> usize {
     let words: Vec<&str> = text.unicode_words().collect();
     let mut output: f32 = 0.0;
@@ -106,6 +86,24 @@ pub fn extract_code_block(text: &str) -> &str {
         .unwrap_or(text)
 }
 
+/// The contents of the shell code blocks in a response, `$ ` prompts removed.
+pub fn extract_shell_commands(text: &str) -> Vec<String> {
+    SHELL_BLOCK_RE
+        .captures_iter(text)
+        .flatten()
+        .filter_map(|v| {
+            let block = v.get(1)?.as_str();
+            let command = block
+                .lines()
+                .map(|v| v.strip_prefix("$ ").unwrap_or(v))
+                .collect::<Vec<_>>()
+                .join("\n");
+            let command = command.trim();
+            (!command.is_empty()).then(|| command.to_string())
+        })
+        .collect()
+}
+
 pub fn convert_option_string(value: &str) -> Option<String> {
     if value.is_empty() {
         None
@@ -126,7 +124,7 @@ where
             Some((v, score))
         })
         .collect();
-    list.sort_unstable_by(|a, b| b.1.cmp(&a.1));
+    list.sort_unstable_by_key(|v| std::cmp::Reverse(v.1));
     list.into_iter().map(|(v, _)| v).collect()
 }
 
@@ -157,31 +155,6 @@ pub fn indent_text<T: ToString>(s: T, size: usize) -> String {
         .join("\n")
 }
 
-pub fn error_text(input: &str) -> String {
-    color_text(input, nu_ansi_term::Color::Red)
-}
-
-pub fn warning_text(input: &str) -> String {
-    color_text(input, nu_ansi_term::Color::Yellow)
-}
-
-pub fn color_text(input: &str, color: nu_ansi_term::Color) -> String {
-    if *NO_COLOR {
-        return input.to_string();
-    }
-    nu_ansi_term::Style::new()
-        .fg(color)
-        .paint(input)
-        .to_string()
-}
-
-pub fn dimmed_text(input: &str) -> String {
-    if *NO_COLOR {
-        return input.to_string();
-    }
-    nu_ansi_term::Style::new().dimmed().paint(input).to_string()
-}
-
 pub fn multiline_text(input: &str) -> String {
     input
         .split('\n')
@@ -197,15 +170,6 @@ pub fn multiline_text(input: &str) -> String {
         .join("\n")
 }
 
-pub fn temp_file(prefix: &str, suffix: &str) -> PathBuf {
-    env::temp_dir().join(format!(
-        "{}-{}{prefix}{}{suffix}",
-        env!("CARGO_CRATE_NAME").to_lowercase(),
-        process::id(),
-        uuid::Uuid::new_v4()
-    ))
-}
-
 pub fn is_url(path: &str) -> bool {
     path.starts_with("http://") || path.starts_with("https://")
 }
@@ -231,6 +195,28 @@ pub fn decode_bin<T: serde::de::DeserializeOwned>(data: &[u8]) -> Result<T> {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_extract_shell_commands() {
+        let text = r#"Find them first:
+```bash
+$ find . -name '*.log' -mtime +14
+```
+Then delete:
+```sh
+find . -name '*.log' -mtime +14 -delete
+```
+```rust
+fn main() {}
+```"#;
+        assert_eq!(
+            extract_shell_commands(text),
+            [
+                "find . -name '*.log' -mtime +14",
+                "find . -name '*.log' -mtime +14 -delete"
+            ]
+        );
+    }
+
     #[test]
     #[cfg(not(target_os = "windows"))]
     fn test_safe_join_path() {
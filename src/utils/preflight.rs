@@ -0,0 +1,37 @@
+use super::*;
+
+/// The filesystem `command` would fill past `threshold` percent or not fit on, with the sizes
+/// of downloads asked from their servers. A threshold of 0 turns the check off.
+pub async fn disk_space_preflight(command: &str, threshold: u8) -> Option<SpaceVerdict> {
+    if threshold == 0 {
+        return None;
+    }
+    let shell = ShellKind::from_name(&SHELL.name);
+    let mut demands = space_demands(command, PathStyle::current(), &shell, &RealFs);
+    if demands.is_empty() {
+        return None;
+    }
+    for demand in demands.iter_mut() {
+        if let (None, Some(url)) = (demand.bytes, &demand.url) {
+            demand.bytes = fetch_content_length(url).await;
+        }
+    }
+    check_disk_space(&demands, &detect_mounts(), threshold)
+}
+
+/// The background hint for downloads whose size the analysis couldn't know, once their
+/// servers have been asked. `None` when the analysis already said it or it's quick.
+pub async fn download_duration_warning(analysis: &CommandAnalysis) -> Option<String> {
+    if total_duration(&analysis.durations).is_some()
+        || duration_warning(&analysis.durations).is_some()
+    {
+        return None;
+    }
+    let mut estimates = analysis.durations.clone();
+    for estimate in estimates.iter_mut() {
+        if let (None, Some(url)) = (estimate.bytes, &estimate.url) {
+            estimate.bytes = fetch_content_length(url).await;
+        }
+    }
+    duration_warning(&estimates)
+}
@@ -3,7 +3,7 @@ use super::*;
 use anyhow::{anyhow, bail, Context, Result};
 use fancy_regex::Regex;
 use futures_util::{stream, StreamExt};
-use http::header::CONTENT_TYPE;
+use http::header::{CONTENT_LENGTH, CONTENT_TYPE};
 use reqwest::Url;
 use scraper::{Html, Selector};
 use serde::Deserialize;
@@ -11,7 +11,11 @@ use serde_json::Value;
 use std::sync::LazyLock;
 use std::{
     collections::{HashMap, HashSet},
-    sync::Arc,
+    net::IpAddr,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc,
+    },
     time::Duration,
 };
 use tokio::io::AsyncWriteExt;
@@ -27,6 +31,8 @@ const MAX_CRAWLS: usize = 5;
 const BREAK_ON_ERROR: bool = false;
 const USER_AGENT: &str = "curl/8.6.0";
 
+const TLDR_PAGES_URL: &str = "https://raw.githubusercontent.com/tldr-pages/tldr/main/pages";
+
 static CLIENT: LazyLock<Result<reqwest::Client>> = LazyLock::new(|| {
     let builder = reqwest::ClientBuilder::new().timeout(Duration::from_secs(16));
     let client = builder.build()?;
@@ -53,11 +59,47 @@ static PRESET: LazyLock<Vec<(Regex, CrawlOptions)>> = LazyLock::new(|| {
     ]
 });
 
+static OFFLINE: AtomicBool = AtomicBool::new(false);
+
 static EXTENSION_RE: LazyLock<Regex> = LazyLock::new(|| Regex::new(r"\.[^.]+$").unwrap());
 static GITHUB_REPO_RE: LazyLock<Regex> =
     LazyLock::new(|| Regex::new(r"^https://github\.com/([^/]+)/([^/]+)/tree/([^/]+)").unwrap());
 
+/// Turn off every network call but the ones to this machine, for `--offline`.
+pub fn set_offline(value: bool) {
+    OFFLINE.store(value, Ordering::Relaxed);
+}
+
+pub fn is_offline() -> bool {
+    OFFLINE.load(Ordering::Relaxed)
+}
+
+/// Fails with a "requires network access" error under `--offline`, unless `url` is served
+/// on this machine.
+pub fn ensure_network(what: &str, url: &str) -> Result<()> {
+    if is_offline() && !is_local_url(url) {
+        bail!("{what} requires network access, which --offline turns off");
+    }
+    Ok(())
+}
+
+/// Whether `url` points at this machine: `localhost` or a loopback address.
+pub fn is_local_url(url: &str) -> bool {
+    let Ok(url) = Url::parse(url) else {
+        return false;
+    };
+    let Some(host) = url.host_str() else {
+        return url.scheme() == "file";
+    };
+    let host = host.trim_start_matches('[').trim_end_matches(']');
+    match host.parse::<IpAddr>() {
+        Ok(ip) => ip.is_loopback(),
+        Err(_) => host.eq_ignore_ascii_case("localhost") || host.ends_with(".localhost"),
+    }
+}
+
 pub async fn fetch(url: &str) -> Result<String> {
+    ensure_network(&format!("Fetching {url}"), url)?;
     let client = match *CLIENT {
         Ok(ref client) => client,
         Err(ref err) => bail!("{err}"),
@@ -67,11 +109,83 @@ pub async fn fetch(url: &str) -> Result<String> {
     Ok(output)
 }
 
+/// The size of what `url` serves, from the Content-Length of a HEAD request.
+pub async fn fetch_content_length(url: &str) -> Option<u64> {
+    if is_offline() && !is_local_url(url) {
+        return None;
+    }
+    let client = CLIENT.as_ref().ok()?;
+    let res = client
+        .head(url)
+        .timeout(Duration::from_secs(5))
+        .send()
+        .await
+        .ok()?;
+    if !res.status().is_success() {
+        return None;
+    }
+    res.headers()
+        .get(CONTENT_LENGTH)?
+        .to_str()
+        .ok()?
+        .parse()
+        .ok()
+}
+
+/// Cache the tldr pages of the commands in `command` the tutor has no description of, for
+/// [`CommandTutorial`]. A command tldr-pages doesn't have is cached as an empty page so it
+/// isn't asked for again; nothing is fetched under `--offline` or without a cache dir.
+pub async fn fetch_tldr_pages(command: &str) -> Result<()> {
+    let Some(dir) = tldr_dir() else {
+        return Ok(());
+    };
+    if is_offline() {
+        return Ok(());
+    }
+    let client = match *CLIENT {
+        Ok(ref client) => client,
+        Err(ref err) => bail!("{err}"),
+    };
+    for name in CommandTutorial::unknown_commands(command) {
+        if cached_tldr_page(&dir, &name).is_some() {
+            continue;
+        }
+        let mut page = None;
+        for platform in tldr_platforms() {
+            let res = client
+                .get(format!("{TLDR_PAGES_URL}/{platform}/{name}.md"))
+                .timeout(Duration::from_secs(5))
+                .send()
+                .await?;
+            if res.status() == reqwest::StatusCode::NOT_FOUND {
+                continue;
+            }
+            page = Some((platform, res.error_for_status()?.text().await?));
+            break;
+        }
+        let (platform, text) = page.unwrap_or(("common", String::new()));
+        let path = dir.join(platform).join(format!("{name}.md"));
+        std::fs::create_dir_all(dir.join(platform))
+            .and_then(|_| std::fs::write(&path, text))
+            .with_context(|| format!("Failed to cache tldr page at {}", path.display()))?;
+    }
+    Ok(())
+}
+
+/// Download the script of a `curl ... | sh` command to a temp file for review.
+pub async fn download_script(pipe: &PipeToShell) -> Result<PathBuf> {
+    let Some(url) = &pipe.url else {
+        bail!("No URL found in the command");
+    };
+    pipe.save(&fetch(url).await?)
+}
+
 pub async fn fetch_with_loaders(
     loaders: &HashMap<String, String>,
     path: &str,
     allow_media: bool,
 ) -> Result<(String, String)> {
+    ensure_network(&format!("Loading {path}"), path)?;
     if let Some(loader_command) = loaders.get(URL_LOADER) {
         let contents = run_loader_command(path, URL_LOADER, loader_command)?;
         return Ok((contents, DEFAULT_EXTENSION.into()));
@@ -167,6 +281,7 @@ pub async fn fetch_with_loaders(
 }
 
 pub async fn fetch_models(api_base: &str, api_key: Option<&str>) -> Result<Vec<String>> {
+    ensure_network("Fetching models", api_base)?;
     let client = match *CLIENT {
         Ok(ref client) => client,
         Err(ref err) => bail!("{err}"),
@@ -211,6 +326,7 @@ impl CrawlOptions {
 }
 
 pub async fn crawl_website(start_url: &str, options: CrawlOptions) -> Result<Vec<Page>> {
+    ensure_network(&format!("Crawling {start_url}"), start_url)?;
     let start_url = Url::parse(start_url)?;
     let mut paths = vec![start_url.path().to_string()];
     let normalized_start_url = normalize_start_url(&start_url);
@@ -462,3 +578,19 @@ fn match_link(path: &str, link: &str) -> bool {
                 .trim_end_matches("/index.html")
                 .trim_end_matches("/index.htm")
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_local_url() {
+        assert!(is_local_url("http://localhost:11434/v1"));
+        assert!(is_local_url("http://127.0.0.1:8080/v1/chat/completions"));
+        assert!(is_local_url("http://[::1]:8000"));
+        assert!(is_local_url("http://ollama.localhost"));
+        assert!(!is_local_url("https://api.openai.com/v1"));
+        assert!(!is_local_url("http://192.168.1.20:11434"));
+        assert!(!is_local_url("not a url"));
+    }
+}
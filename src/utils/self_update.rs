@@ -0,0 +1,274 @@
+//! `aichat --self-update`: install the latest release of this project over the running
+//! binary. The archive must match the SHA-256 published next to it in the release and carry
+//! an ed25519 signature from the release key, built in or set in `self_update_public_key`.
+
+use super::*;
+
+use anyhow::{anyhow, bail, Context, Result};
+use serde::Deserialize;
+use sha2::Digest;
+use std::{
+    fs,
+    io::{Cursor, Read},
+    path::Path,
+    time::Duration,
+};
+
+/// The GitHub repository releases are installed from, not the upstream `aichat` one whose
+/// builds lack the safety features.
+const RELEASE_REPOSITORY: &str = "leeyoyo49/aichat";
+
+/// The key releases are signed with, set by the release build.
+const RELEASE_PUBLIC_KEY: Option<&str> = option_env!("AICHAT_RELEASE_PUBLIC_KEY");
+
+#[derive(Debug, Deserialize)]
+struct Release {
+    tag_name: String,
+    assets: Vec<ReleaseAsset>,
+}
+
+#[derive(Debug, Deserialize)]
+struct ReleaseAsset {
+    name: String,
+    browser_download_url: String,
+}
+
+pub async fn self_update(public_key: Option<&str>) -> Result<()> {
+    let current = env!("CARGO_PKG_VERSION");
+    let Some(public_key) = public_key.or(RELEASE_PUBLIC_KEY) else {
+        bail!("This build has no release signing key, set `self_update_public_key` to the key releases are signed with or update from source");
+    };
+    ensure_network("Self-update", "https://api.github.com")?;
+    let target = release_target(&EnvProfile::detect().os)?;
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(300))
+        .user_agent(format!("{}/{current}", env!("CARGO_CRATE_NAME")))
+        .build()?;
+
+    let release: Release = client
+        .get(format!(
+            "https://api.github.com/repos/{RELEASE_REPOSITORY}/releases/latest"
+        ))
+        .send()
+        .await
+        .and_then(|v| v.error_for_status())
+        .with_context(|| "Failed to check for releases")?
+        .json()
+        .await?;
+    let latest = release.tag_name.trim_start_matches('v');
+    if !is_newer_version(latest, current) {
+        println!("✓ aichat v{current} is the latest release.");
+        return Ok(());
+    }
+
+    let asset = release
+        .assets
+        .iter()
+        .find(|v| {
+            v.name.contains(&target) && (v.name.ends_with(".tar.gz") || v.name.ends_with(".zip"))
+        })
+        .ok_or_else(|| anyhow!("Release v{latest} has no build for {target}"))?;
+    let checksums = release.assets.iter().find(|v| {
+        v.name == format!("{}.sha256", asset.name)
+            || matches!(
+                v.name.to_lowercase().as_str(),
+                "sha256sums" | "sha256sums.txt" | "checksums.txt"
+            )
+    });
+    let Some(checksums) = checksums else {
+        bail!(
+            "Release v{latest} publishes no checksum for '{}', refusing to install an unverified binary",
+            asset.name
+        );
+    };
+
+    println!("Downloading {}...", asset.name);
+    let download = |url: String| {
+        let client = client.clone();
+        async move {
+            let res = client.get(url).send().await?.error_for_status()?;
+            anyhow::Ok(res.bytes().await?)
+        }
+    };
+    let archive = download(asset.browser_download_url.clone()).await?;
+    let sums = download(checksums.browser_download_url.clone()).await?;
+    let expected = find_checksum(&String::from_utf8_lossy(&sums), &asset.name)
+        .ok_or_else(|| anyhow!("No checksum for '{}' in '{}'", asset.name, checksums.name))?;
+    let actual = hex_encode(&sha2::Sha256::digest(&archive));
+    if !actual.eq_ignore_ascii_case(&expected) {
+        bail!(
+            "Checksum mismatch for '{}', the download is corrupt or was tampered with",
+            asset.name
+        );
+    }
+    println!("✓ Checksum verified.");
+
+    let signature = release
+        .assets
+        .iter()
+        .find(|v| v.name == format!("{}.sig", asset.name))
+        .ok_or_else(|| anyhow!("Release v{latest} has no signature for '{}'", asset.name))?;
+    let signature = download(signature.browser_download_url.clone()).await?;
+    verify_signature(public_key, &archive, &signature)?;
+    println!("✓ Signature verified.");
+
+    let binary_name = format!(
+        "{}{}",
+        env!("CARGO_CRATE_NAME"),
+        std::env::consts::EXE_SUFFIX
+    );
+    let binary = extract_binary(&asset.name, &archive, &binary_name)?;
+    let exe = env::current_exe()?;
+    replace_binary(&binary, &exe).with_context(|| {
+        format!(
+            "Failed to replace '{}', rerun with the permissions to write there or update through the package manager that installed it",
+            exe.display()
+        )
+    })?;
+    println!("✓ Updated aichat from v{current} to v{latest}.");
+    Ok(())
+}
+
+/// The Rust target triple of the release build for this machine.
+fn release_target(os: &OSKind) -> Result<String> {
+    let arch = std::env::consts::ARCH;
+    let target = match os {
+        OSKind::MacOS => format!("{arch}-apple-darwin"),
+        OSKind::Linux | OSKind::WSL => format!("{arch}-unknown-linux-musl"),
+        OSKind::Windows => format!("{arch}-pc-windows-msvc"),
+        OSKind::Unknown => bail!("No release builds for this OS, update from source instead"),
+    };
+    Ok(target)
+}
+
+/// Compare dotted versions numerically, `0.30.10` is newer than `0.30.9`.
+fn is_newer_version(latest: &str, current: &str) -> bool {
+    let parse =
+        |v: &str| -> Vec<u64> { v.split(['.', '-']).map_while(|v| v.parse().ok()).collect() };
+    parse(latest) > parse(current)
+}
+
+/// The hash for `name` in a `sha256sum` listing, or the only hash of a `.sha256` file.
+fn find_checksum(sums: &str, name: &str) -> Option<String> {
+    let lines: Vec<Vec<&str>> = sums
+        .lines()
+        .map(|v| v.split_whitespace().collect::<Vec<_>>())
+        .filter(|v| !v.is_empty())
+        .collect();
+    lines
+        .iter()
+        .find(|v| v.get(1).map(|v| v.trim_start_matches('*')) == Some(name))
+        .or_else(|| {
+            lines
+                .iter()
+                .find(|v| v.len() == 1)
+                .filter(|_| lines.len() == 1)
+        })
+        .map(|v| v[0].to_string())
+}
+
+/// Check a base64 ed25519 signature of `data` against a base64 public key.
+fn verify_signature(public_key: &str, data: &[u8], signature: &[u8]) -> Result<()> {
+    let public_key = base64_decode(public_key.trim())
+        .map_err(|_| anyhow!("Invalid self_update_public_key, expected base64"))?;
+    let signature = base64_decode(String::from_utf8_lossy(signature).trim())
+        .map_err(|_| anyhow!("Invalid release signature, expected base64"))?;
+    ring::signature::UnparsedPublicKey::new(&ring::signature::ED25519, public_key)
+        .verify(data, &signature)
+        .map_err(|_| {
+            anyhow!("Signature mismatch, the release wasn't signed with the configured key")
+        })
+}
+
+/// The contents of the file called `binary_name` in a `.tar.gz` or `.zip` release archive.
+fn extract_binary(name: &str, archive: &[u8], binary_name: &str) -> Result<Vec<u8>> {
+    let is_binary = |path: &Path| path.file_name().is_some_and(|v| v == binary_name);
+    let mut output = vec![];
+    if name.ends_with(".zip") {
+        let mut zip = zip::ZipArchive::new(Cursor::new(archive))?;
+        for i in 0..zip.len() {
+            let mut file = zip.by_index(i)?;
+            if file.is_file() && file.enclosed_name().is_some_and(|v| is_binary(&v)) {
+                file.read_to_end(&mut output)?;
+                return Ok(output);
+            }
+        }
+    } else {
+        let mut tar = tar::Archive::new(flate2::read::GzDecoder::new(archive));
+        for entry in tar.entries()? {
+            let mut entry = entry?;
+            if entry.header().entry_type().is_file() && is_binary(&entry.path()?) {
+                entry.read_to_end(&mut output)?;
+                return Ok(output);
+            }
+        }
+    }
+    bail!("No '{binary_name}' in '{name}'")
+}
+
+/// Stage the new binary next to the old one, then rename it into place, so the swap is
+/// atomic. Windows can't replace a running executable but can rename it out of the way.
+fn replace_binary(binary: &[u8], exe: &Path) -> Result<()> {
+    let file_name = exe
+        .file_name()
+        .ok_or_else(|| anyhow!("Invalid executable path"))?
+        .to_string_lossy();
+    let staged = exe.with_file_name(format!("{file_name}.new"));
+    fs::write(&staged, binary)?;
+    #[cfg(unix)]
+    {
+        use std::os::unix::prelude::PermissionsExt;
+        fs::set_permissions(&staged, fs::Permissions::from_mode(0o755))?;
+    }
+    #[cfg(windows)]
+    {
+        let old = exe.with_file_name(format!("{file_name}.old"));
+        let _ = fs::remove_file(&old);
+        fs::rename(exe, &old)?;
+    }
+    fs::rename(&staged, exe).inspect_err(|_| {
+        let _ = fs::remove_file(&staged);
+    })?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_newer_version() {
+        assert!(is_newer_version("0.30.10", "0.30.9"));
+        assert!(is_newer_version("1.0.0", "0.30.0"));
+        assert!(!is_newer_version("0.30.0", "0.30.0"));
+        assert!(!is_newer_version("0.29.1", "0.30.0"));
+    }
+
+    #[test]
+    fn test_find_checksum() {
+        let name = "aichat-v0.31.0-x86_64-unknown-linux-musl.tar.gz";
+        let sums = format!("abc123  aichat-v0.31.0-aarch64-apple-darwin.tar.gz\ndef456 *{name}\n");
+        assert_eq!(find_checksum(&sums, name), Some("def456".into()));
+        assert_eq!(find_checksum("def456\n", name), Some("def456".into()));
+        assert_eq!(find_checksum("abc123  other.zip\n", name), None);
+    }
+
+    #[test]
+    fn test_extract_binary() {
+        let mut tar = tar::Builder::new(flate2::write::GzEncoder::new(
+            vec![],
+            flate2::Compression::default(),
+        ));
+        for (path, data) in [("dist/README.md", "docs"), ("dist/aichat", "binary")] {
+            let mut header = tar::Header::new_gnu();
+            header.set_size(data.len() as u64);
+            header.set_mode(0o755);
+            header.set_cksum();
+            tar.append_data(&mut header, path, data.as_bytes()).unwrap();
+        }
+        let archive = tar.into_inner().unwrap().finish().unwrap();
+        let name = "aichat-v0.31.0-x86_64-unknown-linux-musl.tar.gz";
+        assert_eq!(extract_binary(name, &archive, "aichat").unwrap(), b"binary");
+        assert!(extract_binary(name, &archive, "aichat.exe").is_err());
+    }
+}
@@ -1,4 +1,4 @@
-use super::{poll_abort_signal, wait_abort_signal, AbortSignal, IS_STDOUT_TERMINAL};
+use super::{is_accessible, poll_abort_signal, wait_abort_signal, AbortSignal, IS_STDOUT_TERMINAL};
 
 use anyhow::{bail, Result};
 use crossterm::{cursor, queue, style, terminal};
@@ -25,7 +25,7 @@ impl SpinnerInner {
     const DATA: [&'static str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
 
     fn step(&mut self) -> Result<()> {
-        if !*IS_STDOUT_TERMINAL || self.message.is_empty() {
+        if !*IS_STDOUT_TERMINAL || self.message.is_empty() || is_accessible() {
             return Ok(());
         }
         let mut writer = stdout();
@@ -44,6 +44,11 @@ impl SpinnerInner {
     fn set_message(&mut self, message: String) -> Result<()> {
         self.clear_message()?;
         if !message.is_empty() {
+            if is_accessible() && *IS_STDOUT_TERMINAL {
+                // Announce progress once on its own line instead of animating it.
+                println!("{message}...");
+                return Ok(());
+            }
             self.message = format!(" {message}");
         }
         Ok(())
@@ -1,4 +1,5 @@
 use super::*;
+use crate::config::ShellKind;
 use fancy_regex::{Captures, Regex};
 use std::sync::LazyLock;
 
@@ -20,6 +21,7 @@ pub fn interpolate_variables(text: &mut String) {
                 "__os_family__" => env::consts::FAMILY.to_string(),
                 "__arch__" => env::consts::ARCH.to_string(),
                 "__shell__" => SHELL.name.clone(),
+                "__shell_syntax__" => ShellKind::from_name(&SHELL.name).syntax_hint().to_string(),
                 "__locale__" => sys_locale::get_locale().unwrap_or_default(),
                 "__now__" => now(),
                 "__cwd__" => env::current_dir()